@@ -1,4 +1,4 @@
-use datahugger::resolve;
+use datahugger::{resolve, CrawlOptions};
 use futures_util::future::join_all;
 use indicatif::MultiProgress;
 use reqwest::ClientBuilder;
@@ -36,10 +36,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             // require:
             // use datahugger::DownloadExt;
             let mp = MultiProgress::new();
-            // repo.download_with_validation(&client, "./dummy_tests", mp, 0)
+            // repo.download_with_validation(&client, "./dummy_tests", mp, &DownloadOptions::default())
             //     .await
 
-            repo.print_meta(&client, mp, 0).await
+            repo.print_meta(&client, mp, &CrawlOptions::new()).await
         }
     });
 
@@ -1,16 +1,28 @@
 from .datahugger import (
     resolve,
+    configure_client,
     DOIResolver,
     DirEntry,
     FileEntry,
     Dataset,
+    DownloadReport,
+    FileReport,
     DataverseJsonSrcDataset,
     ZenodoJsonSrcDataset,
     HalJsonSrcDataset,
+    DatahuggerError,
+    NotFoundError,
+    RateLimitedError,
+    ChecksumMismatchError,
+    LocalIOError,
+    AccessRequiredError,
+    InsufficientSpaceError,
+    HttpError,
 )
 
 __all__ = (
     "resolve",
+    "configure_client",
     "DOIResolver",
     "DataverseJsonSrcDataset",
     "ZenodoJsonSrcDataset",
@@ -18,4 +30,14 @@ __all__ = (
     "DirEntry",
     "FileEntry",
     "Dataset",
+    "DownloadReport",
+    "FileReport",
+    "DatahuggerError",
+    "NotFoundError",
+    "RateLimitedError",
+    "ChecksumMismatchError",
+    "LocalIOError",
+    "AccessRequiredError",
+    "InsufficientSpaceError",
+    "HttpError",
 )
@@ -1,7 +1,8 @@
 from dataclasses import dataclass
+import os
 import pathlib
 
-from typing import Protocol, TypeVar, AsyncIterator, Iterator
+from typing import Any, Callable, Protocol, TypeVar, AsyncIterator, Iterator
 
 T = TypeVar("T", covariant=True)
 
@@ -11,6 +12,30 @@ class SyncAsyncIterator(Protocol[T]):
     def __iter__(self) -> Iterator[T]: ...
     def __next__(self) -> T: ...
 
+class DatahuggerError(RuntimeError):
+    """base class of the errors raised by datahugger"""
+
+class NotFoundError(DatahuggerError):
+    """the repository answered 404 Not Found"""
+
+class RateLimitedError(DatahuggerError):
+    """the repository refused the request for exceeding its rate limit"""
+
+class ChecksumMismatchError(DatahuggerError):
+    """a downloaded file does not match the checksum listed by the repository"""
+
+class LocalIOError(DatahuggerError):
+    """reading or writing a local file failed"""
+
+class AccessRequiredError(DatahuggerError):
+    """files of the dataset need a token, terms to accept, access to request or are under embargo"""
+
+class InsufficientSpaceError(DatahuggerError):
+    """the destination has not enough space for the files to download"""
+
+class HttpError(DatahuggerError):
+    """the repository answered an error status"""
+
 class Entry(object):
     """base entry for file and dir"""
 
@@ -32,8 +57,49 @@ class FileEntry(Entry):
     version: str | None
     creation_date: str | None
     last_modification_date: str | None
+    endpoint_url: str | None
+    """API url the file is listed at"""
+    endpoint_key: str | None
+    """key of the file in the API response of `endpoint_url`, e.g. `files.3`"""
+
+class DatasetMetadata(object):
+    """descriptive metadata of a dataset, normalized across repositories"""
+
+    title: str | None
+    description: str | None
+    creators: list[str]
+    license: str | None
+    publication_date: str | None
+    version: str | None
+    doi: str | None
+
+class FileReport(object):
+    """outcome of a single file of a `DownloadReport`"""
 
-class DataverseJsonSrcDataset(Dataset):
+    path: pathlib.Path
+    download_url: str
+    outcome: str
+    """e.g. 'verified', 'unverified', 'skipped', 'failed', 'linked' or 'cancelled'"""
+    message: str | None
+    """reason a file was skipped or error a file failed with"""
+    checksum: tuple[str, str] | None
+    """checksum the file was verified against, as `(algorithm, value)`"""
+    bytes: int
+    elapsed: float
+    """wall time spent on the file, in seconds"""
+
+class DownloadReport(object):
+    """outcome of every file of a download, in the order they finished"""
+
+    files: list[FileReport]
+    doi: str | None
+    cancelled: bool
+    def is_success(self) -> bool:
+        """no file failed and the download was not cancelled"""
+    def stats(self) -> dict[str, Any]:
+        """per-file timing and bandwidth statistics, durations in milliseconds"""
+
+class DataverseJsonSrcDataset(object):
     """
     A Dataverse dataset backend that uses pre-fetched JSON content.
     """
@@ -49,8 +115,10 @@ class DataverseJsonSrcDataset(Dataset):
         Raises:
             RuntimeError: If the URL is invalid or missing required components
         """
+    def crawl_file(self) -> SyncAsyncIterator[FileEntry]:
+        """returns a stream that can be either sync or async iterator over `FileEntry`"""
 
-class ZenodoJsonSrcDataset(Dataset):
+class ZenodoJsonSrcDataset(object):
     """
     A Zenodo dataset backend that uses pre-fetched JSON content.
     """
@@ -66,8 +134,10 @@ class ZenodoJsonSrcDataset(Dataset):
         Raises:
             RuntimeError
         """
+    def crawl_file(self) -> SyncAsyncIterator[FileEntry]:
+        """returns a stream that can be either sync or async iterator over `FileEntry`"""
 
-class HalJsonSrcDataset(Dataset):
+class HalJsonSrcDataset(object):
     """
     A HAL dataset backend that uses pre-fetched JSON content.
     """
@@ -83,18 +153,169 @@ class HalJsonSrcDataset(Dataset):
         Raises:
             RuntimeError
         """
+    def crawl_file(self) -> SyncAsyncIterator[FileEntry]:
+        """returns a stream that can be either sync or async iterator over `FileEntry`"""
 
 class Dataset(object):
-    def download_with_validation(self, dst_dir: pathlib.Path, limit: int = 0) -> None:
-        """blocking call, using rust's async runtime"""
+    def download_with_validation(
+        self,
+        dst_dir: pathlib.Path,
+        limit: int = 0,
+        include: list[str] = [],
+        exclude: list[str] = [],
+        max_file_size: int | None = None,
+        manifest: bool = False,
+        on_progress: Callable[[dict[str, Any]], None] | None = None,
+        check_access: bool = False,
+        expand_zip: bool = False,
+        connections_per_file: int = 1,
+        check_disk_space: bool = False,
+        path_mapper: Callable[[FileEntry], str | os.PathLike[str]] | None = None,
+        continue_on_error: bool = False,
+    ) -> DownloadReport:
+        """blocking call, using rust's async runtime, returns the outcome of every file
+
+        Args:
+            dst_dir: destination directory of the downloaded files.
+            limit: maximum number of concurrent downloads, `0` means no limit.
+            include: glob patterns, only files whose relative path matches are downloaded.
+            exclude: glob patterns, files whose relative path matches are skipped.
+            max_file_size: files larger than this size in bytes are skipped.
+            manifest: write a `datahugger.json` provenance manifest into `dst_dir`.
+            on_progress: called with a dict for every event of every file, with the
+                relative `path` of the file and the kind of `event`: `started` with the
+                expected `size`, `progress` with the chunk `bytes`, the `downloaded` bytes
+                and the `total` size, `finished` with the `size`, `skipped` with the
+                `reason`, `failed` with the `message` or `cancelled`. Sizes are `None` if
+                unknown. Exceptions raised by the callback are printed and ignored.
+            check_access: ask the repository what must be obtained to download the dataset
+                first, and raise `AccessRequiredError` listing it instead of failing on the
+                first restricted file.
+            expand_zip: list the members of zip archives as files with range requests, so
+                `include` / `exclude` select single members and only those are downloaded.
+            connections_per_file: download files of 64 MiB or more over this many parallel
+                range requests, when the server supports them.
+            check_disk_space: raise `InsufficientSpaceError` before downloading if the
+                destination has not enough space for the files of known size.
+            path_mapper: called with the `FileEntry` of every file, returns the path relative
+                to `dst_dir` to write it to instead of its path in the dataset, e.g. to
+                flatten the tree or store files under their checksum. `..` segments are
+                dropped. Exceptions raised are printed and the file keeps its path.
+            continue_on_error: keep downloading the other files when a file fails, its
+                error is then in the returned report instead of raised.
+        """
+    async def download_async(
+        self,
+        dst_dir: pathlib.Path,
+        limit: int = 0,
+        include: list[str] = [],
+        exclude: list[str] = [],
+        max_file_size: int | None = None,
+        manifest: bool = False,
+        on_progress: Callable[[dict[str, Any]], None] | None = None,
+        check_access: bool = False,
+        expand_zip: bool = False,
+        connections_per_file: int = 1,
+        check_disk_space: bool = False,
+        path_mapper: Callable[[FileEntry], str | os.PathLike[str]] | None = None,
+        continue_on_error: bool = False,
+    ) -> DownloadReport:
+        """downloads the dataset like `download_with_validation`, without blocking the event
+        loop
+
+        Cancelling the task, e.g. with `asyncio.wait_for`, stops the download: no other
+        file is requested and the files in flight are removed with their partial data, the
+        files already complete are kept.
+        """
     def crawl_file(self) -> SyncAsyncIterator[FileEntry]:
         """returns a stream that can be either sync or async iterator over `FileEntry`"""
     def crawl(self) -> SyncAsyncIterator[FileEntry | DirEntry]:
         """returns a stream that can be either sync or async iterator over `FileEntry | DirEntry`"""
+    def files(self) -> list[FileEntry]:
+        """blocking call, crawls the dataset to completion and returns its files"""
+    async def files_async(self) -> list[FileEntry]:
+        """crawls the dataset to completion and returns its files"""
+    def metadata(self) -> DatasetMetadata:
+        """blocking call, fetches the title, creators, license, DOI, ... of the dataset
+
+        Raises:
+            RuntimeError: if the repository has no metadata for the dataset or the request fails.
+        """
+    def raw_metadata(self) -> dict[str, Any]:
+        """blocking call, fetches the record of the dataset as returned by the repository's API
+
+        Raises:
+            RuntimeError: if the repository has no JSON API or the request fails.
+        """
     def root_url(self) -> str: ...
+    @property
+    def backend_name(self) -> str:
+        """name of the repository, e.g. 'zenodo' or 'dataverse'"""
+    @property
+    def record_id(self) -> str:
+        """identifier of the dataset in its repository, e.g. the Zenodo record id"""
+    @property
+    def doi(self) -> str | None:
+        """DOI the dataset was resolved from, e.g. '10.5281/zenodo.17867222'"""
+    def destination(self, template: str) -> pathlib.Path:
+        """expands the placeholders '{repo}', '{record_id}', '{doi}' and '{date}' of a
+        destination, e.g. './data/{repo}/{record_id}'"""
+
+def resolve(
+    url: str,
+    /,
+    auth: dict[str, str] | None = None,
+    version: str | None = None,
+    headers: dict[str, str] | None = None,
+    timeout: float | None = None,
+    connect_timeout: float | None = None,
+    proxy: str | None = None,
+    user_agent: str | None = None,
+    tls_backend: str | None = None,
+) -> Dataset:
+    """returns a dataset for the given domain
 
-def resolve(url: str, /) -> Dataset:
-    """returns a dataset for the given domain"""
+    Args:
+        url: URL of the dataset.
+        auth: Tokens per repository, keys are 'github', 'dryad', 'zenodo', 'dataverse',
+          'huggingface', 'osf' and 'softwareheritage'. The token of the dataset's
+          repository is sent with every request. Defaults to the tokens in the environment
+          variables (GITHUB_TOKEN, DRYAD_API_TOKEN, ZENODO_ACCESS_TOKEN, DATAVERSE_API_KEY,
+          HF_TOKEN, OSF_TOKEN, SWH_TOKEN).
+        version: Version of the dataset instead of the latest, the version number for
+          Dataverse ('2' or '2.1') and Dryad, the version name ('v1.2') or index starting
+          at 1 for Zenodo and InvenioRDM.
+        headers, timeout, connect_timeout, proxy, user_agent, tls_backend: settings of the
+          clients resolving, crawling and downloading the dataset, over those of
+          `configure_client`, see there.
+    """
+
+def configure_client(
+    headers: dict[str, str] | None = None,
+    timeout: float | None = None,
+    connect_timeout: float | None = None,
+    proxy: str | None = None,
+    user_agent: str | None = None,
+    tls_backend: str | None = None,
+) -> None:
+    """configures the HTTP clients shared by all datasets of the process
+
+    Clients are built on first use, one per set of auth headers, and reused by every
+    `resolve`, crawl and download so that connections are pooled. Clients already built
+    are dropped, calls made afterwards use the new settings.
+
+    Args:
+        headers: extra headers sent with every request, e.g. a contact address.
+        timeout: timeout in seconds of a whole request, no timeout by default.
+        connect_timeout: timeout in seconds of connecting to a server.
+        proxy: proxy of all requests, e.g. 'http://proxy.example.org:3128', instead of the
+          proxy of the HTTPS_PROXY and HTTP_PROXY environment variables.
+        user_agent: user agent instead of 'datahugger-py/<version>'.
+        tls_backend: 'native-tls' or 'rustls', of those enabled in the build.
+
+    Raises:
+        RuntimeError: if a header, the proxy or the TLS backend is invalid.
+    """
 
 class DOIResolver:
     def __init__(self, timeout: int = 5) -> None:
@@ -117,3 +338,12 @@ class DOIResolver:
           dois: List of DOIs to resolve.
           follow_redirects: Whether to follow redirects. Defaults to True.
         """
+    def metadata(self, doi: str) -> DatasetMetadata:
+        """Fetch the citation metadata of a DOI from its registration agency.
+
+        Args:
+            doi: The DOI, e.g. '10.1000/xyz123'.
+
+        Raises:
+            RuntimeError: if the DOI is invalid, unknown or the request fails.
+        """
@@ -1,4 +1,3 @@
-// TODO: dedicate exception type for PyRuntimeError.
 #![allow(clippy::needless_pass_by_value)]
 // TODO: type alias
 #![allow(clippy::type_complexity)]
@@ -22,26 +21,142 @@ use datahugger::datasets::{DataverseJsonSrcDataset, HalJsonSrcDataset};
 use datahugger::{
     crawl,
     crawler::{CrawlerError, ProgressManager},
-    resolve as inner_resolve, resolve_doi_to_url as inner_resolve_doi_to_url, CrawlExt, Dataset,
-    DownloadExt, Entry, FileMeta,
+    resolve_doi_metadata as inner_resolve_doi_metadata,
+    resolve_doi_to_url as inner_resolve_doi_to_url,
+    resolve_with_options as inner_resolve_with_options, AuthConfig, CancellationToken,
+    ClientOptions, CrawlExt, CrawlOptions, DatahuggerError as ErrorKind, Dataset, DatasetMetadata,
+    DownloadEvent, DownloadExt, DownloadOptions, DownloadReport, Entry, FileFilter, FileMeta,
+    FileOutcome, FileReport, RedirectPolicy, RepositoryConfig, TlsBackend,
 };
 use exn::Exn;
 use futures_core::stream::BoxStream;
 use futures_util::StreamExt;
 use indicatif::ProgressBar;
 use pyo3::{
+    create_exception,
     exceptions::{PyRuntimeError, PyStopAsyncIteration, PyStopIteration},
     prelude::*,
 };
 use pyo3::{ffi::c_str, types::PyDict};
 use pyo3_async_runtimes::tokio::future_into_py;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use reqwest::redirect::Policy;
-use reqwest::{Client, ClientBuilder, Url};
+use reqwest::{Client, Url};
 use std::collections::HashMap;
+use std::future::Future;
 use std::time::Duration;
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    path::PathBuf,
+    sync::{Arc, LazyLock},
+};
 use tokio::sync::Mutex;
 
+create_exception!(
+    datahugger,
+    DatahuggerError,
+    PyRuntimeError,
+    "Base class of the errors raised by datahugger."
+);
+create_exception!(
+    datahugger,
+    NotFoundError,
+    DatahuggerError,
+    "The repository answered 404 Not Found."
+);
+create_exception!(
+    datahugger,
+    RateLimitedError,
+    DatahuggerError,
+    "The repository refused the request for exceeding its rate limit."
+);
+create_exception!(
+    datahugger,
+    ChecksumMismatchError,
+    DatahuggerError,
+    "A downloaded file does not match the checksum listed by the repository."
+);
+create_exception!(
+    datahugger,
+    LocalIOError,
+    DatahuggerError,
+    "Reading or writing a local file failed."
+);
+create_exception!(
+    datahugger,
+    AccessRequiredError,
+    DatahuggerError,
+    "Files of the dataset cannot be downloaded before the access requirements are met."
+);
+create_exception!(
+    datahugger,
+    InsufficientSpaceError,
+    DatahuggerError,
+    "The destination has not enough space for the files to download."
+);
+create_exception!(
+    datahugger,
+    HttpError,
+    DatahuggerError,
+    "The repository answered an error status."
+);
+
+/// Exception of the class matching the cause of `err`, with `message`.
+fn py_err<E>(err: &Exn<E>, message: String) -> PyErr
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    match ErrorKind::from(err) {
+        ErrorKind::NotFound { .. } => NotFoundError::new_err(message),
+        ErrorKind::RateLimited { .. } => RateLimitedError::new_err(message),
+        ErrorKind::ChecksumMismatch { .. } => ChecksumMismatchError::new_err(message),
+        ErrorKind::Io { .. } => LocalIOError::new_err(message),
+        ErrorKind::AccessRequired(_) => AccessRequiredError::new_err(message),
+        ErrorKind::InsufficientSpace { .. } => InsufficientSpaceError::new_err(message),
+        ErrorKind::Http { .. } => HttpError::new_err(message),
+        _ => DatahuggerError::new_err(message),
+    }
+}
+
+/// Progress event as passed to the `on_progress` callback of python, a dict with the kind
+/// of the event under `"event"` and the fields of the event.
+fn event_to_dict<'py>(py: Python<'py>, event: &DownloadEvent) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    dict.set_item("path", event.path().as_str())?;
+    match event {
+        DownloadEvent::Started { size, .. } => {
+            dict.set_item("event", "started")?;
+            dict.set_item("size", size)?;
+        }
+        DownloadEvent::Progress {
+            bytes,
+            downloaded,
+            total,
+            ..
+        } => {
+            dict.set_item("event", "progress")?;
+            dict.set_item("bytes", bytes)?;
+            dict.set_item("downloaded", downloaded)?;
+            dict.set_item("total", total)?;
+        }
+        DownloadEvent::Finished { size, .. } => {
+            dict.set_item("event", "finished")?;
+            dict.set_item("size", size)?;
+        }
+        DownloadEvent::Skipped { reason, .. } => {
+            dict.set_item("event", "skipped")?;
+            dict.set_item("reason", reason)?;
+        }
+        DownloadEvent::Failed { message, .. } => {
+            dict.set_item("event", "failed")?;
+            dict.set_item("message", message)?;
+        }
+        DownloadEvent::Cancelled { .. } => {
+            dict.set_item("event", "cancelled")?;
+        }
+    }
+    Ok(dict)
+}
+
 pub trait CrawlFileExt {
     fn crawl_file(
         self,
@@ -77,7 +192,7 @@ impl CrawlFileExt for Dataset {
 #[pyclass]
 #[pyo3(name = "Dataset")]
 #[derive(Clone)]
-struct PyDataset(Dataset);
+struct PyDataset(Dataset, ClientConfig);
 
 #[derive(Clone)]
 struct NoProgress;
@@ -92,6 +207,177 @@ impl ProgressManager for NoProgress {
     }
 }
 
+/// Runs `future` to completion on the tokio runtime of `pyo3_async_runtimes`, shared by the
+/// blocking calls, the iterators and the awaitables of the module so that the connections
+/// of the shared clients outlive a single call.
+fn block_on<F: Future>(future: F) -> F::Output {
+    pyo3_async_runtimes::tokio::get_runtime().block_on(future)
+}
+
+/// Settings of the clients, set for all datasets with `configure_client` and for the
+/// datasets of a `resolve` call with its arguments.
+#[derive(Debug, Clone, Default)]
+struct ClientConfig {
+    headers: HeaderMap,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    proxy: Option<String>,
+    user_agent: Option<String>,
+    tls_backend: Option<String>,
+}
+
+impl ClientConfig {
+    fn new(
+        headers: Option<HashMap<String, String>>,
+        timeout: Option<f64>,
+        connect_timeout: Option<f64>,
+        proxy: Option<String>,
+        user_agent: Option<String>,
+        tls_backend: Option<String>,
+    ) -> PyResult<Self> {
+        let mut header_map = HeaderMap::new();
+        for (name, value) in headers.unwrap_or_default() {
+            let name = HeaderName::try_from(name.as_str()).map_err(|err| {
+                PyRuntimeError::new_err(format!("invalid header '{name}': {err}"))
+            })?;
+            let value = HeaderValue::try_from(value.as_str()).map_err(|err| {
+                PyRuntimeError::new_err(format!("invalid value of header '{name}': {err}"))
+            })?;
+            header_map.insert(name, value);
+        }
+        let config = ClientConfig {
+            headers: header_map,
+            timeout: duration("timeout", timeout)?,
+            connect_timeout: duration("connect_timeout", connect_timeout)?,
+            proxy,
+            user_agent,
+            tls_backend,
+        };
+        // invalid proxy or backend raised now rather than on first request
+        config.client_options()?;
+        Ok(config)
+    }
+
+    /// Settings of `self` overridden by those set in `other`.
+    fn merged(&self, other: &ClientConfig) -> ClientConfig {
+        let mut headers = self.headers.clone();
+        headers.extend(other.headers.clone());
+        ClientConfig {
+            headers,
+            timeout: other.timeout.or(self.timeout),
+            connect_timeout: other.connect_timeout.or(self.connect_timeout),
+            proxy: other.proxy.clone().or_else(|| self.proxy.clone()),
+            user_agent: other.user_agent.clone().or_else(|| self.user_agent.clone()),
+            tls_backend: other
+                .tls_backend
+                .clone()
+                .or_else(|| self.tls_backend.clone()),
+        }
+    }
+
+    fn client_options(&self) -> PyResult<ClientOptions> {
+        let user_agent = self
+            .user_agent
+            .clone()
+            .unwrap_or_else(|| format!("datahugger-py/{}", env!("CARGO_PKG_VERSION")));
+        let mut options = ClientOptions::new().user_agent(user_agent);
+        for (name, value) in &self.headers {
+            options = options.header(name.clone(), value.clone());
+        }
+        if let Some(timeout) = self.timeout {
+            options = options.timeout(timeout);
+        }
+        if let Some(connect_timeout) = self.connect_timeout {
+            options = options.connect_timeout(connect_timeout);
+        }
+        if let Some(proxy) = &self.proxy {
+            let proxy = reqwest::Proxy::all(proxy).map_err(|err| {
+                PyRuntimeError::new_err(format!("invalid proxy '{proxy}': {err}"))
+            })?;
+            options = options.proxy(proxy);
+        }
+        if let Some(name) = &self.tls_backend {
+            let tls_backend = TlsBackend::from_name(name).ok_or_else(|| {
+                PyRuntimeError::new_err(format!(
+                    "unknown or disabled TLS backend '{name}', expect 'native-tls' or 'rustls'"
+                ))
+            })?;
+            options = options.tls_backend(tls_backend);
+        }
+        Ok(options)
+    }
+}
+
+static CLIENT_CONFIG: LazyLock<std::sync::Mutex<ClientConfig>> = LazyLock::new(Default::default);
+
+/// Client options of the datasets resolved with `config`, over those of `configure_client`.
+fn client_options(config: &ClientConfig) -> PyResult<ClientOptions> {
+    let global = CLIENT_CONFIG.lock().expect("client lock poisoned");
+    global.merged(config).client_options()
+}
+
+/// Clients by the settings and auth headers they were built with, built on first use.
+static CLIENTS: LazyLock<std::sync::Mutex<HashMap<String, Client>>> =
+    LazyLock::new(Default::default);
+
+/// Returns the client shared by the datasets resolved with `config` sending the auth
+/// headers `auth_headers`, so that their connections are pooled across calls.
+fn shared_client(auth_headers: &HeaderMap, config: &ClientConfig) -> PyResult<Client> {
+    let mut auth = auth_headers
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_bytes()))
+        .collect::<Vec<_>>();
+    auth.sort_unstable();
+    let key = format!("{auth:?} {config:?}");
+    let mut clients = CLIENTS.lock().expect("client lock poisoned");
+    if let Some(client) = clients.get(&key) {
+        return Ok(client.clone());
+    }
+    let client = client_options(config)?
+        .builder()
+        .default_headers(auth_headers.clone())
+        .redirect(RedirectPolicy::default().policy())
+        .build()
+        .map_err(|err| PyRuntimeError::new_err(format!("http client fail: {err}")))?;
+    clients.insert(key, client.clone());
+    Ok(client)
+}
+
+fn duration(name: &str, secs: Option<f64>) -> PyResult<Option<Duration>> {
+    secs.map(|secs| {
+        Duration::try_from_secs_f64(secs)
+            .map_err(|err| PyRuntimeError::new_err(format!("invalid {name} '{secs}': {err}")))
+    })
+    .transpose()
+}
+
+/// Sets the extra `headers`, the `timeout` and `connect_timeout` in seconds, the `proxy`,
+/// the `user_agent` and the `tls_backend` of the clients shared by all datasets. Clients
+/// already built are dropped, calls made afterwards use the new settings.
+#[pyfunction]
+#[pyo3(signature = (headers=None, timeout=None, connect_timeout=None, proxy=None, user_agent=None, tls_backend=None))]
+fn configure_client(
+    headers: Option<HashMap<String, String>>,
+    timeout: Option<f64>,
+    connect_timeout: Option<f64>,
+    proxy: Option<String>,
+    user_agent: Option<String>,
+    tls_backend: Option<String>,
+) -> PyResult<()> {
+    let config = ClientConfig::new(
+        headers,
+        timeout,
+        connect_timeout,
+        proxy,
+        user_agent,
+        tls_backend,
+    )?;
+    let mut clients = CLIENTS.lock().expect("client lock poisoned");
+    *CLIENT_CONFIG.lock().expect("client lock poisoned") = config;
+    clients.clear();
+    Ok(())
+}
+
 #[pyclass]
 #[pyo3(name = "DataverseJsonSrcDataset")]
 struct PyDataverseJsonSrcDataset {
@@ -136,21 +422,15 @@ impl PyDataverseJsonSrcDataset {
 
         let version = ":latest-published".to_string();
 
-        let ds = Dataset {
-            backend: Arc::new(DataverseJsonSrcDataset::new(
-                id, &base_url, version, content,
-            )),
-        };
+        let ds = Dataset::new(DataverseJsonSrcDataset::new(
+            id, &base_url, version, content,
+        ));
         Ok(Self {
-            inner: PyDataset(ds),
+            inner: PyDataset(ds, ClientConfig::default()),
         })
     }
     fn crawl_file(&self) -> PyResult<PyFileMetaStream> {
-        let user_agent = format!("datahugger-py/{}", env!("CARGO_PKG_VERSION"));
-        let client = ClientBuilder::new()
-            .user_agent(user_agent)
-            .build()
-            .map_err(|err| PyRuntimeError::new_err(format!("http client fail: {err}")))?;
+        let client = shared_client(&HeaderMap::new(), &self.inner.1)?;
         let mp = NoProgress;
 
         let stream = self.inner.0.clone().crawl_file(&client, mp);
@@ -169,20 +449,14 @@ struct PyZenodoJsonSrcDataset {
 impl PyZenodoJsonSrcDataset {
     #[new]
     fn new(id: String, content: String) -> PyResult<Self> {
-        let ds = Dataset {
-            backend: Arc::new(ZenodoJsonSrcDataset::new(id, content)),
-        };
+        let ds = Dataset::new(ZenodoJsonSrcDataset::new(id, content));
         Ok(Self {
-            inner: PyDataset(ds),
+            inner: PyDataset(ds, ClientConfig::default()),
         })
     }
 
     fn crawl_file(&self) -> PyResult<PyFileMetaStream> {
-        let user_agent = format!("datahugger-py/{}", env!("CARGO_PKG_VERSION"));
-        let client = ClientBuilder::new()
-            .user_agent(user_agent)
-            .build()
-            .map_err(|err| PyRuntimeError::new_err(format!("http client fail: {err}")))?;
+        let client = shared_client(&HeaderMap::new(), &self.inner.1)?;
         let mp = NoProgress;
 
         let stream = self.inner.0.clone().crawl_file(&client, mp);
@@ -201,20 +475,14 @@ struct PyHalJsonSrcDataset {
 impl PyHalJsonSrcDataset {
     #[new]
     fn new(id: String, content: String) -> PyResult<Self> {
-        let ds = Dataset {
-            backend: Arc::new(HalJsonSrcDataset::new(id, content)),
-        };
+        let ds = Dataset::new(HalJsonSrcDataset::new(id, content));
         Ok(Self {
-            inner: PyDataset(ds),
+            inner: PyDataset(ds, ClientConfig::default()),
         })
     }
 
     fn crawl_file(&self) -> PyResult<PyFileMetaStream> {
-        let user_agent = format!("datahugger-py/{}", env!("CARGO_PKG_VERSION"));
-        let client = ClientBuilder::new()
-            .user_agent(user_agent)
-            .build()
-            .map_err(|err| PyRuntimeError::new_err(format!("http client fail: {err}")))?;
+        let client = shared_client(&HeaderMap::new(), &self.inner.1)?;
         let mp = NoProgress;
 
         let stream = self.inner.0.clone().crawl_file(&client, mp);
@@ -225,29 +493,138 @@ impl PyHalJsonSrcDataset {
 
 #[pymethods]
 impl PyDataset {
-    #[pyo3(signature = (dst_dir, limit=0))]
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (dst_dir, limit=0, include=Vec::new(), exclude=Vec::new(), max_file_size=None, manifest=false, on_progress=None, check_access=false, expand_zip=false, connections_per_file=1, check_disk_space=false, path_mapper=None, continue_on_error=false))]
     fn download_with_validation(
         self_: PyRef<'_, Self>,
         dst_dir: PathBuf,
         limit: usize,
-    ) -> PyResult<()> {
-        let user_agent = format!("datahugger-py/{}", env!("CARGO_PKG_VERSION"));
-        let client = ClientBuilder::new()
-            .user_agent(user_agent)
-            .build()
-            .map_err(|err| PyRuntimeError::new_err(format!("http client fail: {err}")))?;
+        include: Vec<String>,
+        exclude: Vec<String>,
+        max_file_size: Option<u64>,
+        manifest: bool,
+        on_progress: Option<Py<PyAny>>,
+        check_access: bool,
+        expand_zip: bool,
+        connections_per_file: usize,
+        check_disk_space: bool,
+        path_mapper: Option<Py<PyAny>>,
+        continue_on_error: bool,
+    ) -> PyResult<PyDownloadReport> {
+        let options = download_options(
+            limit,
+            include,
+            exclude,
+            max_file_size,
+            manifest,
+            on_progress,
+            check_access,
+            expand_zip,
+            connections_per_file,
+            check_disk_space,
+            path_mapper,
+            continue_on_error,
+        )?;
+        let client = shared_client(&self_.0.auth_headers(), &self_.1)?;
         let mp = NoProgress;
+        let dataset = self_.0.clone();
 
         // blocking call to download, not ideal, but just to sync with original API.
-        let rt = tokio::runtime::Runtime::new().expect("unable to create tokio runtime");
-        rt.block_on(async move {
-            self_
-                .0
-                .clone()
-                .download_with_validation(&client, dst_dir, mp, limit)
+        // The GIL is released so that `on_progress` can be called from the runtime threads.
+        self_
+            .py()
+            .detach(|| {
+                block_on(async move {
+                    dataset
+                        .download_with_validation(&client, dst_dir, mp, &options)
+                        .await
+                })
+            })
+            .map(PyDownloadReport::from)
+            .map_err(|err| py_err(&err, format!("{err}")))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (dst_dir, limit=0, include=Vec::new(), exclude=Vec::new(), max_file_size=None, manifest=false, on_progress=None, check_access=false, expand_zip=false, connections_per_file=1, check_disk_space=false, path_mapper=None, continue_on_error=false))]
+    fn download_async<'py>(
+        self_: PyRef<'py, Self>,
+        dst_dir: PathBuf,
+        limit: usize,
+        include: Vec<String>,
+        exclude: Vec<String>,
+        max_file_size: Option<u64>,
+        manifest: bool,
+        on_progress: Option<Py<PyAny>>,
+        check_access: bool,
+        expand_zip: bool,
+        connections_per_file: usize,
+        check_disk_space: bool,
+        path_mapper: Option<Py<PyAny>>,
+        continue_on_error: bool,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let cancel = CancellationToken::new();
+        let options = download_options(
+            limit,
+            include,
+            exclude,
+            max_file_size,
+            manifest,
+            on_progress,
+            check_access,
+            expand_zip,
+            connections_per_file,
+            check_disk_space,
+            path_mapper,
+            continue_on_error,
+        )?
+        .cancel(cancel.clone());
+        let client = shared_client(&self_.0.auth_headers(), &self_.1)?;
+        let mp = NoProgress;
+        let dataset = self_.0.clone();
+
+        // the download runs on its own task: cancelling the asyncio task drops the guard
+        // below, and the download removes its partial files before it stops.
+        let download = pyo3_async_runtimes::tokio::get_runtime().spawn(async move {
+            dataset
+                .download_with_validation(&client, dst_dir, mp, &options)
                 .await
+        });
+        future_into_py(self_.py(), async move {
+            let _guard = cancel.drop_guard();
+            match download.await {
+                Ok(res) => res
+                    .map(PyDownloadReport::from)
+                    .map_err(|err| py_err(&err, format!("{err}"))),
+                Err(err) => Err(PyRuntimeError::new_err(format!(
+                    "download task failed: {err}"
+                ))),
+            }
         })
-        .map_err(|err| PyRuntimeError::new_err(format!("{err}")))
+    }
+
+    fn metadata(self_: PyRef<'_, Self>) -> PyResult<PyDatasetMetadata> {
+        let client = shared_client(&self_.0.auth_headers(), &self_.1)?;
+        let dataset = self_.0.clone();
+
+        // blocking call, same as `download_with_validation`.
+        self_
+            .py()
+            .detach(|| block_on(async move { dataset.metadata(&client).await }))
+            .map(PyDatasetMetadata::from)
+            .map_err(|err| py_err(&err, format!("{err}")))
+    }
+
+    fn raw_metadata<'py>(self_: PyRef<'py, Self>) -> PyResult<Bound<'py, PyAny>> {
+        let py = self_.py();
+        let client = shared_client(&self_.0.auth_headers(), &self_.1)?;
+        let dataset = self_.0.clone();
+
+        // blocking call, same as `download_with_validation`.
+        let raw = py
+            .detach(|| block_on(async move { dataset.raw_metadata(&client).await }))
+            .map_err(|err| py_err(&err, format!("{err}")))?;
+        // round trip through `json` gives plain dicts, lists and scalars
+        py.import("json")?.call_method1("loads", (raw.to_string(),))
     }
 
     fn root_url(self_: PyRef<'_, Self>) -> String {
@@ -255,36 +632,260 @@ impl PyDataset {
         repo.root_url().as_str().into()
     }
 
+    #[getter]
+    fn backend_name(self_: PyRef<'_, Self>) -> &'static str {
+        self_.0.backend_name()
+    }
+
+    #[getter]
+    fn record_id(self_: PyRef<'_, Self>) -> String {
+        self_.0.record_id()
+    }
+
+    #[getter]
+    fn doi(self_: PyRef<'_, Self>) -> Option<String> {
+        self_.0.doi.clone()
+    }
+
+    fn destination(self_: PyRef<'_, Self>, template: &str) -> PathBuf {
+        self_.0.destination(template)
+    }
+
     fn crawl(self_: PyRef<'_, Self>) -> PyResult<PyEntryStream> {
-        let user_agent = format!("datahugger-py/{}", env!("CARGO_PKG_VERSION"));
-        let client = ClientBuilder::new()
-            .user_agent(user_agent)
-            .build()
-            .map_err(|err| PyRuntimeError::new_err(format!("http client fail: {err}")))?;
+        let client = shared_client(&self_.0.auth_headers(), &self_.1)?;
         let mp = NoProgress;
 
-        let stream = self_.0.clone().crawl(&client, mp);
+        let stream = self_.0.clone().crawl(&client, mp, &CrawlOptions::new());
         let stream = PyEntryStream::new(stream);
         Ok(stream)
     }
 
     fn crawl_file(self_: PyRef<'_, Self>) -> PyResult<PyFileMetaStream> {
-        let user_agent = format!("datahugger-py/{}", env!("CARGO_PKG_VERSION"));
-        let client = ClientBuilder::new()
-            .user_agent(user_agent)
-            .build()
-            .map_err(|err| PyRuntimeError::new_err(format!("http client fail: {err}")))?;
+        let client = shared_client(&self_.0.auth_headers(), &self_.1)?;
         let mp = NoProgress;
 
         let stream = self_.0.clone().crawl_file(&client, mp);
         let stream = PyFileMetaStream::new(stream);
         Ok(stream)
     }
+
+    fn files(self_: PyRef<'_, Self>) -> PyResult<Vec<PyFileMeta>> {
+        let client = shared_client(&self_.0.auth_headers(), &self_.1)?;
+        let mp = NoProgress;
+
+        let stream = self_.0.clone().crawl_file(&client, mp);
+        // blocking call, same as `download_with_validation`.
+        self_.py().detach(|| block_on(collect_files(stream)))
+    }
+
+    fn files_async<'py>(self_: PyRef<'py, Self>) -> PyResult<Bound<'py, PyAny>> {
+        let client = shared_client(&self_.0.auth_headers(), &self_.1)?;
+        let mp = NoProgress;
+
+        let stream = self_.0.clone().crawl_file(&client, mp);
+        future_into_py(self_.py(), collect_files(stream))
+    }
+}
+
+/// Options of `Dataset.download_with_validation` and `Dataset.download_async`.
+#[allow(clippy::too_many_arguments)]
+fn download_options(
+    limit: usize,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    max_file_size: Option<u64>,
+    manifest: bool,
+    on_progress: Option<Py<PyAny>>,
+    check_access: bool,
+    expand_zip: bool,
+    connections_per_file: usize,
+    check_disk_space: bool,
+    path_mapper: Option<Py<PyAny>>,
+    continue_on_error: bool,
+) -> PyResult<DownloadOptions> {
+    let filter = FileFilter::new(&include, &exclude)
+        .map_err(|err| PyRuntimeError::new_err(format!("invalid filter: {err}")))?;
+    let mut options = DownloadOptions::new()
+        .limit(limit)
+        .filter(filter)
+        .manifest(manifest)
+        .check_access(check_access)
+        .expand_zip(expand_zip)
+        .connections_per_file(connections_per_file)
+        .check_disk_space(check_disk_space)
+        .continue_on_error(continue_on_error);
+    if let Some(max_file_size) = max_file_size {
+        options = options.max_file_size(max_file_size);
+    }
+    if let Some(on_progress) = on_progress {
+        options = options.on_event(move |event| {
+            Python::attach(|py| {
+                let res = event_to_dict(py, event)
+                    .and_then(|event| on_progress.call1(py, (event,)).map(|_| ()));
+                if let Err(err) = res {
+                    err.print(py);
+                }
+            });
+        });
+    }
+    if let Some(path_mapper) = path_mapper {
+        options = options.path_mapper(move |file_meta| {
+            Python::attach(|py| {
+                let entry = Py::new(py, (PyFileEntry::from(file_meta.clone()), PyEntryBase))
+                    .and_then(|entry| path_mapper.call1(py, (entry,)))
+                    .and_then(|path| path.extract::<PathBuf>(py));
+                match entry {
+                    Ok(path) => path.to_string_lossy().into_owned(),
+                    Err(err) => {
+                        // the path in the dataset is kept
+                        err.print(py);
+                        file_meta.relative().as_str().to_string()
+                    }
+                }
+            })
+        });
+    }
+    Ok(options)
+}
+
+/// Crawls `stream` to completion, failing on the first error.
+async fn collect_files(
+    mut stream: BoxStream<'static, Result<FileMeta, Exn<CrawlerError>>>,
+) -> PyResult<Vec<PyFileMeta>> {
+    let mut files = Vec::new();
+    while let Some(res) = stream.next().await {
+        let fm = res.map_err(|e| py_err(&e, format!("{e:?}")))?;
+        files.push(PyFileMeta(fm));
+    }
+    Ok(files)
+}
+
+#[pyclass]
+#[pyo3(name = "DatasetMetadata")]
+struct PyDatasetMetadata {
+    #[pyo3(get)]
+    title: Option<String>,
+    #[pyo3(get)]
+    description: Option<String>,
+    #[pyo3(get)]
+    creators: Vec<String>,
+    #[pyo3(get)]
+    license: Option<String>,
+    #[pyo3(get)]
+    publication_date: Option<String>,
+    #[pyo3(get)]
+    version: Option<String>,
+    #[pyo3(get)]
+    doi: Option<String>,
+}
+
+impl From<DatasetMetadata> for PyDatasetMetadata {
+    fn from(metadata: DatasetMetadata) -> Self {
+        PyDatasetMetadata {
+            title: metadata.title,
+            description: metadata.description,
+            creators: metadata.creators,
+            license: metadata.license,
+            publication_date: metadata.publication_date,
+            version: metadata.version,
+            doi: metadata.doi,
+        }
+    }
+}
+
+/// Outcome of a single file of a `DownloadReport`.
+#[pyclass]
+#[pyo3(name = "FileReport")]
+#[derive(Clone)]
+struct PyFileReport {
+    #[pyo3(get)]
+    path: PathBuf,
+    #[pyo3(get)]
+    download_url: String,
+    /// e.g. `verified`, `skipped` or `failed`.
+    #[pyo3(get)]
+    outcome: String,
+    /// reason a file was skipped or error a file failed with.
+    #[pyo3(get)]
+    message: Option<String>,
+    /// checksum the file was verified against, as `(algorithm, value)`.
+    #[pyo3(get)]
+    checksum: Option<(String, String)>,
+    #[pyo3(get)]
+    bytes: u64,
+    /// wall time spent on the file, in seconds.
+    #[pyo3(get)]
+    elapsed: f64,
+}
+
+impl From<FileReport> for PyFileReport {
+    fn from(report: FileReport) -> Self {
+        let message = match &report.outcome {
+            FileOutcome::Skipped(message) | FileOutcome::Failed(message) => Some(message.clone()),
+            _ => None,
+        };
+        PyFileReport {
+            path: PathBuf::from(report.path.as_str()),
+            download_url: report.download_url.to_string(),
+            outcome: report.outcome.label().to_string(),
+            message,
+            checksum: report
+                .checksum
+                .map(|cs| (cs.algorithm().to_string(), cs.value().to_string())),
+            bytes: report.bytes,
+            elapsed: report.elapsed.as_secs_f64(),
+        }
+    }
+}
+
+/// Per-file outcomes of `Dataset.download_with_validation` and `Dataset.download_async`.
+#[pyclass]
+#[pyo3(name = "DownloadReport")]
+struct PyDownloadReport {
+    #[pyo3(get)]
+    files: Vec<PyFileReport>,
+    #[pyo3(get)]
+    doi: Option<String>,
+    #[pyo3(get)]
+    cancelled: bool,
+    report: DownloadReport,
+}
+
+impl From<DownloadReport> for PyDownloadReport {
+    fn from(report: DownloadReport) -> Self {
+        PyDownloadReport {
+            files: report
+                .files
+                .iter()
+                .cloned()
+                .map(PyFileReport::from)
+                .collect(),
+            doi: report.doi.clone(),
+            cancelled: report.cancelled,
+            report,
+        }
+    }
+}
+
+#[pymethods]
+impl PyDownloadReport {
+    fn is_success(&self) -> bool {
+        self.report.is_success()
+    }
+
+    fn stats<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        // round trip through `json` gives plain dicts, lists and scalars
+        py.import("json")?
+            .call_method1("loads", (self.report.stats().to_string(),))
+    }
+
+    fn __str__(&self) -> String {
+        self.report.to_string()
+    }
 }
 
 #[pyclass]
 struct DOIResolver {
-    runtime: tokio::runtime::Runtime,
     client: Client,
 }
 
@@ -294,29 +895,25 @@ impl DOIResolver {
     #[pyo3(signature = (timeout=5))]
     fn new(timeout: u64) -> PyResult<Self> {
         Ok(Self {
-            runtime: tokio::runtime::Runtime::new().map_err(|err| {
-                PyRuntimeError::new_err(format!("failed to create runtime: {err}"))
-            })?,
-            client: Client::builder()
-                .use_native_tls()
-                .timeout(Duration::from_secs(timeout))
-                .redirect(Policy::limited(5)) // limit number of redirects (relevant if follow_redirects is set to true)
-                .build()
-                .map_err(|err| {
-                    PyRuntimeError::new_err(format!("failed to create client: {err}"))
-                })?,
+            client: client_options(&ClientConfig {
+                timeout: Some(Duration::from_secs(timeout)),
+                ..ClientConfig::default()
+            })?
+            .builder()
+            .redirect(Policy::limited(5)) // limit number of redirects (relevant if follow_redirects is set to true)
+            .build()
+            .map_err(|err| PyRuntimeError::new_err(format!("failed to create client: {err}")))?,
         })
     }
 
     #[pyo3(signature = (doi, follow_redirects=true))]
     fn resolve(&self, doi: String, follow_redirects: bool) -> PyResult<String> {
-        self.runtime
-            .block_on(inner_resolve_doi_to_url(
-                &self.client,
-                &doi,
-                follow_redirects,
-            ))
-            .map_err(|err| PyRuntimeError::new_err(format!("{err}")))
+        block_on(inner_resolve_doi_to_url(
+            &self.client,
+            &doi,
+            follow_redirects,
+        ))
+        .map_err(|err| py_err(&err, format!("{err}")))
     }
 
     #[pyo3(signature = (dois, follow_redirects=true))]
@@ -324,22 +921,75 @@ impl DOIResolver {
         let futures = dois
             .iter()
             .map(|doi| inner_resolve_doi_to_url(&self.client, doi, follow_redirects));
-        self.runtime
-            .block_on(futures::future::join_all(futures))
+        block_on(futures::future::join_all(futures))
             .into_iter()
             .collect::<Result<Vec<String>, _>>()
-            .map_err(|err| PyRuntimeError::new_err(format!("{err}")))
+            .map_err(|err| py_err(&err, format!("{err}")))
+    }
+
+    #[pyo3(signature = (doi))]
+    fn metadata(&self, doi: String) -> PyResult<PyDatasetMetadata> {
+        block_on(inner_resolve_doi_metadata(&self.client, &doi))
+            .map(PyDatasetMetadata::from)
+            .map_err(|err| py_err(&err, format!("{err}")))
     }
 }
 
+fn auth_config(auth: HashMap<String, String>) -> PyResult<AuthConfig> {
+    let mut config = AuthConfig::new();
+    for (repo, token) in auth {
+        config = config.token(&repo, token).ok_or_else(|| {
+            PyRuntimeError::new_err(format!(
+                "unknown repository '{repo}' in auth, expect one of 'github', 'dryad', \
+                 'zenodo', 'dataverse', 'huggingface', 'osf', 'softwareheritage'"
+            ))
+        })?;
+    }
+    Ok(config)
+}
+
 #[pyfunction]
-#[pyo3(signature = (url, /))]
-fn resolve(_py: Python, url: &str) -> PyResult<PyDataset> {
-    let rt = tokio::runtime::Runtime::new().unwrap(); // create a runtime
-    let ds = rt
-        .block_on(inner_resolve(url))
+#[allow(clippy::too_many_arguments)]
+#[pyo3(signature = (url, /, auth=None, version=None, headers=None, timeout=None, connect_timeout=None, proxy=None, user_agent=None, tls_backend=None))]
+fn resolve(
+    _py: Python,
+    url: &str,
+    auth: Option<HashMap<String, String>>,
+    version: Option<String>,
+    headers: Option<HashMap<String, String>>,
+    timeout: Option<f64>,
+    connect_timeout: Option<f64>,
+    proxy: Option<String>,
+    user_agent: Option<String>,
+    tls_backend: Option<String>,
+) -> PyResult<PyDataset> {
+    let auth = match auth {
+        Some(auth) => auth_config(auth)?,
+        None => AuthConfig::from_env(),
+    };
+    let config = ClientConfig::new(
+        headers,
+        timeout,
+        connect_timeout,
+        proxy,
+        user_agent,
+        tls_backend,
+    )?;
+    let options = client_options(&config)?;
+    let ds = block_on(inner_resolve_with_options(
+        url,
+        auth,
+        &RepositoryConfig::load(),
+        &options,
+    ))
+    .map_err(|err| py_err(&err, format!("{err}")))?;
+    let Some(version) = version else {
+        return Ok(PyDataset(ds, config));
+    };
+    let client = shared_client(&ds.auth_headers(), &config)?;
+    let ds = block_on(ds.with_version(&client, &version))
         .map_err(|err| PyRuntimeError::new_err(format!("{err}")))?;
-    Ok(PyDataset(ds))
+    Ok(PyDataset(ds, config))
 }
 
 #[pyclass]
@@ -414,12 +1064,43 @@ struct PyFileEntry {
     creation_date: Option<String>,
     #[pyo3(get, set)]
     last_modification_date: Option<String>,
+    /// API url the file is listed at.
+    #[pyo3(get, set)]
+    endpoint_url: Option<String>,
+    /// key of the file in the API response of `endpoint_url`, e.g. `files.3`.
+    #[pyo3(get, set)]
+    endpoint_key: Option<String>,
+}
+
+impl From<FileMeta> for PyFileEntry {
+    fn from(meta: FileMeta) -> Self {
+        let endpoint = meta.endpoint();
+        PyFileEntry {
+            filename: meta.filename().map(|s| s.to_string()),
+            file_identifier: meta.file_identifier().map(|s| s.to_string()),
+            path_crawl_rel: PathBuf::from(meta.path().as_str()),
+            download_url: meta.download_url().as_str().to_string(),
+            size: meta.size(),
+            checksum: meta
+                .checksum()
+                .iter()
+                .map(|cs| (cs.algorithm().to_string(), cs.value().to_string()))
+                .collect::<Vec<_>>(),
+            mimetype: meta.mimetype().map(|mime| mime.to_string()),
+            version: meta.version().map(|v| v.to_string()),
+            creation_date: meta.creation_date().map(|v| v.to_string()),
+            last_modification_date: meta.last_modification_date().map(|v| v.to_string()),
+            endpoint_url: Some(endpoint.parent_url.to_string()),
+            endpoint_key: endpoint.key,
+        }
+    }
 }
 
 #[pymethods]
 impl PyFileEntry {
     #[allow(clippy::too_many_arguments)]
     #[new]
+    #[pyo3(signature = (filename, file_identifier, path_crawl_rel, download_url, size, checksum, mimetype, version, creation_date, last_modification_date, endpoint_url=None, endpoint_key=None))]
     fn new(
         filename: Option<String>,
         file_identifier: Option<String>,
@@ -431,6 +1112,8 @@ impl PyFileEntry {
         version: Option<String>,
         creation_date: Option<String>,
         last_modification_date: Option<String>,
+        endpoint_url: Option<String>,
+        endpoint_key: Option<String>,
     ) -> (Self, PyEntryBase) {
         (
             PyFileEntry {
@@ -444,6 +1127,8 @@ impl PyFileEntry {
                 version,
                 creation_date,
                 last_modification_date,
+                endpoint_url,
+                endpoint_key,
             },
             PyEntryBase::new(),
         )
@@ -473,38 +1158,9 @@ impl<'py> IntoPyObject<'py> for PyEntry {
             )
             .map(pyo3::Py::into_any)
             .expect("cannot construct the PyDirEntry"),
-            Entry::File(meta) => Py::new(
-                py,
-                (
-                    PyFileEntry {
-                        filename: meta.filename().map(|s| s.to_string()),
-                        file_identifier: meta.file_identifier().map(|s| s.to_string()),
-                        path_crawl_rel: PathBuf::from(meta.path().as_str()),
-                        download_url: meta.download_url().as_str().to_string(),
-                        size: meta.size(),
-                        checksum: meta
-                            .checksum()
-                            .iter()
-                            .map(|cs| match cs {
-                                datahugger::Checksum::Md5(v) => ("md5".to_string(), v.clone()),
-                                datahugger::Checksum::Sha256(v) => {
-                                    ("sha256".to_string(), v.clone())
-                                }
-                                datahugger::Checksum::Sha1(v) => ("sha1".to_string(), v.clone()),
-                            })
-                            .collect::<Vec<_>>(),
-                        mimetype: meta.mimetype().map(|mime| mime.to_string()),
-                        version: meta.version().map(|v| v.to_string()),
-                        creation_date: meta.creation_date().map(|v| v.to_string()),
-                        last_modification_date: meta
-                            .last_modification_date()
-                            .map(|v| v.to_string()),
-                    },
-                    PyEntryBase,
-                ),
-            )
-            .map(pyo3::Py::into_any)
-            .expect("cannot construct the PyDirEntry"),
+            Entry::File(meta) => Py::new(py, (PyFileEntry::from(meta), PyEntryBase))
+                .map(pyo3::Py::into_any)
+                .expect("cannot construct the PyFileEntry"),
         };
 
         Ok(obj.into_bound(py))
@@ -521,34 +1177,9 @@ impl<'py> IntoPyObject<'py> for PyFileMeta {
 
     fn into_pyobject(self, py: Python<'py>) -> Result<Self::Output, Self::Error> {
         let meta = self.0;
-        let obj = Py::new(
-            py,
-            (
-                PyFileEntry {
-                    filename: meta.filename().map(|s| s.to_string()),
-                    file_identifier: meta.file_identifier().map(|s| s.to_string()),
-                    path_crawl_rel: PathBuf::from(meta.path().as_str()),
-                    download_url: meta.download_url().as_str().to_string(),
-                    size: meta.size(),
-                    checksum: meta
-                        .checksum()
-                        .iter()
-                        .map(|cs| match cs {
-                            datahugger::Checksum::Md5(v) => ("md5".to_string(), v.clone()),
-                            datahugger::Checksum::Sha256(v) => ("sha256".to_string(), v.clone()),
-                            datahugger::Checksum::Sha1(v) => ("sha1".to_string(), v.clone()),
-                        })
-                        .collect::<Vec<_>>(),
-                    mimetype: meta.mimetype().map(|mime| mime.to_string()),
-                    version: meta.version().map(|v| v.to_string()),
-                    creation_date: meta.creation_date().map(|v| v.to_string()),
-                    last_modification_date: meta.last_modification_date().map(|v| v.to_string()),
-                },
-                PyEntryBase,
-            ),
-        )
-        .map(pyo3::Py::into_any)
-        .expect("cannot construct the PyDirEntry");
+        let obj = Py::new(py, (PyFileEntry::from(meta), PyEntryBase))
+            .map(pyo3::Py::into_any)
+            .expect("cannot construct the PyFileEntry");
 
         Ok(obj.into_bound(py))
     }
@@ -573,9 +1204,8 @@ impl PyEntryStream {
     }
 
     fn __next__(&self, _py: Python<'_>) -> PyResult<PyEntry> {
-        let runtime = pyo3_async_runtimes::tokio::get_runtime();
         let stream = self.stream.clone();
-        runtime.block_on(next_stream(stream, true))
+        block_on(next_stream(stream, true))
     }
 }
 
@@ -589,8 +1219,7 @@ async fn next_stream(
             let py_entry = PyEntry(entry);
             Ok(py_entry)
         }
-        // TODO: Errors mapping to py types as well and return the PyCrawrError.
-        Some(Err(e)) => Err(PyRuntimeError::new_err(format!("{e:?}"))),
+        Some(Err(e)) => Err(py_err(&e, format!("{e:?}"))),
         None => {
             if is_sync {
                 Err(PyStopIteration::new_err("stream exhausted"))
@@ -618,9 +1247,8 @@ impl PyFileMetaStream {
     }
 
     fn __next__(&self, _py: Python<'_>) -> PyResult<PyFileMeta> {
-        let runtime = pyo3_async_runtimes::tokio::get_runtime();
         let stream = self.stream.clone();
-        runtime.block_on(next_stream_file(stream, true))
+        block_on(next_stream_file(stream, true))
     }
 }
 
@@ -634,8 +1262,7 @@ async fn next_stream_file(
             let frame = PyFileMeta(fm);
             Ok(frame)
         }
-        // TODO: Errors mapping to py types as well and return the PyCrawrError.
-        Some(Err(e)) => Err(PyRuntimeError::new_err(format!("{e:?}"))),
+        Some(Err(e)) => Err(py_err(&e, format!("{e:?}"))),
         None => {
             if is_sync {
                 Err(PyStopIteration::new_err("stream exhausted"))
@@ -650,12 +1277,30 @@ async fn next_stream_file(
 #[pyo3(name = "datahugger")]
 fn datahuggerpy(py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(resolve, m)?)?;
+    m.add_function(wrap_pyfunction!(configure_client, m)?)?;
     m.add_class::<DOIResolver>()?;
     m.add_class::<PyDataset>()?;
     m.add_class::<PyEntryBase>()?;
+    m.add_class::<PyDatasetMetadata>()?;
+    m.add_class::<PyDownloadReport>()?;
+    m.add_class::<PyFileReport>()?;
     m.add_class::<PyDataverseJsonSrcDataset>()?;
     m.add_class::<PyZenodoJsonSrcDataset>()?;
     m.add_class::<PyHalJsonSrcDataset>()?;
+    m.add("DatahuggerError", py.get_type::<DatahuggerError>())?;
+    m.add("NotFoundError", py.get_type::<NotFoundError>())?;
+    m.add("RateLimitedError", py.get_type::<RateLimitedError>())?;
+    m.add(
+        "ChecksumMismatchError",
+        py.get_type::<ChecksumMismatchError>(),
+    )?;
+    m.add("LocalIOError", py.get_type::<LocalIOError>())?;
+    m.add("AccessRequiredError", py.get_type::<AccessRequiredError>())?;
+    m.add(
+        "InsufficientSpaceError",
+        py.get_type::<InsufficientSpaceError>(),
+    )?;
+    m.add("HttpError", py.get_type::<HttpError>())?;
 
     // Dir
     let dir = py.get_type::<PyDirEntry>();
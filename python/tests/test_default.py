@@ -4,6 +4,7 @@ import pytest
 from pathlib import Path
 from datahugger import (
     FileEntry,
+    configure_client,
     resolve,
     DOIResolver,
     DataverseJsonSrcDataset,
@@ -22,6 +23,22 @@ def test_resolve_default():
         ds.root_url()
         == "https://dataverse.harvard.edu/api/datasets/:persistentId/versions/:latest-published?persistentId=doi%3A10.7910%2FDVN%2FKBHLOD"
     )
+    assert ds.destination("data/{repo}/{doi}").as_posix() == (
+        f"data/{ds.backend_name}/10.7910_DVN_KBHLOD"
+    )
+
+
+def test_configure_client():
+    configure_client(headers={"From": "test@example.org"}, connect_timeout=30)
+    with pytest.raises(RuntimeError):
+        configure_client(headers={"bad header": "x"})
+    with pytest.raises(RuntimeError):
+        configure_client(timeout=-1)
+    with pytest.raises(RuntimeError):
+        configure_client(proxy="not a url")
+    with pytest.raises(RuntimeError):
+        configure_client(tls_backend="gnutls")
+    configure_client()
 
 
 def test_resolve_doi_blocking():
@@ -53,6 +70,10 @@ def test_resolve_doi_blocking():
         "https://phys-techsciences.datastations.nl/dataset.xhtml?persistentId=doi:10.17026/DANS-2BA-UAVX",
     ]
 
+    metadata = doi_resolver.metadata("doi:10.34894/0B7ZLK")
+    assert metadata.doi == "10.34894/0B7ZLK"
+    assert metadata.title
+
 
 def test_download(tmp_path: Path) -> None:
     """real call to download, can be not stable. Since it is only for the non-recommended API,
@@ -61,7 +82,12 @@ def test_download(tmp_path: Path) -> None:
     ds = resolve(
         "https://dataverse.harvard.edu/dataset.xhtml?persistentId=doi:10.7910/DVN/KBHLOD"
     )
-    ds.download_with_validation(tmp_path)
+    report = ds.download_with_validation(tmp_path, continue_on_error=True)
+    assert report.is_success(), str(report)
+    assert sorted(file.path.name for file in report.files) == sorted(
+        i.name for i in tmp_path.iterdir()
+    )
+    assert all(file.checksum is not None for file in report.files)
     assert sorted([i.name for i in tmp_path.iterdir()]) == [
         "ECM_matrix.py",
         "Markov_comp.py",
@@ -93,6 +119,8 @@ def test_dataclass_constructor():
 
     entry.size = 12
     assert entry.size == 12
+    assert entry.endpoint_url is None
+    assert entry.endpoint_key is None
 
 
 def test_crawl_blocking():
@@ -106,6 +134,32 @@ def test_crawl_blocking():
         print(i)
 
 
+def test_files_blocking():
+    ds = resolve(
+        "https://dataverse.harvard.edu/dataset.xhtml?persistentId=doi:10.7910/DVN/KBHLOD"
+    )
+    files = ds.files()
+    assert all(isinstance(f, FileEntry) for f in files)
+    assert sorted(f.path_crawl_rel.name for f in files) == [
+        "ECM_matrix.py",
+        "Markov_comp.py",
+        "Markov_learning.py",
+        "tutorial1.py",
+        "tutorial2.py",
+        "tutorial3.py",
+        "tutorial4.py",
+    ]
+
+
+@pytest.mark.asyncio
+async def test_files_async():
+    ds = resolve(
+        "https://dataverse.harvard.edu/dataset.xhtml?persistentId=doi:10.7910/DVN/KBHLOD"
+    )
+    files = await ds.files_async()
+    assert len(files) == len(ds.files())
+
+
 def test_dataverse_from_json():
     try:
         response = requests.get(
@@ -0,0 +1,157 @@
+"""The stubs of `datahugger/datahugger.pyi` are written by hand, these tests keep them in
+sync with the classes and functions the Rust extension module exports, and their
+parameters with the `#[pyo3(signature = ...)]` of the Rust functions."""
+
+import ast
+import inspect
+import pathlib
+from typing import Any
+
+import datahugger
+from datahugger import datahugger as ext
+
+STUB = pathlib.Path(datahugger.__file__).parent / "datahugger.pyi"
+
+# declared in the stub only, to type the crawl streams
+STUB_ONLY = {"SyncAsyncIterator"}
+
+
+def stub_members() -> tuple[dict[str, set[str]], set[str]]:
+    """returns the members of each class of the stub and its module functions"""
+    tree = ast.parse(STUB.read_text())
+    classes: dict[str, set[str]] = {}
+    functions: set[str] = set()
+    for node in tree.body:
+        if isinstance(node, ast.ClassDef):
+            members = set()
+            for item in node.body:
+                if isinstance(item, (ast.FunctionDef, ast.AsyncFunctionDef)):
+                    members.add(item.name)
+                elif isinstance(item, ast.AnnAssign) and isinstance(
+                    item.target, ast.Name
+                ):
+                    members.add(item.target.id)
+            classes[node.name] = members
+        elif isinstance(node, ast.FunctionDef):
+            functions.add(node.name)
+    return classes, functions
+
+
+def stub_signatures() -> dict[str, ast.FunctionDef | ast.AsyncFunctionDef]:
+    """returns the functions of the stub by qualified name, e.g. `Dataset.files`"""
+    tree = ast.parse(STUB.read_text())
+    signatures: dict[str, ast.FunctionDef | ast.AsyncFunctionDef] = {}
+    for node in tree.body:
+        if isinstance(node, ast.ClassDef):
+            for item in node.body:
+                if isinstance(item, (ast.FunctionDef, ast.AsyncFunctionDef)):
+                    signatures[f"{node.name}.{item.name}"] = item
+        elif isinstance(node, ast.FunctionDef):
+            signatures[node.name] = node
+    return signatures
+
+
+def stub_parameters(
+    node: ast.FunctionDef | ast.AsyncFunctionDef,
+) -> list[tuple[str, ast.expr | None]]:
+    """returns the parameters of a stub function with their default expression"""
+    args = node.args
+    positional = args.posonlyargs + args.args
+    defaults = [None] * (len(positional) - len(args.defaults)) + args.defaults
+    params = list(zip((arg.arg for arg in positional), defaults))
+    params += zip((arg.arg for arg in args.kwonlyargs), args.kw_defaults)
+    return params
+
+
+def compiled_signature(qualname: str) -> inspect.Signature | None:
+    """returns the signature pyo3 exposes in `__text_signature__`, `None` if it has none"""
+    owner, _, name = qualname.rpartition(".")
+    obj = getattr(ext, owner) if owner else ext
+    # the `#[new]` signature is the one of the class, without `self`
+    target = obj if name == "__init__" else getattr(obj, name, None)
+    if target is None or getattr(target, "__text_signature__", None) is None:
+        return None
+    return inspect.signature(target)
+
+
+def same_default(stub: ast.expr, compiled: Any) -> bool:
+    if compiled is Ellipsis or (
+        isinstance(stub, ast.Constant) and stub.value is Ellipsis
+    ):
+        # pyo3 renders defaults that are no Python literal, e.g. `Vec::new()`, as `...`
+        return True
+    try:
+        return ast.literal_eval(stub) == compiled
+    except ValueError:
+        return ast.unparse(stub) == repr(compiled)
+
+
+def public(names):
+    return {name for name in names if not name.startswith("_")}
+
+
+def test_stub_declares_module():
+    classes, functions = stub_members()
+    for name in public(dir(ext)):
+        obj = getattr(ext, name)
+        if inspect.isclass(obj):
+            assert name in classes, f"class '{name}' is missing from the stub"
+            if issubclass(obj, BaseException):
+                continue
+            missing = public(vars(obj)) - classes[name]
+            assert not missing, f"'{name}' members {missing} are missing from the stub"
+        elif callable(obj):
+            assert name in functions, f"function '{name}' is missing from the stub"
+
+
+def test_module_implements_stub():
+    classes, functions = stub_members()
+    for name in functions:
+        assert callable(getattr(ext, name, None)), f"function '{name}' does not exist"
+    for name, members in classes.items():
+        if name in STUB_ONLY:
+            continue
+        cls = getattr(ext, name, None)
+        assert inspect.isclass(cls), f"class '{name}' does not exist"
+        missing = {m for m in public(members) if not hasattr(cls, m)}
+        assert not missing, f"'{name}' members {missing} do not exist"
+
+
+def test_stub_signatures_match_module():
+    for qualname, node in stub_signatures().items():
+        if qualname.split(".")[0] in STUB_ONLY:
+            continue
+        signature = compiled_signature(qualname)
+        if signature is None:
+            continue
+        params = stub_parameters(node)
+        if "." in qualname and params and params[0][0] == "self":
+            if qualname.endswith(".__init__") or "self" not in signature.parameters:
+                params = params[1:]
+        compiled = [
+            param
+            for param in signature.parameters.values()
+            if param.kind not in (param.VAR_POSITIONAL, param.VAR_KEYWORD)
+        ]
+        names = [name for name, _ in params]
+        assert names == [param.name for param in compiled], (
+            f"'{qualname}' parameters {names} differ from the module's "
+            f"{[param.name for param in compiled]}"
+        )
+        for (name, default), param in zip(params, compiled):
+            if default is None:
+                assert param.default is param.empty, (
+                    f"'{qualname}' parameter '{name}' has a default in the module"
+                )
+            else:
+                assert param.default is not param.empty, (
+                    f"'{qualname}' parameter '{name}' has no default in the module"
+                )
+                assert same_default(default, param.default), (
+                    f"'{qualname}' parameter '{name}' defaults to "
+                    f"'{ast.unparse(default)}' in the stub, {param.default!r} in the module"
+                )
+
+
+def test_py_typed_is_shipped():
+    assert (STUB.parent / "py.typed").is_file()
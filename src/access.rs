@@ -0,0 +1,80 @@
+use url::Url;
+
+use crate::CrawlPath;
+
+/// A condition to meet before files of a dataset can be downloaded, see
+/// [`Dataset::access_requirements`](crate::Dataset::access_requirements).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AccessRequirement {
+    /// A credential for the repository must be configured, see
+    /// [`AuthConfig`](crate::AuthConfig).
+    Authentication { repository: String },
+    /// Terms must be accepted, e.g. the terms of access of a Dataverse dataset.
+    Terms { text: String, url: Option<Url> },
+    /// Access must be requested from and granted by the owners of the dataset at `url`, for
+    /// the files at `paths`, or for all files if empty.
+    Request { url: Url, paths: Vec<CrawlPath> },
+    /// The files are under embargo, until the given date if known.
+    Embargo { until: Option<String> },
+}
+
+impl std::fmt::Display for AccessRequirement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccessRequirement::Authentication { repository } => {
+                write!(f, "a {repository} access token is required")
+            }
+            AccessRequirement::Terms { text, url } => {
+                write!(f, "terms must be accepted: {text}")?;
+                if let Some(url) = url {
+                    write!(f, " ({url})")?;
+                }
+                Ok(())
+            }
+            AccessRequirement::Request { url, paths } => {
+                if paths.is_empty() {
+                    write!(f, "access must be requested at {url}")
+                } else {
+                    write!(
+                        f,
+                        "access to {} restricted files must be requested at {url}",
+                        paths.len()
+                    )
+                }
+            }
+            AccessRequirement::Embargo { until } => match until {
+                Some(until) => write!(f, "files are under embargo until {until}"),
+                None => write!(f, "files are under embargo"),
+            },
+        }
+    }
+}
+
+/// What must be obtained before a dataset can be downloaded, as far as the repository tells
+/// without trying to download its files.
+///
+/// Requirements a configured credential may already satisfy, e.g. access granted to the
+/// owner of the token, are not listed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccessRequirements {
+    pub requirements: Vec<AccessRequirement>,
+}
+
+impl AccessRequirements {
+    /// Returns `true` if nothing is required.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.requirements.is_empty()
+    }
+}
+
+impl std::fmt::Display for AccessRequirements {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "access requirements not met:")?;
+        for requirement in &self.requirements {
+            write!(f, "\n  - {requirement}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,486 @@
+use exn::{Exn, ResultExt};
+use futures_util::{future, stream, StreamExt, TryStreamExt};
+use indicatif::{ProgressBar, ProgressStyle};
+use reqwest::Client;
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{Read, Write},
+    path::Path,
+    sync::Arc,
+    time::Instant,
+};
+use tokio::io::AsyncWriteExt;
+use tracing::warn;
+use url::Url;
+
+use crate::retry::RetryExt;
+use crate::{
+    crawler::{crawl_with_fanout, CrawlerError, ProgressManager},
+    error::ErrorStatus,
+    metrics::record_file,
+    ops::{download_entries, lock_dst},
+    progress::TotalProgress,
+    provenance,
+    redirect::follow_cross_host,
+    validate::expected_checksum,
+    validate::Validator,
+    ClientOptions, DatahuggerError, Dataset, DownloadEvent, DownloadOptions, DownloadReport, Entry,
+    FileMeta, FileOutcome, FileReport,
+};
+
+/// Name of the archive temporarily written into the destination directory in archive mode.
+const ARCHIVE_FILE_NAME: &str = ".datahugger-archive.zip";
+
+/// Downloads `dataset` as the single archive at `archive_url` and extracts it into `dst_dir`.
+///
+/// The dataset is still crawled, the listing is used to select the files to extract and to
+/// validate them. Listed files missing from the archive (e.g. restricted files, or files
+/// beyond the archive size limit of the server) are downloaded one by one.
+pub(crate) async fn download_archive(
+    dataset: &Dataset,
+    archive_url: Url,
+    client: &Client,
+    dst_dir: &Path,
+    mp: impl ProgressManager,
+    options: &DownloadOptions,
+) -> Result<DownloadReport, Exn<CrawlerError>> {
+    fs::create_dir_all(dst_dir).or_raise(|| CrawlerError {
+        message: format!("cannot create dir at '{}'", dst_dir.display()),
+        status: ErrorStatus::Permanent,
+    })?;
+    let _lock = lock_dst(dst_dir, options)?;
+
+    let files: HashMap<String, FileMeta> = crawl_with_fanout(
+        client.clone(),
+        Arc::clone(&dataset.backend),
+        dataset.root_dir(),
+        mp.clone(),
+        options.crawl_fanout,
+    )
+    .try_filter_map(|entry| {
+        let file = match entry {
+            Entry::File(file_meta) if options.is_selected(&entry) => {
+                Some((file_meta.relative().as_str().to_string(), file_meta))
+            }
+            _ => None,
+        };
+        future::ready(Ok(file))
+    })
+    .try_collect()
+    .await?;
+
+    let archive = dst_dir.join(ARCHIVE_FILE_NAME);
+    let fetched = tokio::select! {
+        res = fetch_archive(client, &options.client_options, &archive_url, &archive, &mp) => Some(res),
+        () = options.cancel.cancelled() => None,
+    };
+    let Some(fetched) = fetched else {
+        let _ = fs::remove_file(&archive);
+        return Ok(DownloadReport {
+            cancelled: true,
+            ..DownloadReport::default()
+        });
+    };
+    fetched?;
+
+    let pb = mp.insert_from_back(0, ProgressBar::new(files.len() as u64));
+    pb.set_style(
+        ProgressStyle::with_template("{msg} [{bar:40.cyan/blue}] {pos}/{len}")
+            .expect("indicatif template error")
+            .progress_chars("=>-"),
+    );
+    pb.set_message("Extracting dataset archive");
+    let extracted = {
+        let archive = archive.clone();
+        let dst_dir = dst_dir.to_path_buf();
+        let options = options.clone();
+        let pb = pb.clone();
+        tokio::task::spawn_blocking(move || {
+            extract_archive(&archive, &archive_url, &dst_dir, files, &options, &pb)
+        })
+        .await
+        .or_raise(|| CrawlerError {
+            message: "archive extraction task failed".to_string(),
+            status: ErrorStatus::Permanent,
+        })
+    };
+    pb.finish_and_clear();
+    if let Err(err) = fs::remove_file(&archive) {
+        warn!("cannot remove archive '{}': {err}", archive.display());
+    }
+    let (mut reports, missing) = extracted??;
+
+    let mut cancelled = false;
+    if !missing.is_empty() {
+        warn!(
+            "{} files are missing from the dataset archive, downloading them one by one",
+            missing.len()
+        );
+        // the destination is already locked above
+        let options = options.clone().lock(false);
+        let total = TotalProgress::planned(&mp, &missing);
+        let entries = stream::iter(missing.into_iter().map(|file| Ok(Entry::File(file))));
+        let report = download_entries(entries, client, dst_dir, mp, &options, total).await?;
+        reports.extend(report.files);
+        cancelled = report.cancelled;
+    }
+
+    Ok(DownloadReport {
+        files: reports,
+        doi: None,
+        cancelled,
+    })
+}
+
+async fn fetch_archive(
+    client: &Client,
+    client_options: &ClientOptions,
+    archive_url: &Url,
+    dst: &Path,
+    mp: &impl ProgressManager,
+) -> Result<(), Exn<CrawlerError>> {
+    let resp = client
+        .get(archive_url.clone())
+        .send_with_retry()
+        .await
+        .or_raise(|| CrawlerError {
+            message: format!("fail at client sent GET {archive_url}"),
+            status: ErrorStatus::Temporary,
+        })?;
+    let resp = follow_cross_host(resp, client_options)
+        .await
+        .or_raise(|| CrawlerError {
+            message: format!("fail to follow the redirect of {archive_url}"),
+            status: ErrorStatus::Temporary,
+        })?;
+    let resp = resp.error_for_status().or_raise(|| CrawlerError {
+        message: format!("fail GET {archive_url}"),
+        status: ErrorStatus::Permanent,
+    })?;
+
+    let pb = if let Some(len) = resp.content_length() {
+        mp.insert_from_back(0, ProgressBar::new(len))
+    } else {
+        mp.insert_from_back(0, ProgressBar::no_length())
+    };
+    pb.set_style(
+        ProgressStyle::with_template(
+            "{msg:<60} [{bar:40.cyan/blue}] \
+             {decimal_bytes:>8}/{decimal_total_bytes:>8} \
+             ({decimal_bytes_per_sec:>12}, {eta:>3})",
+        )
+        .expect("indicatif template error")
+        .progress_chars("=>-"),
+    );
+    pb.enable_steady_tick(std::time::Duration::from_millis(100));
+    pb.set_message("dataset archive");
+
+    let mut fh = tokio::fs::File::create(dst)
+        .await
+        .or_raise(|| CrawlerError {
+            message: format!("fail on create file at {}", dst.display()),
+            status: ErrorStatus::Permanent,
+        })?;
+    let mut stream = resp.bytes_stream();
+    while let Some(item) = stream.next().await {
+        let bytes = item.or_raise(|| CrawlerError {
+            message: "reqwest error stream".to_string(),
+            status: ErrorStatus::Permanent,
+        })?;
+        fh.write_all(&bytes).await.or_raise(|| CrawlerError {
+            message: "fail at writing to fs".to_string(),
+            status: ErrorStatus::Permanent,
+        })?;
+        pb.inc(bytes.len() as u64);
+    }
+    fh.flush().await.or_raise(|| CrawlerError {
+        message: "fail at writing to fs".to_string(),
+        status: ErrorStatus::Permanent,
+    })?;
+    pb.finish_and_clear();
+    Ok(())
+}
+
+/// Extracts the listed `files` from the archive, returns the report of the extracted files
+/// and the listed files that are not in the archive.
+fn extract_archive(
+    archive: &Path,
+    archive_url: &Url,
+    dst_dir: &Path,
+    mut files: HashMap<String, FileMeta>,
+    options: &DownloadOptions,
+    pb: &ProgressBar,
+) -> Result<(Vec<FileReport>, Vec<FileMeta>), Exn<CrawlerError>> {
+    let fh = File::open(archive).or_raise(|| CrawlerError {
+        message: format!("cannot open archive '{}'", archive.display()),
+        status: ErrorStatus::Permanent,
+    })?;
+    let mut zip = zip::ZipArchive::new(fh).or_raise(|| CrawlerError {
+        message: format!("'{archive_url}' is not a valid zip archive"),
+        status: ErrorStatus::Permanent,
+    })?;
+
+    let mut reports = Vec::new();
+    for idx in 0..zip.len() {
+        let mut entry = zip.by_index(idx).or_raise(|| CrawlerError {
+            message: format!("cannot read entry {idx} of the archive"),
+            status: ErrorStatus::Permanent,
+        })?;
+        if entry.is_dir() {
+            continue;
+        }
+        // `enclosed_name` rejects names escaping the destination, e.g. '../a'
+        let Some(name) = entry.enclosed_name() else {
+            warn!("skip '{}' of the archive, unsafe path", entry.name());
+            continue;
+        };
+        let key = name.to_string_lossy().replace('\\', "/");
+        // not listed, e.g. the 'MANIFEST.TXT' added by Dataverse, or filtered out
+        let Some(file_meta) = files.remove(&key) else {
+            continue;
+        };
+        let file_meta = options.local_file(file_meta);
+        pb.inc(1);
+
+        let path = file_meta.relative();
+        let precheck = options.validation_policy.precheck(&file_meta);
+        let skip = match (file_meta.size(), options.max_file_size) {
+            (Some(size), Some(max_file_size)) if size > max_file_size => Some(format!(
+                "size {size} bytes exceeds max file size {max_file_size} bytes"
+            )),
+            _ => precheck.as_ref().ok().cloned().flatten(),
+        };
+        if let Some(reason) = skip {
+            options.emit_blocking(DownloadEvent::Skipped {
+                path: path.clone(),
+                reason: reason.clone(),
+            });
+            let report = FileReport {
+                path,
+                download_url: file_meta.download_url(),
+                checksum: None,
+                bytes: 0,
+                elapsed: std::time::Duration::ZERO,
+                timing: None,
+                outcome: FileOutcome::Skipped(reason),
+            };
+            record_file(&report, None);
+            reports.push(report);
+            continue;
+        }
+
+        options.emit_blocking(DownloadEvent::Started {
+            path: path.clone(),
+            size: file_meta.size(),
+        });
+        let start = Instant::now();
+        let dst = dst_dir.join(&path);
+        let mut got_size = 0;
+        let mut error = None;
+        let extracted = precheck
+            .and_then(|_| extract_file(&mut entry, &dst, &file_meta, &mut got_size))
+            .and_then(|outcome| options.validation_policy.check(&file_meta, outcome));
+        let outcome = match extracted {
+            Ok(FileOutcome::Skipped(reason)) => {
+                let _ = fs::remove_file(&dst);
+                options.emit_blocking(DownloadEvent::Skipped {
+                    path: path.clone(),
+                    reason: reason.clone(),
+                });
+                FileOutcome::Skipped(reason)
+            }
+            Ok(outcome) => {
+                if options.xattrs {
+                    provenance::record_xattrs(&dst, archive_url, expected_checksum(&file_meta));
+                }
+                options.emit_blocking(DownloadEvent::Finished {
+                    path: path.clone(),
+                    size: got_size,
+                });
+                outcome
+            }
+            Err(err) => {
+                let message = format!("{err}");
+                options.emit_blocking(DownloadEvent::Failed {
+                    path: path.clone(),
+                    message: message.clone(),
+                });
+                if !options.continue_on_error {
+                    return Err(err);
+                }
+                warn!("extraction of '{path}' failed: {err:?}");
+                error = Some(DatahuggerError::from(&err));
+                FileOutcome::Failed(message)
+            }
+        };
+        let checksum = expected_checksum(&file_meta)
+            .filter(|_| outcome.is_checksum_verified())
+            .cloned();
+        let report = FileReport {
+            path,
+            download_url: file_meta.download_url(),
+            checksum,
+            bytes: got_size,
+            elapsed: start.elapsed(),
+            timing: None,
+            outcome,
+        };
+        record_file(&report, error.as_ref());
+        reports.push(report);
+    }
+
+    Ok((reports, files.into_values().collect()))
+}
+
+fn extract_file(
+    src: &mut impl Read,
+    dst: &Path,
+    file_meta: &FileMeta,
+    got_size: &mut u64,
+) -> Result<FileOutcome, Exn<CrawlerError>> {
+    if let Some(parent_dir) = dst.parent() {
+        fs::create_dir_all(parent_dir).or_raise(|| CrawlerError {
+            message: format!("connot create folder dir of '{}'", parent_dir.display()),
+            status: ErrorStatus::Permanent,
+        })?;
+    }
+    let mut fh = File::create(dst).or_raise(|| CrawlerError {
+        message: format!("fail on create file at {}", dst.display()),
+        status: ErrorStatus::Permanent,
+    })?;
+
+    let mut validator = Validator::new(file_meta, false);
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = src.read(&mut buf).or_raise(|| CrawlerError {
+            message: format!(
+                "fail at reading '{}' from the archive",
+                file_meta.relative()
+            ),
+            status: ErrorStatus::Permanent,
+        })?;
+        if n == 0 {
+            break;
+        }
+        validator.update(&buf[..n]);
+        fh.write_all(&buf[..n]).or_raise(|| CrawlerError {
+            message: "fail at writing to fs".to_string(),
+            status: ErrorStatus::Permanent,
+        })?;
+        *got_size += n as u64;
+    }
+
+    Ok(validator.finish(file_meta, *got_size)?.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{datasets::DataverseDataset, testutil::TempDir, DownloadExt};
+    use digest::Digest;
+    use indicatif::MultiProgress;
+    use serde_json::Value as JsonValue;
+    use std::{io::Cursor, str::FromStr};
+    use wiremock::{
+        matchers::{method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+    use zip::write::SimpleFileOptions;
+
+    fn dataverse_file(id: u64, dir_label: Option<&str>, name: &str, content: &str) -> JsonValue {
+        let mut filej = serde_json::json!({
+            "restricted": false,
+            "version": 1,
+            "dataFile": {
+                "id": id,
+                "filename": name,
+                "filesize": content.len(),
+                "creationDate": "2024-01-01",
+                "contentType": "text/plain",
+                "checksum": {
+                    "type": "MD5",
+                    "value": hex::encode(md5::Md5::digest(content.as_bytes())),
+                },
+            },
+        });
+        if let Some(dir_label) = dir_label {
+            filej["directoryLabel"] = dir_label.into();
+        }
+        filej
+    }
+
+    #[tokio::test]
+    async fn test_download_archive_mode() {
+        let mock_server = MockServer::start().await;
+        let id = "doi:10.5072/FK2/ABC";
+        Mock::given(method("GET"))
+            .and(path(
+                "/api/datasets/:persistentId/versions/:latest-published",
+            ))
+            .and(query_param("persistentId", id))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": { "files": [
+                    dataverse_file(1, None, "a.txt", "aaa"),
+                    dataverse_file(2, Some("sub"), "b.txt", "bbb"),
+                    dataverse_file(3, None, "c.txt", "ccc"),
+                ]}
+            })))
+            .mount(&mock_server)
+            .await;
+
+        // 'c.txt' is missing from the archive and 'sub/b.txt' is corrupted
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in [
+            ("a.txt", "aaa"),
+            ("sub/b.txt", "corrupted"),
+            ("MANIFEST.TXT", "manifest"),
+        ] {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        let archive = zip.finish().unwrap().into_inner();
+        Mock::given(method("GET"))
+            .and(path(
+                "/api/access/dataset/:persistentId/versions/:latest-published",
+            ))
+            .and(query_param("persistentId", id))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(archive))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/access/datafile/3"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("ccc"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let base_url = Url::from_str(&mock_server.uri()).unwrap();
+        let ds = Dataset::new(DataverseDataset::new(id, &base_url, ":latest-published"));
+        let dst = TempDir::new("download-archive-mode");
+        let options = DownloadOptions::new()
+            .archive_mode(true)
+            .continue_on_error(true);
+        let report = ds
+            .download_with_validation(&Client::new(), &dst, MultiProgress::new(), &options)
+            .await
+            .unwrap();
+
+        let mut outcomes: Vec<_> = report
+            .files
+            .iter()
+            .map(|f| (f.path.as_str().to_string(), f.outcome.clone()))
+            .collect();
+        outcomes.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(outcomes[0], ("a.txt".to_string(), FileOutcome::Verified));
+        assert_eq!(outcomes[1], ("c.txt".to_string(), FileOutcome::Verified));
+        assert_eq!(outcomes[2].0, "sub/b.txt");
+        assert!(matches!(&outcomes[2].1, FileOutcome::Failed(m) if m.contains("size wrong")));
+
+        assert_eq!(fs::read_to_string(dst.join("a.txt")).unwrap(), "aaa");
+        assert_eq!(fs::read_to_string(dst.join("c.txt")).unwrap(), "ccc");
+        assert!(!dst.join("MANIFEST.TXT").exists());
+        assert!(!dst.join(ARCHIVE_FILE_NAME).exists());
+    }
+}
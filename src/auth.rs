@@ -0,0 +1,234 @@
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use tracing::warn;
+
+/// Per-repository credentials used to access restricted data or to get higher rate limits.
+///
+/// Each backend picks the token of its repository and sends it with the header scheme the
+/// repository expects, see [`Dataset::auth_headers`](crate::Dataset::auth_headers).
+///
+/// # Examples
+///
+/// ```
+/// use datahugger::AuthConfig;
+///
+/// let auth = AuthConfig::new().zenodo("my-access-token").dataverse("my-api-key");
+/// assert!(!auth.is_empty());
+/// ```
+#[derive(Clone, Default)]
+pub struct AuthConfig {
+    pub(crate) github: Option<String>,
+    pub(crate) dryad: Option<String>,
+    pub(crate) zenodo: Option<String>,
+    pub(crate) dataverse: Option<String>,
+    pub(crate) huggingface: Option<String>,
+    pub(crate) osf: Option<String>,
+    pub(crate) softwareheritage: Option<String>,
+}
+
+// tokens are never printed, only whether they are set.
+impl std::fmt::Debug for AuthConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let redact = |token: &Option<String>| token.as_ref().map(|_| "***");
+        f.debug_struct("AuthConfig")
+            .field("github", &redact(&self.github))
+            .field("dryad", &redact(&self.dryad))
+            .field("zenodo", &redact(&self.zenodo))
+            .field("dataverse", &redact(&self.dataverse))
+            .field("huggingface", &redact(&self.huggingface))
+            .field("osf", &redact(&self.osf))
+            .field("softwareheritage", &redact(&self.softwareheritage))
+            .finish()
+    }
+}
+
+fn env_token(names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .find_map(|name| std::env::var(name).ok())
+        .filter(|token| !token.is_empty())
+}
+
+impl AuthConfig {
+    /// Creates a config without any credential.
+    #[must_use]
+    pub fn new() -> Self {
+        AuthConfig::default()
+    }
+
+    /// Reads the credentials from the environment:
+    ///
+    /// | Repository   | Variable                                  |
+    /// |--------------|-------------------------------------------|
+    /// | GitHub       | `GITHUB_TOKEN`                            |
+    /// | Dryad        | `DRYAD_API_TOKEN` (or `DRYAD_API_KEY`)    |
+    /// | Zenodo       | `ZENODO_ACCESS_TOKEN` (or `ZENODO_TOKEN`) |
+    /// | Dataverse    | `DATAVERSE_API_KEY`                       |
+    /// | Hugging Face | `HF_TOKEN`                                |
+    /// | OSF          | `OSF_TOKEN`                               |
+    /// | Software Heritage | `SWH_TOKEN`                          |
+    #[must_use]
+    pub fn from_env() -> Self {
+        AuthConfig {
+            github: env_token(&["GITHUB_TOKEN"]),
+            dryad: env_token(&["DRYAD_API_TOKEN", "DRYAD_API_KEY"]),
+            zenodo: env_token(&["ZENODO_ACCESS_TOKEN", "ZENODO_TOKEN"]),
+            dataverse: env_token(&["DATAVERSE_API_KEY"]),
+            huggingface: env_token(&["HF_TOKEN"]),
+            osf: env_token(&["OSF_TOKEN"]),
+            softwareheritage: env_token(&["SWH_TOKEN"]),
+        }
+    }
+
+    /// Returns `true` if no credential is set.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.github.is_none()
+            && self.dryad.is_none()
+            && self.zenodo.is_none()
+            && self.dataverse.is_none()
+            && self.huggingface.is_none()
+            && self.osf.is_none()
+            && self.softwareheritage.is_none()
+    }
+
+    /// Sets the GitHub personal access token.
+    #[must_use]
+    pub fn github(mut self, token: impl Into<String>) -> Self {
+        self.github = Some(token.into());
+        self
+    }
+
+    /// Sets the Dryad API bearer token.
+    #[must_use]
+    pub fn dryad(mut self, token: impl Into<String>) -> Self {
+        self.dryad = Some(token.into());
+        self
+    }
+
+    /// Sets the Zenodo personal access token.
+    #[must_use]
+    pub fn zenodo(mut self, token: impl Into<String>) -> Self {
+        self.zenodo = Some(token.into());
+        self
+    }
+
+    /// Sets the Dataverse API key, sent to every Dataverse installation.
+    #[must_use]
+    pub fn dataverse(mut self, token: impl Into<String>) -> Self {
+        self.dataverse = Some(token.into());
+        self
+    }
+
+    /// Sets the Hugging Face user access token.
+    #[must_use]
+    pub fn huggingface(mut self, token: impl Into<String>) -> Self {
+        self.huggingface = Some(token.into());
+        self
+    }
+
+    /// Sets the OSF personal access token.
+    #[must_use]
+    pub fn osf(mut self, token: impl Into<String>) -> Self {
+        self.osf = Some(token.into());
+        self
+    }
+
+    /// Sets the Software Heritage API bearer token.
+    #[must_use]
+    pub fn softwareheritage(mut self, token: impl Into<String>) -> Self {
+        self.softwareheritage = Some(token.into());
+        self
+    }
+
+    /// Sets the token of `repository` by its name, `github`, `dryad`, `zenodo`,
+    /// `dataverse`, `huggingface`, `osf` or `softwareheritage`, `None` for another name.
+    #[must_use]
+    pub fn token(self, repository: &str, token: impl Into<String>) -> Option<Self> {
+        let auth = match repository {
+            "github" => self.github(token),
+            "dryad" => self.dryad(token),
+            "zenodo" => self.zenodo(token),
+            "dataverse" => self.dataverse(token),
+            "huggingface" => self.huggingface(token),
+            "osf" => self.osf(token),
+            "softwareheritage" => self.softwareheritage(token),
+            _ => return None,
+        };
+        Some(auth)
+    }
+
+    /// Fills the credentials not set with those of `fallback`, e.g. the environment over
+    /// the tokens of a config file.
+    #[must_use]
+    pub fn with_fallback(self, fallback: AuthConfig) -> Self {
+        AuthConfig {
+            github: self.github.or(fallback.github),
+            dryad: self.dryad.or(fallback.dryad),
+            zenodo: self.zenodo.or(fallback.zenodo),
+            dataverse: self.dataverse.or(fallback.dataverse),
+            huggingface: self.huggingface.or(fallback.huggingface),
+            osf: self.osf.or(fallback.osf),
+            softwareheritage: self.softwareheritage.or(fallback.softwareheritage),
+        }
+    }
+}
+
+/// Headers with `value` set as the sensitive value of header `name`, empty if `value` is not
+/// a valid header value.
+pub(crate) fn auth_header(name: HeaderName, value: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    match HeaderValue::from_str(value) {
+        Ok(mut value) => {
+            value.set_sensitive(true);
+            headers.insert(name, value);
+        }
+        Err(err) => warn!("ignore credential for header '{name}', {err}"),
+    }
+    headers
+}
+
+/// `Authorization: Bearer <token>` headers, empty without token.
+pub(crate) fn bearer(token: Option<&str>) -> HeaderMap {
+    token.map_or_else(HeaderMap::new, |token| {
+        auth_header(AUTHORIZATION, &format!("Bearer {token}"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auth_config_debug_redacts_tokens() {
+        let auth = AuthConfig::new().zenodo("secret");
+        let debug = format!("{auth:?}");
+        assert!(!debug.contains("secret"));
+        assert!(debug.contains("zenodo: Some(\"***\")"));
+        assert!(debug.contains("github: None"));
+    }
+
+    #[test]
+    fn test_auth_config_with_fallback() {
+        let fallback = AuthConfig::new()
+            .token("zenodo", "file-zenodo")
+            .unwrap()
+            .token("osf", "file-osf")
+            .unwrap();
+        let auth = AuthConfig::new()
+            .zenodo("env-zenodo")
+            .with_fallback(fallback);
+        assert_eq!(auth.zenodo.as_deref(), Some("env-zenodo"));
+        assert_eq!(auth.osf.as_deref(), Some("file-osf"));
+        assert!(auth.github.is_none());
+        assert!(AuthConfig::new().token("figshare", "x").is_none());
+    }
+
+    #[test]
+    fn test_bearer() {
+        let headers = bearer(Some("abc"));
+        assert_eq!(headers[AUTHORIZATION], "Bearer abc");
+        assert!(headers[AUTHORIZATION].is_sensitive());
+        assert!(bearer(None).is_empty());
+        assert!(bearer(Some("bad\ntoken")).is_empty());
+    }
+}
@@ -0,0 +1,207 @@
+use futures_util::{stream, StreamExt};
+use reqwest::Client;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+use crate::{
+    crawler::ProgressManager, resolve_with_options, AuthConfig, Dataset, DownloadExt,
+    DownloadOptions, DownloadReport, RepositoryConfig,
+};
+
+/// Outcome of one dataset of a [`download_many`] batch.
+#[derive(Debug)]
+pub struct BatchEntry {
+    /// URL or DOI the dataset was given as.
+    pub input: String,
+    /// Directory the files of the dataset are downloaded to, `None` if it failed to
+    /// resolve.
+    pub dir: Option<PathBuf>,
+    /// Report of the download, or the message of the error that stopped it.
+    pub result: Result<DownloadReport, String>,
+}
+
+/// Consolidated report of a [`download_many`] batch, in the order of the inputs.
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    pub datasets: Vec<BatchEntry>,
+}
+
+impl BatchReport {
+    /// Returns `true` if every dataset was downloaded without failed file.
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        self.datasets
+            .iter()
+            .all(|entry| entry.result.as_ref().is_ok_and(DownloadReport::is_success))
+    }
+}
+
+impl std::fmt::Display for BatchReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let succeeded = self
+            .datasets
+            .iter()
+            .filter(|entry| entry.result.as_ref().is_ok_and(DownloadReport::is_success))
+            .count();
+        writeln!(
+            f,
+            "downloaded {succeeded} of {} datasets",
+            self.datasets.len()
+        )?;
+        for entry in &self.datasets {
+            match (&entry.result, &entry.dir) {
+                (Ok(report), Some(dir)) => {
+                    write!(f, "{} -> {}: {report}", entry.input, dir.display())?;
+                }
+                (Ok(report), None) => write!(f, "{}: {report}", entry.input)?,
+                (Err(message), _) => writeln!(f, "{}: failed: {message}", entry.input)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Resolves each URL or DOI of `inputs` with `auth` and downloads it into its own
+/// subdirectory of `dst_dir`, named after its repository and record id, e.g.
+/// `zenodo-17867222`, or by [`DownloadOptions::dir_template`].
+///
+/// Up to [`DownloadOptions::dataset_limit`] datasets are downloaded at the same time, each
+/// with the `options` of a single download. The datasets are resolved with
+/// [`DownloadOptions::client_options`] and [`DownloadOptions::repositories`]. `client_for` builds the client of a dataset,
+/// e.g. to send the credentials of its repository with
+/// [`Dataset::auth_headers`](crate::Dataset::auth_headers).
+///
+/// A dataset failing to resolve or download does not stop the others, its error is
+/// recorded in the returned [`BatchReport`]. Once [`DownloadOptions::cancel`] is cancelled
+/// no other dataset is started.
+///
+/// # Examples
+///
+/// ```no_run
+/// # async fn example() {
+/// use datahugger::{client_builder, download_many, AuthConfig, DownloadOptions};
+/// use indicatif::MultiProgress;
+///
+/// let inputs = ["https://zenodo.org/records/17867222", "doi:10.7910/DVN/KBHLOD"];
+/// let report = download_many(
+///     &inputs,
+///     &AuthConfig::from_env(),
+///     "./mirror",
+///     MultiProgress::new(),
+///     &DownloadOptions::new().dataset_limit(4),
+///     |ds| client_builder().default_headers(ds.auth_headers()).build(),
+/// )
+/// .await;
+/// assert!(report.is_success(), "{report}");
+/// # }
+/// ```
+pub async fn download_many<S, F>(
+    inputs: &[S],
+    auth: &AuthConfig,
+    dst_dir: impl AsRef<Path>,
+    mp: impl ProgressManager,
+    options: &DownloadOptions,
+    client_for: F,
+) -> BatchReport
+where
+    S: AsRef<str> + Sync,
+    F: Fn(&Dataset) -> Result<Client, reqwest::Error> + Sync,
+{
+    let dst_dir = dst_dir.as_ref();
+    let limit = if options.dataset_limit == 0 {
+        usize::MAX
+    } else {
+        options.dataset_limit
+    };
+    let repositories = &options
+        .repositories
+        .clone()
+        .unwrap_or_else(RepositoryConfig::load);
+    let datasets = stream::iter(inputs.iter().map(|input| {
+        let input = input.as_ref().trim().to_string();
+        let mp = mp.clone();
+        let client_for = &client_for;
+        async move {
+            if options.cancel.is_cancelled() {
+                return BatchEntry {
+                    input,
+                    dir: None,
+                    result: Err("cancelled".to_string()),
+                };
+            }
+            let ds = match resolve_with_options(
+                &input,
+                auth.clone(),
+                repositories,
+                &options.client_options,
+            )
+            .await
+            {
+                Ok(ds) => ds,
+                Err(err) => {
+                    warn!("cannot resolve '{input}': {err:?}");
+                    return BatchEntry {
+                        input,
+                        dir: None,
+                        result: Err(format!("{err}")),
+                    };
+                }
+            };
+            let dir = dst_dir.join(ds.destination(&options.dir_template));
+            let result = match client_for(&ds) {
+                Ok(client) => ds
+                    .download_with_validation(&client, &dir, mp, options)
+                    .await
+                    .map_err(|err| {
+                        warn!("download of '{input}' failed: {err:?}");
+                        format!("{err}")
+                    }),
+                Err(err) => Err(format!("cannot build the http client: {err}")),
+            };
+            BatchEntry {
+                input,
+                dir: Some(dir),
+                result,
+            }
+        }
+    }))
+    .buffered(limit)
+    .collect()
+    .await;
+    BatchReport { datasets }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{datasets::ZenodoJsonSrcDataset, testutil::TempDir};
+
+    #[test]
+    fn test_record_dir() {
+        let ds = Dataset::new(ZenodoJsonSrcDataset::new("17867222", String::new()));
+        let options = DownloadOptions::new();
+        assert_eq!(
+            ds.destination(&options.dir_template),
+            Path::new(&format!("{}-17867222", ds.backend_name()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_many_unresolvable() {
+        let dst = TempDir::new("download-many");
+        let report = download_many(
+            &["not a dataset", "http://127.0.0.1:9/nothing"],
+            &AuthConfig::default(),
+            &dst,
+            indicatif::MultiProgress::with_draw_target(indicatif::ProgressDrawTarget::hidden()),
+            &DownloadOptions::new(),
+            |_| Client::builder().build(),
+        )
+        .await;
+        assert_eq!(report.datasets.len(), 2);
+        assert_eq!(report.datasets[0].input, "not a dataset");
+        assert!(report.datasets.iter().all(|entry| entry.result.is_err()));
+        assert!(!report.is_success());
+        assert!(report.to_string().starts_with("downloaded 0 of 2 datasets"));
+    }
+}
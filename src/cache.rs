@@ -0,0 +1,47 @@
+use reqwest::header::{HeaderMap, HeaderValue, CACHE_CONTROL};
+use std::time::Duration;
+
+/// How HTTP caches between datahugger and the repository, e.g. a caching proxy in front of
+/// scheduled mirror jobs, may answer the listing and download requests.
+///
+/// The policy is sent as `Cache-Control` request header, set [`CachePolicy::headers`] as
+/// default headers of the client.
+///
+/// # Examples
+///
+/// ```
+/// use datahugger::CachePolicy;
+/// use std::time::Duration;
+///
+/// let headers = CachePolicy::MaxAge(Duration::from_secs(3600)).headers();
+/// assert_eq!(headers["cache-control"], "max-age=3600");
+/// let client = reqwest::ClientBuilder::new().default_headers(headers).build().unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CachePolicy {
+    /// No `Cache-Control` header, caches apply their own freshness rules.
+    #[default]
+    Default,
+    /// Caches must revalidate with the repository before answering.
+    NoCache,
+    /// Caches may answer with responses not older than the given duration.
+    MaxAge(Duration),
+}
+
+impl CachePolicy {
+    /// Returns the request headers carrying the policy, empty for [`CachePolicy::Default`].
+    #[must_use]
+    pub fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let value = match self {
+            CachePolicy::Default => return headers,
+            CachePolicy::NoCache => HeaderValue::from_static("no-cache"),
+            CachePolicy::MaxAge(max_age) => {
+                // Safe to unwrap: digits are always a valid header value
+                HeaderValue::from_str(&format!("max-age={}", max_age.as_secs())).unwrap()
+            }
+        };
+        headers.insert(CACHE_CONTROL, value);
+        headers
+    }
+}
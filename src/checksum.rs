@@ -0,0 +1,177 @@
+use exn::Exn;
+use std::{
+    collections::HashMap,
+    sync::{Arc, OnceLock, RwLock},
+};
+
+use crate::{error::ErrorStatus, repo::normalize_algo, Checksum, ChecksumError};
+
+/// A checksum algorithm datahugger does not compute itself, e.g. SHA3 or xxHash as published
+/// by some institutional stores, see [`register_checksum_algorithm`].
+///
+/// # Examples
+///
+/// ```
+/// use datahugger::{register_checksum_algorithm, Checksum, ChecksumAlgorithm, DigestHasher};
+///
+/// /// Sum of the bytes modulo 256, as a 2 hex digits digest.
+/// struct Sum8;
+///
+/// struct Sum8Hasher(u8);
+///
+/// impl DigestHasher for Sum8Hasher {
+///     fn update(&mut self, data: &[u8]) {
+///         self.0 = data.iter().fold(self.0, |sum, b| sum.wrapping_add(*b));
+///     }
+///
+///     fn finalize(self: Box<Self>) -> Vec<u8> {
+///         vec![self.0]
+///     }
+/// }
+///
+/// impl ChecksumAlgorithm for Sum8 {
+///     fn name(&self) -> &'static str {
+///         "sum8"
+///     }
+///
+///     fn hex_len(&self) -> usize {
+///         2
+///     }
+///
+///     fn hasher(&self) -> Box<dyn DigestHasher> {
+///         Box::new(Sum8Hasher(0))
+///     }
+/// }
+///
+/// register_checksum_algorithm(Sum8).unwrap();
+/// let checksum = Checksum::parse("SUM-8", "0f").unwrap();
+/// assert_eq!(checksum.algorithm(), "sum8");
+/// ```
+pub trait ChecksumAlgorithm: Send + Sync {
+    /// Name of the algorithm as published by repositories, matched case-insensitively and
+    /// with or without dash, e.g. `sha3-256`.
+    fn name(&self) -> &'static str;
+
+    /// Length of the hex encoded digest, e.g. 64 for SHA3-256.
+    fn hex_len(&self) -> usize;
+
+    /// Returns a hasher computing a digest from scratch.
+    fn hasher(&self) -> Box<dyn DigestHasher>;
+}
+
+/// Incremental computation of a digest of a [`ChecksumAlgorithm`].
+pub trait DigestHasher: Send {
+    fn update(&mut self, data: &[u8]);
+
+    /// Returns the digest, whose hex encoding is compared to the published one.
+    fn finalize(self: Box<Self>) -> Vec<u8>;
+}
+
+type Registry = RwLock<HashMap<String, Arc<dyn ChecksumAlgorithm>>>;
+
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(Registry::default)
+}
+
+/// Registers `algorithm`, so that [`Checksum::parse`] accepts its digests and downloaded
+/// files are validated against them, replacing an algorithm registered under the same name.
+///
+/// Registration is process wide, call it once before resolving datasets.
+///
+/// # Errors
+/// Returns an error if the name is the one of a built-in algorithm, e.g. `sha256`.
+pub fn register_checksum_algorithm(
+    algorithm: impl ChecksumAlgorithm + 'static,
+) -> Result<(), Exn<ChecksumError>> {
+    let name = normalize_algo(algorithm.name());
+    if Checksum::is_builtin(&name) {
+        exn::bail!(ChecksumError {
+            message: format!("'{}' is a built-in checksum algorithm", algorithm.name()),
+            status: ErrorStatus::Permanent,
+        });
+    }
+    registry()
+        .write()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .insert(name, Arc::new(algorithm));
+    Ok(())
+}
+
+/// Returns the algorithm registered under the normalized name `algo`.
+pub(crate) fn registered_algorithm(algo: &str) -> Option<Arc<dyn ChecksumAlgorithm>> {
+    registry()
+        .read()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .get(algo)
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // xor of all bytes
+    struct Xor8;
+
+    struct Xor8Hasher(u8);
+
+    impl DigestHasher for Xor8Hasher {
+        fn update(&mut self, data: &[u8]) {
+            self.0 = data.iter().fold(self.0, |acc, b| acc ^ b);
+        }
+
+        fn finalize(self: Box<Self>) -> Vec<u8> {
+            vec![self.0]
+        }
+    }
+
+    impl ChecksumAlgorithm for Xor8 {
+        fn name(&self) -> &'static str {
+            "xor-8"
+        }
+
+        fn hex_len(&self) -> usize {
+            2
+        }
+
+        fn hasher(&self) -> Box<dyn DigestHasher> {
+            Box::new(Xor8Hasher(0))
+        }
+    }
+
+    struct FakeSha256;
+
+    impl ChecksumAlgorithm for FakeSha256 {
+        fn name(&self) -> &'static str {
+            "SHA-256"
+        }
+
+        fn hex_len(&self) -> usize {
+            2
+        }
+
+        fn hasher(&self) -> Box<dyn DigestHasher> {
+            Box::new(Xor8Hasher(0))
+        }
+    }
+
+    #[test]
+    fn test_register_checksum_algorithm() {
+        assert!(Checksum::parse("xor8", "03").is_err());
+        register_checksum_algorithm(Xor8).unwrap();
+        assert!(Checksum::is_supported("XOR8"));
+
+        let checksum = Checksum::parse("XOR8", "xor8:0C").unwrap();
+        assert_eq!(checksum.algorithm(), "xor-8");
+        assert_eq!(checksum.value(), "0c");
+        assert!(Checksum::parse("xor8", "0c0c").is_err());
+
+        let mut hasher = checksum.hasher(None).unwrap();
+        hasher.update(&[0x01, 0x02]);
+        hasher.update(&[0x0f]);
+        assert_eq!(hex::encode(hasher.finalize()), checksum.value());
+
+        assert!(register_checksum_algorithm(FakeSha256).is_err());
+    }
+}
@@ -0,0 +1,320 @@
+use bytes::Bytes;
+use exn::{Exn, ResultExt};
+use futures_core::stream::BoxStream;
+use futures_util::{stream, FutureExt, StreamExt};
+use reqwest::{
+    header::{HeaderMap, CONTENT_RANGE},
+    Client, Response, StatusCode,
+};
+use tracing::warn;
+use url::Url;
+
+use crate::{crawler::CrawlerError, error::ErrorStatus, zipindex::read_range, ClientOptions};
+
+/// Files of at least this size in bytes are downloaded in parallel chunks when
+/// [`DownloadOptions::connections_per_file`](crate::DownloadOptions::connections_per_file)
+/// is above 1.
+pub const DEFAULT_CHUNK_THRESHOLD: u64 = 64 * 1024 * 1024;
+
+/// Bytes requested per range, at most `connections` chunks are held in memory per file.
+pub(crate) const CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+
+/// Times a chunk is requested before the download of its file fails.
+const CHUNK_ATTEMPTS: u32 = 3;
+
+/// Range of the first chunk from `start`, a multiple of [`CHUNK_SIZE`], requested with the
+/// first GET of a file downloaded in chunks.
+pub(crate) fn first_range(start: u64) -> String {
+    format!("bytes={start}-{}", start + CHUNK_SIZE - 1)
+}
+
+/// Offset at which a download in chunks resumes a part file of `len` bytes, the start of
+/// the chunk it ends in, whose bytes are downloaded again.
+pub(crate) fn chunk_start(len: u64) -> u64 {
+    len - len % CHUNK_SIZE
+}
+
+/// Total length of the resource answered to a range request starting at `start`, `None`
+/// unless the response is a partial content, e.g. when the server ignored the range.
+pub(crate) fn ranged_total(resp: &Response, start: u64) -> Option<u64> {
+    if resp.status() != StatusCode::PARTIAL_CONTENT {
+        return None;
+    }
+    content_range(resp.headers())
+        .filter(|&(first, _)| first == start)
+        .and_then(|(_, total)| total)
+}
+
+/// Range of the bytes of a resource after the first `offset`, requested to resume a
+/// partial download.
+pub(crate) fn resume_range(offset: u64) -> String {
+    format!("bytes={offset}-")
+}
+
+/// Returns `true` if `resp` answers the bytes of the resource from `offset` on, see
+/// [`resume_range`].
+pub(crate) fn is_resumed(resp: &Response, offset: u64) -> bool {
+    resp.status() == StatusCode::PARTIAL_CONTENT
+        && content_range(resp.headers()).is_some_and(|(start, _)| start == offset)
+}
+
+/// Start and total length of a `Content-Range`, e.g. `bytes 0-99/1000`, the total is `None`
+/// when unknown (`bytes 0-99/*`).
+fn content_range(headers: &HeaderMap) -> Option<(u64, Option<u64>)> {
+    let value = headers.get(CONTENT_RANGE)?.to_str().ok()?;
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, _) = range.split_once('-')?;
+    Some((start.parse().ok()?, total.parse().ok()))
+}
+
+/// Streams the bytes of `url` from `start` to its `total` length in [`CHUNK_SIZE`] ranges
+/// requested over up to `connections` concurrent requests, `first` being the response to
+/// [`first_range`] from `start`.
+///
+/// Chunks are yielded in order, so they can be written and hashed as a single stream. A
+/// chunk whose request fails or answers a wrong length is requested again, up to
+/// [`CHUNK_ATTEMPTS`] times.
+pub(crate) fn ranged_stream(
+    client: &Client,
+    client_options: &ClientOptions,
+    url: &Url,
+    first: Response,
+    start: u64,
+    total: u64,
+    connections: usize,
+) -> BoxStream<'static, Result<Bytes, Exn<CrawlerError>>> {
+    let first_end = (start + CHUNK_SIZE).min(total) - 1;
+    let (client, client_options, url) = (client.clone(), client_options.clone(), url.clone());
+    let first = {
+        let (client, client_options, url) = (client.clone(), client_options.clone(), url.clone());
+        async move {
+            let bytes = first.bytes().await.or_raise(|| CrawlerError {
+                message: format!("fail to read the chunk at offset {start} of {url}"),
+                status: ErrorStatus::Temporary,
+            });
+            match bytes.and_then(|bytes| check_len(bytes, first_end - start + 1, &url, start)) {
+                Ok(bytes) => Ok(bytes),
+                Err(err) => {
+                    warn!("requesting the chunk at offset {start} again: {err:?}");
+                    let attempts = CHUNK_ATTEMPTS - 1;
+                    read_chunk(&client, &client_options, &url, start, first_end, attempts).await
+                }
+            }
+        }
+        .boxed()
+    };
+    let rest = std::iter::successors(Some(first_end + 1), |start| Some(start + CHUNK_SIZE))
+        .take_while(move |&start| start < total)
+        .map(move |start| {
+            let (client, client_options, url) =
+                (client.clone(), client_options.clone(), url.clone());
+            async move {
+                let end = (start + CHUNK_SIZE).min(total) - 1;
+                read_chunk(&client, &client_options, &url, start, end, CHUNK_ATTEMPTS).await
+            }
+            .boxed()
+        });
+    stream::iter(std::iter::once(first).chain(rest))
+        .buffered(connections.max(1))
+        .boxed()
+}
+
+/// Reads the bytes `start..=end` of `url`, requested up to `attempts` times while the
+/// request fails temporarily or answers a wrong length.
+async fn read_chunk(
+    client: &Client,
+    client_options: &ClientOptions,
+    url: &Url,
+    start: u64,
+    end: u64,
+    attempts: u32,
+) -> Result<Bytes, Exn<CrawlerError>> {
+    let range = format!("{start}-{end}");
+    let mut attempt = 1;
+    loop {
+        let res = read_range(client, client_options, url, &range)
+            .await
+            .and_then(|(bytes, _)| check_len(bytes, end - start + 1, url, start));
+        match res {
+            Err(err) if attempt < attempts && matches!(err.status, ErrorStatus::Temporary) => {
+                warn!("requesting the chunk at offset {start} again: {err:?}");
+                attempt += 1;
+            }
+            res => return res,
+        }
+    }
+}
+
+fn check_len(bytes: Bytes, len: u64, url: &Url, start: u64) -> Result<Bytes, Exn<CrawlerError>> {
+    if bytes.len() as u64 != len {
+        exn::bail!(CrawlerError {
+            message: format!(
+                "{url} answered {} bytes for the {len} bytes at offset {start}",
+                bytes.len()
+            ),
+            status: ErrorStatus::Temporary,
+        });
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::TryStreamExt;
+    use wiremock::{
+        matchers::{header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[test]
+    fn test_content_range() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(content_range(&headers), None);
+        headers.insert(CONTENT_RANGE, "bytes 0-99/1000".parse().unwrap());
+        assert_eq!(content_range(&headers), Some((0, Some(1000))));
+        headers.insert(CONTENT_RANGE, "bytes 100-199/*".parse().unwrap());
+        assert_eq!(content_range(&headers), Some((100, None)));
+    }
+
+    #[tokio::test]
+    async fn test_ranged_stream() {
+        let total = 2 * CHUNK_SIZE + 10;
+        let content = (0..total).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let mock_server = MockServer::start().await;
+        let ranges = [
+            (0, CHUNK_SIZE),
+            (CHUNK_SIZE, 2 * CHUNK_SIZE),
+            (2 * CHUNK_SIZE, total),
+        ];
+        for (start, end) in ranges {
+            Mock::given(method("GET"))
+                .and(path("/large.bin"))
+                .and(header("range", format!("bytes={start}-{}", end - 1)))
+                .respond_with(
+                    ResponseTemplate::new(206)
+                        .insert_header(
+                            "content-range",
+                            format!("bytes {start}-{}/{total}", end - 1),
+                        )
+                        .set_body_bytes(&content[start as usize..end as usize]),
+                )
+                .expect(1)
+                .mount(&mock_server)
+                .await;
+        }
+
+        let client = Client::new();
+        let url = Url::parse(&format!("{}/large.bin", mock_server.uri())).unwrap();
+        let first = client
+            .get(url.clone())
+            .header("range", first_range(0))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(ranged_total(&first, 0), Some(total));
+        let chunks = ranged_stream(&client, &ClientOptions::default(), &url, first, 0, total, 3)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(chunks.concat(), content);
+    }
+
+    #[tokio::test]
+    async fn test_ranged_stream_short_chunk() {
+        let total = CHUNK_SIZE + 10;
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/large.bin"))
+            .and(header("range", format!("bytes={CHUNK_SIZE}-{}", total - 1)))
+            .respond_with(
+                ResponseTemplate::new(206)
+                    .insert_header(
+                        "content-range",
+                        format!("bytes {CHUNK_SIZE}-{}/{total}", total - 1),
+                    )
+                    .set_body_bytes(vec![0; 5]),
+            )
+            .expect(u64::from(CHUNK_ATTEMPTS))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/large.bin"))
+            .respond_with(
+                ResponseTemplate::new(206)
+                    .insert_header(
+                        "content-range",
+                        format!("bytes 0-{}/{total}", CHUNK_SIZE - 1),
+                    )
+                    .set_body_bytes(vec![0; CHUNK_SIZE as usize]),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let client = Client::new();
+        let url = Url::parse(&format!("{}/large.bin", mock_server.uri())).unwrap();
+        let first = client.get(url.clone()).send().await.unwrap();
+        let err = ranged_stream(&client, &ClientOptions::default(), &url, first, 0, total, 2)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("answered 5 bytes"), "{err:?}");
+    }
+
+    #[tokio::test]
+    async fn test_ranged_stream_resumed_retry() {
+        let total = 2 * CHUNK_SIZE + 10;
+        let content = (0..total).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let mock_server = MockServer::start().await;
+        let last = format!("bytes={}-{}", 2 * CHUNK_SIZE, total - 1);
+        // the last chunk is short once, then requested again
+        Mock::given(method("GET"))
+            .and(path("/large.bin"))
+            .and(header("range", last.as_str()))
+            .respond_with(ResponseTemplate::new(206).set_body_bytes(vec![0; 5]))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        for (start, end) in [(CHUNK_SIZE, 2 * CHUNK_SIZE), (2 * CHUNK_SIZE, total)] {
+            Mock::given(method("GET"))
+                .and(path("/large.bin"))
+                .and(header("range", format!("bytes={start}-{}", end - 1)))
+                .respond_with(
+                    ResponseTemplate::new(206)
+                        .insert_header(
+                            "content-range",
+                            format!("bytes {start}-{}/{total}", end - 1),
+                        )
+                        .set_body_bytes(&content[start as usize..end as usize]),
+                )
+                .expect(1)
+                .mount(&mock_server)
+                .await;
+        }
+
+        let client = Client::new();
+        let url = Url::parse(&format!("{}/large.bin", mock_server.uri())).unwrap();
+        // resumed after the first chunk
+        let first = client
+            .get(url.clone())
+            .header("range", first_range(CHUNK_SIZE))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(ranged_total(&first, CHUNK_SIZE), Some(total));
+        let chunks = ranged_stream(
+            &client,
+            &ClientOptions::default(),
+            &url,
+            first,
+            CHUNK_SIZE,
+            total,
+            2,
+        )
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+        assert_eq!(chunks.concat(), &content[CHUNK_SIZE as usize..]);
+    }
+}
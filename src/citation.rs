@@ -0,0 +1,304 @@
+use serde_json::{json, Value as JsonValue};
+use url::Url;
+
+use crate::{
+    metadata::{json_str, normalize_doi},
+    DatasetMetadata,
+};
+
+/// Format of a dataset citation, see [`Dataset::citation`](crate::Dataset::citation).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CitationStyle {
+    /// APA 7th edition reference, e.g.
+    /// `Doe, Jane. (2020). Survey data (Version 2) [Data set]. https://doi.org/10.1234/x`.
+    #[default]
+    Apa,
+    /// A BibTeX `@misc` entry.
+    Bibtex,
+    /// CSL-JSON, an array with a single `dataset` item, as read by Zotero and pandoc.
+    CslJson,
+}
+
+impl DatasetMetadata {
+    /// Formats the metadata as citation in `style`.
+    ///
+    /// The DOI is cited as `https://doi.org/` link, `url` is cited for datasets without DOI.
+    #[must_use]
+    pub fn citation(&self, style: CitationStyle, url: &Url) -> String {
+        let link = self
+            .doi
+            .as_ref()
+            .map_or_else(|| url.to_string(), |doi| format!("https://doi.org/{doi}"));
+        match style {
+            CitationStyle::Apa => self.apa(&link),
+            CitationStyle::Bibtex => self.bibtex(&link),
+            CitationStyle::CslJson => self.csl_json(&link),
+        }
+    }
+
+    fn year(&self) -> Option<&str> {
+        self.publication_date
+            .as_deref()
+            .and_then(|date| date.get(..4))
+            .filter(|year| year.chars().all(|c| c.is_ascii_digit()))
+    }
+
+    fn apa(&self, link: &str) -> String {
+        let mut citation = String::new();
+        match self.creators.as_slice() {
+            [] => {}
+            [creator] => citation.push_str(&format!("{creator}. ")),
+            [creators @ .., last] => {
+                citation.push_str(&format!("{}, & {last}. ", creators.join(", ")));
+            }
+        }
+        citation.push_str(&format!("({}). ", self.year().unwrap_or("n.d.")));
+        citation.push_str(self.title.as_deref().unwrap_or("Untitled dataset"));
+        if let Some(version) = &self.version {
+            citation.push_str(&format!(" (Version {version})"));
+        }
+        citation.push_str(&format!(" [Data set]. {link}"));
+        citation
+    }
+
+    fn bibtex(&self, link: &str) -> String {
+        // key like 'doe2020', from the family name of the first creator, "Doe, Jane" or "Jane Doe"
+        let name = self.creators.first().map_or("dataset", |creator| {
+            creator.split_once(',').map_or_else(
+                || creator.split_whitespace().last().unwrap_or(creator),
+                |(family, _)| family,
+            )
+        });
+        let mut key: String = name
+            .chars()
+            .filter(char::is_ascii_alphanumeric)
+            .collect::<String>()
+            .to_lowercase();
+        if key.is_empty() {
+            key = "dataset".to_string();
+        }
+        key.push_str(self.year().unwrap_or_default());
+
+        let mut fields = Vec::new();
+        if !self.creators.is_empty() {
+            fields.push(("author", bibtex_escape(&self.creators.join(" and "))));
+        }
+        if let Some(title) = &self.title {
+            // double braces keep the capitalization
+            fields.push(("title", format!("{{{}}}", bibtex_escape(title))));
+        }
+        if let Some(year) = self.year() {
+            fields.push(("year", year.to_string()));
+        }
+        if let Some(version) = &self.version {
+            fields.push(("version", bibtex_escape(version)));
+        }
+        if let Some(doi) = &self.doi {
+            fields.push(("doi", doi.clone()));
+        }
+        fields.push(("url", link.to_string()));
+        fields.push(("note", "Data set".to_string()));
+
+        // url and doi are read verbatim by BibTeX styles, they are not escaped
+        let fields: Vec<String> = fields
+            .into_iter()
+            .map(|(name, value)| format!("  {name} = {{{value}}}"))
+            .collect();
+        format!("@misc{{{key},\n{}\n}}", fields.join(",\n"))
+    }
+
+    fn csl_json(&self, link: &str) -> String {
+        let authors: Vec<_> = self
+            .creators
+            .iter()
+            .map(|creator| match creator.split_once(',') {
+                Some((family, given)) => json!({ "family": family.trim(), "given": given.trim() }),
+                None => json!({ "literal": creator }),
+            })
+            .collect();
+        let mut item = json!({
+            "id": self.doi.as_deref().unwrap_or(link),
+            "type": "dataset",
+            "title": self.title,
+            "author": authors,
+            "URL": link,
+        });
+        // '2020-03-02' or '2020-03-02T10:00:00Z' to [2020, 3, 2]
+        let date_parts: Vec<u32> = self
+            .publication_date
+            .as_deref()
+            .unwrap_or_default()
+            .split(['-', 'T'])
+            .take(3)
+            .map_while(|part| part.parse().ok())
+            .collect();
+        if !date_parts.is_empty() {
+            item["issued"] = json!({ "date-parts": [date_parts] });
+        }
+        for (key, value) in [
+            ("abstract", &self.description),
+            ("version", &self.version),
+            ("DOI", &self.doi),
+            ("license", &self.license),
+        ] {
+            if let Some(value) = value {
+                item[key] = json!(value);
+            }
+        }
+        // Safe to unwrap: a json value always serializes
+        serde_json::to_string_pretty(&json!([item])).unwrap()
+    }
+}
+
+impl DatasetMetadata {
+    /// Reads the metadata of a CSL-JSON item, e.g. as returned by DOI content negotiation,
+    /// or of the first item of a CSL-JSON array.
+    #[must_use]
+    pub fn from_csl_json(csl: &JsonValue) -> Self {
+        let item = csl.get(0).unwrap_or(csl);
+        let creators = item
+            .get("author")
+            .and_then(JsonValue::as_array)
+            .map(|authors| {
+                authors
+                    .iter()
+                    .filter_map(|author| {
+                        match (json_str(author, "/family"), json_str(author, "/given")) {
+                            (Some(family), Some(given)) => Some(format!("{family}, {given}")),
+                            (Some(family), None) => Some(family),
+                            _ => json_str(author, "/literal"),
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        // [[2020, 3, 2]] to '2020-03-02'
+        let publication_date = ["/issued/date-parts/0", "/published/date-parts/0"]
+            .iter()
+            .find_map(|pointer| item.pointer(pointer).and_then(JsonValue::as_array))
+            .map(|parts| {
+                parts
+                    .iter()
+                    .filter_map(|part| {
+                        part.as_u64()
+                            .or_else(|| part.as_str().and_then(|s| s.parse().ok()))
+                    })
+                    .enumerate()
+                    .map(|(i, part)| {
+                        if i == 0 {
+                            format!("{part:04}")
+                        } else {
+                            format!("{part:02}")
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("-")
+            })
+            .filter(|date| !date.is_empty());
+        DatasetMetadata {
+            title: json_str(item, "/title"),
+            description: json_str(item, "/abstract"),
+            creators,
+            license: json_str(item, "/license").or_else(|| json_str(item, "/copyright")),
+            publication_date,
+            version: json_str(item, "/version"),
+            doi: json_str(item, "/DOI").map(|doi| normalize_doi(&doi)),
+        }
+    }
+}
+
+/// Escapes the characters BibTeX (LaTeX) treats specially in field values.
+fn bibtex_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '&' | '%' | '$' | '#' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata() -> DatasetMetadata {
+        DatasetMetadata {
+            title: Some("Survey data & code".to_string()),
+            description: Some("Replication data.".to_string()),
+            creators: vec!["Doe, Jane".to_string(), "Roe, Richard".to_string()],
+            license: Some("CC0 1.0".to_string()),
+            publication_date: Some("2020-03-02".to_string()),
+            version: Some("2".to_string()),
+            doi: Some("10.7910/DVN/KBHLOD".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_citation() {
+        let url = Url::parse("https://dataverse.harvard.edu/dataset.xhtml").unwrap();
+        let metadata = metadata();
+
+        assert_eq!(
+            metadata.citation(CitationStyle::Apa, &url),
+            "Doe, Jane, & Roe, Richard. (2020). Survey data & code (Version 2) [Data set]. \
+             https://doi.org/10.7910/DVN/KBHLOD"
+        );
+        assert_eq!(
+            metadata.citation(CitationStyle::Bibtex, &url),
+            "@misc{doe2020,
+  author = {Doe, Jane and Roe, Richard},
+  title = {{Survey data \\& code}},
+  year = {2020},
+  version = {2},
+  doi = {10.7910/DVN/KBHLOD},
+  url = {https://doi.org/10.7910/DVN/KBHLOD},
+  note = {Data set}
+}"
+        );
+        let csl: serde_json::Value =
+            serde_json::from_str(&metadata.citation(CitationStyle::CslJson, &url)).unwrap();
+        assert_eq!(csl[0]["type"], "dataset");
+        assert_eq!(
+            csl[0]["author"][1],
+            json!({ "family": "Roe", "given": "Richard" })
+        );
+        assert_eq!(csl[0]["issued"], json!({ "date-parts": [[2020, 3, 2]] }));
+        assert_eq!(DatasetMetadata::from_csl_json(&csl), metadata);
+
+        let untitled = DatasetMetadata::default();
+        assert_eq!(
+            untitled.citation(CitationStyle::Apa, &url),
+            "(n.d.). Untitled dataset [Data set]. https://dataverse.harvard.edu/dataset.xhtml"
+        );
+    }
+
+    #[test]
+    fn test_from_csl_json() {
+        // as answered by DataCite
+        let csl = json!({
+            "type": "dataset",
+            "id": "https://doi.org/10.5281/zenodo.17867222",
+            "DOI": "10.5281/ZENODO.17867222",
+            "title": "Survey data",
+            "author": [
+                { "family": "Doe", "given": "Jane" },
+                { "literal": "EOSC Data Commons" }
+            ],
+            "issued": { "date-parts": [[2025, 12]] },
+            "copyright": "Creative Commons Attribution 4.0 International",
+            "publisher": "Zenodo"
+        });
+        let metadata = DatasetMetadata::from_csl_json(&csl);
+        assert_eq!(metadata.title.as_deref(), Some("Survey data"));
+        assert_eq!(metadata.creators, ["Doe, Jane", "EOSC Data Commons"]);
+        assert_eq!(metadata.publication_date.as_deref(), Some("2025-12"));
+        assert_eq!(
+            metadata.license.as_deref(),
+            Some("Creative Commons Attribution 4.0 International")
+        );
+        assert_eq!(metadata.doi.as_deref(), Some("10.5281/ZENODO.17867222"));
+        assert_eq!(metadata.version, None);
+    }
+}
@@ -0,0 +1,315 @@
+use futures_util::future::BoxFuture;
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    redirect::Policy,
+    Client, ClientBuilder, Proxy,
+};
+use std::{
+    cell::Cell,
+    future::Future,
+    sync::{Arc, OnceLock},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::{client_builder, redirect::DEFAULT_MAX_HOPS};
+
+/// User agent of the clients built from [`ClientOptions`] without
+/// [`ClientOptions::user_agent`].
+pub const DEFAULT_USER_AGENT: &str = concat!("datahugger-cli/", env!("CARGO_PKG_VERSION"));
+
+/// TLS implementation of a client, see [`ClientOptions::tls_backend`].
+///
+/// Only the backends of the enabled `native-tls` and `rustls` features exist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TlsBackend {
+    /// The platform library, OpenSSL on Linux.
+    #[cfg(feature = "native-tls")]
+    NativeTls,
+    /// Pure Rust TLS verifying certificates against the system store.
+    #[cfg(feature = "rustls")]
+    Rustls,
+}
+
+impl TlsBackend {
+    /// Returns the backend named `name`, `native-tls` or `rustls`, `None` if it is unknown
+    /// or not enabled in this build.
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            #[cfg(feature = "native-tls")]
+            "native-tls" => Some(TlsBackend::NativeTls),
+            #[cfg(feature = "rustls")]
+            "rustls" => Some(TlsBackend::Rustls),
+            _ => None,
+        }
+    }
+}
+
+/// Settings of the HTTP clients resolving and downloading datasets: timeouts, proxy,
+/// extra headers, user agent and TLS backend.
+///
+/// Unset options keep the defaults of [`client_builder`]: no timeout, the proxy of the
+/// `HTTPS_PROXY` / `HTTP_PROXY` environment variables and [`DEFAULT_USER_AGENT`].
+///
+/// # Examples
+///
+/// ```
+/// use datahugger::ClientOptions;
+/// use reqwest::header::{HeaderName, HeaderValue};
+/// use std::time::Duration;
+///
+/// let options = ClientOptions::new()
+///     .timeout(Duration::from_secs(300))
+///     .proxy(reqwest::Proxy::all("http://proxy.example.org:3128").unwrap())
+///     .header(
+///         HeaderName::from_static("from"),
+///         HeaderValue::from_static("data-team@example.org"),
+///     );
+/// let client = options.builder().build().unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ClientOptions {
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    proxy: Option<Proxy>,
+    headers: HeaderMap,
+    user_agent: Option<String>,
+    tls_backend: Option<TlsBackend>,
+    max_redirects: Option<usize>,
+    /// client following redirects to another host without credentials, built once for all
+    /// clones of the options, reset by the setters.
+    redirect_client: Arc<OnceLock<Client>>,
+}
+
+impl ClientOptions {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the timeout of a whole request, body included, e.g. a few minutes to give up on
+    /// stalled downloads of small files. Defaults to none.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self.changed()
+    }
+
+    /// Sets the timeout of connecting to a server. Defaults to none.
+    #[must_use]
+    pub fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = Some(connect_timeout);
+        self.changed()
+    }
+
+    /// Sends every request through `proxy` instead of the proxy of the environment.
+    #[must_use]
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self.changed()
+    }
+
+    /// Sends the header `name` with `value` with every request, replacing a previous value.
+    /// The credentials of a dataset, see [`Dataset::auth_headers`](crate::Dataset::auth_headers),
+    /// take precedence.
+    #[must_use]
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self.changed()
+    }
+
+    /// Sends `user_agent` instead of [`DEFAULT_USER_AGENT`].
+    #[must_use]
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self.changed()
+    }
+
+    /// Uses the TLS implementation `tls_backend` instead of the one [`client_builder`]
+    /// selects, for builds with both the `native-tls` and `rustls` features.
+    #[must_use]
+    pub fn tls_backend(mut self, tls_backend: TlsBackend) -> Self {
+        self.tls_backend = Some(tls_backend);
+        self.changed()
+    }
+
+    /// Sets the maximum number of redirects of a request redirected to another host, the hop
+    /// to that host included, as [`RedirectPolicy::max_hops`](crate::RedirectPolicy::max_hops)
+    /// does for the client carrying the credentials of a dataset. Defaults to 10.
+    #[must_use]
+    pub fn max_redirects(mut self, max_redirects: usize) -> Self {
+        self.max_redirects = Some(max_redirects);
+        self.changed()
+    }
+
+    fn changed(mut self) -> Self {
+        self.redirect_client = Arc::default();
+        self
+    }
+
+    /// Returns the client without the credentials of a dataset that follows the redirects to
+    /// another host, see [`RedirectPolicy`](crate::RedirectPolicy), with the options applied.
+    pub(crate) fn redirect_client(&self) -> Result<&Client, reqwest::Error> {
+        if let Some(client) = self.redirect_client.get() {
+            return Ok(client);
+        }
+        // the hop to the other host was taken by the client of the dataset
+        let max_redirects = self.max_redirects.unwrap_or(DEFAULT_MAX_HOPS);
+        let client = self
+            .builder()
+            .redirect(Policy::limited(max_redirects.saturating_sub(1)))
+            .build()?;
+        Ok(self.redirect_client.get_or_init(|| client))
+    }
+
+    /// Returns a client builder with the options applied, to add the auth headers of a
+    /// dataset or a redirect policy before building it.
+    pub fn builder(&self) -> ClientBuilder {
+        let mut builder = match self.tls_backend {
+            None => client_builder(),
+            #[cfg(feature = "native-tls")]
+            Some(TlsBackend::NativeTls) => ClientBuilder::new().tls_backend_native(),
+            #[cfg(feature = "rustls")]
+            Some(TlsBackend::Rustls) => ClientBuilder::new().tls_backend_rustls(),
+        };
+        builder = builder
+            .user_agent(self.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT))
+            .default_headers(self.headers.clone());
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(connect_timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(connect_timeout);
+        }
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(proxy.clone());
+        }
+        builder.connector_layer(ConnectTimingLayer)
+    }
+}
+
+tokio::task_local! {
+    /// Time spent opening connections for the request run by [`with_connect_time`].
+    static CONNECT_TIME: Cell<Option<Duration>>;
+}
+
+/// Runs `request`, returning its output and the time spent opening connections for it,
+/// TLS handshakes included. `None` if no connection was opened, e.g. one was reused from
+/// the pool, or the client was not built from [`ClientOptions`].
+pub(crate) async fn with_connect_time<F: Future>(request: F) -> (F::Output, Option<Duration>) {
+    CONNECT_TIME
+        .scope(Cell::new(None), async move {
+            let output = request.await;
+            (output, CONNECT_TIME.with(Cell::get))
+        })
+        .await
+}
+
+/// Connector layer adding the time of each connection to the request it is opened for,
+/// see [`with_connect_time`].
+#[derive(Debug, Clone, Copy)]
+struct ConnectTimingLayer;
+
+impl<S> Layer<S> for ConnectTimingLayer {
+    type Service = ConnectTiming<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConnectTiming(inner)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ConnectTiming<S>(S);
+
+impl<S, R> Service<R> for ConnectTiming<S>
+where
+    S: Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<S::Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.0.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: R) -> Self::Future {
+        let connect = self.0.call(req);
+        Box::pin(async move {
+            let start = Instant::now();
+            let res = connect.await;
+            // a connection finishing in the background after the request got another one
+            // is not recorded
+            let _ = CONNECT_TIME.try_with(|time| {
+                time.set(Some(time.get().unwrap_or_default() + start.elapsed()));
+            });
+            res
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{header, method},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_client_options_headers() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(header("user-agent", "mirror/1.0"))
+            .and(header("from", "data-team@example.org"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client = ClientOptions::new()
+            .user_agent("mirror/1.0")
+            .header(
+                HeaderName::from_static("from"),
+                HeaderValue::from_static("data-team@example.org"),
+            )
+            .timeout(Duration::from_secs(5))
+            .builder()
+            .build()
+            .unwrap();
+        let resp = client.get(mock_server.uri()).send().await.unwrap();
+        assert!(resp.status().is_success());
+    }
+
+    #[test]
+    fn test_tls_backend_from_name() {
+        #[cfg(feature = "native-tls")]
+        assert_eq!(
+            TlsBackend::from_name("native-tls"),
+            Some(TlsBackend::NativeTls)
+        );
+        assert_eq!(TlsBackend::from_name("gnutls"), None);
+    }
+
+    #[tokio::test]
+    async fn test_connect_time() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+
+        let client = ClientOptions::new().builder().build().unwrap();
+        let (resp, connect) = with_connect_time(client.get(mock_server.uri()).send()).await;
+        resp.unwrap().bytes().await.unwrap();
+        assert!(connect.is_some());
+        // the connection is reused
+        let (resp, connect) = with_connect_time(client.get(mock_server.uri()).send()).await;
+        resp.unwrap();
+        assert_eq!(connect, None);
+    }
+}
@@ -0,0 +1,210 @@
+use exn::{Exn, ResultExt};
+use serde_json::Value as JsonValue;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{error::ErrorStatus, repositories::user_config_dir, AuthConfig, RepositoryConfig};
+
+#[derive(Debug)]
+pub struct ConfigError {
+    pub message: String,
+    pub status: ErrorStatus,
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "config fail: {}", self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Settings of the `datahugger` command line, read from a TOML config file so scripts
+/// don't repeat credentials and options:
+///
+/// ```toml
+/// # defaults of the options of every subcommand
+/// timeout = 600
+/// header = ["From: data-team@example.org"]
+///
+/// # tokens, the environment variables of `AuthConfig::from_env` take precedence
+/// [tokens]
+/// zenodo = "my-access-token"
+///
+/// # additional installations, as in `repositories.toml`
+/// [repositories]
+/// dataverse = ["dataverse.example.edu"]
+///
+/// # defaults of the options of a subcommand, by long name
+/// [download]
+/// limit = 4
+/// to = "/data/{repository}/{record_id}"
+/// ```
+///
+/// # Examples
+///
+/// ```
+/// use datahugger::Config;
+///
+/// let config = Config::from_toml("[download]\nlimit = 4").unwrap();
+/// assert_eq!(config.defaults["download"]["limit"], ["4"]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// Credentials of the `[tokens]` table.
+    pub auth: AuthConfig,
+    /// Installations of the `[repositories]` table.
+    pub repositories: RepositoryConfig,
+    /// Default values of options by long name, e.g. `limit`, by subcommand, `""` for the
+    /// options of every subcommand. Lists hold the values of options given repeatedly.
+    pub defaults: BTreeMap<String, BTreeMap<String, Vec<String>>>,
+}
+
+/// Value of option `key` as given on the command line.
+fn option_values(key: &str, value: &JsonValue) -> Result<Vec<String>, Exn<ConfigError>> {
+    let scalar = |value: &JsonValue| match value {
+        JsonValue::String(value) => Some(value.clone()),
+        JsonValue::Number(value) => Some(value.to_string()),
+        JsonValue::Bool(value) => Some(value.to_string()),
+        _ => None,
+    };
+    let values = match value {
+        JsonValue::Array(items) => items.iter().map(scalar).collect(),
+        value => scalar(value).map(|value| vec![value]),
+    };
+    values.ok_or_else(|| {
+        ConfigError {
+            message: format!("'{key}' is not a string, number, boolean or list of them"),
+            status: ErrorStatus::Permanent,
+        }
+        .into()
+    })
+}
+
+impl Config {
+    /// Parses a TOML config, see [`Config`] for the format.
+    ///
+    /// # Errors
+    /// Returns an error if `content` is not valid TOML, a token is not a string or of an
+    /// unknown repository, or an option has no valid value.
+    pub fn from_toml(content: &str) -> Result<Self, Exn<ConfigError>> {
+        let table = content.parse::<toml::Table>().or_raise(|| ConfigError {
+            message: "invalid TOML".to_string(),
+            status: ErrorStatus::Permanent,
+        })?;
+        let invalid = |message: String| ConfigError {
+            message,
+            status: ErrorStatus::Permanent,
+        };
+        // Safe to unwrap: a TOML table always converts to json
+        let JsonValue::Object(json) = serde_json::to_value(table).unwrap() else {
+            unreachable!("a TOML table converts to a json object")
+        };
+        let mut config = Config::default();
+        for (key, value) in json {
+            match (key.as_str(), value) {
+                ("tokens", JsonValue::Object(tokens)) => {
+                    for (repository, token) in tokens {
+                        let token = token.as_str().ok_or_else(|| {
+                            invalid(format!("token of '{repository}' is not a string"))
+                        })?;
+                        config.auth = config.auth.token(&repository, token).ok_or_else(|| {
+                            invalid(format!("no token for unknown repository '{repository}'"))
+                        })?;
+                    }
+                }
+                ("repositories", repositories @ JsonValue::Object(_)) => {
+                    config.repositories = RepositoryConfig::from_json_value(&repositories)
+                        .or_raise(|| invalid("invalid [repositories]".to_string()))?;
+                }
+                (subcommand, JsonValue::Object(options)) => {
+                    let defaults = config.defaults.entry(subcommand.to_string()).or_default();
+                    for (name, value) in options {
+                        let values = option_values(&format!("{subcommand}.{name}"), &value)?;
+                        defaults.insert(name, values);
+                    }
+                }
+                (name, value) => {
+                    let values = option_values(name, &value)?;
+                    config
+                        .defaults
+                        .entry(String::new())
+                        .or_default()
+                        .insert(name.to_string(), values);
+                }
+            }
+        }
+        Ok(config)
+    }
+
+    /// Reads the TOML config file at `path`.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or parsed.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Exn<ConfigError>> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).or_raise(|| ConfigError {
+            message: format!("cannot read '{}'", path.display()),
+            status: ErrorStatus::Permanent,
+        })?;
+        Self::from_toml(&content).or_raise(|| ConfigError {
+            message: format!("cannot parse '{}'", path.display()),
+            status: ErrorStatus::Permanent,
+        })
+    }
+
+    /// Default location of the config file, `datahugger/config.toml` in the user config
+    /// directory (`$XDG_CONFIG_HOME`, else `~/.config`, or `%APPDATA%` on Windows).
+    #[must_use]
+    pub fn default_path() -> Option<PathBuf> {
+        Some(user_config_dir()?.join("config.toml"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_parse() {
+        let config = Config::from_toml(
+            r#"
+            timeout = 600
+            header = ["From: data-team@example.org", "X-Project: eosc"]
+
+            [tokens]
+            zenodo = "secret"
+
+            [repositories]
+            dataverse = ["dataverse.example.edu"]
+
+            [download]
+            limit = 4
+            to = "/data/{repository}/{record_id}"
+            dedup = true
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.auth.zenodo.as_deref(), Some("secret"));
+        assert!(config
+            .repositories
+            .dataverse
+            .contains("dataverse.example.edu"));
+        assert_eq!(config.defaults[""]["timeout"], ["600"]);
+        assert_eq!(config.defaults[""]["header"].len(), 2);
+        assert_eq!(config.defaults["download"]["limit"], ["4"]);
+        assert_eq!(config.defaults["download"]["dedup"], ["true"]);
+        assert_eq!(
+            config.defaults["download"]["to"],
+            ["/data/{repository}/{record_id}"]
+        );
+
+        assert!(Config::from_toml("[tokens]\nfigshare = \"x\"").is_err());
+        assert!(Config::from_toml("[tokens]\nzenodo = 1").is_err());
+        assert!(Config::from_toml("[download]\nlimit = { max = 4 }").is_err());
+        assert!(Config::from_toml("[repositories]\ndataverse = \"x\"").is_err());
+    }
+}
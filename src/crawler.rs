@@ -1,12 +1,18 @@
+use async_stream::try_stream;
 use exn::{Exn, ResultExt};
 use futures_core::stream::BoxStream;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use reqwest::Client;
 
-use async_stream::try_stream;
-use std::sync::Arc;
+use futures_util::{
+    future::{self, BoxFuture},
+    stream::{self, FuturesUnordered},
+    Stream, StreamExt,
+};
+use std::{collections::HashMap, future::Future, sync::Arc};
+use tracing::warn;
 
-use crate::{error::ErrorStatus, DatasetBackend, DirMeta, Entry};
+use crate::{error::ErrorStatus, repo::RepoError, DatasetBackend, DirMeta, Entry, FileMeta};
 
 #[derive(Debug)]
 pub struct CrawlerError {
@@ -36,8 +42,13 @@ impl ProgressManager for MultiProgress {
     }
 }
 
-/// # Panics
-/// indicatif template error
+/// Default number of directories listed concurrently by [`crawl`].
+pub const DEFAULT_CRAWL_FANOUT: usize = 8;
+
+type ListFuture = BoxFuture<'static, (DirMeta, Result<Vec<Entry>, Exn<RepoError>>)>;
+
+/// Recursively crawls `dir`, see [`crawl_with_fanout`], listing at most
+/// [`DEFAULT_CRAWL_FANOUT`] directories concurrently.
 // TODO: return fused BoxStream??
 pub fn crawl<D>(
     client: Client,
@@ -48,46 +59,352 @@ pub fn crawl<D>(
 where
     D: DatasetBackend + 'static + ?Sized,
 {
-    Box::pin(try_stream! {
-        // TODO: this is at boundary need to deal with error to retry.
-        let pb = mp.insert(0, ProgressBar::new_spinner());
-        pb.set_style(
-            ProgressStyle::with_template("{spinner:.green} {msg}")
-                .expect("indicatif template error"),
-        );
-        pb.enable_steady_tick(std::time::Duration::from_millis(100));
-        pb.set_message(format!("listing files of {}", dir.api_url().as_str()));
-        let entries = dataset_backend.list(&client, dir.clone())
-            .await
-            .or_raise(||
-                CrawlerError{
-                    message: format!("cannot list all entries of '{dir}', after retry"),
-                    status: ErrorStatus::Persistent,
-                })?;
-        pb.finish_and_clear();
-
-        for entry in entries {
+    crawl_with_fanout(client, dataset_backend, dir, mp, DEFAULT_CRAWL_FANOUT)
+}
+
+/// Recursively crawls `dir` and yields every file and directory under it.
+///
+/// Sibling directories are expanded concurrently: discovered directories are put in a
+/// work queue and up to `fanout` of them are listed at the same time. A value of `0`
+/// disables the limit.
+///
+/// A directory entry is always yielded before the entries inside it, but entries of
+/// different directories are interleaved in the order their listings complete.
+///
+/// Memory stays bounded for trees of hundreds of thousands of entries: directories are
+/// expanded depth first, so the queue holds the siblings along the current path rather
+/// than a whole level of the tree, a directory is only queued once its entry is consumed,
+/// and the progress bar of a listing is dropped as soon as it completes.
+///
+/// # Panics
+/// indicatif template error
+pub fn crawl_with_fanout<D>(
+    client: Client,
+    dataset_backend: Arc<D>,
+    dir: DirMeta,
+    mp: impl ProgressManager,
+    fanout: usize,
+) -> BoxStream<'static, Result<Entry, Exn<CrawlerError>>>
+where
+    D: DatasetBackend + 'static + ?Sized,
+{
+    let fanout = if fanout == 0 { usize::MAX } else { fanout };
+    let list = move |dir: DirMeta| -> ListFuture {
+        let client = client.clone();
+        let dataset_backend = Arc::clone(&dataset_backend);
+        let mp = mp.clone();
+        Box::pin(async move {
+            // TODO: this is at boundary need to deal with error to retry.
             let pb = mp.insert(0, ProgressBar::new_spinner());
             pb.set_style(
                 ProgressStyle::with_template("{spinner:.green} {msg}")
-                    .expect("indicatif template error")
+                    .expect("indicatif template error"),
             );
             pb.enable_steady_tick(std::time::Duration::from_millis(100));
-            match entry {
-                Entry::File(f) => {
-                    pb.set_message(format!("Crawling {}...", f.relative()));
-                    yield Entry::File(f)
-                }
-                Entry::Dir(sub_dir) => {
-                    pb.set_message(format!("Crawling {}...", sub_dir.relative()));
-                    yield Entry::Dir(sub_dir.clone());
-                    let client = client.clone();
-                    let sub_stream = crawl(client, Arc::clone(&dataset_backend), sub_dir, mp.clone());
-                    for await item in sub_stream {
-                        yield item?;
-                    }
+            pb.set_message(format!("listing files of {}", dir.api_url().as_str()));
+            let res = dataset_backend.list(&client, dir.clone()).await;
+            pb.finish_and_clear();
+            (dir, res)
+        })
+    };
+
+    let state = CrawlState {
+        list,
+        fanout,
+        queue: vec![dir],
+        pending: FuturesUnordered::new(),
+        listed: Vec::new().into_iter(),
+    };
+    stream::try_unfold(state, |mut state| async move {
+        loop {
+            if let Some(entry) = state.listed.next() {
+                if let Entry::Dir(sub_dir) = &entry {
+                    state.queue.push(sub_dir.clone());
                 }
+                state.schedule();
+                return Ok(Some((entry, state)));
             }
+            state.schedule();
+            let Some((dir, res)) = state.pending.next().await else {
+                return Ok(None);
+            };
+            let entries = res.or_raise(|| CrawlerError {
+                message: format!("cannot list all entries of '{dir}', after retry"),
+                status: ErrorStatus::Persistent,
+            })?;
+            state.listed = entries.into_iter();
         }
     })
+    .boxed()
+}
+
+/// Work queue of a crawl.
+struct CrawlState<L> {
+    list: L,
+    fanout: usize,
+    /// directories yielded but not listed yet, the last one is listed first.
+    queue: Vec<DirMeta>,
+    pending: FuturesUnordered<ListFuture>,
+    /// entries of the last completed listing not yielded yet.
+    listed: std::vec::IntoIter<Entry>,
+}
+
+impl<L> CrawlState<L>
+where
+    L: Fn(DirMeta) -> ListFuture,
+{
+    /// Starts listing queued directories until `fanout` listings are in flight.
+    fn schedule(&mut self) {
+        while self.pending.len() < self.fanout {
+            let Some(dir) = self.queue.pop() else {
+                break;
+            };
+            self.pending.push((self.list)(dir));
+        }
+    }
+}
+
+/// Files already yielded by a crawl, by relative path, to detect files listed twice.
+#[derive(Debug, Default)]
+struct SeenFiles {
+    checksums: HashMap<String, Vec<String>>,
+}
+
+impl SeenFiles {
+    /// Records `file`, returns why it is dropped if a file at the same path was already seen.
+    fn duplicate(&mut self, file: &FileMeta) -> Option<String> {
+        let checksums: Vec<String> = file.checksum().iter().map(ToString::to_string).collect();
+        let path = file.relative().as_str().to_string();
+        let Some(seen) = self.checksums.get(&path) else {
+            self.checksums.insert(path, checksums);
+            return None;
+        };
+        if seen == &checksums {
+            Some("listed more than once".to_string())
+        } else {
+            Some(format!(
+                "listed more than once with different checksums, kept {seen:?}, dropped {checksums:?}"
+            ))
+        }
+    }
+}
+
+/// Drops files of `entries` already yielded under the same relative path, keeping the
+/// first one, and logs a warning for every dropped file.
+///
+/// Buggy or paginated listings can return the same file twice, which would otherwise be
+/// downloaded twice into the same destination concurrently.
+pub fn dedup<S>(entries: S) -> BoxStream<'static, Result<Entry, Exn<CrawlerError>>>
+where
+    S: Stream<Item = Result<Entry, Exn<CrawlerError>>> + Send + 'static,
+{
+    dedup_with(entries, |_, _| future::ready(())).boxed()
+}
+
+/// Drops files of `entries` already yielded under the same relative path like [`dedup`],
+/// awaiting `on_duplicate` with every dropped file and the reason it is dropped.
+pub(crate) fn dedup_with<'a, S, F, Fut>(
+    entries: S,
+    mut on_duplicate: F,
+) -> impl Stream<Item = Result<Entry, Exn<CrawlerError>>> + Send + 'a
+where
+    S: Stream<Item = Result<Entry, Exn<CrawlerError>>> + Send + 'a,
+    F: FnMut(FileMeta, String) -> Fut + Send + 'a,
+    Fut: Future<Output = ()> + Send,
+{
+    try_stream! {
+        let mut seen = SeenFiles::default();
+        for await entry in entries {
+            let entry = entry?;
+            if let Entry::File(file_meta) = &entry {
+                if let Some(reason) = seen.duplicate(file_meta) {
+                    warn!(path = %file_meta.relative(), "skip duplicate file: {reason}");
+                    on_duplicate(file_meta.clone(), reason).await;
+                    continue;
+                }
+            }
+            yield entry;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{NoProgress, TestFile};
+    use async_trait::async_trait;
+    use futures_util::TryStreamExt;
+    use std::{
+        any::Any,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+    use url::Url;
+
+    // Every directory at depth < 2 holds 3 sub-directories and one file.
+    struct TreeBackend {
+        active: AtomicUsize,
+        max_active: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl DatasetBackend for TreeBackend {
+        async fn list(&self, _client: &Client, dir: DirMeta) -> Result<Vec<Entry>, Exn<RepoError>> {
+            let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_active.fetch_max(active, Ordering::SeqCst);
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            self.active.fetch_sub(1, Ordering::SeqCst);
+
+            let depth = dir
+                .relative()
+                .as_str()
+                .split('/')
+                .filter(|s| !s.is_empty())
+                .count();
+            let mut entries = vec![Entry::File(
+                TestFile::at(dir.join("file.txt"))
+                    .url(dir.api_url())
+                    .build(),
+            )];
+            if depth < 2 {
+                for i in 0..3 {
+                    entries.push(Entry::Dir(DirMeta::new(
+                        dir.join(&format!("d{i}")),
+                        dir.api_url(),
+                        dir.root_url(),
+                    )));
+                }
+            }
+            Ok(entries)
+        }
+
+        fn root_url(&self) -> Url {
+            Url::parse("https://example.org/").unwrap()
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    // Directories nested 3 deep, the listings of the first siblings take the longest.
+    struct SkewedBackend;
+
+    #[async_trait]
+    impl DatasetBackend for SkewedBackend {
+        async fn list(&self, _client: &Client, dir: DirMeta) -> Result<Vec<Entry>, Exn<RepoError>> {
+            let path = dir.relative().as_str().to_string();
+            let slow = path.split('/').filter(|s| *s == "d0").count();
+            tokio::time::sleep(std::time::Duration::from_millis(10 * slow as u64)).await;
+            if path.split('/').filter(|s| !s.is_empty()).count() >= 3 {
+                return Ok(vec![]);
+            }
+            let entries = (0..2)
+                .map(|i| {
+                    Entry::Dir(DirMeta::new(
+                        dir.join(&format!("d{i}")),
+                        dir.api_url(),
+                        dir.root_url(),
+                    ))
+                })
+                .collect();
+            Ok(entries)
+        }
+
+        fn root_url(&self) -> Url {
+            Url::parse("https://example.org/").unwrap()
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    #[tokio::test]
+    async fn test_crawl_with_fanout() {
+        let backend = Arc::new(TreeBackend {
+            active: AtomicUsize::new(0),
+            max_active: AtomicUsize::new(0),
+        });
+        let root = DirMeta::new_root(&backend.root_url());
+        let entries = crawl_with_fanout(Client::new(), Arc::clone(&backend), root, NoProgress, 2)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        // 1 + 3 + 9 directories listed, each with one file; 3 + 9 sub-directories.
+        let paths = entries
+            .iter()
+            .map(|e| match e {
+                Entry::Dir(d) => d.relative().as_str().to_string(),
+                Entry::File(f) => f.relative().as_str().to_string(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(paths.len(), 13 + 12);
+
+        // parents are always yielded before their children
+        for (idx, path) in paths.iter().enumerate() {
+            if let Some((parent, _)) = path.rsplit_once('/') {
+                let parent_idx = paths.iter().position(|p| p == parent).unwrap();
+                assert!(parent_idx < idx, "'{parent}' yielded after '{path}'");
+            }
+        }
+
+        assert_eq!(backend.max_active.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_crawl_yields_parents_before_children() {
+        let backend = Arc::new(SkewedBackend);
+        let root = DirMeta::new_root(&backend.root_url());
+        let entries = crawl_with_fanout(Client::new(), backend, root, NoProgress, 4)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        // 2 + 4 + 8 directories
+        let paths = entries
+            .iter()
+            .map(|e| match e {
+                Entry::Dir(d) => d.relative().as_str().to_string(),
+                Entry::File(f) => f.relative().as_str().to_string(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(paths.len(), 14);
+        for (idx, path) in paths.iter().enumerate() {
+            if let Some((parent, _)) = path.rsplit_once('/') {
+                let parent_idx = paths.iter().position(|p| p == parent).unwrap();
+                assert!(parent_idx < idx, "'{parent}' yielded after '{path}'");
+            }
+        }
+        // the listings completed out of order
+        let position = |path: &str| paths.iter().position(|p| p == path).unwrap();
+        assert!(position("d1/d0") < position("d0/d0"));
+    }
+
+    #[tokio::test]
+    async fn test_dedup() {
+        let root = DirMeta::new_root(&Url::parse("https://example.org/").unwrap());
+        let file = |name: &str, md5: &str| {
+            Entry::File(
+                TestFile::at(root.join(name))
+                    .checksum(crate::Checksum::Md5(md5.to_string()))
+                    .build(),
+            )
+        };
+        let entries = futures_util::stream::iter([
+            Ok(file("a.txt", "1")),
+            Ok(file("b.txt", "1")),
+            Ok(file("a.txt", "1")),
+            Ok(file("b.txt", "2")),
+        ]);
+        let paths = dedup(entries)
+            .map_ok(|entry| match entry {
+                Entry::File(f) => f.relative().as_str().to_string(),
+                Entry::Dir(d) => d.relative().as_str().to_string(),
+            })
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(paths, ["a.txt", "b.txt"]);
+    }
 }
@@ -1,33 +1,112 @@
 #![allow(clippy::upper_case_acronyms)]
 
 use async_trait::async_trait;
-use exn::Exn;
+use exn::{Exn, OptionExt};
 use url::Url;
+use xmltree::Element;
 
 use reqwest::Client;
-use std::{any::Any, str::FromStr};
+use std::{any::Any, borrow::Cow, str::FromStr};
 
 use crate::{
+    http::get_xml,
     repo::{Endpoint, FileMeta, RepoError},
-    DatasetBackend, DirMeta, Entry,
+    DatasetBackend, DatasetMetadata, DirMeta, Entry,
 };
 
+fn child_text<'a>(elem: &'a Element, name: &str) -> Option<Cow<'a, str>> {
+    elem.get_child(name).and_then(Element::get_text)
+}
+
+// Whitespace of the Atom text fields (line breaks of the title and abstract) is collapsed.
+fn collapse(text: &str) -> Option<String> {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+// Atom entry of http://export.arxiv.org/api/query?id_list=<id>, the DOI is the DataCite DOI
+// '10.48550/arXiv.<id>' every arXiv paper has, not the DOI of a later journal publication.
+fn metadata_from_entry(entry: &Element, id: &str) -> DatasetMetadata {
+    // e.g. <id>http://arxiv.org/abs/2101.00001v2</id>
+    let version = child_text(entry, "id").and_then(|abs| {
+        let (_, version) = abs.rsplit_once('v')?;
+        version
+            .chars()
+            .all(|c| c.is_ascii_digit())
+            .then(|| format!("v{version}"))
+    });
+    let id = id
+        .rsplit_once('v')
+        .filter(|(_, v)| !v.is_empty() && v.chars().all(|c| c.is_ascii_digit()))
+        .map_or(id, |(id, _)| id);
+    let creators = entry
+        .children
+        .iter()
+        .filter_map(|n| n.as_element())
+        .filter(|e| e.name == "author")
+        .filter_map(|author| child_text(author, "name").and_then(|n| collapse(&n)))
+        .collect();
+    DatasetMetadata {
+        title: child_text(entry, "title").and_then(|t| collapse(&t)),
+        description: child_text(entry, "summary").and_then(|t| collapse(&t)),
+        creators,
+        license: None,
+        publication_date: child_text(entry, "published").and_then(|t| collapse(&t)),
+        version,
+        doi: Some(format!("10.48550/arXiv.{id}")),
+    }
+}
+
+/// Format an arXiv paper is downloaded in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArxivFormat {
+    /// The rendered paper, `<id>.pdf` from `https://arxiv.org/pdf/<id>`.
+    Pdf,
+    /// The e-print source, `<id>.tar.gz` from `https://arxiv.org/e-print/<id>`, usually a
+    /// gzipped tarball of the TeX files and figures.
+    Source,
+}
+
 // https://arxiv.org/
 // API root url at https://arxiv.org/pdf/
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Arxiv {
     pub id: String,
+    pub formats: Vec<ArxivFormat>,
 }
 
 impl Arxiv {
+    /// The paper `id`, downloaded as pdf.
     #[must_use]
     pub fn new(id: impl Into<String>) -> Self {
-        Arxiv { id: id.into() }
+        Arxiv {
+            id: id.into(),
+            formats: vec![ArxivFormat::Pdf],
+        }
+    }
+
+    /// Downloads the paper in each of `formats`, e.g. both the pdf and the e-print source,
+    /// each listed as its own file to be selected by a filtered download.
+    #[must_use]
+    pub fn formats(mut self, formats: impl IntoIterator<Item = ArxivFormat>) -> Self {
+        self.formats.clear();
+        for format in formats {
+            if !self.formats.contains(&format) {
+                self.formats.push(format);
+            }
+        }
+        self
     }
 }
 
 #[async_trait]
 impl DatasetBackend for Arxiv {
+    fn name(&self) -> &'static str {
+        "arxiv"
+    }
+    fn record_id(&self) -> String {
+        self.id.clone()
+    }
     fn root_url(&self) -> Url {
         // https://arxiv.org/pdf/<id> to get the record pdf
 
@@ -44,31 +123,95 @@ impl DatasetBackend for Arxiv {
         // safe to unwrap, because I create the root_url
         let name: Vec<&str> = root_url.path_segments().unwrap().collect::<Vec<_>>();
         let name = name[1];
-        let download_url = root_url.clone();
-        let endpoint = Endpoint {
-            parent_url: dir.root_url(),
-            key: Some(name.to_string()),
-        };
-        let file = FileMeta::new(
-            None,
-            None,
-            dir.join(&format!("{name}.pdf")),
-            endpoint,
-            download_url,
-            None,
-            vec![],
-            // the mime-type of arxiv.org/pdf/ is surely a valid PDF
-            Some(mime::APPLICATION_PDF),
-            None,
-            None,
-            None,
-            true,
-        );
-
-        Ok(vec![Entry::File(file)])
+        let entries = self
+            .formats
+            .iter()
+            .map(|format| {
+                let (path, download_url, mime) = match format {
+                    // the mime-type of arxiv.org/pdf/ is surely a valid PDF
+                    ArxivFormat::Pdf => (
+                        format!("{name}.pdf"),
+                        root_url.clone(),
+                        Some(mime::APPLICATION_PDF),
+                    ),
+                    // the e-print is a tarball, a single gzipped TeX file or even a pdf when
+                    // the paper was submitted without source, its type is left to sniffing
+                    ArxivFormat::Source => {
+                        let mut url = root_url.clone();
+                        // Safe to unwrap: the root url is an https URL
+                        url.path_segments_mut()
+                            .unwrap()
+                            .clear()
+                            .extend(["e-print", name]);
+                        (format!("{name}.tar.gz"), url, None)
+                    }
+                };
+                let endpoint = Endpoint {
+                    parent_url: dir.root_url(),
+                    key: Some(path.clone()),
+                };
+                Entry::File(FileMeta::new(
+                    None,
+                    Some(name.to_string()),
+                    dir.join(&path),
+                    endpoint,
+                    download_url,
+                    None,
+                    vec![],
+                    mime,
+                    None,
+                    None,
+                    None,
+                    true,
+                ))
+            })
+            .collect();
+        Ok(entries)
+    }
+
+    async fn metadata(&self, client: &Client) -> Result<DatasetMetadata, Exn<RepoError>> {
+        // Safe to unwrap:
+        // - the base URL is a hard-coded, valid absolute URL
+        let mut url = Url::from_str("https://export.arxiv.org/api/query").unwrap();
+        url.query_pairs_mut().append_pair("id_list", &self.id);
+        let feed = get_xml(client, &url).await?;
+        let entry = feed.get_child("entry").ok_or_raise(|| RepoError {
+            message: format!("no arXiv entry '{}' found at {url}", self.id),
+        })?;
+        Ok(metadata_from_entry(entry, &self.id))
     }
 
     fn as_any(&self) -> &dyn Any {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_arxiv_metadata_from_entry() {
+        let feed = r#"<?xml version="1.0" encoding="UTF-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <entry>
+    <id>http://arxiv.org/abs/2101.00001v2</id>
+    <published>2021-01-01T00:00:00Z</published>
+    <title>A Study of
+      Things</title>
+    <summary>  We study things.
+    </summary>
+    <author><name>Jane Doe</name></author>
+    <author><name>Richard Roe</name></author>
+  </entry>
+</feed>"#;
+        let feed = Element::parse(Cursor::new(feed)).unwrap();
+        let metadata = metadata_from_entry(feed.get_child("entry").unwrap(), "2101.00001");
+        assert_eq!(metadata.title.as_deref(), Some("A Study of Things"));
+        assert_eq!(metadata.description.as_deref(), Some("We study things."));
+        assert_eq!(metadata.creators, ["Jane Doe", "Richard Roe"]);
+        assert_eq!(metadata.version.as_deref(), Some("v2"));
+        assert_eq!(metadata.doi.as_deref(), Some("10.48550/arXiv.2101.00001"));
+    }
+}
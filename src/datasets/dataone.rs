@@ -2,14 +2,17 @@
 
 use async_trait::async_trait;
 use exn::{Exn, OptionExt, ResultExt};
+use futures_util::{stream, StreamExt};
 use url::Url;
 
-use reqwest::{Client, StatusCode};
+use reqwest::Client;
 use std::{any::Any, io::Cursor, str::FromStr};
+use tracing::warn;
 
 use crate::{
+    http::{get_bytes, get_xml},
     repo::{Endpoint, FileMeta, RepoError},
-    DatasetBackend, DirMeta, Entry,
+    Checksum, DatasetBackend, DirMeta, Entry,
 };
 
 // https://www.dataone.org/
@@ -17,9 +20,8 @@ use crate::{
 // XXX: read about https://dataoneorg.github.io/api-documentation/design/DataPackage.html?utm_source=chatgpt.com
 // not planned because Dataone is extremly slow in HTTP response.
 // XXX: potentially it support: https://dataoneorg.github.io/api-documentation/apis/MN_APIs.html#MNPackage.getPackage
-// TODO: dataone return OAI-MPH for the whole dataset on request, which contains more then what I
-// paresd below. The mime-type and the size in principle can get from there, but it is slow and I
-// don't know the API endpoint to get those.
+// The size, checksum and format of each data object are read from its system metadata at
+// https://cn.dataone.org/cn/v2/meta/{pid}, one request per object.
 #[derive(Debug)]
 pub struct Dataone {
     pub base_url: Url,
@@ -36,8 +38,101 @@ impl Dataone {
     }
 }
 
+/// System metadata requests sent concurrently while listing a dataset.
+const SYSTEM_METADATA_CONCURRENCY: usize = 8;
+
+/// Authoritative size, checksum and format of a data object, from its system metadata at
+/// `/meta/{pid}`.
+#[derive(Debug, Default)]
+struct SystemMetadata {
+    size: Option<u64>,
+    checksum: Option<Checksum>,
+    format_id: Option<String>,
+}
+
+fn parse_system_metadata(xml: &[u8]) -> Result<SystemMetadata, Exn<RepoError>> {
+    let tree = xmltree::Element::parse(Cursor::new(xml)).or_raise(|| RepoError {
+        message: "Failed to parse system metadata XML".to_string(),
+    })?;
+    let text = |name: &str| {
+        tree.get_child(name)
+            .and_then(|e| e.get_text())
+            .map(|s| s.trim().to_string())
+    };
+    // e.g. '<checksum algorithm="MD5">...</checksum>'
+    let checksum = tree.get_child("checksum").and_then(|e| {
+        let algo = e.attributes.get("algorithm")?;
+        if !Checksum::is_supported(algo) {
+            return None;
+        }
+        Checksum::parse(algo, &e.get_text()?)
+            .inspect_err(|err| warn!("ignore system metadata checksum, {err}"))
+            .ok()
+    });
+    Ok(SystemMetadata {
+        size: text("size").and_then(|size| size.parse().ok()),
+        checksum,
+        format_id: text("formatId"),
+    })
+}
+
+async fn system_metadata(client: &Client, url: &Url) -> Result<SystemMetadata, Exn<RepoError>> {
+    let bytes = get_bytes(client, url).await?;
+    parse_system_metadata(&bytes)
+}
+
+/// Data object of the EML of a dataset, before its system metadata is fetched.
+struct EmlEntity {
+    name: String,
+    id: Option<String>,
+    download_url: Url,
+    size: Option<u64>,
+    checksum: Vec<Checksum>,
+}
+
+impl EmlEntity {
+    /// Identifier of the data object, the last segment of object or resolve download urls,
+    /// e.g. 'https://cn.dataone.org/cn/v2/resolve/urn%3Auuid%3A...', else the entity id.
+    fn pid(&self) -> Option<String> {
+        let mut segments = self.download_url.path_segments()?;
+        let from_url = segments
+            .by_ref()
+            .find(|seg| matches!(*seg, "object" | "resolve"))
+            .and_then(|_| segments.next())
+            .filter(|pid| !pid.is_empty())
+            .map(|pid| {
+                percent_encoding::percent_decode_str(pid)
+                    .decode_utf8_lossy()
+                    .into_owned()
+            });
+        from_url.or_else(|| self.id.clone())
+    }
+}
+
+/// System metadata url of the data object `pid`, next to the object url `api_url`
+/// (`.../cn/v2/object/{id}` to `.../cn/v2/meta/{pid}`).
+fn meta_url(api_url: &Url, pid: &str) -> Result<Url, Exn<RepoError>> {
+    let mut url = api_url.clone();
+    url.path_segments_mut()
+        .map_err(|()| RepoError {
+            message: format!("'{api_url}' cannot be base"),
+        })?
+        .pop()
+        .pop()
+        .extend(["meta", pid]);
+    Ok(url)
+}
+
 #[async_trait]
 impl DatasetBackend for Dataone {
+    fn name(&self) -> &'static str {
+        "dataone"
+    }
+    fn record_id(&self) -> String {
+        percent_encoding::percent_decode_str(&self.id)
+            .decode_utf8_lossy()
+            .into_owned()
+    }
     fn root_url(&self) -> Url {
         // the dashboard can be at https://data.ess-dive.lbl.gov/view/doi%3A10.15485%2F1971251
         // the xml to describe datasets are all at https://cn.dataone.org/cn/v2/object/
@@ -49,39 +144,12 @@ impl DatasetBackend for Dataone {
         url.join(&self.id).expect("cannot parse new url")
     }
     async fn list(&self, client: &Client, dir: DirMeta) -> Result<Vec<Entry>, Exn<RepoError>> {
-        let resp = client
-            .get(dir.api_url().clone())
-            .send()
-            .await
-            .or_raise(|| RepoError {
-                message: format!("fail at client sent GET {}", dir.api_url()),
-            })?;
-        let resp = resp.error_for_status().map_err(|err| match err.status() {
-            Some(StatusCode::NOT_FOUND) => RepoError {
-                message: format!("resource not found when GET {}", dir.api_url()),
-            },
-            Some(status_code) => RepoError {
-                message: format!(
-                    "fail GET {}, with state code: {}",
-                    dir.api_url(),
-                    status_code.as_str()
-                ),
-            },
-            None => RepoError {
-                message: format!("fail GET {}, network / protocol error", dir.api_url(),),
-            },
-        })?;
         // TODO: I use xmltree at the moment, which load full xml and then the parsed tree in
         // memory, it can be improve by buf and async when performance requirements comes for
         // DataOne repositories.
-        let bytes = resp.bytes().await.map_err(|_| RepoError {
-            message: "Failed to get bytes from response".to_string(),
-        })?;
-        let meta_tree = xmltree::Element::parse(Cursor::new(bytes)).map_err(|_| RepoError {
-            message: "Failed to parse XML".to_string(),
-        })?;
-
-        let mut entries = Vec::new();
+        let meta_tree = get_xml(client, &dir.api_url()).await?;
+
+        let mut entities = Vec::new();
         if let Some(dataset_elem) = meta_tree.get_child("dataset") {
             for data_elem in &dataset_elem.children {
                 if let Some(elem) = data_elem.as_element() {
@@ -132,37 +200,175 @@ impl DatasetBackend for Dataone {
                             })
                             .transpose()?;
 
-                        let endpoint = Endpoint {
-                            parent_url: dir.api_url().clone(),
-                            key: Some(
-                                "dataset.physical.distribution.online.url[@function='download']"
-                                    .to_string(),
-                            ),
-                        };
-
-                        let file = FileMeta::new(
-                            None,
-                            None,
-                            dir.join(&name),
-                            endpoint,
+                        // e.g. '<authentication method="SHA-1">...</authentication>'
+                        let checksum = elem
+                            .get_child("physical")
+                            .into_iter()
+                            .flat_map(|p| &p.children)
+                            .filter_map(|n| n.as_element())
+                            .filter(|e| e.name == "authentication")
+                            .filter_map(|e| {
+                                let method = e.attributes.get("method")?;
+                                if !Checksum::is_supported(method) {
+                                    return None;
+                                }
+                                Checksum::parse(method, &e.get_text()?)
+                                    .inspect_err(|err| warn!("ignore authentication, {err}"))
+                                    .ok()
+                            })
+                            .collect();
+
+                        entities.push(EmlEntity {
+                            name,
+                            id: elem.attributes.get("id").cloned(),
                             download_url,
                             size,
-                            vec![],
-                            None,
-                            None,
-                            None,
-                            None,
-                            true,
-                        );
-                        entries.push(Entry::File(file));
+                            checksum,
+                        });
                     }
                 }
             }
         }
 
+        // the system metadata is authoritative for the size and checksum, which the EML
+        // often lacks
+        let dir = &dir;
+        let entries = stream::iter(entities)
+            .map(|entity| async move {
+                let meta = match entity.pid() {
+                    Some(pid) => {
+                        let url = meta_url(&dir.api_url(), &pid)?;
+                        system_metadata(client, &url)
+                            .await
+                            .inspect_err(|err| warn!("no system metadata of '{pid}', {err}"))
+                            .unwrap_or_default()
+                    }
+                    None => SystemMetadata::default(),
+                };
+                let mut checksum = entity.checksum;
+                if let Some(sys_checksum) = meta.checksum {
+                    // preferred over the EML checksum of the same algorithm
+                    checksum.insert(0, sys_checksum);
+                }
+                let endpoint = Endpoint {
+                    parent_url: dir.api_url().clone(),
+                    key: Some(
+                        "dataset.physical.distribution.online.url[@function='download']"
+                            .to_string(),
+                    ),
+                };
+
+                // EML entities are identified by the pid of their data object
+                let file = FileMeta::new(
+                    None,
+                    entity.id,
+                    dir.join(&entity.name),
+                    endpoint,
+                    entity.download_url,
+                    meta.size.or(entity.size),
+                    checksum,
+                    meta.format_id.and_then(|format| format.parse().ok()),
+                    None,
+                    None,
+                    None,
+                    true,
+                );
+                Ok::<_, Exn<RepoError>>(Entry::File(file))
+            })
+            .buffered(SYSTEM_METADATA_CONCURRENCY)
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?;
+
         Ok(entries)
     }
     fn as_any(&self) -> &dyn Any {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_dataone_list_system_metadata() {
+        let mock_server = MockServer::start().await;
+        let uri = mock_server.uri();
+        let eml = format!(
+            r#"<eml:eml xmlns:eml="https://eml.ecoinformatics.org/eml-2.2.0">
+              <dataset>
+                <dataTable id="urn-uuid-a">
+                  <entityName>a.csv</entityName>
+                  <physical>
+                    <size unit="bytes">10</size>
+                    <authentication method="SHA-1">2fd4e1c67a2d28fced849ee1bb76e7391b93eb12</authentication>
+                    <distribution><online>
+                      <url function="download">{uri}/cn/v2/resolve/urn%3Auuid%3Aa</url>
+                    </online></distribution>
+                  </physical>
+                </dataTable>
+                <otherEntity id="urn-uuid-b">
+                  <entityName>b.txt</entityName>
+                  <physical>
+                    <size unit="bytes">7</size>
+                    <distribution><online>
+                      <url function="download">{uri}/cn/v2/resolve/urn%3Auuid%3Ab</url>
+                    </online></distribution>
+                  </physical>
+                </otherEntity>
+              </dataset>
+            </eml:eml>"#
+        );
+        Mock::given(method("GET"))
+            .and(path("/cn/v2/object/doi%3A10.5063%2FF1TEST"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(eml))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/cn/v2/meta/urn:uuid:a"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"<d1:systemMetadata xmlns:d1="http://ns.dataone.org/service/types/v2.0">
+                  <identifier>urn:uuid:a</identifier>
+                  <formatId>text/csv</formatId>
+                  <size>42</size>
+                  <checksum algorithm="MD5">9e107d9d372bb6826bd81d3542a419d6</checksum>
+                </d1:systemMetadata>"#,
+            ))
+            .mount(&mock_server)
+            .await;
+        // no system metadata for 'urn:uuid:b', the EML values are kept
+
+        let api_url = Url::parse(&format!("{uri}/cn/v2/object/doi%3A10.5063%2FF1TEST")).unwrap();
+        let dataone = Dataone::new(&api_url, "doi%3A10.5063%2FF1TEST");
+        let entries = dataone
+            .list(&Client::new(), DirMeta::new_root(&api_url))
+            .await
+            .unwrap();
+        assert_eq!(entries.len(), 2);
+
+        let Entry::File(a) = &entries[0] else {
+            panic!("expect a file")
+        };
+        assert_eq!(a.relative().as_str(), "a.csv");
+        assert_eq!(a.size(), Some(42));
+        assert_eq!(
+            a.mimetype().map(|m| m.to_string()).as_deref(),
+            Some("text/csv")
+        );
+        let algorithms: Vec<_> = a.checksum().iter().map(Checksum::algorithm).collect();
+        assert_eq!(algorithms, vec!["md5", "sha1"]);
+        assert_eq!(a.checksum()[0].value(), "9e107d9d372bb6826bd81d3542a419d6");
+
+        let Entry::File(b) = &entries[1] else {
+            panic!("expect a file")
+        };
+        assert_eq!(b.size(), Some(7));
+        assert!(b.checksum().is_empty());
+    }
+}
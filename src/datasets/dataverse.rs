@@ -5,15 +5,82 @@ use exn::{Exn, ResultExt};
 use serde_json::Value as JsonValue;
 use url::Url;
 
-use reqwest::{Client, StatusCode};
-use std::{any::Any, str::FromStr};
+use reqwest::{
+    header::{HeaderMap, HeaderName},
+    Client,
+};
+use std::{any::Any, collections::HashMap, str::FromStr, sync::Arc};
 
+use crate::auth::auth_header;
 use crate::helper::json_extract;
+use crate::http::get_json;
+use crate::metadata::{json_str, json_strs, normalize_doi};
 use crate::{
     repo::{Endpoint, FileMeta, RepoError},
-    Checksum, DatasetBackend, DirMeta, Entry,
+    AccessRequirement, AuthConfig, Checksum, CrawlPath, DatasetBackend, DatasetMetadata, DirMeta,
+    Entry,
 };
 
+/// Version of a dataset as named by the Dataverse API, `2` is the major version `2.0`,
+/// `2.1` and special versions such as `:latest-published` or `:draft` are kept.
+pub(crate) fn version_name(version: &str) -> String {
+    if !version.is_empty() && version.chars().all(|c| c.is_ascii_digit()) {
+        format!("{version}.0")
+    } else {
+        version.to_string()
+    }
+}
+
+// Only DOIs are reported, datasets can also be registered with a Handle ('hdl:...').
+fn doi_of(persistent_id: Option<String>) -> Option<String> {
+    persistent_id
+        .filter(|pid| pid.starts_with("doi:"))
+        .map(|pid| normalize_doi(&pid))
+}
+
+// The dataset version json lists the citation metadata block as
+// '{"typeName": "title", "value": ...}' fields, compound fields have an array of objects
+// keyed by the sub field name as value.
+fn metadata_from_json(json: &JsonValue) -> DatasetMetadata {
+    let fields = json
+        .pointer("/data/metadataBlocks/citation/fields")
+        .and_then(JsonValue::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let field = |name: &str| {
+        fields
+            .iter()
+            .find(|f| f.get("typeName").and_then(JsonValue::as_str) == Some(name))
+    };
+    let description = field("dsDescription")
+        .map(|f| json_strs(f, "/value", "/dsDescriptionValue/value"))
+        .filter(|descs| !descs.is_empty())
+        .map(|descs| descs.join("\n\n"));
+    let version = match (
+        json.pointer("/data/versionNumber")
+            .and_then(JsonValue::as_u64),
+        json.pointer("/data/versionMinorNumber")
+            .and_then(JsonValue::as_u64),
+    ) {
+        (Some(major), Some(minor)) => Some(format!("{major}.{minor}")),
+        (Some(major), None) => Some(major.to_string()),
+        _ => None,
+    };
+    DatasetMetadata {
+        title: field("title").and_then(|f| json_str(f, "/value")),
+        description,
+        creators: field("author")
+            .map(|f| json_strs(f, "/value", "/authorName/value"))
+            .unwrap_or_default(),
+        // 'license' is an object since Dataverse 5.10, a plain string before
+        license: json_str(json, "/data/license/name").or_else(|| json_str(json, "/data/license")),
+        publication_date: json_str(json, "/data/publicationDate")
+            .or_else(|| json_str(json, "/data/releaseTime")),
+        version,
+        doi: doi_of(json_str(json, "/data/datasetPersistentId")),
+    }
+}
+
 fn parse_url(base_url: Url, version: &str, id: &str) -> Url {
     // "https://dataverse.harvard.edu/api/datasets/:persistentId/versions/:latest-published/?persistentId=doi:10.7910/DVN/KBHLOD"
     // Safe to unwrap:
@@ -34,7 +101,118 @@ fn parse_url(base_url: Url, version: &str, id: &str) -> Url {
     url
 }
 
-fn analyse_json(json: &JsonValue, dir: &DirMeta) -> Result<Vec<Entry>, Exn<RepoError>> {
+// https://datavers.example/api/access/datafile/<id>, relative to the base url of the
+// installation, which may be hosted under a path
+fn datafile_url(base_url: &Url, id: u64) -> Result<Url, Exn<RepoError>> {
+    let mut url = base_url.clone();
+    url.path_segments_mut()
+        .map_err(|()| RepoError {
+            message: format!("'{base_url}' cannot be base"),
+        })?
+        .pop_if_empty()
+        .extend(["api", "access", "datafile", &id.to_string()]);
+    Ok(url)
+}
+
+// Installations are configured to checksum files with MD5, SHA-1, SHA-256 or SHA-512, given
+// at 'dataFile.checksum'. Old installations only have the MD5 at 'dataFile.md5'.
+fn checksum_of(filej: &JsonValue, name: &str) -> Result<Checksum, Exn<RepoError>> {
+    let (typ, hash) = match (
+        json_extract::<String>(filej, "dataFile.checksum.type"),
+        json_extract::<String>(filej, "dataFile.checksum.value"),
+    ) {
+        (Ok(typ), Ok(hash)) => (typ, hash),
+        _ => {
+            let hash: String = json_extract(filej, "dataFile.md5").or_raise(|| RepoError {
+                message: format!("no 'dataFile.checksum' nor 'dataFile.md5' of '{name}'"),
+            })?;
+            ("MD5".to_string(), hash)
+        }
+    };
+    Checksum::parse(&typ, &hash).or_raise(|| RepoError {
+        message: format!("invalid '{typ}' checksum of '{name}'"),
+    })
+}
+
+// Extensions of the files of a shapefile, compared lowercase, longest first.
+const SHAPEFILE_PARTS: [&str; 17] = [
+    "shp.xml", "shp", "shx", "dbf", "prj", "cpg", "sbn", "sbx", "qix", "qpj", "fbn", "fbx", "ain",
+    "aih", "atx", "ixs", "mxs",
+];
+
+// Lowercase stem of `name` if it is a file of a shapefile, e.g. 'roads' of 'Roads.SHP'.
+fn shapefile_stem(name: &str) -> Option<String> {
+    let name = name.to_lowercase();
+    SHAPEFILE_PARTS.iter().find_map(|ext| {
+        let stem = name.strip_suffix(ext)?.strip_suffix('.')?;
+        (!stem.is_empty()).then(|| stem.to_string())
+    })
+}
+
+// A file of a shapefile listed with the other files of the shapefile.
+struct ShapefilePart {
+    name: String,
+    // ingested by the installation, e.g. a '.dbf' turned into a '.tab', the original file is
+    // downloaded
+    original: bool,
+    others: Vec<String>,
+}
+
+// Shapefiles uploaded unzipped (borealisdata.ca and other installations keep them so) are
+// listed as separate files, `.shp`, `.shx`, `.dbf`, ... of a same stem in a same folder,
+// they are bundled to be selected together. Returns the part of every file of `files`, if
+// it is one of a shapefile with a `.shp`.
+fn shapefile_parts(files: &[JsonValue]) -> Vec<Option<ShapefilePart>> {
+    let parts = files
+        .iter()
+        .map(|filej| {
+            let label = json_extract::<String>(filej, "directoryLabel").ok();
+            let name = json_extract::<String>(filej, "dataFile.filename").ok()?;
+            let (name, original) = if shapefile_stem(&name).is_some() {
+                (name, false)
+            } else {
+                let original = json_extract::<String>(filej, "dataFile.originalFileName").ok()?;
+                (original, true)
+            };
+            let stem = shapefile_stem(&name)?;
+            let path = match &label {
+                Some(label) => format!("{label}/{name}"),
+                None => name.clone(),
+            };
+            Some(((label, stem), name, original, path))
+        })
+        .collect::<Vec<_>>();
+
+    let mut bundles: HashMap<&(Option<String>, String), Vec<&str>> = HashMap::new();
+    for (key, _, _, path) in parts.iter().flatten() {
+        bundles.entry(key).or_default().push(path);
+    }
+    bundles.retain(|_, paths| {
+        paths.len() > 1 && paths.iter().any(|p| p.to_lowercase().ends_with(".shp"))
+    });
+    parts
+        .iter()
+        .map(|part| {
+            let (key, name, original, path) = part.as_ref()?;
+            let paths = bundles.get(key)?;
+            Some(ShapefilePart {
+                name: name.clone(),
+                original: *original,
+                others: paths
+                    .iter()
+                    .filter(|p| **p != path.as_str())
+                    .map(ToString::to_string)
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+fn analyse_json(
+    json: &JsonValue,
+    dir: &DirMeta,
+    base_url: &Url,
+) -> Result<Vec<Entry>, Exn<RepoError>> {
     let files = json
         .get("data")
         .and_then(|d| d.get("files"))
@@ -43,8 +221,10 @@ fn analyse_json(json: &JsonValue, dir: &DirMeta) -> Result<Vec<Entry>, Exn<RepoE
             message: "field with key 'data.files' not resolve to an json array".to_string(),
         })?;
 
+    let shapefile_parts = shapefile_parts(files);
     let mut entries = Vec::with_capacity(files.len());
     for (idx, filej) in files.iter().enumerate() {
+        let part = shapefile_parts[idx].as_ref();
         let endpoint = Endpoint {
             parent_url: dir.api_url().clone(),
             key: Some(format!("data.files.{idx}")),
@@ -82,56 +262,32 @@ fn analyse_json(json: &JsonValue, dir: &DirMeta) -> Result<Vec<Entry>, Exn<RepoE
             message: "fail to extracting 'version' as u64 from json".to_string(),
         })?;
 
-        let download_url = dir
-            .api_url()
-            .join("/api/access/datafile/")
-            .or_raise(|| RepoError {
-                message: "cannot parse download base url".to_string(),
-            })?;
-        let download_url = download_url.join(&format!("{id}")).or_raise(|| RepoError {
-            message: format!("cannot parse '{download_url}' download url"),
-        })?;
+        let mut download_url = datafile_url(base_url, id)?;
+        // the checksum is the one of the original file
+        let checksum = checksum_of(filej, &name)?;
+        let (name, size, mime_type) = match part {
+            Some(part) if part.original => {
+                let ingested = mime_type;
+                download_url.set_query(Some("format=original"));
+                let size = json_extract::<u64>(filej, "dataFile.originalFileSize").ok();
+                let mime_type = json_extract::<String>(filej, "dataFile.originalFileFormat")
+                    .ok()
+                    .and_then(|mime_type| mime::Mime::from_str(&mime_type).ok());
+                (part.name.clone(), size, mime_type.unwrap_or(ingested))
+            }
+            _ => (name, Some(size), mime_type),
+        };
         let dst_path = match json_extract::<String>(filej, "directoryLabel") {
             Ok(dir_label) => dir.join(&format!("{dir_label}/{name}")),
             Err(_) => dir.join(&name),
         };
-        let checksum_typ: String =
-            json_extract(filej, "dataFile.checksum.type").or_raise(|| RepoError {
-                message: "fail to extracting 'dataFile.checksum.type' as String from json"
-                    .to_string(),
-            })?;
-        let checksum = match checksum_typ.as_str() {
-            "MD5" | "md5" => {
-                let hash: String =
-                    json_extract(filej, "dataFile.checksum.value").or_raise(|| RepoError {
-                        message: "fail to extracting 'dataFile.checksum.value' as String from json"
-                            .to_string(),
-                    })?;
-                Checksum::Md5(hash)
-            }
-            "SHA-1" | "sha-1" => {
-                let hash: String =
-                    json_extract(filej, "dataFile.checksum.value").or_raise(|| RepoError {
-                        message: "fail to extracting 'dataFile.checksum.value' as String from json"
-                            .to_string(),
-                    })?;
-                Checksum::Sha1(hash)
-            }
-            v => {
-                exn::bail!(RepoError {
-                    message: format!(
-                        "{v} is not yet support, please open an issue so we can add it"
-                    )
-                });
-            }
-        };
         let file = FileMeta::new(
             Some(name),
             Some(id.to_string()),
             dst_path,
             endpoint,
             download_url,
-            Some(size),
+            size,
             vec![checksum],
             Some(mime_type),
             Some(version.to_string()),
@@ -139,12 +295,60 @@ fn analyse_json(json: &JsonValue, dir: &DirMeta) -> Result<Vec<Entry>, Exn<RepoE
             last_modification_date,
             downloadable,
         );
+        let file = match part {
+            Some(part) => file.with_bundle(part.others.iter().map(|p| dir.join(p)).collect()),
+            None => file,
+        };
         entries.push(Entry::File(file));
     }
 
     Ok(entries)
 }
 
+// Restricted files must be requested on the landing page of the dataset, after accepting its
+// terms of access if any, embargoed files are listed with the date they become available.
+fn access_from_json(json: &JsonValue, landing: &Url) -> Vec<AccessRequirement> {
+    let files = json
+        .pointer("/data/files")
+        .and_then(JsonValue::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let path_of = |filej: &JsonValue| {
+        let name = json_str(filej, "/dataFile/filename").unwrap_or_default();
+        match json_str(filej, "/directoryLabel") {
+            Some(dir_label) => CrawlPath::root().join(&format!("{dir_label}/{name}")),
+            None => CrawlPath::root().join(&name),
+        }
+    };
+    let restricted = files
+        .iter()
+        .filter(|filej| filej.get("restricted").and_then(JsonValue::as_bool) == Some(true))
+        .map(path_of)
+        .collect::<Vec<_>>();
+
+    let mut requirements = Vec::new();
+    if !restricted.is_empty() {
+        if let Some(text) = json_str(json, "/data/termsOfAccess") {
+            requirements.push(AccessRequirement::Terms {
+                text,
+                url: Some(landing.clone()),
+            });
+        }
+        requirements.push(AccessRequirement::Request {
+            url: landing.clone(),
+            paths: restricted,
+        });
+    }
+    if let Some(until) = files
+        .iter()
+        .filter_map(|filej| json_str(filej, "/dataFile/embargo/dateAvailable"))
+        .max()
+    {
+        requirements.push(AccessRequirement::Embargo { until: Some(until) });
+    }
+    requirements
+}
+
 // https://datavers.example/api/datasets/:persistentId/versions/:latest-poblished/?persistentId=<id>
 #[derive(Debug)]
 pub struct DataverseDataset {
@@ -166,42 +370,93 @@ impl DataverseDataset {
 
 #[async_trait]
 impl DatasetBackend for DataverseDataset {
+    fn name(&self) -> &'static str {
+        "dataverse"
+    }
+    fn record_id(&self) -> String {
+        self.id.clone()
+    }
     fn root_url(&self) -> Url {
         parse_url(self.base_url.clone(), &self.version, &self.id)
     }
 
+    fn archive_url(&self) -> Option<Url> {
+        // "https://dataverse.harvard.edu/api/access/dataset/:persistentId/versions/:latest-published?persistentId=doi:10.7910/DVN/KBHLOD"
+        // Safe to unwrap:
+        // - the base URL is a hard-coded, valid absolute URL
+        let mut url = self.base_url.clone();
+        url.path_segments_mut().unwrap().extend([
+            "api",
+            "access",
+            "dataset",
+            ":persistentId",
+            "versions",
+            &self.version,
+        ]);
+        url.query_pairs_mut().append_pair("persistentId", &self.id);
+        Some(url)
+    }
+
     async fn list(&self, client: &Client, dir: DirMeta) -> Result<Vec<Entry>, Exn<RepoError>> {
-        let resp = client
-            .get(dir.api_url().clone())
-            .send()
-            .await
-            .or_raise(|| RepoError {
-                message: format!("fail at client sent GET {}", dir.api_url()),
-            })?;
-        let resp = resp.error_for_status().map_err(|err| match err.status() {
-            Some(StatusCode::NOT_FOUND) => RepoError {
-                message: format!("resource not found when GET {}", dir.api_url()),
-            },
-            Some(status_code) => RepoError {
-                message: format!(
-                    "fail GET {}, with state code: {}",
-                    dir.api_url(),
-                    status_code.as_str()
-                ),
-            },
-            None => RepoError {
-                message: format!("fail GET {}, network / protocol error", dir.api_url(),),
-            },
-        })?;
-        let resp: JsonValue = resp.json().await.or_raise(|| RepoError {
-            message: format!("fail GET {}, unable to convert to json", dir.api_url(),),
-        })?;
+        let resp = get_json(client, &dir.api_url()).await?;
 
-        let entries = analyse_json(&resp, &dir)?;
+        let entries = analyse_json(&resp, &dir, &self.base_url)?;
 
         Ok(entries)
     }
 
+    async fn raw_metadata(&self, client: &Client) -> Result<JsonValue, Exn<RepoError>> {
+        get_json(client, &self.root_url()).await
+    }
+
+    async fn metadata(&self, client: &Client) -> Result<DatasetMetadata, Exn<RepoError>> {
+        let json = self.raw_metadata(client).await?;
+        Ok(metadata_from_json(&json))
+    }
+
+    async fn access_requirements(
+        &self,
+        client: &Client,
+        auth: &AuthConfig,
+    ) -> Result<Vec<AccessRequirement>, Exn<RepoError>> {
+        // access may have been granted to the owner of the token
+        if !self.auth_headers(auth).is_empty() {
+            return Ok(Vec::new());
+        }
+        let json = self.raw_metadata(client).await?;
+        // https://dataverse.example/dataset.xhtml?persistentId=<id>
+        let mut landing = self.base_url.clone();
+        landing
+            .path_segments_mut()
+            .unwrap()
+            .pop_if_empty()
+            .push("dataset.xhtml");
+        landing
+            .query_pairs_mut()
+            .append_pair("persistentId", &self.id);
+        Ok(access_from_json(&json, &landing))
+    }
+
+    async fn with_version(
+        &self,
+        _client: &Client,
+        version: &str,
+    ) -> Result<Arc<dyn DatasetBackend>, Exn<RepoError>> {
+        Ok(Arc::new(DataverseDataset::new(
+            &self.id,
+            &self.base_url,
+            version_name(version),
+        )))
+    }
+
+    fn auth_headers(&self, auth: &AuthConfig) -> HeaderMap {
+        auth.dataverse
+            .as_deref()
+            .map_or_else(HeaderMap::new, |token| {
+                auth_header(HeaderName::from_static("x-dataverse-key"), token)
+            })
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -234,16 +489,33 @@ impl DataverseJsonSrcDataset {
 
 #[async_trait]
 impl DatasetBackend for DataverseJsonSrcDataset {
+    fn name(&self) -> &'static str {
+        "dataverse"
+    }
+    fn record_id(&self) -> String {
+        self.id.clone()
+    }
     async fn list(&self, _client: &Client, dir: DirMeta) -> Result<Vec<Entry>, Exn<RepoError>> {
         let json_value: JsonValue = serde_json::from_str(self.content).or_raise(|| RepoError {
             message: "Failed to parse JSON".to_string(),
         })?;
 
-        let entries = analyse_json(&json_value, &dir)?;
+        let entries = analyse_json(&json_value, &dir, &self.base_url)?;
 
         Ok(entries)
     }
 
+    async fn raw_metadata(&self, _client: &Client) -> Result<JsonValue, Exn<RepoError>> {
+        serde_json::from_str(self.content).or_raise(|| RepoError {
+            message: "Failed to parse JSON".to_string(),
+        })
+    }
+
+    async fn metadata(&self, client: &Client) -> Result<DatasetMetadata, Exn<RepoError>> {
+        let json_value = self.raw_metadata(client).await?;
+        Ok(metadata_from_json(&json_value))
+    }
+
     fn root_url(&self) -> Url {
         parse_url(self.base_url.clone(), &self.version, &self.id)
     }
@@ -274,6 +546,12 @@ impl DataverseFile {
 
 #[async_trait]
 impl DatasetBackend for DataverseFile {
+    fn name(&self) -> &'static str {
+        "dataverse"
+    }
+    fn record_id(&self) -> String {
+        self.id.clone()
+    }
     fn root_url(&self) -> Url {
         // "https://datavers.example/api/files/:persistentId/versions/:latest-poblished/?persistentId=doi:10.7910/DVN/KBHLOD/DHJ45U"
         // Safe to unwrap:
@@ -295,31 +573,7 @@ impl DatasetBackend for DataverseFile {
     }
 
     async fn list(&self, client: &Client, dir: DirMeta) -> Result<Vec<Entry>, Exn<RepoError>> {
-        let resp = client
-            .get(dir.api_url().clone())
-            .send()
-            .await
-            .or_raise(|| RepoError {
-                message: format!("fail at client sent GET {}", dir.api_url()),
-            })?;
-        let resp = resp.error_for_status().map_err(|err| match err.status() {
-            Some(StatusCode::NOT_FOUND) => RepoError {
-                message: format!("resource not found when GET {}", dir.api_url()),
-            },
-            Some(status_code) => RepoError {
-                message: format!(
-                    "fail GET {}, with state code: {}",
-                    dir.api_url(),
-                    status_code.as_str()
-                ),
-            },
-            None => RepoError {
-                message: format!("fail GET {}, network / protocol error", dir.api_url(),),
-            },
-        })?;
-        let resp: JsonValue = resp.json().await.or_raise(|| RepoError {
-            message: format!("fail GET {}, unable to convert to json", dir.api_url(),),
-        })?;
+        let resp = get_json(client, &dir.api_url()).await?;
 
         let filej = resp.get("data").ok_or_else(|| RepoError {
             message: "field with key 'data' not resolve to an json value".to_string(),
@@ -347,19 +601,8 @@ impl DatasetBackend for DataverseFile {
         let mime_type = mime::Mime::from_str(&mime_type).or_raise(|| RepoError {
             message: format!("fail to parse the '{}' to proper mime type", mime_type),
         })?;
-        let download_url = dir
-            .api_url()
-            .join("/api/access/datafile/")
-            .or_raise(|| RepoError {
-                message: "cannot parse download base url".to_string(),
-            })?;
-        let download_url = download_url.join(&format!("{id}")).or_raise(|| RepoError {
-            message: format!("cannot parse '{download_url}' download url"),
-        })?;
-        let hash: String = json_extract(filej, "dataFile.md5").or_raise(|| RepoError {
-            message: "fail to extracting 'dataFile.md5' as String from json".to_string(),
-        })?;
-        let checksum = Checksum::Md5(hash);
+        let download_url = datafile_url(&self.base_url, id)?;
+        let checksum = checksum_of(filej, &name)?;
         let endpoint = Endpoint {
             parent_url: dir.api_url().clone(),
             key: Some("data".to_string()),
@@ -383,7 +626,279 @@ impl DatasetBackend for DataverseFile {
         Ok(entries)
     }
 
+    async fn raw_metadata(&self, client: &Client) -> Result<JsonValue, Exn<RepoError>> {
+        get_json(client, &self.root_url()).await
+    }
+
+    async fn metadata(&self, client: &Client) -> Result<DatasetMetadata, Exn<RepoError>> {
+        // the file version json has the file description, the citation metadata is on the
+        // dataset
+        let json = self.raw_metadata(client).await?;
+        Ok(DatasetMetadata {
+            title: json_str(&json, "/data/label"),
+            description: json_str(&json, "/data/description"),
+            publication_date: json_str(&json, "/data/dataFile/publicationDate"),
+            doi: doi_of(json_str(&json, "/data/dataFile/persistentId")),
+            ..DatasetMetadata::default()
+        })
+    }
+
+    async fn with_version(
+        &self,
+        _client: &Client,
+        version: &str,
+    ) -> Result<Arc<dyn DatasetBackend>, Exn<RepoError>> {
+        Ok(Arc::new(DataverseFile::new(
+            &self.id,
+            &self.base_url,
+            &version_name(version),
+        )))
+    }
+
+    fn auth_headers(&self, auth: &AuthConfig) -> HeaderMap {
+        auth.dataverse
+            .as_deref()
+            .map_or_else(HeaderMap::new, |token| {
+                auth_header(HeaderName::from_static("x-dataverse-key"), token)
+            })
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_dataverse_file_sha256_on_subpath() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/dv/api/files/:persistentId/versions/:latest-published"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "status": "OK",
+                "data": {
+                    "restricted": false,
+                    "dataFile": {
+                        "id": 42,
+                        "filename": "data.csv",
+                        "filesize": 3,
+                        "contentType": "text/csv",
+                        "checksum": {
+                            "type": "SHA-256",
+                            "value": "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+                        },
+                    },
+                },
+            })))
+            .mount(&server)
+            .await;
+
+        let base_url = Url::parse(&format!("{}/dv", server.uri())).unwrap();
+        let dataset = DataverseFile::new("doi:10.1234/X/ABC", &base_url, ":latest-published");
+        let entries = dataset
+            .list(&Client::new(), DirMeta::new_root(&dataset.root_url()))
+            .await
+            .unwrap();
+        let Entry::File(file) = &entries[0] else {
+            panic!("expect a file")
+        };
+        assert!(matches!(file.checksum(), [Checksum::Sha256(_)]));
+        assert_eq!(
+            file.download_url().as_str(),
+            format!("{}/dv/api/access/datafile/42", server.uri())
+        );
+    }
+
+    #[test]
+    fn test_dataverse_shapefile_bundle() {
+        let file = |id: u64, name: &str, original: Option<&str>| {
+            let mut filej = serde_json::json!({
+                "restricted": false,
+                "version": 1,
+                "directoryLabel": "gis",
+                "dataFile": {
+                    "id": id,
+                    "filename": name,
+                    "filesize": 10,
+                    "contentType": "application/octet-stream",
+                    "creationDate": "2024-01-01",
+                    "md5": "d41d8cd98f00b204e9800998ecf8427e",
+                },
+            });
+            if let Some(original) = original {
+                let data_file = filej["dataFile"].as_object_mut().unwrap();
+                data_file.insert("contentType".into(), "text/tab-separated-values".into());
+                data_file.insert("originalFileName".into(), original.into());
+                data_file.insert("originalFileFormat".into(), "application/dbf".into());
+                data_file.insert("originalFileSize".into(), 42.into());
+            }
+            filej
+        };
+        let json = serde_json::json!({ "data": { "files": [
+            file(1, "roads.shp", None),
+            file(2, "roads.shx", None),
+            file(3, "roads.tab", Some("roads.dbf")),
+            file(4, "rivers.prj", None),
+            file(5, "README.txt", None),
+        ]}});
+        let base_url = Url::parse("https://borealisdata.ca/").unwrap();
+        let root = DirMeta::new_root(&base_url);
+        let files = analyse_json(&json, &root, &base_url)
+            .unwrap()
+            .into_iter()
+            .map(|entry| match entry {
+                Entry::File(file) => file,
+                Entry::Dir(_) => panic!("expect a file"),
+            })
+            .collect::<Vec<_>>();
+
+        let bundle = |file: &FileMeta| {
+            file.bundle()
+                .iter()
+                .map(|p| p.relative().as_str().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(bundle(&files[0]), ["gis/roads.shx", "gis/roads.dbf"]);
+        assert_eq!(files[2].relative().as_str(), "gis/roads.dbf");
+        assert_eq!(files[2].size(), Some(42));
+        assert_eq!(
+            files[2].download_url().as_str(),
+            "https://borealisdata.ca/api/access/datafile/3?format=original"
+        );
+        // no '.shp', no bundle
+        assert!(files[3].bundle().is_empty());
+
+        let filter = crate::FileFilter::new(&["*.shp"], &[] as &[&str]).unwrap();
+        let selected = files
+            .iter()
+            .filter(|f| filter.is_match_file(f))
+            .map(|f| f.relative().as_str().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            selected,
+            ["gis/roads.shp", "gis/roads.shx", "gis/roads.dbf"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dataverse_access_requirements() {
+        let server = MockServer::start().await;
+        let file = |name: &str, restricted: bool| {
+            serde_json::json!({
+                "restricted": restricted,
+                "directoryLabel": "raw",
+                "dataFile": { "id": 1, "filename": name },
+            })
+        };
+        Mock::given(method("GET"))
+            .and(path(
+                "/api/datasets/:persistentId/versions/:latest-published",
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "status": "OK",
+                "data": {
+                    "termsOfAccess": "For non-commercial use only.",
+                    "files": [file("a.csv", false), file("b.csv", true)],
+                },
+            })))
+            .mount(&server)
+            .await;
+
+        let base_url = Url::parse(&server.uri()).unwrap();
+        let dataset = DataverseDataset::new("doi:10.1234/X/ABC", &base_url, ":latest-published");
+        let requirements = dataset
+            .access_requirements(&Client::new(), &AuthConfig::new())
+            .await
+            .unwrap();
+        let landing = Url::parse(&format!(
+            "{}/dataset.xhtml?persistentId=doi%3A10.1234%2FX%2FABC",
+            server.uri()
+        ))
+        .unwrap();
+        assert_eq!(
+            requirements,
+            [
+                AccessRequirement::Terms {
+                    text: "For non-commercial use only.".to_string(),
+                    url: Some(landing.clone()),
+                },
+                AccessRequirement::Request {
+                    url: landing,
+                    paths: vec![CrawlPath::root().join("raw/b.csv")],
+                },
+            ]
+        );
+
+        // access may have been granted to the owner of the token
+        let requirements = dataset
+            .access_requirements(&Client::new(), &AuthConfig::new().dataverse("token"))
+            .await
+            .unwrap();
+        assert!(requirements.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dataverse_raw_metadata() {
+        let server = MockServer::start().await;
+        let record = serde_json::json!({
+            "status": "OK",
+            "data": { "datasetPersistentId": "doi:10.7910/DVN/KBHLOD", "versionState": "RELEASED" },
+        });
+        Mock::given(method("GET"))
+            .and(path(
+                "/api/datasets/:persistentId/versions/:latest-published",
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&record))
+            .mount(&server)
+            .await;
+
+        let base_url = Url::parse(&server.uri()).unwrap();
+        let dataset =
+            DataverseDataset::new("doi:10.7910/DVN/KBHLOD", &base_url, ":latest-published");
+        let raw = dataset.raw_metadata(&Client::new()).await.unwrap();
+        assert_eq!(raw, record);
+    }
+
+    #[test]
+    fn test_dataverse_metadata_from_json() {
+        let json = serde_json::json!({
+            "status": "OK",
+            "data": {
+                "datasetPersistentId": "doi:10.7910/DVN/KBHLOD",
+                "versionNumber": 2,
+                "versionMinorNumber": 1,
+                "publicationDate": "2020-03-02",
+                "license": { "name": "CC0 1.0", "uri": "http://creativecommons.org/publicdomain/zero/1.0" },
+                "metadataBlocks": { "citation": { "fields": [
+                    { "typeName": "title", "value": "Replication Data" },
+                    { "typeName": "author", "value": [
+                        { "authorName": { "typeName": "authorName", "value": "Doe, Jane" } },
+                    ] },
+                    { "typeName": "dsDescription", "value": [
+                        { "dsDescriptionValue": { "typeName": "dsDescriptionValue", "value": "Survey data." } },
+                    ] },
+                ] } },
+            },
+        });
+        let metadata = metadata_from_json(&json);
+        assert_eq!(
+            metadata,
+            DatasetMetadata {
+                title: Some("Replication Data".to_string()),
+                description: Some("Survey data.".to_string()),
+                creators: vec!["Doe, Jane".to_string()],
+                license: Some("CC0 1.0".to_string()),
+                publication_date: Some("2020-03-02".to_string()),
+                version: Some("2.1".to_string()),
+                doi: Some("10.7910/DVN/KBHLOD".to_string()),
+            }
+        );
+    }
+}
@@ -5,21 +5,63 @@ use exn::{Exn, ResultExt};
 use serde_json::Value as JsonValue;
 use url::Url;
 
-use reqwest::{Client, StatusCode};
-use std::{any::Any, str::FromStr};
+use reqwest::{header::HeaderMap, Client};
+use std::{any::Any, str::FromStr, sync::Arc};
+use tracing::warn;
 
+use crate::auth::bearer;
 use crate::helper::json_extract;
+use crate::http::get_json;
+use crate::metadata::{json_str, normalize_doi};
 use crate::{
     repo::{Endpoint, FileMeta, RepoError},
-    Checksum, DatasetBackend, DirMeta, Entry,
+    AccessRequirement, AuthConfig, Checksum, DatasetBackend, DatasetMetadata, DirMeta, Entry,
 };
 
+// Dataset json at https://datadryad.org/api/v2/datasets/<doi>
+fn metadata_from_json(json: &JsonValue) -> DatasetMetadata {
+    let creators = json
+        .get("authors")
+        .and_then(JsonValue::as_array)
+        .map(|authors| {
+            authors
+                .iter()
+                .filter_map(|author| {
+                    let name = [
+                        json_str(author, "/firstName"),
+                        json_str(author, "/lastName"),
+                    ]
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                    (!name.is_empty()).then_some(name)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    DatasetMetadata {
+        title: json_str(json, "/title"),
+        description: json_str(json, "/abstract"),
+        creators,
+        license: json_str(json, "/license"),
+        publication_date: json_str(json, "/publicationDate"),
+        version: json
+            .get("versionNumber")
+            .and_then(JsonValue::as_u64)
+            .map(|v| v.to_string()),
+        doi: json_str(json, "/identifier").map(|doi| normalize_doi(&doi)),
+    }
+}
+
 // https://datadryad.org/
 // API root url at https://datadryad.org/api/v2
 #[derive(Debug)]
 pub struct DataDryad {
     pub id: String,
     base_url: Url,
+    /// `versionNumber` of the version to download, the latest if `None`.
+    version: Option<u64>,
 }
 
 impl DataDryad {
@@ -28,97 +70,51 @@ impl DataDryad {
         DataDryad {
             id: id.into(),
             base_url: base_url.clone(),
+            version: None,
         }
     }
-}
-
-#[allow(clippy::too_many_lines)]
-#[async_trait]
-impl DatasetBackend for DataDryad {
-    fn root_url(&self) -> Url {
-        // https://datadryad.org/api/v2/datasets/<id> to start for every dateset entry
 
-        // Safe to unwrap:
-        // - the base URL is a hard-coded, valid absolute URL
-        // - `path_segments_mut` cannot fail for this URL scheme
-        let mut url = Url::from_str("https://datadryad.org/api/v2/datasets").unwrap();
-        url.path_segments_mut().unwrap().extend([&self.id]);
-        url
-    }
-
-    async fn list(&self, client: &Client, dir: DirMeta) -> Result<Vec<Entry>, Exn<RepoError>> {
-        let resp = client
-            .get(dir.api_url().clone())
-            .send()
-            .await
-            .or_raise(|| RepoError {
-                message: format!("fail at client sent GET {}", dir.api_url()),
-            })?;
-        let resp = resp.error_for_status().map_err(|err| match err.status() {
-            Some(StatusCode::NOT_FOUND) => RepoError {
-                message: format!("resource not found when GET {}", dir.api_url()),
-            },
-            Some(status_code) => RepoError {
-                message: format!(
-                    "fail GET {}, with state code: {}",
-                    dir.api_url(),
-                    status_code.as_str()
-                ),
-            },
-            None => RepoError {
-                message: format!("fail GET {}, network / protocol error", dir.api_url(),),
-            },
-        })?;
-        let resp: JsonValue = resp.json().await.or_raise(|| RepoError {
-            message: format!("fail GET {}, unable to convert to json", dir.api_url(),),
-        })?;
-
-        // get link to the api of latest version of dataset
-        let version: String =
-            json_extract(&resp, "_links.stash:version.href").or_raise(|| RepoError {
-                message: "fail to extract '_links.stash:version.href' as string from json"
-                    .to_string(),
-            })?;
-
-        // second http GET call to get files
-        // safe to unwrap: because base_url is from url.
-        let mut files_api_url = self.base_url.join(&version).or_raise(|| RepoError {
-            message: format!(
-                "cannot join version '{}' to base url '{}'",
-                version,
-                self.base_url.as_str()
-            ),
-        })?;
-        files_api_url
+    // href of the api of the selected version, listed at
+    // https://datadryad.org/api/v2/datasets/<doi>/versions
+    async fn version_href(&self, client: &Client, version: u64) -> Result<String, Exn<RepoError>> {
+        let mut versions_url = self.root_url();
+        versions_url
             .path_segments_mut()
             .expect("url cannot be base")
-            .extend(["files"]);
-        let resp = client
-            .get(files_api_url.clone())
-            .send()
-            .await
-            .or_raise(|| RepoError {
-                message: format!("fail at client sent GET {files_api_url}"),
-            })?;
-        let resp = resp.error_for_status().map_err(|err| match err.status() {
-            Some(StatusCode::NOT_FOUND) => RepoError {
-                message: format!("resource not found when GET {files_api_url}"),
-            },
-            Some(status_code) => RepoError {
+            .push("versions");
+        let resp = get_json(client, &versions_url).await?;
+        let versions = resp
+            .pointer("/_embedded/stash:versions")
+            .and_then(JsonValue::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let href = versions
+            .iter()
+            .find(|v| v.get("versionNumber").and_then(JsonValue::as_u64) == Some(version))
+            .and_then(|v| json_str(v, "/_links/self/href"));
+        href.ok_or_else(|| {
+            let numbers = versions
+                .iter()
+                .filter_map(|v| v.get("versionNumber").and_then(JsonValue::as_u64))
+                .map(|n| n.to_string())
+                .collect::<Vec<_>>();
+            Exn::new(RepoError {
                 message: format!(
-                    "fail GET {}, with state code: {}",
-                    dir.api_url(),
-                    status_code.as_str()
+                    "dataset {} has no version {version}, its versions are [{}]",
+                    self.id,
+                    numbers.join(", ")
                 ),
-            },
-            None => RepoError {
-                message: format!("fail GET {files_api_url}, network / protocol error"),
-            },
-        })?;
-        let resp: JsonValue = resp.json().await.or_raise(|| RepoError {
-            message: format!("fail GET {files_api_url}, unable to convert to json"),
-        })?;
+            })
+        })
+    }
 
+    // Files of a page of https://datadryad.org/api/v2/versions/<id>/files
+    fn analyse_files(
+        &self,
+        resp: &JsonValue,
+        dir: &DirMeta,
+        files_api_url: &Url,
+    ) -> Result<Vec<Entry>, Exn<RepoError>> {
         let files = resp
             .get("_embedded")
             .and_then(|d| d.get("stash:files"))
@@ -162,26 +158,30 @@ impl DatasetBackend for DataDryad {
             let hash_type: String = json_extract(filej, "digestType").or_raise(|| RepoError {
                 message: "fail to extracting 'digestType' as String from json".to_string(),
             })?;
-            let checksum = if hash_type.to_lowercase() == "md5" {
-                let hash: String = json_extract(filej, "digest").or_raise(|| RepoError {
-                    message:
-                        "fail to extracting 'attributes.extra.hashes.sha256' as String from json"
-                            .to_string(),
-                })?;
-                Checksum::Md5(hash)
+            let hash: String = json_extract(filej, "digest").or_raise(|| RepoError {
+                message: "fail to extracting 'digest' as String from json".to_string(),
+            })?;
+            // files with a digest we can't compute are downloaded unverified
+            let checksum = if Checksum::is_supported(&hash_type) {
+                vec![Checksum::parse(&hash_type, &hash).or_raise(|| RepoError {
+                    message: format!("invalid checksum of '{name}'"),
+                })?]
             } else {
-                exn::bail!(RepoError {
-                    message: format!("unsupported hash type, '{hash_type}'")
-                })
+                warn!("unsupported checksum type '{hash_type}' of '{name}', not validated");
+                vec![]
             };
+            // file id as in '/api/v2/files/<id>'
+            let file_id: Option<String> = json_extract(filej, "_links.self.href")
+                .ok()
+                .and_then(|href: String| href.rsplit('/').next().map(ToString::to_string));
             let file = FileMeta::new(
                 None,
-                None,
+                file_id,
                 dir.join(&name),
                 endpoint,
                 download_url,
                 Some(size),
-                vec![checksum],
+                checksum,
                 Some(mime_type),
                 None,
                 None,
@@ -190,10 +190,133 @@ impl DatasetBackend for DataDryad {
             );
             entries.push(Entry::File(file));
         }
+        Ok(entries)
+    }
+}
+
+#[allow(clippy::too_many_lines)]
+#[async_trait]
+impl DatasetBackend for DataDryad {
+    fn name(&self) -> &'static str {
+        "dryad"
+    }
+    fn record_id(&self) -> String {
+        self.id.clone()
+    }
+    fn root_url(&self) -> Url {
+        // https://datadryad.org/api/v2/datasets/<id> to start for every dateset entry
 
+        // Safe to unwrap:
+        // - the base URL is a hard-coded, valid absolute URL
+        // - `path_segments_mut` cannot fail for this URL scheme
+        let mut url = Url::from_str("https://datadryad.org/api/v2/datasets").unwrap();
+        url.path_segments_mut().unwrap().extend([&self.id]);
+        url
+    }
+
+    async fn list(&self, client: &Client, dir: DirMeta) -> Result<Vec<Entry>, Exn<RepoError>> {
+        // get link to the api of the selected or latest version of dataset
+        let version: String = if let Some(version) = self.version {
+            self.version_href(client, version).await?
+        } else {
+            let resp = get_json(client, &dir.api_url()).await?;
+            json_extract(&resp, "_links.stash:version.href").or_raise(|| RepoError {
+                message: "fail to extract '_links.stash:version.href' as string from json"
+                    .to_string(),
+            })?
+        };
+
+        // second http GET call to get files
+        // safe to unwrap: because base_url is from url.
+        let mut files_api_url = self.base_url.join(&version).or_raise(|| RepoError {
+            message: format!(
+                "cannot join version '{}' to base url '{}'",
+                version,
+                self.base_url.as_str()
+            ),
+        })?;
+        files_api_url
+            .path_segments_mut()
+            .expect("url cannot be base")
+            .extend(["files"]);
+
+        // the files are listed in pages linked by '_links.next.href'
+        let mut entries = Vec::new();
+        let mut page_url = Some(files_api_url);
+        while let Some(files_api_url) = page_url.take() {
+            let resp = get_json(client, &files_api_url).await?;
+            entries.extend(self.analyse_files(&resp, &dir, &files_api_url)?);
+            if let Some(next) = json_str(&resp, "/_links/next/href") {
+                let next = self.base_url.join(&next).or_raise(|| RepoError {
+                    message: format!("invalid next page '{next}' of {files_api_url}"),
+                })?;
+                // a page linking to itself would never end
+                if next != files_api_url {
+                    page_url = Some(next);
+                }
+            }
+        }
         Ok(entries)
     }
 
+    fn archive_url(&self) -> Option<Url> {
+        // https://datadryad.org/api/v2/datasets/<doi>/download, the zip of the latest version
+        if self.version.is_some() {
+            return None;
+        }
+        let mut url = self.base_url.clone();
+        url.path_segments_mut()
+            .ok()?
+            .pop_if_empty()
+            .extend(["api", "v2", "datasets", &self.id, "download"]);
+        Some(url)
+    }
+
+    async fn raw_metadata(&self, client: &Client) -> Result<JsonValue, Exn<RepoError>> {
+        get_json(client, &self.root_url()).await
+    }
+
+    async fn metadata(&self, client: &Client) -> Result<DatasetMetadata, Exn<RepoError>> {
+        let json = self.raw_metadata(client).await?;
+        Ok(metadata_from_json(&json))
+    }
+
+    async fn access_requirements(
+        &self,
+        client: &Client,
+        _auth: &AuthConfig,
+    ) -> Result<Vec<AccessRequirement>, Exn<RepoError>> {
+        // 'curationStatus' is "Embargoed" until the 'publicationDate' of the dataset
+        let json = self.raw_metadata(client).await?;
+        let embargoed = json_str(&json, "/curationStatus")
+            .is_some_and(|status| status.to_lowercase().contains("embargo"));
+        if !embargoed {
+            return Ok(Vec::new());
+        }
+        Ok(vec![AccessRequirement::Embargo {
+            until: json_str(&json, "/publicationDate"),
+        }])
+    }
+
+    async fn with_version(
+        &self,
+        _client: &Client,
+        version: &str,
+    ) -> Result<Arc<dyn DatasetBackend>, Exn<RepoError>> {
+        let number = version.parse::<u64>().or_raise(|| RepoError {
+            message: format!("dryad versions are numbers, got '{version}'"),
+        })?;
+        Ok(Arc::new(DataDryad {
+            id: self.id.clone(),
+            base_url: self.base_url.clone(),
+            version: Some(number),
+        }))
+    }
+
+    fn auth_headers(&self, auth: &AuthConfig) -> HeaderMap {
+        bearer(auth.dryad.as_deref())
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -5,19 +5,39 @@ use exn::{Exn, ResultExt};
 use serde_json::Value as JsonValue;
 use url::Url;
 
-use reqwest::{Client, StatusCode};
-use std::{any::Any, str::FromStr};
+use reqwest::{
+    header::{HeaderMap, AUTHORIZATION},
+    Client, StatusCode,
+};
+use std::{any::Any, collections::HashMap, str::FromStr, sync::Mutex};
+use tracing::{debug, warn};
 
+use crate::auth::auth_header;
 use crate::helper::json_extract;
+use crate::http::{self, get_json};
+use crate::metadata::json_str;
+use crate::retry::RetryExt;
 use crate::{
-    repo::{Endpoint, FileMeta, RepoError},
-    DatasetBackend, DirMeta, Entry,
+    repo::{CrawlPath, Endpoint, FileMeta, RepoError},
+    AuthConfig, Checksum, DatasetBackend, DatasetMetadata, DirMeta, Entry,
 };
 
+/// GitHub `GraphQL` API endpoint, only usable with a token.
+pub const GITHUB_GRAPHQL_URL: &str = "https://api.github.com/graphql";
+
+// Number of tree levels fetched by one GraphQL query, the deeper levels are listed by the
+// next query on the directories at the last level.
+const GRAPHQL_TREE_DEPTH: usize = 4;
+
 pub struct GitHub {
     pub owner: String,
     pub repo: String,
     pub branch_or_commit: String,
+    /// `GraphQL` endpoint used to list the tree, `None` lists with the REST API.
+    pub graphql_url: Option<Url>,
+    // entries of the sub directories fetched ahead by a GraphQL query or a recursive REST
+    // listing, keyed by relative path
+    prefetched: Mutex<HashMap<String, Vec<Entry>>>,
 }
 
 impl GitHub {
@@ -31,110 +51,323 @@ impl GitHub {
             owner: owner.into(),
             repo: repo.into(),
             branch_or_commit: branch_or_commit.into(),
+            graphql_url: None,
+            prefetched: Mutex::new(HashMap::new()),
         }
     }
-}
-
-fn github_branch_or_commit_from_url(url: &Url) -> Option<String> {
-    let segments: Vec<&str> = url.path_segments()?.collect();
 
-    // GitHub tree URL format:
-    // ["repos", "owner", "repo", "git", "trees", "<branch_or_commit>"]
-    //https://api.github.com/repos/rs4rse/vizmat/git/trees/main?recursive=1
-    if segments.len() >= 6 && segments[3] == "git" && segments[4] == "trees" {
-        Some(segments[5].to_string())
-    } else {
-        None
+    /// Lists the tree with the `GraphQL` API, which fetches several levels of the tree per
+    /// request and so consumes far less of the rate limit than the REST API that needs one
+    /// request per directory. It requires a GitHub token, when its rate limit is exhausted
+    /// the listing falls back to the REST API.
+    #[must_use]
+    pub fn with_graphql(mut self) -> Self {
+        // Safe to unwrap: hard-coded, valid absolute URL
+        self.graphql_url = Some(Url::parse(GITHUB_GRAPHQL_URL).unwrap());
+        self
     }
-}
-
-#[async_trait]
-impl DatasetBackend for GitHub {
-    fn root_url(&self) -> Url {
-        // id for github repo is the commit hash or branch name
 
+    // REST url of a tree by its object id, used as `api_url` of the directories.
+    fn tree_url(&self, oid: &str) -> Url {
         // Safe to unwrap:
         // - the base URL is a hard-coded, valid absolute URL
         let mut url = Url::parse("https://api.github.com/repos").unwrap();
-        url.path_segments_mut().unwrap().extend([
-            &self.owner,
-            &self.repo,
-            "git",
-            "trees",
-            &self.branch_or_commit,
-        ]);
+        url.path_segments_mut()
+            .unwrap()
+            .extend([&self.owner, &self.repo, "git", "trees", oid]);
         url
     }
 
-    async fn list(&self, client: &Client, dir: DirMeta) -> Result<Vec<Entry>, Exn<RepoError>> {
-        let resp = client
-            .get(dir.api_url().clone())
-            .send()
-            .await
-            .map_err(|e| RepoError {
-                message: format!("HTTP GET failed: {e}"),
-            })?;
+    fn raw_url(&self, record_id: &str, path: &CrawlPath) -> Result<Url, Exn<RepoError>> {
+        let download_url = format!(
+            "https://raw.githubusercontent.com/{}/{}/{}/{}",
+            self.owner,
+            self.repo,
+            record_id,
+            path.relative()
+        );
+        Url::parse(&download_url).or_raise(|| RepoError {
+            message: format!("cannot parse '{download_url}' download url"),
+        })
+    }
+
+    // Tree at `api_url` and whether GitHub truncated it, which it does for trees of more
+    // than 100,000 entries or 7 MB.
+    async fn get_tree(
+        &self,
+        client: &Client,
+        api_url: &Url,
+    ) -> Result<(Vec<JsonValue>, bool), Exn<RepoError>> {
+        let resp = http::send_get(client, api_url).await?;
         // Check status code before calling `error_for_status`
         if resp.status() == StatusCode::FORBIDDEN {
-            exn::bail!(RepoError {
-                message: "GitHub API rate limit excceded. \
+            return Err(http::rate_limited(
+                &resp,
+                api_url,
+                "GitHub API rate limit excceded. \
                     You may need to provide a personal access token via the `GITHUB_TOKEN` environment variable \
-                ".to_string(),
+                "
+                .to_string(),
+            ));
+        }
+
+        let resp = http::error_for_status(resp, api_url)?;
+        let mut json = http::read_json(resp, api_url).await?;
+
+        let truncated = json
+            .get("truncated")
+            .and_then(JsonValue::as_bool)
+            .unwrap_or(false);
+        let Some(JsonValue::Array(tree)) = json.get_mut("tree").map(JsonValue::take) else {
+            exn::bail!(RepoError {
+                message: "No 'tree' field in GitHub API response".to_string(),
             });
+        };
+        Ok((tree, truncated))
+    }
+
+    async fn list_rest(&self, client: &Client, dir: DirMeta) -> Result<Vec<Entry>, Exn<RepoError>> {
+        let record_id = github_branch_or_commit_from_url(&dir.root_url())
+            .expect("can parse branch or commit from url");
+
+        // the whole tree is listed by a single recursive request, unless it is truncated
+        if dir.api_url() == dir.root_url() {
+            let mut api_url = dir.api_url();
+            api_url.query_pairs_mut().append_pair("recursive", "1");
+            let (tree, truncated) = self.get_tree(client, &api_url).await?;
+            if !truncated {
+                return self.analyse_recursive_tree(&tree, &dir, &api_url, &record_id);
+            }
+            warn!(
+                "GitHub truncated the tree of {}/{}, listing it directory by directory",
+                self.owner, self.repo
+            );
         }
 
-        let resp = resp.error_for_status().map_err(|e| RepoError {
-            message: format!("HTTP error GET {}: {}", dir.api_url(), e),
+        let api_url = dir.api_url();
+        let (tree, truncated) = self.get_tree(client, &api_url).await?;
+        if truncated {
+            exn::bail!(RepoError {
+                message: format!(
+                    "GitHub truncated the listing of '{}', a directory with more entries than \
+                     its API lists",
+                    dir.relative()
+                ),
+            });
+        }
+        let mut entries = Vec::with_capacity(tree.len());
+        for (i, filej) in tree.iter().enumerate() {
+            entries.extend(self.rest_entry(filej, i, &dir, &api_url, &record_id)?);
+        }
+        Ok(entries)
+    }
+
+    // Entries right below `dir` of a tree listed recursively, whose paths are relative to
+    // `dir`, the entries of the sub directories are put into `prefetched`.
+    fn analyse_recursive_tree(
+        &self,
+        tree: &[JsonValue],
+        dir: &DirMeta,
+        api_url: &Url,
+        record_id: &str,
+    ) -> Result<Vec<Entry>, Exn<RepoError>> {
+        let mut listed: HashMap<String, Vec<Entry>> = HashMap::new();
+        for (i, filej) in tree.iter().enumerate() {
+            let Some(entry) = self.rest_entry(filej, i, dir, api_url, record_id)? else {
+                continue;
+            };
+            let relative = match &entry {
+                Entry::File(file) => file.relative(),
+                Entry::Dir(subdir) => {
+                    // listed even if empty, so that it is not requested again
+                    listed
+                        .entry(subdir.relative().as_str().to_string())
+                        .or_default();
+                    subdir.relative()
+                }
+            };
+            let parent = relative
+                .as_str()
+                .rsplit_once('/')
+                .map_or("", |(parent, _)| parent)
+                .to_string();
+            listed.entry(parent).or_default().push(entry);
+        }
+        let entries = listed.remove(dir.relative().as_str()).unwrap_or_default();
+        self.prefetched
+            .lock()
+            .expect("prefetched lock not poisoned")
+            .extend(listed);
+        Ok(entries)
+    }
+
+    // Entry of an item of a REST tree listing of `dir`, `None` for submodules.
+    fn rest_entry(
+        &self,
+        filej: &JsonValue,
+        i: usize,
+        dir: &DirMeta,
+        api_url: &Url,
+        record_id: &str,
+    ) -> Result<Option<Entry>, Exn<RepoError>> {
+        let path: String = json_extract(filej, "path").or_raise(|| RepoError {
+            message: "Missing 'path' in tree entry".to_string(),
+        })?;
+        let kind: String = json_extract(filej, "type").or_raise(|| RepoError {
+            message: "Missing 'type' in tree entry".to_string(),
         })?;
 
+        match kind.as_ref() {
+            "blob" => {
+                let size: u64 = json_extract(filej, "size").unwrap_or(0);
+                let path = dir.join(&path);
+                let download_url = self.raw_url(record_id, &path)?;
+                let guess = mime_guess::from_path(&path);
+                let sha: Option<String> = json_extract(filej, "sha").ok();
+
+                let file = FileMeta::new(
+                    None,
+                    sha.clone(),
+                    path,
+                    Endpoint {
+                        parent_url: api_url.clone(),
+                        key: Some(format!("tree.{i}")),
+                    },
+                    download_url,
+                    Some(size),
+                    blob_checksum(sha.as_deref()),
+                    guess.first(),
+                    None,
+                    None,
+                    None,
+                    true,
+                );
+                Ok(Some(Entry::File(file)))
+            }
+            "tree" => {
+                let tree_url: String = json_extract(filej, "url").or_raise(|| RepoError {
+                    message: "Missing 'url' in tree entry".to_string(),
+                })?;
+                let tree_url = Url::from_str(&tree_url).or_raise(|| RepoError {
+                    message: format!("cannot parse '{tree_url}' api url"),
+                })?;
+                let dir = DirMeta::new(dir.join(&path), tree_url, dir.root_url());
+                Ok(Some(Entry::Dir(dir)))
+            }
+            // submodules are not part of the repository content
+            "commit" => Ok(None),
+            other => {
+                exn::bail!(RepoError {
+                    message: format!("Unknown tree type: {other}"),
+                });
+            }
+        }
+    }
+
+    /// Lists `dir` and prefetches its sub directories up to [`GRAPHQL_TREE_DEPTH`] levels,
+    /// returns `None` if the `GraphQL` rate limit is exhausted.
+    async fn list_graphql(
+        &self,
+        client: &Client,
+        dir: &DirMeta,
+        graphql_url: &Url,
+    ) -> Result<Option<Vec<Entry>>, Exn<RepoError>> {
+        let expression = format!("{}:{}", self.branch_or_commit, dir.relative());
+        let body = serde_json::json!({
+            "query": graphql_query(GRAPHQL_TREE_DEPTH),
+            "variables": {
+                "owner": self.owner,
+                "name": self.repo,
+                "expression": expression,
+            },
+        });
+        let resp = client
+            .post(graphql_url.clone())
+            .json(&body)
+            .send_with_retry()
+            .await
+            .map_err(|e| RepoError {
+                message: format!("HTTP POST failed: {e}"),
+            })?;
+        if matches!(
+            resp.status(),
+            StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS
+        ) {
+            return Ok(None);
+        }
+        let resp = resp.error_for_status().map_err(|e| RepoError {
+            message: format!("HTTP error POST {graphql_url}: {e}"),
+        })?;
         let json: JsonValue = resp.json().await.map_err(|e| RepoError {
-            message: format!("Failed to parse JSON from {}: {}", dir.api_url(), e),
+            message: format!("Failed to parse JSON from {graphql_url}: {e}"),
         })?;
 
+        if let Some(errors) = json.get("errors").and_then(JsonValue::as_array) {
+            if errors
+                .iter()
+                .any(|err| err.get("type").and_then(JsonValue::as_str) == Some("RATE_LIMITED"))
+            {
+                return Ok(None);
+            }
+            exn::bail!(RepoError {
+                message: format!("GitHub GraphQL query for '{expression}' failed: {errors:?}"),
+            });
+        }
+        if let Some(rate_limit) = json.pointer("/data/rateLimit") {
+            debug!("GitHub GraphQL rate limit: {rate_limit}");
+        }
+
         let tree = json
-            .get("tree")
+            .pointer("/data/repository/object/entries")
             .and_then(JsonValue::as_array)
             .ok_or_else(|| RepoError {
-                message: "No 'tree' field in GitHub API response".to_string(),
+                message: format!("'{expression}' is not a tree in GitHub GraphQL response"),
             })?;
 
-        let mut entries = Vec::with_capacity(tree.len());
+        let mut prefetched = HashMap::new();
+        let entries = self.analyse_tree(tree, dir, graphql_url, &mut prefetched)?;
+        self.prefetched
+            .lock()
+            .expect("prefetched lock not poisoned")
+            .extend(prefetched);
+        Ok(Some(entries))
+    }
 
-        for (i, filej) in tree.iter().enumerate() {
-            let path: String = json_extract(filej, "path").or_raise(|| RepoError {
-                message: "Missing 'path' in tree entry".to_string(),
+    // Entries right below `dir`, the entries of the sub directories fetched by the same query
+    // are put into `prefetched`.
+    fn analyse_tree(
+        &self,
+        tree: &[JsonValue],
+        dir: &DirMeta,
+        graphql_url: &Url,
+        prefetched: &mut HashMap<String, Vec<Entry>>,
+    ) -> Result<Vec<Entry>, Exn<RepoError>> {
+        let mut entries = Vec::with_capacity(tree.len());
+        for filej in tree {
+            let name: String = json_extract(filej, "name").or_raise(|| RepoError {
+                message: "Missing 'name' in tree entry".to_string(),
             })?;
             let kind: String = json_extract(filej, "type").or_raise(|| RepoError {
                 message: "Missing 'type' in tree entry".to_string(),
             })?;
-
-            let record_id = github_branch_or_commit_from_url(&dir.root_url())
-                .expect("can parse branch or commit from url");
+            let path = dir.join(&name);
             match kind.as_ref() {
                 "blob" => {
-                    let size: u64 = json_extract(filej, "size").unwrap_or(0);
-                    let path = dir.join(&path);
-                    let download_url = format!(
-                        "https://raw.githubusercontent.com/{}/{}/{}/{}",
-                        self.owner,
-                        self.repo,
-                        record_id,
-                        path.relative()
-                    );
-                    let download_url = Url::parse(&download_url).unwrap();
+                    let size: u64 = json_extract(filej, "object.byteSize").unwrap_or(0);
+                    let oid: Option<String> = json_extract(filej, "object.oid").ok();
+                    let download_url = self.raw_url(&self.branch_or_commit, &path)?;
                     let guess = mime_guess::from_path(&path);
-
                     let file = FileMeta::new(
                         None,
-                        None,
-                        path,
+                        oid.clone(),
+                        path.clone(),
                         Endpoint {
-                            parent_url: dir.api_url().clone(),
-                            key: Some(format!("tree.{i}")),
+                            parent_url: graphql_url.clone(),
+                            key: Some(path.relative().as_str().to_string()),
                         },
                         download_url,
                         Some(size),
-                        vec![],
+                        blob_checksum(oid.as_deref()),
                         guess.first(),
                         None,
                         None,
@@ -144,15 +377,22 @@ impl DatasetBackend for GitHub {
                     entries.push(Entry::File(file));
                 }
                 "tree" => {
-                    let tree_url: String = json_extract(filej, "url").or_raise(|| RepoError {
-                        message: "Missing 'url' in tree entry".to_string(),
-                    })?;
-                    let tree_url = Url::from_str(&tree_url).or_raise(|| RepoError {
-                        message: format!("cannot parse '{tree_url}' api url"),
+                    let oid: String = json_extract(filej, "object.oid").or_raise(|| RepoError {
+                        message: "Missing 'object.oid' in tree entry".to_string(),
                     })?;
-                    let dir = DirMeta::new(dir.join(&path), tree_url, dir.root_url());
-                    entries.push(Entry::Dir(dir));
+                    let subdir = DirMeta::new(path, self.tree_url(&oid), dir.root_url());
+                    if let Some(subtree) = filej
+                        .pointer("/object/entries")
+                        .and_then(JsonValue::as_array)
+                    {
+                        let subentries =
+                            self.analyse_tree(subtree, &subdir, graphql_url, prefetched)?;
+                        prefetched.insert(subdir.relative().as_str().to_string(), subentries);
+                    }
+                    entries.push(Entry::Dir(subdir));
                 }
+                // submodules are not part of the repository content
+                "commit" => {}
                 other => {
                     exn::bail!(RepoError {
                         message: format!("Unknown tree type: {other}"),
@@ -160,11 +400,486 @@ impl DatasetBackend for GitHub {
                 }
             }
         }
+        Ok(entries)
+    }
+}
+
+// Checksum of a blob from its git object id, raw.githubusercontent.com serves the blob as is,
+// the pointer of files in Git LFS included.
+fn blob_checksum(oid: Option<&str>) -> Vec<Checksum> {
+    oid.and_then(|oid| Checksum::parse("gitsha1", oid).ok())
+        .into_iter()
+        .collect()
+}
 
+// Tree entries with the sub trees nested `depth` levels deep.
+fn graphql_entries(depth: usize) -> String {
+    let subtree = if depth > 1 {
+        graphql_entries(depth - 1)
+    } else {
+        String::new()
+    };
+    format!(
+        "entries {{ name type object {{ ... on Blob {{ oid byteSize }} ... on Tree {{ oid {subtree} }} }} }}"
+    )
+}
+
+fn graphql_query(depth: usize) -> String {
+    format!(
+        "query($owner: String!, $name: String!, $expression: String!) {{ \
+            rateLimit {{ cost remaining resetAt }} \
+            repository(owner: $owner, name: $name) {{ \
+                object(expression: $expression) {{ ... on Tree {{ {} }} }} \
+            }} \
+        }}",
+        graphql_entries(depth)
+    )
+}
+
+fn github_branch_or_commit_from_url(url: &Url) -> Option<String> {
+    let segments: Vec<&str> = url.path_segments()?.collect();
+
+    // GitHub tree URL format:
+    // ["repos", "owner", "repo", "git", "trees", "<branch_or_commit>"]
+    //https://api.github.com/repos/rs4rse/vizmat/git/trees/main?recursive=1
+    if segments.len() >= 6 && segments[3] == "git" && segments[4] == "trees" {
+        Some(segments[5].to_string())
+    } else {
+        None
+    }
+}
+
+#[async_trait]
+impl DatasetBackend for GitHub {
+    fn name(&self) -> &'static str {
+        "github"
+    }
+    fn record_id(&self) -> String {
+        format!("{}/{}", self.owner, self.repo)
+    }
+    fn root_url(&self) -> Url {
+        // id for github repo is the commit hash or branch name
+
+        // Safe to unwrap:
+        // - the base URL is a hard-coded, valid absolute URL
+        let mut url = Url::parse("https://api.github.com/repos").unwrap();
+        url.path_segments_mut().unwrap().extend([
+            &self.owner,
+            &self.repo,
+            "git",
+            "trees",
+            &self.branch_or_commit,
+        ]);
+        url
+    }
+
+    async fn list(&self, client: &Client, dir: DirMeta) -> Result<Vec<Entry>, Exn<RepoError>> {
+        let prefetched = self
+            .prefetched
+            .lock()
+            .expect("prefetched lock not poisoned")
+            .remove(dir.relative().as_str());
+        if let Some(entries) = prefetched {
+            return Ok(entries);
+        }
+        let Some(graphql_url) = &self.graphql_url else {
+            return self.list_rest(client, dir).await;
+        };
+        if let Some(entries) = self.list_graphql(client, &dir, graphql_url).await? {
+            return Ok(entries);
+        }
+        warn!("GitHub GraphQL rate limit exceeded, listing '{dir}' with the REST API");
+        self.list_rest(client, dir).await
+    }
+
+    async fn raw_metadata(&self, client: &Client) -> Result<JsonValue, Exn<RepoError>> {
+        // https://api.github.com/repos/<owner>/<repo>
+        // Safe to unwrap:
+        // - the base URL is a hard-coded, valid absolute URL
+        let mut url = Url::parse("https://api.github.com/repos").unwrap();
+        url.path_segments_mut()
+            .unwrap()
+            .extend([&self.owner, &self.repo]);
+        get_json(client, &url).await
+    }
+
+    async fn metadata(&self, client: &Client) -> Result<DatasetMetadata, Exn<RepoError>> {
+        let json = self.raw_metadata(client).await?;
+        Ok(DatasetMetadata {
+            title: json_str(&json, "/full_name"),
+            description: json_str(&json, "/description"),
+            creators: json_str(&json, "/owner/login").into_iter().collect(),
+            // 'NOASSERTION' for a license file GitHub can not identify
+            license: json_str(&json, "/license/spdx_id").filter(|id| id != "NOASSERTION"),
+            publication_date: json_str(&json, "/created_at"),
+            version: Some(self.branch_or_commit.clone()),
+            doi: None,
+        })
+    }
+
+    fn auth_headers(&self, auth: &AuthConfig) -> HeaderMap {
+        auth.github.as_deref().map_or_else(HeaderMap::new, |token| {
+            auth_header(AUTHORIZATION, &format!("token {token}"))
+        })
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// Asset json of a release, 'digest' is only listed for assets uploaded since mid 2025.
+fn analyse_release(json: &JsonValue, dir: &DirMeta) -> Result<Vec<Entry>, Exn<RepoError>> {
+    let assets = json
+        .get("assets")
+        .and_then(JsonValue::as_array)
+        .ok_or_else(|| RepoError {
+            message: "No 'assets' field in GitHub release".to_string(),
+        })?;
+    let mut entries = Vec::with_capacity(assets.len() + 1);
+    for (i, asset) in assets.iter().enumerate() {
+        let name: String = json_extract(asset, "name").or_raise(|| RepoError {
+            message: "Missing 'name' in release asset".to_string(),
+        })?;
+        let download_url: String =
+            json_extract(asset, "browser_download_url").or_raise(|| RepoError {
+                message: format!("Missing 'browser_download_url' of release asset '{name}'"),
+            })?;
+        let download_url = Url::from_str(&download_url).or_raise(|| RepoError {
+            message: format!("cannot parse '{download_url}' download url"),
+        })?;
+        let checksum = match json_str(asset, "/digest")
+            .as_deref()
+            .map(|d| d.split_once(':'))
+        {
+            Some(Some((algorithm, hash))) if Checksum::is_supported(algorithm) => {
+                vec![Checksum::parse(algorithm, hash).or_raise(|| RepoError {
+                    message: format!("invalid checksum of '{name}'"),
+                })?]
+            }
+            _ => vec![],
+        };
+        let mimetype = json_str(asset, "/content_type")
+            .and_then(|mime| mime.parse().ok())
+            .or_else(|| mime_guess::from_path(&name).first());
+        let file = FileMeta::new(
+            None,
+            json_extract::<u64>(asset, "id")
+                .ok()
+                .map(|id| id.to_string()),
+            dir.join(&name),
+            Endpoint {
+                parent_url: dir.api_url(),
+                key: Some(format!("assets.{i}")),
+            },
+            download_url,
+            asset.get("size").and_then(JsonValue::as_u64),
+            checksum,
+            mimetype,
+            None,
+            json_str(asset, "/created_at"),
+            json_str(asset, "/updated_at"),
+            true,
+        );
+        entries.push(Entry::File(file));
+    }
+    Ok(entries)
+}
+
+/// A release of a GitHub repository, its assets and the source tarball of its tag, e.g.
+/// `https://github.com/<owner>/<repo>/releases/tag/v1.0`.
+///
+/// Research software is cited by release, whose assets often hold built artifacts or data
+/// that are not in the git tree.
+#[derive(Debug)]
+pub struct GitHubRelease {
+    pub owner: String,
+    pub repo: String,
+    /// Tag of the release, `None` for the latest release.
+    pub tag: Option<String>,
+}
+
+impl GitHubRelease {
+    #[must_use]
+    pub fn new(owner: impl Into<String>, repo: impl Into<String>, tag: Option<String>) -> Self {
+        GitHubRelease {
+            owner: owner.into(),
+            repo: repo.into(),
+            tag,
+        }
+    }
+
+    // Source tarball of the tag as GitHub generates it, listed without size nor checksum.
+    fn source_tarball(&self, tag: &str, dir: &DirMeta) -> Result<FileMeta, Exn<RepoError>> {
+        // Safe to unwrap:
+        // - the base URL is a hard-coded, valid absolute URL
+        let mut download_url = Url::parse("https://github.com").unwrap();
+        download_url.path_segments_mut().unwrap().extend([
+            &self.owner,
+            &self.repo,
+            "archive",
+            "refs",
+            "tags",
+            &format!("{tag}.tar.gz"),
+        ]);
+        let name = format!("{}-{}.tar.gz", self.repo, tag.replace('/', "-"));
+        Ok(FileMeta::new(
+            None,
+            None,
+            dir.join(&name),
+            Endpoint {
+                parent_url: dir.api_url(),
+                key: Some("tarball_url".to_string()),
+            },
+            download_url,
+            None,
+            vec![],
+            mime_guess::from_path(&name).first(),
+            Some(tag.to_string()),
+            None,
+            None,
+            true,
+        ))
+    }
+
+    async fn release_json(&self, client: &Client, url: &Url) -> Result<JsonValue, Exn<RepoError>> {
+        let resp = http::send_get(client, url).await?;
+        if resp.status() == StatusCode::FORBIDDEN {
+            return Err(http::rate_limited(
+                &resp,
+                url,
+                "GitHub API rate limit exceeded, you may need to provide a personal access \
+                 token via the `GITHUB_TOKEN` environment variable"
+                    .to_string(),
+            ));
+        }
+        let resp = http::error_for_status(resp, url)?;
+        http::read_json(resp, url).await
+    }
+}
+
+#[async_trait]
+impl DatasetBackend for GitHubRelease {
+    fn name(&self) -> &'static str {
+        "github"
+    }
+    fn record_id(&self) -> String {
+        format!(
+            "{}/{}@{}",
+            self.owner,
+            self.repo,
+            self.tag.as_deref().unwrap_or("latest")
+        )
+    }
+    fn root_url(&self) -> Url {
+        // https://api.github.com/repos/<owner>/<repo>/releases/tags/<tag>
+        // https://api.github.com/repos/<owner>/<repo>/releases/latest
+
+        // Safe to unwrap:
+        // - the base URL is a hard-coded, valid absolute URL
+        let mut url = Url::parse("https://api.github.com/repos").unwrap();
+        {
+            let mut segments = url.path_segments_mut().unwrap();
+            segments.extend([&self.owner, &self.repo, "releases"]);
+            match &self.tag {
+                Some(tag) => segments.extend(["tags", tag]),
+                None => segments.push("latest"),
+            };
+        }
+        url
+    }
+
+    async fn list(&self, client: &Client, dir: DirMeta) -> Result<Vec<Entry>, Exn<RepoError>> {
+        let json = self.release_json(client, &dir.api_url()).await?;
+        let mut entries = analyse_release(&json, &dir)?;
+        let tag: String = json_extract(&json, "tag_name").or_raise(|| RepoError {
+            message: "Missing 'tag_name' in GitHub release".to_string(),
+        })?;
+        entries.push(Entry::File(self.source_tarball(&tag, &dir)?));
         Ok(entries)
     }
 
+    async fn raw_metadata(&self, client: &Client) -> Result<JsonValue, Exn<RepoError>> {
+        self.release_json(client, &self.root_url()).await
+    }
+
+    async fn metadata(&self, client: &Client) -> Result<DatasetMetadata, Exn<RepoError>> {
+        let json = self.raw_metadata(client).await?;
+        Ok(DatasetMetadata {
+            title: json_str(&json, "/name")
+                .filter(|name| !name.is_empty())
+                .or_else(|| Some(format!("{}/{}", self.owner, self.repo))),
+            description: json_str(&json, "/body"),
+            creators: json_str(&json, "/author/login").into_iter().collect(),
+            license: None,
+            publication_date: json_str(&json, "/published_at"),
+            version: json_str(&json, "/tag_name"),
+            doi: None,
+        })
+    }
+
+    fn auth_headers(&self, auth: &AuthConfig) -> HeaderMap {
+        auth.github.as_deref().map_or_else(HeaderMap::new, |token| {
+            auth_header(AUTHORIZATION, &format!("token {token}"))
+        })
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{body_partial_json, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn blob(name: &str, size: u64) -> JsonValue {
+        serde_json::json!({
+            "name": name,
+            "type": "blob",
+            "object": { "oid": format!("oid-{name}"), "byteSize": size },
+        })
+    }
+
+    #[tokio::test]
+    async fn test_github_graphql_list_prefetches_subtrees() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/graphql"))
+            .and(body_partial_json(serde_json::json!({
+                "variables": { "owner": "o", "name": "r", "expression": "main:" }
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": {
+                    "rateLimit": { "cost": 1, "remaining": 4999, "resetAt": "2026-01-01T00:00:00Z" },
+                    "repository": { "object": { "entries": [
+                        blob("README.md", 10),
+                        { "name": "data", "type": "tree", "object": { "oid": "abc", "entries": [
+                            blob("a.csv", 4),
+                            { "name": "deep", "type": "tree", "object": { "oid": "def" } },
+                        ] } },
+                        { "name": "vendored", "type": "commit", "object": {} },
+                    ] } }
+                }
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut github = GitHub::new("o", "r", "main");
+        github.graphql_url = Some(Url::parse(&format!("{}/graphql", mock_server.uri())).unwrap());
+        let client = Client::new();
+        let root = DirMeta::new_root(&github.root_url());
+
+        let entries = github.list(&client, root).await.unwrap();
+        assert_eq!(entries.len(), 2);
+        let Entry::File(file) = &entries[0] else {
+            panic!("expect a file")
+        };
+        assert_eq!(
+            file.download_url().as_str(),
+            "https://raw.githubusercontent.com/o/r/main/README.md"
+        );
+        assert_eq!(file.size(), Some(10));
+        assert_eq!(file.file_identifier(), Some("oid-README.md"));
+        let Entry::Dir(data) = &entries[1] else {
+            panic!("expect a dir")
+        };
+        assert_eq!(
+            data.api_url().as_str(),
+            "https://api.github.com/repos/o/r/git/trees/abc"
+        );
+
+        // served from the first query
+        let entries = github.list(&client, data.clone()).await.unwrap();
+        let names: Vec<_> = entries
+            .iter()
+            .map(|entry| match entry {
+                Entry::File(file) => file.relative().as_str().to_string(),
+                Entry::Dir(dir) => dir.relative().as_str().to_string(),
+            })
+            .collect();
+        assert_eq!(names, ["data/a.csv", "data/deep"]);
+    }
+
+    #[tokio::test]
+    async fn test_github_graphql_rate_limited_falls_back_to_rest() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/graphql"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "errors": [{ "type": "RATE_LIMITED", "message": "API rate limit exceeded" }]
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/repos/o/r/git/trees/main"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "tree": [{ "path": "README.md", "type": "blob", "size": 10 }]
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut github = GitHub::new("o", "r", "main");
+        github.graphql_url = Some(Url::parse(&format!("{}/graphql", mock_server.uri())).unwrap());
+        let root_url =
+            Url::parse(&format!("{}/repos/o/r/git/trees/main", mock_server.uri())).unwrap();
+        let entries = github
+            .list(&Client::new(), DirMeta::new_root(&root_url))
+            .await
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_github_release_lists_assets_and_tarball() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/repos/o/r/releases/tags/v1.0"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "tag_name": "v1.0",
+                "assets": [{
+                    "id": 42,
+                    "name": "data.zip",
+                    "size": 1024,
+                    "content_type": "application/zip",
+                    "digest": "sha256:88d4266fd4e6338d13b845fcf289579d209c897823b9217da3e161936f031589",
+                    "browser_download_url": "https://github.com/o/r/releases/download/v1.0/data.zip",
+                }],
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let release = GitHubRelease::new("o", "r", Some("v1.0".to_string()));
+        assert_eq!(
+            release.root_url().as_str(),
+            "https://api.github.com/repos/o/r/releases/tags/v1.0"
+        );
+        let api_url = Url::parse(&format!(
+            "{}/repos/o/r/releases/tags/v1.0",
+            mock_server.uri()
+        ))
+        .unwrap();
+        let entries = release
+            .list(&Client::new(), DirMeta::new_root(&api_url))
+            .await
+            .unwrap();
+        let [Entry::File(asset), Entry::File(tarball)] = entries.as_slice() else {
+            panic!("expect the asset and the source tarball")
+        };
+        assert_eq!(asset.relative().as_str(), "data.zip");
+        assert_eq!(asset.size(), Some(1024));
+        assert_eq!(asset.checksum().len(), 1);
+        assert_eq!(asset.file_identifier(), Some("42"));
+        assert_eq!(tarball.relative().as_str(), "r-v1.0.tar.gz");
+        assert_eq!(
+            tarball.download_url().as_str(),
+            "https://github.com/o/r/archive/refs/tags/v1.0.tar.gz"
+        );
+    }
+}
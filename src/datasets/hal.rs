@@ -5,13 +5,16 @@ use exn::{Exn, ResultExt};
 use serde_json::Value as JsonValue;
 use url::Url;
 
-use reqwest::{Client, StatusCode};
+use percent_encoding::percent_decode_str;
+use reqwest::Client;
 use std::{any::Any, str::FromStr};
 
 use crate::helper::{json_extract, json_extract_opt};
+use crate::http::get_json;
+use crate::metadata::{json_str, json_strs, normalize_doi};
 use crate::{
     repo::{Endpoint, FileMeta, RepoError},
-    DatasetBackend, DirMeta, Entry,
+    DatasetBackend, DatasetMetadata, DirMeta, Entry,
 };
 
 fn analyse_json(json: &JsonValue, dir: &DirMeta) -> Result<Vec<Entry>, Exn<RepoError>> {
@@ -24,33 +27,17 @@ fn analyse_json(json: &JsonValue, dir: &DirMeta) -> Result<Vec<Entry>, Exn<RepoE
         })?;
 
     let mut entries = Vec::with_capacity(files.len());
-
-    // if not files are given, return empty vec
-    if files.first().and_then(|d| d.get("files_s")).is_none() {
-        return Ok(entries);
-    }
     for (idx, filej) in files.iter().enumerate() {
-        let endpoint = Endpoint {
-            parent_url: dir.api_url(),
-            key: Some(format!("files_s.{idx}")),
+        // the main file and the annexes (data, supplementary material, ...) of the record, by
+        // their original name, records without file have no 'files_s'
+        let Some(urls) = filej.get("files_s").and_then(JsonValue::as_array) else {
+            continue;
         };
-        let download_url: String = json_extract(filej, "files_s.0").or_raise(|| RepoError {
-            message: "failed to extract 'files_s.0' as String from json".to_string(),
-        })?;
-        let filename = download_url
-            .split('/')
-            .next_back()
-            .ok_or_else(|| RepoError {
-                message: format!("didn't get filename from '{download_url}'"),
-            })?;
-        let guess = mime_guess::from_path(filename);
-        let download_url = Url::from_str(download_url.as_str()).or_raise(|| RepoError {
-            message: format!("invalid download url '{download_url}'"),
-        })?;
 
-        let creation_date = json_extract(filej, "producedDate_tdate").or_raise(|| RepoError {
-            message: "fail to extracting 'producedDate_tdate' as String from json".to_string(),
-        })?;
+        let creation_date: String =
+            json_extract(filej, "producedDate_tdate").or_raise(|| RepoError {
+                message: "fail to extracting 'producedDate_tdate' as String from json".to_string(),
+            })?;
         let last_modification_date: Option<String> = json_extract_opt(filej, "modifiedDate_tdate")
             .or_raise(|| RepoError {
                 message: "fail to extracting 'modifiedDate_tdate' as String from json".to_string(),
@@ -59,26 +46,69 @@ fn analyse_json(json: &JsonValue, dir: &DirMeta) -> Result<Vec<Entry>, Exn<RepoE
             message: "fail to extracting 'version_i' as String from json".to_string(),
         })?;
 
-        let file = FileMeta::new(
-            Some(filename.to_string()),
-            None,
-            dir.join(filename),
-            endpoint,
-            download_url,
-            None,
-            vec![],
-            guess.first(),
-            version.map(|v| v.to_string()),
-            Some(creation_date),
-            last_modification_date,
-            true,
-        );
-        entries.push(Entry::File(file));
+        for (i, url) in urls.iter().enumerate() {
+            let endpoint = Endpoint {
+                parent_url: dir.api_url(),
+                key: Some(format!("response.docs.{idx}.files_s.{i}")),
+            };
+            let download_url = url.as_str().ok_or_else(|| RepoError {
+                message: format!("'files_s.{i}' is not a String"),
+            })?;
+            let download_url = Url::from_str(download_url).or_raise(|| RepoError {
+                message: format!("invalid download url '{download_url}'"),
+            })?;
+            let filename = download_url
+                .path_segments()
+                .and_then(Iterator::last)
+                .filter(|name| !name.is_empty())
+                .map(|name| percent_decode_str(name).decode_utf8_lossy().into_owned())
+                .ok_or_else(|| RepoError {
+                    message: format!("didn't get filename from '{download_url}'"),
+                })?;
+            let guess = mime_guess::from_path(&filename);
+
+            let file = FileMeta::new(
+                Some(filename.clone()),
+                None,
+                dir.join(&filename),
+                endpoint,
+                download_url,
+                None,
+                vec![],
+                guess.first(),
+                version.map(|v| v.to_string()),
+                Some(creation_date.clone()),
+                last_modification_date.clone(),
+                true,
+            );
+            entries.push(Entry::File(file));
+        }
     }
 
     Ok(entries)
 }
 
+// Search API doc with the fields requested in `HalScience::metadata`, the text fields are
+// multi valued (one value per language).
+fn metadata_from_json(json: &JsonValue) -> DatasetMetadata {
+    let Some(doc) = json.pointer("/response/docs/0") else {
+        return DatasetMetadata::default();
+    };
+    DatasetMetadata {
+        title: json_str(doc, "/title_s/0"),
+        description: json_str(doc, "/abstract_s/0"),
+        creators: json_strs(doc, "/authFullName_s", ""),
+        license: json_str(doc, "/licence_s"),
+        publication_date: json_str(doc, "/publicationDate_s")
+            .or_else(|| json_str(doc, "/producedDate_s")),
+        version: doc
+            .get("version_i")
+            .and_then(JsonValue::as_i64)
+            .map(|v| v.to_string()),
+        doi: json_str(doc, "/doiId_s").map(|doi| normalize_doi(&doi)),
+    }
+}
+
 // https://hal.science/
 // API root url at https://hal.science/<id>?
 #[derive(Debug)]
@@ -95,6 +125,12 @@ impl HalScience {
 
 #[async_trait]
 impl DatasetBackend for HalScience {
+    fn name(&self) -> &'static str {
+        "hal"
+    }
+    fn record_id(&self) -> String {
+        self.id.clone()
+    }
     fn root_url(&self) -> Url {
         // HAL Search API endpoint
         // can get files of a record by following search api call, e.g. for 'cel-01830944'
@@ -130,37 +166,34 @@ impl DatasetBackend for HalScience {
     }
 
     async fn list(&self, client: &Client, dir: DirMeta) -> Result<Vec<Entry>, Exn<RepoError>> {
-        let resp = client
-            .get(dir.api_url().clone())
-            .send()
-            .await
-            .or_raise(|| RepoError {
-                message: format!("fail at client sent GET {}", dir.api_url()),
-            })?;
-        let resp = resp.error_for_status().map_err(|err| match err.status() {
-            Some(StatusCode::NOT_FOUND) => RepoError {
-                message: format!("resource not found when GET {}", dir.api_url()),
-            },
-            Some(status_code) => RepoError {
-                message: format!(
-                    "fail GET {}, with state code: {}",
-                    dir.api_url(),
-                    status_code.as_str()
-                ),
-            },
-            None => RepoError {
-                message: format!("fail GET {}, network / protocol error", dir.api_url(),),
-            },
-        })?;
-        let resp: JsonValue = resp.json().await.or_raise(|| RepoError {
-            message: format!("fail GET {}, unable to convert to json", dir.api_url(),),
-        })?;
+        let resp = get_json(client, &dir.api_url()).await?;
 
         let entries = analyse_json(&resp, &dir)?;
 
         Ok(entries)
     }
 
+    async fn raw_metadata(&self, client: &Client) -> Result<JsonValue, Exn<RepoError>> {
+        // Safe to unwrap:
+        // - the base URL is a hard-coded, valid absolute URL
+        let mut url = Url::from_str("https://api.archives-ouvertes.fr/search/").unwrap();
+        url.query_pairs_mut()
+            .append_pair("q", &format!("halId_s:{}", self.id))
+            .append_pair("wt", "json")
+            .append_pair("fl", "*");
+        get_json(client, &url).await
+    }
+
+    async fn metadata(&self, client: &Client) -> Result<DatasetMetadata, Exn<RepoError>> {
+        let json = self.raw_metadata(client).await?;
+        if json.pointer("/response/docs/0").is_none() {
+            exn::bail!(RepoError {
+                message: format!("no HAL record '{}' found", self.id),
+            });
+        }
+        Ok(metadata_from_json(&json))
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -184,6 +217,12 @@ impl HalJsonSrcDataset {
 
 #[async_trait]
 impl DatasetBackend for HalJsonSrcDataset {
+    fn name(&self) -> &'static str {
+        "hal"
+    }
+    fn record_id(&self) -> String {
+        self.id.clone()
+    }
     fn root_url(&self) -> Url {
         // HAL Search API endpoint
         // can get files of a record by following search api call, e.g. for 'cel-01830944'
@@ -5,15 +5,50 @@ use exn::{Exn, OptionExt, ResultExt};
 use serde_json::Value as JsonValue;
 use url::Url;
 
-use reqwest::{Client, StatusCode};
+use reqwest::{header::HeaderMap, Client, StatusCode};
 use std::any::Any;
 
+use crate::auth::bearer;
 use crate::helper::json_extract;
+use crate::http::{self, get_json};
+use crate::metadata::{json_str, json_strs, normalize_doi};
 use crate::{
     repo::{Endpoint, FileMeta, RepoError},
-    Checksum, DatasetBackend, DirMeta, Entry,
+    AccessRequirement, AuthConfig, Checksum, DatasetBackend, DatasetMetadata, DirMeta, Entry,
 };
 
+// Dataset info at https://huggingface.co/api/datasets/<owner>/<repo>/revision/<revision>, the
+// license and the DOI minted by Hugging Face are given as 'license:<id>' and 'doi:<doi>' tags.
+fn metadata_from_json(json: &JsonValue) -> DatasetMetadata {
+    let tags: Vec<String> = json
+        .get("tags")
+        .and_then(JsonValue::as_array)
+        .map(|tags| {
+            tags.iter()
+                .filter_map(JsonValue::as_str)
+                .map(ToString::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    let tag = |prefix: &str| {
+        tags.iter()
+            .find_map(|t| t.strip_prefix(prefix))
+            .map(ToString::to_string)
+    };
+    DatasetMetadata {
+        title: json_str(json, "/cardData/pretty_name").or_else(|| json_str(json, "/id")),
+        description: json_str(json, "/description"),
+        creators: json_str(json, "/author").into_iter().collect(),
+        license: tag("license:").or_else(|| {
+            json_str(json, "/cardData/license")
+                .or_else(|| json_strs(json, "/cardData/license", "").into_iter().next())
+        }),
+        publication_date: json_str(json, "/createdAt"),
+        version: json_str(json, "/sha"),
+        doi: tag("doi:").map(|doi| normalize_doi(&doi)),
+    }
+}
+
 #[derive(Debug)]
 pub struct HuggingFace {
     pub owner: String,
@@ -46,44 +81,15 @@ impl HuggingFace {
             .extend(path.split('/'));
         url
     }
-}
-
-#[async_trait]
-impl DatasetBackend for HuggingFace {
-    fn root_url(&self) -> Url {
-        // https://huggingface.co/api/datasets/{owner}/{repo}/tree/{revision}/{path}
-        let mut url = Url::parse("https://huggingface.co/api/datasets").unwrap();
-        // safe to unwrap, we know the url.
-        url.path_segments_mut()
-            .unwrap()
-            .extend([&self.owner, &self.repo, "tree", &self.revision]);
-
-        url
-    }
-
-    async fn list(&self, client: &Client, dir: DirMeta) -> Result<Vec<Entry>, Exn<RepoError>> {
-        let resp = client
-            .get(dir.api_url())
-            .send()
-            .await
-            .map_err(|e| RepoError {
-                message: format!("HTTP GET failed: {e}"),
-            })?;
-
-        if resp.status() == StatusCode::FORBIDDEN {
-            exn::bail!(RepoError {
-                message: "Hugging Face API rate limit exceeded".to_string(),
-            });
-        }
-
-        let resp = resp.error_for_status().map_err(|e| RepoError {
-            message: format!("HTTP error GET {}: {e}", dir.api_url()),
-        })?;
-
-        let json: JsonValue = resp.json().await.map_err(|e| RepoError {
-            message: format!("Failed to parse JSON from {}: {e}", dir.api_url()),
-        })?;
 
+    // Entries of a page of the tree listing of `dir`, whose 'path' are relative to the root of
+    // the repository, not to `dir`.
+    fn analyse_tree(
+        &self,
+        json: &JsonValue,
+        dir: &DirMeta,
+        page_url: &Url,
+    ) -> Result<Vec<Entry>, Exn<RepoError>> {
         let files = json.as_array().ok_or_else(|| RepoError {
             message: "Expected array from Hugging Face tree API".to_string(),
         })?;
@@ -91,10 +97,10 @@ impl DatasetBackend for HuggingFace {
         let mut entries = Vec::with_capacity(files.len());
 
         for (i, filej) in files.iter().enumerate() {
-            let path: String = json_extract(filej, "path").or_raise(|| RepoError {
+            let full_path: String = json_extract(filej, "path").or_raise(|| RepoError {
                 message: "Missing 'path'".to_string(),
             })?;
-            let path = path.split('/').next_back().ok_or_raise(|| RepoError {
+            let path = full_path.split('/').next_back().ok_or_raise(|| RepoError {
                 message: "not get the basename of path".to_string(),
             })?;
             let kind: String = json_extract(filej, "type").or_raise(|| RepoError {
@@ -104,30 +110,38 @@ impl DatasetBackend for HuggingFace {
             match kind.as_str() {
                 "file" => {
                     let size: u64 = json_extract(filej, "size").or_raise(|| RepoError {
-                        message: format!("Missing size from {}", dir.api_url()),
+                        message: format!("Missing size from {page_url}"),
                     })?;
-                    let checksum: String = json_extract(filej, "lfs.oid")
-                        .or_else(|_| json_extract(filej, "oid"))
-                        .or_raise(|| RepoError {
-                            message: format!("Missing 'lfs.oid' from {}", dir.api_url()),
-                        })?;
-                    let checksum = Checksum::Sha256(checksum);
+                    // 'oid' is the git blob id, of the content of files in git and of the pointer
+                    // of LFS files, whose 'lfs.oid' is the sha256 of the content.
+                    let file_id: Option<String> = json_extract(filej, "oid").ok();
+                    let checksum = match (json_extract::<String>(filej, "lfs.oid"), &file_id) {
+                        (Ok(oid), _) => {
+                            vec![Checksum::parse("sha256", &oid).or_raise(|| RepoError {
+                                message: format!("invalid checksum of '{full_path}'"),
+                            })?]
+                        }
+                        (Err(_), Some(oid)) => {
+                            Checksum::parse("gitsha1", oid).ok().into_iter().collect()
+                        }
+                        (Err(_), None) => vec![],
+                    };
                     let path = dir.join(path);
                     let guess = mime_guess::from_path(&path);
 
-                    let download_url = self.download_url(path.relative().as_str());
+                    let download_url = self.download_url(&full_path);
 
                     let file = FileMeta::new(
                         None,
-                        None,
+                        file_id,
                         path,
                         Endpoint {
-                            parent_url: dir.api_url(),
+                            parent_url: page_url.clone(),
                             key: Some(format!("filej.{i}")),
                         },
                         download_url,
                         Some(size),
-                        vec![checksum],
+                        checksum,
                         guess.first(),
                         None,
                         None,
@@ -138,16 +152,17 @@ impl DatasetBackend for HuggingFace {
                     entries.push(Entry::File(file));
                 }
                 "directory" => {
+                    // the tree of a sub directory is listed at its full path below the root
                     let mut api_url = dir.root_url();
-                    // huggingface, path field return the relative path to the root, not to the
-                    // parent folder.
+                    api_url.set_query(None);
                     api_url
                         .path_segments_mut()
                         .map_err(|err| RepoError {
                             message: format!("path_segments_mut fail with {err:?}"),
                         })?
-                        .extend([path]);
-                    let subdir = DirMeta::new(dir.join(path), api_url.clone(), api_url.clone());
+                        .pop_if_empty()
+                        .extend(full_path.split('/'));
+                    let subdir = DirMeta::new(dir.join(path), api_url, dir.root_url());
                     entries.push(Entry::Dir(subdir));
                 }
                 other => {
@@ -160,8 +175,165 @@ impl DatasetBackend for HuggingFace {
 
         Ok(entries)
     }
+}
+
+#[async_trait]
+impl DatasetBackend for HuggingFace {
+    fn name(&self) -> &'static str {
+        "huggingface"
+    }
+    fn record_id(&self) -> String {
+        format!("{}/{}", self.owner, self.repo)
+    }
+    fn root_url(&self) -> Url {
+        // https://huggingface.co/api/datasets/{owner}/{repo}/tree/{revision}/{path}
+        let mut url = Url::parse("https://huggingface.co/api/datasets").unwrap();
+        // safe to unwrap, we know the url.
+        url.path_segments_mut()
+            .unwrap()
+            .extend([&self.owner, &self.repo, "tree", &self.revision]);
+
+        url
+    }
+
+    async fn list(&self, client: &Client, dir: DirMeta) -> Result<Vec<Entry>, Exn<RepoError>> {
+        // large directories are paginated, the next page is given in the 'Link' header
+        let mut entries = Vec::new();
+        let mut page_url = dir.api_url();
+        loop {
+            let resp = http::send_get(client, &page_url).await?;
+
+            if resp.status() == StatusCode::FORBIDDEN {
+                return Err(http::rate_limited(
+                    &resp,
+                    &page_url,
+                    "Hugging Face API rate limit exceeded".to_string(),
+                ));
+            }
+
+            let resp = http::error_for_status(resp, &page_url)?;
+            let next = http::next_link(&resp).filter(|next| *next != page_url);
+            let json = http::read_json(resp, &page_url).await?;
+
+            entries.extend(self.analyse_tree(&json, &dir, &page_url)?);
+
+            match next {
+                Some(next) => page_url = next,
+                None => break,
+            }
+        }
+
+        Ok(entries)
+    }
+
+    async fn raw_metadata(&self, client: &Client) -> Result<JsonValue, Exn<RepoError>> {
+        let mut url = Url::parse("https://huggingface.co/api/datasets").unwrap();
+        // safe to unwrap, we know the url.
+        url.path_segments_mut().unwrap().extend([
+            &self.owner,
+            &self.repo,
+            "revision",
+            &self.revision,
+        ]);
+        get_json(client, &url).await
+    }
+
+    async fn metadata(&self, client: &Client) -> Result<DatasetMetadata, Exn<RepoError>> {
+        let json = self.raw_metadata(client).await?;
+        Ok(metadata_from_json(&json))
+    }
+
+    async fn access_requirements(
+        &self,
+        client: &Client,
+        auth: &AuthConfig,
+    ) -> Result<Vec<AccessRequirement>, Exn<RepoError>> {
+        // 'gated' is false, or "auto" / "manual" for how access requests are approved, a
+        // token of an account the access was granted to is needed either way.
+        if auth.huggingface.is_some() {
+            return Ok(Vec::new());
+        }
+        let json = self.raw_metadata(client).await?;
+        if !json.get("gated").is_some_and(JsonValue::is_string) {
+            return Ok(Vec::new());
+        }
+        let mut url = Url::parse("https://huggingface.co/datasets").unwrap();
+        url.path_segments_mut()
+            .unwrap()
+            .extend([&self.owner, &self.repo]);
+        Ok(vec![
+            AccessRequirement::Authentication {
+                repository: self.name().to_string(),
+            },
+            AccessRequirement::Request {
+                url,
+                paths: Vec::new(),
+            },
+        ])
+    }
+
+    fn auth_headers(&self, auth: &AuthConfig) -> HeaderMap {
+        bearer(auth.huggingface.as_deref())
+    }
 
     fn as_any(&self) -> &dyn Any {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{method, path, query_param, query_param_is_missing},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_huggingface_list_pages_and_nested_dirs() {
+        let mock_server = MockServer::start().await;
+        let tree_url = format!("{}/api/datasets/o/r/tree/main", mock_server.uri());
+        let entry =
+            |typ: &str, path: &str| serde_json::json!({ "type": typ, "size": 4, "path": path });
+        Mock::given(method("GET"))
+            .and(path("/api/datasets/o/r/tree/main/data"))
+            .and(query_param_is_missing("cursor"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("link", format!("<{tree_url}/data?cursor=2>; rel=\"next\""))
+                    .set_body_json(serde_json::json!([entry("file", "data/a.csv")])),
+            )
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/datasets/o/r/tree/main/data"))
+            .and(query_param("cursor", "2"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!([entry("directory", "data/data"),])),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let root_url = Url::parse(&tree_url).unwrap();
+        let root = DirMeta::new_root(&root_url);
+        let dir = DirMeta::new(
+            root.join("data"),
+            Url::parse(&format!("{tree_url}/data")).unwrap(),
+            root_url,
+        );
+        let hf = HuggingFace::new("o", "r", "main");
+        let entries = hf.list(&Client::new(), dir).await.unwrap();
+        let [Entry::File(file), Entry::Dir(subdir)] = entries.as_slice() else {
+            panic!("expect the entries of both pages")
+        };
+        assert_eq!(file.relative().as_str(), "data/a.csv");
+        assert_eq!(
+            file.download_url().as_str(),
+            "https://huggingface.co/datasets/o/r/resolve/main/data/a.csv"
+        );
+        assert_eq!(subdir.relative().as_str(), "data/data");
+        assert_eq!(subdir.api_url().as_str(), format!("{tree_url}/data/data"));
+        assert_eq!(subdir.root_url().as_str(), tree_url);
+    }
+}
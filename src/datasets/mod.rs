@@ -6,14 +6,23 @@ mod github;
 mod hal;
 mod huggingface;
 mod osf;
+mod pangaea;
+mod s3;
+mod softwareheritage;
+mod thredds;
 mod zenodo;
 
-pub use arxiv::Arxiv;
+pub use arxiv::{Arxiv, ArxivFormat};
 pub use dataone::Dataone;
+pub(crate) use dataverse::version_name as dataverse_version_name;
 pub use dataverse::{DataverseDataset, DataverseFile, DataverseJsonSrcDataset};
 pub use dryad::DataDryad;
-pub use github::GitHub;
+pub use github::{GitHub, GitHubRelease};
 pub use hal::{HalJsonSrcDataset, HalScience};
 pub use huggingface::HuggingFace;
 pub use osf::OSF;
-pub use zenodo::{Zenodo, ZenodoJsonSrcDataset};
+pub use pangaea::{Pangaea, PANGAEA_URL};
+pub use s3::S3Public;
+pub use softwareheritage::{SoftwareHeritage, SOFTWARE_HERITAGE_URL};
+pub use thredds::Thredds;
+pub use zenodo::{Zenodo, ZenodoJsonSrcDataset, ZENODO_URL};
@@ -5,15 +5,210 @@ use exn::{Exn, ResultExt};
 use serde_json::Value as JsonValue;
 use url::Url;
 
-use reqwest::{Client, StatusCode};
+use reqwest::{header::HeaderMap, Client};
 use std::{any::Any, str::FromStr};
+use tracing::debug;
 
+use crate::auth::bearer;
 use crate::helper::json_extract;
+use crate::http::get_json;
+use crate::metadata::json_str;
 use crate::{
     repo::{Endpoint, FileMeta, RepoError},
-    Checksum, DatasetBackend, DirMeta, Entry,
+    AuthConfig, Checksum, DatasetBackend, DatasetMetadata, DirMeta, Entry,
 };
 
+fn analyse_json(
+    json: &JsonValue,
+    dir: &DirMeta,
+    page_url: &Url,
+) -> Result<Vec<Entry>, Exn<RepoError>> {
+    let files = json
+        .get("data")
+        .and_then(JsonValue::as_array)
+        .ok_or_else(|| RepoError {
+            message: "field with key 'data' not resolve to an json array".to_string(),
+        })?;
+
+    let mut entries = Vec::with_capacity(files.len());
+    for (idx, filej) in files.iter().enumerate() {
+        let endpoint = Endpoint {
+            parent_url: page_url.clone(),
+            key: Some(format!("data.{idx}")),
+        };
+        let name: String = json_extract(filej, "attributes.name").or_raise(|| RepoError {
+            message: "fail to extracting 'attributes.name' as String from json".to_string(),
+        })?;
+        let kind: String = json_extract(filej, "attributes.kind").or_raise(|| RepoError {
+            message: "fail to extracting 'attributes.kind' as String from json".to_string(),
+        })?;
+        let guess = mime_guess::from_path(&name);
+        match kind.as_ref() {
+            "file" => {
+                // files of add-on storages (GitHub, Dropbox, S3, ...) may come without size
+                let size = filej
+                    .pointer("/attributes/size")
+                    .and_then(JsonValue::as_u64);
+                let download_url: String =
+                    json_extract(filej, "links.download").or_raise(|| RepoError {
+                        message: "fail to extracting 'links.download' as String from json"
+                            .to_string(),
+                    })?;
+                let download_url = Url::from_str(&download_url).or_raise(|| RepoError {
+                    message: format!("cannot parse '{download_url}' download url"),
+                })?;
+                // and without sha256, they are downloaded unverified
+                let checksum = match json_str(filej, "/attributes/extra/hashes/sha256") {
+                    Some(hash) => {
+                        vec![Checksum::parse("sha256", &hash).or_raise(|| RepoError {
+                            message: format!("invalid checksum of '{name}'"),
+                        })?]
+                    }
+                    None => {
+                        debug!("no sha256 listed for '{name}', not validated");
+                        vec![]
+                    }
+                };
+                let file_id: Option<String> = json_extract(filej, "id").ok();
+                let file = FileMeta::new(
+                    None,
+                    file_id,
+                    dir.join(&name),
+                    endpoint,
+                    download_url,
+                    size,
+                    checksum,
+                    guess.first(),
+                    None,
+                    None,
+                    None,
+                    true,
+                );
+                entries.push(Entry::File(file));
+            }
+            "folder" => {
+                let api_url: String =
+                    json_extract(filej, "relationships.files.links.related.href")
+                    .or_raise(|| RepoError {
+                        message: "fail to extracting 'relationships.files.links.related.href' as String from json"
+                            .to_string(),
+                    })?;
+                let api_url = Url::from_str(&api_url).or_raise(|| RepoError {
+                    message: format!("cannot parse '{api_url}' api url"),
+                })?;
+                let dir = DirMeta::new(dir.join(&name), api_url, dir.root_url());
+                entries.push(Entry::Dir(dir));
+            }
+            typ => {
+                exn::bail!(RepoError {
+                    message: format!("kind can be 'dataset' or 'kind' for an OSF entry, got {typ}")
+                });
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+// Components of a project are nodes of their own, listed at
+// https://api.osf.io/v2/nodes/<id>/children/ and crawled as folders named after their title,
+// next to the storage providers of the project.
+fn analyse_children(json: &JsonValue, dir: &DirMeta) -> Result<Vec<Entry>, Exn<RepoError>> {
+    let children = json
+        .get("data")
+        .and_then(JsonValue::as_array)
+        .ok_or_else(|| RepoError {
+            message: "field with key 'data' not resolve to an json array".to_string(),
+        })?;
+    let mut entries = Vec::with_capacity(children.len());
+    for child in children {
+        let id: String = json_extract(child, "id").or_raise(|| RepoError {
+            message: "fail to extracting 'id' of a component as String from json".to_string(),
+        })?;
+        let api_url: String = json_extract(child, "relationships.files.links.related.href")
+            .or_raise(|| RepoError {
+                message: format!("fail to extracting the files url of component '{id}' from json"),
+            })?;
+        let api_url = Url::from_str(&api_url).or_raise(|| RepoError {
+            message: format!("cannot parse '{api_url}' api url"),
+        })?;
+        let name = json_str(child, "/attributes/title")
+            .map(|title| title.replace('/', "_"))
+            .filter(|title| !title.trim().is_empty())
+            .unwrap_or(id);
+        entries.push(Entry::Dir(DirMeta::new(
+            dir.join(&name),
+            api_url,
+            dir.root_url(),
+        )));
+    }
+    Ok(entries)
+}
+
+// Components listing of the node whose storage providers are listed at `files_url`,
+// https://api.osf.io/v2/nodes/<id>/files, `None` for folders of a storage provider.
+fn children_url(files_url: &Url) -> Option<Url> {
+    let segments: Vec<_> = files_url
+        .path_segments()?
+        .filter(|s| !s.is_empty())
+        .collect();
+    let [.., "nodes", _, "files"] = segments.as_slice() else {
+        return None;
+    };
+    let mut url = files_url.clone();
+    url.set_query(None);
+    url.path_segments_mut()
+        .ok()?
+        .pop_if_empty()
+        .pop()
+        .extend(["children", ""]);
+    Some(url)
+}
+
+// The files listing is paginated (10 items per page by default), the url of the next page is
+// given at 'links.next' and is null on the last page.
+fn next_page(json: &JsonValue, page_url: &Url) -> Result<Option<Url>, Exn<RepoError>> {
+    let Some(next) = json.pointer("/links/next").and_then(JsonValue::as_str) else {
+        return Ok(None);
+    };
+    let next = Url::from_str(next).or_raise(|| RepoError {
+        message: format!("fail to parse next page url '{next}' at parsing {page_url}"),
+    })?;
+    if next == *page_url {
+        return Ok(None);
+    }
+    Ok(Some(next))
+}
+
+// Node json at https://api.osf.io/v2/nodes/<id>/ with the contributors and license embedded,
+// only the bibliographic contributors are authors of the project.
+fn metadata_from_json(json: &JsonValue) -> DatasetMetadata {
+    let creators = json
+        .pointer("/data/embeds/contributors/data")
+        .and_then(JsonValue::as_array)
+        .map(|contributors| {
+            contributors
+                .iter()
+                .filter(|c| {
+                    c.pointer("/attributes/bibliographic")
+                        .and_then(JsonValue::as_bool)
+                        .unwrap_or(true)
+                })
+                .filter_map(|c| json_str(c, "/embeds/users/data/attributes/full_name"))
+                .collect()
+        })
+        .unwrap_or_default();
+    DatasetMetadata {
+        title: json_str(json, "/data/attributes/title"),
+        description: json_str(json, "/data/attributes/description"),
+        creators,
+        license: json_str(json, "/data/embeds/license/data/attributes/name"),
+        publication_date: json_str(json, "/data/attributes/date_created"),
+        version: None,
+        doi: None,
+    }
+}
+
 // https://osf.io/
 // API root url at https://api.osf.io/v2/nodes/
 #[derive(Debug)]
@@ -30,6 +225,12 @@ impl OSF {
 
 #[async_trait]
 impl DatasetBackend for OSF {
+    fn name(&self) -> &'static str {
+        "osf"
+    }
+    fn record_id(&self) -> String {
+        self.id.clone()
+    }
     fn root_url(&self) -> Url {
         // https://api.osf.io/v2/nodes/<id>/files to start for every dateset entry
 
@@ -42,115 +243,220 @@ impl DatasetBackend for OSF {
     }
 
     async fn list(&self, client: &Client, dir: DirMeta) -> Result<Vec<Entry>, Exn<RepoError>> {
-        let resp = client
-            .get(dir.api_url())
-            .send()
-            .await
-            .or_raise(|| RepoError {
-                message: format!("fail at client sent GET {}", dir.api_url()),
-            })?;
-        let resp = resp.error_for_status().map_err(|err| match err.status() {
-            Some(StatusCode::NOT_FOUND) => RepoError {
-                message: format!("resource not found when GET {}", dir.api_url()),
-            },
-            Some(status_code) => RepoError {
-                message: format!(
-                    "fail GET {}, with state code: {}",
-                    dir.api_url(),
-                    status_code.as_str()
-                ),
-            },
-            None => RepoError {
-                message: format!("fail GET {}, network / protocol error", dir.api_url(),),
-            },
-        })?;
-        let resp: JsonValue = resp.json().await.or_raise(|| RepoError {
-            message: format!("fail GET {}, unable to convert to json", dir.api_url(),),
-        })?;
-        let files = resp
-            .get("data")
-            .and_then(JsonValue::as_array)
-            .ok_or_else(|| RepoError {
-                message: "field with key 'data' not resolve to an json array".to_string(),
-            })?;
+        // the files of a node are its storage providers, osfstorage and add-ons such as
+        // GitHub, Dropbox or S3, listed as folders
+        let mut entries = Vec::new();
+        let mut page_url = dir.api_url();
+        loop {
+            let resp = get_json(client, &page_url).await?;
 
-        let mut entries = Vec::with_capacity(files.len());
-        for (idx, filej) in files.iter().enumerate() {
-            let endpoint = Endpoint {
-                parent_url: dir.api_url(),
-                key: Some(format!("data.{idx}")),
-            };
-            let name: String = json_extract(filej, "attributes.name").or_raise(|| RepoError {
-                message: "fail to extracting 'attributes.name' as String from json".to_string(),
-            })?;
-            let kind: String = json_extract(filej, "attributes.kind").or_raise(|| RepoError {
-                message: "fail to extracting 'attributes.kind' as String from json".to_string(),
-            })?;
-            let guess = mime_guess::from_path(&name);
-            match kind.as_ref() {
-                "file" => {
-                    let size: u64 =
-                        json_extract(filej, "attributes.size").or_raise(|| RepoError {
-                            message: "fail to extracting 'attributes.size' as u64 from json"
-                                .to_string(),
-                        })?;
-                    let download_url: String =
-                        json_extract(filej, "links.download").or_raise(|| RepoError {
-                            message: "fail to extracting 'links.download' as String from json"
-                                .to_string(),
-                        })?;
-                    let download_url = Url::from_str(&download_url).or_raise(|| RepoError {
-                        message: format!("cannot parse '{download_url}' download url"),
-                    })?;
-                    let hash: String = json_extract(filej, "attributes.extra.hashes.sha256")
-                        .or_raise(|| RepoError {
-                            message: "fail to extracting 'attributes.extra.hashes.sha256' as String from json"
-                                .to_string(),
-                        })?;
-                    let checksum = Checksum::Sha256(hash);
-                    let file = FileMeta::new(
-                        None,
-                        None,
-                        dir.join(&name),
-                        endpoint,
-                        download_url,
-                        Some(size),
-                        vec![checksum],
-                        guess.first(),
-                        None,
-                        None,
-                        None,
-                        true,
-                    );
-                    entries.push(Entry::File(file));
-                }
-                "folder" => {
-                    let api_url: String =
-                        json_extract(filej, "relationships.files.links.related.href")
-                        .or_raise(|| RepoError {
-                            message: "fail to extracting 'relationships.files.links.related.href' as String from json"
-                                .to_string(),
-                        })?;
-                    let api_url = Url::from_str(&api_url).or_raise(|| RepoError {
-                        message: format!("cannot parse '{api_url}' api url"),
-                    })?;
-                    let dir = DirMeta::new(dir.join(&name), api_url, dir.root_url());
-                    entries.push(Entry::Dir(dir));
-                }
-                typ => {
-                    exn::bail!(RepoError {
-                        message: format!(
-                            "kind can be 'dataset' or 'kind' for an OSF entry, got {typ}"
-                        )
-                    });
-                }
+            entries.extend(analyse_json(&resp, &dir, &page_url)?);
+
+            match next_page(&resp, &page_url)? {
+                Some(next) => page_url = next,
+                None => break,
+            }
+        }
+
+        let Some(mut page_url) = children_url(&dir.api_url()) else {
+            return Ok(entries);
+        };
+        loop {
+            let resp = get_json(client, &page_url).await?;
+
+            entries.extend(analyse_children(&resp, &dir)?);
+
+            match next_page(&resp, &page_url)? {
+                Some(next) => page_url = next,
+                None => break,
             }
         }
 
         Ok(entries)
     }
 
+    async fn raw_metadata(&self, client: &Client) -> Result<JsonValue, Exn<RepoError>> {
+        // Safe to unwrap:
+        // - the base URL is a hard-coded, valid absolute URL
+        // - `path_segments_mut` cannot fail for this URL scheme
+        let mut url = Url::from_str("https://api.osf.io/v2/nodes/").unwrap();
+        url.path_segments_mut()
+            .unwrap()
+            .pop_if_empty()
+            .extend([&self.id, ""]);
+        url.query_pairs_mut()
+            .append_pair("embed", "contributors")
+            .append_pair("embed", "license");
+        get_json(client, &url).await
+    }
+
+    async fn metadata(&self, client: &Client) -> Result<DatasetMetadata, Exn<RepoError>> {
+        let json = self.raw_metadata(client).await?;
+        Ok(metadata_from_json(&json))
+    }
+
+    fn auth_headers(&self, auth: &AuthConfig) -> HeaderMap {
+        bearer(auth.osf.as_deref())
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{method, path, query_param, query_param_is_missing},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn file_json(name: &str) -> JsonValue {
+        serde_json::json!({
+            "id": format!("id-{name}"),
+            "attributes": {
+                "name": name,
+                "kind": "file",
+                "size": 4,
+                "extra": { "hashes": { "sha256": "88d4266fd4e6338d13b845fcf289579d209c897823b9217da3e161936f031589" } },
+            },
+            "links": { "download": format!("https://osf.io/download/{name}/") },
+        })
+    }
+
+    #[tokio::test]
+    async fn test_osf_list_follows_pagination() {
+        let mock_server = MockServer::start().await;
+        let files_url = format!("{}/v2/nodes/abcde/files/osfstorage/", mock_server.uri());
+        let pages = [
+            (None, vec![file_json("a.txt"), file_json("b.txt")], Some(2)),
+            (Some("2"), vec![file_json("c.txt")], Some(3)),
+            (Some("3"), vec![file_json("d.txt")], None),
+        ];
+        for (page, data, next) in pages {
+            let next = next.map(|n| format!("{files_url}?page={n}"));
+            let mock = Mock::given(method("GET")).and(path("/v2/nodes/abcde/files/osfstorage/"));
+            let mock = match page {
+                Some(page) => mock.and(query_param("page", page)),
+                None => mock.and(query_param_is_missing("page")),
+            };
+            mock.respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": data,
+                "links": { "next": next, "prev": null },
+            })))
+            .mount(&mock_server)
+            .await;
+        }
+
+        let dir = DirMeta::new_root(&Url::from_str(&files_url).unwrap());
+        let entries = OSF::new("abcde").list(&Client::new(), dir).await.unwrap();
+
+        let names: Vec<_> = entries
+            .iter()
+            .map(|entry| match entry {
+                Entry::File(file_meta) => file_meta.relative().as_str().to_string(),
+                Entry::Dir(_) => panic!("expect only files"),
+            })
+            .collect();
+        assert_eq!(names, ["a.txt", "b.txt", "c.txt", "d.txt"]);
+        let Entry::File(file) = &entries[0] else {
+            panic!("expect a file")
+        };
+        assert_eq!(file.file_identifier(), Some("id-a.txt"));
+    }
+
+    #[tokio::test]
+    async fn test_osf_list_providers_and_components() {
+        let mock_server = MockServer::start().await;
+        let uri = mock_server.uri();
+        let folder = |name: &str, href: String| {
+            serde_json::json!({
+                "attributes": { "name": name, "kind": "folder" },
+                "relationships": { "files": { "links": { "related": { "href": href } } } },
+            })
+        };
+        Mock::given(method("GET"))
+            .and(path("/v2/nodes/abcde/files"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": [
+                    folder("osfstorage", format!("{uri}/v2/nodes/abcde/files/osfstorage/")),
+                    folder("github", format!("{uri}/v2/nodes/abcde/files/github/")),
+                ],
+                "links": { "next": null },
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/nodes/abcde/children/"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": [{
+                    "id": "fghij",
+                    "attributes": { "title": "Analysis/R" },
+                    "relationships": { "files": { "links": { "related": {
+                        "href": format!("{uri}/v2/nodes/fghij/files/"),
+                    } } } },
+                }],
+                "links": { "next": null },
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/nodes/abcde/files/github/"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": [{
+                    "id": "id-readme",
+                    "attributes": { "name": "README.md", "kind": "file", "size": null, "extra": {} },
+                    "links": { "download": "https://osf.io/download/readme/" },
+                }],
+                "links": { "next": null },
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let root =
+            DirMeta::new_root(&Url::from_str(&format!("{uri}/v2/nodes/abcde/files")).unwrap());
+        let entries = OSF::new("abcde").list(&Client::new(), root).await.unwrap();
+        let dirs: Vec<_> = entries
+            .iter()
+            .map(|entry| match entry {
+                Entry::Dir(dir) => (
+                    dir.relative().as_str().to_string(),
+                    dir.api_url().path().to_string(),
+                ),
+                Entry::File(_) => panic!("expect only folders"),
+            })
+            .collect();
+        assert_eq!(
+            dirs,
+            [
+                (
+                    "osfstorage".to_string(),
+                    "/v2/nodes/abcde/files/osfstorage/".to_string()
+                ),
+                (
+                    "github".to_string(),
+                    "/v2/nodes/abcde/files/github/".to_string()
+                ),
+                (
+                    "Analysis_R".to_string(),
+                    "/v2/nodes/fghij/files/".to_string()
+                ),
+            ]
+        );
+
+        // add-on storages list files without size nor checksum
+        let Entry::Dir(github) = &entries[1] else {
+            panic!("expect a folder")
+        };
+        let entries = OSF::new("abcde")
+            .list(&Client::new(), github.clone())
+            .await
+            .unwrap();
+        let [Entry::File(readme)] = entries.as_slice() else {
+            panic!("expect a single file")
+        };
+        assert_eq!(readme.size(), None);
+        assert!(readme.checksum().is_empty());
+    }
+}
@@ -0,0 +1,273 @@
+use async_trait::async_trait;
+use exn::{Exn, ResultExt};
+use serde_json::Value as JsonValue;
+use url::Url;
+
+use reqwest::Client;
+use std::any::Any;
+
+use crate::http::get_json;
+use crate::metadata::{json_str, normalize_doi};
+use crate::{
+    repo::{Endpoint, FileMeta, RepoError},
+    DatasetBackend, DatasetMetadata, DirMeta, Entry,
+};
+
+pub const PANGAEA_URL: &str = "https://doi.pangaea.de/";
+
+/// DOI prefix of all Pangaea datasets.
+const DOI_PREFIX: &str = "10.1594";
+
+/// `value` if it is an array, else `value` itself as single element, schema.org allows
+/// both for most properties.
+fn as_list(value: Option<&JsonValue>) -> Vec<&JsonValue> {
+    match value {
+        Some(JsonValue::Array(items)) => items.iter().collect(),
+        Some(JsonValue::Null) | None => Vec::new(),
+        Some(item) => vec![item],
+    }
+}
+
+fn metadata_from_json(json: &JsonValue) -> DatasetMetadata {
+    let license = json.get("license").and_then(|license| {
+        license
+            .as_str()
+            .map(ToString::to_string)
+            .or_else(|| json_str(license, "/url"))
+            .or_else(|| json_str(license, "/name"))
+    });
+    DatasetMetadata {
+        title: json_str(json, "/name"),
+        description: json_str(json, "/description"),
+        creators: as_list(json.get("creator"))
+            .into_iter()
+            .filter_map(|creator| json_str(creator, "/name"))
+            .collect(),
+        license,
+        publication_date: json_str(json, "/datePublished"),
+        version: None,
+        doi: json_str(json, "/identifier").map(|doi| normalize_doi(&doi)),
+    }
+}
+
+// https://www.pangaea.de/
+// Every dataset is published under a DOI 'https://doi.pangaea.de/10.1594/PANGAEA.<n>', its
+// schema.org JSON-LD is at '?format=metadata_jsonld'. The JSON-LD 'distribution' links the
+// tab separated data ('?format=textfile') of tabular datasets, or a ZIP ('?format=zip') of
+// the files; collections list their child datasets at 'hasPart', these are crawled as
+// directories.
+#[derive(Debug)]
+pub struct Pangaea {
+    pub base_url: Url,
+    /// DOI suffix of the dataset, e.g. 'PANGAEA.942110'.
+    pub id: String,
+}
+
+impl Pangaea {
+    #[must_use]
+    pub fn new(id: impl Into<String>, base_url: &Url) -> Self {
+        Pangaea {
+            base_url: base_url.clone(),
+            id: id.into(),
+        }
+    }
+
+    fn dataset_url(&self, id: &str, format: &str) -> Result<Url, Exn<RepoError>> {
+        let mut url = self.base_url.clone();
+        url.path_segments_mut()
+            .map_err(|()| RepoError {
+                message: format!("'{}' cannot be base", self.base_url),
+            })?
+            .pop_if_empty()
+            .extend([DOI_PREFIX, id]);
+        url.query_pairs_mut().append_pair("format", format);
+        Ok(url)
+    }
+
+    fn analyse_json(&self, json: &JsonValue, dir: &DirMeta) -> Result<Vec<Entry>, Exn<RepoError>> {
+        let children = as_list(json.get("hasPart"));
+        let mut entries = Vec::new();
+        for child in &children {
+            // '@id' is the DOI url of the child dataset
+            let Some(id) = json_str(child, "/@id")
+                .as_deref()
+                .and_then(|id| id.rsplit('/').next())
+                .map(ToString::to_string)
+            else {
+                continue;
+            };
+            let api_url = self.dataset_url(&id, "metadata_jsonld")?;
+            entries.push(Entry::Dir(DirMeta::new(
+                dir.join(&id),
+                api_url,
+                dir.root_url(),
+            )));
+        }
+
+        let id = json_str(json, "/identifier")
+            .as_deref()
+            .and_then(|id| id.rsplit('/').next())
+            .map_or_else(|| self.id.clone(), ToString::to_string);
+        for (idx, distribution) in as_list(json.get("distribution")).into_iter().enumerate() {
+            let Some(content_url) = json_str(distribution, "/contentUrl") else {
+                continue;
+            };
+            let format = json_str(distribution, "/encodingFormat");
+            // the ZIP of a collection bundles the child datasets, already listed above
+            if !children.is_empty() && format.as_deref() == Some("application/zip") {
+                continue;
+            }
+            let download_url = Url::parse(&content_url).or_raise(|| RepoError {
+                message: format!("'{content_url}' of '{id}' is not a valid url"),
+            })?;
+            let extension = match format.as_deref() {
+                Some("text/tab-separated-values") => "tab",
+                Some("application/zip") => "zip",
+                Some(mime) => mime_guess::get_mime_extensions_str(mime)
+                    .and_then(|exts| exts.first())
+                    .copied()
+                    .unwrap_or("bin"),
+                None => "bin",
+            };
+            let name = format!("{id}.{extension}");
+            let endpoint = Endpoint {
+                parent_url: dir.api_url(),
+                key: Some(format!("distribution.{idx}")),
+            };
+            let file = FileMeta::new(
+                None,
+                Some(format!("{DOI_PREFIX}/{id}")),
+                dir.join(&name),
+                endpoint,
+                download_url,
+                // the data files are generated on request, their size is not known upfront
+                None,
+                vec![],
+                format.and_then(|mime| mime.parse().ok()),
+                None,
+                json_str(json, "/datePublished"),
+                json_str(json, "/dateModified"),
+                true,
+            );
+            entries.push(Entry::File(file));
+        }
+        Ok(entries)
+    }
+}
+
+#[async_trait]
+impl DatasetBackend for Pangaea {
+    fn name(&self) -> &'static str {
+        "pangaea"
+    }
+    fn record_id(&self) -> String {
+        self.id.clone()
+    }
+    fn root_url(&self) -> Url {
+        // https://doi.pangaea.de/10.1594/PANGAEA.942110?format=metadata_jsonld
+        self.dataset_url(&self.id, "metadata_jsonld")
+            .unwrap_or_else(|_| self.base_url.clone())
+    }
+
+    async fn list(&self, client: &Client, dir: DirMeta) -> Result<Vec<Entry>, Exn<RepoError>> {
+        let json = get_json(client, &dir.api_url()).await?;
+        self.analyse_json(&json, &dir)
+    }
+
+    fn archive_url(&self) -> Option<Url> {
+        // https://doi.pangaea.de/10.1594/PANGAEA.942110?format=zip
+        self.dataset_url(&self.id, "zip").ok()
+    }
+
+    async fn raw_metadata(&self, client: &Client) -> Result<JsonValue, Exn<RepoError>> {
+        get_json(client, &self.root_url()).await
+    }
+
+    async fn metadata(&self, client: &Client) -> Result<DatasetMetadata, Exn<RepoError>> {
+        let json = self.raw_metadata(client).await?;
+        Ok(metadata_from_json(&json))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_pangaea_list_collection() {
+        let mock_server = MockServer::start().await;
+        let base_url = Url::parse(&format!("{}/", mock_server.uri())).unwrap();
+        Mock::given(method("GET"))
+            .and(path("/10.1594/PANGAEA.900000"))
+            .and(query_param("format", "metadata_jsonld"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "@type": "Dataset",
+                "identifier": "https://doi.org/10.1594/PANGAEA.900000",
+                "name": "Ocean temperature collection",
+                "creator": { "@type": "Person", "name": "Doe, Jane" },
+                "license": "https://creativecommons.org/licenses/by/4.0/",
+                "datePublished": "2019-01-21",
+                "distribution": {
+                    "@type": "DataDownload",
+                    "encodingFormat": "application/zip",
+                    "contentUrl": format!("{base_url}10.1594/PANGAEA.900000?format=zip"),
+                },
+                "hasPart": [
+                    { "@id": "https://doi.org/10.1594/PANGAEA.900001", "@type": "Dataset" },
+                ],
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/10.1594/PANGAEA.900001"))
+            .and(query_param("format", "metadata_jsonld"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "@type": "Dataset",
+                "identifier": "https://doi.org/10.1594/PANGAEA.900001",
+                "distribution": [{
+                    "@type": "DataDownload",
+                    "encodingFormat": "text/tab-separated-values",
+                    "contentUrl": format!("{base_url}10.1594/PANGAEA.900001?format=textfile"),
+                }],
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let pangaea = Pangaea::new("PANGAEA.900000", &base_url);
+        let client = Client::new();
+        let entries = pangaea
+            .list(&client, DirMeta::new_root(&pangaea.root_url()))
+            .await
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        let Entry::Dir(dir) = &entries[0] else {
+            panic!("expect a dir")
+        };
+        assert_eq!(dir.relative().as_str(), "PANGAEA.900001");
+
+        let entries = pangaea.list(&client, dir.clone()).await.unwrap();
+        let Entry::File(file) = &entries[0] else {
+            panic!("expect a file")
+        };
+        assert_eq!(
+            file.relative().as_str(),
+            "PANGAEA.900001/PANGAEA.900001.tab"
+        );
+        assert_eq!(
+            file.download_url().as_str(),
+            format!("{base_url}10.1594/PANGAEA.900001?format=textfile")
+        );
+
+        let metadata = pangaea.metadata(&client).await.unwrap();
+        assert_eq!(metadata.creators, vec!["Doe, Jane".to_string()]);
+        assert_eq!(metadata.doi.as_deref(), Some("10.1594/PANGAEA.900000"));
+    }
+}
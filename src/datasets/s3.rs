@@ -0,0 +1,342 @@
+use async_trait::async_trait;
+use exn::{Exn, OptionExt, ResultExt};
+use url::Url;
+use xmltree::Element;
+
+use reqwest::Client;
+use std::{any::Any, borrow::Cow};
+
+use crate::{
+    http::get_xml,
+    repo::{Endpoint, FileMeta, RepoError},
+    Checksum, DatasetBackend, DirMeta, Entry,
+};
+
+fn child_text<'a>(elem: &'a Element, name: &str) -> Option<Cow<'a, str>> {
+    elem.get_child(name).and_then(Element::get_text)
+}
+
+// The ETag of an object uploaded in a single part is the md5 of its content, the ETag of a
+// multipart upload ('<md5 of md5s>-<parts>') can not be validated against the content.
+fn etag_checksum(etag: &str) -> Option<Checksum> {
+    Checksum::parse("md5", etag.trim_matches('"')).ok()
+}
+
+// Url of an object, the key is split on '/' so every segment is percent encoded on its own.
+fn object_url(bucket_url: &Url, key: &str) -> Url {
+    let mut url = bucket_url.clone();
+    // Safe to unwrap: bucket urls are http(s) urls, which can be base
+    url.path_segments_mut()
+        .unwrap()
+        .pop_if_empty()
+        .extend(key.split('/'));
+    url
+}
+
+/// Url listing the objects and sub-prefixes right below `prefix`, continuing at `token`.
+fn list_url(bucket_url: &Url, prefix: &str, token: Option<&str>) -> Url {
+    let mut url = bucket_url.clone();
+    {
+        let mut query = url.query_pairs_mut();
+        query
+            .clear()
+            .append_pair("list-type", "2")
+            .append_pair("delimiter", "/")
+            .append_pair("prefix", prefix);
+        if let Some(token) = token {
+            query.append_pair("continuation-token", token);
+        }
+    }
+    url
+}
+
+fn prefix_of(url: &Url) -> String {
+    url.query_pairs()
+        .find(|(k, _)| k == "prefix")
+        .map(|(_, v)| v.into_owned())
+        .unwrap_or_default()
+}
+
+/// Parses one page of a `ListObjectsV2` response, returns the entries and the continuation
+/// token of the next page if the listing is truncated.
+fn analyse_xml(
+    tree: &Element,
+    bucket_url: &Url,
+    dir: &DirMeta,
+    page_url: &Url,
+) -> Result<(Vec<Entry>, Option<String>), Exn<RepoError>> {
+    let prefix = prefix_of(&dir.api_url());
+    let mut entries = Vec::new();
+    for (idx, elem) in tree
+        .children
+        .iter()
+        .filter_map(|n| n.as_element())
+        .enumerate()
+    {
+        match elem.name.as_str() {
+            "Contents" => {
+                let key = child_text(elem, "Key").ok_or_raise(|| RepoError {
+                    message: format!("'Contents' without 'Key' at {page_url}"),
+                })?;
+                let Some(name) = key.strip_prefix(&prefix).filter(|n| !n.is_empty()) else {
+                    continue;
+                };
+                // zero sized folder placeholders created by some S3 clients
+                if name.ends_with('/') {
+                    continue;
+                }
+                let size = child_text(elem, "Size")
+                    .map(|s| {
+                        s.trim().parse::<u64>().or_raise(|| RepoError {
+                            message: format!("cannot parse size of '{key}'"),
+                        })
+                    })
+                    .transpose()?;
+                let checksum = child_text(elem, "ETag")
+                    .and_then(|etag| etag_checksum(&etag))
+                    .into_iter()
+                    .collect();
+                let endpoint = Endpoint {
+                    parent_url: page_url.clone(),
+                    key: Some(format!("ListBucketResult.*[{idx}]")),
+                };
+                let guess = mime_guess::from_path(name);
+                let file = FileMeta::new(
+                    Some(name.to_string()),
+                    Some(key.to_string()),
+                    dir.join(name),
+                    endpoint,
+                    object_url(bucket_url, &key),
+                    size,
+                    checksum,
+                    guess.first(),
+                    None,
+                    None,
+                    child_text(elem, "LastModified").map(Cow::into_owned),
+                    true,
+                );
+                entries.push(Entry::File(file));
+            }
+            "CommonPrefixes" => {
+                let sub_prefix = child_text(elem, "Prefix").ok_or_raise(|| RepoError {
+                    message: format!("'CommonPrefixes' without 'Prefix' at {page_url}"),
+                })?;
+                let Some(name) = sub_prefix
+                    .strip_prefix(&prefix)
+                    .map(|n| n.trim_end_matches('/'))
+                    .filter(|n| !n.is_empty())
+                else {
+                    continue;
+                };
+                let dir = DirMeta::new(
+                    dir.join(name),
+                    list_url(bucket_url, &sub_prefix, None),
+                    dir.root_url(),
+                );
+                entries.push(Entry::Dir(dir));
+            }
+            _ => (),
+        }
+    }
+
+    let truncated = child_text(tree, "IsTruncated").is_some_and(|t| t.trim() == "true");
+    let token = if truncated {
+        let token = child_text(tree, "NextContinuationToken").ok_or_raise(|| RepoError {
+            message: format!("truncated listing without continuation token at {page_url}"),
+        })?;
+        Some(token.into_owned())
+    } else {
+        None
+    };
+    Ok((entries, token))
+}
+
+// https://docs.aws.amazon.com/AmazonS3/latest/API/API_ListObjectsV2.html
+//
+// Public buckets (e.g. OpenNeuro at 's3://openneuro.org') are listed anonymously with the
+// ListObjectsV2 API, one prefix level per call using '/' as delimiter, so sub-prefixes are
+// crawled as folders.
+#[derive(Debug)]
+pub struct S3Public {
+    /// url of the bucket, e.g. `https://my-bucket.s3.amazonaws.com/`.
+    pub bucket_url: Url,
+    /// prefix of the objects to download, empty for the whole bucket.
+    pub prefix: String,
+}
+
+impl S3Public {
+    /// Creates a backend for the objects below `prefix` in the bucket at `bucket_url`.
+    #[must_use]
+    pub fn new(bucket_url: &Url, prefix: &str) -> Self {
+        let mut bucket_url = bucket_url.clone();
+        if !bucket_url.path().ends_with('/') {
+            let path = format!("{}/", bucket_url.path());
+            bucket_url.set_path(&path);
+        }
+        bucket_url.set_query(None);
+        let prefix = prefix.trim_start_matches('/');
+        let prefix = if prefix.is_empty() || prefix.ends_with('/') {
+            prefix.to_string()
+        } else {
+            format!("{prefix}/")
+        };
+        S3Public { bucket_url, prefix }
+    }
+
+    /// Creates a backend for a bucket of the AWS global endpoint, bucket names with dots
+    /// are addressed in path style since they don't match the TLS certificate of the
+    /// virtual host.
+    ///
+    /// # Panics
+    /// if `bucket` is not a valid host name.
+    #[must_use]
+    pub fn aws(bucket: &str, prefix: &str) -> Self {
+        let bucket_url = if bucket.contains('.') {
+            format!("https://s3.amazonaws.com/{bucket}/")
+        } else {
+            format!("https://{bucket}.s3.amazonaws.com/")
+        };
+        let bucket_url = Url::parse(&bucket_url).expect("bucket is a valid host name");
+        S3Public::new(&bucket_url, prefix)
+    }
+}
+
+#[async_trait]
+impl DatasetBackend for S3Public {
+    fn name(&self) -> &'static str {
+        "s3"
+    }
+    fn record_id(&self) -> String {
+        format!("{}{}", self.bucket_url, self.prefix)
+    }
+    fn root_url(&self) -> Url {
+        list_url(&self.bucket_url, &self.prefix, None)
+    }
+
+    async fn list(&self, client: &Client, dir: DirMeta) -> Result<Vec<Entry>, Exn<RepoError>> {
+        let mut entries = Vec::new();
+        let mut page_url = dir.api_url();
+        loop {
+            let tree = get_xml(client, &page_url).await?;
+
+            let (page, token) = analyse_xml(&tree, &self.bucket_url, &dir, &page_url)?;
+            entries.extend(page);
+
+            match token {
+                Some(token) => {
+                    page_url = list_url(&self.bucket_url, &prefix_of(&page_url), Some(&token));
+                }
+                None => break,
+            }
+        }
+
+        Ok(entries)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use wiremock::{
+        matchers::{method, path, query_param, query_param_is_missing},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[test]
+    fn test_s3_new() {
+        let s3 = S3Public::aws("openneuro.org", "ds000001");
+        assert_eq!(
+            s3.root_url().as_str(),
+            "https://s3.amazonaws.com/openneuro.org/?list-type=2&delimiter=%2F&prefix=ds000001%2F"
+        );
+        let s3 = S3Public::aws("my-bucket", "");
+        assert_eq!(
+            s3.root_url().as_str(),
+            "https://my-bucket.s3.amazonaws.com/?list-type=2&delimiter=%2F&prefix="
+        );
+    }
+
+    #[test]
+    fn test_etag_checksum() {
+        assert!(matches!(
+            etag_checksum("\"8D777F385D3DFEC8815D20F7496026DC\""),
+            Some(Checksum::Md5(h)) if h == "8d777f385d3dfec8815d20f7496026dc"
+        ));
+        // multipart upload
+        assert!(etag_checksum("\"d41d8cd98f00b204e9800998ecf8427e-3\"").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_s3_list_paginated() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/bucket/"))
+            .and(query_param("prefix", "ds/"))
+            .and(query_param_is_missing("continuation-token"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListBucketResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+  <Name>bucket</Name><Prefix>ds/</Prefix><IsTruncated>true</IsTruncated>
+  <NextContinuationToken>next/token=</NextContinuationToken>
+  <Contents>
+    <Key>ds/</Key><Size>0</Size>
+  </Contents>
+  <Contents>
+    <Key>ds/a b.txt</Key><LastModified>2024-01-01T00:00:00.000Z</LastModified>
+    <ETag>"8d777f385d3dfec8815d20f7496026dc"</ETag><Size>4</Size>
+  </Contents>
+  <CommonPrefixes><Prefix>ds/sub/</Prefix></CommonPrefixes>
+</ListBucketResult>"#,
+            ))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/bucket/"))
+            .and(query_param("prefix", "ds/"))
+            .and(query_param("continuation-token", "next/token="))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"<ListBucketResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+  <IsTruncated>false</IsTruncated>
+  <Contents>
+    <Key>ds/big.bin</Key><ETag>"d41d8cd98f00b204e9800998ecf8427e-3"</ETag><Size>99</Size>
+  </Contents>
+</ListBucketResult>"#,
+            ))
+            .mount(&mock_server)
+            .await;
+
+        let bucket_url = Url::from_str(&format!("{}/bucket", mock_server.uri())).unwrap();
+        let s3 = S3Public::new(&bucket_url, "ds");
+        let dir = DirMeta::new_root(&s3.root_url());
+        let entries = s3.list(&Client::new(), dir).await.unwrap();
+        assert_eq!(entries.len(), 3);
+
+        let Entry::File(file) = &entries[0] else {
+            panic!("expect a file")
+        };
+        assert_eq!(file.relative().as_str(), "a b.txt");
+        assert_eq!(
+            file.download_url().as_str(),
+            format!("{}/bucket/ds/a%20b.txt", mock_server.uri())
+        );
+        assert_eq!(file.size(), Some(4));
+        assert_eq!(file.checksum().len(), 1);
+
+        let Entry::Dir(dir) = &entries[1] else {
+            panic!("expect a dir")
+        };
+        assert_eq!(dir.relative().as_str(), "sub");
+        assert_eq!(prefix_of(&dir.api_url()), "ds/sub/");
+
+        let Entry::File(file) = &entries[2] else {
+            panic!("expect a file")
+        };
+        assert_eq!(file.relative().as_str(), "big.bin");
+        assert!(file.checksum().is_empty());
+    }
+}
@@ -0,0 +1,420 @@
+use async_trait::async_trait;
+use exn::{Exn, OptionExt, ResultExt};
+use serde_json::Value as JsonValue;
+use url::Url;
+
+use reqwest::{header::HeaderMap, Client};
+use std::{any::Any, str::FromStr};
+
+use crate::auth::bearer;
+use crate::helper::json_extract;
+use crate::http::get_json;
+use crate::{
+    repo::{Endpoint, FileMeta, RepoError},
+    AuthConfig, Checksum, DatasetBackend, DirMeta, Entry,
+};
+
+pub const SOFTWARE_HERITAGE_URL: &str = "https://archive.softwareheritage.org/";
+
+/// Object types of a SWHID, `swh:1:<type>:<sha1_git>`.
+const OBJECT_TYPES: [&str; 5] = ["cnt", "dir", "rev", "rel", "snp"];
+
+// https://archive.softwareheritage.org/
+// API doc at https://archive.softwareheritage.org/api/1/
+// Archived objects are identified by SWHIDs (https://www.swhid.org/), e.g.
+// 'swh:1:dir:<sha1_git>'. Revisions, releases and snapshots are resolved to their root
+// directory, which is listed one directory per request. Anonymous requests are limited to
+// 120 per hour, a token raises the limit.
+#[derive(Debug)]
+pub struct SoftwareHeritage {
+    pub base_url: Url,
+    /// one of `cnt`, `dir`, `rev`, `rel` or `snp`.
+    pub object_type: String,
+    /// sha1_git of the object, hex encoded.
+    pub object_id: String,
+}
+
+impl SoftwareHeritage {
+    #[must_use]
+    pub fn new(
+        object_type: impl Into<String>,
+        object_id: impl Into<String>,
+        base_url: &Url,
+    ) -> Self {
+        SoftwareHeritage {
+            base_url: base_url.clone(),
+            object_type: object_type.into(),
+            object_id: object_id.into(),
+        }
+    }
+
+    /// Parses the core of a SWHID, `swh:1:<type>:<sha1_git>`, qualifiers after `;` are
+    /// ignored.
+    #[must_use]
+    pub fn from_swhid(swhid: &str, base_url: &Url) -> Option<Self> {
+        let core = swhid.split(';').next().unwrap_or_default();
+        let mut parts = core.split(':');
+        let (Some("swh"), Some("1"), Some(object_type), Some(object_id), None) = (
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+        ) else {
+            return None;
+        };
+        if !OBJECT_TYPES.contains(&object_type)
+            || object_id.len() != 40
+            || !object_id.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            return None;
+        }
+        Some(SoftwareHeritage::new(
+            object_type,
+            object_id.to_lowercase(),
+            base_url,
+        ))
+    }
+
+    /// Returns the SWHID of the archived object.
+    #[must_use]
+    pub fn swhid(&self) -> String {
+        format!("swh:1:{}:{}", self.object_type, self.object_id)
+    }
+
+    fn api_url(&self, segments: &[&str]) -> Result<Url, Exn<RepoError>> {
+        let mut url = self.base_url.clone();
+        url.path_segments_mut()
+            .map_err(|()| RepoError {
+                message: format!("'{}' cannot be base", self.base_url),
+            })?
+            .pop_if_empty()
+            .extend(["api", "1"])
+            .extend(segments)
+            .push("");
+        Ok(url)
+    }
+
+    fn directory_url(&self, id: &str) -> Result<Url, Exn<RepoError>> {
+        self.api_url(&["directory", id])
+    }
+
+    fn content_url(&self, id: &str) -> Result<Url, Exn<RepoError>> {
+        self.api_url(&["content", &format!("sha1_git:{id}")])
+    }
+
+    fn raw_url(&self, id: &str) -> Result<Url, Exn<RepoError>> {
+        self.api_url(&["content", &format!("sha1_git:{id}"), "raw"])
+    }
+
+    /// Follows releases, snapshots and revisions down to the id of their root directory.
+    async fn root_directory(&self, client: &Client) -> Result<String, Exn<RepoError>> {
+        let mut object_type = self.object_type.clone();
+        let mut id = self.object_id.clone();
+        loop {
+            match object_type.as_str() {
+                "dir" | "directory" => return Ok(id),
+                "rev" | "revision" => {
+                    let json = get_json(client, &self.api_url(&["revision", &id])?).await?;
+                    id = json_extract(&json, "directory").or_raise(|| RepoError {
+                        message: format!("no 'directory' of revision '{id}'"),
+                    })?;
+                    object_type = "dir".to_string();
+                }
+                "rel" | "release" => {
+                    let json = get_json(client, &self.api_url(&["release", &id])?).await?;
+                    object_type = json_extract(&json, "target_type").or_raise(|| RepoError {
+                        message: format!("no 'target_type' of release '{id}'"),
+                    })?;
+                    id = json_extract(&json, "target").or_raise(|| RepoError {
+                        message: format!("no 'target' of release '{id}'"),
+                    })?;
+                }
+                "snp" | "snapshot" => {
+                    let json = get_json(client, &self.api_url(&["snapshot", &id])?).await?;
+                    (object_type, id) = snapshot_head(&json).ok_or_raise(|| RepoError {
+                        message: format!("no 'HEAD' branch in snapshot '{id}'"),
+                    })?;
+                }
+                typ => exn::bail!(RepoError {
+                    message: format!("'{typ}' object of '{}' has no directory", self.swhid())
+                }),
+            }
+        }
+    }
+
+    /// Lists a content SWHID as a single file, named by the id since contents have no name.
+    async fn list_content(&self, client: &Client, dir: &DirMeta) -> Result<Entry, Exn<RepoError>> {
+        let json = get_json(client, &self.content_url(&self.object_id)?).await?;
+        let endpoint = Endpoint {
+            parent_url: dir.api_url(),
+            key: None,
+        };
+        Ok(Entry::File(FileMeta::new(
+            None,
+            Some(self.swhid()),
+            dir.join(&self.object_id),
+            endpoint,
+            self.raw_url(&self.object_id)?,
+            json_extract(&json, "length").ok(),
+            checksums(&json, &self.object_id)?,
+            None,
+            None,
+            None,
+            None,
+            true,
+        )))
+    }
+
+    fn analyse_directory(
+        &self,
+        json: &JsonValue,
+        dir: &DirMeta,
+    ) -> Result<Vec<Entry>, Exn<RepoError>> {
+        let items = json.as_array().ok_or_raise(|| RepoError {
+            message: format!("directory listing of {} is not a json array", dir.api_url()),
+        })?;
+
+        let mut entries = Vec::with_capacity(items.len());
+        for (idx, item) in items.iter().enumerate() {
+            let name: String = json_extract(item, "name").or_raise(|| RepoError {
+                message: "fail to extracting 'name' as String from json".to_string(),
+            })?;
+            let typ: String = json_extract(item, "type").or_raise(|| RepoError {
+                message: "fail to extracting 'type' as String from json".to_string(),
+            })?;
+            let target: String = json_extract(item, "target").or_raise(|| RepoError {
+                message: "fail to extracting 'target' as String from json".to_string(),
+            })?;
+            match typ.as_str() {
+                "file" => {
+                    let endpoint = Endpoint {
+                        parent_url: dir.api_url(),
+                        key: Some(format!("{idx}")),
+                    };
+                    let file = FileMeta::new(
+                        None,
+                        Some(format!("swh:1:cnt:{target}")),
+                        dir.join(&name),
+                        endpoint,
+                        self.raw_url(&target)?,
+                        json_extract(item, "length").ok(),
+                        checksums(item, &name)?,
+                        mime_guess::from_path(&name).first(),
+                        None,
+                        None,
+                        None,
+                        true,
+                    );
+                    entries.push(Entry::File(file));
+                }
+                "dir" => {
+                    let api_url = self.directory_url(&target)?;
+                    entries.push(Entry::Dir(DirMeta::new(
+                        dir.join(&name),
+                        api_url,
+                        dir.root_url(),
+                    )));
+                }
+                // submodules, i.e. revisions of another repository, are not archived in the
+                // directory
+                _ => {}
+            }
+        }
+        Ok(entries)
+    }
+}
+
+/// Target of the `HEAD` branch of a snapshot json, following its alias.
+fn snapshot_head(json: &JsonValue) -> Option<(String, String)> {
+    let branches = json.get("branches")?;
+    let mut branch = branches.get("HEAD")?;
+    // an alias targets another branch, e.g. 'refs/heads/main'
+    for _ in 0..branches.as_object()?.len() {
+        let target_type = branch.get("target_type")?.as_str()?;
+        let target = branch.get("target")?.as_str()?;
+        if target_type != "alias" {
+            return Some((target_type.to_string(), target.to_string()));
+        }
+        branch = branches.get(target)?;
+    }
+    None
+}
+
+// directory entries and contents list the checksums at 'checksums', e.g.
+// '{"sha1": ..., "sha1_git": ..., "sha256": ..., "blake2s256": ...}'
+fn checksums(json: &JsonValue, name: &str) -> Result<Vec<Checksum>, Exn<RepoError>> {
+    let mut checksums = Vec::new();
+    for algo in ["sha256", "sha1"] {
+        if let Some(hash) = json
+            .get("checksums")
+            .and_then(|c| c.get(algo))
+            .and_then(JsonValue::as_str)
+        {
+            checksums.push(Checksum::parse(algo, hash).or_raise(|| RepoError {
+                message: format!("invalid checksum of '{name}'"),
+            })?);
+        }
+    }
+    Ok(checksums)
+}
+
+#[async_trait]
+impl DatasetBackend for SoftwareHeritage {
+    fn name(&self) -> &'static str {
+        "softwareheritage"
+    }
+    fn record_id(&self) -> String {
+        self.swhid()
+    }
+    fn root_url(&self) -> Url {
+        // https://archive.softwareheritage.org/swh:1:dir:<sha1_git>
+        let url = format!("{}{}", self.base_url, self.swhid());
+        Url::from_str(&url).unwrap_or_else(|_| self.base_url.clone())
+    }
+
+    async fn list(&self, client: &Client, dir: DirMeta) -> Result<Vec<Entry>, Exn<RepoError>> {
+        let api_url = if dir.relative().as_str().is_empty() {
+            if self.object_type == "cnt" {
+                return Ok(vec![self.list_content(client, &dir).await?]);
+            }
+            let id = self.root_directory(client).await?;
+            self.directory_url(&id)?
+        } else {
+            dir.api_url()
+        };
+        let json = get_json(client, &api_url).await?;
+        self.analyse_directory(&json, &dir)
+    }
+
+    async fn raw_metadata(&self, client: &Client) -> Result<JsonValue, Exn<RepoError>> {
+        let url = match self.object_type.as_str() {
+            "cnt" => self.content_url(&self.object_id)?,
+            "dir" => self.directory_url(&self.object_id)?,
+            "rev" => self.api_url(&["revision", &self.object_id])?,
+            "rel" => self.api_url(&["release", &self.object_id])?,
+            _ => self.api_url(&["snapshot", &self.object_id])?,
+        };
+        get_json(client, &url).await
+    }
+
+    fn auth_headers(&self, auth: &AuthConfig) -> HeaderMap {
+        bearer(auth.softwareheritage.as_deref())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    const REV: &str = "aafb16d69fd30ff58afdd69036a26047f3aebdc6";
+    const ROOT: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+    const SUB: &str = "b0e4c3b8e2b4e8a5c7f6f0b1c4f3d7f1c0b1a2c3";
+
+    #[test]
+    fn test_from_swhid() {
+        let base_url = Url::parse(SOFTWARE_HERITAGE_URL).unwrap();
+        let swh =
+            SoftwareHeritage::from_swhid(&format!("swh:1:rev:{REV};origin=https://x"), &base_url)
+                .unwrap();
+        assert_eq!(swh.object_type, "rev");
+        assert_eq!(swh.swhid(), format!("swh:1:rev:{REV}"));
+        assert_eq!(
+            swh.root_url().as_str(),
+            format!("https://archive.softwareheritage.org/swh:1:rev:{REV}")
+        );
+        assert!(SoftwareHeritage::from_swhid("swh:1:ori:abc", &base_url).is_none());
+        assert!(SoftwareHeritage::from_swhid("swh:2:dir:abc", &base_url).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_software_heritage_list_revision() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(format!("/api/1/revision/{REV}/")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": REV,
+                "directory": ROOT,
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(format!("/api/1/directory/{ROOT}/")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                {
+                    "dir_id": ROOT,
+                    "name": "README.md",
+                    "type": "file",
+                    "target": "8d777f385d3dfec8815d20f7496026dc8d777f38",
+                    "length": 4,
+                    "checksums": {
+                        "sha1": "a9993e364706816aba3e25717850c26c9cd0d89d",
+                        "sha1_git": "8d777f385d3dfec8815d20f7496026dc8d777f38",
+                        "sha256": "88d4266fd4e6338d13b845fcf289579d209c897823b9217da3e161936f031589",
+                    },
+                },
+                { "dir_id": ROOT, "name": "src", "type": "dir", "target": SUB },
+                { "dir_id": ROOT, "name": "vendor", "type": "rev", "target": REV },
+            ])))
+            .mount(&mock_server)
+            .await;
+
+        let base_url = Url::parse(&format!("{}/", mock_server.uri())).unwrap();
+        let swh = SoftwareHeritage::new("rev", REV, &base_url);
+        let entries = swh
+            .list(&Client::new(), DirMeta::new_root(&swh.root_url()))
+            .await
+            .unwrap();
+
+        assert_eq!(entries.len(), 2);
+        let Entry::File(file) = &entries[0] else {
+            panic!("expect a file")
+        };
+        assert_eq!(file.relative().as_str(), "README.md");
+        assert_eq!(
+            file.download_url().as_str(),
+            format!(
+                "{}/api/1/content/sha1_git:8d777f385d3dfec8815d20f7496026dc8d777f38/raw/",
+                mock_server.uri()
+            )
+        );
+        assert_eq!(
+            file.file_identifier(),
+            Some("swh:1:cnt:8d777f385d3dfec8815d20f7496026dc8d777f38")
+        );
+        assert!(matches!(
+            file.checksum(),
+            [Checksum::Sha256(_), Checksum::Sha1(_)]
+        ));
+        let Entry::Dir(dir) = &entries[1] else {
+            panic!("expect a dir")
+        };
+        assert_eq!(dir.relative().as_str(), "src");
+        assert_eq!(
+            dir.api_url().as_str(),
+            format!("{}/api/1/directory/{SUB}/", mock_server.uri())
+        );
+    }
+
+    #[test]
+    fn test_snapshot_head() {
+        let json = serde_json::json!({
+            "branches": {
+                "HEAD": { "target": "refs/heads/main", "target_type": "alias" },
+                "refs/heads/main": { "target": REV, "target_type": "revision" },
+            }
+        });
+        assert_eq!(
+            snapshot_head(&json),
+            Some(("revision".to_string(), REV.to_string()))
+        );
+    }
+}
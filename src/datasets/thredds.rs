@@ -0,0 +1,394 @@
+use async_trait::async_trait;
+use exn::{Exn, ResultExt};
+use url::Url;
+use xmltree::Element;
+
+use reqwest::Client;
+use std::any::Any;
+use tracing::warn;
+
+use crate::{
+    http::get_xml,
+    repo::{Endpoint, FileMeta, RepoError},
+    Checksum, DatasetBackend, DirMeta, Entry,
+};
+
+// Access services declared by a catalog, the `base` of each is resolved against the catalog url
+// and the `urlPath` of a dataset is appended to it to get the access url.
+// Compound services are flattened, datasets are not matched against the `serviceName` they
+// reference since catalogs in the wild declare at most one service of each type.
+#[derive(Debug, Default)]
+struct Services {
+    http: Option<Url>,
+    ncss: Option<Url>,
+    opendap: Option<Url>,
+}
+
+impl Services {
+    fn collect(elem: &Element, catalog_url: &Url, services: &mut Services) {
+        for child in elem.children.iter().filter_map(|n| n.as_element()) {
+            if child.name != "service" {
+                continue;
+            }
+            let base = child
+                .attributes
+                .get("base")
+                .and_then(|base| catalog_url.join(base).ok());
+            match child
+                .attributes
+                .get("serviceType")
+                .map(|t| t.to_lowercase())
+                .as_deref()
+            {
+                Some("compound") => Services::collect(child, catalog_url, services),
+                Some("httpserver") => services.http = services.http.take().or(base),
+                Some("netcdfsubset") => services.ncss = services.ncss.take().or(base),
+                Some("opendap") => services.opendap = services.opendap.take().or(base),
+                _ => (),
+            }
+        }
+    }
+}
+
+// size is only exact when given in bytes, e.g. '<dataSize units="bytes">1024</dataSize>', the
+// rounded 'Kbytes'/'Mbytes' sizes would fail the size validation.
+fn data_size(elem: &Element) -> Option<u64> {
+    let size = elem.get_child("dataSize")?;
+    if !size
+        .attributes
+        .get("units")
+        .is_some_and(|u| u.eq_ignore_ascii_case("bytes"))
+    {
+        return None;
+    }
+    size.get_text()?.trim().parse().ok()
+}
+
+// checksums are not part of the catalog spec, servers that publish them do it as properties,
+// e.g. '<property name="md5" value="..."/>', malformed values are ignored since they are not
+// part of the spec.
+fn checksums(elem: &Element) -> Vec<Checksum> {
+    elem.children
+        .iter()
+        .filter_map(|n| n.as_element())
+        .filter(|e| e.name == "property")
+        .filter_map(|e| {
+            let name = e.attributes.get("name")?.to_lowercase();
+            if !Checksum::is_supported(&name) {
+                return None;
+            }
+            let value = e.attributes.get("value")?;
+            Checksum::parse(&name, value)
+                .inspect_err(|err| warn!("ignore checksum property, {err}"))
+                .ok()
+        })
+        .collect()
+}
+
+fn date(elem: &Element, typ: &str) -> Option<String> {
+    elem.children
+        .iter()
+        .filter_map(|n| n.as_element())
+        .find(|e| e.name == "date" && e.attributes.get("type").is_some_and(|t| t == typ))
+        .and_then(Element::get_text)
+        .map(|s| s.trim().to_string())
+}
+
+struct Catalog<'a> {
+    dir: &'a DirMeta,
+    services: Services,
+    subset: Option<&'a str>,
+}
+
+impl Catalog<'_> {
+    fn file(&self, elem: &Element, url_path: &str, prefix: &str) -> Result<Entry, Exn<RepoError>> {
+        let catalog_url = self.dir.api_url();
+        let name = url_path
+            .rsplit('/')
+            .find(|s| !s.is_empty())
+            .unwrap_or(url_path)
+            .to_string();
+        let join = |base: &Url, suffix: &str| {
+            base.join(&format!("{}{suffix}", url_path.trim_start_matches('/')))
+                .or_raise(|| RepoError {
+                    message: format!("fail to build access url of '{url_path}' from '{base}'"),
+                })
+        };
+        // a subset is generated by the server, the published size and checksum are the ones of
+        // the whole file.
+        let (download_url, downloadable, size, checksum) = if let Some(query) = self.subset {
+            let mut url = if let Some(ncss) = &self.services.ncss {
+                join(ncss, "")?
+            } else if let Some(opendap) = &self.services.opendap {
+                // file out response of OPeNDAP (Hyrax) servers, the query is a constraint
+                // expression.
+                join(opendap, ".nc")?
+            } else {
+                exn::bail!(RepoError {
+                    message: format!(
+                        "no 'NetcdfSubset' or 'OPeNDAP' service to subset '{url_path}' at {catalog_url}"
+                    ),
+                })
+            };
+            url.set_query(Some(query));
+            (url, true, None, vec![])
+        } else if let Some(http) = &self.services.http {
+            (join(http, "")?, true, data_size(elem), checksums(elem))
+        } else if let Some(opendap) = &self.services.opendap {
+            // the dataset is only served through OPeNDAP, it can not be downloaded as a whole
+            (join(opendap, "")?, false, data_size(elem), checksums(elem))
+        } else {
+            exn::bail!(RepoError {
+                message: format!("no access service for '{url_path}' at {catalog_url}"),
+            })
+        };
+
+        let endpoint = Endpoint {
+            parent_url: catalog_url,
+            key: Some(format!("dataset[@urlPath='{url_path}']")),
+        };
+        let guess = mime_guess::from_path(&name);
+        let file = FileMeta::new(
+            Some(name.clone()),
+            elem.attributes.get("ID").cloned(),
+            self.dir.join(&format!("{prefix}{name}")),
+            endpoint,
+            download_url,
+            size,
+            checksum,
+            guess.first(),
+            None,
+            date(elem, "created"),
+            date(elem, "modified"),
+            downloadable,
+        );
+        Ok(Entry::File(file))
+    }
+
+    // Nested datasets are collections, they are mapped to folders by prefixing the path of
+    // their files with the collection name. The top level datasets are not, as a catalog
+    // usually wraps all its datasets in a single one.
+    fn walk(
+        &self,
+        elem: &Element,
+        prefix: &str,
+        nested: bool,
+        entries: &mut Vec<Entry>,
+    ) -> Result<(), Exn<RepoError>> {
+        for child in elem.children.iter().filter_map(|n| n.as_element()) {
+            match child.name.as_str() {
+                "catalogRef" => {
+                    let Some(href) = child.attributes.get("href") else {
+                        exn::bail!(RepoError {
+                            message: format!("catalogRef without href at {}", self.dir.api_url()),
+                        })
+                    };
+                    let name = child
+                        .attributes
+                        .get("title")
+                        .or_else(|| child.attributes.get("name"))
+                        .filter(|s| !s.is_empty())
+                        .map_or_else(
+                            || href.trim_end_matches("/catalog.xml").to_string(),
+                            Clone::clone,
+                        );
+                    let api_url = self.dir.api_url().join(href).or_raise(|| RepoError {
+                        message: format!("fail to resolve catalogRef '{href}'"),
+                    })?;
+                    let dir = DirMeta::new(
+                        self.dir.join(&format!("{prefix}{name}")),
+                        api_url,
+                        self.dir.root_url(),
+                    );
+                    entries.push(Entry::Dir(dir));
+                }
+                "dataset" => {
+                    if let Some(url_path) = child.attributes.get("urlPath") {
+                        entries.push(self.file(child, url_path, prefix)?);
+                    }
+                    let prefix = match child.attributes.get("name") {
+                        Some(name) if nested => format!("{prefix}{name}/"),
+                        _ => prefix.to_string(),
+                    };
+                    self.walk(child, &prefix, true, entries)?;
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn analyse_catalog(
+    tree: &Element,
+    dir: &DirMeta,
+    subset: Option<&str>,
+) -> Result<Vec<Entry>, Exn<RepoError>> {
+    let mut services = Services::default();
+    Services::collect(tree, &dir.api_url(), &mut services);
+    let catalog = Catalog {
+        dir,
+        services,
+        subset,
+    };
+    let mut entries = Vec::new();
+    catalog.walk(tree, "", false, &mut entries)?;
+    Ok(entries)
+}
+
+// https://www.unidata.ucar.edu/software/tds/
+// Catalog spec at https://docs.unidata.ucar.edu/tds/current/userguide/basic_client_catalog.html
+//
+// THREDDS (and Hyrax) servers describe their datasets in xml catalogs, a 'catalogRef' points to
+// a sub-catalog and is crawled as a folder. Files are downloaded whole from the 'HTTPServer'
+// service or, with a subset query, generated by the 'NetcdfSubset' (or OPeNDAP) service.
+#[derive(Debug, Clone)]
+pub struct Thredds {
+    pub catalog_url: Url,
+    pub subset: Option<String>,
+}
+
+impl Thredds {
+    /// Creates a backend for the catalog at `catalog_url`, a `catalog.html` page is mapped to
+    /// its `catalog.xml`.
+    #[must_use]
+    pub fn new(catalog_url: &Url) -> Self {
+        let mut catalog_url = catalog_url.clone();
+        if let Some(path) = catalog_url.path().strip_suffix(".html") {
+            let path = format!("{path}.xml");
+            catalog_url.set_path(&path);
+        }
+        Thredds {
+            catalog_url,
+            subset: None,
+        }
+    }
+
+    /// Downloads a server side subset of every dataset instead of the whole file.
+    ///
+    /// `query` is appended as query string to the 'NetcdfSubset' service url, e.g.
+    /// `var=temp&north=50&south=40&west=0&east=10&accept=netcdf`, or to the
+    /// `.nc` response of the OPeNDAP service as constraint expression when the server has no
+    /// 'NetcdfSubset' service.
+    #[must_use]
+    pub fn subset(mut self, query: impl Into<String>) -> Self {
+        self.subset = Some(query.into());
+        self
+    }
+}
+
+#[async_trait]
+impl DatasetBackend for Thredds {
+    fn name(&self) -> &'static str {
+        "thredds"
+    }
+    fn root_url(&self) -> Url {
+        self.catalog_url.clone()
+    }
+
+    async fn list(&self, client: &Client, dir: DirMeta) -> Result<Vec<Entry>, Exn<RepoError>> {
+        let tree = get_xml(client, &dir.api_url()).await?;
+
+        analyse_catalog(&tree, &dir, self.subset.as_deref())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{io::Cursor, str::FromStr};
+
+    const CATALOG: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<catalog xmlns="http://www.unidata.ucar.edu/namespaces/thredds/InvCatalog/v1.0"
+         xmlns:xlink="http://www.w3.org/1999/xlink" version="1.2">
+  <service name="all" serviceType="Compound" base="">
+    <service name="odap" serviceType="OPENDAP" base="/thredds/dodsC/"/>
+    <service name="http" serviceType="HTTPServer" base="/thredds/fileServer/"/>
+    <service name="ncss" serviceType="NetcdfSubset" base="/thredds/ncss/grid/"/>
+  </service>
+  <dataset name="Ocean model" ID="ocean">
+    <metadata inherited="true"><serviceName>all</serviceName></metadata>
+    <dataset name="sst_2024.nc" ID="ocean/sst_2024.nc" urlPath="ocean/sst_2024.nc">
+      <dataSize units="bytes">1024</dataSize>
+      <date type="modified">2024-05-01T00:00:00Z</date>
+      <property name="md5" value="8D777F385D3DFEC8815D20F7496026DC"/>
+    </dataset>
+    <dataset name="daily">
+      <dataset name="d1.nc" urlPath="ocean/daily/d1.nc">
+        <dataSize units="Mbytes">1.5</dataSize>
+      </dataset>
+    </dataset>
+    <catalogRef xlink:href="archive/catalog.xml" xlink:title="archive" name=""/>
+  </dataset>
+</catalog>"#;
+
+    fn catalog_entries(subset: Option<&str>) -> Vec<Entry> {
+        let url = Url::from_str("https://example.org/thredds/catalog/ocean/catalog.xml").unwrap();
+        let tree = Element::parse(Cursor::new(CATALOG)).unwrap();
+        analyse_catalog(&tree, &DirMeta::new_root(&url), subset).unwrap()
+    }
+
+    #[test]
+    fn test_thredds_catalog() {
+        let entries = catalog_entries(None);
+        assert_eq!(entries.len(), 3);
+
+        let Entry::File(file) = &entries[0] else {
+            panic!("expect a file")
+        };
+        assert_eq!(file.relative().as_str(), "sst_2024.nc");
+        assert_eq!(
+            file.download_url().as_str(),
+            "https://example.org/thredds/fileServer/ocean/sst_2024.nc"
+        );
+        assert_eq!(file.size(), Some(1024));
+        assert!(matches!(
+            file.checksum(),
+            [Checksum::Md5(h)] if h == "8d777f385d3dfec8815d20f7496026dc"
+        ));
+        assert_eq!(file.last_modification_date(), Some("2024-05-01T00:00:00Z"));
+
+        let Entry::File(file) = &entries[1] else {
+            panic!("expect a file")
+        };
+        assert_eq!(file.relative().as_str(), "daily/d1.nc");
+        assert_eq!(file.size(), None);
+
+        let Entry::Dir(dir) = &entries[2] else {
+            panic!("expect a dir")
+        };
+        assert_eq!(dir.relative().as_str(), "archive");
+        assert_eq!(
+            dir.api_url().as_str(),
+            "https://example.org/thredds/catalog/ocean/archive/catalog.xml"
+        );
+    }
+
+    #[test]
+    fn test_thredds_catalog_subset() {
+        let entries = catalog_entries(Some("var=sst&north=50&south=40&accept=netcdf"));
+
+        let Entry::File(file) = &entries[0] else {
+            panic!("expect a file")
+        };
+        assert_eq!(
+            file.download_url().as_str(),
+            "https://example.org/thredds/ncss/grid/ocean/sst_2024.nc?var=sst&north=50&south=40&accept=netcdf"
+        );
+        assert_eq!(file.size(), None);
+        assert!(file.checksum().is_empty());
+    }
+
+    #[test]
+    fn test_thredds_html_catalog_url() {
+        let url = Url::from_str("https://example.org/thredds/catalog/ocean/catalog.html").unwrap();
+        assert_eq!(
+            Thredds::new(&url).root_url().as_str(),
+            "https://example.org/thredds/catalog/ocean/catalog.xml"
+        );
+    }
+}
@@ -5,16 +5,24 @@ use exn::{Exn, ResultExt};
 use serde_json::Value as JsonValue;
 use url::Url;
 
-use reqwest::{Client, StatusCode};
-use std::{any::Any, str::FromStr};
+use reqwest::{header::HeaderMap, Client, StatusCode};
+use std::{any::Any, str::FromStr, sync::Arc};
+use tracing::info;
 
+use crate::auth::bearer;
 use crate::helper::json_extract;
+use crate::http;
+use crate::metadata::{json_str, json_strs, normalize_doi};
 use crate::{
     repo::{Endpoint, FileMeta, RepoError},
-    Checksum, DatasetBackend, DirMeta, Entry,
+    AuthConfig, Checksum, DatasetBackend, DatasetMetadata, DirMeta, Entry,
 };
 
-fn analyse_json(json: &JsonValue, dir: &DirMeta) -> Result<Vec<Entry>, Exn<RepoError>> {
+fn analyse_json(
+    json: &JsonValue,
+    dir: &DirMeta,
+    page_url: &Url,
+) -> Result<Vec<Entry>, Exn<RepoError>> {
     let files = json
         .get("entries")
         .and_then(JsonValue::as_array)
@@ -25,7 +33,7 @@ fn analyse_json(json: &JsonValue, dir: &DirMeta) -> Result<Vec<Entry>, Exn<RepoE
     let mut entries = Vec::with_capacity(files.len());
     for (idx, filej) in files.iter().enumerate() {
         let endpoint = Endpoint {
-            parent_url: dir.api_url(),
+            parent_url: page_url.clone(),
             key: Some(format!("entries.{idx}")),
         };
         let name: String = json_extract(filej, "key").or_raise(|| RepoError {
@@ -53,31 +61,13 @@ fn analyse_json(json: &JsonValue, dir: &DirMeta) -> Result<Vec<Entry>, Exn<RepoE
         let checksum: String = json_extract(filej, "checksum").or_raise(|| RepoError {
             message: "fail to extracting 'checksum' as String from json".to_string(),
         })?;
-        let mut checksum_split = checksum.split(':');
-        let checksum = match checksum_split.next() {
-            Some("md5") => {
-                if let Some(checksum) = checksum_split.next() {
-                    Checksum::Md5(checksum.to_lowercase())
-                } else {
-                    exn::bail!(RepoError {
-                        message: "checksum format is wrong, type md5 but no checksum".to_string()
-                    })
-                }
-            }
-            Some("sha256") => {
-                if let Some(checksum) = checksum_split.next() {
-                    Checksum::Sha256(checksum.to_lowercase())
-                } else {
-                    exn::bail!(RepoError {
-                        message: "checksum format is wrong, type sha256 but no checksum"
-                            .to_string()
-                    })
-                }
-            }
-            _ => exn::bail!(RepoError {
-                message: "checksum field is wrong".to_string()
-            }),
-        };
+        // in the format '<algo>:<hex digest>', e.g. 'md5:8d777f385d3dfec8815d20f7496026dc'
+        let (algo, _) = checksum.split_once(':').ok_or_else(|| RepoError {
+            message: format!("checksum '{checksum}' is not in the format '<algo>:<digest>'"),
+        })?;
+        let checksum = Checksum::parse(algo, &checksum).or_raise(|| RepoError {
+            message: format!("invalid checksum of '{name}' at {page_url}"),
+        })?;
         let created: String = json_extract(filej, "created").or_raise(|| RepoError {
             message: "fail to extracting 'created' as String from json".to_string(),
         })?;
@@ -104,70 +94,254 @@ fn analyse_json(json: &JsonValue, dir: &DirMeta) -> Result<Vec<Entry>, Exn<RepoE
     Ok(entries)
 }
 
+// The files listing is paginated, the url of the next page is given at 'links.next' and absent
+// on the last page.
+fn next_page(json: &JsonValue, page_url: &Url) -> Result<Option<Url>, Exn<RepoError>> {
+    let Some(next) = json.pointer("/links/next").and_then(JsonValue::as_str) else {
+        return Ok(None);
+    };
+    let next = Url::from_str(next).or_raise(|| RepoError {
+        message: format!("fail to parse next page url '{next}' at parsing {page_url}"),
+    })?;
+    if next == *page_url {
+        return Ok(None);
+    }
+    Ok(Some(next))
+}
+
+// Versions of a record at https://zenodo.org/api/records/<id>/versions, the record of each
+// version has its own id, its version name at 'metadata.version' (free text, e.g. 'v1.2') and
+// its position at 'versions.index', starting at 1.
+fn version_record_id(hits: &[JsonValue], version: &str) -> Option<String> {
+    let same_name = |name: &str| name.trim_start_matches('v') == version.trim_start_matches('v');
+    let by_name = hits
+        .iter()
+        .find(|hit| json_str(hit, "/metadata/version").is_some_and(|name| same_name(&name)));
+    let by_index = || {
+        let index = version.parse::<u64>().ok()?;
+        hits.iter()
+            .find(|hit| hit.pointer("/versions/index").and_then(JsonValue::as_u64) == Some(index))
+    };
+    record_id_of(by_name.or_else(by_index)?)
+}
+
+// 'id' of a record, a number on zenodo.org and a string on other InvenioRDM installations.
+fn record_id_of(json: &JsonValue) -> Option<String> {
+    match json.get("id")? {
+        JsonValue::String(id) => Some(id.clone()),
+        JsonValue::Number(id) => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+// Record at https://zenodo.org/api/records/<id>, its 'metadata' follows the Zenodo deposit
+// metadata schema.
+fn metadata_from_json(json: &JsonValue) -> DatasetMetadata {
+    DatasetMetadata {
+        title: json_str(json, "/metadata/title"),
+        description: json_str(json, "/metadata/description"),
+        creators: json_strs(json, "/metadata/creators", "/name"),
+        license: json_str(json, "/metadata/license/id"),
+        publication_date: json_str(json, "/metadata/publication_date"),
+        version: json_str(json, "/metadata/version"),
+        doi: json_str(json, "/doi")
+            .or_else(|| json_str(json, "/metadata/doi"))
+            .map(|doi| normalize_doi(&doi)),
+    }
+}
+
+pub const ZENODO_URL: &str = "https://zenodo.org/";
+
 // https://zenodo.org/
 // API root url at https://zenodo.org/api/
 //
 // Zenodo use flatten folder tree structure, all files with nexted parent folder are list in one
 // API call.
+//
+// Any other InvenioRDM installation, e.g. https://data.caltech.edu/, serves the same
+// '/api/records/<id>/files' API under its own base url.
 #[derive(Debug)]
 pub struct Zenodo {
+    pub base_url: Url,
     pub id: String,
 }
 
 impl Zenodo {
     #[must_use]
-    pub fn new(id: impl Into<String>) -> Self {
-        Zenodo { id: id.into() }
+    pub fn new(id: impl Into<String>, base_url: &Url) -> Self {
+        Zenodo {
+            base_url: base_url.clone(),
+            id: id.into(),
+        }
+    }
+
+    /// Whether the record is on zenodo.org itself, the only installation the Zenodo access
+    /// token is sent to.
+    fn is_zenodo(&self) -> bool {
+        self.base_url.host_str() == Some("zenodo.org")
+    }
+
+    fn api_url(&self, segments: &[&str]) -> Result<Url, Exn<RepoError>> {
+        let mut url = self.base_url.clone();
+        url.path_segments_mut()
+            .map_err(|()| RepoError {
+                message: format!("'{}' cannot be base", self.base_url),
+            })?
+            .pop_if_empty()
+            .extend(["api", "records", &self.id])
+            .extend(segments);
+        Ok(url)
+    }
+
+    /// Latest version of the record if `id` is a concept id, the id shared by all versions of
+    /// a record that concept DOIs such as `10.5281/zenodo.1234567` point to, which has no
+    /// files nor metadata of its own. `None` if `id` is no concept id either.
+    async fn latest_of_concept(&self, client: &Client) -> Result<Option<Zenodo>, Exn<RepoError>> {
+        // https://zenodo.org/api/records/<id>/versions/latest resolves concept ids as well
+        let url = self.api_url(&["versions", "latest"])?;
+        let resp = http::send_get(client, &url).await?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let json = http::read_json(http::error_for_status(resp, &url)?, &url).await?;
+        let latest = record_id_of(&json)
+            .filter(|id| *id != self.id)
+            .map(|id| Zenodo::new(id, &self.base_url));
+        if let Some(latest) = &latest {
+            info!(
+                "record {} is a concept covering all versions, using its latest version {}",
+                self.id, latest.id
+            );
+        }
+        Ok(latest)
     }
 }
 
 #[allow(clippy::too_many_lines)]
 #[async_trait]
 impl DatasetBackend for Zenodo {
+    fn name(&self) -> &'static str {
+        if self.is_zenodo() {
+            "zenodo"
+        } else {
+            "invenio"
+        }
+    }
+    fn record_id(&self) -> String {
+        self.id.clone()
+    }
     fn root_url(&self) -> Url {
-        // https://zenodo.org/api/<id> to start for every dateset entry
-
-        // Safe to unwrap:
-        // - the base URL is a hard-coded, valid absolute URL
-        // - `path_segments_mut` cannot fail for this URL scheme
-        let mut url = Url::from_str("https://zenodo.org/api/records").unwrap();
-        url.path_segments_mut().unwrap().extend([&self.id, "files"]);
-        url
+        // https://zenodo.org/api/records/<id>/files to start for every dateset entry
+        self.api_url(&["files"])
+            .unwrap_or_else(|_| self.base_url.clone())
     }
 
     async fn list(&self, client: &Client, dir: DirMeta) -> Result<Vec<Entry>, Exn<RepoError>> {
         // NOTE: for dev, the first entry point url for the `dir.api_url` is the `root_dir` (from `root_url`) of the Dataset
-        let resp = client
-            .get(dir.api_url())
-            .send()
-            .await
-            .or_raise(|| RepoError {
-                message: format!("fail at client sent GET {}", dir.api_url()),
-            })?;
-        let resp = resp.error_for_status().map_err(|err| match err.status() {
-            Some(StatusCode::NOT_FOUND) => RepoError {
-                message: format!("resource not found when GET {}", dir.api_url()),
-            },
-            Some(status_code) => RepoError {
-                message: format!(
-                    "fail GET {}, with state code: {}",
-                    dir.api_url(),
-                    status_code.as_str()
-                ),
-            },
-            None => RepoError {
-                message: format!("fail GET {}, network / protocol error", dir.api_url(),),
-            },
-        })?;
-        let resp: JsonValue = resp.json().await.or_raise(|| RepoError {
-            message: format!("fail GET {}, unable to convert to json", dir.api_url(),),
-        })?;
+        let mut entries = Vec::new();
+        let mut page_url = dir.api_url();
+        loop {
+            let resp = http::send_get(client, &page_url).await?;
+            if resp.status() == StatusCode::NOT_FOUND && page_url == self.root_url() {
+                if let Some(latest) = self.latest_of_concept(client).await? {
+                    let dir = DirMeta::new(dir.path(), latest.root_url(), dir.root_url());
+                    return latest.list(client, dir).await;
+                }
+            }
+            // restricted and embargoed records are only listed with an access token
+            if matches!(
+                resp.status(),
+                StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+            ) {
+                let message = if self.is_zenodo() {
+                    format!(
+                        "record {} is restricted, provide a Zenodo access token granted access to it (ZENODO_ACCESS_TOKEN or ZENODO_TOKEN), GET {page_url}",
+                        self.id
+                    )
+                } else {
+                    format!("record {} is restricted, GET {page_url}", self.id)
+                };
+                return Err(http::status_error(&resp, &page_url, message));
+            }
+            let resp = http::error_for_status(resp, &page_url)?;
+            let resp = http::read_json(resp, &page_url).await?;
 
-        let entries = analyse_json(&resp, &dir)?;
+            entries.extend(analyse_json(&resp, &dir, &page_url)?);
+
+            match next_page(&resp, &page_url)? {
+                Some(next) => page_url = next,
+                None => break,
+            }
+        }
 
         Ok(entries)
     }
 
+    async fn raw_metadata(&self, client: &Client) -> Result<JsonValue, Exn<RepoError>> {
+        // https://zenodo.org/api/records/<id>
+        let url = self.api_url(&[])?;
+        let resp = http::send_get(client, &url).await?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            if let Some(latest) = self.latest_of_concept(client).await? {
+                return latest.raw_metadata(client).await;
+            }
+        }
+        http::read_json(http::error_for_status(resp, &url)?, &url).await
+    }
+
+    async fn metadata(&self, client: &Client) -> Result<DatasetMetadata, Exn<RepoError>> {
+        let json = self.raw_metadata(client).await?;
+        Ok(metadata_from_json(&json))
+    }
+
+    async fn with_version(
+        &self,
+        client: &Client,
+        version: &str,
+    ) -> Result<Arc<dyn DatasetBackend>, Exn<RepoError>> {
+        // https://zenodo.org/api/records/<id>/versions
+        let mut page_url = self.api_url(&["versions"])?;
+        let mut hits = Vec::new();
+        loop {
+            let resp = http::send_get(client, &page_url).await?;
+            // versions are listed by the id of a version, not by the concept id
+            if resp.status() == StatusCode::NOT_FOUND && hits.is_empty() {
+                if let Some(latest) = self.latest_of_concept(client).await? {
+                    return latest.with_version(client, version).await;
+                }
+            }
+            let json = http::read_json(http::error_for_status(resp, &page_url)?, &page_url).await?;
+            if let Some(page) = json.pointer("/hits/hits").and_then(JsonValue::as_array) {
+                hits.extend(page.iter().cloned());
+            }
+            match next_page(&json, &page_url)? {
+                Some(next) => page_url = next,
+                None => break,
+            }
+        }
+        let Some(id) = version_record_id(&hits, version) else {
+            let names = hits
+                .iter()
+                .filter_map(|hit| json_str(hit, "/metadata/version"))
+                .collect::<Vec<_>>();
+            exn::bail!(RepoError {
+                message: format!(
+                    "record {} has no version '{version}', its versions are [{}]",
+                    self.id,
+                    names.join(", ")
+                ),
+            });
+        };
+        Ok(Arc::new(Zenodo::new(id, &self.base_url)))
+    }
+
+    fn auth_headers(&self, auth: &AuthConfig) -> HeaderMap {
+        if !self.is_zenodo() {
+            return HeaderMap::new();
+        }
+        bearer(auth.zenodo.as_deref())
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -191,6 +365,12 @@ impl ZenodoJsonSrcDataset {
 
 #[async_trait]
 impl DatasetBackend for ZenodoJsonSrcDataset {
+    fn name(&self) -> &'static str {
+        "zenodo"
+    }
+    fn record_id(&self) -> String {
+        self.id.clone()
+    }
     fn root_url(&self) -> Url {
         // https://zenodo.org/api/<id> to start for every dateset entry
 
@@ -207,7 +387,7 @@ impl DatasetBackend for ZenodoJsonSrcDataset {
             message: "Failed to parse JSON".to_string(),
         })?;
 
-        let entries = analyse_json(&json_value, &dir)?;
+        let entries = analyse_json(&json_value, &dir, &dir.api_url())?;
 
         Ok(entries)
     }
@@ -216,3 +396,182 @@ impl DatasetBackend for ZenodoJsonSrcDataset {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{method, path, query_param, query_param_is_missing},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn file_json(key: &str) -> JsonValue {
+        serde_json::json!({
+            "key": key,
+            "file_id": format!("id-{key}"),
+            "version_id": "v1",
+            "size": 4,
+            "checksum": "md5:8d777f385d3dfec8815d20f7496026dc",
+            "links": { "content": format!("https://zenodo.org/api/records/1/files/{key}/content") },
+            "created": "2024-01-01T00:00:00",
+            "updated": "2024-01-01T00:00:00",
+        })
+    }
+
+    #[test]
+    fn test_zenodo_metadata_from_json() {
+        let json = serde_json::json!({
+            "id": 1,
+            "doi": "10.5281/zenodo.1",
+            "metadata": {
+                "title": "A dataset",
+                "description": "<p>Measurements</p>",
+                "creators": [{ "name": "Doe, Jane" }, { "name": "Roe, Richard" }],
+                "license": { "id": "cc-by-4.0" },
+                "publication_date": "2024-01-01",
+                "version": "v1.0",
+            },
+        });
+        let metadata = metadata_from_json(&json);
+        assert_eq!(metadata.title.as_deref(), Some("A dataset"));
+        assert_eq!(metadata.creators, ["Doe, Jane", "Roe, Richard"]);
+        assert_eq!(metadata.license.as_deref(), Some("cc-by-4.0"));
+        assert_eq!(metadata.publication_date.as_deref(), Some("2024-01-01"));
+        assert_eq!(metadata.version.as_deref(), Some("v1.0"));
+        assert_eq!(metadata.doi.as_deref(), Some("10.5281/zenodo.1"));
+    }
+
+    #[tokio::test]
+    async fn test_zenodo_list_restricted_record() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/records/1/files"))
+            .respond_with(ResponseTemplate::new(403))
+            .mount(&mock_server)
+            .await;
+
+        let files_url = format!("{}/api/records/1/files", mock_server.uri());
+        let dir = DirMeta::new_root(&Url::from_str(&files_url).unwrap());
+        let base_url = Url::from_str(&format!("{}/", mock_server.uri())).unwrap();
+        let err = Zenodo::new("1", &base_url)
+            .list(&Client::new(), dir)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("record 1 is restricted"));
+    }
+
+    #[tokio::test]
+    async fn test_zenodo_list_follows_pagination() {
+        let mock_server = MockServer::start().await;
+        let files_url = format!("{}/api/records/1/files", mock_server.uri());
+        Mock::given(method("GET"))
+            .and(path("/api/records/1/files"))
+            .and(query_param_is_missing("page"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "entries": [file_json("a.txt"), file_json("b.txt")],
+                "links": { "self": files_url, "next": format!("{files_url}?page=2") },
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/records/1/files"))
+            .and(query_param("page", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "entries": [file_json("c.txt")],
+                "links": { "self": format!("{files_url}?page=2") },
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let dir = DirMeta::new_root(&Url::from_str(&files_url).unwrap());
+        let base_url = Url::from_str(&format!("{}/", mock_server.uri())).unwrap();
+        let zenodo = Zenodo::new("1", &base_url);
+        assert_eq!(zenodo.root_url().as_str(), files_url);
+        let entries = zenodo.list(&Client::new(), dir).await.unwrap();
+
+        let names: Vec<_> = entries
+            .iter()
+            .map(|entry| match entry {
+                Entry::File(file_meta) => file_meta.relative().as_str().to_string(),
+                Entry::Dir(_) => panic!("zenodo lists only files"),
+            })
+            .collect();
+        assert_eq!(names, ["a.txt", "b.txt", "c.txt"]);
+    }
+
+    #[tokio::test]
+    async fn test_zenodo_concept_record() {
+        // unmatched requests, e.g. the files of concept 100, are answered with 404
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/records/100/versions/latest"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": 30,
+                "metadata": { "title": "latest" },
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/records/30"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": 30,
+                "metadata": { "title": "latest" },
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/records/30/files"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "entries": [file_json("a.txt")],
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let base_url = Url::from_str(&format!("{}/", mock_server.uri())).unwrap();
+        let concept = Zenodo::new("100", &base_url);
+        let entries = concept
+            .list(&Client::new(), DirMeta::new_root(&concept.root_url()))
+            .await
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        let metadata = concept.metadata(&Client::new()).await.unwrap();
+        assert_eq!(metadata.title.as_deref(), Some("latest"));
+
+        // not a concept either
+        let missing = Zenodo::new("200", &base_url);
+        assert!(missing
+            .list(&Client::new(), DirMeta::new_root(&missing.root_url()))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_zenodo_with_version() {
+        let mock_server = MockServer::start().await;
+        let hit = |id: u64, name: &str, index: u64| {
+            serde_json::json!({
+                "id": id,
+                "metadata": { "version": name },
+                "versions": { "index": index },
+            })
+        };
+        Mock::given(method("GET"))
+            .and(path("/api/records/30/versions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "hits": { "hits": [hit(30, "v2.0", 2), hit(10, "v1.0", 1)] },
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let base_url = Url::from_str(&format!("{}/", mock_server.uri())).unwrap();
+        let zenodo = Zenodo::new("30", &base_url);
+        for (version, id) in [("v1.0", "10"), ("1.0", "10"), ("1", "10"), ("2", "30")] {
+            let backend = zenodo.with_version(&Client::new(), version).await.unwrap();
+            assert_eq!(backend.record_id(), id, "version {version}");
+        }
+        let Err(err) = zenodo.with_version(&Client::new(), "v3").await else {
+            panic!("v3 is not a version");
+        };
+        assert!(err.to_string().contains("[v2.0, v1.0]"), "{err}");
+    }
+}
@@ -0,0 +1,507 @@
+use exn::{Exn, ResultExt};
+use futures_util::StreamExt;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::time::Instant;
+
+use reqwest::{
+    header::{HeaderMap, HeaderValue, IF_MODIFIED_SINCE, RANGE},
+    Client, StatusCode,
+};
+
+use crate::retry::RetryExt;
+use crate::{
+    chunked::{chunk_start, first_range, is_resumed, ranged_stream, ranged_total, resume_range},
+    client::with_connect_time,
+    crawler::{CrawlerError, ProgressManager},
+    error::ErrorStatus,
+    progress::TotalProgress,
+    provenance,
+    redirect::follow_cross_host_with,
+    validate::{expected_checksum, Validator},
+    zipindex::member_stream,
+    Checksum, CrawlPath, DownloadEvent, DownloadOptions, Entry, FileOutcome, FileTiming,
+    LocalWriter,
+};
+
+use bytes::Buf;
+use std::{fs, path::Path};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tracing::{debug, warn};
+
+/// What is known of a file while it downloads, kept for the report when the download fails
+/// midway.
+#[derive(Debug, Default)]
+pub(crate) struct FileProgress {
+    /// bytes written to the destination.
+    pub(crate) bytes: u64,
+    /// sha256 computed with [`DownloadOptions::self_checksum`].
+    pub(crate) self_checksum: Option<Checksum>,
+    /// sha256 of the content, hashed while it downloads when it goes to the
+    /// [`DownloadOptions::store`].
+    pub(crate) sha256: Option<String>,
+    pub(crate) timing: Option<FileTiming>,
+}
+
+#[allow(clippy::too_many_lines)]
+pub(crate) async fn download_crawled_file_with_validation<P>(
+    client: &Client,
+    src: Entry,
+    dst: P,
+    mp: impl ProgressManager,
+    options: &DownloadOptions,
+    progress: &mut FileProgress,
+    total: &TotalProgress,
+) -> Result<Option<FileOutcome>, Exn<CrawlerError>>
+where
+    P: AsRef<Path> + std::fmt::Debug,
+{
+    debug!("downloading with validating");
+    match src {
+        Entry::Dir(_) if !options.creates_dirs() => Ok(None),
+        Entry::Dir(dir_meta) => {
+            let path = dst.as_ref().join(dir_meta.relative());
+            // files create their parent folders whatever the order of the entries, this only
+            // keeps empty folders of the dataset
+            fs::create_dir_all(path.as_path()).or_raise(|| CrawlerError {
+                message: format!("cannot create dir {}", path.display()),
+                status: ErrorStatus::Permanent,
+            })?;
+            Ok(None)
+        }
+        Entry::File(file_meta) => {
+            // prepare stream src
+            let pb = mp.insert(0, ProgressBar::new_spinner());
+            pb.set_style(
+                ProgressStyle::with_template("{spinner:.green} {msg}")
+                    .expect("indicatif template error"),
+            );
+            pb.enable_steady_tick(std::time::Duration::from_millis(100));
+            pb.set_message(format!(
+                "Connecting... {}",
+                file_meta.download_url().as_str()
+            ));
+
+            if !file_meta.is_downloadable() {
+                pb.set_message(format!(
+                    "{} is not downloadable",
+                    file_meta.download_url().as_str()
+                ));
+                let reason = "not downloadable".to_string();
+                options
+                    .emit(DownloadEvent::Skipped {
+                        path: file_meta.relative(),
+                        reason: reason.clone(),
+                    })
+                    .await;
+                return Ok(Some(FileOutcome::Skipped(reason)));
+            }
+
+            if let (Some(size), Some(max_file_size)) = (file_meta.size(), options.max_file_size) {
+                if size > max_file_size {
+                    pb.finish_and_clear();
+                    warn!(
+                        "skip '{}', size {size} bytes exceeds max file size {max_file_size} bytes",
+                        file_meta.relative()
+                    );
+                    let reason =
+                        format!("size {size} bytes exceeds max file size {max_file_size} bytes");
+                    options
+                        .emit(DownloadEvent::Skipped {
+                            path: file_meta.relative(),
+                            reason: reason.clone(),
+                        })
+                        .await;
+                    return Ok(Some(FileOutcome::Skipped(reason)));
+                }
+            }
+
+            match options.validation_policy.precheck(&file_meta) {
+                Ok(None) => {}
+                Ok(Some(reason)) => {
+                    pb.finish_and_clear();
+                    options
+                        .emit(DownloadEvent::Skipped {
+                            path: file_meta.relative(),
+                            reason: reason.clone(),
+                        })
+                        .await;
+                    return Ok(Some(FileOutcome::Skipped(reason)));
+                }
+                Err(err) => {
+                    pb.finish_and_clear();
+                    return Err(err);
+                }
+            }
+
+            let path = dst.as_ref().join(file_meta.relative());
+            // without a custom writer, files are written to a part file renamed once validated
+            let local_writer = LocalWriter::new(dst.as_ref());
+            let mut sent = None;
+            let (content_length, mut stream, resumed) = if let Some(member) = file_meta.zip_member()
+            {
+                // only the bytes of the member are requested from the archive
+                let stream = member_stream(
+                    client,
+                    &options.client_options,
+                    &file_meta.download_url(),
+                    member,
+                )
+                .await
+                .or_raise(|| CrawlerError {
+                    message: format!(
+                        "fail to read '{}' from the zip archive {}",
+                        file_meta.relative(),
+                        file_meta.download_url()
+                    ),
+                    status: ErrorStatus::Temporary,
+                })?;
+                pb.finish_and_clear();
+                let stream = stream.map(|item| {
+                    item.or_raise(|| CrawlerError {
+                        message: "zip member error stream".to_string(),
+                        status: ErrorStatus::Permanent,
+                    })
+                });
+                (None, stream.boxed(), None)
+            } else {
+                let mut headers = HeaderMap::new();
+                if let Some(modified) = (options.if_modified_since && options.writes_local())
+                    .then(|| local_modified(&path, file_meta.size()))
+                    .flatten()
+                {
+                    // Safe to unwrap: an http date is ascii
+                    headers.insert(
+                        IF_MODIFIED_SINCE,
+                        HeaderValue::from_str(&httpdate::fmt_http_date(modified)).unwrap(),
+                    );
+                }
+                let chunked = options.is_chunked(&file_meta);
+                // a part file left by an interrupted download is resumed, a full one may be
+                // corrupted and is downloaded again
+                let partial = options
+                    .writer
+                    .is_none()
+                    .then(|| fs::metadata(local_writer.part_path(&file_meta.relative())).ok())
+                    .flatten()
+                    .map(|meta| meta.len())
+                    .filter(|&len| len > 0 && file_meta.size().is_none_or(|size| len < size))
+                    // chunks are resumed from the start of the chunk the part file ends in
+                    .map(|len| if chunked { chunk_start(len) } else { len })
+                    .filter(|&len| len > 0);
+                if chunked {
+                    let range = first_range(partial.unwrap_or(0));
+                    // Safe to unwrap: a byte range is ascii
+                    headers.insert(RANGE, HeaderValue::from_str(&range).unwrap());
+                } else if let Some(offset) = partial {
+                    // Safe to unwrap: a byte range is ascii
+                    headers.insert(RANGE, HeaderValue::from_str(&resume_range(offset)).unwrap());
+                }
+
+                let start = Instant::now();
+                let mut retries = 0;
+                let (resp, connect) = with_connect_time(async {
+                    let resp = client
+                        .get(file_meta.download_url())
+                        .headers(headers.clone())
+                        .send_with_retry_counted(&mut retries)
+                        .await
+                        .or_raise(|| CrawlerError {
+                            message: format!(
+                                "fail to send http GET to {}",
+                                file_meta.download_url()
+                            ),
+                            status: ErrorStatus::Temporary,
+                        })?;
+                    follow_cross_host_with(resp, headers, &options.client_options)
+                        .await
+                        .or_raise(|| CrawlerError {
+                            message: format!(
+                                "fail to follow the redirect of {}",
+                                file_meta.download_url()
+                            ),
+                            status: ErrorStatus::Temporary,
+                        })
+                })
+                .await;
+                let resp = resp?.error_for_status().or_raise(|| CrawlerError {
+                    message: format!("fail to send http GET to {}", file_meta.download_url()),
+                    // Temporary??
+                    status: ErrorStatus::Temporary,
+                })?;
+                pb.finish_and_clear();
+                progress.timing = Some(FileTiming {
+                    response: start.elapsed(),
+                    connect,
+                    first_byte: None,
+                    retries,
+                });
+                sent = Some(start);
+                if resp.status() == StatusCode::NOT_MODIFIED {
+                    let reason = "not modified since the local copy".to_string();
+                    options
+                        .emit(DownloadEvent::Skipped {
+                            path: file_meta.relative(),
+                            reason: reason.clone(),
+                        })
+                        .await;
+                    return Ok(Some(FileOutcome::Skipped(reason)));
+                }
+                // a server ignoring the range answers the whole file
+                let resumed = partial.filter(|&offset| is_resumed(&resp, offset));
+                if let Some(offset) = resumed {
+                    debug!("resuming {} after {offset} bytes", file_meta.download_url());
+                }
+                let start = resumed.unwrap_or(0);
+                if let Some(total) = chunked.then(|| ranged_total(&resp, start)).flatten() {
+                    debug!(
+                        "downloading {} in chunks over {} connections",
+                        file_meta.download_url(),
+                        options.connections_per_file
+                    );
+                    let stream = ranged_stream(
+                        client,
+                        &options.client_options,
+                        &file_meta.download_url(),
+                        resp,
+                        start,
+                        total,
+                        options.connections_per_file,
+                    );
+                    (Some(total), stream, resumed)
+                } else {
+                    // a server ignoring the range answers the whole file
+                    let content_length =
+                        resp.content_length().map(|len| len + resumed.unwrap_or(0));
+                    let stream = resp.bytes_stream().map(|item| {
+                        item.or_raise(|| CrawlerError {
+                            message: "reqwest error stream".to_string(),
+                            status: ErrorStatus::Permanent,
+                        })
+                    });
+                    (content_length, stream.boxed(), resumed)
+                }
+            };
+            options
+                .emit(DownloadEvent::Started {
+                    path: file_meta.relative(),
+                    size: file_meta.size(),
+                })
+                .await;
+            // prepare file dst
+            let mut fh = match &options.writer {
+                Some(writer) => writer.create(&file_meta.relative()).await,
+                None => {
+                    local_writer
+                        .create_part(&file_meta.relative(), resumed)
+                        .await
+                }
+            }
+            .or_raise(|| CrawlerError {
+                message: format!("fail to open '{}' for writing", file_meta.relative()),
+                status: ErrorStatus::Permanent,
+            })?;
+
+            let checksum = expected_checksum(&file_meta);
+            if checksum.is_none() && !options.self_checksum {
+                warn!("unable to find expected checksum to verify");
+            }
+            let mut validator = Validator::new(&file_meta, options.self_checksum);
+            if options.store.is_some() && options.writer.is_none() {
+                validator = validator.with_sha256();
+            }
+            if let Some(offset) = resumed {
+                if validator.is_hashing() {
+                    hash_part(
+                        &local_writer.part_path(&file_meta.relative()),
+                        &mut validator,
+                    )
+                    .await?;
+                }
+                progress.bytes = offset;
+                total.inc(offset);
+            }
+
+            // a listed size of 0 is often a placeholder, the server may know the length
+            let length = file_meta
+                .size()
+                .filter(|&size| size > 0)
+                .or(content_length)
+                .filter(|&size| size > 0);
+            let pb = if let Some(length) = length {
+                let style = ProgressStyle::with_template(
+                    "{msg:<60} [{bar:40.cyan/blue}] \
+                     {decimal_bytes:>8}/{decimal_total_bytes:>8} \
+                     ({decimal_bytes_per_sec:>12}, {eta:>3})",
+                )
+                .unwrap()
+                .progress_chars("=>-");
+                mp.insert_from_back(0, ProgressBar::new(length).with_style(style))
+            } else {
+                // unknown length, no bar to fill and no eta
+                let style = ProgressStyle::with_template(
+                    "{msg:<60} {spinner:.green} {decimal_bytes:>8} ({decimal_bytes_per_sec:>12})",
+                )
+                .unwrap();
+                mp.insert_from_back(0, ProgressBar::no_length().with_style(style))
+            };
+            pb.enable_steady_tick(std::time::Duration::from_millis(100));
+            pb.set_message(compact_path(file_meta.relative().as_str()));
+            pb.set_position(progress.bytes);
+
+            while let Some(item) = stream.next().await {
+                let mut bytes = item?;
+                if let (Some(sent), Some(timing)) = (sent, progress.timing.as_mut()) {
+                    timing.first_byte.get_or_insert_with(|| sent.elapsed());
+                }
+                validator.update(bytes.chunk());
+                let bytes_len = bytes.len() as u64;
+                progress.bytes += bytes_len;
+                fh.write_all_buf(&mut bytes)
+                    .await
+                    .or_raise(|| CrawlerError {
+                        message: "fail at writing to fs".to_string(),
+                        status: ErrorStatus::Permanent,
+                    })?;
+                pb.inc(bytes_len);
+                total.inc(bytes_len);
+                options.try_emit(DownloadEvent::Progress {
+                    path: file_meta.relative(),
+                    bytes: bytes_len,
+                    downloaded: progress.bytes,
+                    total: length,
+                });
+            }
+
+            fh.shutdown().await.or_raise(|| CrawlerError {
+                message: format!("fail to complete writing '{}'", file_meta.relative()),
+                status: ErrorStatus::Permanent,
+            })?;
+            pb.finish_and_clear();
+
+            let got_size = progress.bytes;
+            let validated = validator
+                .finish(&file_meta, got_size)
+                .map(|(outcome, self_checksum, sha256)| {
+                    progress.self_checksum = self_checksum;
+                    progress.sha256 = sha256;
+                    outcome
+                })
+                .and_then(|outcome| options.validation_policy.check(&file_meta, outcome));
+            if options.writer.is_none() {
+                // a wrong or skipped file never reaches its final path, nor is it resumed
+                if validated.as_ref().is_ok_and(FileOutcome::is_downloaded) {
+                    local_writer
+                        .commit(&file_meta.relative())
+                        .await
+                        .or_raise(|| CrawlerError {
+                            message: format!("fail to complete writing '{}'", file_meta.relative()),
+                            status: ErrorStatus::Permanent,
+                        })?;
+                } else {
+                    local_writer.discard(&file_meta.relative()).await;
+                }
+            }
+            let outcome = validated?;
+            if let FileOutcome::Skipped(reason) = &outcome {
+                options
+                    .emit(DownloadEvent::Skipped {
+                        path: file_meta.relative(),
+                        reason: reason.clone(),
+                    })
+                    .await;
+                return Ok(Some(outcome));
+            }
+            if options.xattrs && options.writes_local() {
+                provenance::record_xattrs(&path, &file_meta.download_url(), checksum);
+            }
+            options
+                .emit(DownloadEvent::Finished {
+                    path: file_meta.relative(),
+                    size: got_size,
+                })
+                .await;
+            Ok(Some(outcome))
+        }
+    }
+}
+
+/// Reports the file at `path`, stored in `dst_dir` without download as `outcome`, finished
+/// with its local size.
+pub(crate) async fn finish_local(
+    options: &DownloadOptions,
+    dst_dir: &Path,
+    path: &CrawlPath,
+    outcome: Result<FileOutcome, Exn<CrawlerError>>,
+) -> Result<Option<FileOutcome>, Exn<CrawlerError>> {
+    let outcome = outcome?;
+    debug!("'{path}' is {}", outcome.label());
+    let size = fs::metadata(dst_dir.join(path)).map_or(0, |m| m.len());
+    options
+        .emit(DownloadEvent::Finished {
+            path: path.clone(),
+            size,
+        })
+        .await;
+    Ok(Some(outcome))
+}
+
+/// Feeds the bytes of the part file at `path`, left by an interrupted download, to
+/// `validator`.
+async fn hash_part(path: &Path, validator: &mut Validator) -> Result<(), Exn<CrawlerError>> {
+    let mut fh = tokio::fs::File::open(path)
+        .await
+        .or_raise(|| CrawlerError {
+            message: format!("fail to open '{}'", path.display()),
+            status: ErrorStatus::Permanent,
+        })?;
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = fh.read(&mut buf).await.or_raise(|| CrawlerError {
+            message: format!("fail to read '{}'", path.display()),
+            status: ErrorStatus::Permanent,
+        })?;
+        if n == 0 {
+            return Ok(());
+        }
+        validator.update(&buf[..n]);
+    }
+}
+
+/// Modification time of the local copy at `path`, `None` if there is none or its size is not
+/// the `expected_size`, e.g. a partial download.
+fn local_modified(path: &Path, expected_size: Option<u64>) -> Option<std::time::SystemTime> {
+    let meta = fs::metadata(path).ok()?;
+    if expected_size.is_some_and(|size| size != meta.len()) {
+        return None;
+    }
+    meta.modified().ok()
+}
+
+fn compact_path(full_path: &str) -> String {
+    let path = Path::new(full_path);
+
+    // Get components
+    let mut comps: Vec<String> = path
+        .parent() // everything except the file name
+        .map(|p| {
+            p.components()
+                .map(|c| {
+                    let s = c.as_os_str().to_string_lossy();
+                    if s.is_empty() {
+                        String::new()
+                    } else {
+                        s.chars().next().unwrap().to_string()
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // Add base file name
+    if let Some(file_name) = path.file_name() {
+        comps.push(file_name.to_string_lossy().to_string());
+    }
+
+    // Join with slashes
+    comps.join("/")
+}
@@ -1,6 +1,217 @@
+use exn::{Exn, Frame};
+use reqwest::StatusCode;
+use std::{io, time::Duration};
+use url::Url;
+
+use crate::AccessRequirements;
+
 #[derive(Debug)]
 pub enum ErrorStatus {
     Permanent,  // Don't retry
     Temporary,  // Safe to retry
     Persistent, // Was retried, still failing
 }
+
+/// Cause of a failed resolve, crawl or download, for callers that react to it
+/// programmatically instead of matching error messages.
+///
+/// Obtained from any error of the crate with [`DatahuggerError::from`], which looks for the
+/// innermost cause with a known kind in the error tree, e.g.
+///
+/// ```no_run
+/// # async fn run(dataset: datahugger::Dataset, client: reqwest::Client) {
+/// use datahugger::{DatahuggerError, DownloadExt, DownloadOptions};
+/// use indicatif::MultiProgress;
+///
+/// let options = DownloadOptions::new();
+/// let mp = MultiProgress::new();
+/// if let Err(err) = dataset.download_with_validation(&client, "data", mp, &options).await {
+///     match DatahuggerError::from(&err) {
+///         DatahuggerError::RateLimited { retry_after, .. } => println!("retry in {retry_after:?}"),
+///         DatahuggerError::NotFound { url } => println!("{url} is gone"),
+///         other => println!("failed: {other}"),
+///     }
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DatahuggerError {
+    /// The repository answered `404 Not Found`.
+    NotFound { url: Url },
+    /// The repository answered `429 Too Many Requests`, with the wait it asked for if any.
+    RateLimited {
+        url: Url,
+        retry_after: Option<Duration>,
+    },
+    /// A downloaded file does not match the checksum listed by the repository, both as hex.
+    ChecksumMismatch { expected: String, got: String },
+    /// Reading or writing a local file failed.
+    Io {
+        kind: io::ErrorKind,
+        message: String,
+    },
+    /// Files of the dataset cannot be downloaded before the requirements are met, see
+    /// [`DownloadOptions::check_access`](crate::DownloadOptions::check_access).
+    AccessRequired(AccessRequirements),
+    /// The files to download need more space than available on the destination, both in
+    /// bytes, see [`DownloadOptions::check_disk_space`](crate::DownloadOptions::check_disk_space).
+    InsufficientSpace { required: u64, available: u64 },
+    /// The repository answered another error status.
+    Http {
+        url: Option<Url>,
+        status: StatusCode,
+    },
+    /// Any other failure, with the message of the outermost error.
+    Other(String),
+}
+
+impl std::fmt::Display for DatahuggerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DatahuggerError::NotFound { url } => write!(f, "resource not found at {url}"),
+            DatahuggerError::RateLimited { url, retry_after } => {
+                write!(f, "rate limited by {url}")?;
+                if let Some(retry_after) = retry_after {
+                    write!(f, ", retry after {}s", retry_after.as_secs())?;
+                }
+                Ok(())
+            }
+            DatahuggerError::ChecksumMismatch { expected, got } => {
+                write!(f, "checksum wrong, expect {expected}, got {got}")
+            }
+            DatahuggerError::Io { message, .. } => write!(f, "io error: {message}"),
+            DatahuggerError::AccessRequired(requirements) => write!(f, "{requirements}"),
+            DatahuggerError::InsufficientSpace {
+                required,
+                available,
+            } => write!(
+                f,
+                "not enough disk space, {required} bytes required, {available} bytes available"
+            ),
+            DatahuggerError::Http { url, status } => match url {
+                Some(url) => write!(f, "{url} answered {status}"),
+                None => write!(f, "answered {status}"),
+            },
+            DatahuggerError::Other(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for DatahuggerError {}
+
+impl<E> From<&Exn<E>> for DatahuggerError
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    fn from(err: &Exn<E>) -> Self {
+        cause(err.frame()).unwrap_or_else(|| DatahuggerError::Other(err.to_string()))
+    }
+}
+
+/// Innermost error of a known kind under `frame`, depth first.
+fn cause(frame: &Frame) -> Option<DatahuggerError> {
+    if let Some(cause) = frame.children().iter().find_map(cause) {
+        return Some(cause);
+    }
+    let error = frame.error();
+    if let Some(err) = error.downcast_ref::<DatahuggerError>() {
+        return Some(err.clone());
+    }
+    if let Some(err) = error.downcast_ref::<io::Error>() {
+        return Some(DatahuggerError::Io {
+            kind: err.kind(),
+            message: err.to_string(),
+        });
+    }
+    let err = error.downcast_ref::<reqwest::Error>()?;
+    let status = err.status()?;
+    Some(DatahuggerError::from_status(
+        err.url().cloned(),
+        status,
+        None,
+    ))
+}
+
+impl DatahuggerError {
+    /// Short label of the kind of error, e.g. `not_found` or `checksum_mismatch`, to count
+    /// failures by kind.
+    #[must_use]
+    pub fn category(&self) -> &'static str {
+        match self {
+            DatahuggerError::NotFound { .. } => "not_found",
+            DatahuggerError::RateLimited { .. } => "rate_limited",
+            DatahuggerError::ChecksumMismatch { .. } => "checksum_mismatch",
+            DatahuggerError::Io { .. } => "io",
+            DatahuggerError::AccessRequired(_) => "access_required",
+            DatahuggerError::InsufficientSpace { .. } => "insufficient_space",
+            DatahuggerError::Http { .. } => "http",
+            DatahuggerError::Other(_) => "other",
+        }
+    }
+
+    /// Kind of an error `status` answered by `url`.
+    pub(crate) fn from_status(
+        url: Option<Url>,
+        status: StatusCode,
+        retry_after: Option<Duration>,
+    ) -> Self {
+        match (status, url) {
+            (StatusCode::NOT_FOUND, Some(url)) => DatahuggerError::NotFound { url },
+            (StatusCode::TOO_MANY_REQUESTS, Some(url)) => {
+                DatahuggerError::RateLimited { url, retry_after }
+            }
+            (status, url) => DatahuggerError::Http { url, status },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo::RepoError;
+    use exn::ResultExt;
+
+    #[test]
+    fn test_datahugger_error_from_exn() {
+        let url = Url::parse("https://zenodo.org/api/records/1").unwrap();
+        let err = Exn::new(DatahuggerError::from_status(
+            Some(url.clone()),
+            StatusCode::TOO_MANY_REQUESTS,
+            Some(Duration::from_secs(60)),
+        ))
+        .raise(RepoError {
+            message: "fail GET".to_string(),
+        });
+        assert_eq!(
+            DatahuggerError::from(&err),
+            DatahuggerError::RateLimited {
+                url,
+                retry_after: Some(Duration::from_secs(60))
+            }
+        );
+
+        let res: Result<(), _> = Err(io::Error::from(io::ErrorKind::PermissionDenied));
+        let err = res
+            .or_raise(|| RepoError {
+                message: "cannot write".to_string(),
+            })
+            .unwrap_err();
+        assert!(matches!(
+            DatahuggerError::from(&err),
+            DatahuggerError::Io {
+                kind: io::ErrorKind::PermissionDenied,
+                ..
+            }
+        ));
+
+        let err = Exn::new(RepoError {
+            message: "no 'files' in json".to_string(),
+        });
+        assert_eq!(
+            DatahuggerError::from(&err),
+            DatahuggerError::Other("repo fail: no 'files' in json".to_string())
+        );
+        assert_eq!(DatahuggerError::from(&err).category(), "other");
+    }
+}
@@ -0,0 +1,51 @@
+use crate::CrawlPath;
+
+/// Events emitted while downloading a dataset, see [`DownloadOptions::event_sink`] and
+/// [`DownloadOptions::on_event`].
+///
+/// Lifecycle events (`Started`, `Finished`, `Skipped`, `Failed`, `Cancelled`) are always
+/// delivered,
+/// the sender waits for free capacity in the channel. `Progress` events are best-effort
+/// and are dropped when the channel is full, so a slow consumer never stalls a download.
+///
+/// All paths are relative crawl paths of the files.
+///
+/// [`DownloadOptions::event_sink`]: crate::DownloadOptions::event_sink
+/// [`DownloadOptions::on_event`]: crate::DownloadOptions::on_event
+#[derive(Debug, Clone)]
+pub enum DownloadEvent {
+    /// A file download starts, `size` is the expected size if known.
+    Started { path: CrawlPath, size: Option<u64> },
+    /// A chunk of `bytes` was written, `downloaded` is the total written so far out of
+    /// `total`, the listed size or the content length if known.
+    Progress {
+        path: CrawlPath,
+        bytes: u64,
+        downloaded: u64,
+        total: Option<u64>,
+    },
+    /// A file was downloaded and validated.
+    Finished { path: CrawlPath, size: u64 },
+    /// A file was not downloaded.
+    Skipped { path: CrawlPath, reason: String },
+    /// A file download or its validation failed.
+    Failed { path: CrawlPath, message: String },
+    /// A file download was cancelled midway, see
+    /// [`DownloadOptions::cancel`](crate::DownloadOptions::cancel).
+    Cancelled { path: CrawlPath },
+}
+
+impl DownloadEvent {
+    /// Returns the relative crawl path of the file the event is about.
+    #[must_use]
+    pub fn path(&self) -> &CrawlPath {
+        match self {
+            DownloadEvent::Started { path, .. }
+            | DownloadEvent::Progress { path, .. }
+            | DownloadEvent::Finished { path, .. }
+            | DownloadEvent::Skipped { path, .. }
+            | DownloadEvent::Failed { path, .. }
+            | DownloadEvent::Cancelled { path } => path,
+        }
+    }
+}
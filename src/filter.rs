@@ -0,0 +1,260 @@
+use exn::{Exn, ResultExt};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use jiff::{civil, tz::TimeZone, Timestamp};
+use std::time::SystemTime;
+
+use crate::{error::ErrorStatus, Entry, FileMeta};
+
+#[derive(Debug)]
+pub struct FilterError {
+    pub message: String,
+    pub status: ErrorStatus,
+}
+
+impl std::fmt::Display for FilterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "filter fail: {}", self.message)
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+/// Include/exclude glob patterns used to select which crawled files are downloaded.
+///
+/// Patterns are matched against the relative crawl path of a file (see
+/// [`FileMeta::relative`](crate::FileMeta::relative)), e.g. `raw/2024/a.csv`.
+/// A `*` also matches path separators, so `*.csv` selects csv files at any depth,
+/// while `raw/**` selects everything below the `raw` folder.
+///
+/// - With no include pattern, every file is included.
+/// - A file matching any exclude pattern is dropped, even if it is also included.
+/// - With [`FileFilter::modified_after`], a file last modified (or else created) at or before
+///   the given time is dropped. Files without any date listed by the repository are kept.
+///
+/// # Examples
+///
+/// ```
+/// use datahugger::FileFilter;
+///
+/// let filter = FileFilter::new(&["*.csv"], &["raw/**"]).unwrap();
+///
+/// assert!(filter.is_match("data/a.csv"));
+/// assert!(!filter.is_match("raw/a.csv"));
+/// assert!(!filter.is_match("README.md"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FileFilter {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+    modified_after: Option<SystemTime>,
+}
+
+/// Names of files and folders created by operating systems and archivers rather than by the
+/// authors of a dataset, compared case-insensitively.
+const SYSTEM_ARTIFACTS: [&str; 4] = ["__macosx", "desktop.ini", "ehthumbs.db", "thumbs.db"];
+
+/// Returns `true` if any component of the relative path `path` is a dotfile, e.g.
+/// `.DS_Store` or `.git`, or a system artifact, e.g. `Thumbs.db` or `__MACOSX`.
+pub(crate) fn is_hidden_path(path: &str) -> bool {
+    path.split('/').any(|name| {
+        (name.starts_with('.') && name != "." && name != "..")
+            || SYSTEM_ARTIFACTS.contains(&name.to_lowercase().as_str())
+    })
+}
+
+/// Parses a date listed by a repository, an RFC 3339 timestamp (e.g.
+/// `2024-05-01T12:00:00Z`), or a date and time without offset, or a date alone, both taken
+/// as UTC.
+pub(crate) fn parse_date(date: &str) -> Option<SystemTime> {
+    let date = date.trim();
+    if let Ok(timestamp) = date.parse::<Timestamp>() {
+        return Some(timestamp.into());
+    }
+    // a date alone parses as a datetime at midnight
+    let datetime = date.parse::<civil::DateTime>().ok()?;
+    let timestamp = datetime.to_zoned(TimeZone::UTC).ok()?.timestamp();
+    Some(timestamp.into())
+}
+
+fn build_globset<S>(patterns: &[S]) -> Result<Option<GlobSet>, Exn<FilterError>>
+where
+    S: AsRef<str>,
+{
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let pattern = pattern.as_ref();
+        let glob = Glob::new(pattern).or_raise(|| FilterError {
+            message: format!("'{pattern}' is not a valid glob pattern"),
+            status: ErrorStatus::Permanent,
+        })?;
+        builder.add(glob);
+    }
+    let set = builder.build().or_raise(|| FilterError {
+        message: "cannot build glob set from patterns".to_string(),
+        status: ErrorStatus::Permanent,
+    })?;
+    Ok(Some(set))
+}
+
+impl FileFilter {
+    /// Creates a filter from include and exclude glob patterns.
+    ///
+    /// # Errors
+    /// when any of the patterns is not a valid glob.
+    pub fn new<I, E>(include: &[I], exclude: &[E]) -> Result<Self, Exn<FilterError>>
+    where
+        I: AsRef<str>,
+        E: AsRef<str>,
+    {
+        Ok(FileFilter {
+            include: build_globset(include)?,
+            exclude: build_globset(exclude)?,
+            modified_after: None,
+        })
+    }
+
+    /// Keeps only the files modified after `time`, to pick up the files added to a dataset
+    /// since a previous harvest.
+    ///
+    /// A file is dated by its last modification date, or its creation date if the repository
+    /// lists none, see [`FileMeta::last_modification_date`]. Files without a date are kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datahugger::FileFilter;
+    /// use std::time::{Duration, SystemTime};
+    ///
+    /// let yesterday = SystemTime::now() - Duration::from_secs(24 * 60 * 60);
+    /// let filter = FileFilter::default().modified_after(yesterday);
+    /// assert!(!filter.is_empty());
+    /// ```
+    #[must_use]
+    pub fn modified_after(mut self, time: SystemTime) -> Self {
+        self.modified_after = Some(time);
+        self
+    }
+
+    /// Returns `true` if the filter has no pattern nor date and therefore lets every file
+    /// pass.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.include.is_none() && self.exclude.is_none() && self.modified_after.is_none()
+    }
+
+    /// Returns `true` if a file at the relative path `path` passes the filter.
+    #[must_use]
+    pub fn is_match(&self, path: &str) -> bool {
+        if self.exclude.as_ref().is_some_and(|set| set.is_match(path)) {
+            return false;
+        }
+        self.include.as_ref().is_none_or(|set| set.is_match(path))
+    }
+
+    /// Returns `true` if a crawled file passes the filter, on its relative crawl path and
+    /// its dates.
+    ///
+    /// A file of a bundle, see [`FileMeta::bundle`], passes on the path of any file of the
+    /// bundle, so bundles are kept together.
+    #[must_use]
+    pub fn is_match_file(&self, file_meta: &FileMeta) -> bool {
+        let on_path = self.is_match(file_meta.relative().as_str())
+            || file_meta
+                .bundle()
+                .iter()
+                .any(|other| self.is_match(other.relative().as_str()));
+        if !on_path {
+            return false;
+        }
+        let Some(after) = self.modified_after else {
+            return true;
+        };
+        file_meta
+            .last_modification_date()
+            .or_else(|| file_meta.creation_date())
+            .and_then(parse_date)
+            .is_none_or(|modified| modified > after)
+    }
+
+    /// Returns `true` if a crawled entry passes the filter.
+    ///
+    /// Files are matched on their relative crawl path and dates. Directories only pass
+    /// when the filter is empty, when any pattern or date is given the folders are
+    /// created on demand as parents of the selected files instead.
+    #[must_use]
+    pub fn is_match_entry(&self, entry: &Entry) -> bool {
+        match entry {
+            Entry::Dir(_) => self.is_empty(),
+            Entry::File(file_meta) => self.is_match_file(file_meta),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TestFile;
+
+    #[test]
+    fn test_file_filter_default() {
+        let filter = FileFilter::default();
+        assert!(filter.is_empty());
+        assert!(filter.is_match("a/b/c.txt"));
+    }
+
+    #[test]
+    fn test_file_filter_include_exclude() {
+        let filter = FileFilter::new(&["*.csv", "docs/**"], &["raw/**"]).unwrap();
+        assert!(!filter.is_empty());
+        assert!(filter.is_match("a.csv"));
+        assert!(filter.is_match("sub/dir/a.csv"));
+        assert!(filter.is_match("docs/index.md"));
+        assert!(!filter.is_match("raw/a.csv"));
+        assert!(!filter.is_match("README.md"));
+    }
+
+    #[test]
+    fn test_file_filter_exclude_only() {
+        let filter = FileFilter::new::<&str, _>(&[], &["*.zip"]).unwrap();
+        assert!(filter.is_match("a.csv"));
+        assert!(!filter.is_match("sub/a.zip"));
+    }
+
+    #[test]
+    fn test_file_filter_modified_after() {
+        let file = |created: Option<&str>, modified: Option<&str>| {
+            TestFile::new("a.csv")
+                .created(created)
+                .modified(modified)
+                .build()
+        };
+        let filter = FileFilter::default().modified_after(parse_date("2024-05-01").unwrap());
+        assert!(filter.is_match_file(&file(None, Some("2024-05-02T08:00:00Z"))));
+        assert!(filter.is_match_file(&file(None, Some("2024-05-01T10:00:00.123+02:00"))));
+        assert!(!filter.is_match_file(&file(None, Some("2024-04-30T23:59:59"))));
+        assert!(!filter.is_match_file(&file(Some("2024-06-01"), Some("2024-05-01"))));
+        assert!(filter.is_match_file(&file(Some("2024-06-01"), None)));
+        assert!(filter.is_match_file(&file(None, None)));
+        assert!(filter.is_match_file(&file(None, Some("yesterday"))));
+    }
+
+    #[test]
+    fn test_is_hidden_path() {
+        assert!(is_hidden_path(".DS_Store"));
+        assert!(is_hidden_path("data/._a.csv"));
+        assert!(is_hidden_path("__MACOSX/data/a.csv"));
+        assert!(is_hidden_path("data/THUMBS.DB"));
+        assert!(is_hidden_path(".git/config"));
+        assert!(!is_hidden_path("data/a.csv"));
+        assert!(!is_hidden_path("data/thumbs.db.csv"));
+    }
+
+    #[test]
+    fn test_file_filter_invalid_pattern() {
+        let err = FileFilter::new(&["a[b"], &[] as &[&str]).unwrap_err();
+        assert!(err.message.contains("'a[b' is not a valid glob pattern"));
+    }
+}
@@ -0,0 +1,168 @@
+use bytes::Bytes;
+use exn::{Exn, ResultExt};
+use reqwest::{header::LINK, Client, Response, StatusCode};
+use serde_json::Value as JsonValue;
+use std::io::Cursor;
+use tracing::debug;
+use url::Url;
+use xmltree::Element;
+
+use crate::{
+    redirect::{default_client_options, follow_cross_host},
+    repo::RepoError,
+    retry::{retry_after, RetryExt},
+    DatahuggerError,
+};
+
+/// Sends a GET to `url`, the request every backend lists and fetches metadata with.
+///
+/// Requests answered with `Retry-After` are retried, see
+/// [`RetryExt::send_with_retry`], and redirects to another host are followed without the
+/// credentials the client carries as default headers, see
+/// [`DatasetBackend::auth_headers`](crate::DatasetBackend::auth_headers).
+///
+/// The response is returned whatever its status, for backends that explain some statuses,
+/// e.g. rate limits, before [`error_for_status`].
+pub(crate) async fn send_get(client: &Client, url: &Url) -> Result<Response, Exn<RepoError>> {
+    debug!("GET {url}");
+    let resp = client
+        .get(url.clone())
+        .send_with_retry()
+        .await
+        .or_raise(|| RepoError {
+            message: format!("fail at client sent GET {url}"),
+        })?;
+    let resp = follow_cross_host(resp, default_client_options())
+        .await
+        .or_raise(|| RepoError {
+            message: format!("fail to follow the redirect of {url}"),
+        })?;
+    debug!("GET {url}: {}", resp.status());
+    Ok(resp)
+}
+
+/// Fails with a message naming `url` if `resp` has an error status, raised over the
+/// [`DatahuggerError`] of the status.
+pub(crate) fn error_for_status(resp: Response, url: &Url) -> Result<Response, Exn<RepoError>> {
+    let status = resp.status();
+    if !status.is_client_error() && !status.is_server_error() {
+        return Ok(resp);
+    }
+    let message = if status == StatusCode::NOT_FOUND {
+        format!("resource not found when GET {url}")
+    } else {
+        format!("fail GET {url}, with state code: {}", status.as_str())
+    };
+    Err(status_error(&resp, url, message))
+}
+
+/// Error for a response to `url` whose status a backend explains with `message`, raised
+/// over the [`DatahuggerError`] of the status.
+pub(crate) fn status_error(resp: &Response, url: &Url, message: String) -> Exn<RepoError> {
+    let cause = DatahuggerError::from_status(
+        Some(url.clone()),
+        resp.status(),
+        retry_after(resp.headers()),
+    );
+    Exn::new(cause).raise(RepoError { message })
+}
+
+/// Error for a response to `url` refused for exceeding the rate limit of the repository,
+/// which some answer with another status than `429`, e.g. `403` by GitHub.
+pub(crate) fn rate_limited(resp: &Response, url: &Url, message: String) -> Exn<RepoError> {
+    let cause = DatahuggerError::RateLimited {
+        url: url.clone(),
+        retry_after: retry_after(resp.headers()),
+    };
+    Exn::new(cause).raise(RepoError { message })
+}
+
+/// Url of the next page given in the `Link` header of `resp`, as APIs paginating with
+/// cursors do, e.g. `<https://huggingface.co/api/...?cursor=abc>; rel="next"`.
+pub(crate) fn next_link(resp: &Response) -> Option<Url> {
+    let link = resp.headers().get(LINK)?.to_str().ok()?;
+    link.split(',').find_map(|part| {
+        let (url, params) = part.split_once(';')?;
+        params
+            .split(';')
+            .any(|param| matches!(param.trim(), "rel=\"next\"" | "rel=next"))
+            .then_some(())?;
+        let url = url.trim().strip_prefix('<')?.strip_suffix('>')?;
+        Url::parse(url).ok()
+    })
+}
+
+/// Reads the body of `resp` to `url` as json.
+pub(crate) async fn read_json(resp: Response, url: &Url) -> Result<JsonValue, Exn<RepoError>> {
+    let json: JsonValue = resp.json().await.or_raise(|| RepoError {
+        message: format!("fail GET {url}, unable to convert to json"),
+    })?;
+    Ok(json)
+}
+
+/// GETs the json at `url`.
+pub(crate) async fn get_json(client: &Client, url: &Url) -> Result<JsonValue, Exn<RepoError>> {
+    let resp = error_for_status(send_get(client, url).await?, url)?;
+    read_json(resp, url).await
+}
+
+/// GETs the body at `url`.
+pub(crate) async fn get_bytes(client: &Client, url: &Url) -> Result<Bytes, Exn<RepoError>> {
+    let resp = error_for_status(send_get(client, url).await?, url)?;
+    let bytes = resp.bytes().await.or_raise(|| RepoError {
+        message: format!("fail GET {url}, unable to read body"),
+    })?;
+    Ok(bytes)
+}
+
+/// GETs the xml document at `url`, loaded in memory as a whole.
+pub(crate) async fn get_xml(client: &Client, url: &Url) -> Result<Element, Exn<RepoError>> {
+    let bytes = get_bytes(client, url).await?;
+    let tree = Element::parse(Cursor::new(bytes)).or_raise(|| RepoError {
+        message: format!("fail to parse xml at {url}"),
+    })?;
+    Ok(tree)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_get_helpers() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/record.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"id": 1}"#))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/catalog.xml"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<catalog><a/></catalog>"))
+            .mount(&mock_server)
+            .await;
+        let client = Client::new();
+        let url = |p: &str| Url::parse(&format!("{}{p}", mock_server.uri())).unwrap();
+
+        let json = get_json(&client, &url("/record.json")).await.unwrap();
+        assert_eq!(json["id"], 1);
+        let tree = get_xml(&client, &url("/catalog.xml")).await.unwrap();
+        assert_eq!(tree.name, "catalog");
+        assert!(tree.get_child("a").is_some());
+
+        let err = get_bytes(&client, &url("/missing")).await.unwrap_err();
+        assert!(format!("{err}").contains("resource not found"));
+        assert_eq!(
+            DatahuggerError::from(&err),
+            DatahuggerError::NotFound {
+                url: url("/missing")
+            }
+        );
+        let err = get_xml(&client, &url("/record.json")).await.unwrap_err();
+        assert!(format!("{err}").contains("fail to parse xml"));
+    }
+}
@@ -0,0 +1,108 @@
+use exn::{Exn, ResultExt};
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::{Mutex as SlotMutex, OwnedMutexGuard};
+use tracing::debug;
+
+use crate::{
+    crawler::CrawlerError, error::ErrorStatus, provenance::checksum_str,
+    validate::expected_checksum, CrawlPath, FileMeta,
+};
+
+/// How a file identical to one already downloaded is stored, see
+/// [`DownloadOptions::link_identical`](crate::DownloadOptions::link_identical).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkMode {
+    /// A hard link to the downloaded file, or a copy of it where the filesystem has no hard
+    /// links.
+    Hardlink,
+    /// A symbolic link relative to the downloaded file, so the destination directory can be
+    /// moved.
+    Symlink,
+    /// A copy of the downloaded file, only the transfer is saved.
+    Copy,
+}
+
+/// Files downloaded so far, by checksum and size, to store files identical to one of them
+/// without downloading them again.
+#[derive(Debug, Default)]
+pub(crate) struct IdenticalFiles {
+    slots: Mutex<HashMap<String, Arc<SlotMutex<Option<CrawlPath>>>>>,
+}
+
+impl IdenticalFiles {
+    /// Returns the slot of the content of `file`, holding the path of the first file with
+    /// that content once it is downloaded, `None` if the repository lists no checksum.
+    ///
+    /// The slot is held until the guard drops, so a file waits for an identical one being
+    /// downloaded instead of downloading it too.
+    pub(crate) async fn slot(&self, file: &FileMeta) -> Option<OwnedMutexGuard<Option<CrawlPath>>> {
+        let checksum = expected_checksum(file)?;
+        let key = format!(
+            "{}:{}",
+            checksum_str(checksum),
+            file.size()
+                .map_or_else(String::new, |size| size.to_string())
+        );
+        let slot = self
+            .slots
+            .lock()
+            .expect("identical files lock poisoned")
+            .entry(key)
+            .or_default()
+            .clone();
+        Some(slot.lock_owned().await)
+    }
+}
+
+/// Stores `link` in `dst_dir` as a link to, or copy of, the identical `original`, replacing
+/// a file already at `link`.
+pub(crate) fn link_identical(
+    dst_dir: &Path,
+    original: &CrawlPath,
+    link: &CrawlPath,
+    mode: LinkMode,
+) -> Result<(), Exn<CrawlerError>> {
+    let src = dst_dir.join(original);
+    let dst = dst_dir.join(link);
+    let err = || CrawlerError {
+        message: format!(
+            "cannot link '{}' to the identical '{}'",
+            dst.display(),
+            src.display()
+        ),
+        status: ErrorStatus::Permanent,
+    };
+    if let Some(parent) = dst.parent() {
+        fs::create_dir_all(parent).or_raise(err)?;
+    }
+    match fs::remove_file(&dst) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e).or_raise(err),
+        _ => {}
+    }
+    match mode {
+        LinkMode::Hardlink => {
+            if let Err(e) = fs::hard_link(&src, &dst) {
+                debug!("cannot hard link '{}', copying it: {e}", dst.display());
+                fs::copy(&src, &dst).or_raise(err)?;
+            }
+        }
+        LinkMode::Symlink => {
+            // relative to the directory of the link
+            let depth = link.as_str().matches('/').count();
+            let target = Path::new(&"../".repeat(depth)).join(original);
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(&target, &dst).or_raise(err)?;
+            #[cfg(windows)]
+            std::os::windows::fs::symlink_file(&target, &dst).or_raise(err)?;
+        }
+        LinkMode::Copy => {
+            fs::copy(&src, &dst).or_raise(err)?;
+        }
+    }
+    Ok(())
+}
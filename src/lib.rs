@@ -1,8 +1,11 @@
 pub mod error;
+pub use crate::error::DatahuggerError;
 
 mod repo;
 pub use crate::repo::Checksum;
+pub use crate::repo::ChecksumError;
 pub use crate::repo::CrawlPath;
+pub use crate::repo::CustomChecksum;
 pub use crate::repo::Dataset;
 pub use crate::repo::DatasetBackend;
 pub use crate::repo::DirMeta;
@@ -12,14 +15,134 @@ pub use crate::repo::Hasher;
 
 mod helper;
 
+mod checksum;
+pub use crate::checksum::{register_checksum_algorithm, ChecksumAlgorithm, DigestHasher};
+
+mod access;
+pub use crate::access::{AccessRequirement, AccessRequirements};
+
+mod auth;
+pub use crate::auth::AuthConfig;
+
+mod metadata;
+pub use crate::metadata::DatasetMetadata;
+
+mod citation;
+pub use crate::citation::CitationStyle;
+
+mod cache;
+pub use crate::cache::CachePolicy;
+
+mod tls;
+pub use crate::tls::client_builder;
+
+mod client;
+pub use crate::client::{ClientOptions, TlsBackend, DEFAULT_USER_AGENT};
+
+mod redirect;
+pub use crate::redirect::RedirectPolicy;
+
+mod retry;
+
+mod http;
+
+mod repositories;
+pub use crate::repositories::{RepositoryConfig, RepositoryConfigError};
+
+mod config;
+pub use crate::config::{Config, ConfigError};
+
+pub mod runtime;
+
 mod resolver;
 pub use crate::resolver::resolve;
+pub use crate::resolver::resolve_doi_metadata;
+pub use crate::resolver::resolve_doi_redirects;
 pub use crate::resolver::resolve_doi_to_url;
+pub use crate::resolver::resolve_with_auth;
+pub use crate::resolver::resolve_with_options;
+pub use crate::resolver::resolve_with_repositories;
+pub use crate::resolver::DoiRedirects;
 
 pub mod crawler;
 pub use crawler::crawl;
 
+mod filter;
+pub use crate::filter::{FileFilter, FilterError};
+
+mod event;
+pub use crate::event::DownloadEvent;
+
+mod options;
+pub use crate::options::{CrawlOptions, DownloadOptions};
+pub use tokio_util::sync::CancellationToken;
+
+mod sanitize;
+pub use crate::sanitize::TargetOs;
+
+mod writer;
+#[cfg(feature = "object-store")]
+pub use crate::writer::ObjectStoreWriter;
+pub use crate::writer::{LocalWriter, ObjectSink, ObjectWriter, WriterError};
+
+mod lock;
+pub use crate::lock::{DirLock, LockError, LOCK_FILE_NAME};
+
+mod provenance;
+pub use crate::provenance::XATTR_PREFIX;
+
+mod identical;
+pub use crate::identical::LinkMode;
+
+mod store;
+pub use crate::store::{ContentStore, StoreError, StoreGc};
+
+mod validate;
+pub use crate::validate::{ValidatedStream, ValidationPolicy};
+
+mod manifest;
+pub use crate::manifest::MANIFEST_FILE_NAME;
+
+mod verify;
+pub use crate::verify::{
+    verify_manifest, verify_remote, FileVerification, VerifyError, VerifyOutcome, VerifyReport,
+};
+
+mod metrics;
+#[cfg(feature = "metrics")]
+pub use crate::metrics::{metrics, Metrics};
+
+mod report;
+pub use crate::report::{DownloadReport, FileOutcome, FileReport, FileTiming};
+
+mod archive;
+
+mod progress;
+
+mod chunked;
+pub use crate::chunked::DEFAULT_CHUNK_THRESHOLD;
+
+mod zipindex;
+pub use crate::zipindex::{expand_zip, zip_members};
+
+mod summary;
+pub use crate::summary::DatasetSummary;
+
+mod snapshot;
+pub use crate::snapshot::{Snapshot, SnapshotError};
+
+mod batch;
+pub use crate::batch::{download_many, BatchEntry, BatchReport};
+
+mod download;
+
 mod ops;
 pub use crate::ops::{CrawlExt, DownloadExt};
 
+mod plan;
+pub use crate::plan::{CollisionPolicy, DownloadPlan, PlanError};
+
+#[cfg(test)]
+mod testutil;
+
 pub mod datasets;
@@ -0,0 +1,295 @@
+use exn::{Exn, ResultExt};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing::warn;
+
+use crate::error::ErrorStatus;
+
+/// Name of the lock file created in the destination directory.
+pub const LOCK_FILE_NAME: &str = ".datahugger.lock";
+
+/// A lock older than this is considered stale even if its process looks alive,
+/// since the PID may have been reused.
+const STALE_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A lock whose content cannot be parsed is held by a writer that did not finish yet,
+/// unless it was not modified for this long.
+const UNPARSEABLE_STALE_AFTER: Duration = Duration::from_secs(60);
+
+/// Suffix of the temporary and set aside lock files, unique in the process.
+static SIBLING_SEQ: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug)]
+pub struct LockError {
+    pub message: String,
+    pub status: ErrorStatus,
+}
+
+impl std::fmt::Display for LockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "lock fail: {}", self.message)
+    }
+}
+
+impl std::error::Error for LockError {}
+
+/// Advisory lock on a destination directory, released when dropped.
+///
+/// The lock is a [`LOCK_FILE_NAME`] file holding the PID of the owner, the time the
+/// lock was taken and a sequence number telling apart the locks of the process. It only
+/// guards against other datahugger processes that honor it.
+#[derive(Debug)]
+pub struct DirLock {
+    path: PathBuf,
+    /// content written to the lock file, to release it only while it is ours.
+    content: String,
+}
+
+#[derive(Debug)]
+struct LockOwner {
+    pid: u32,
+    since: u64,
+}
+
+impl LockOwner {
+    fn parse(content: &str) -> Option<Self> {
+        let mut lines = content.lines();
+        let pid = lines.next()?.trim().parse().ok()?;
+        let since = lines.next()?.trim().parse().ok()?;
+        Some(LockOwner { pid, since })
+    }
+
+    fn is_stale(&self) -> bool {
+        let age = now_secs().saturating_sub(self.since);
+        age > STALE_AFTER.as_secs() || !process_alive(self.pid)
+    }
+}
+
+/// Whether the lock file at `path` holding `content` may be taken over.
+fn is_stale(path: &Path, content: &str) -> bool {
+    match LockOwner::parse(content) {
+        Some(owner) => owner.is_stale(),
+        None => fs::metadata(path)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_none_or(|age| age > UNPARSEABLE_STALE_AFTER),
+    }
+}
+
+/// Path next to the lock file `path`, unique to this process and call.
+fn sibling(path: &Path, tag: &str) -> PathBuf {
+    let seq = SIBLING_SEQ.fetch_add(1, Ordering::Relaxed);
+    path.with_file_name(format!(
+        "{LOCK_FILE_NAME}.{tag}-{}-{seq}",
+        std::process::id()
+    ))
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(target_os = "linux")]
+fn process_alive(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+// Without a portable way to probe a PID, only the timestamp decides staleness.
+#[cfg(not(target_os = "linux"))]
+fn process_alive(_pid: u32) -> bool {
+    true
+}
+
+impl DirLock {
+    /// Takes the lock on `dir`.
+    ///
+    /// An existing lock is taken over if it is stale (its process is gone, or it is
+    /// older than a day, or it cannot be read and was not modified for a minute), or
+    /// unconditionally when `force` is set.
+    ///
+    /// # Errors
+    /// when the directory is locked by another live process, or the lock file cannot
+    /// be written.
+    pub fn acquire(dir: &Path, force: bool) -> Result<Self, Exn<LockError>> {
+        let path = dir.join(LOCK_FILE_NAME);
+        // the lock is written aside and linked into place, so that it never appears
+        // without its content.
+        let tmp = sibling(&path, "tmp");
+        let content = format!(
+            "{}\n{}\n{}\n",
+            std::process::id(),
+            now_secs(),
+            SIBLING_SEQ.fetch_add(1, Ordering::Relaxed)
+        );
+        fs::write(&tmp, &content).or_raise(|| LockError {
+            message: format!("cannot write lock file '{}'", tmp.display()),
+            status: ErrorStatus::Permanent,
+        })?;
+        let acquired = Self::link(dir, &path, &tmp, force);
+        if let Err(err) = fs::remove_file(&tmp) {
+            warn!(
+                "cannot remove temporary lock file '{}': {err}",
+                tmp.display()
+            );
+        }
+        acquired?;
+        Ok(DirLock { path, content })
+    }
+
+    /// Links the written lock `tmp` to `path`, taking over a stale or forced lock.
+    fn link(dir: &Path, path: &Path, tmp: &Path, force: bool) -> Result<(), Exn<LockError>> {
+        // two attempts: the second one after setting aside a stale or forced lock.
+        for _ in 0..2 {
+            match fs::hard_link(tmp, path) {
+                Ok(()) => return Ok(()),
+                Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {
+                    let content = fs::read_to_string(path).unwrap_or_default();
+                    if !(force || is_stale(path, &content)) {
+                        exn::bail!(locked(dir, path, &content));
+                    }
+                    // another process may replace the lock between the check and its
+                    // removal, so it is renamed aside and checked again before dropping it.
+                    let aside = sibling(path, "stale");
+                    match fs::rename(path, &aside) {
+                        Ok(()) => {}
+                        Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+                        Err(err) => {
+                            return Err(err).or_raise(|| LockError {
+                                message: format!("cannot remove lock file '{}'", path.display()),
+                                status: ErrorStatus::Permanent,
+                            });
+                        }
+                    }
+                    let taken = fs::read_to_string(&aside).unwrap_or_default();
+                    if !(force || taken == content || is_stale(&aside, &taken)) {
+                        // a live lock taken in between, put back unless yet another
+                        // process holds the lock by now.
+                        let _ = fs::hard_link(&aside, path);
+                        let _ = fs::remove_file(&aside);
+                        exn::bail!(locked(dir, path, &taken));
+                    }
+                    warn!("taking over lock '{}' ({taken:?})", path.display());
+                    fs::remove_file(&aside).or_raise(|| LockError {
+                        message: format!("cannot remove lock file '{}'", aside.display()),
+                        status: ErrorStatus::Permanent,
+                    })?;
+                }
+                Err(err) => {
+                    return Err(err).or_raise(|| LockError {
+                        message: format!("cannot create lock file '{}'", path.display()),
+                        status: ErrorStatus::Permanent,
+                    });
+                }
+            }
+        }
+        exn::bail!(LockError {
+            message: format!("lost the race for lock file '{}'", path.display()),
+            status: ErrorStatus::Temporary,
+        })
+    }
+}
+
+fn locked(dir: &Path, path: &Path, content: &str) -> LockError {
+    let owner = match LockOwner::parse(content) {
+        Some(owner) => format!("process {} since unix time {}", owner.pid, owner.since),
+        None => "another process".to_string(),
+    };
+    LockError {
+        message: format!(
+            "'{}' is locked by {owner}, remove '{}' or force the lock if it is not running",
+            dir.display(),
+            path.display()
+        ),
+        status: ErrorStatus::Temporary,
+    }
+}
+
+impl Drop for DirLock {
+    fn drop(&mut self) {
+        // the lock may have been taken over, e.g. forced by another process
+        match fs::read_to_string(&self.path) {
+            Ok(content) if content == self.content => {}
+            Ok(content) => {
+                warn!(
+                    "lock file '{}' was taken over ({content:?}), left in place",
+                    self.path.display()
+                );
+                return;
+            }
+            Err(err) => {
+                warn!("cannot read lock file '{}': {err}", self.path.display());
+                return;
+            }
+        }
+        if let Err(err) = fs::remove_file(&self.path) {
+            warn!("cannot remove lock file '{}': {err}", self.path.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    #[test]
+    fn test_dir_lock() {
+        let dir = TempDir::new("dir-lock");
+
+        let lock = DirLock::acquire(&dir, false).unwrap();
+        let err = DirLock::acquire(&dir, false).unwrap_err();
+        assert!(err.message.contains("is locked by process"));
+
+        // force takes over a live lock, which its former holder leaves in place
+        let forced = DirLock::acquire(&dir, true).unwrap();
+        drop(lock);
+        assert!(dir.join(LOCK_FILE_NAME).exists());
+        drop(forced);
+        assert!(!dir.join(LOCK_FILE_NAME).exists());
+
+        // lock of a process that is gone
+        fs::write(
+            dir.join(LOCK_FILE_NAME),
+            format!("{}\n{}\n", u32::MAX, now_secs()),
+        )
+        .unwrap();
+        let lock = DirLock::acquire(&dir, false);
+        if cfg!(target_os = "linux") {
+            assert!(lock.is_ok());
+        }
+        drop(lock);
+
+        // lock that is too old
+        fs::write(
+            dir.join(LOCK_FILE_NAME),
+            format!("{}\n0\n", std::process::id()),
+        )
+        .unwrap();
+        assert!(DirLock::acquire(&dir, false).is_ok());
+
+        // lock being written by another process
+        fs::write(dir.join(LOCK_FILE_NAME), "").unwrap();
+        let err = DirLock::acquire(&dir, false).unwrap_err();
+        assert!(err.message.contains("is locked by another process"));
+
+        // lock left unreadable long ago
+        let old = SystemTime::now() - 2 * UNPARSEABLE_STALE_AFTER;
+        fs::File::options()
+            .write(true)
+            .open(dir.join(LOCK_FILE_NAME))
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        drop(DirLock::acquire(&dir, false).unwrap());
+
+        // no temporary or set aside lock is left behind
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+    }
+}
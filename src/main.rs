@@ -1,29 +1,241 @@
-use std::{fs, path::PathBuf};
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime},
+};
 
-use clap::{Args, Parser, Subcommand};
-use datahugger::{resolve, DownloadExt};
+use clap::{Arg, Args, Command, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use datahugger::{
+    datasets::{Arxiv, ArxivFormat, Thredds},
+    download_many, resolve_with_options, verify_manifest, verify_remote, AuthConfig, CachePolicy,
+    CancellationToken, CitationStyle, ClientOptions, CollisionPolicy, Config, ContentStore,
+    CrawlOptions, DatahuggerError, Dataset, DownloadExt, DownloadOptions, FileFilter, LinkMode,
+    RedirectPolicy, RepositoryConfig, Snapshot, TargetOs, TlsBackend, ValidationPolicy,
+    DEFAULT_CHUNK_THRESHOLD,
+};
 use indicatif::MultiProgress;
 use reqwest::{
-    header::{HeaderMap, HeaderValue, AUTHORIZATION, USER_AGENT},
-    ClientBuilder,
+    header::{HeaderName, HeaderValue},
+    Client,
 };
-use tracing_subscriber::{EnvFilter, FmtSubscriber};
+use tracing_subscriber::{fmt::format::FmtSpan, EnvFilter, FmtSubscriber};
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 #[command(propagate_version = true)]
 struct Cli {
+    /// Config file with tokens, repository installations and option defaults, instead of
+    /// `datahugger/config.toml` in the user config directory
+    #[arg(long, global = true, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    #[command(flatten)]
+    client: ClientArgs,
+
     #[command(subcommand)]
     command: Commands,
 }
 
+/// Options of the http clients resolving and downloading datasets, for every subcommand.
+#[derive(Args)]
+struct ClientArgs {
+    /// Timeout in seconds of each request, the body of a download included
+    #[arg(long, global = true, value_name = "SECS")]
+    timeout: Option<u64>,
+
+    /// Timeout in seconds of connecting to a server
+    #[arg(long, global = true, value_name = "SECS")]
+    connect_timeout: Option<u64>,
+
+    /// Proxy of all requests, e.g. 'http://proxy.example.org:3128', instead of the proxy
+    /// of the HTTPS_PROXY and HTTP_PROXY environment variables
+    #[arg(long, global = true, value_name = "URL", value_parser = parse_proxy)]
+    proxy: Option<reqwest::Proxy>,
+
+    /// Extra header sent with every request, as 'Name: value', can be repeated
+    #[arg(long = "header", global = true, value_name = "HEADER", value_parser = parse_header)]
+    headers: Vec<(HeaderName, HeaderValue)>,
+
+    /// User agent instead of 'datahugger-cli/<version>'
+    #[arg(long, global = true)]
+    user_agent: Option<String>,
+
+    /// TLS implementation, of those enabled in this build
+    #[arg(long, global = true, value_enum)]
+    tls_backend: Option<TlsBackendArg>,
+}
+
+impl ClientArgs {
+    fn options(&self) -> ClientOptions {
+        let mut options = ClientOptions::new();
+        if let Some(timeout) = self.timeout {
+            options = options.timeout(Duration::from_secs(timeout));
+        }
+        if let Some(connect_timeout) = self.connect_timeout {
+            options = options.connect_timeout(Duration::from_secs(connect_timeout));
+        }
+        if let Some(proxy) = &self.proxy {
+            options = options.proxy(proxy.clone());
+        }
+        for (name, value) in &self.headers {
+            options = options.header(name.clone(), value.clone());
+        }
+        if let Some(user_agent) = &self.user_agent {
+            options = options.user_agent(user_agent);
+        }
+        if let Some(tls_backend) = self.tls_backend {
+            options = options.tls_backend(tls_backend.into());
+        }
+        options
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Download files of dataset
-    Download(DownloadArgs),
+    Download(Box<DownloadArgs>),
 
     /// Inspect files of dataset
     Inspect(InspectArgs),
+
+    /// Print what a URL resolves to: repository, record, number and size of files, metadata
+    Info(InfoArgs),
+
+    /// Re-validate a previously downloaded dataset
+    Verify(VerifyArgs),
+
+    /// Print the citation of dataset
+    Cite(CiteArgs),
+
+    /// Pin the current files of dataset into a snapshot file
+    Snapshot(SnapshotArgs),
+
+    /// Download the files pinned in a snapshot file
+    Restore(RestoreArgs),
+
+    /// Remove the contents of a store no downloaded dataset links to any more
+    Gc(GcArgs),
+
+    /// Print the completion script of a shell
+    Completions(CompletionsArgs),
+
+    /// Print the man page, or write the pages of all subcommands into a directory
+    #[command(hide = true)]
+    Manpage(ManpageArgs),
+}
+
+#[derive(Args)]
+struct CompletionsArgs {
+    /// Shell to complete in, e.g. `datahugger completions bash > /etc/bash_completion.d/datahugger`.
+    shell: Shell,
+}
+
+#[derive(Args)]
+struct ManpageArgs {
+    /// Directory to write `datahugger.1` and a page per subcommand into, instead of
+    /// printing `datahugger.1`.
+    #[arg(long, value_name = "DIR")]
+    out_dir: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct SnapshotArgs {
+    /// URL of the data record to pin.
+    url: String,
+
+    /// Version of the dataset to pin instead of the latest, see `download --dataset-version`.
+    #[arg(long, value_name = "VERSION")]
+    dataset_version: Option<String>,
+
+    /// Path of the snapshot file to write.
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        default_value = "datahugger.pin.json"
+    )]
+    output: PathBuf,
+}
+
+#[derive(Args)]
+struct RestoreArgs {
+    /// Snapshot file written by `datahugger snapshot`.
+    snapshot: PathBuf,
+
+    /// Destination directory for downloaded files.
+    ///
+    /// Defaults to the current directory (`"./"`).
+    #[arg(short, long, value_name = "DIR")]
+    to: Option<PathBuf>,
+
+    /// Maximum number of concurrent downloads, `0` (the default) disables the limit.
+    #[arg(short, long, default_value_t = 0)]
+    limit: usize,
+
+    /// Write a `datahugger.json` manifest of the restored files into the destination.
+    #[arg(long)]
+    manifest: bool,
+}
+
+#[derive(Args)]
+struct CiteArgs {
+    /// URL of the data record to cite.
+    url: String,
+
+    /// Citation format.
+    #[arg(long, value_enum, default_value_t = Style::Apa)]
+    style: Style,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Style {
+    /// APA reference.
+    Apa,
+    /// BibTeX entry.
+    Bibtex,
+    /// CSL-JSON, as imported by Zotero and pandoc.
+    CslJson,
+}
+
+impl From<Style> for CitationStyle {
+    fn from(style: Style) -> Self {
+        match style {
+            Style::Apa => CitationStyle::Apa,
+            Style::Bibtex => CitationStyle::Bibtex,
+            Style::CslJson => CitationStyle::CslJson,
+        }
+    }
+}
+
+#[derive(Args)]
+struct GcArgs {
+    /// Directory of the store, see `download --store`.
+    store: PathBuf,
+}
+
+#[derive(Args)]
+struct VerifyArgs {
+    /// Directory the dataset was downloaded to.
+    dir: PathBuf,
+
+    /// URL of the data record to verify against, crawled again from the repository.
+    ///
+    /// When not given, files are verified against the `datahugger.json` manifest written by
+    /// `download --manifest`.
+    #[arg(long)]
+    url: Option<String>,
+}
+
+#[derive(Args)]
+struct InfoArgs {
+    /// URL of the data record to summarize.
+    url: String,
+
+    /// Version of the dataset to summarize instead of the latest, see
+    /// `download --dataset-version`.
+    #[arg(long, value_name = "VERSION")]
+    dataset_version: Option<String>,
 }
 
 #[derive(Args)]
@@ -43,7 +255,28 @@ struct InspectArgs {
 #[derive(Args)]
 struct DownloadArgs {
     /// URL of the data record to download.
-    url: String,
+    #[arg(required_unless_present = "input")]
+    url: Option<String>,
+
+    /// Download every URL or DOI listed in this file, one per line, each into its own
+    /// subdirectory of the destination named after its repository and record id.
+    ///
+    /// Blank lines and lines starting with `#` are ignored. The summary of every dataset
+    /// is printed at the end.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["url", "subset", "arxiv_format", "stats_out"])]
+    input: Option<PathBuf>,
+
+    /// Maximum number of datasets of `--input` downloaded at the same time, `0` disables
+    /// the limit.
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    dataset_limit: usize,
+
+    /// Download this version of the dataset instead of the latest.
+    ///
+    /// A version number for Dataverse (`2` or `2.1`) and Dryad, the version name (e.g.
+    /// `v1.2`) or index starting at 1 for Zenodo and InvenioRDM.
+    #[arg(long, value_name = "VERSION", conflicts_with = "input")]
+    dataset_version: Option<String>,
 
     /// Maximum number of concurrent downloads.
     ///
@@ -55,17 +288,522 @@ struct DownloadArgs {
 
     /// Destination directory for downloaded files.
     ///
-    /// Defaults to the current directory (`"./"`).
+    /// Defaults to the current directory (`"./"`). The placeholders `{repo}`,
+    /// `{record_id}`, `{doi}` and `{date}` (of the download, UTC) are replaced by those of
+    /// the dataset, e.g. `--to './data/{repo}/{record_id}'`, also for each dataset of
+    /// `--input`.
     #[arg(short, long, value_name = "DIR")]
     to: Option<PathBuf>,
+
+    /// Only download files whose relative path matches the glob pattern.
+    ///
+    /// Can be given multiple times, e.g. `--include '*.csv' --include 'docs/**'`.
+    /// When not given, all files are downloaded.
+    #[arg(long, value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// Skip files whose relative path matches the glob pattern.
+    ///
+    /// Can be given multiple times, e.g. `--exclude 'raw/**'`.
+    /// Takes precedence over `--include`.
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Only download files modified after this time, e.g. `2024-05-01` or
+    /// `2024-05-01T12:00:00Z` (dates without offset are UTC).
+    ///
+    /// Files are dated by the modification or creation date listed by the repository, files
+    /// without a date are always downloaded.
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    modified_after: Option<SystemTime>,
+
+    /// Also download dotfiles and system artifacts such as `.DS_Store`, `Thumbs.db` or
+    /// `__MACOSX/`, which are skipped by default.
+    #[arg(long)]
+    include_hidden: bool,
+
+    /// List the members of zip archives as files, read with range requests, and download
+    /// only the members selected by `--include` / `--exclude` instead of whole archives.
+    #[arg(long)]
+    expand_zip: bool,
+
+    /// Check what must be obtained to access the dataset before downloading, e.g. a token,
+    /// terms to accept, access to request or an embargo, and fail listing it if anything is.
+    #[arg(long)]
+    check_access: bool,
+
+    /// Check that the destination has space for all files before downloading, and fail if
+    /// not. The whole dataset is crawled first, files of unknown size are not counted.
+    #[arg(long)]
+    check_disk_space: bool,
+
+    /// Download files larger than `--chunk-threshold` over this many parallel range requests.
+    ///
+    /// Speeds up single large files from servers throttling each connection.
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    connections_per_file: usize,
+
+    /// Size in bytes from which files are downloaded in parallel chunks.
+    #[arg(long, value_name = "BYTES", default_value_t = DEFAULT_CHUNK_THRESHOLD)]
+    chunk_threshold: u64,
+
+    /// Skip files larger than this size in bytes.
+    ///
+    /// Files of unknown size are always downloaded.
+    #[arg(long, value_name = "BYTES")]
+    max_file_size: Option<u64>,
+
+    /// Keep downloading the remaining files when a file fails.
+    ///
+    /// Failed files are listed in the summary at the end, and the command exits with an error.
+    #[arg(long)]
+    continue_on_error: bool,
+
+    /// Take over the lock of the destination directory held by another run.
+    ///
+    /// A lock file in the destination directory prevents concurrent downloads into it.
+    /// Locks of processes that are gone are taken over automatically.
+    #[arg(long)]
+    force_lock: bool,
+
+    /// Record the source URL, checksum and retrieval time of each file as extended attributes.
+    ///
+    /// Attributes are named `user.datahugger.*`, on Windows they are stored as
+    /// alternate data streams. Filesystems without support only log a warning.
+    #[arg(long)]
+    xattr: bool,
+
+    /// Write a `datahugger.json` manifest into the destination directory after the download.
+    ///
+    /// It records the source URL, the resolve time and the path, size, checksum and
+    /// download URL of every downloaded file.
+    #[arg(long)]
+    manifest: bool,
+
+    /// Compute the sha256 of files the repository publishes no checksum for, e.g. on GitHub,
+    /// HAL or arXiv, and record it as `self-computed` in the manifest.
+    ///
+    /// Later `datahugger verify` runs then detect changes of these files.
+    #[arg(long)]
+    self_checksum: bool,
+
+    /// What to do with files the repository lists without checksum or size.
+    #[arg(long, value_enum, value_name = "POLICY", default_value_t = Validation::Warn)]
+    validation: Validation,
+
+    /// Write timing and bandwidth statistics of the download as json to this file.
+    ///
+    /// Records the time to the response headers, time to first byte, throughput and
+    /// number of retries of every file, to benchmark repositories.
+    #[arg(long, value_name = "FILE")]
+    stats_out: Option<PathBuf>,
+
+    /// Write the request, byte and failure counters in the Prometheus text format to FILE.
+    ///
+    /// Written at the end of the download, also when it fails, e.g. for the textfile
+    /// collector of the node exporter.
+    #[cfg(feature = "metrics")]
+    #[arg(long, value_name = "FILE")]
+    metrics_out: Option<PathBuf>,
+
+    /// Download a server side subset of each file of a THREDDS catalog.
+    ///
+    /// The query is passed to the NetcdfSubset service,
+    /// e.g. `--subset 'var=temp&north=50&south=40&west=0&east=10&accept=netcdf'`.
+    #[arg(long, value_name = "QUERY")]
+    subset: Option<String>,
+
+    /// Formats to download an arXiv paper in, e.g. `--arxiv-format pdf,source`.
+    ///
+    /// Each format is its own file, the e-print source is usually a tarball of the TeX
+    /// files and figures. Defaults to the pdf.
+    #[arg(long, value_enum, value_name = "FORMAT", value_delimiter = ',')]
+    arxiv_format: Vec<ArxivFormatArg>,
+
+    /// Download the whole dataset as a single server generated ZIP and extract it.
+    ///
+    /// Much faster for datasets with thousands of small files. Only Dataverse, Pangaea and
+    /// Dryad provide dataset archives, other repositories download files one by one.
+    #[arg(long)]
+    archive_mode: bool,
+
+    /// Ask caching proxies to revalidate every listing and download with the repository.
+    #[arg(long, conflicts_with = "max_age")]
+    no_cache: bool,
+
+    /// Accept responses of caching proxies not older than this many seconds.
+    #[arg(long, value_name = "SECS")]
+    max_age: Option<u64>,
+
+    /// Keep files already in the destination when the repository answers they are not
+    /// modified since, instead of downloading them again.
+    ///
+    /// Reduces traffic of scheduled mirror jobs, kept files are reported as skipped.
+    #[arg(long)]
+    if_modified_since: bool,
+
+    /// What to do with files whose paths differ only by case (e.g. `README.md` and
+    /// `Readme.md`), which collide on case-insensitive filesystems.
+    #[arg(long, value_enum, default_value_t = OnCollision::Overwrite)]
+    on_collision: OnCollision,
+
+    /// Operating system the names of downloaded files must be valid on, e.g. `windows` to
+    /// download onto a Windows share from Linux.
+    ///
+    /// Characters the target rejects (`:`, `?`, ... on Windows) are replaced by `_`.
+    #[arg(long, value_enum, default_value_t = TargetOsArg::Host)]
+    target_os: TargetOsArg,
+
+    /// Request the size of files listed without one (HAL, arXiv, ...) with HEAD requests
+    /// before downloading.
+    ///
+    /// Gives accurate progress bars and applies `--max-file-size` to these files too.
+    #[arg(long)]
+    probe_sizes: bool,
+
+    /// Download a file listed more than once under the same path only once.
+    ///
+    /// Guards against repositories whose listings return the same file twice.
+    #[arg(long)]
+    dedup: bool,
+
+    /// Download files with the same checksum and size only once, and store the others
+    /// as links to, or copies of, the downloaded one.
+    #[arg(long, value_enum, value_name = "MODE")]
+    link_identical: Option<LinkIdentical>,
+
+    /// Keep the content of the downloaded files in a store shared by datasets, and link
+    /// files whose content it holds, e.g. of another version of the dataset, instead of
+    /// downloading them again.
+    ///
+    /// The store should be on the filesystem of `--to` for the files to be hard links.
+    #[arg(long, value_name = "DIR")]
+    store: Option<PathBuf>,
+
+    /// Crawl and filter the dataset and print the files that would be downloaded with
+    /// their total size, without downloading or writing anything.
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Maximum number of redirects followed by a request.
+    #[arg(long, value_name = "N", default_value_t = 10)]
+    max_redirects: usize,
+
+    /// Keep sending repository credentials when a request is redirected to another host.
+    ///
+    /// By default credentials (tokens, API keys) are only sent to the repository itself,
+    /// e.g. not to the S3 storage a download is redirected to.
+    #[arg(long)]
+    cross_host_auth: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OnCollision {
+    /// Fail before downloading.
+    Error,
+    /// Rename all but the first path in byte order, e.g. `Readme~1.md`.
+    Rename,
+    /// Download every file to its own path, they may overwrite each other.
+    Overwrite,
+}
+
+impl From<OnCollision> for CollisionPolicy {
+    fn from(on_collision: OnCollision) -> Self {
+        match on_collision {
+            OnCollision::Error => CollisionPolicy::Error,
+            OnCollision::Rename => CollisionPolicy::RenameDeterministic,
+            OnCollision::Overwrite => CollisionPolicy::Overwrite,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Validation {
+    /// Fail them without downloading them, e.g. to mirror datasets for archival.
+    Strict,
+    /// Download them with a warning.
+    Warn,
+    /// Skip them.
+    Skip,
+}
+
+impl From<Validation> for ValidationPolicy {
+    fn from(validation: Validation) -> Self {
+        match validation {
+            Validation::Strict => ValidationPolicy::Strict,
+            Validation::Warn => ValidationPolicy::WarnAndContinue,
+            Validation::Skip => ValidationPolicy::Skip,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum LinkIdentical {
+    /// Hard links, copies where the filesystem has none.
+    Hardlink,
+    /// Relative symbolic links.
+    Symlink,
+    /// Copies, only saving the transfer.
+    Copy,
+}
+
+impl From<LinkIdentical> for LinkMode {
+    fn from(link_identical: LinkIdentical) -> Self {
+        match link_identical {
+            LinkIdentical::Hardlink => LinkMode::Hardlink,
+            LinkIdentical::Symlink => LinkMode::Symlink,
+            LinkIdentical::Copy => LinkMode::Copy,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ArxivFormatArg {
+    /// The rendered paper.
+    Pdf,
+    /// The e-print source, usually a tarball of the TeX files and figures.
+    Source,
+}
+
+impl From<ArxivFormatArg> for ArxivFormat {
+    fn from(format: ArxivFormatArg) -> Self {
+        match format {
+            ArxivFormatArg::Pdf => ArxivFormat::Pdf,
+            ArxivFormatArg::Source => ArxivFormat::Source,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum TargetOsArg {
+    /// The operating system datahugger runs on.
+    Host,
+    /// Linux, macOS and other unix.
+    Unix,
+    /// Windows.
+    Windows,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum TlsBackendArg {
+    /// The platform library, OpenSSL on Linux.
+    #[cfg(feature = "native-tls")]
+    NativeTls,
+    /// Pure Rust TLS.
+    #[cfg(feature = "rustls")]
+    Rustls,
+}
+
+impl From<TlsBackendArg> for TlsBackend {
+    fn from(tls_backend: TlsBackendArg) -> Self {
+        match tls_backend {
+            #[cfg(feature = "native-tls")]
+            TlsBackendArg::NativeTls => TlsBackend::NativeTls,
+            #[cfg(feature = "rustls")]
+            TlsBackendArg::Rustls => TlsBackend::Rustls,
+        }
+    }
+}
+
+impl From<TargetOsArg> for TargetOs {
+    fn from(target_os: TargetOsArg) -> Self {
+        match target_os {
+            TargetOsArg::Host => TargetOs::Host,
+            TargetOsArg::Unix => TargetOs::Unix,
+            TargetOsArg::Windows => TargetOs::Windows,
+        }
+    }
+}
+
+/// Settings shared by the subcommands.
+struct Settings {
+    client_options: ClientOptions,
+    /// Credentials of the environment, else of the config file.
+    auth: AuthConfig,
+    /// Installations of `repositories.toml` and the config file.
+    repositories: RepositoryConfig,
+}
+
+/// Resolves `url` with the credentials, repository installations and client options of
+/// `settings`.
+async fn resolve(url: &str, settings: &Settings) -> Result<Dataset, impl std::fmt::Debug> {
+    resolve_with_options(
+        url,
+        settings.auth.clone(),
+        &settings.repositories,
+        &settings.client_options,
+    )
+    .await
+}
+
+/// Path given by `--config`, read before parsing the command line as the config sets the
+/// defaults of its options.
+fn config_arg() -> Option<PathBuf> {
+    let mut args = std::env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            break;
+        }
+        if arg == "--config" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.to_str().and_then(|arg| arg.strip_prefix("--config=")) {
+            return Some(PathBuf::from(path));
+        }
+    }
+    None
+}
+
+/// Config of `--config`, else of [`Config::default_path`] if it exists, exits if it is
+/// invalid.
+fn load_config() -> Config {
+    let Some(path) = config_arg().or_else(|| Config::default_path().filter(|path| path.is_file()))
+    else {
+        return Config::default();
+    };
+    match Config::from_file(&path) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{err:?}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Sets the option defaults of `config` on the command line `cmd`, options given on the
+/// command line still override them.
+fn with_defaults(mut cmd: Command, config: &Config) -> Result<Command, String> {
+    for (subcommand, defaults) in &config.defaults {
+        let ids = {
+            let target = if subcommand.is_empty() {
+                &cmd
+            } else {
+                cmd.find_subcommand(subcommand)
+                    .ok_or_else(|| format!("unknown subcommand [{subcommand}] in config"))?
+            };
+            defaults
+                .keys()
+                .map(|name| {
+                    target
+                        .get_arguments()
+                        .find(|arg| arg.get_long() == Some(name.as_str()))
+                        .map(|arg| arg.get_id().clone())
+                        .ok_or_else(|| match subcommand.as_str() {
+                            "" => format!("unknown option '{name}' in config"),
+                            subcommand => {
+                                format!("unknown option '{name}' of [{subcommand}] in config")
+                            }
+                        })
+                })
+                .collect::<Result<Vec<_>, _>>()?
+        };
+        for (id, values) in ids.into_iter().zip(defaults.values()) {
+            let set_default = |arg: Arg| arg.default_values(values.clone());
+            cmd = if subcommand.is_empty() {
+                cmd.mut_arg(id, set_default)
+            } else {
+                cmd.mut_subcommand(subcommand, |sub| sub.mut_arg(id, set_default))
+            };
+        }
+    }
+    Ok(cmd)
+}
+
+fn parse_proxy(url: &str) -> Result<reqwest::Proxy, String> {
+    reqwest::Proxy::all(url).map_err(|err| err.to_string())
+}
+
+/// Parses a header given as 'Name: value'.
+fn parse_header(header: &str) -> Result<(HeaderName, HeaderValue), String> {
+    let (name, value) = header
+        .split_once(':')
+        .ok_or_else(|| format!("expect 'Name: value', got '{header}'"))?;
+    let name = HeaderName::try_from(name.trim()).map_err(|err| err.to_string())?;
+    let value = HeaderValue::try_from(value.trim()).map_err(|err| err.to_string())?;
+    Ok((name, value))
+}
+
+fn parse_date(date: &str) -> Result<SystemTime, String> {
+    if let Ok(timestamp) = date.parse::<jiff::Timestamp>() {
+        return Ok(timestamp.into());
+    }
+    let datetime = date.parse::<jiff::civil::DateTime>().map_err(|err| {
+        format!("expect a date like '2024-05-01' or '2024-05-01T12:00:00Z', {err}")
+    })?;
+    let zoned = datetime
+        .to_zoned(jiff::tz::TimeZone::UTC)
+        .map_err(|err| err.to_string())?;
+    Ok(zoned.timestamp().into())
+}
+
+/// Client sending the credentials of the repository of `repo` and the `cache` policy,
+/// following redirects by `redirect`.
+fn build_client(
+    client_options: &ClientOptions,
+    repo: &Dataset,
+    cache: CachePolicy,
+    redirect: RedirectPolicy,
+) -> Result<Client, reqwest::Error> {
+    let mut headers = repo.auth_headers();
+    headers.extend(cache.headers());
+    client_options
+        .builder()
+        .default_headers(headers)
+        .redirect(redirect.policy())
+        .build()
+}
+
+/// Writes the counters of the process to `path` if given, see `--metrics-out`.
+#[cfg(feature = "metrics")]
+fn write_metrics(path: Option<&std::path::Path>) {
+    let Some(path) = path else {
+        return;
+    };
+    if let Err(err) = fs::write(path, datahugger::metrics().render_prometheus()) {
+        eprintln!("cannot write metrics to '{}': {err}", path.display());
+    }
+}
+
+/// Selects `version` of the dataset if given, exits when the repository has no such version.
+async fn with_version(repo: Dataset, client: &Client, version: Option<&str>) -> Dataset {
+    let Some(version) = version else {
+        return repo;
+    };
+    match repo.with_version(client, version).await {
+        Ok(repo) => repo,
+        Err(err) => {
+            eprintln!("{err:?}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Returns a token cancelled by the first Ctrl-C, for the download to remove its partial
+/// files and report what is done. A second Ctrl-C exits at once.
+fn cancel_on_ctrl_c() -> CancellationToken {
+    let token = CancellationToken::new();
+    let cancel = token.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            eprintln!("cancelling the download, press Ctrl-C again to exit at once");
+            cancel.cancel();
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(130);
+            }
+        }
+    });
+    token
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // console_subscriber::init();
+    // the 'dataset' and 'file' spans are logged when they close, with the bytes, duration,
+    // retries and outcome recorded in them
     let subscriber = FmtSubscriber::builder()
         .with_thread_ids(true)
         .with_target(false)
+        .with_span_events(FmtSpan::CLOSE)
         .with_env_filter(EnvFilter::from_default_env())
         .finish();
 
@@ -75,88 +813,409 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // "https://osf.io/3ua2c/",
     // "https://dataverse.harvard.edu/dataset.xhtml?persistentId=doi:10.7910/DVN/KBHLOD",
     // "https://dataverse.harvard.edu/file.xhtml?persistentId=doi:10.7910/DVN/KBHLOD/DHJ45U",
-    let cli = Cli::parse();
+    let config = load_config();
+    let cmd = match with_defaults(Cli::command(), &config) {
+        Ok(cmd) => cmd,
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+    };
+    let cli = Cli::from_arg_matches(&cmd.get_matches()).unwrap_or_else(|err| err.exit());
+    let settings = Settings {
+        client_options: cli.client.options(),
+        auth: AuthConfig::from_env().with_fallback(config.auth),
+        repositories: RepositoryConfig::load().extend(config.repositories),
+    };
+    let client_options = &settings.client_options;
     match cli.command {
+        Commands::Completions(args) => {
+            clap_complete::generate(
+                args.shell,
+                &mut Cli::command(),
+                "datahugger",
+                &mut std::io::stdout(),
+            );
+        }
+        Commands::Manpage(args) => match args.out_dir {
+            Some(out_dir) => {
+                fs::create_dir_all(&out_dir)?;
+                clap_mangen::generate_to(Cli::command(), &out_dir)?;
+            }
+            None => clap_mangen::Man::new(Cli::command()).render(&mut std::io::stdout())?,
+        },
         Commands::Download(args) => {
-            let url = &args.url;
-            let user_agent = format!("datahugger-cli/{}", env!("CARGO_PKG_VERSION"));
-            let mut headers = HeaderMap::new();
-            if let Ok(token) = std::env::var("GITHUB_TOKEN") {
-                headers.insert(
-                    AUTHORIZATION,
-                    HeaderValue::from_str(&format!("token {token}"))?,
-                );
-            }
-            if let Ok(token) = std::env::var("DRYAD_API_TOKEN") {
-                headers.insert(
-                    AUTHORIZATION,
-                    HeaderValue::from_str(&format!("Bearer {token}"))?,
-                );
-            }
-            headers.insert(USER_AGENT, HeaderValue::from_str(&user_agent)?);
-            let client = ClientBuilder::new()
-                .user_agent(user_agent)
-                .default_headers(headers)
-                .use_native_tls()
-                .build()?;
-            let repo = match resolve(url).await {
+            let filter = match FileFilter::new(&args.include, &args.exclude) {
+                Ok(filter) => match args.modified_after {
+                    Some(time) => filter.modified_after(time),
+                    None => filter,
+                },
+                Err(err) => {
+                    eprintln!("invalid filter: {err:?}");
+                    std::process::exit(1);
+                }
+            };
+            let cache = match (args.no_cache, args.max_age) {
+                (true, _) => CachePolicy::NoCache,
+                (false, Some(max_age)) => CachePolicy::MaxAge(Duration::from_secs(max_age)),
+                (false, None) => CachePolicy::Default,
+            };
+            let redirect = RedirectPolicy::new()
+                .max_hops(args.max_redirects)
+                .same_host_auth_only(!args.cross_host_auth);
+            let mp = MultiProgress::new();
+            let dst = args.to.unwrap_or_else(|| PathBuf::from("."));
+            let mut options = DownloadOptions::new()
+                .limit(args.limit)
+                .filter(filter)
+                .include_hidden(args.include_hidden)
+                .expand_zip(args.expand_zip)
+                .check_access(args.check_access)
+                .check_disk_space(args.check_disk_space)
+                .continue_on_error(args.continue_on_error)
+                .lock(true)
+                .force_lock(args.force_lock)
+                .xattrs(args.xattr)
+                .manifest(args.manifest)
+                .self_checksum(args.self_checksum)
+                .validation_policy(args.validation.into())
+                .if_modified_since(args.if_modified_since)
+                .collision_policy(args.on_collision.into())
+                .target_os(args.target_os.into())
+                .probe_sizes(args.probe_sizes)
+                .dedup(args.dedup)
+                .dry_run(args.dry_run)
+                .connections_per_file(args.connections_per_file)
+                .chunk_threshold(args.chunk_threshold)
+                .archive_mode(args.archive_mode)
+                .client_options(client_options.clone().max_redirects(args.max_redirects))
+                .cancel(cancel_on_ctrl_c());
+            if let Some(max_file_size) = args.max_file_size {
+                options = options.max_file_size(max_file_size);
+            }
+            if let Some(link_identical) = args.link_identical {
+                options = options.link_identical(link_identical.into());
+            }
+            if let Some(store) = args.store {
+                options = options.store(ContentStore::new(store));
+            }
+
+            if let Some(input) = args.input {
+                let inputs = match fs::read_to_string(&input) {
+                    Ok(content) => content
+                        .lines()
+                        .map(str::trim)
+                        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                        .map(str::to_string)
+                        .collect::<Vec<_>>(),
+                    Err(err) => {
+                        eprintln!("cannot read '{}': {err}", input.display());
+                        std::process::exit(1);
+                    }
+                };
+                // with placeholders `--to` is the directory of each dataset
+                let (dst, options) = if dst.to_string_lossy().contains('{') {
+                    let template = dst.to_string_lossy().into_owned();
+                    (PathBuf::new(), options.dir_template(template))
+                } else {
+                    if !args.dry_run {
+                        fs::create_dir_all(&dst)?;
+                    }
+                    (dst, options)
+                };
+                let options = options
+                    .dataset_limit(args.dataset_limit)
+                    .repositories(settings.repositories.clone());
+                let report = download_many(&inputs, &settings.auth, dst, mp, &options, |repo| {
+                    build_client(client_options, repo, cache, redirect)
+                })
+                .await;
+                #[cfg(feature = "metrics")]
+                write_metrics(args.metrics_out.as_deref());
+                if args.dry_run {
+                    print!("{report}");
+                } else {
+                    eprint!("{report}");
+                }
+                if !report.is_success() {
+                    std::process::exit(1);
+                }
+                return Ok(());
+            }
+
+            let url = args.url.expect("clap requires the url without --input");
+            let repo = match resolve(&url, &settings).await {
                 Ok(repo) => repo,
                 Err(err) => {
                     eprintln!("failed to resolve '{url}': {err:?}");
                     std::process::exit(1);
                 }
             };
+            let client = build_client(client_options, &repo, cache, redirect)?;
+            let repo = match (args.subset, repo.backend.as_any().downcast_ref::<Thredds>()) {
+                (Some(query), Some(thredds)) => {
+                    let subset = thredds.clone().subset(query);
+                    repo.with_backend(subset)
+                }
+                (Some(_), None) => {
+                    eprintln!("--subset is only supported for THREDDS catalogs");
+                    std::process::exit(1);
+                }
+                (None, _) => repo,
+            };
+            let repo = match (
+                args.arxiv_format.is_empty(),
+                repo.backend.as_any().downcast_ref::<Arxiv>(),
+            ) {
+                (false, Some(arxiv)) => {
+                    let formats = arxiv
+                        .clone()
+                        .formats(args.arxiv_format.into_iter().map(ArxivFormat::from));
+                    repo.with_backend(formats)
+                }
+                (false, None) => {
+                    eprintln!("--arxiv-format is only supported for arXiv papers");
+                    std::process::exit(1);
+                }
+                (true, _) => repo,
+            };
+            let repo = with_version(repo, &client, args.dataset_version.as_deref()).await;
+            let dst = repo.destination(&dst.to_string_lossy());
+            if !args.dry_run {
+                fs::create_dir_all(&dst)?;
+            }
 
-            let mp = MultiProgress::new();
-            let dst = args.to.unwrap_or_else(|| PathBuf::from("."));
-            fs::create_dir_all(&dst)?;
-            let _ = repo
-                .download_with_validation(&client, dst, mp, args.limit)
-                .await
-                .map_err(|err| {
-                    eprintln!("download failed: {err:?}");
+            let source_url = repo.backend.root_url();
+            let backend = repo.backend_name();
+            let start = Instant::now();
+            let res = repo
+                .download_with_validation(&client, dst, mp, &options)
+                .await;
+            #[cfg(feature = "metrics")]
+            write_metrics(args.metrics_out.as_deref());
+            let report = match res {
+                Ok(report) => report,
+                Err(err) => {
+                    match DatahuggerError::from(&err) {
+                        DatahuggerError::AccessRequired(requirements) => {
+                            eprintln!("{requirements}")
+                        }
+                        err @ DatahuggerError::InsufficientSpace { .. } => eprintln!("{err}"),
+                        _ => eprintln!("download failed: {err:?}"),
+                    }
                     std::process::exit(1);
-                });
+                }
+            };
+            if let Some(stats_out) = args.stats_out {
+                let mut stats = report.stats();
+                stats["source_url"] = source_url.as_str().into();
+                stats["backend"] = backend.into();
+                stats["elapsed_ms"] = (start.elapsed().as_secs_f64() * 1000.0).into();
+                fs::write(&stats_out, serde_json::to_vec_pretty(&stats)?)?;
+            }
+            if args.dry_run {
+                print!("{report}");
+            } else {
+                eprint!("{report}");
+            }
+            if !report.is_success() {
+                std::process::exit(1);
+            }
         }
         Commands::Inspect(args) => {
             let url = &args.url;
-            let user_agent = format!("datahugger-cli/{}", env!("CARGO_PKG_VERSION"));
-            let mut headers = HeaderMap::new();
-            if let Ok(token) = std::env::var("GITHUB_TOKEN") {
-                headers.insert(
-                    AUTHORIZATION,
-                    HeaderValue::from_str(&format!("token {token}"))?,
-                );
-            }
-            if let Ok(token) = std::env::var("DRYAD_API_TOKEN") {
-                headers.insert(
-                    AUTHORIZATION,
-                    HeaderValue::from_str(&format!("Bearer {token}"))?,
-                );
-            }
-            headers.insert(USER_AGENT, HeaderValue::from_str(&user_agent)?);
-            let client = ClientBuilder::new()
-                .user_agent(user_agent)
-                .default_headers(headers)
-                .use_native_tls()
-                .build()?;
-            let repo = match resolve(url).await {
+            let repo = match resolve(url, &settings).await {
                 Ok(repo) => repo,
                 Err(err) => {
                     eprintln!("failed to resolve '{url}': {err:?}");
                     std::process::exit(1);
                 }
             };
+            let client = build_client(
+                client_options,
+                &repo,
+                CachePolicy::Default,
+                RedirectPolicy::default(),
+            )?;
 
             let mp = MultiProgress::new();
             let _ = repo
-                .print_meta(&client, mp, args.limit)
+                .print_meta(&client, mp, &CrawlOptions::new().limit(args.limit))
                 .await
                 .map_err(|err| {
                     eprintln!("inspect failed: {err:?}");
                     std::process::exit(1);
                 });
         }
+        Commands::Info(args) => {
+            let repo = match resolve(&args.url, &settings).await {
+                Ok(repo) => repo,
+                Err(err) => {
+                    eprintln!("failed to resolve '{}': {err:?}", args.url);
+                    std::process::exit(1);
+                }
+            };
+            let client = build_client(
+                client_options,
+                &repo,
+                CachePolicy::Default,
+                RedirectPolicy::default(),
+            )?;
+            let repo = with_version(repo, &client, args.dataset_version.as_deref()).await;
+            match repo.summary(&client, MultiProgress::new()).await {
+                Ok(summary) => print!("{summary}"),
+                Err(err) => {
+                    eprintln!("info failed: {err:?}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Gc(args) => match ContentStore::new(&args.store).gc() {
+            Ok(gc) => eprintln!(
+                "removed {} unused files ({} bytes)",
+                gc.removed, gc.freed_bytes
+            ),
+            Err(err) => {
+                eprintln!("gc failed: {err:?}");
+                std::process::exit(1);
+            }
+        },
+        Commands::Verify(args) => {
+            let report = if let Some(url) = &args.url {
+                let repo = match resolve(url, &settings).await {
+                    Ok(repo) => repo,
+                    Err(err) => {
+                        eprintln!("failed to resolve '{url}': {err:?}");
+                        std::process::exit(1);
+                    }
+                };
+                let client = build_client(
+                    client_options,
+                    &repo,
+                    CachePolicy::Default,
+                    RedirectPolicy::default(),
+                )?;
+                verify_remote(repo, &client, &args.dir, MultiProgress::new()).await
+            } else {
+                verify_manifest(&args.dir)
+            };
+            let report = match report {
+                Ok(report) => report,
+                Err(err) => {
+                    eprintln!("verify failed: {err:?}");
+                    std::process::exit(1);
+                }
+            };
+            eprint!("{report}");
+            if !report.is_intact() {
+                std::process::exit(1);
+            }
+        }
+        Commands::Cite(args) => {
+            let repo = match resolve(&args.url, &settings).await {
+                Ok(repo) => repo,
+                Err(err) => {
+                    eprintln!("failed to resolve '{}': {err:?}", args.url);
+                    std::process::exit(1);
+                }
+            };
+            let client = build_client(
+                client_options,
+                &repo,
+                CachePolicy::Default,
+                RedirectPolicy::default(),
+            )?;
+            match repo.citation(&client, args.style.into()).await {
+                Ok(citation) => println!("{citation}"),
+                Err(err) => {
+                    eprintln!("failed to cite '{}': {err:?}", args.url);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Snapshot(args) => {
+            let repo = match resolve(&args.url, &settings).await {
+                Ok(repo) => repo,
+                Err(err) => {
+                    eprintln!("failed to resolve '{}': {err:?}", args.url);
+                    std::process::exit(1);
+                }
+            };
+            let client = build_client(
+                client_options,
+                &repo,
+                CachePolicy::Default,
+                RedirectPolicy::default(),
+            )?;
+            let repo = with_version(repo, &client, args.dataset_version.as_deref()).await;
+            let snapshot = match repo.snapshot(&client, MultiProgress::new()).await {
+                Ok(snapshot) => snapshot,
+                Err(err) => {
+                    eprintln!("snapshot failed: {err:?}");
+                    std::process::exit(1);
+                }
+            };
+            if let Err(err) = snapshot.write(&args.output) {
+                eprintln!("{err:?}");
+                std::process::exit(1);
+            }
+            eprintln!(
+                "pinned {} files of {} record '{}' in {}",
+                snapshot.files.len(),
+                snapshot.backend,
+                snapshot.record_id,
+                args.output.display()
+            );
+        }
+        Commands::Restore(args) => {
+            let repo = match Snapshot::read(&args.snapshot) {
+                Ok(snapshot) => {
+                    Dataset::from_snapshot(
+                        &snapshot,
+                        settings.auth.clone(),
+                        &settings.repositories,
+                        client_options,
+                    )
+                    .await
+                }
+                Err(err) => Err(err),
+            };
+            let repo = match repo {
+                Ok(repo) => repo,
+                Err(err) => {
+                    eprintln!("failed to restore '{}': {err:?}", args.snapshot.display());
+                    std::process::exit(1);
+                }
+            };
+            let client = build_client(
+                client_options,
+                &repo,
+                CachePolicy::Default,
+                RedirectPolicy::default(),
+            )?;
+            let dst = args.to.unwrap_or_else(|| PathBuf::from("."));
+            fs::create_dir_all(&dst)?;
+            let options = DownloadOptions::new()
+                .limit(args.limit)
+                .lock(true)
+                .manifest(args.manifest)
+                .client_options(client_options.clone())
+                .cancel(cancel_on_ctrl_c());
+            let report = match repo
+                .download_with_validation(&client, dst, MultiProgress::new(), &options)
+                .await
+            {
+                Ok(report) => report,
+                Err(err) => {
+                    eprintln!("restore failed: {err:?}");
+                    std::process::exit(1);
+                }
+            };
+            eprint!("{report}");
+            if !report.is_success() {
+                std::process::exit(1);
+            }
+        }
     }
 
     Ok(())
@@ -0,0 +1,74 @@
+use exn::{Exn, ResultExt};
+use serde_json::json;
+use std::{fs, path::Path, time::SystemTime};
+
+use crate::{
+    crawler::CrawlerError,
+    error::ErrorStatus,
+    provenance::{checksum_str, unix_secs},
+    Dataset, DownloadReport, FileOutcome,
+};
+
+/// Name of the provenance manifest written into the destination directory, see
+/// [`DownloadOptions::manifest`](crate::DownloadOptions::manifest).
+pub const MANIFEST_FILE_NAME: &str = "datahugger.json";
+
+/// Writes the provenance manifest of the files downloaded in `report` into `dst_dir`.
+///
+/// Times are unix time in seconds, checksums are `<algo>:<hex>` and only set for verified
+/// files, or files hashed locally with
+/// [`DownloadOptions::self_checksum`](crate::DownloadOptions::self_checksum), which
+/// `checksum_source` tells apart. Skipped and failed files are not listed.
+pub(crate) fn write_manifest(
+    dst_dir: &Path,
+    dataset: &Dataset,
+    report: &DownloadReport,
+) -> Result<(), Exn<CrawlerError>> {
+    let mut files: Vec<_> = report
+        .downloaded()
+        .chain(report.linked())
+        .chain(report.reused())
+        .collect();
+    files.sort_by(|a, b| a.path.as_str().cmp(b.path.as_str()));
+    let files: Vec<_> = files
+        .into_iter()
+        .map(|file| {
+            json!({
+                "path": file.path.as_str(),
+                "download_url": file.download_url.as_str(),
+                // linked files are not written, their size is the local one
+                "size": match &file.outcome {
+                    FileOutcome::Linked(_) | FileOutcome::Reused => {
+                        fs::metadata(dst_dir.join(&file.path)).map_or(0, |m| m.len())
+                    }
+                    _ => file.bytes,
+                },
+                "checksum": file.checksum.as_ref().map(checksum_str),
+                "checksum_source": file.checksum.as_ref().map(|_| {
+                    if file.outcome == FileOutcome::SelfComputed {
+                        "self-computed"
+                    } else {
+                        "repository"
+                    }
+                }),
+            })
+        })
+        .collect();
+    let manifest = json!({
+        "datahugger_version": env!("CARGO_PKG_VERSION"),
+        "source_url": dataset.backend.root_url().as_str(),
+        "doi": dataset.doi,
+        "resolved_at": unix_secs(dataset.resolved_at),
+        "downloaded_at": unix_secs(SystemTime::now()),
+        "files": files,
+    });
+
+    let path = dst_dir.join(MANIFEST_FILE_NAME);
+    // Safe to unwrap: a json value always serializes
+    let content = serde_json::to_vec_pretty(&manifest).unwrap();
+    fs::write(&path, content).or_raise(|| CrawlerError {
+        message: format!("cannot write manifest at '{}'", path.display()),
+        status: ErrorStatus::Permanent,
+    })?;
+    Ok(())
+}
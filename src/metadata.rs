@@ -0,0 +1,69 @@
+use serde_json::Value as JsonValue;
+
+/// Descriptive metadata of a dataset, normalized across repositories.
+///
+/// Fields the repository does not provide are `None` (or empty for `creators`). Dates are
+/// kept as published by the repository, usually ISO 8601.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DatasetMetadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// authors or creators, in the order given by the repository.
+    pub creators: Vec<String>,
+    /// license identifier, name or url, as given by the repository.
+    pub license: Option<String>,
+    pub publication_date: Option<String>,
+    pub version: Option<String>,
+    /// DOI without the `doi:` or `https://doi.org/` prefix.
+    pub doi: Option<String>,
+}
+
+/// Strips the `doi:` and resolver prefixes of a DOI.
+pub(crate) fn normalize_doi(doi: &str) -> String {
+    let doi = doi.trim();
+    [
+        "https://doi.org/",
+        "http://doi.org/",
+        "https://dx.doi.org/",
+        "doi:",
+    ]
+    .iter()
+    .find_map(|prefix| doi.strip_prefix(prefix))
+    .unwrap_or(doi)
+    .to_string()
+}
+
+/// String at `pointer` of `json`, `None` if it is missing, not a string or empty.
+pub(crate) fn json_str(json: &JsonValue, pointer: &str) -> Option<String> {
+    json.pointer(pointer)
+        .and_then(JsonValue::as_str)
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(ToString::to_string)
+}
+
+/// Strings at `item` of every element of the array at `array`, e.g. the names of the authors.
+pub(crate) fn json_strs(json: &JsonValue, array: &str, item: &str) -> Vec<String> {
+    json.pointer(array)
+        .and_then(JsonValue::as_array)
+        .map(|items| items.iter().filter_map(|it| json_str(it, item)).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_doi() {
+        assert_eq!(
+            normalize_doi("doi:10.5061/dryad.mj8m0"),
+            "10.5061/dryad.mj8m0"
+        );
+        assert_eq!(
+            normalize_doi("https://doi.org/10.5281/zenodo.1"),
+            "10.5281/zenodo.1"
+        );
+        assert_eq!(normalize_doi("10.5281/zenodo.1"), "10.5281/zenodo.1");
+    }
+}
@@ -0,0 +1,205 @@
+//! Process wide counters of the requests and downloads of datahugger, exported in the
+//! Prometheus text format with the `metrics` feature.
+//!
+//! Without the feature the counters are not kept and recording is a no-op.
+
+#[cfg(feature = "metrics")]
+use crate::FileOutcome;
+#[cfg(feature = "metrics")]
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use crate::{DatahuggerError, FileReport};
+
+/// Counters of all downloads of the process, see [`metrics`].
+#[cfg(feature = "metrics")]
+#[derive(Debug)]
+pub struct Metrics {
+    requests: AtomicU64,
+    retries: AtomicU64,
+    bytes: AtomicU64,
+    /// files by [`FileOutcome`] label.
+    files: Mutex<BTreeMap<&'static str, u64>>,
+    /// failed files by [`DatahuggerError::category`].
+    failures: Mutex<BTreeMap<&'static str, u64>>,
+}
+
+#[cfg(feature = "metrics")]
+static METRICS: Metrics = Metrics {
+    requests: AtomicU64::new(0),
+    retries: AtomicU64::new(0),
+    bytes: AtomicU64::new(0),
+    files: Mutex::new(BTreeMap::new()),
+    failures: Mutex::new(BTreeMap::new()),
+};
+
+/// Returns the counters of the process, e.g. to serve them to Prometheus with
+/// [`Metrics::render_prometheus`].
+#[cfg(feature = "metrics")]
+#[must_use]
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+#[cfg(feature = "metrics")]
+impl Metrics {
+    /// HTTP requests sent, each retry included.
+    #[must_use]
+    pub fn requests(&self) -> u64 {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    /// Requests sent again after a `Retry-After`.
+    #[must_use]
+    pub fn retries(&self) -> u64 {
+        self.retries.load(Ordering::Relaxed)
+    }
+
+    /// Bytes of files written to their destination, partial downloads included.
+    #[must_use]
+    pub fn bytes(&self) -> u64 {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    /// Files with the outcome `label`, e.g. `verified` or `failed`.
+    #[must_use]
+    pub fn files(&self, label: &str) -> u64 {
+        let files = self.files.lock().expect("metrics lock poisoned");
+        files.get(label).copied().unwrap_or(0)
+    }
+
+    /// Failed files of the [`DatahuggerError::category`] `category`.
+    #[must_use]
+    pub fn failures(&self, category: &str) -> u64 {
+        let failures = self.failures.lock().expect("metrics lock poisoned");
+        failures.get(category).copied().unwrap_or(0)
+    }
+
+    /// Renders the counters in the Prometheus text exposition format, to be served on a
+    /// `/metrics` endpoint or written for the textfile collector of the node exporter.
+    #[must_use]
+    pub fn render_prometheus(&self) -> String {
+        let mut out = String::new();
+        let mut counter = |name: &str, help: &str, values: &[(String, u64)]| {
+            let _ = writeln!(out, "# HELP datahugger_{name} {help}");
+            let _ = writeln!(out, "# TYPE datahugger_{name} counter");
+            for (labels, value) in values {
+                let _ = writeln!(out, "datahugger_{name}{labels} {value}");
+            }
+        };
+        let labeled = |label: &str, map: &Mutex<BTreeMap<&'static str, u64>>| {
+            map.lock()
+                .expect("metrics lock poisoned")
+                .iter()
+                .map(|(key, value)| (format!("{{{label}=\"{key}\"}}"), *value))
+                .collect::<Vec<_>>()
+        };
+        counter(
+            "requests_total",
+            "HTTP requests sent to repositories and file hosts.",
+            &[(String::new(), self.requests())],
+        );
+        counter(
+            "retries_total",
+            "Requests sent again after a Retry-After.",
+            &[(String::new(), self.retries())],
+        );
+        counter(
+            "downloaded_bytes_total",
+            "Bytes of files written to their destination.",
+            &[(String::new(), self.bytes())],
+        );
+        counter(
+            "files_total",
+            "Files by outcome.",
+            &labeled("outcome", &self.files),
+        );
+        counter(
+            "failures_total",
+            "Failed files by category of error.",
+            &labeled("category", &self.failures),
+        );
+        out
+    }
+}
+
+/// Counts a request sent, `retry` if it is sent again after a `Retry-After`.
+pub(crate) fn record_request(retry: bool) {
+    #[cfg(feature = "metrics")]
+    {
+        METRICS.requests.fetch_add(1, Ordering::Relaxed);
+        if retry {
+            METRICS.retries.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = retry;
+}
+
+/// Counts the bytes and outcome of a file, with the category of `error` if it failed.
+pub(crate) fn record_file(report: &FileReport, error: Option<&DatahuggerError>) {
+    #[cfg(feature = "metrics")]
+    {
+        METRICS.bytes.fetch_add(report.bytes, Ordering::Relaxed);
+        let label = report.outcome.label();
+        *METRICS
+            .files
+            .lock()
+            .expect("metrics lock poisoned")
+            .entry(label)
+            .or_default() += 1;
+        if let FileOutcome::Failed(_) = report.outcome {
+            let category = error.map_or("other", DatahuggerError::category);
+            *METRICS
+                .failures
+                .lock()
+                .expect("metrics lock poisoned")
+                .entry(category)
+                .or_default() += 1;
+        }
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = (report, error);
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use super::*;
+    use crate::CrawlPath;
+    use std::time::Duration;
+    use url::Url;
+
+    #[test]
+    fn test_metrics_render_prometheus() {
+        let report = FileReport {
+            path: CrawlPath::root().join("a.csv").relative(),
+            download_url: Url::parse("https://example.org/a.csv").unwrap(),
+            checksum: None,
+            bytes: 10,
+            elapsed: Duration::ZERO,
+            timing: None,
+            outcome: FileOutcome::Failed("checksum mismatch".to_string()),
+        };
+        let error = DatahuggerError::ChecksumMismatch {
+            expected: "00".to_string(),
+            got: "11".to_string(),
+        };
+        // the counters are shared with the other tests of the process
+        let failures = metrics().failures("checksum_mismatch");
+        record_request(true);
+        record_file(&report, Some(&error));
+        assert!(metrics().retries() >= 1);
+        assert!(metrics().failures("checksum_mismatch") > failures);
+
+        let text = metrics().render_prometheus();
+        assert!(text.contains("# TYPE datahugger_requests_total counter\n"));
+        assert!(text.contains("datahugger_failures_total{category=\"checksum_mismatch\"} "));
+        assert!(text.contains("datahugger_files_total{outcome=\"failed\"} "));
+    }
+}
@@ -1,40 +1,55 @@
 use async_trait::async_trait;
-use exn::{Exn, OptionExt, ResultExt};
-use futures_core::stream::BoxStream;
-use futures_util::{StreamExt, TryStreamExt};
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use std::sync::Arc;
+use exn::{Exn, ResultExt};
+use futures_core::stream::{BoxStream, Stream};
+use futures_util::{future, StreamExt, TryStreamExt};
+use indicatif::MultiProgress;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+use std::time::{Duration, Instant};
 
 use reqwest::Client;
 
+use crate::retry::RetryExt;
 use crate::{
-    crawl,
-    crawler::{CrawlerError, ProgressManager},
+    archive::download_archive,
+    crawler::{crawl_with_fanout, dedup, dedup_with, CrawlerError, ProgressManager},
+    download::{download_crawled_file_with_validation, finish_local, FileProgress},
     error::ErrorStatus,
-    Dataset, Entry,
+    expand_zip,
+    identical::{link_identical, IdenticalFiles},
+    manifest,
+    metrics::record_file,
+    plan::local_files,
+    progress::TotalProgress,
+    redirect::follow_cross_host,
+    validate::expected_checksum,
+    zipindex::member_stream,
+    ClientOptions, CollisionPolicy, CrawlOptions, DatahuggerError, Dataset, DirLock, DownloadEvent,
+    DownloadOptions, DownloadPlan, DownloadReport, Entry, FileOutcome, FileReport, LocalWriter,
+    ValidatedStream,
 };
 
-use bytes::Buf;
-use digest::Digest;
+use bytes::Bytes;
 use std::{fs, path::Path};
-use tokio::{fs::OpenOptions, io::AsyncWriteExt};
-use tracing::{debug, instrument, warn};
+use tracing::{field::Empty, info_span, warn, Instrument, Span};
 
-use crate::{Checksum, Hasher};
+use crate::FileMeta;
 
 impl Dataset {
-    /// crawling and print the metadata of dirs and files
+    /// crawling and print the metadata of dirs and files selected by `options`
     /// # Errors
     /// when crawl fails
     pub async fn print_meta(
         &self,
         client: &Client,
         mp: MultiProgress,
-        limit: usize,
+        options: &CrawlOptions,
     ) -> Result<(), Exn<CrawlerError>> {
-        let root_dir = self.root_dir();
-        crawl(client.clone(), Arc::clone(&self.backend), root_dir, mp)
-            .try_for_each_concurrent(limit, |entry| async move {
+        self.clone()
+            .crawl(client, mp, options)
+            .try_for_each_concurrent(options.limit, |entry| async move {
                 match entry {
                     Entry::Dir(dir_meta) => {
                         println!("{dir_meta}");
@@ -54,201 +69,82 @@ impl Dataset {
     }
 }
 
-#[allow(clippy::too_many_lines)]
-#[instrument(skip(client, mp))]
-async fn download_crawled_file_with_validation<P>(
-    client: &Client,
-    src: Entry,
-    dst: P,
-    mp: impl ProgressManager,
-) -> Result<(), Exn<CrawlerError>>
-where
-    P: AsRef<Path> + std::fmt::Debug,
-{
-    debug!("downloading with validating");
-    match src {
-        Entry::Dir(dir_meta) => {
-            let path = dst.as_ref().join(dir_meta.relative());
-            // TODO: create_dir to be more strict on stream order
-            fs::create_dir_all(path.as_path()).or_raise(|| CrawlerError {
-                message: format!("cannot create dir {}", path.display()),
-                status: ErrorStatus::Permanent,
-            })?;
-            Ok(())
-        }
-        Entry::File(file_meta) => {
-            // prepare stream src
-            let pb = mp.insert(0, ProgressBar::new_spinner());
-            pb.set_style(
-                ProgressStyle::with_template("{spinner:.green} {msg}")
-                    .expect("indicatif template error"),
-            );
-            pb.enable_steady_tick(std::time::Duration::from_millis(100));
-            pb.set_message(format!(
-                "Connecting... {}",
-                file_meta.download_url().as_str()
-            ));
-
-            if !file_meta.is_downloadable() {
-                pb.set_message(format!(
-                    "{} is not downloadable",
-                    file_meta.download_url().as_str()
-                ));
-                return Ok(());
-            }
-
-            let resp = client
-                .get(file_meta.download_url())
-                .send()
+impl Dataset {
+    /// Streams the content of `file_meta`, a file crawled from the dataset, without writing
+    /// it to disk, e.g. to parse a csv on the fly or pipe it into a database.
+    ///
+    /// The size and checksum listed by the repository are verified as the stream ends, the
+    /// last item is an error if the content does not match them. Wrap the stream in a
+    /// `tokio_util::io::StreamReader` to read it as an `AsyncRead`.
+    ///
+    /// Redirects to another host are followed with a client of `client_options`, those
+    /// `client` was built from.
+    ///
+    /// ```no_run
+    /// # async fn run(dataset: datahugger::Dataset, file: datahugger::FileMeta) {
+    /// use futures_util::TryStreamExt;
+    ///
+    /// let options = datahugger::ClientOptions::default();
+    /// let client = options.builder().build().unwrap();
+    /// let mut stream = dataset.fetch_file(&client, &options, &file).await.unwrap();
+    /// let mut lines = 0;
+    /// while let Some(bytes) = stream.try_next().await.unwrap() {
+    ///     lines += bytes.iter().filter(|b| **b == b'\n').count();
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    /// when the request fails or the repository answers an error status.
+    pub async fn fetch_file(
+        &self,
+        client: &Client,
+        client_options: &ClientOptions,
+        file_meta: &FileMeta,
+    ) -> Result<BoxStream<'static, Result<Bytes, Exn<CrawlerError>>>, Exn<CrawlerError>> {
+        let url = file_meta.download_url();
+        let stream = if let Some(member) = file_meta.zip_member() {
+            member_stream(client, client_options, &url, member)
                 .await
                 .or_raise(|| CrawlerError {
-                    message: format!("fail to send http GET to {}", file_meta.download_url()),
+                    message: format!(
+                        "fail to read '{}' from the zip archive {url}",
+                        file_meta.relative()
+                    ),
                     status: ErrorStatus::Temporary,
                 })?
-                .error_for_status()
+        } else {
+            let resp = client
+                .get(url.clone())
+                .send_with_retry()
+                .await
                 .or_raise(|| CrawlerError {
-                    message: format!("fail to send http GET to {}", file_meta.download_url()),
-                    // Temporary??
+                    message: format!("fail to send http GET to {url}"),
                     status: ErrorStatus::Temporary,
                 })?;
-            pb.finish_and_clear();
-            let mut stream = resp.bytes_stream();
-            // prepare file dst
-            // NOTE: like in zenodo, the file path can exist without its parent dir as Dir entity
-            // being created first. To cover that case, the folder of the path will be created no
-            // matter it existed or not using `create_dir_all`.
-            // See issue #54.
-            let path = dst.as_ref().join(file_meta.relative());
-            let parent_dir = path.parent().ok_or_raise(|| CrawlerError {
-                message: format!("connot get parent dir for '{}'", path.display()),
-                status: ErrorStatus::Permanent,
-            })?;
-            fs::create_dir_all(parent_dir).or_raise(|| CrawlerError {
-                message: format!("connot create folder dir of '{}'", parent_dir.display()),
-                status: ErrorStatus::Permanent,
-            })?;
-            let mut fh = OpenOptions::new()
-                .write(true)
-                .create(true)
-                .truncate(true)
-                .open(path.as_path())
+            let resp = follow_cross_host(resp, client_options)
                 .await
                 .or_raise(|| CrawlerError {
-                    message: format!("fail on create file at {}", path.display()),
-                    status: ErrorStatus::Permanent,
-                })?;
-
-            let checksum = file_meta
-                .checksum()
-                .iter()
-                .find(|c| matches!(c, Checksum::Sha256(_)))
-                .or_else(|| file_meta.checksum().first());
-            let expected_size = file_meta.size();
-            let (mut hasher, expected_checksum) = if let Some(checksum) = checksum {
-                match checksum {
-                    Checksum::Sha256(value) => {
-                        (Some(Hasher::Sha256(sha2::Sha256::new())), Some(value))
-                    }
-                    Checksum::Md5(value) => (Some(Hasher::Md5(md5::Md5::new())), Some(value)),
-                    Checksum::Sha1(value) => (Some(Hasher::Sha1(sha1::Sha1::new())), Some(value)),
-                }
-            } else {
-                warn!("unable to find expected checksum to verify");
-                (None, None)
-            };
-
-            let style = ProgressStyle::with_template(
-                "{msg:<60} [{bar:40.cyan/blue}] \
-                 {decimal_bytes:>8}/{decimal_total_bytes:>8} \
-                 ({decimal_bytes_per_sec:>12}, {eta:>3})",
-            )
-            .unwrap()
-            .progress_chars("=>-");
-            let pb = if let Some(expected_size) = expected_size {
-                mp.insert_from_back(0, ProgressBar::new(expected_size))
-            } else {
-                mp.insert_from_back(0, ProgressBar::no_length())
-            };
-            pb.set_style(style);
-            pb.enable_steady_tick(std::time::Duration::from_millis(100));
-            pb.set_message(compact_path(file_meta.relative().as_str()));
-
-            let mut got_size = 0;
-            while let Some(item) = stream.next().await {
-                let mut bytes = item.or_raise(|| CrawlerError {
-                    message: "reqwest error stream".to_string(),
-                    status: ErrorStatus::Permanent,
+                    message: format!("fail to follow the redirect of {url}"),
+                    status: ErrorStatus::Temporary,
+                })?
+                .error_for_status()
+                .or_raise(|| CrawlerError {
+                    message: format!("fail to send http GET to {url}"),
+                    status: ErrorStatus::Temporary,
                 })?;
-                let chunk = bytes.chunk();
-                if let Some(ref mut hasher) = hasher {
-                    hasher.update(chunk);
-                }
-                let bytes_len = bytes.len() as u64;
-                got_size += bytes_len;
-                fh.write_all_buf(&mut bytes)
-                    .await
-                    .or_raise(|| CrawlerError {
-                        message: "fail at writing to fs".to_string(),
+            resp.bytes_stream()
+                .map(|item| {
+                    item.or_raise(|| CrawlerError {
+                        message: "reqwest error stream".to_string(),
                         status: ErrorStatus::Permanent,
-                    })?;
-                pb.inc(bytes_len);
-            }
-
-            pb.finish_and_clear();
-
-            if let (Some(expected_size), Some(expected_checksum)) =
-                (expected_size, expected_checksum)
-            {
-                if got_size != expected_size {
-                    exn::bail!(CrawlerError {
-                        message: format!("size wrong, expect {expected_size}, got {got_size}"),
-                        status: ErrorStatus::Permanent
-                    })
-                }
-
-                let checksum = hex::encode(hasher.expect("hasher is not none").finalize());
-
-                if checksum != *expected_checksum {
-                    exn::bail!(CrawlerError {
-                        message: format!(
-                            "checksum wrong, expect {expected_checksum}, got {checksum}"
-                        ),
-                        status: ErrorStatus::Permanent
                     })
-                }
-            }
-            Ok(())
-        }
-    }
-}
-
-fn compact_path(full_path: &str) -> String {
-    let path = Path::new(full_path);
-
-    // Get components
-    let mut comps: Vec<String> = path
-        .parent() // everything except the file name
-        .map(|p| {
-            p.components()
-                .map(|c| {
-                    let s = c.as_os_str().to_string_lossy();
-                    if s.is_empty() {
-                        String::new()
-                    } else {
-                        s.chars().next().unwrap().to_string()
-                    }
                 })
-                .collect()
-        })
-        .unwrap_or_default();
+                .boxed()
+        };
 
-    // Add base file name
-    if let Some(file_name) = path.file_name() {
-        comps.push(file_name.to_string_lossy().to_string());
+        Ok(ValidatedStream::new(stream, file_meta.clone()).boxed())
     }
-
-    // Join with slashes
-    comps.join("/")
 }
 
 #[async_trait]
@@ -258,8 +154,8 @@ pub trait DownloadExt {
         client: &Client,
         dst_dir: P,
         mp: impl ProgressManager,
-        limit: usize,
-    ) -> Result<(), Exn<CrawlerError>>
+        options: &DownloadOptions,
+    ) -> Result<DownloadReport, Exn<CrawlerError>>
     where
         P: AsRef<Path> + Sync + Send;
 }
@@ -280,21 +176,49 @@ impl DownloadExt for Dataset {
     /// - The downloaded file size matches the expected size.
     /// - The computed checksum matches the checksum provided by the repository metadata.
     ///
-    /// A validation failure for any file causes the entire operation to fail.
+    /// Local files are written to `<name>.part` and renamed to their final path once
+    /// validated, a part file failing validation is removed. A part file left by an
+    /// interrupted download is resumed with a range request when the server supports them.
+    ///
+    /// A validation failure for any file causes the entire operation to fail, unless
+    /// `DownloadOptions::continue_on_error` is set, then the failure is recorded in the
+    /// returned [`DownloadReport`] and the remaining files are still downloaded.
+    ///
+    /// # Options
+    ///
+    /// - Only files whose relative path and dates pass the filter of `options` are downloaded,
+    ///   see [`FileFilter`](crate::FileFilter). Hidden files such as `.DS_Store` are skipped
+    ///   unless [`DownloadOptions::include_hidden`] is set.
+    /// - Files larger than the max file size of `options` are skipped, files of unknown
+    ///   size are always downloaded.
+    /// - When an event sink is set, a [`DownloadEvent`] is sent for every file.
+    /// - Zip archives are replaced by their members when [`DownloadOptions::expand_zip`] is
+    ///   set, so that the filter selects single members.
+    /// - With [`DownloadOptions::check_access`], the access requirements of the dataset are
+    ///   checked before anything is downloaded.
+    /// - With [`DownloadOptions::check_disk_space`], the destination must have space for all
+    ///   files before anything is downloaded.
+    /// - In archive mode, the dataset is fetched as a single archive when the repository
+    ///   provides one, see [`DownloadOptions::archive_mode`].
+    ///
+    /// Use `DownloadOptions::default()` to download every file without limit.
     ///
     /// # Concurrency
     ///
-    /// Downloads are performed concurrently with a fixed upper limit to avoid overwhelming
-    /// the network or filesystem.
+    /// Downloads are performed concurrently with an upper limit (`DownloadOptions::limit`)
+    /// to avoid overwhelming the network or filesystem.
     ///
     /// # Errors
     ///
     /// Returns an error if:
     /// - Dataset crawling fails (e.g. invalid URLs or metadata).
+    /// - The destination directory cannot be created.
+    /// - Access requirements are not met when checking them before downloading.
+    /// - The destination has not enough space when checking it before downloading.
+    ///
+    /// and, when not continuing on per-file errors, if:
     /// - A file cannot be downloaded due to network or I/O errors.
-    /// - The destination directory cannot be created or written to.
     /// - File size or checksum validation fails for any file.
-    /// - Any underlying repository or HTTP client operation fails.
     ///
     ///
     /// * `P` is A path-like type specifying the destination directory.
@@ -303,49 +227,509 @@ impl DownloadExt for Dataset {
         client: &Client,
         dst_dir: P,
         mp: impl ProgressManager,
-        limit: usize,
-    ) -> Result<(), Exn<CrawlerError>>
+        options: &DownloadOptions,
+    ) -> Result<DownloadReport, Exn<CrawlerError>>
     where
         P: AsRef<Path> + Sync + Send,
     {
-        // TODO: deal with zip differently according to input instruction
+        let span = info_span!(
+            "dataset",
+            repo = self.backend_name(),
+            record_id = %self.record_id(),
+            files = Empty,
+            bytes = Empty,
+            failed = Empty,
+            duration_ms = Empty,
+        );
+        let start = Instant::now();
+        let res = download_dataset(self, client, dst_dir.as_ref(), mp, options)
+            .instrument(span.clone())
+            .await;
+        span.record("duration_ms", start.elapsed().as_millis());
+        let report = res?;
+        span.record("files", report.files.len());
+        span.record("bytes", report.total_bytes());
+        span.record("failed", report.failed().count());
+        Ok(report)
+    }
+}
 
-        let root_dir = self.root_dir();
-        let path = dst_dir.as_ref().join(root_dir.relative());
+/// Downloads `dataset` into `dst_dir`, see [`DownloadExt::download_with_validation`].
+async fn download_dataset(
+    dataset: Dataset,
+    client: &Client,
+    dst_dir: &Path,
+    mp: impl ProgressManager,
+    options: &DownloadOptions,
+) -> Result<DownloadReport, Exn<CrawlerError>> {
+    if options.check_access {
+        let requirements = dataset
+            .access_requirements(client)
+            .await
+            .or_raise(|| CrawlerError {
+                message: "fail to check the access requirements".to_string(),
+                status: ErrorStatus::Temporary,
+            })?;
+        if !requirements.is_empty() {
+            let message = format!("{requirements}");
+            return Err(
+                Exn::new(DatahuggerError::AccessRequired(requirements)).raise(CrawlerError {
+                    message,
+                    status: ErrorStatus::Permanent,
+                }),
+            );
+        }
+    }
+
+    let archive_url = if options.archive_mode && !options.writes_local() {
+        warn!("archive mode needs a local destination, downloading files one by one");
+        None
+    } else if options.archive_mode {
+        let archive_url = dataset.backend.archive_url();
+        if archive_url.is_none() {
+            warn!("the repository provides no dataset archive, downloading files one by one");
+        }
+        archive_url
+    } else {
+        None
+    };
+
+    // files listed more than once, reported as skipped
+    let duplicates = Mutex::new(Vec::new());
+    let mut report = if options.dry_run {
+        let entries = crawl_with_fanout(
+            client.clone(),
+            Arc::clone(&dataset.backend),
+            dataset.root_dir(),
+            mp,
+            options.crawl_fanout,
+        );
+        let entries = if options.expand_zip {
+            expand_zip(client.clone(), entries)
+        } else {
+            entries
+        };
+        plan_entries(dedup_entries(entries, options, &duplicates), options).await?
+    } else if let Some(archive_url) = archive_url {
+        download_archive(&dataset, archive_url, client, dst_dir, mp, options).await?
+    } else {
+        let root_dir = dataset.root_dir();
+        let path = dst_dir.join(root_dir.relative());
         fs::create_dir_all(path.as_path()).or_raise(|| CrawlerError {
             message: format!("cannot create dir at '{}'", path.display()),
             status: ErrorStatus::Permanent,
         })?;
-        crawl(
+        let entries = crawl_with_fanout(
             client.clone(),
-            Arc::clone(&self.backend),
+            Arc::clone(&dataset.backend),
             root_dir,
             mp.clone(),
-        )
+            options.crawl_fanout,
+        );
+        let entries = if options.expand_zip {
+            expand_zip(client.clone(), entries)
+        } else {
+            entries
+        };
+        // the crawl ends on cancel too, before all paths are collected below
+        let entries = dedup_entries(entries, options, &duplicates)
+            .take_until(options.cancel.clone().cancelled_owned());
+        if options.collision_policy == CollisionPolicy::Overwrite
+            && !options.probe_sizes
+            && !options.check_disk_space
+        {
+            let total = TotalProgress::streamed(&mp);
+            download_entries(entries, client, dst_dir, mp, options, total).await?
+        } else {
+            // collisions are only known once every path is crawled, sizes are probed
+            // and summed for all files before downloading
+            let files = entries
+                .try_filter_map(|entry| async move {
+                    match entry {
+                        Entry::Dir(_) => Ok(None),
+                        Entry::File(file_meta) => Ok(Some(file_meta)),
+                    }
+                })
+                .try_collect::<Vec<_>>()
+                .await?;
+            let plan = DownloadPlan::new(files);
+            plan.download_with_validation(client, dst_dir, mp, options)
+                .await?
+        }
+    };
+
+    report
+        .files
+        .extend(duplicates.into_inner().expect("report lock poisoned"));
+    report.doi.clone_from(&dataset.doi);
+    if options.manifest && !options.dry_run {
+        manifest::write_manifest(dst_dir, &dataset, &report)?;
+    }
+    Ok(report)
+}
+
+/// Drops files of `entries` listed more than once if `options` asks for it, reporting them
+/// in `skipped` as [`FileOutcome::Skipped`].
+fn dedup_entries<'a, S>(
+    entries: S,
+    options: &'a DownloadOptions,
+    skipped: &'a Mutex<Vec<FileReport>>,
+) -> BoxStream<'a, Result<Entry, Exn<CrawlerError>>>
+where
+    S: Stream<Item = Result<Entry, Exn<CrawlerError>>> + Send + 'a,
+{
+    if !options.dedup {
+        return entries.boxed();
+    }
+    dedup_with(entries, move |file_meta, reason| async move {
+        let file_meta = options.local_file(file_meta);
+        let path = file_meta.relative();
+        options
+            .emit(DownloadEvent::Skipped {
+                path: path.clone(),
+                reason: reason.clone(),
+            })
+            .await;
+        skipped
+            .lock()
+            .expect("report lock poisoned")
+            .push(FileReport {
+                path,
+                download_url: file_meta.download_url(),
+                checksum: None,
+                bytes: 0,
+                elapsed: Duration::ZERO,
+                timing: None,
+                outcome: FileOutcome::Skipped(reason),
+            });
+    })
+    .boxed()
+}
+
+/// Reports the files of `entries` selected by `options` as [`FileOutcome::Planned`], or
+/// skipped as a download would skip them, without requesting or writing any of them, see
+/// [`DownloadOptions::dry_run`].
+async fn plan_entries<S>(
+    entries: S,
+    options: &DownloadOptions,
+) -> Result<DownloadReport, Exn<CrawlerError>>
+where
+    S: Stream<Item = Result<Entry, Exn<CrawlerError>>> + Send,
+{
+    let files = entries
+        .try_filter(|entry| future::ready(options.is_selected(entry)))
+        .try_filter_map(|entry| async move {
+            match entry {
+                Entry::Dir(_) => Ok(None),
+                Entry::File(file_meta) => Ok(Some(file_meta)),
+            }
+        })
+        .try_collect::<Vec<_>>()
+        .await?;
+    // reported at the paths they would be downloaded to
+    let files = local_files(files, options).or_raise(|| CrawlerError {
+        message: "cannot resolve path collisions".to_string(),
+        status: ErrorStatus::Permanent,
+    })?;
+    let files = files
+        .into_iter()
+        .map(|file_meta| {
+            let outcome = match (file_meta.size(), options.max_file_size) {
+                _ if !file_meta.is_downloadable() => {
+                    FileOutcome::Skipped("not downloadable".to_string())
+                }
+                (Some(size), Some(max_file_size)) if size > max_file_size => FileOutcome::Skipped(
+                    format!("size {size} bytes exceeds max file size {max_file_size} bytes"),
+                ),
+                (size, _) => match options.validation_policy.precheck(&file_meta) {
+                    Ok(None) => FileOutcome::Planned(size),
+                    Ok(Some(reason)) => FileOutcome::Skipped(reason),
+                    Err(err) => FileOutcome::Failed(format!("{err}")),
+                },
+            };
+            FileReport {
+                path: file_meta.relative(),
+                download_url: file_meta.download_url(),
+                checksum: None,
+                bytes: 0,
+                elapsed: Duration::ZERO,
+                timing: None,
+                outcome,
+            }
+        })
+        .collect();
+    Ok(DownloadReport {
+        files,
+        doi: None,
+        cancelled: false,
+    })
+}
+
+/// Locks `dst_dir` for the duration of the download if `options` asks for it.
+pub(crate) fn lock_dst(
+    dst_dir: &Path,
+    options: &DownloadOptions,
+) -> Result<Option<DirLock>, Exn<CrawlerError>> {
+    if !options.lock {
+        return Ok(None);
+    }
+    let lock = DirLock::acquire(dst_dir, options.force_lock).or_raise(|| CrawlerError {
+        message: format!("cannot lock destination '{}'", dst_dir.display()),
+        status: ErrorStatus::Permanent,
+    })?;
+    Ok(Some(lock))
+}
+
+/// Downloads a stream of crawled entries into `dst_dir`, applying the filter, concurrency
+/// limit and event sink of `options`.
+pub(crate) async fn download_entries<S>(
+    entries: S,
+    client: &Client,
+    dst_dir: &Path,
+    mp: impl ProgressManager,
+    options: &DownloadOptions,
+    total: TotalProgress,
+) -> Result<DownloadReport, Exn<CrawlerError>>
+where
+    S: Stream<Item = Result<Entry, Exn<CrawlerError>>> + Send,
+{
+    let entries = entries
+        .try_filter(|entry| future::ready(options.is_selected(entry)))
+        .map_ok(|entry| options.local_entry(entry));
+    download_local_entries(entries, client, dst_dir, mp, options, total).await
+}
+
+/// Downloads a stream of entries selected by the filter of `options` and already moved to
+/// their local paths into `dst_dir`, like [`download_entries`].
+pub(crate) async fn download_local_entries<S>(
+    entries: S,
+    client: &Client,
+    dst_dir: &Path,
+    mp: impl ProgressManager,
+    options: &DownloadOptions,
+    total: TotalProgress,
+) -> Result<DownloadReport, Exn<CrawlerError>>
+where
+    S: Stream<Item = Result<Entry, Exn<CrawlerError>>> + Send,
+{
+    let _lock = lock_dst(dst_dir, options)?;
+    let reports = Mutex::new(Vec::new());
+    let identical = IdenticalFiles::default();
+    // no file is started once cancelled
+    let stopped = AtomicBool::new(false);
+    let res = entries
+        .take_until(async {
+            options.cancel.cancelled().await;
+            stopped.store(true, Ordering::Relaxed);
+        })
+        .inspect_ok(|entry| total.add_streamed(entry))
         // NOTE: limit set to 0 as default for cli download,
         // should set to 20 for polite crawling for every dataset, it limit the stream consumer rate.
-        .try_for_each_concurrent(limit, |entry| {
-            let dst_dir = dst_dir.as_ref().to_path_buf();
+        .try_for_each_concurrent(options.limit, |entry| {
             let mp = mp.clone();
+            let reports = &reports;
+            let total = &total;
+            let identical = &identical;
             async move {
-                download_crawled_file_with_validation(client, entry, &dst_dir, mp).await?;
-                Ok(())
+                let source = match &entry {
+                    Entry::File(file_meta) => Some((
+                        file_meta.relative(),
+                        file_meta.download_url(),
+                        expected_checksum(file_meta).cloned(),
+                        file_meta.size(),
+                    )),
+                    Entry::Dir(_) => None,
+                };
+                let span = match &source {
+                    Some((path, download_url, _, size)) => info_span!(
+                        "file",
+                        path = %path,
+                        url = %download_url,
+                        size,
+                        bytes = Empty,
+                        duration_ms = Empty,
+                        retries = Empty,
+                        outcome = Empty,
+                    ),
+                    None => Span::none(),
+                };
+                let start = Instant::now();
+                // held until the file is downloaded, for identical files to wait for it
+                let mut slot = match (&entry, options.link_identical) {
+                    (Entry::File(file_meta), Some(_)) if options.writer.is_none() => {
+                        identical.slot(file_meta).await
+                    }
+                    _ => None,
+                };
+                let store = options.store.as_ref().filter(|_| options.writer.is_none());
+                let stored = match (store, &source) {
+                    (Some(store), Some((_, _, Some(checksum), _))) => store.lookup(checksum),
+                    _ => None,
+                };
+                let mut progress = FileProgress::default();
+                let res = match (stored, slot.as_deref(), &source, options.link_identical) {
+                    (Some(object), _, Some((path, ..)), _) => {
+                        let res = store
+                            .expect("stored content without store")
+                            .materialize(&object, &dst_dir.join(path))
+                            .or_raise(|| CrawlerError {
+                                message: format!("cannot reuse '{}'", object.display()),
+                                status: ErrorStatus::Permanent,
+                            });
+                        finish_local(options, dst_dir, path, res.map(|()| FileOutcome::Reused))
+                            .await
+                    }
+                    (None, Some(Some(original)), Some((path, ..)), Some(mode)) => {
+                        let res = link_identical(dst_dir, original, path, mode);
+                        let outcome = FileOutcome::Linked(original.clone());
+                        finish_local(options, dst_dir, path, res.map(|()| outcome)).await
+                    }
+                    _ => {
+                        let download = download_crawled_file_with_validation(
+                            client,
+                            entry,
+                            dst_dir,
+                            mp,
+                            options,
+                            &mut progress,
+                            total,
+                        )
+                        .instrument(span.clone());
+                        // the transfer is dropped, with the file it writes, before its part
+                        // file is removed
+                        let res = tokio::select! {
+                            res = download => Some(res),
+                            () = options.cancel.cancelled() => None,
+                        };
+                        match (res, &source) {
+                            (Some(res), _) => res,
+                            (None, Some((path, ..))) => {
+                                if options.writer.is_none() {
+                                    LocalWriter::new(dst_dir).discard(path).await;
+                                }
+                                options
+                                    .emit(DownloadEvent::Cancelled { path: path.clone() })
+                                    .await;
+                                Ok(Some(FileOutcome::Cancelled))
+                            }
+                            (None, None) => Ok(None),
+                        }
+                    }
+                };
+                if let (Some(store), Some((path, _, checksum, _)), Ok(Some(outcome))) =
+                    (store, &source, &res)
+                {
+                    if outcome.is_downloaded() {
+                        let verified = match outcome {
+                            FileOutcome::SelfComputed => progress.self_checksum.clone(),
+                            _ => checksum.clone().filter(|_| outcome.is_checksum_verified()),
+                        };
+                        let store = store.clone();
+                        let file = dst_dir.join(path);
+                        let sha256 = progress.sha256.take();
+                        let inserted = tokio::task::spawn_blocking(move || match sha256 {
+                            Some(sha256) => store.insert_hashed(&file, sha256, verified.as_ref()),
+                            None => store.insert(&file, verified.as_ref()),
+                        })
+                        .await;
+                        match inserted {
+                            Ok(Ok(_)) => {}
+                            Ok(Err(err)) => warn!("cannot keep '{path}' in the store: {err:?}"),
+                            Err(err) => warn!("cannot keep '{path}' in the store: {err}"),
+                        }
+                    }
+                }
+                if let (Some(slot), Some((path, ..))) = (&mut slot, &source) {
+                    if slot.is_none()
+                        && res.as_ref().is_ok_and(|o| {
+                            o.as_ref().is_some_and(FileOutcome::is_checksum_verified)
+                        })
+                    {
+                        **slot = Some(path.clone());
+                    }
+                }
+                drop(slot);
+                let Some((path, download_url, mut checksum, size)) = source else {
+                    return res.map(|_| ());
+                };
+                total.finish_file(size, progress.bytes);
+                let mut error = None;
+                let (outcome, res) = match res {
+                    Ok(outcome) => (outcome.expect("file entry has an outcome"), Ok(())),
+                    Err(err) => {
+                        error = Some(DatahuggerError::from(&err));
+                        let message = format!("{err}");
+                        options
+                            .emit(DownloadEvent::Failed {
+                                path: path.clone(),
+                                message: message.clone(),
+                            })
+                            .await;
+                        let res = if options.continue_on_error {
+                            warn!("download of '{path}' failed: {err:?}");
+                            Ok(())
+                        } else {
+                            Err(err)
+                        };
+                        (FileOutcome::Failed(message), res)
+                    }
+                };
+                if outcome == FileOutcome::SelfComputed {
+                    checksum = progress.self_checksum;
+                }
+                let report = FileReport {
+                    path,
+                    download_url,
+                    checksum: checksum.filter(|_| {
+                        outcome.is_checksum_verified()
+                            || matches!(
+                                outcome,
+                                FileOutcome::SelfComputed
+                                    | FileOutcome::Linked(_)
+                                    | FileOutcome::Reused
+                            )
+                    }),
+                    bytes: progress.bytes,
+                    elapsed: start.elapsed(),
+                    timing: progress.timing,
+                    outcome,
+                };
+                span.record("bytes", report.bytes);
+                span.record("duration_ms", report.elapsed.as_millis());
+                span.record("retries", report.timing.map_or(0, |t| t.retries));
+                span.record("outcome", report.outcome.label());
+                record_file(&report, error.as_ref());
+                reports.lock().expect("report lock poisoned").push(report);
+                res
             }
         })
-        .await
-        .or_raise(|| CrawlerError {
-            message: "crawl, download and validation failed".to_string(),
-            status: ErrorStatus::Permanent,
-        })?;
-        Ok(())
-    }
+        .await;
+    total.finish();
+    res.or_raise(|| CrawlerError {
+        message: "crawl, download and validation failed".to_string(),
+        status: ErrorStatus::Permanent,
+    })?;
+    let files = reports.into_inner().expect("report lock poisoned");
+    let cancelled =
+        stopped.into_inner() || files.iter().any(|f| f.outcome == FileOutcome::Cancelled);
+    Ok(DownloadReport {
+        files,
+        doi: None,
+        cancelled,
+    })
 }
 
 pub trait CrawlExt {
+    /// Streams the entries of the dataset selected by `options`, directories listed
+    /// concurrently up to [`CrawlOptions::crawl_fanout`].
+    ///
+    /// Parents are yielded before children, a directory before the entries inside it, see
+    /// [`Entry`].
     fn crawl(
         self,
         client: &Client,
         mp: impl ProgressManager,
+        options: &CrawlOptions,
     ) -> BoxStream<'static, Result<Entry, Exn<CrawlerError>>>;
 }
 
@@ -354,13 +738,1073 @@ impl CrawlExt for Dataset {
         self,
         client: &Client,
         mp: impl ProgressManager,
+        options: &CrawlOptions,
     ) -> BoxStream<'static, Result<Entry, Exn<CrawlerError>>> {
         let root_dir = self.root_dir();
-        crawl(
+        let entries = crawl_with_fanout(
             client.clone(),
             Arc::clone(&self.backend),
             root_dir,
             mp.clone(),
+            options.crawl_fanout,
+        );
+        let entries = if options.expand_zip {
+            expand_zip(client.clone(), entries)
+        } else {
+            entries
+        };
+        let entries = if options.dedup {
+            dedup(entries)
+        } else {
+            entries
+        };
+        let options = options.clone();
+        entries
+            .take_until(options.cancel.clone().cancelled_owned())
+            .try_filter(move |entry| future::ready(options.is_selected(entry)))
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        chunked::{first_range, CHUNK_SIZE},
+        datasets::{HalJsonSrcDataset, ZenodoJsonSrcDataset},
+        testutil::{zenodo_entry, NoProgress, TempDir, TestFile},
+        Checksum, ContentStore, DirMeta, FileFilter, LinkMode, ObjectWriter, ValidationPolicy,
+    };
+    use digest::Digest;
+    use tokio::sync::mpsc;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    // writes below another root, like a bucket would
+    #[derive(Debug)]
+    struct RemoteWriter(LocalWriter);
+
+    #[async_trait::async_trait]
+    impl ObjectWriter for RemoteWriter {
+        async fn create(
+            &self,
+            path: &crate::CrawlPath,
+        ) -> Result<crate::ObjectSink, Exn<crate::WriterError>> {
+            self.0.create(path).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_download_with_writer() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/files/raw/b.csv"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("c,d\n"))
+            .mount(&mock_server)
+            .await;
+        let json = serde_json::json!({
+            "entries": [zenodo_entry(&mock_server.uri(), "raw/b.csv", "c,d\n")]
+        });
+        let ds = Dataset::new(ZenodoJsonSrcDataset::new("1", json.to_string()));
+
+        let dst = TempDir::new("download-writer");
+        let bucket = TempDir::new("download-writer-bucket");
+        let options = DownloadOptions::new()
+            .writer(RemoteWriter(LocalWriter::new(&*bucket)))
+            .manifest(true);
+        let report = ds
+            .download_with_validation(&Client::new(), &dst, NoProgress, &options)
+            .await
+            .unwrap();
+
+        assert_eq!(report.files[0].outcome, FileOutcome::Verified);
+        assert_eq!(
+            fs::read_to_string(bucket.join("raw/b.csv")).unwrap(),
+            "c,d\n"
+        );
+        assert!(!dst.join("raw").exists());
+        assert!(dst.join(crate::MANIFEST_FILE_NAME).exists());
+    }
+
+    #[tokio::test]
+    async fn test_crawl_options() {
+        let uri = "https://example.org";
+        let json = serde_json::json!({
+            "entries": [
+                zenodo_entry(uri, "a.csv", "a"),
+                zenodo_entry(uri, "b.txt", "b"),
+                zenodo_entry(uri, ".DS_Store", "c"),
+            ]
+        });
+        let ds = Dataset::new(ZenodoJsonSrcDataset::new("1", json.to_string()));
+        let paths = |options: CrawlOptions| {
+            ds.clone()
+                .crawl(&Client::new(), NoProgress, &options)
+                .map_ok(|entry| match entry {
+                    Entry::File(file_meta) => file_meta.relative().as_str().to_string(),
+                    Entry::Dir(dir_meta) => dir_meta.relative().as_str().to_string(),
+                })
+                .try_collect::<Vec<_>>()
+        };
+        // every entry by default, hidden files included
+        let mut all = paths(CrawlOptions::new()).await.unwrap();
+        all.sort();
+        assert_eq!(all, [".DS_Store", "a.csv", "b.txt"]);
+
+        let options = CrawlOptions::new()
+            .filter(FileFilter::new(&["*.csv", ".*"], &["tmp/**"]).unwrap())
+            .include_hidden(false);
+        assert_eq!(paths(options).await.unwrap(), ["a.csv"]);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_file() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/files/a.csv"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("a,b\n1,2\n"))
+            .mount(&mock_server)
+            .await;
+        let json = serde_json::json!({
+            "entries": [
+                zenodo_entry(&mock_server.uri(), "a.csv", "a,b\n1,2\n"),
+                // listed with the checksum of other content
+                zenodo_entry(&mock_server.uri(), "a.csv", "c,d\n3,4\n"),
+            ]
+        });
+        let ds = Dataset::new(ZenodoJsonSrcDataset::new("1", json.to_string()));
+        let client = Client::new();
+        let files = ds
+            .clone()
+            .crawl(&client, NoProgress, &CrawlOptions::new())
+            .try_filter_map(|entry| async move {
+                match entry {
+                    Entry::File(file_meta) => Ok(Some(file_meta)),
+                    Entry::Dir(_) => Ok(None),
+                }
+            })
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        let content = ds
+            .fetch_file(&client, &ClientOptions::default(), &files[0])
+            .await
+            .unwrap()
+            .map_ok(|bytes| bytes.to_vec())
+            .try_concat()
+            .await
+            .unwrap();
+        assert_eq!(content, b"a,b\n1,2\n");
+
+        let err = ds
+            .fetch_file(&client, &ClientOptions::default(), &files[1])
+            .await
+            .unwrap()
+            .map_ok(|bytes| bytes.to_vec())
+            .try_concat()
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            DatahuggerError::from(&err),
+            DatahuggerError::ChecksumMismatch { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_download_with_filter_and_event_sink() {
+        let mock_server = MockServer::start().await;
+        for (key, content) in [("a.csv", "a,b\n1,2\n"), ("raw/b.csv", "c,d\n")] {
+            Mock::given(method("GET"))
+                .and(path(format!("/files/{key}")))
+                .respond_with(ResponseTemplate::new(200).set_body_string(content))
+                .mount(&mock_server)
+                .await;
+        }
+        let json = serde_json::json!({
+            "entries": [
+                zenodo_entry(&mock_server.uri(), "a.csv", "a,b\n1,2\n"),
+                zenodo_entry(&mock_server.uri(), "raw/b.csv", "c,d\n"),
+            ]
+        });
+        let ds = Dataset::new(ZenodoJsonSrcDataset::new("1", json.to_string()));
+
+        let dst = TempDir::new("download-event-sink");
+        let (tx, mut rx) = mpsc::channel(64);
+        let called = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let options = DownloadOptions::new()
+            .filter(FileFilter::new(&["*.csv"], &["raw/**"]).unwrap())
+            .event_sink(tx)
+            .on_event({
+                let called = Arc::clone(&called);
+                move |event| called.lock().unwrap().push(event.clone())
+            });
+        ds.download_with_validation(&Client::new(), &dst, NoProgress, &options)
+            .await
+            .unwrap();
+        drop(options);
+
+        assert_eq!(fs::read_to_string(dst.join("a.csv")).unwrap(), "a,b\n1,2\n");
+        assert!(!dst.join("raw").exists());
+
+        let mut events = Vec::new();
+        while let Some(event) = rx.recv().await {
+            events.push(event);
+        }
+        assert!(matches!(
+            events.first(),
+            Some(DownloadEvent::Started { .. })
+        ));
+        assert!(matches!(
+            events.last(),
+            Some(DownloadEvent::Finished { size: 8, .. })
+        ));
+        assert!(events.iter().all(|e| e.path().as_str() == "a.csv"));
+        assert!(events.iter().any(|e| matches!(
+            e,
+            DownloadEvent::Progress {
+                downloaded: 8,
+                total: Some(8),
+                ..
+            }
+        )));
+        assert_eq!(called.lock().unwrap().len(), events.len());
+    }
+
+    #[tokio::test]
+    async fn test_download_path_mapper() {
+        let mock_server = MockServer::start().await;
+        for name in ["a.txt", "b.txt"] {
+            Mock::given(method("GET"))
+                .and(path(format!("/files/{name}")))
+                .respond_with(ResponseTemplate::new(200).set_body_string(name))
+                .mount(&mock_server)
+                .await;
+        }
+        let json = serde_json::json!({
+            "entries": [
+                zenodo_entry(&mock_server.uri(), "a.txt", "a.txt"),
+                zenodo_entry(&mock_server.uri(), "b.txt", "b.txt"),
+            ]
+        });
+        let ds = Dataset::new(ZenodoJsonSrcDataset::new("1", json.to_string()));
+
+        let dst = TempDir::new("download-path-mapper");
+        // `..` does not escape the destination
+        let options = DownloadOptions::new()
+            .path_mapper(|f| format!("../objects//{}", f.relative().as_str().to_uppercase()));
+        let report = ds
+            .download_with_validation(&Client::new(), &dst, NoProgress, &options)
+            .await
+            .unwrap();
+
+        assert_eq!(report.downloaded().count(), 2);
+        assert_eq!(
+            fs::read_to_string(dst.join("objects/A.TXT")).unwrap(),
+            "a.txt"
+        );
+        assert!(dst.join("objects/B.TXT").exists());
+        assert!(!dst.join("a.txt").exists());
+        assert!(report
+            .files
+            .iter()
+            .all(|f| f.path.as_str().starts_with("objects/")));
+    }
+
+    #[tokio::test]
+    async fn test_download_collisions_of_local_paths() {
+        let mock_server = MockServer::start().await;
+        for name in ["a-b.txt", "a_b.txt"] {
+            Mock::given(method("GET"))
+                .and(path(format!("/files/{name}")))
+                .respond_with(ResponseTemplate::new(200).set_body_string(name))
+                .mount(&mock_server)
+                .await;
+        }
+        let json = serde_json::json!({
+            "entries": [
+                zenodo_entry(&mock_server.uri(), "a-b.txt", "a-b.txt"),
+                zenodo_entry(&mock_server.uri(), "a_b.txt", "a_b.txt"),
+            ]
+        });
+        let ds = Dataset::new(ZenodoJsonSrcDataset::new("1", json.to_string()));
+
+        let dst = TempDir::new("download-local-collisions");
+        // the paths only collide once mapped
+        let options = DownloadOptions::new()
+            .path_mapper(|f| f.relative().as_str().replace('-', "_"))
+            .collision_policy(CollisionPolicy::Error);
+        let err = ds
+            .clone()
+            .download_with_validation(
+                &Client::new(),
+                &dst,
+                NoProgress,
+                &options.clone().dry_run(true),
+            )
+            .await
+            .unwrap_err();
+        assert!(format!("{err:?}").contains("paths differ only by case: 'a_b.txt', 'a_b.txt'"));
+
+        let options = options.collision_policy(CollisionPolicy::RenameDeterministic);
+        let report = ds
+            .clone()
+            .download_with_validation(
+                &Client::new(),
+                &dst,
+                NoProgress,
+                &options.clone().dry_run(true),
+            )
+            .await
+            .unwrap();
+        let mut paths = report
+            .files
+            .iter()
+            .map(|f| f.path.as_str())
+            .collect::<Vec<_>>();
+        paths.sort_unstable();
+        assert_eq!(paths, ["a_b.txt", "a_b~1.txt"]);
+
+        let report = ds
+            .download_with_validation(&Client::new(), &dst, NoProgress, &options)
+            .await
+            .unwrap();
+        assert_eq!(report.downloaded().count(), 2);
+        assert_eq!(fs::read_to_string(dst.join("a_b.txt")).unwrap(), "a-b.txt");
+        assert_eq!(
+            fs::read_to_string(dst.join("a_b~1.txt")).unwrap(),
+            "a_b.txt"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_dedup() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/files/a.txt"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("a"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let json = serde_json::json!({
+            "entries": [
+                zenodo_entry(&mock_server.uri(), "a.txt", "a"),
+                zenodo_entry(&mock_server.uri(), "a.txt", "a"),
+            ]
+        });
+        let ds = Dataset::new(ZenodoJsonSrcDataset::new("1", json.to_string()));
+
+        let dst = TempDir::new("download-dedup");
+        let report = ds
+            .download_with_validation(
+                &Client::new(),
+                &dst,
+                NoProgress,
+                &DownloadOptions::new().dedup(true),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(report.downloaded().count(), 1);
+        let skipped = report.skipped().collect::<Vec<_>>();
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].path.as_str(), "a.txt");
+        assert!(matches!(
+            &skipped[0].outcome,
+            FileOutcome::Skipped(reason) if reason == "listed more than once"
+        ));
+        assert!(report.is_success());
+    }
+
+    #[tokio::test]
+    async fn test_download_skip_max_file_size() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/files/small.txt"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("small"))
+            .mount(&mock_server)
+            .await;
+        let json = serde_json::json!({
+            "entries": [
+                zenodo_entry(&mock_server.uri(), "small.txt", "small"),
+                zenodo_entry(&mock_server.uri(), "large.txt", "a much larger file"),
+            ]
+        });
+        let ds = Dataset::new(ZenodoJsonSrcDataset::new("1", json.to_string()));
+
+        let dst = TempDir::new("download-max-file-size");
+        let (tx, mut rx) = mpsc::channel(64);
+        let options = DownloadOptions::new().max_file_size(10).event_sink(tx);
+        let report = ds
+            .download_with_validation(&Client::new(), &dst, NoProgress, &options)
+            .await
+            .unwrap();
+        drop(options);
+
+        assert_eq!(report.downloaded().count(), 1);
+        assert_eq!(report.skipped().count(), 1);
+
+        assert!(dst.join("small.txt").exists());
+        assert!(!dst.join("large.txt").exists());
+        let mut skipped = Vec::new();
+        while let Some(event) = rx.recv().await {
+            if let DownloadEvent::Skipped { path, .. } = event {
+                skipped.push(path.as_str().to_string());
+            }
+        }
+        assert_eq!(skipped, ["large.txt"]);
+    }
+
+    #[tokio::test]
+    async fn test_download_dry_run() {
+        let mock_server = MockServer::start().await;
+        // no file is requested
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("data"))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+        let json = serde_json::json!({
+            "entries": [
+                zenodo_entry(&mock_server.uri(), "small.txt", "small"),
+                zenodo_entry(&mock_server.uri(), "large.txt", "a much larger file"),
+                zenodo_entry(&mock_server.uri(), "notes.md", "notes"),
+            ]
+        });
+        let ds = Dataset::new(ZenodoJsonSrcDataset::new("1", json.to_string()));
+
+        let tmp = TempDir::new("download-dry-run");
+        let dst = tmp.join("dataset");
+        let options = DownloadOptions::new()
+            .dry_run(true)
+            .lock(true)
+            .manifest(true)
+            .max_file_size(10)
+            .filter(FileFilter::new(&["*.txt"], &[] as &[&str]).unwrap());
+        let report = ds
+            .download_with_validation(&Client::new(), &dst, NoProgress, &options)
+            .await
+            .unwrap();
+
+        assert_eq!(report.planned().count(), 1);
+        assert_eq!(report.planned_bytes(), 5);
+        assert_eq!(report.skipped().count(), 1);
+        assert_eq!(report.downloaded().count(), 0);
+        assert!(report.is_success());
+        assert!(report
+            .to_string()
+            .starts_with("would download 1 files (5 bytes), skipped 1\n"));
+        assert!(!dst.exists());
+    }
+
+    #[tokio::test]
+    async fn test_download_link_identical() {
+        let mock_server = MockServer::start().await;
+        // the identical files are requested once
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("same"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let json = serde_json::json!({
+            "entries": [
+                zenodo_entry(&mock_server.uri(), "a.txt", "same"),
+                zenodo_entry(&mock_server.uri(), "b.txt", "same"),
+            ]
+        });
+        let ds = Dataset::new(ZenodoJsonSrcDataset::new("1", json.to_string()));
+
+        let dst = TempDir::new("download-link-identical");
+        let options = DownloadOptions::new()
+            .limit(2)
+            .manifest(true)
+            .link_identical(LinkMode::Symlink);
+        let report = ds
+            .download_with_validation(&Client::new(), &dst, NoProgress, &options)
+            .await
+            .unwrap();
+
+        assert_eq!(report.downloaded().count(), 1);
+        assert_eq!(report.linked().count(), 1);
+        let linked = report.linked().next().unwrap();
+        let FileOutcome::Linked(original) = &linked.outcome else {
+            unreachable!()
+        };
+        assert_eq!(&report.downloaded().next().unwrap().path, original);
+        assert!(fs::symlink_metadata(dst.join(&linked.path))
+            .unwrap()
+            .is_symlink());
+        assert_eq!(fs::read_to_string(dst.join("a.txt")).unwrap(), "same");
+        assert_eq!(fs::read_to_string(dst.join("b.txt")).unwrap(), "same");
+        assert!(report.to_string().contains("linked 1, "));
+        let manifest: serde_json::Value =
+            serde_json::from_slice(&fs::read(dst.join(crate::MANIFEST_FILE_NAME)).unwrap())
+                .unwrap();
+        assert_eq!(manifest["files"][1]["size"], 4);
+    }
+
+    #[tokio::test]
+    async fn test_download_store() {
+        let mock_server = MockServer::start().await;
+        // the content shared by both versions is requested once
+        Mock::given(method("GET"))
+            .and(path("/files/a.txt"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("shared"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/files/b.txt"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("new"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let v1 = serde_json::json!({
+            "entries": [zenodo_entry(&mock_server.uri(), "a.txt", "shared")]
+        });
+        let v2 = serde_json::json!({
+            "entries": [
+                zenodo_entry(&mock_server.uri(), "a.txt", "shared"),
+                zenodo_entry(&mock_server.uri(), "b.txt", "new"),
+            ]
+        });
+
+        let dir = TempDir::new("download-store");
+        let store = ContentStore::new(dir.join("store"));
+        let options = DownloadOptions::new().store(store.clone());
+        let report = Dataset::new(ZenodoJsonSrcDataset::new("1", v1.to_string()))
+            .download_with_validation(&Client::new(), dir.join("v1"), NoProgress, &options)
+            .await
+            .unwrap();
+        assert_eq!(report.downloaded().count(), 1);
+        let report = Dataset::new(ZenodoJsonSrcDataset::new("2", v2.to_string()))
+            .download_with_validation(&Client::new(), dir.join("v2"), NoProgress, &options)
+            .await
+            .unwrap();
+
+        assert_eq!(report.downloaded().count(), 1);
+        assert_eq!(report.reused().count(), 1);
+        assert_eq!(report.reused().next().unwrap().path.as_str(), "a.txt");
+        assert!(report.to_string().contains("reused 1, "));
+        assert_eq!(fs::read_to_string(dir.join("v2/a.txt")).unwrap(), "shared");
+        let objects = fs::read_dir(store.root()).unwrap().flatten();
+        assert_eq!(objects.filter(|e| e.path().is_file()).count(), 2);
+        // keyed by the sha256 hashed while downloading, the repository lists md5
+        let sha256 = hex::encode(sha2::Sha256::digest(b"shared"));
+        assert!(store.object_path(&sha256).is_file());
+    }
+
+    #[tokio::test]
+    async fn test_download_writes_manifest() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/files/a.txt"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("hello"))
+            .mount(&mock_server)
+            .await;
+        let json = serde_json::json!({
+            "entries": [zenodo_entry(&mock_server.uri(), "a.txt", "hello")]
+        });
+        let ds = Dataset::new(ZenodoJsonSrcDataset::new("1", json.to_string()));
+        let root_url = ds.backend.root_url();
+
+        let dst = TempDir::new("download-manifest");
+        let options = DownloadOptions::new().manifest(true);
+        ds.download_with_validation(&Client::new(), &dst, NoProgress, &options)
+            .await
+            .unwrap();
+
+        let manifest: serde_json::Value =
+            serde_json::from_slice(&fs::read(dst.join(crate::MANIFEST_FILE_NAME)).unwrap())
+                .unwrap();
+        assert_eq!(manifest["source_url"], root_url.as_str());
+        let files = manifest["files"].as_array().unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0]["path"], "a.txt");
+        assert_eq!(files[0]["size"], 5);
+        assert_eq!(
+            files[0]["download_url"],
+            format!("{}/files/a.txt", mock_server.uri())
+        );
+        assert_eq!(
+            files[0]["checksum"],
+            format!("md5:{}", hex::encode(md5::Md5::digest(b"hello")))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_if_modified_since() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/files/a.txt"))
+            .and(wiremock::matchers::header_exists("if-modified-since"))
+            .respond_with(ResponseTemplate::new(304))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/files/a.txt"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("hello"))
+            .mount(&mock_server)
+            .await;
+        let json = serde_json::json!({
+            "entries": [zenodo_entry(&mock_server.uri(), "a.txt", "hello")]
+        });
+
+        let dst = TempDir::new("download-if-modified-since");
+        let options = DownloadOptions::new().if_modified_since(true);
+        let mut outcomes = Vec::new();
+        for _ in 0..2 {
+            let ds = Dataset::new(ZenodoJsonSrcDataset::new("1", json.to_string()));
+            let report = ds
+                .download_with_validation(&Client::new(), &dst, NoProgress, &options)
+                .await
+                .unwrap();
+            outcomes.push(report.files[0].outcome.clone());
+        }
+        assert!(matches!(
+            outcomes.as_slice(),
+            [FileOutcome::Verified, FileOutcome::Skipped(_)]
+        ));
+        assert_eq!(fs::read_to_string(dst.join("a.txt")).unwrap(), "hello");
+    }
+
+    #[tokio::test]
+    async fn test_download_continue_on_error() {
+        let mock_server = MockServer::start().await;
+        for (key, content) in [("good.txt", "good"), ("bad.txt", "corrupted")] {
+            Mock::given(method("GET"))
+                .and(path(format!("/files/{key}")))
+                .respond_with(ResponseTemplate::new(200).set_body_string(content))
+                .mount(&mock_server)
+                .await;
+        }
+        let json = serde_json::json!({
+            "entries": [
+                zenodo_entry(&mock_server.uri(), "good.txt", "good"),
+                zenodo_entry(&mock_server.uri(), "bad.txt", "original"),
+            ]
+        });
+
+        let dst = TempDir::new("download-continue-on-error");
+
+        let ds = Dataset::new(ZenodoJsonSrcDataset::new("1", json.to_string()));
+        let err = ds
+            .download_with_validation(&Client::new(), &dst, NoProgress, &DownloadOptions::new())
+            .await;
+        assert!(err.is_err());
+
+        let ds = Dataset::new(ZenodoJsonSrcDataset::new("1", json.to_string()));
+        let options = DownloadOptions::new().continue_on_error(true);
+        let report = ds
+            .download_with_validation(&Client::new(), &dst, NoProgress, &options)
+            .await
+            .unwrap();
+        assert!(!report.is_success());
+        let good = report
+            .files
+            .iter()
+            .find(|f| f.path.as_str() == "good.txt")
+            .unwrap();
+        assert_eq!(good.outcome, FileOutcome::Verified);
+        assert_eq!(good.bytes, 4);
+        let failed = report.failed().collect::<Vec<_>>();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].path.as_str(), "bad.txt");
+        assert!(failed[0].error().unwrap().contains("size wrong"));
+    }
+
+    #[tokio::test]
+    async fn test_download_zero_size_listed() {
+        let mock_server = MockServer::start().await;
+        for (key, content) in [("external.txt", "hosted elsewhere"), ("empty.txt", "")] {
+            Mock::given(method("GET"))
+                .and(path(format!("/files/{key}")))
+                .respond_with(ResponseTemplate::new(200).set_body_string(content))
+                .mount(&mock_server)
+                .await;
+        }
+        let mut external = zenodo_entry(&mock_server.uri(), "external.txt", "hosted elsewhere");
+        external["size"] = 0.into();
+        let json = serde_json::json!({
+            "entries": [external, zenodo_entry(&mock_server.uri(), "empty.txt", "")]
+        });
+        let ds = Dataset::new(ZenodoJsonSrcDataset::new("1", json.to_string()));
+
+        let dst = TempDir::new("download-zero-size");
+        let report = ds
+            .download_with_validation(&Client::new(), &dst, NoProgress, &DownloadOptions::new())
+            .await
+            .unwrap();
+        let outcome = |name: &str| {
+            let file = report.files.iter().find(|f| f.path.as_str() == name);
+            file.unwrap().outcome.clone()
+        };
+        assert_eq!(outcome("external.txt"), FileOutcome::UnverifiedLength);
+        assert_eq!(outcome("empty.txt"), FileOutcome::Verified);
+        assert_eq!(report.downloaded().count(), 2);
+        assert_eq!(
+            fs::read_to_string(dst.join("external.txt")).unwrap(),
+            "hosted elsewhere"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_self_checksum() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/file/slides.pdf"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("slides"))
+            .mount(&mock_server)
+            .await;
+        // HAL publishes no checksum
+        let json = serde_json::json!({
+            "response": { "docs": [{
+                "halId_s": "cel-1",
+                "files_s": [format!("{}/file/slides.pdf", mock_server.uri())],
+                "producedDate_tdate": "2024-01-01T00:00:00Z",
+            }]}
+        });
+        let ds = Dataset::new(HalJsonSrcDataset::new("cel-1", json.to_string()));
+
+        let dst = TempDir::new("download-self-checksum");
+        let options = DownloadOptions::new().manifest(true).self_checksum(true);
+        let client = crate::ClientOptions::new().builder().build().unwrap();
+        let report = ds
+            .download_with_validation(&client, &dst, NoProgress, &options)
+            .await
+            .unwrap();
+        let sha256 = hex::encode(sha2::Sha256::digest(b"slides"));
+        assert_eq!(report.files[0].outcome, FileOutcome::SelfComputed);
+        assert_eq!(report.files[0].checksum.as_ref().unwrap().value(), sha256);
+        let timing = report.files[0].timing.unwrap();
+        assert_eq!(timing.retries, 0);
+        assert!(timing.first_byte.unwrap() >= timing.response);
+        assert!(timing.connect.unwrap() <= timing.response);
+        let stats = report.stats();
+        assert!(stats["files"][0]["connect_ms"].is_f64());
+        assert_eq!(stats["downloaded"], 1);
+        assert_eq!(stats["files"][0]["bytes"], 6);
+        assert_eq!(stats["files"][0]["outcome"], "self_computed");
+
+        let manifest: serde_json::Value =
+            serde_json::from_slice(&fs::read(dst.join(crate::MANIFEST_FILE_NAME)).unwrap())
+                .unwrap();
+        let files = manifest["files"].as_array().unwrap();
+        assert_eq!(files[0]["checksum"], format!("sha256:{sha256}"));
+        assert_eq!(files[0]["checksum_source"], "self-computed");
+
+        assert!(crate::verify_manifest(&dst).unwrap().is_intact());
+        fs::write(dst.join("slides.pdf"), "SLIDES").unwrap();
+        assert!(!crate::verify_manifest(&dst).unwrap().is_intact());
+    }
+
+    #[tokio::test]
+    async fn test_download_validation_policy() {
+        let mock_server = MockServer::start().await;
+        // files without checksum are not requested
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("slides"))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+        // HAL publishes no checksum and no size
+        let json = serde_json::json!({
+            "response": { "docs": [{
+                "halId_s": "cel-1",
+                "files_s": [format!("{}/file/slides.pdf", mock_server.uri())],
+                "producedDate_tdate": "2024-01-01T00:00:00Z",
+            }]}
+        });
+        let ds = Dataset::new(HalJsonSrcDataset::new("cel-1", json.to_string()));
+        let dst = TempDir::new("download-validation-policy");
+
+        let options = DownloadOptions::new().validation_policy(ValidationPolicy::Skip);
+        let report = ds
+            .clone()
+            .download_with_validation(&Client::new(), &dst, NoProgress, &options)
+            .await
+            .unwrap();
+        assert_eq!(
+            report.files[0].outcome,
+            FileOutcome::Skipped("listed without checksum and size".to_string())
+        );
+
+        let options = DownloadOptions::new()
+            .continue_on_error(true)
+            .self_checksum(true)
+            .validation_policy(ValidationPolicy::Strict);
+        let report = ds
+            .download_with_validation(&Client::new(), &dst, NoProgress, &options)
+            .await
+            .unwrap();
+        assert!(report.files[0]
+            .error()
+            .unwrap()
+            .contains("required by the strict validation policy"));
+        assert!(!dst.join("slides.pdf").exists());
+    }
+
+    #[tokio::test]
+    async fn test_download_chunked() {
+        let content = "x".repeat(100);
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/files/large.bin"))
+            .and(wiremock::matchers::header("range", first_range(0).as_str()))
+            .respond_with(
+                ResponseTemplate::new(206)
+                    .insert_header("content-range", "bytes 0-99/100")
+                    .set_body_string(&content),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        // the range is ignored, the whole file is answered
+        Mock::given(method("GET"))
+            .and(path("/files/other.bin"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(&content))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let json = serde_json::json!({
+            "entries": [
+                zenodo_entry(&mock_server.uri(), "large.bin", &content),
+                zenodo_entry(&mock_server.uri(), "other.bin", &content),
+            ]
+        });
+        let ds = Dataset::new(ZenodoJsonSrcDataset::new("1", json.to_string()));
+
+        let dst = TempDir::new("download-chunked");
+        let options = DownloadOptions::new()
+            .connections_per_file(4)
+            .chunk_threshold(100);
+        let report = ds
+            .download_with_validation(&Client::new(), &dst, NoProgress, &options)
+            .await
+            .unwrap();
+        assert!(report
+            .files
+            .iter()
+            .all(|file| file.outcome == FileOutcome::Verified));
+        assert_eq!(fs::read_to_string(dst.join("large.bin")).unwrap(), content);
+        assert_eq!(fs::read_to_string(dst.join("other.bin")).unwrap(), content);
+    }
+
+    #[tokio::test]
+    async fn test_download_chunked_resumed() {
+        let total = CHUNK_SIZE + 10;
+        let content = (0..total)
+            .map(|i| char::from(b'a' + (i % 26) as u8))
+            .collect::<String>();
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/files/large.bin"))
+            .and(wiremock::matchers::header(
+                "range",
+                first_range(CHUNK_SIZE).as_str(),
+            ))
+            .respond_with(
+                ResponseTemplate::new(206)
+                    .insert_header(
+                        "content-range",
+                        format!("bytes {CHUNK_SIZE}-{}/{total}", total - 1),
+                    )
+                    .set_body_string(&content[CHUNK_SIZE as usize..]),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let json = serde_json::json!({
+            "entries": [zenodo_entry(&mock_server.uri(), "large.bin", &content)]
+        });
+        let ds = Dataset::new(ZenodoJsonSrcDataset::new("1", json.to_string()));
+
+        let dst = TempDir::new("download-chunked-resumed");
+        fs::create_dir_all(&dst).unwrap();
+        // the first chunk and a few bytes of the second, dropped and downloaded again
+        let part = format!("{}zzz", &content[..CHUNK_SIZE as usize]);
+        fs::write(dst.join("large.bin.part"), part).unwrap();
+        let options = DownloadOptions::new()
+            .connections_per_file(2)
+            .chunk_threshold(100);
+        let report = ds
+            .download_with_validation(&Client::new(), &dst, NoProgress, &options)
+            .await
+            .unwrap();
+
+        assert_eq!(report.files[0].outcome, FileOutcome::Verified);
+        assert_eq!(fs::read_to_string(dst.join("large.bin")).unwrap(), content);
+    }
+
+    #[tokio::test]
+    async fn test_download_part_file() {
+        let content = "0123456789abcdef";
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/files/resumed.bin"))
+            .and(wiremock::matchers::header("range", "bytes=6-"))
+            .respond_with(
+                ResponseTemplate::new(206)
+                    .insert_header("content-range", "bytes 6-15/16")
+                    .set_body_string(&content[6..]),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/files/corrupted.bin"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("fedcba9876543210"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let json = serde_json::json!({
+            "entries": [
+                zenodo_entry(&mock_server.uri(), "resumed.bin", content),
+                zenodo_entry(&mock_server.uri(), "corrupted.bin", content),
+            ]
+        });
+        let ds = Dataset::new(ZenodoJsonSrcDataset::new("1", json.to_string()));
+
+        let dst = TempDir::new("download-part-file");
+        fs::create_dir_all(&dst).unwrap();
+        fs::write(dst.join("resumed.bin.part"), &content[..6]).unwrap();
+        let options = DownloadOptions::new().continue_on_error(true);
+        let report = ds
+            .download_with_validation(&Client::new(), &dst, NoProgress, &options)
+            .await
+            .unwrap();
+        let outcome = |name: &str| {
+            report
+                .files
+                .iter()
+                .find(|file| file.path.as_str() == name)
+                .map(|file| file.outcome.clone())
+                .unwrap()
+        };
+        assert_eq!(outcome("resumed.bin"), FileOutcome::Verified);
+        assert_eq!(
+            fs::read_to_string(dst.join("resumed.bin")).unwrap(),
+            content
+        );
+        assert!(!dst.join("resumed.bin.part").exists());
+        // a file failing validation is neither at its final path nor resumed
+        assert!(matches!(outcome("corrupted.bin"), FileOutcome::Failed(_)));
+        assert!(!dst.join("corrupted.bin").exists());
+        assert!(!dst.join("corrupted.bin.part").exists());
+    }
+
+    #[tokio::test]
+    async fn test_download_cancel() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/files/done.txt"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("done"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/files/slow.bin"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string("slow")
+                    .set_delay(Duration::from_secs(30)),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/files/later.txt"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("later"))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+        let json = serde_json::json!({
+            "entries": [
+                zenodo_entry(&mock_server.uri(), "done.txt", "done"),
+                zenodo_entry(&mock_server.uri(), "slow.bin", "slow"),
+                zenodo_entry(&mock_server.uri(), "later.txt", "later"),
+            ]
+        });
+        let ds = Dataset::new(ZenodoJsonSrcDataset::new("1", json.to_string()));
+
+        let dst = TempDir::new("download-cancel");
+        fs::create_dir_all(&dst).unwrap();
+        // left by an earlier interrupted download
+        fs::write(dst.join("slow.bin.part"), "sl").unwrap();
+        let cancel = crate::CancellationToken::new();
+        let options = DownloadOptions::new().limit(1).cancel(cancel.clone());
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            cancel.cancel();
+        });
+        let report = ds
+            .download_with_validation(&Client::new(), &dst, NoProgress, &options)
+            .await
+            .unwrap();
+        assert!(report.cancelled);
+        assert!(!report.is_success());
+        let outcomes: Vec<_> = report
+            .files
+            .iter()
+            .map(|file| (file.path.as_str(), file.outcome.clone()))
+            .collect();
+        assert_eq!(
+            outcomes,
+            [
+                ("done.txt", FileOutcome::Verified),
+                ("slow.bin", FileOutcome::Cancelled)
+            ]
+        );
+        assert_eq!(fs::read_to_string(dst.join("done.txt")).unwrap(), "done");
+        assert!(!dst.join("slow.bin").exists());
+        assert!(!dst.join("slow.bin.part").exists());
+        assert!(!dst.join("later.txt").exists());
+        assert!(report
+            .to_string()
+            .starts_with("cancelled, downloaded 1 files"));
+    }
+
+    #[tokio::test]
+    async fn test_download_file_before_its_dirs() {
+        let content = "nested";
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/files/c.txt"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(content))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let api_url = url::Url::parse(&mock_server.uri()).unwrap();
+        let root = DirMeta::new_root(&api_url);
+        let dir = |p: &str| {
+            Ok(Entry::Dir(DirMeta::new(
+                root.path().join(p),
+                api_url.clone(),
+                api_url.clone(),
+            )))
+        };
+        let file = TestFile::at(root.path().join("a/b/c.txt"))
+            .url(api_url.join("files/c.txt").unwrap())
+            .size(content.len() as u64)
+            .checksum(Checksum::Md5(hex::encode(md5::Md5::digest(
+                content.as_bytes(),
+            ))))
+            .build();
+        // a source yielding a file before the folders it is in
+        let entries = futures_util::stream::iter(vec![Ok(Entry::File(file)), dir("a"), dir("a/b")]);
+
+        let dst = TempDir::new("download-file-before-its-dirs");
+        let report = download_entries(
+            entries,
+            &Client::new(),
+            &dst,
+            NoProgress,
+            &DownloadOptions::new(),
+            TotalProgress::streamed(&NoProgress),
         )
+        .await
+        .unwrap();
+        assert!(report.is_success(), "{report}");
+        assert_eq!(fs::read_to_string(dst.join("a/b/c.txt")).unwrap(), content);
     }
 }
@@ -0,0 +1,750 @@
+use std::{borrow::Cow, fmt, sync::Arc};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
+
+use crate::{
+    crawler::DEFAULT_CRAWL_FANOUT, ClientOptions, CollisionPolicy, ContentStore, DownloadEvent,
+    Entry, FileFilter, FileMeta, LinkMode, ObjectWriter, RepositoryConfig, TargetOs,
+    ValidationPolicy, DEFAULT_CHUNK_THRESHOLD,
+};
+
+/// Options controlling how a dataset is downloaded, see
+/// [`DownloadExt::download_with_validation`](crate::DownloadExt::download_with_validation).
+///
+/// # Examples
+///
+/// ```
+/// use datahugger::{DownloadOptions, FileFilter};
+///
+/// let (tx, _rx) = tokio::sync::mpsc::channel(64);
+/// let _options = DownloadOptions::new()
+///     .limit(20)
+///     .filter(FileFilter::new(&["*.csv"], &["raw/**"]).unwrap())
+///     .event_sink(tx);
+/// ```
+#[derive(Debug, Clone)]
+pub struct DownloadOptions {
+    pub(crate) limit: usize,
+    pub(crate) dataset_limit: usize,
+    pub(crate) client_options: ClientOptions,
+    pub(crate) repositories: Option<RepositoryConfig>,
+    pub(crate) dir_template: String,
+    pub(crate) crawl_fanout: usize,
+    pub(crate) filter: FileFilter,
+    pub(crate) include_hidden: bool,
+    pub(crate) max_file_size: Option<u64>,
+    pub(crate) continue_on_error: bool,
+    pub(crate) lock: bool,
+    pub(crate) force_lock: bool,
+    pub(crate) xattrs: bool,
+    pub(crate) archive_mode: bool,
+    pub(crate) manifest: bool,
+    pub(crate) self_checksum: bool,
+    pub(crate) validation_policy: ValidationPolicy,
+    pub(crate) if_modified_since: bool,
+    pub(crate) collision_policy: CollisionPolicy,
+    pub(crate) target_os: TargetOs,
+    pub(crate) writer: Option<Arc<dyn ObjectWriter>>,
+    pub(crate) probe_sizes: bool,
+    pub(crate) dedup: bool,
+    pub(crate) link_identical: Option<LinkMode>,
+    pub(crate) store: Option<ContentStore>,
+    pub(crate) dry_run: bool,
+    pub(crate) expand_zip: bool,
+    pub(crate) check_access: bool,
+    pub(crate) check_disk_space: bool,
+    pub(crate) connections_per_file: usize,
+    pub(crate) chunk_threshold: u64,
+    pub(crate) cancel: CancellationToken,
+    event_sink: Option<mpsc::Sender<DownloadEvent>>,
+    on_event: Option<EventCallback>,
+    path_mapper: Option<PathMapper>,
+}
+
+/// Callback set with [`DownloadOptions::on_event`].
+#[derive(Clone)]
+struct EventCallback(Arc<dyn Fn(&DownloadEvent) + Send + Sync>);
+
+impl fmt::Debug for EventCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EventCallback")
+    }
+}
+
+/// Callback set with [`DownloadOptions::path_mapper`].
+#[derive(Clone)]
+struct PathMapper(Arc<dyn Fn(&FileMeta) -> String + Send + Sync>);
+
+impl fmt::Debug for PathMapper {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PathMapper")
+    }
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        DownloadOptions {
+            limit: 0,
+            dataset_limit: 1,
+            client_options: ClientOptions::default(),
+            repositories: None,
+            dir_template: "{repo}-{record_id}".to_string(),
+            crawl_fanout: DEFAULT_CRAWL_FANOUT,
+            filter: FileFilter::default(),
+            include_hidden: false,
+            max_file_size: None,
+            continue_on_error: false,
+            lock: false,
+            force_lock: false,
+            xattrs: false,
+            archive_mode: false,
+            manifest: false,
+            self_checksum: false,
+            validation_policy: ValidationPolicy::default(),
+            if_modified_since: false,
+            collision_policy: CollisionPolicy::default(),
+            target_os: TargetOs::default(),
+            writer: None,
+            probe_sizes: false,
+            dedup: false,
+            link_identical: None,
+            store: None,
+            dry_run: false,
+            expand_zip: false,
+            check_access: false,
+            check_disk_space: false,
+            connections_per_file: 1,
+            chunk_threshold: DEFAULT_CHUNK_THRESHOLD,
+            cancel: CancellationToken::new(),
+            event_sink: None,
+            on_event: None,
+            path_mapper: None,
+        }
+    }
+}
+
+impl DownloadOptions {
+    /// Creates options that download every file without concurrency limit.
+    #[must_use]
+    pub fn new() -> Self {
+        DownloadOptions::default()
+    }
+
+    /// Sets the maximum number of concurrent downloads, `0` disables the limit.
+    #[must_use]
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Sets the maximum number of datasets downloaded at the same time by
+    /// [`download_many`](crate::download_many), `0` disables the limit. Defaults to 1, the
+    /// files of each dataset are still downloaded concurrently up to [`Self::limit`].
+    #[must_use]
+    pub fn dataset_limit(mut self, dataset_limit: usize) -> Self {
+        self.dataset_limit = dataset_limit;
+        self
+    }
+
+    /// Sets the client options the datasets of [`download_many`](crate::download_many) are
+    /// resolved with, e.g. to go through a proxy. The files are downloaded with the clients
+    /// of its `client_for`.
+    #[must_use]
+    pub fn client_options(mut self, client_options: ClientOptions) -> Self {
+        self.client_options = client_options;
+        self
+    }
+
+    /// Sets the repository installations the datasets of
+    /// [`download_many`](crate::download_many) are resolved with. Defaults to
+    /// [`RepositoryConfig::load`].
+    #[must_use]
+    pub fn repositories(mut self, repositories: RepositoryConfig) -> Self {
+        self.repositories = Some(repositories);
+        self
+    }
+
+    /// Sets the subdirectory of the destination each dataset of
+    /// [`download_many`](crate::download_many) is downloaded into, with the placeholders
+    /// of [`Dataset::destination`](crate::Dataset::destination), e.g. `{repo}/{record_id}`.
+    /// An absolute template replaces the destination. Defaults to `{repo}-{record_id}`.
+    #[must_use]
+    pub fn dir_template(mut self, dir_template: impl Into<String>) -> Self {
+        self.dir_template = dir_template.into();
+        self
+    }
+
+    /// Sets the maximum number of directories listed concurrently while crawling,
+    /// `0` disables the limit. Defaults to [`DEFAULT_CRAWL_FANOUT`].
+    #[must_use]
+    pub fn crawl_fanout(mut self, crawl_fanout: usize) -> Self {
+        self.crawl_fanout = crawl_fanout;
+        self
+    }
+
+    /// Sets the filter selecting which files are downloaded.
+    #[must_use]
+    pub fn filter(mut self, filter: FileFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Also downloads dotfiles and operating system artifacts such as `.DS_Store`,
+    /// `Thumbs.db` or `__MACOSX/`, which are skipped by default, see
+    /// [`FileMeta::is_hidden`].
+    #[must_use]
+    pub fn include_hidden(mut self, include_hidden: bool) -> Self {
+        self.include_hidden = include_hidden;
+        self
+    }
+
+    /// Returns `true` if a crawled file passes the filter and is not a skipped hidden file.
+    pub(crate) fn is_selected_file(&self, file_meta: &FileMeta) -> bool {
+        (self.include_hidden || !file_meta.is_hidden()) && self.filter.is_match_file(file_meta)
+    }
+
+    /// Returns `true` if a crawled entry passes the filter and is not a skipped hidden file
+    /// or folder.
+    pub(crate) fn is_selected(&self, entry: &Entry) -> bool {
+        is_selected(&self.filter, self.include_hidden, entry)
+    }
+
+    /// Skips files whose size reported by the repository exceeds `max_file_size` bytes.
+    ///
+    /// Skipped files are reported as [`DownloadEvent::Skipped`]. Files of unknown size
+    /// are always downloaded.
+    #[must_use]
+    pub fn max_file_size(mut self, max_file_size: u64) -> Self {
+        self.max_file_size = Some(max_file_size);
+        self
+    }
+
+    /// Keeps downloading the remaining files when a file fails to download or validate.
+    ///
+    /// Failures are then recorded in the returned [`DownloadReport`](crate::DownloadReport)
+    /// instead of aborting the whole dataset.
+    #[must_use]
+    pub fn continue_on_error(mut self, continue_on_error: bool) -> Self {
+        self.continue_on_error = continue_on_error;
+        self
+    }
+
+    /// Holds an advisory lock on the destination directory during the download, so that
+    /// concurrent datahugger runs do not interleave writes to the same directory.
+    ///
+    /// See [`DirLock`](crate::DirLock) for how stale locks are detected.
+    #[must_use]
+    pub fn lock(mut self, lock: bool) -> Self {
+        self.lock = lock;
+        self
+    }
+
+    /// Takes over an existing lock of the destination directory even if its owner looks
+    /// alive. Only has effect together with [`DownloadOptions::lock`].
+    #[must_use]
+    pub fn force_lock(mut self, force_lock: bool) -> Self {
+        self.force_lock = force_lock;
+        self
+    }
+
+    /// Records the source URL, checksum and retrieval time of each downloaded file as
+    /// extended attributes, see [`XATTR_PREFIX`](crate::XATTR_PREFIX).
+    #[must_use]
+    pub fn xattrs(mut self, xattrs: bool) -> Self {
+        self.xattrs = xattrs;
+        self
+    }
+
+    /// Downloads the whole dataset as a single server generated archive and extracts it,
+    /// instead of requesting every file.
+    ///
+    /// Much faster for datasets with many small files. Extracted files are still validated
+    /// against the listed checksums, and files missing from the archive are downloaded one
+    /// by one. Ignored for repositories without dataset archives, see
+    /// [`DatasetBackend::archive_url`](crate::DatasetBackend::archive_url).
+    #[must_use]
+    pub fn archive_mode(mut self, archive_mode: bool) -> Self {
+        self.archive_mode = archive_mode;
+        self
+    }
+
+    /// Writes a [`MANIFEST_FILE_NAME`](crate::MANIFEST_FILE_NAME) manifest into the
+    /// destination after the download, with the source URL, resolve time and the download
+    /// URL, size and checksum of every downloaded file.
+    #[must_use]
+    pub fn manifest(mut self, manifest: bool) -> Self {
+        self.manifest = manifest;
+        self
+    }
+
+    /// Computes the sha256 of files the repository publishes no checksum for, as GitHub, HAL
+    /// or arXiv, while downloading them.
+    ///
+    /// Such files are reported as [`FileOutcome::SelfComputed`](crate::FileOutcome) with the
+    /// digest in [`FileReport::checksum`](crate::FileReport::checksum), and recorded as
+    /// `self-computed` in the manifest, so later verification at least detects changes.
+    #[must_use]
+    pub fn self_checksum(mut self, self_checksum: bool) -> Self {
+        self.self_checksum = self_checksum;
+        self
+    }
+
+    /// Sets what to do with files listed without checksum or size, see
+    /// [`ValidationPolicy`]. Defaults to [`ValidationPolicy::WarnAndContinue`].
+    ///
+    /// A self-computed checksum, see [`self_checksum`](Self::self_checksum), is no checksum
+    /// to [`ValidationPolicy::Strict`].
+    #[must_use]
+    pub fn validation_policy(mut self, validation_policy: ValidationPolicy) -> Self {
+        self.validation_policy = validation_policy;
+        self
+    }
+
+    /// Requests files already in the destination with `If-Modified-Since` set to their
+    /// modification time, and keeps the local copy when the server answers
+    /// `304 Not Modified`.
+    ///
+    /// Such files are reported as skipped, this is a fast path for mirror jobs re-downloading
+    /// the same dataset. Local files whose size differs from the listed size are always
+    /// downloaded again.
+    #[must_use]
+    pub fn if_modified_since(mut self, if_modified_since: bool) -> Self {
+        self.if_modified_since = if_modified_since;
+        self
+    }
+
+    /// Sets what to do with files whose paths differ only by case, see [`CollisionPolicy`].
+    ///
+    /// Unless it is [`CollisionPolicy::Overwrite`] (the default), the whole dataset is crawled
+    /// before the first file is downloaded, since collisions are only known once all paths are.
+    #[must_use]
+    pub fn collision_policy(mut self, collision_policy: CollisionPolicy) -> Self {
+        self.collision_policy = collision_policy;
+        self
+    }
+
+    /// Asks the repository what must be obtained to download the dataset before crawling it,
+    /// and fails with [`DatahuggerError::AccessRequired`](crate::DatahuggerError) listing the
+    /// [`AccessRequirements`](crate::AccessRequirements) if any, instead of failing file by
+    /// file with `403 Forbidden` mid-run.
+    #[must_use]
+    pub fn check_access(mut self, check_access: bool) -> Self {
+        self.check_access = check_access;
+        self
+    }
+
+    /// Sums the sizes of the files to download before the first one is, and fails with
+    /// [`DatahuggerError::InsufficientSpace`](crate::DatahuggerError) if the destination
+    /// filesystem has not that much space available, instead of running out of space midway.
+    ///
+    /// Files of unknown size are not counted, see [`probe_sizes`](Self::probe_sizes), nor
+    /// the bytes of local files that are overwritten. As for collisions, the whole dataset
+    /// is crawled before the first file is downloaded.
+    #[must_use]
+    pub fn check_disk_space(mut self, check_disk_space: bool) -> Self {
+        self.check_disk_space = check_disk_space;
+        self
+    }
+
+    /// Sets the operating system the names of downloaded files and folders must be valid on,
+    /// see [`TargetOs`]. Defaults to the host.
+    ///
+    /// Useful when downloading onto a share read from another operating system, e.g.
+    /// [`TargetOs::Windows`] on a Linux host replaces the `:` and `?` Windows rejects. The
+    /// report and manifest list the sanitized paths.
+    #[must_use]
+    pub fn target_os(mut self, target_os: TargetOs) -> Self {
+        self.target_os = target_os;
+        self
+    }
+
+    /// Writes the downloaded files with `writer` instead of into the destination directory,
+    /// e.g. with an [`ObjectStoreWriter`](crate::ObjectStoreWriter) straight into a bucket.
+    ///
+    /// The destination directory still holds the lock and the manifest if asked for.
+    /// Extended attributes, `If-Modified-Since` and archive mode need local files and are
+    /// not applied unless the writer [is local](ObjectWriter::is_local).
+    #[must_use]
+    pub fn writer(mut self, writer: impl ObjectWriter + 'static) -> Self {
+        self.writer = Some(Arc::new(writer));
+        self
+    }
+
+    /// Writes each file to the relative path `mapper` returns for it instead of its path in
+    /// the dataset, e.g. to flatten the tree or to store files under their checksum.
+    ///
+    /// Segments of the returned path are separated by `/`, empty, `.` and `..` segments are
+    /// dropped so files stay below the destination. The folders of the dataset are not
+    /// created and files mapped to the same path overwrite each other. Mapped paths are
+    /// still sanitized for the [`target_os`](Self::target_os).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datahugger::DownloadOptions;
+    ///
+    /// // every file directly in the destination, named after its identifier
+    /// let _options = DownloadOptions::new().path_mapper(|f| {
+    ///     f.file_identifier()
+    ///         .map_or_else(|| f.relative().as_str().replace('/', "_"), str::to_string)
+    /// });
+    /// ```
+    #[must_use]
+    pub fn path_mapper<F>(mut self, mapper: F) -> Self
+    where
+        F: Fn(&FileMeta) -> String + Send + Sync + 'static,
+    {
+        self.path_mapper = Some(PathMapper(Arc::new(mapper)));
+        self
+    }
+
+    /// Returns `true` if the folders of the dataset are created in the destination, not
+    /// when files are written by a custom writer or to mapped paths.
+    pub(crate) fn creates_dirs(&self) -> bool {
+        self.writer.is_none() && self.path_mapper.is_none()
+    }
+
+    /// Returns `true` unless files are written by a writer that is not local.
+    pub(crate) fn writes_local(&self) -> bool {
+        self.writer.as_ref().is_none_or(|writer| writer.is_local())
+    }
+
+    /// Moves `entry` to the local path of its crawl path, sanitized for the target
+    /// operating system.
+    pub(crate) fn local_entry(&self, entry: Entry) -> Entry {
+        match entry {
+            Entry::Dir(dir) => match self.target_os.sanitize(dir.relative().as_str()) {
+                Cow::Owned(local) => Entry::Dir(dir.with_relative(&local)),
+                Cow::Borrowed(_) => Entry::Dir(dir),
+            },
+            Entry::File(file_meta) => Entry::File(self.local_file(file_meta)),
+        }
+    }
+
+    /// Moves `file_meta` to the local path of its crawl path, or the path of the
+    /// [`path_mapper`](Self::path_mapper), sanitized for the target operating system.
+    pub(crate) fn local_file(&self, file_meta: FileMeta) -> FileMeta {
+        let file_meta = match &self.path_mapper {
+            Some(PathMapper(mapper)) => {
+                let mapped = mapper(&file_meta)
+                    .split(['/', '\\'])
+                    .filter(|segment| !matches!(*segment, "" | "." | ".."))
+                    .collect::<Vec<_>>()
+                    .join("/");
+                if mapped.is_empty() {
+                    warn!(path = %file_meta.relative(), "empty mapped path, path kept");
+                    file_meta
+                } else {
+                    debug!(path = %file_meta.relative(), "mapped to '{mapped}'");
+                    file_meta.with_relative(&mapped)
+                }
+            }
+            None => file_meta,
+        };
+        match self.target_os.sanitize(file_meta.relative().as_str()) {
+            Cow::Owned(local) => {
+                debug!(path = %file_meta.relative(), "sanitized to '{local}'");
+                file_meta.with_relative(&local)
+            }
+            Cow::Borrowed(_) => file_meta,
+        }
+    }
+
+    /// Issues a HEAD request for every file the repository lists without size (HAL, arXiv,
+    /// some DataONE members) and takes its size from the `Content-Length` header.
+    ///
+    /// Gives these files accurate progress bars and subjects them to
+    /// [`DownloadOptions::max_file_size`]. As for collisions, the whole dataset is crawled
+    /// before the first file is downloaded.
+    #[must_use]
+    pub fn probe_sizes(mut self, probe_sizes: bool) -> Self {
+        self.probe_sizes = probe_sizes;
+        self
+    }
+
+    /// Downloads a file listed more than once under the same path only once, the duplicates
+    /// are reported as [`DownloadEvent::Skipped`] and [`FileOutcome::Skipped`](crate::FileOutcome).
+    ///
+    /// Guards against buggy or paginated listings returning the same file twice, whose
+    /// downloads would otherwise race to write the same destination file.
+    #[must_use]
+    pub fn dedup(mut self, dedup: bool) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
+    /// Downloads files with the same checksum and size only once, the others are stored as
+    /// hard links, symbolic links or copies of the downloaded one by `mode` and reported as
+    /// [`FileOutcome::Linked`](crate::FileOutcome), saving bandwidth and, but for
+    /// [`LinkMode::Copy`], disk space.
+    ///
+    /// Only files whose checksum the repository lists are compared, and only a file whose
+    /// checksum was verified is linked to. Not applied with a custom
+    /// [`writer`](Self::writer).
+    #[must_use]
+    pub fn link_identical(mut self, mode: LinkMode) -> Self {
+        self.link_identical = Some(mode);
+        self
+    }
+
+    /// Keeps the content of the downloaded files in `store`, and links files whose content
+    /// it already holds, e.g. downloaded with another version of the dataset, instead of
+    /// downloading them again. Such files are reported as
+    /// [`FileOutcome::Reused`](crate::FileOutcome).
+    ///
+    /// Contents are looked up by the checksum the repository lists. Not applied with a
+    /// custom [`writer`](Self::writer).
+    #[must_use]
+    pub fn store(mut self, store: ContentStore) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Crawls the dataset and applies the filter without downloading or writing anything,
+    /// the files that would be downloaded are reported as
+    /// [`FileOutcome::Planned`](crate::FileOutcome) with their size, e.g. to check the
+    /// total size before a large transfer.
+    ///
+    /// Files are skipped as they would be by their size or as not downloadable, the other
+    /// checks of a download, e.g. [`DownloadOptions::if_modified_since`], need requests and
+    /// are not applied.
+    #[must_use]
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Lists the members of zip archives as files instead of the archives, and downloads
+    /// only the members that pass the filter, see [`expand_zip`](crate::expand_zip).
+    ///
+    /// Useful for records consisting of one large zip, as common on Zenodo. Archives on
+    /// servers without range requests are downloaded whole.
+    #[must_use]
+    pub fn expand_zip(mut self, expand_zip: bool) -> Self {
+        self.expand_zip = expand_zip;
+        self
+    }
+
+    /// Downloads files of at least [`chunk_threshold`](Self::chunk_threshold) bytes over up
+    /// to `connections` concurrent range requests, when the server answers range requests.
+    ///
+    /// Speeds up single multi-GB files from servers throttling each connection, e.g. OSF or
+    /// Zenodo. Chunks are written and hashed in order, files are validated as when
+    /// downloaded in one stream. A failed chunk is requested again a few times, an
+    /// interrupted download resumes from the last chunk written whole. Defaults to 1, a
+    /// single request per file.
+    #[must_use]
+    pub fn connections_per_file(mut self, connections: usize) -> Self {
+        self.connections_per_file = connections;
+        self
+    }
+
+    /// Sets the size in bytes from which files are downloaded in chunks, see
+    /// [`connections_per_file`](Self::connections_per_file). Defaults to
+    /// [`DEFAULT_CHUNK_THRESHOLD`], only sizes listed by the repository are considered.
+    #[must_use]
+    pub fn chunk_threshold(mut self, chunk_threshold: u64) -> Self {
+        self.chunk_threshold = chunk_threshold;
+        self
+    }
+
+    /// Stops the download once `token` is cancelled, e.g. on Ctrl-C: no new file is
+    /// requested, the transfers in flight are aborted and their part files removed, and
+    /// the files done so far are returned in a report marked
+    /// [`cancelled`](crate::DownloadReport::cancelled).
+    ///
+    /// A file is thus either complete at its path or absent, never truncated. The dataset
+    /// archive of [`archive_mode`](Self::archive_mode) is only cancelled while it is
+    /// fetched.
+    #[must_use]
+    pub fn cancel(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// Returns `true` if `file_meta` is downloaded in chunks.
+    pub(crate) fn is_chunked(&self, file_meta: &FileMeta) -> bool {
+        self.connections_per_file > 1
+            && file_meta
+                .size()
+                .is_some_and(|size| size >= self.chunk_threshold)
+    }
+
+    /// Forwards [`DownloadEvent`]s of every file into `sink`.
+    ///
+    /// Useful when embedding the crate in a service to relay progress into a job system.
+    /// Fine-grained `Progress` events are dropped when the channel is full.
+    #[must_use]
+    pub fn event_sink(mut self, sink: mpsc::Sender<DownloadEvent>) -> Self {
+        self.event_sink = Some(sink);
+        self
+    }
+
+    /// Calls `callback` with the [`DownloadEvent`]s of every file, for applications drawing
+    /// their own progress, e.g. a GUI or a notebook, instead of the indicatif bars of a
+    /// [`ProgressManager`](crate::crawler::ProgressManager).
+    ///
+    /// Unlike [`event_sink`](Self::event_sink) every event is delivered, `Progress` events
+    /// included. The callback runs on the task downloading the file and should return
+    /// quickly.
+    #[must_use]
+    pub fn on_event<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&DownloadEvent) + Send + Sync + 'static,
+    {
+        self.on_event = Some(EventCallback(Arc::new(callback)));
+        self
+    }
+
+    fn notify(&self, event: &DownloadEvent) {
+        if let Some(EventCallback(callback)) = &self.on_event {
+            callback(event);
+        }
+    }
+
+    /// Sends a lifecycle event, waiting for the consumer if the channel is full.
+    pub(crate) async fn emit(&self, event: DownloadEvent) {
+        self.notify(&event);
+        if let Some(sink) = &self.event_sink {
+            if sink.send(event).await.is_err() {
+                debug!("event sink closed, event dropped");
+            }
+        }
+    }
+
+    /// Sends a best-effort event, dropped if the channel is full or closed.
+    pub(crate) fn try_emit(&self, event: DownloadEvent) {
+        self.notify(&event);
+        if let Some(sink) = &self.event_sink {
+            let _ = sink.try_send(event);
+        }
+    }
+
+    /// Sends a lifecycle event from blocking code, waiting for the consumer if the channel
+    /// is full.
+    pub(crate) fn emit_blocking(&self, event: DownloadEvent) {
+        self.notify(&event);
+        if let Some(sink) = &self.event_sink {
+            if sink.blocking_send(event).is_err() {
+                debug!("event sink closed, event dropped");
+            }
+        }
+    }
+}
+
+fn is_selected(filter: &FileFilter, include_hidden: bool, entry: &Entry) -> bool {
+    match entry {
+        Entry::Dir(dir) => (include_hidden || !dir.is_hidden()) && filter.is_match_entry(entry),
+        Entry::File(file_meta) => {
+            (include_hidden || !file_meta.is_hidden()) && filter.is_match_file(file_meta)
+        }
+    }
+}
+
+/// Options controlling how a dataset is crawled, see [`CrawlExt::crawl`](crate::CrawlExt::crawl)
+/// and [`Dataset::print_meta`](crate::Dataset::print_meta).
+///
+/// Unlike a download, a crawl lists every entry by default, hidden files included.
+///
+/// # Examples
+///
+/// ```
+/// use datahugger::{CrawlOptions, FileFilter};
+///
+/// let _options = CrawlOptions::new()
+///     .limit(20)
+///     .filter(FileFilter::new(&["*.csv"], &["raw/**"]).unwrap())
+///     .include_hidden(false);
+/// ```
+#[derive(Debug, Clone)]
+pub struct CrawlOptions {
+    pub(crate) limit: usize,
+    pub(crate) crawl_fanout: usize,
+    pub(crate) filter: FileFilter,
+    pub(crate) include_hidden: bool,
+    pub(crate) expand_zip: bool,
+    pub(crate) dedup: bool,
+    pub(crate) cancel: CancellationToken,
+}
+
+impl Default for CrawlOptions {
+    fn default() -> Self {
+        CrawlOptions {
+            limit: 0,
+            crawl_fanout: DEFAULT_CRAWL_FANOUT,
+            filter: FileFilter::default(),
+            include_hidden: true,
+            expand_zip: false,
+            dedup: false,
+            cancel: CancellationToken::new(),
+        }
+    }
+}
+
+impl CrawlOptions {
+    /// Creates options that list every entry without concurrency limit.
+    #[must_use]
+    pub fn new() -> Self {
+        CrawlOptions::default()
+    }
+
+    /// Sets the maximum number of entries processed concurrently, e.g. printed by
+    /// [`Dataset::print_meta`](crate::Dataset::print_meta), `0` disables the limit.
+    #[must_use]
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Sets the maximum number of directories listed concurrently, `0` disables the limit.
+    /// Defaults to [`DEFAULT_CRAWL_FANOUT`].
+    #[must_use]
+    pub fn crawl_fanout(mut self, crawl_fanout: usize) -> Self {
+        self.crawl_fanout = crawl_fanout;
+        self
+    }
+
+    /// Sets the filter selecting which entries are listed.
+    #[must_use]
+    pub fn filter(mut self, filter: FileFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Lists dotfiles and operating system artifacts, see [`FileMeta::is_hidden`]. Defaults
+    /// to `true`.
+    #[must_use]
+    pub fn include_hidden(mut self, include_hidden: bool) -> Self {
+        self.include_hidden = include_hidden;
+        self
+    }
+
+    /// Lists the members of zip archives as files, see [`expand_zip`](crate::expand_zip).
+    #[must_use]
+    pub fn expand_zip(mut self, expand_zip: bool) -> Self {
+        self.expand_zip = expand_zip;
+        self
+    }
+
+    /// Lists a file listed more than once under the same path only once, see
+    /// [`crawler::dedup`](crate::crawler::dedup).
+    #[must_use]
+    pub fn dedup(mut self, dedup: bool) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
+    /// Ends the crawl once `token` is cancelled, the listings in flight are dropped.
+    #[must_use]
+    pub fn cancel(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// Returns `true` if a crawled entry passes the filter and is not a skipped hidden file
+    /// or folder.
+    pub(crate) fn is_selected(&self, entry: &Entry) -> bool {
+        is_selected(&self.filter, self.include_hidden, entry)
+    }
+}
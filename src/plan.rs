@@ -0,0 +1,546 @@
+use async_trait::async_trait;
+use exn::{Exn, ResultExt};
+use futures_util::{stream, StreamExt, TryStreamExt};
+use reqwest::{header::CONTENT_LENGTH, Client};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    path::Path,
+};
+use tracing::{debug, warn};
+
+use crate::retry::RetryExt;
+use crate::{
+    crawler::{CrawlerError, ProgressManager},
+    error::ErrorStatus,
+    ops::download_local_entries,
+    progress::TotalProgress,
+    redirect::{default_client_options, follow_cross_host_head},
+    ClientOptions, CrawlExt, CrawlOptions, DatahuggerError, Dataset, DownloadExt, DownloadOptions,
+    DownloadReport, Entry, FileMeta,
+};
+
+#[derive(Debug)]
+pub struct PlanError {
+    pub message: String,
+    pub status: ErrorStatus,
+}
+
+impl std::fmt::Display for PlanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "plan fail: {}", self.message)
+    }
+}
+
+impl std::error::Error for PlanError {}
+
+/// What to do when crawl paths of several files differ only by case, e.g. `README.md` and
+/// `Readme.md` in a GitHub repository, and would be the same file on case-insensitive
+/// filesystems (macOS, Windows).
+///
+/// Collisions are resolved the same way on every platform, so a dataset always downloads
+/// to the same tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CollisionPolicy {
+    /// Fail before downloading, listing the colliding paths.
+    Error,
+    /// Keep the first path in byte order and rename the others by appending `~1`, `~2`, ...
+    /// to their file stem, e.g. `Readme~1.md`.
+    RenameDeterministic,
+    /// Download every file to its own path, on case-insensitive filesystems the files
+    /// overwrite each other.
+    #[default]
+    Overwrite,
+}
+
+/// Applies `policy` to the files whose relative paths collide case-insensitively.
+pub(crate) fn resolve_collisions(
+    files: Vec<FileMeta>,
+    policy: CollisionPolicy,
+) -> Result<Vec<FileMeta>, Exn<PlanError>> {
+    if policy == CollisionPolicy::Overwrite {
+        return Ok(files);
+    }
+    // paths with the index of their file, mapped paths may even be the same
+    let mut groups: BTreeMap<String, Vec<(String, usize)>> = BTreeMap::new();
+    for (idx, file) in files.iter().enumerate() {
+        let path = file.relative().as_str().to_string();
+        groups
+            .entry(path.to_lowercase())
+            .or_default()
+            .push((path, idx));
+    }
+    let mut collisions = groups
+        .values_mut()
+        .filter(|paths| paths.len() > 1)
+        .map(|paths| {
+            paths.sort();
+            paths.clone()
+        })
+        .collect::<Vec<_>>();
+    if collisions.is_empty() {
+        return Ok(files);
+    }
+    if policy == CollisionPolicy::Error {
+        let collisions = collisions
+            .iter()
+            .map(|paths| {
+                let paths = paths.iter().map(|(path, _)| path.as_str());
+                format!("'{}'", paths.collect::<Vec<_>>().join("', '"))
+            })
+            .collect::<Vec<_>>();
+        exn::bail!(PlanError {
+            message: format!("paths differ only by case: {}", collisions.join("; ")),
+            status: ErrorStatus::Permanent,
+        });
+    }
+
+    let mut taken = groups.into_keys().collect::<HashSet<_>>();
+    let mut renames = HashMap::new();
+    for paths in &mut collisions {
+        for (path, idx) in paths.drain(..).skip(1) {
+            let (parent, name) = path.rsplit_once('/').unwrap_or(("", &path));
+            let (stem, ext) = match name.rsplit_once('.') {
+                Some((stem, ext)) if !stem.is_empty() => (stem, format!(".{ext}")),
+                _ => (name, String::new()),
+            };
+            let renamed = (1..)
+                .map(|n| {
+                    if parent.is_empty() {
+                        format!("{stem}~{n}{ext}")
+                    } else {
+                        format!("{parent}/{stem}~{n}{ext}")
+                    }
+                })
+                .find(|renamed| !taken.contains(&renamed.to_lowercase()))
+                .expect("a free name exists");
+            taken.insert(renamed.to_lowercase());
+            renames.insert(idx, renamed);
+        }
+    }
+    let files = files
+        .into_iter()
+        .enumerate()
+        .map(|(idx, file)| match renames.get(&idx) {
+            Some(renamed) => file.with_relative(renamed),
+            None => file,
+        })
+        .collect();
+    Ok(files)
+}
+
+/// A materialized list of files of a dataset to be downloaded.
+///
+/// Unlike downloading a [`Dataset`] directly, which streams files while crawling, a
+/// plan holds all the crawled files upfront. This allows pinning an exact subset of
+/// files with [`DownloadPlan::select`], e.g. from a manifest of a prior crawl, and
+/// failing early if any of them is no longer in the dataset.
+///
+/// # Examples
+///
+/// ```no_run
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// use datahugger::{DownloadExt, DownloadOptions, DownloadPlan};
+/// use indicatif::MultiProgress;
+///
+/// let client = reqwest::Client::new();
+/// let ds = datahugger::resolve("https://osf.io/3ua2c/").await?;
+/// let plan = DownloadPlan::crawl(ds, &client, MultiProgress::new())
+///     .await?
+///     .select(&["osfstorage/README.txt"])?;
+/// plan.download_with_validation(&client, "./data", MultiProgress::new(), &DownloadOptions::default())
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct DownloadPlan {
+    files: Vec<FileMeta>,
+}
+
+impl DownloadPlan {
+    #[must_use]
+    pub fn new(files: Vec<FileMeta>) -> Self {
+        DownloadPlan { files }
+    }
+
+    /// Crawls the whole dataset and collects all its files into a plan.
+    ///
+    /// # Errors
+    /// when crawl fails
+    pub async fn crawl(
+        dataset: Dataset,
+        client: &Client,
+        mp: impl ProgressManager,
+    ) -> Result<Self, Exn<CrawlerError>> {
+        let files = dataset
+            .crawl(client, mp, &CrawlOptions::new())
+            .try_filter_map(|entry| async move {
+                match entry {
+                    Entry::Dir(_) => Ok(None),
+                    Entry::File(file_meta) => Ok(Some(file_meta)),
+                }
+            })
+            .try_collect::<Vec<_>>()
+            .await?;
+        Ok(DownloadPlan { files })
+    }
+
+    /// Returns the files in the plan.
+    #[must_use]
+    pub fn files(&self) -> &[FileMeta] {
+        &self.files
+    }
+
+    /// Keeps only the files whose relative crawl path is exactly one of `paths`.
+    ///
+    /// # Errors
+    /// when any of `paths` is not a file of the plan, all unknown paths are listed.
+    pub fn select(self, paths: &[&str]) -> Result<Self, Exn<PlanError>> {
+        let known = self
+            .files
+            .iter()
+            .map(|f| f.relative().as_str().to_string())
+            .collect::<HashSet<_>>();
+        let unknown = paths
+            .iter()
+            .filter(|p| !known.contains(**p))
+            .map(|p| format!("'{p}'"))
+            .collect::<Vec<_>>();
+        if !unknown.is_empty() {
+            exn::bail!(PlanError {
+                message: format!("unknown paths: {}", unknown.join(", ")),
+                status: ErrorStatus::Permanent,
+            });
+        }
+
+        let paths = paths.iter().copied().collect::<HashSet<_>>();
+        let files = self
+            .files
+            .into_iter()
+            .filter(|f| paths.contains(f.relative().as_str()))
+            .collect();
+        Ok(DownloadPlan { files })
+    }
+
+    /// Discovers the size of files listed without one, e.g. from HAL or arXiv, with HEAD
+    /// requests, at most `limit` at a time (`0` for no limit).
+    ///
+    /// Known sizes give accurate progress bars and make `max_file_size` apply to these files
+    /// too, the downloaded files are validated against them.
+    pub async fn probe_sizes(self, client: &Client, limit: usize) -> Self {
+        let files = probe_sizes(self.files, client, default_client_options(), limit).await;
+        DownloadPlan { files }
+    }
+
+    /// Applies `policy` to the files whose paths differ only by case.
+    ///
+    /// # Errors
+    /// when files collide and `policy` is [`CollisionPolicy::Error`].
+    pub fn resolve_collisions(self, policy: CollisionPolicy) -> Result<Self, Exn<PlanError>> {
+        let files = resolve_collisions(self.files, policy)?;
+        Ok(DownloadPlan { files })
+    }
+}
+
+/// Moves the selected `files` to their local paths, see [`DownloadOptions::path_mapper`] and
+/// [`DownloadOptions::target_os`], and applies the collision policy of `options` to the
+/// local paths, which are the ones that collide on the filesystem.
+pub(crate) fn local_files(
+    files: Vec<FileMeta>,
+    options: &DownloadOptions,
+) -> Result<Vec<FileMeta>, Exn<PlanError>> {
+    let files = files
+        .into_iter()
+        .map(|file_meta| options.local_file(file_meta))
+        .collect();
+    resolve_collisions(files, options.collision_policy)
+}
+
+/// Fills in the size of the downloadable files listed without one, or with the placeholder
+/// size 0, from the `Content-Length` of a HEAD request to their download url, at most `limit` requests at a
+/// time (`0` for no limit).
+///
+/// Files whose HEAD request fails or has no `Content-Length` keep an unknown size.
+pub(crate) async fn probe_sizes(
+    files: Vec<FileMeta>,
+    client: &Client,
+    client_options: &ClientOptions,
+    limit: usize,
+) -> Vec<FileMeta> {
+    let limit = if limit == 0 {
+        files.len().max(1)
+    } else {
+        limit
+    };
+    stream::iter(files)
+        .map(|file| async move {
+            if file.size().is_some_and(|size| size > 0) || !file.is_downloadable() {
+                return file;
+            }
+            let resp = match client.head(file.download_url()).send_with_retry().await {
+                Ok(resp) => follow_cross_host_head(resp, client_options).await,
+                Err(err) => Err(err),
+            };
+            let resp = match resp {
+                Ok(resp) if resp.status().is_success() => resp,
+                Ok(resp) => {
+                    debug!("HEAD {} answered {}", file.download_url(), resp.status());
+                    return file;
+                }
+                Err(err) => {
+                    debug!("HEAD {} failed: {err}", file.download_url());
+                    return file;
+                }
+            };
+            // read the header, the body size hint of a HEAD response is always zero
+            let size = resp
+                .headers()
+                .get(CONTENT_LENGTH)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<u64>().ok());
+            match size {
+                Some(size) => file.with_size(size),
+                None => file,
+            }
+        })
+        .buffered(limit)
+        .collect()
+        .await
+}
+
+/// Fails if the selected `files`, moved to their local paths, that `options` downloads into
+/// `dst_dir` need more space than available on its filesystem.
+///
+/// Only known sizes are summed, less the size of the local files they overwrite.
+pub(crate) fn check_disk_space(
+    files: &[FileMeta],
+    dst_dir: &Path,
+    options: &DownloadOptions,
+) -> Result<(), Exn<CrawlerError>> {
+    let mut required = 0u64;
+    let mut unknown = 0;
+    for file in files.iter().filter(|f| f.is_downloadable()) {
+        match file.size().filter(|&size| size > 0) {
+            Some(size) if options.max_file_size.is_some_and(|max| size > max) => {}
+            Some(size) => {
+                let overwritten =
+                    fs::metadata(dst_dir.join(file.relative())).map_or(0, |m| m.len());
+                required += size.saturating_sub(overwritten);
+            }
+            None => unknown += 1,
+        }
+    }
+    if unknown > 0 {
+        warn!("{unknown} files of unknown size are not counted in the required disk space");
+    }
+    let available = match fs4::available_space(dst_dir) {
+        Ok(available) => available,
+        Err(err) => {
+            warn!(
+                "cannot check the space available at '{}': {err}",
+                dst_dir.display()
+            );
+            return Ok(());
+        }
+    };
+    debug!("{required} bytes required, {available} bytes available");
+    if required > available {
+        let cause = DatahuggerError::InsufficientSpace {
+            required,
+            available,
+        };
+        let message = format!("{cause} at '{}'", dst_dir.display());
+        return Err(Exn::new(cause).raise(CrawlerError {
+            message,
+            status: ErrorStatus::Permanent,
+        }));
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl DownloadExt for DownloadPlan {
+    /// Downloads the files of the plan into `dst_dir`, validating both checksum and size.
+    ///
+    /// Behaves as [`Dataset::download_with_validation`](DownloadExt::download_with_validation)
+    /// but without crawling, the filter of `options` is still applied on the planned files.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any file fails to download or to validate, unless
+    /// `DownloadOptions::continue_on_error` is set.
+    async fn download_with_validation<P>(
+        self,
+        client: &Client,
+        dst_dir: P,
+        mp: impl ProgressManager,
+        options: &DownloadOptions,
+    ) -> Result<DownloadReport, Exn<CrawlerError>>
+    where
+        P: AsRef<Path> + Sync + Send,
+    {
+        let path = dst_dir.as_ref();
+        fs::create_dir_all(path).or_raise(|| CrawlerError {
+            message: format!("cannot create dir at '{}'", path.display()),
+            status: ErrorStatus::Permanent,
+        })?;
+        let files = self
+            .files
+            .into_iter()
+            .filter(|f| options.is_selected_file(f))
+            .collect();
+        let files = local_files(files, options).or_raise(|| CrawlerError {
+            message: "cannot resolve path collisions".to_string(),
+            status: ErrorStatus::Permanent,
+        })?;
+        let files = if options.probe_sizes {
+            probe_sizes(files, client, &options.client_options, options.limit).await
+        } else {
+            files
+        };
+        if options.check_disk_space && options.writes_local() {
+            check_disk_space(&files, path, options)?;
+        }
+        let total = TotalProgress::planned(&mp, &files);
+        let entries = stream::iter(files.into_iter().map(|f| Ok(Entry::File(f))));
+        download_local_entries(entries, client, path, mp, options, total).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{TempDir, TestFile};
+    use url::Url;
+
+    #[test]
+    fn test_plan_select() {
+        let plan = DownloadPlan::new(vec![
+            TestFile::new("a.csv").build(),
+            TestFile::new("raw/b.csv").build(),
+            TestFile::new("c.txt").build(),
+        ]);
+        let plan = plan.select(&["raw/b.csv", "c.txt"]).unwrap();
+        let paths = plan
+            .files()
+            .iter()
+            .map(|f| f.relative().as_str().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["raw/b.csv", "c.txt"]);
+    }
+
+    #[test]
+    fn test_resolve_collisions() {
+        let files = || {
+            vec![
+                TestFile::new("Readme.md").build(),
+                TestFile::new("README.md").build(),
+                TestFile::new("readme~1.md").build(),
+                TestFile::new("docs/LICENSE").build(),
+                TestFile::new("docs/license").build(),
+            ]
+        };
+        let paths = resolve_collisions(files(), CollisionPolicy::RenameDeterministic)
+            .unwrap()
+            .iter()
+            .map(|f| f.relative().as_str().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            [
+                "Readme~2.md",
+                "README.md",
+                "readme~1.md",
+                "docs/LICENSE",
+                "docs/license~1"
+            ]
+        );
+
+        let err = resolve_collisions(files(), CollisionPolicy::Error).unwrap_err();
+        assert_eq!(
+            err.message,
+            "paths differ only by case: 'docs/LICENSE', 'docs/license'; 'README.md', 'Readme.md'"
+        );
+        assert_eq!(
+            resolve_collisions(files(), CollisionPolicy::Overwrite)
+                .unwrap()
+                .len(),
+            5
+        );
+    }
+
+    #[tokio::test]
+    async fn test_probe_sizes() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path("/a.pdf"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![0u8; 1234]))
+            .mount(&server)
+            .await;
+        let cdn = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path("/moved.pdf"))
+            .respond_with(ResponseTemplate::new(302).insert_header(
+                "location",
+                // another host than the repository at '127.0.0.1'
+                format!("{}/blob", cdn.uri().replace("127.0.0.1", "localhost")),
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("HEAD"))
+            .and(path("/blob"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![0u8; 42]))
+            .expect(1)
+            .mount(&cdn)
+            .await;
+        let file = |name: &str| {
+            let url = Url::parse(&format!("{}/{name}", server.uri())).unwrap();
+            TestFile::new(name).url(url).build()
+        };
+
+        let client = Client::builder()
+            .redirect(crate::RedirectPolicy::new().policy())
+            .build()
+            .unwrap();
+        let plan = DownloadPlan::new(vec![file("a.pdf"), file("missing.pdf"), file("moved.pdf")])
+            .probe_sizes(&client, 0)
+            .await;
+        let sizes = plan.files().iter().map(FileMeta::size).collect::<Vec<_>>();
+        assert_eq!(sizes, [Some(1234), None, Some(42)]);
+    }
+
+    #[test]
+    fn test_plan_select_unknown_path() {
+        let plan = DownloadPlan::new(vec![TestFile::new("a.csv").build()]);
+        let err = plan.select(&["a.csv", "b.csv", "raw"]).unwrap_err();
+        assert_eq!(err.message, "unknown paths: 'b.csv', 'raw'");
+    }
+
+    #[test]
+    fn test_check_disk_space() {
+        let dst = TempDir::new("disk-space");
+        let options = DownloadOptions::new();
+        let huge = TestFile::new("huge.bin").size(u64::MAX / 2).build();
+
+        let files = [
+            TestFile::new("unknown.bin").build(),
+            TestFile::new("small.bin").size(10).build(),
+        ];
+        assert!(check_disk_space(&files, &dst, &options).is_ok());
+
+        let err = check_disk_space(std::slice::from_ref(&huge), &dst, &options).unwrap_err();
+        assert!(matches!(
+            DatahuggerError::from(&err),
+            DatahuggerError::InsufficientSpace { required, .. } if required == u64::MAX / 2
+        ));
+        // skipped files need no space
+        let options = DownloadOptions::new().max_file_size(1000);
+        assert!(check_disk_space(&[huge], &dst, &options).is_ok());
+    }
+}
@@ -0,0 +1,133 @@
+use indicatif::{ProgressBar, ProgressStyle};
+use std::sync::Mutex;
+
+use crate::{crawler::ProgressManager, Entry, FileMeta};
+
+/// Aggregate progress of the files of a download, shown as "N of M files" with the bytes
+/// downloaded out of the summed sizes, above the bars of single files.
+#[derive(Debug)]
+pub(crate) struct TotalProgress {
+    pb: ProgressBar,
+    /// files are counted as the crawl yields them, unless all are known upfront.
+    streamed: bool,
+    counts: Mutex<Counts>,
+}
+
+#[derive(Debug, Default)]
+struct Counts {
+    files: u64,
+    done: u64,
+    /// expected bytes of all files.
+    length: u64,
+}
+
+impl TotalProgress {
+    /// Progress of files counted as they are streamed from the crawl, the totals grow
+    /// until the crawl completes.
+    pub(crate) fn streamed(mp: &impl ProgressManager) -> Self {
+        Self::new(mp, true)
+    }
+
+    /// Progress of the selected `files`, whose totals are known before downloading.
+    pub(crate) fn planned(mp: &impl ProgressManager, files: &[FileMeta]) -> Self {
+        let total = Self::new(mp, false);
+        for file in files {
+            total.add_file(file);
+        }
+        total
+    }
+
+    fn new(mp: &impl ProgressManager, streamed: bool) -> Self {
+        let style = ProgressStyle::with_template(
+            "{msg:<60} [{bar:40.green/white}] \
+             {decimal_bytes:>8}/{decimal_total_bytes:>8} \
+             ({decimal_bytes_per_sec:>12}, {eta:>3})",
+        )
+        .expect("indicatif template error")
+        .progress_chars("=>-");
+        let pb = mp.insert_from_back(0, ProgressBar::new(0).with_style(style));
+        let total = TotalProgress {
+            pb,
+            streamed,
+            counts: Mutex::default(),
+        };
+        total.update(|_| {});
+        total
+    }
+
+    /// Counts the file of `entry` as it is streamed, files of a planned download are
+    /// already counted.
+    pub(crate) fn add_streamed(&self, entry: &Entry) {
+        if let (true, Entry::File(file_meta)) = (self.streamed, entry) {
+            self.add_file(file_meta);
+        }
+    }
+
+    fn add_file(&self, file_meta: &FileMeta) {
+        self.update(|counts| {
+            counts.files += 1;
+            counts.length += file_meta.size().unwrap_or(0);
+        });
+    }
+
+    /// Advances the downloaded bytes by `bytes`.
+    pub(crate) fn inc(&self, bytes: u64) {
+        self.pb.inc(bytes);
+    }
+
+    /// Counts a file listed with `size` as done after `bytes` were downloaded, the total is
+    /// corrected for files skipped, failed, or of unknown size.
+    pub(crate) fn finish_file(&self, size: Option<u64>, bytes: u64) {
+        self.update(|counts| {
+            counts.done += 1;
+            counts.length = (counts.length + bytes).saturating_sub(size.unwrap_or(0));
+        });
+    }
+
+    pub(crate) fn finish(&self) {
+        self.pb.finish_and_clear();
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Counts)) {
+        let mut counts = self.counts.lock().expect("progress lock poisoned");
+        f(&mut counts);
+        self.pb.set_length(counts.length);
+        self.pb
+            .set_message(format!("{} of {} files", counts.done, counts.files));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{NoProgress, TestFile};
+
+    #[test]
+    fn test_total_progress() {
+        let files = [
+            TestFile::new("a.csv").size(10).build(),
+            TestFile::new("b.csv").size(20).build(),
+        ];
+        let total = TotalProgress::planned(&NoProgress, &files);
+        assert_eq!(total.pb.message(), "0 of 2 files");
+        assert_eq!(total.pb.length(), Some(30));
+        // planned files are not counted twice
+        total.add_streamed(&Entry::File(files[0].clone()));
+
+        total.inc(10);
+        total.finish_file(Some(10), 10);
+        // skipped
+        total.finish_file(Some(20), 0);
+        assert_eq!(total.pb.message(), "2 of 2 files");
+        assert_eq!(total.pb.length(), Some(10));
+        assert_eq!(total.pb.position(), 10);
+
+        let total = TotalProgress::streamed(&NoProgress);
+        total.add_streamed(&Entry::File(TestFile::new("c.csv").build()));
+        assert_eq!(total.pb.length(), Some(0));
+        total.inc(5);
+        total.finish_file(None, 5);
+        assert_eq!(total.pb.message(), "1 of 1 files");
+        assert_eq!(total.pb.length(), Some(5));
+    }
+}
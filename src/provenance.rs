@@ -0,0 +1,94 @@
+use std::{
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::warn;
+use url::Url;
+
+use crate::Checksum;
+
+/// Prefix of the extended attributes recording where a downloaded file comes from.
+pub const XATTR_PREFIX: &str = "user.datahugger.";
+
+/// Formats `checksum` as `<algo>:<hex>`.
+pub(crate) fn checksum_str(checksum: &Checksum) -> String {
+    format!("{}:{}", checksum.algorithm(), checksum.value())
+}
+
+/// Seconds since the unix epoch, `0` for times before it.
+pub(crate) fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// Records the provenance of a downloaded file as extended attributes (NTFS alternate
+/// data streams on Windows): `source_url`, `checksum` (as `<algo>:<hex>`, if known) and
+/// `retrieved_at` (unix time in seconds), each under [`XATTR_PREFIX`].
+///
+/// This is best effort, a filesystem without extended attribute support only logs a
+/// warning.
+pub(crate) fn record_xattrs(path: &Path, source_url: &Url, checksum: Option<&Checksum>) {
+    let retrieved_at = unix_secs(SystemTime::now());
+    let mut attrs = vec![
+        ("source_url", source_url.as_str().to_string()),
+        ("retrieved_at", retrieved_at.to_string()),
+    ];
+    if let Some(checksum) = checksum {
+        attrs.push(("checksum", checksum_str(checksum)));
+    }
+    for (key, value) in attrs {
+        let name = format!("{XATTR_PREFIX}{key}");
+        if let Err(err) = set_attr(path, &name, value.as_bytes()) {
+            warn!(
+                "cannot record provenance '{name}' on '{}': {err}",
+                path.display()
+            );
+            return;
+        }
+    }
+}
+
+#[cfg(unix)]
+fn set_attr(path: &Path, name: &str, value: &[u8]) -> std::io::Result<()> {
+    xattr::set(path, name, value)
+}
+
+#[cfg(windows)]
+fn set_attr(path: &Path, name: &str, value: &[u8]) -> std::io::Result<()> {
+    let mut stream = path.as_os_str().to_owned();
+    stream.push(":");
+    stream.push(name);
+    std::fs::write(stream, value)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn set_attr(_path: &Path, _name: &str, _value: &[u8]) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "extended attributes are not supported on this platform",
+    ))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    #[test]
+    fn test_record_xattrs() {
+        let dir = TempDir::new("record-xattrs");
+        let path = dir.join("a.txt");
+        std::fs::write(&path, "data").unwrap();
+        let url = Url::parse("https://zenodo.org/api/files/a.txt").unwrap();
+        record_xattrs(&path, &url, Some(&Checksum::Md5("8d777f38".to_string())));
+
+        // the temp filesystem may not support user xattrs, recording is best effort then
+        if let Ok(Some(value)) = xattr::get(&path, "user.datahugger.source_url") {
+            assert_eq!(value, url.as_str().as_bytes());
+            let checksum = xattr::get(&path, "user.datahugger.checksum").unwrap();
+            assert_eq!(checksum.as_deref(), Some(b"md5:8d777f38".as_slice()));
+            assert!(xattr::get(&path, "user.datahugger.retrieved_at")
+                .unwrap()
+                .is_some());
+        }
+    }
+}
@@ -0,0 +1,289 @@
+use reqwest::{
+    header::{HeaderMap, LOCATION},
+    redirect::Policy,
+    Method, Response, StatusCode,
+};
+use std::sync::LazyLock;
+
+use crate::{metrics::record_request, ClientOptions};
+
+/// Redirects followed by default, as reqwest does.
+pub(crate) const DEFAULT_MAX_HOPS: usize = 10;
+
+/// How the client carrying the repository credentials follows redirects, e.g. Zenodo
+/// downloads redirected to its S3 CDN or Dataverse files redirected to a presigned S3 url.
+///
+/// reqwest drops `Authorization` and `Cookie` on a redirect to another host, but not the
+/// custom credential headers of some repositories, e.g. `X-Dataverse-Key`. With
+/// [`RedirectPolicy::same_host_auth_only`] (the default) the client stops at the first
+/// redirect to another host, and datahugger requests the new location with a fresh client
+/// without any of the credentials and default headers of the original one.
+///
+/// Set [`RedirectPolicy::policy`] as redirect policy of the client.
+///
+/// # Examples
+///
+/// ```
+/// use datahugger::RedirectPolicy;
+///
+/// let policy = RedirectPolicy::new().max_hops(5);
+/// let client = reqwest::ClientBuilder::new()
+///     .redirect(policy.policy())
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RedirectPolicy {
+    max_hops: usize,
+    same_host_auth_only: bool,
+}
+
+impl Default for RedirectPolicy {
+    fn default() -> Self {
+        RedirectPolicy {
+            max_hops: DEFAULT_MAX_HOPS,
+            same_host_auth_only: true,
+        }
+    }
+}
+
+impl RedirectPolicy {
+    /// Creates a policy following up to 10 redirects, sending credentials to the
+    /// original host only.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of redirects followed by a request, `0` follows none.
+    #[must_use]
+    pub fn max_hops(mut self, max_hops: usize) -> Self {
+        self.max_hops = max_hops;
+        self
+    }
+
+    /// Sets whether credentials are only sent to the host of the original request.
+    ///
+    /// When disabled, redirects are followed by the client itself, which only drops the
+    /// standard `Authorization` and `Cookie` headers on another host.
+    #[must_use]
+    pub fn same_host_auth_only(mut self, same_host_auth_only: bool) -> Self {
+        self.same_host_auth_only = same_host_auth_only;
+        self
+    }
+
+    /// Returns the reqwest redirect policy implementing this policy.
+    #[must_use]
+    pub fn policy(&self) -> Policy {
+        let RedirectPolicy {
+            max_hops,
+            same_host_auth_only,
+        } = *self;
+        Policy::custom(move |attempt| {
+            if attempt.previous().len() > max_hops {
+                return attempt.error(format!("more than {max_hops} redirects"));
+            }
+            let origin_host = attempt.previous().first().and_then(|url| url.host_str());
+            if same_host_auth_only && attempt.url().host_str() != origin_host {
+                return attempt.stop();
+            }
+            attempt.follow()
+        })
+    }
+}
+
+/// Options of the redirects followed where no [`ClientOptions`] are given, e.g. the listing
+/// requests of the backends.
+pub(crate) fn default_client_options() -> &'static ClientOptions {
+    static OPTIONS: LazyLock<ClientOptions> = LazyLock::new(ClientOptions::default);
+    &OPTIONS
+}
+
+/// Follows a redirect to another host at which [`RedirectPolicy::policy`] stopped, with the
+/// client of `options` without the credentials of the one that received `resp`.
+///
+/// Any other response is returned as is.
+pub(crate) async fn follow_cross_host(
+    resp: Response,
+    options: &ClientOptions,
+) -> Result<Response, reqwest::Error> {
+    follow_cross_host_with(resp, HeaderMap::new(), options).await
+}
+
+/// Like [`follow_cross_host`], sending `headers` of the original request that carry no
+/// credentials along to the new location, e.g. `Range`.
+pub(crate) async fn follow_cross_host_with(
+    resp: Response,
+    headers: HeaderMap,
+    options: &ClientOptions,
+) -> Result<Response, reqwest::Error> {
+    follow_cross_host_as(resp, Method::GET, headers, options).await
+}
+
+/// Like [`follow_cross_host`] for the response to a HEAD request, the new location is
+/// requested with HEAD too.
+pub(crate) async fn follow_cross_host_head(
+    resp: Response,
+    options: &ClientOptions,
+) -> Result<Response, reqwest::Error> {
+    follow_cross_host_as(resp, Method::HEAD, HeaderMap::new(), options).await
+}
+
+async fn follow_cross_host_as(
+    resp: Response,
+    method: Method,
+    headers: HeaderMap,
+    options: &ClientOptions,
+) -> Result<Response, reqwest::Error> {
+    if !resp.status().is_redirection() || resp.status() == StatusCode::NOT_MODIFIED {
+        return Ok(resp);
+    }
+    let Some(location) = resp
+        .headers()
+        .get(LOCATION)
+        .and_then(|location| location.to_str().ok())
+        .and_then(|location| resp.url().join(location).ok())
+    else {
+        return Ok(resp);
+    };
+    let client = options.redirect_client()?;
+    record_request(false);
+    client
+        .request(method, location)
+        .headers(headers)
+        .send()
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+    use wiremock::{
+        matchers::{header, header_exists, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_cross_host_redirect_drops_credentials() {
+        let repo = MockServer::start().await;
+        let cdn = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/file"))
+            .respond_with(ResponseTemplate::new(303).insert_header(
+                "location",
+                // another host than the repository at '127.0.0.1'
+                format!("{}/blob", cdn.uri().replace("127.0.0.1", "localhost")),
+            ))
+            .mount(&repo)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/blob"))
+            .and(header_exists("x-dataverse-key"))
+            .respond_with(ResponseTemplate::new(403))
+            .mount(&cdn)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/blob"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("data"))
+            .mount(&cdn)
+            .await;
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-dataverse-key", HeaderValue::from_static("secret"));
+        let client = crate::client_builder()
+            .default_headers(headers)
+            .redirect(RedirectPolicy::new().policy())
+            .build()
+            .unwrap();
+        let resp = client
+            .get(format!("{}/file", repo.uri()))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::SEE_OTHER);
+        let resp = follow_cross_host(resp, default_client_options())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.text().await.unwrap(), "data");
+    }
+
+    #[tokio::test]
+    async fn test_cross_host_redirect_client_options() {
+        let repo = MockServer::start().await;
+        let cdn = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path("/file"))
+            .respond_with(ResponseTemplate::new(302).insert_header(
+                "location",
+                format!("{}/blob", cdn.uri().replace("127.0.0.1", "localhost")),
+            ))
+            .mount(&repo)
+            .await;
+        Mock::given(method("HEAD"))
+            .and(path("/blob"))
+            .and(header("user-agent", "mirror/1.0"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(2)
+            .mount(&cdn)
+            .await;
+
+        let options = ClientOptions::new().user_agent("mirror/1.0");
+        let client = options
+            .builder()
+            .redirect(RedirectPolicy::new().policy())
+            .build()
+            .unwrap();
+        for _ in 0..2 {
+            let resp = client
+                .head(format!("{}/file", repo.uri()))
+                .send()
+                .await
+                .unwrap();
+            let resp = follow_cross_host_head(resp, &options).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cross_host_redirect_max_redirects() {
+        let repo = MockServer::start().await;
+        let cdn = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/file"))
+            .respond_with(ResponseTemplate::new(302).insert_header(
+                "location",
+                format!("{}/blob", cdn.uri().replace("127.0.0.1", "localhost")),
+            ))
+            .mount(&repo)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/blob"))
+            .respond_with(ResponseTemplate::new(302).insert_header("location", "/signed"))
+            .mount(&cdn)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/signed"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&cdn)
+            .await;
+
+        let follow = |max_redirects: usize| {
+            let url = format!("{}/file", repo.uri());
+            async move {
+                let options = ClientOptions::new().max_redirects(max_redirects);
+                let client = options
+                    .builder()
+                    .redirect(RedirectPolicy::new().max_hops(max_redirects).policy())
+                    .build()
+                    .unwrap();
+                let resp = client.get(url).send().await.unwrap();
+                follow_cross_host(resp, &options).await
+            }
+        };
+        // the hop to the cdn and the one to the signed url
+        assert_eq!(follow(2).await.unwrap().status(), StatusCode::OK);
+        assert!(follow(1).await.unwrap_err().is_redirect());
+    }
+}
@@ -1,10 +1,23 @@
 use async_trait::async_trait;
 use exn::Exn;
 use mime::Mime;
-use reqwest::Client;
+use reqwest::{header::HeaderMap, Client};
 use url::Url;
 
-use std::{any::Any, path::Path, sync::Arc};
+use crate::{
+    checksum::{registered_algorithm, ChecksumAlgorithm, DigestHasher},
+    error::ErrorStatus,
+    filter::is_hidden_path,
+    zipindex::ZipMember,
+    AccessRequirement, AccessRequirements, AuthConfig, CitationStyle, DatasetMetadata,
+};
+
+use std::{
+    any::Any,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
 
 use digest::Digest;
 
@@ -45,7 +58,7 @@ const ROOT: &str = "__ROOT__";
 /// assert!(p.is_absolute());
 /// assert_eq!(p.relative().as_ref(), std::path::Path::new("dir/file.txt"));
 /// ```
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CrawlPath(String);
 
 impl std::fmt::Display for CrawlPath {
@@ -128,7 +141,14 @@ impl CrawlPath {
 pub enum Hasher {
     Md5(md5::Md5),
     Sha256(sha2::Sha256),
+    Sha512(sha2::Sha512),
+    Sha384(sha2::Sha384),
     Sha1(sha1::Sha1),
+    Blake2b(blake2::Blake2b512),
+    Crc32(crc32fast::Hasher),
+    Adler32(simd_adler32::Adler32),
+    /// Hasher of a registered [`ChecksumAlgorithm`].
+    Custom(Box<dyn DigestHasher>),
 }
 
 impl Hasher {
@@ -136,20 +156,40 @@ impl Hasher {
         match self {
             Hasher::Md5(h) => h.update(data),
             Hasher::Sha256(h) => h.update(data),
+            Hasher::Sha512(h) => h.update(data),
+            Hasher::Sha384(h) => h.update(data),
             Hasher::Sha1(h) => h.update(data),
+            Hasher::Blake2b(h) => h.update(data),
+            Hasher::Crc32(h) => h.update(data),
+            Hasher::Adler32(h) => h.write(data),
+            Hasher::Custom(h) => h.update(data),
         }
     }
 
+    /// Returns the digest, crc32 and adler32 as their 4 big-endian bytes.
     #[must_use]
     pub fn finalize(self) -> Vec<u8> {
         match self {
             Hasher::Md5(h) => h.finalize().to_vec(),
             Hasher::Sha256(h) => h.finalize().to_vec(),
+            Hasher::Sha512(h) => h.finalize().to_vec(),
+            Hasher::Sha384(h) => h.finalize().to_vec(),
             Hasher::Sha1(h) => h.finalize().to_vec(),
+            Hasher::Blake2b(h) => h.finalize().to_vec(),
+            Hasher::Crc32(h) => h.finalize().to_be_bytes().to_vec(),
+            Hasher::Adler32(h) => h.finish().to_be_bytes().to_vec(),
+            Hasher::Custom(h) => h.finalize(),
         }
     }
 }
 
+/// A directory or file yielded by a crawl.
+///
+/// A crawl yields parents before children: the entry of a directory always comes before
+/// the entries inside it, see [`crawl_with_fanout`](crate::crawler::crawl_with_fanout).
+/// Filters drop directory entries while keeping files inside them, so a consumer must not
+/// expect the entry of the parent of a file; downloads create the parent folders of every
+/// file from its path.
 #[allow(clippy::large_enum_variant)]
 #[derive(Debug)]
 pub enum Entry {
@@ -215,6 +255,20 @@ impl DirMeta {
         self.path.relative()
     }
 
+    /// Moves the folder to the relative crawl path `relative`, e.g. to sanitize its name.
+    #[must_use]
+    pub(crate) fn with_relative(mut self, relative: &str) -> Self {
+        self.path = CrawlPath::root().join(relative);
+        self
+    }
+
+    /// Returns `true` for hidden folders and operating system artifacts, see
+    /// [`FileMeta::is_hidden`].
+    #[must_use]
+    pub fn is_hidden(&self) -> bool {
+        is_hidden_path(self.relative().as_str())
+    }
+
     #[must_use]
     pub fn join(&self, p: &str) -> CrawlPath {
         self.path.join(p)
@@ -247,7 +301,7 @@ impl std::fmt::Display for Endpoint {
 /// validated against the file contents. As a result, it may be incorrect.
 /// For example, some APIs infer MIME types from file extensions rather
 /// than inspecting the actual data.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FileMeta {
     filename: Option<String>,
     file_identifier: Option<String>,
@@ -261,6 +315,8 @@ pub struct FileMeta {
     creation_date: Option<String>,
     last_modification_date: Option<String>,
     downloadable: bool,
+    zip_member: Option<ZipMember>,
+    bundle: Vec<CrawlPath>,
 }
 
 impl FileMeta {
@@ -268,6 +324,9 @@ impl FileMeta {
         self.filename.as_deref()
     }
 
+    /// Repository-native identifier of the file, e.g. the Dataverse `dataFile.id`, the
+    /// Zenodo `file_id`, the OSF file id or the git blob id on GitHub and Hugging Face,
+    /// to cross-reference the file with the repository API.
     pub fn file_identifier(&self) -> Option<&str> {
         self.file_identifier.as_deref()
     }
@@ -378,35 +437,272 @@ impl FileMeta {
             creation_date,
             last_modification_date,
             downloadable,
+            zip_member: None,
+            bundle: Vec::new(),
         }
     }
     #[must_use]
     pub fn relative(&self) -> CrawlPath {
         self.path.relative()
     }
+
+    /// Returns `true` for dotfiles and operating system artifacts, e.g. `.DS_Store`,
+    /// `Thumbs.db` or anything below a `__MACOSX/` folder, which deposited datasets often
+    /// contain by accident.
+    #[must_use]
+    pub fn is_hidden(&self) -> bool {
+        is_hidden_path(self.relative().as_str())
+    }
+
+    /// Moves the file to the relative crawl path `relative`, e.g. to resolve a collision or
+    /// sanitize its name.
+    #[must_use]
+    pub(crate) fn with_relative(mut self, relative: &str) -> Self {
+        self.path = CrawlPath::root().join(relative);
+        self
+    }
+
+    pub(crate) fn with_size(mut self, size: u64) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    /// Marks the file as the `member` of the zip archive at its download url, see
+    /// [`expand_zip`](crate::expand_zip).
+    pub(crate) fn with_zip_member(mut self, member: ZipMember) -> Self {
+        self.zip_member = Some(member);
+        self
+    }
+
+    /// Marks the file as part of a bundle with the files at the crawl paths `others`, e.g. the
+    /// `.shx` and `.dbf` of a shapefile, which are only usable downloaded together.
+    ///
+    /// A file of a bundle passes a [`FileFilter`](crate::FileFilter) if any file of the
+    /// bundle does.
+    #[must_use]
+    pub fn with_bundle(mut self, others: Vec<CrawlPath>) -> Self {
+        self.bundle = others;
+        self
+    }
+
+    /// Crawl paths of the other files of the bundle of the file, see [`FileMeta::with_bundle`].
+    #[must_use]
+    pub fn bundle(&self) -> &[CrawlPath] {
+        &self.bundle
+    }
+
+    pub(crate) fn zip_member(&self) -> Option<&ZipMember> {
+        self.zip_member.as_ref()
+    }
+
     #[must_use]
     pub fn endpoint(&self) -> Endpoint {
         self.endpoint.clone()
     }
 }
 
-// XXX: github blob didnt validate, it use sha1 but compute (maybe) with 'blob {}' as prefix of
-// content. I lean to not validate github downloads for simplicity. Only do it when requests come.
 #[derive(Debug, Clone)]
 pub enum Checksum {
     Md5(String),
     Sha256(String),
+    Sha512(String),
+    Sha384(String),
     Sha1(String),
+    /// BLAKE2b with a 512 bits digest, as computed by `b2sum`.
+    Blake2b(String),
+    /// CRC-32 (IEEE), hex of the big-endian value as printed by `crc32`.
+    Crc32(String),
+    /// Adler-32, hex of the big-endian value, as listed by Dryad.
+    Adler32(String),
+    /// Git blob id, the SHA-1 of the content prefixed by the `blob <len>\0` header, as
+    /// listed by GitHub and Hugging Face for files in git.
+    GitSha1(String),
+    /// Digest of an algorithm registered with
+    /// [`register_checksum_algorithm`](crate::register_checksum_algorithm).
+    Custom(CustomChecksum),
 }
 
-impl std::fmt::Display for Checksum {
+/// Digest of a registered [`ChecksumAlgorithm`], see [`Checksum::parse`].
+#[derive(Clone)]
+pub struct CustomChecksum {
+    algorithm: Arc<dyn ChecksumAlgorithm>,
+    value: String,
+}
+
+impl std::fmt::Debug for CustomChecksum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CustomChecksum")
+            .field("algorithm", &self.algorithm.name())
+            .field("value", &self.value)
+            .finish()
+    }
+}
+
+/// Error of [`Checksum::parse`], raised for unknown algorithms and malformed digests.
+#[derive(Debug)]
+pub struct ChecksumError {
+    pub message: String,
+    pub status: ErrorStatus,
+}
+
+impl std::fmt::Display for ChecksumError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "checksum fail: {}", self.message)
+    }
+}
+
+impl std::error::Error for ChecksumError {}
+
+impl Checksum {
+    /// Parses the hex encoded digest `value` computed with `algo` as published by a
+    /// repository.
+    ///
+    /// `algo` is matched case-insensitively and with or without dash (`md5`, `SHA-1`,
+    /// `sha256`, `SHA-384`, `SHA-512`, `blake2b`, `crc32`, `adler-32`, `git-sha1`, ...), other algorithms must be registered
+    /// with [`register_checksum_algorithm`](crate::register_checksum_algorithm). `value` is
+    /// trimmed, stripped of an `<algo>:` prefix and lowercased.
+    ///
+    /// # Errors
+    /// Returns an error if `algo` is not supported or `value` is not a hex digest of the
+    /// length of the algorithm (e.g. truncated).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datahugger::Checksum;
+    ///
+    /// let checksum = Checksum::parse("MD5", "md5:8D777F385D3DFEC8815D20F7496026DC").unwrap();
+    /// assert_eq!(checksum.to_string(), "(md5: 8d777f385d3dfec8815d20f7496026dc)");
+    /// assert!(Checksum::parse("sha256", "8d777f38").is_err());
+    /// ```
+    pub fn parse(algo: &str, value: &str) -> Result<Checksum, Exn<ChecksumError>> {
+        let algo = normalize_algo(algo);
+        let (checksum, len): (Box<dyn FnOnce(String) -> Checksum>, usize) =
+            if let Some((variant, len)) = algo_spec(&algo) {
+                (Box::new(variant), len)
+            } else if let Some(algorithm) = registered_algorithm(&algo) {
+                let len = algorithm.hex_len();
+                let custom = |value| Checksum::Custom(CustomChecksum { algorithm, value });
+                (Box::new(custom), len)
+            } else {
+                exn::bail!(ChecksumError {
+                    message: format!("unsupported checksum algorithm '{algo}'"),
+                    status: ErrorStatus::Permanent,
+                })
+            };
+        let value = value.trim();
+        let value = value
+            .split_once(':')
+            .filter(|(prefix, _)| normalize_algo(prefix) == algo)
+            .map_or(value, |(_, value)| value)
+            .to_lowercase();
+        if value.len() != len || !value.chars().all(|c| c.is_ascii_hexdigit()) {
+            exn::bail!(ChecksumError {
+                message: format!("'{value}' is not a {algo} digest of {len} hex digits"),
+                status: ErrorStatus::Permanent,
+            });
+        }
+        Ok(checksum(value))
+    }
+
+    /// Returns `true` if [`Checksum::parse`] accepts digests of `algo`.
+    pub(crate) fn is_supported(algo: &str) -> bool {
+        let algo = normalize_algo(algo);
+        algo_spec(&algo).is_some() || registered_algorithm(&algo).is_some()
+    }
+
+    /// Returns `true` if the normalized algorithm name `algo` is built into datahugger.
+    pub(crate) fn is_builtin(algo: &str) -> bool {
+        algo_spec(algo).is_some()
+    }
+
+    /// Returns the name of the algorithm, as accepted by [`Checksum::parse`].
+    #[must_use]
+    pub fn algorithm(&self) -> &'static str {
         match self {
-            Checksum::Md5(h) => write!(f, "(md5: {h})"),
-            Checksum::Sha256(h) => write!(f, "(sha256: {h})"),
-            Checksum::Sha1(h) => write!(f, "(sha1: {h})"),
+            Checksum::Md5(_) => "md5",
+            Checksum::Sha256(_) => "sha256",
+            Checksum::Sha512(_) => "sha512",
+            Checksum::Sha384(_) => "sha384",
+            Checksum::Sha1(_) => "sha1",
+            Checksum::Blake2b(_) => "blake2b",
+            Checksum::Crc32(_) => "crc32",
+            Checksum::Adler32(_) => "adler32",
+            Checksum::GitSha1(_) => "gitsha1",
+            Checksum::Custom(custom) => custom.algorithm.name(),
         }
     }
+
+    /// Returns the expected digest, hex encoded.
+    #[must_use]
+    pub fn value(&self) -> &str {
+        match self {
+            Checksum::Md5(h)
+            | Checksum::Sha256(h)
+            | Checksum::Sha512(h)
+            | Checksum::Sha384(h)
+            | Checksum::Sha1(h)
+            | Checksum::Blake2b(h)
+            | Checksum::Crc32(h)
+            | Checksum::Adler32(h)
+            | Checksum::GitSha1(h) => h,
+            Checksum::Custom(custom) => &custom.value,
+        }
+    }
+
+    /// Returns a hasher computing the same kind of digest of content of `len` bytes.
+    ///
+    /// Git blob ids hash the length ahead of the content, their hasher is `None` when `len`
+    /// is unknown.
+    pub(crate) fn hasher(&self, len: Option<u64>) -> Option<Hasher> {
+        let hasher = match self {
+            Checksum::Md5(_) => Hasher::Md5(md5::Md5::new()),
+            Checksum::Sha256(_) => Hasher::Sha256(sha2::Sha256::new()),
+            Checksum::Sha512(_) => Hasher::Sha512(sha2::Sha512::new()),
+            Checksum::Sha384(_) => Hasher::Sha384(sha2::Sha384::new()),
+            Checksum::Sha1(_) => Hasher::Sha1(sha1::Sha1::new()),
+            Checksum::Blake2b(_) => Hasher::Blake2b(blake2::Blake2b512::new()),
+            Checksum::Crc32(_) => Hasher::Crc32(crc32fast::Hasher::new()),
+            Checksum::Adler32(_) => Hasher::Adler32(simd_adler32::Adler32::new()),
+            Checksum::GitSha1(_) => {
+                let mut sha1 = sha1::Sha1::new();
+                sha1.update(format!("blob {}\0", len?));
+                Hasher::Sha1(sha1)
+            }
+            Checksum::Custom(custom) => Hasher::Custom(custom.algorithm.hasher()),
+        };
+        Some(hasher)
+    }
+}
+
+pub(crate) fn normalize_algo(algo: &str) -> String {
+    algo.trim().to_lowercase().replace(['-', '_'], "")
+}
+
+/// Variant and digest length in hex digits of an algorithm.
+type AlgoSpec = (fn(String) -> Checksum, usize);
+
+/// Returns the [`AlgoSpec`] of a normalized algorithm name.
+fn algo_spec(algo: &str) -> Option<AlgoSpec> {
+    let spec: AlgoSpec = match algo {
+        "md5" => (Checksum::Md5, 32),
+        "sha1" => (Checksum::Sha1, 40),
+        "sha256" => (Checksum::Sha256, 64),
+        "sha384" => (Checksum::Sha384, 96),
+        "sha512" => (Checksum::Sha512, 128),
+        "blake2" | "blake2b" | "blake2b512" => (Checksum::Blake2b, 128),
+        "crc32" => (Checksum::Crc32, 8),
+        "adler32" => (Checksum::Adler32, 8),
+        "gitsha1" | "sha1git" => (Checksum::GitSha1, 40),
+        _ => return None,
+    };
+    Some(spec)
+}
+
+impl std::fmt::Display for Checksum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}: {})", self.algorithm(), self.value())
+    }
 }
 
 #[derive(Debug)]
@@ -427,11 +723,99 @@ pub trait DatasetBackend: Send + Sync + Any {
     async fn list(&self, client: &Client, dir: DirMeta) -> Result<Vec<Entry>, Exn<RepoError>>;
     fn root_url(&self) -> Url;
     fn as_any(&self) -> &dyn Any;
+
+    /// Short lowercase name of the repository, e.g. `zenodo` or `dataverse`, to label
+    /// downloads in logs and reports.
+    fn name(&self) -> &'static str {
+        "unknown"
+    }
+
+    /// Identifier of the record in its repository, e.g. the Zenodo record id or the
+    /// Dataverse persistent id. Defaults to the root url.
+    fn record_id(&self) -> String {
+        self.root_url().to_string()
+    }
+
+    /// URL of a server generated archive (ZIP) of the whole dataset, if the repository
+    /// provides one. Used by [`DownloadOptions::archive_mode`](crate::DownloadOptions::archive_mode).
+    fn archive_url(&self) -> Option<Url> {
+        None
+    }
+
+    /// Headers carrying the credential of `auth` for this repository, in the scheme the
+    /// repository expects. Empty by default and when `auth` has no credential for it.
+    fn auth_headers(&self, _auth: &AuthConfig) -> HeaderMap {
+        HeaderMap::new()
+    }
+
+    /// Descriptive metadata of the dataset (title, creators, license, ...) from the
+    /// repository's API.
+    ///
+    /// # Errors
+    /// Returns an error if the record cannot be fetched or parsed, and by default for
+    /// repositories that do not publish dataset level metadata.
+    async fn metadata(&self, _client: &Client) -> Result<DatasetMetadata, Exn<RepoError>> {
+        exn::bail!(RepoError {
+            message: format!("no dataset metadata available for '{}'", self.root_url()),
+        })
+    }
+
+    /// The record as returned by the repository's API, unmodified, for fields that
+    /// [`DatasetMetadata`] does not carry.
+    ///
+    /// # Errors
+    /// Returns an error if the record cannot be fetched or parsed, and by default for
+    /// repositories without a JSON API.
+    async fn raw_metadata(&self, _client: &Client) -> Result<serde_json::Value, Exn<RepoError>> {
+        exn::bail!(RepoError {
+            message: format!("no raw metadata available for '{}'", self.root_url()),
+        })
+    }
+
+    /// Conditions to meet before the files of the dataset can be downloaded with the
+    /// credentials of `auth`, e.g. gated access to request or an embargo, see
+    /// [`AccessRequirements`]. None by default.
+    ///
+    /// # Errors
+    /// Returns an error if the record cannot be fetched or parsed.
+    async fn access_requirements(
+        &self,
+        _client: &Client,
+        _auth: &AuthConfig,
+    ) -> Result<Vec<AccessRequirement>, Exn<RepoError>> {
+        Ok(Vec::new())
+    }
+
+    /// Backend of the version `version` of the dataset instead of the latest published
+    /// one, as numbered or named by the repository, e.g. `2` or `v1.2`.
+    ///
+    /// # Errors
+    /// Returns an error if the dataset has no such version, and by default for
+    /// repositories without versioned datasets.
+    async fn with_version(
+        &self,
+        _client: &Client,
+        version: &str,
+    ) -> Result<Arc<dyn DatasetBackend>, Exn<RepoError>> {
+        exn::bail!(RepoError {
+            message: format!(
+                "cannot select version '{version}', {} datasets are not versioned",
+                self.name()
+            ),
+        })
+    }
 }
 
 #[derive(Clone)]
 pub struct Dataset {
     pub backend: Arc<dyn DatasetBackend>,
+    pub auth: AuthConfig,
+    /// when the dataset was resolved.
+    pub resolved_at: SystemTime,
+    /// DOI the dataset was resolved from, e.g. `10.5281/zenodo.17867222`.
+    pub doi: Option<String>,
+    /// URL or DOI the dataset was resolved from, as passed to [`resolve`](crate::resolve).
+    pub resolved_from: Option<String>,
 }
 
 impl Dataset {
@@ -439,10 +823,300 @@ impl Dataset {
     pub fn new(backend: impl DatasetBackend) -> Self {
         Dataset {
             backend: Arc::new(backend),
+            auth: AuthConfig::default(),
+            resolved_at: SystemTime::now(),
+            doi: None,
+            resolved_from: None,
         }
     }
+
+    /// Replaces the backend of the dataset, e.g. by a subset of it, keeping its credentials
+    /// and where it was resolved from.
+    #[must_use]
+    pub fn with_backend(mut self, backend: impl DatasetBackend) -> Self {
+        self.backend = Arc::new(backend);
+        self
+    }
+
+    /// Sets the credentials used to access the dataset.
+    #[must_use]
+    pub fn with_auth(mut self, auth: AuthConfig) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// Sets the DOI identifying the dataset, recorded in reports and manifests.
+    #[must_use]
+    pub fn with_doi(mut self, doi: impl Into<String>) -> Self {
+        self.doi = Some(doi.into());
+        self
+    }
+
+    /// Sets the URL or DOI the dataset was resolved from, recorded in snapshots.
+    #[must_use]
+    pub fn with_resolved_from(mut self, input: impl Into<String>) -> Self {
+        self.resolved_from = Some(input.into());
+        self
+    }
+
+    /// Headers carrying the credential for the repository of the dataset, to be set as
+    /// default headers of the client used to crawl and download it.
+    ///
+    /// ```no_run
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// use datahugger::{resolve_with_auth, AuthConfig};
+    ///
+    /// let auth = AuthConfig::new().zenodo("my-access-token");
+    /// let ds = resolve_with_auth("https://zenodo.org/records/17867222", auth).await?;
+    /// let client = reqwest::ClientBuilder::new()
+    ///     .default_headers(ds.auth_headers())
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn auth_headers(&self) -> HeaderMap {
+        self.backend.auth_headers(&self.auth)
+    }
+    /// Fetches the descriptive metadata of the dataset, see [`DatasetBackend::metadata`].
+    ///
+    /// # Errors
+    /// Returns an error if the repository has no metadata for the dataset or the request
+    /// fails.
+    pub async fn metadata(&self, client: &Client) -> Result<DatasetMetadata, Exn<RepoError>> {
+        self.backend.metadata(client).await
+    }
+
+    /// Fetches the metadata of the dataset and formats it as citation in `style`, see
+    /// [`DatasetMetadata::citation`].
+    ///
+    /// # Errors
+    /// Returns an error if the repository has no metadata for the dataset or the request
+    /// fails.
+    pub async fn citation(
+        &self,
+        client: &Client,
+        style: CitationStyle,
+    ) -> Result<String, Exn<RepoError>> {
+        let metadata = self.metadata(client).await?;
+        Ok(metadata.citation(style, &self.backend.root_url()))
+    }
+
+    /// Fetches the unmodified API record of the dataset, see
+    /// [`DatasetBackend::raw_metadata`].
+    ///
+    /// # Errors
+    /// Returns an error if the repository has no JSON record for the dataset or the
+    /// request fails.
+    pub async fn raw_metadata(&self, client: &Client) -> Result<serde_json::Value, Exn<RepoError>> {
+        self.backend.raw_metadata(client).await
+    }
+
+    /// Fetches what must be obtained before the dataset can be downloaded, e.g. to fail
+    /// before downloading instead of on every restricted file, see
+    /// [`DatasetBackend::access_requirements`].
+    ///
+    /// # Errors
+    /// Returns an error if the request fails.
+    pub async fn access_requirements(
+        &self,
+        client: &Client,
+    ) -> Result<AccessRequirements, Exn<RepoError>> {
+        let requirements = self.backend.access_requirements(client, &self.auth).await?;
+        Ok(AccessRequirements { requirements })
+    }
+
+    /// Selects the version `version` of the dataset, e.g. the one cited in a paper, instead
+    /// of the latest published one, see [`DatasetBackend::with_version`].
+    ///
+    /// Supported for Dataverse (`2` or `2.1`), Dryad (version number) and Zenodo or other
+    /// InvenioRDM records (version name such as `v1.2`, or index of the version starting at
+    /// 1 within all versions of the record).
+    ///
+    /// # Errors
+    /// Returns an error if the repository has no such version or no versions at all.
+    pub async fn with_version(
+        mut self,
+        client: &Client,
+        version: &str,
+    ) -> Result<Dataset, Exn<RepoError>> {
+        self.backend = self.backend.with_version(client, version.trim()).await?;
+        Ok(self)
+    }
+
+    /// Name of the repository of the dataset, see [`DatasetBackend::name`].
+    #[must_use]
+    pub fn backend_name(&self) -> &'static str {
+        self.backend.name()
+    }
+
+    /// Identifier of the dataset in its repository, see [`DatasetBackend::record_id`].
+    #[must_use]
+    pub fn record_id(&self) -> String {
+        self.backend.record_id()
+    }
+
     #[must_use]
     pub fn root_dir(&self) -> DirMeta {
         DirMeta::new_root(&self.backend.root_url())
     }
+
+    /// Expands the placeholders of the destination `template`, e.g.
+    /// `./data/{repo}/{record_id}` to mirror many datasets without collisions:
+    ///
+    /// | Placeholder                 | Value                                          |
+    /// |-----------------------------|------------------------------------------------|
+    /// | `{repo}` (or `{repository}`) | [`Dataset::backend_name`]                     |
+    /// | `{record_id}`               | [`Dataset::record_id`]                         |
+    /// | `{doi}`                     | [`Dataset::doi`], the record id without DOI    |
+    /// | `{date}`                    | UTC date the dataset was resolved, `2025-01-31` |
+    ///
+    /// Characters of the values other than ascii alphanumerics, `.`, `-` and `_` are
+    /// replaced by `_`, so that each placeholder expands to a single path component.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use datahugger::{datasets::ZenodoJsonSrcDataset, Dataset};
+    ///
+    /// let ds = Dataset::new(ZenodoJsonSrcDataset::new("17867222", String::new()))
+    ///     .with_doi("10.5281/zenodo.17867222");
+    /// let dst = ds.destination("./data/{doi}");
+    /// assert_eq!(dst, std::path::Path::new("./data/10.5281_zenodo.17867222"));
+    /// ```
+    #[must_use]
+    pub fn destination(&self, template: &str) -> PathBuf {
+        let record_id = path_component(&self.record_id());
+        let doi = self
+            .doi
+            .as_deref()
+            .map_or_else(|| record_id.clone(), path_component);
+        let date = jiff::Timestamp::try_from(self.resolved_at)
+            .map(|resolved_at| resolved_at.to_zoned(jiff::tz::TimeZone::UTC).date())
+            .map_or_else(|_| String::new(), |date| date.to_string());
+        PathBuf::from(
+            template
+                .replace("{repo}", self.backend_name())
+                .replace("{repository}", self.backend_name())
+                .replace("{record_id}", &record_id)
+                .replace("{doi}", &doi)
+                .replace("{date}", &date),
+        )
+    }
+}
+
+/// `value` as a single path component, characters other than ascii alphanumerics, `.`,
+/// `-` and `_` are replaced by `_`.
+fn path_component(value: &str) -> String {
+    let component = value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    component.trim_matches('_').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datasets::ZenodoJsonSrcDataset;
+    use std::time::Duration;
+
+    #[test]
+    fn test_dataset_destination() {
+        let mut ds = Dataset::new(ZenodoJsonSrcDataset::new("a/b:c", String::new()));
+        // 2025-01-31T12:00:00Z
+        ds.resolved_at = SystemTime::UNIX_EPOCH + Duration::from_secs(1_738_324_800);
+        let repo = ds.backend_name();
+        assert_eq!(
+            ds.destination("/data/{repo}/{record_id}/{date}"),
+            Path::new(&format!("/data/{repo}/a_b_c/2025-01-31"))
+        );
+        // the record id without DOI
+        assert_eq!(ds.destination("{doi}"), Path::new("a_b_c"));
+        assert_eq!(ds.destination("./mirror"), Path::new("./mirror"));
+    }
+
+    #[test]
+    fn test_dataset_with_backend() {
+        let ds = Dataset::new(ZenodoJsonSrcDataset::new("1", String::new()))
+            .with_doi("10.5281/zenodo.1")
+            .with_resolved_from("https://zenodo.org/records/1")
+            .with_backend(ZenodoJsonSrcDataset::new("2", String::new()));
+        assert_eq!(ds.record_id(), "2");
+        assert_eq!(ds.doi.as_deref(), Some("10.5281/zenodo.1"));
+        assert_eq!(
+            ds.resolved_from.as_deref(),
+            Some("https://zenodo.org/records/1")
+        );
+    }
+
+    #[test]
+    fn test_checksum_parse() {
+        let md5 = "8d777f385d3dfec8815d20f7496026dc";
+        for (algo, value) in [
+            ("md5", md5.to_string()),
+            ("MD5", md5.to_uppercase()),
+            ("md5", format!("md5:{md5}")),
+            ("md5", format!(" {md5}\n")),
+        ] {
+            assert!(matches!(
+                Checksum::parse(algo, &value).unwrap(),
+                Checksum::Md5(h) if h == md5
+            ));
+        }
+        let sha1 = "a9993e364706816aba3e25717850c26c9cd0d89d";
+        assert!(matches!(
+            Checksum::parse("SHA-1", sha1).unwrap(),
+            Checksum::Sha1(_)
+        ));
+        assert!(matches!(
+            Checksum::parse("sha-256", &"0".repeat(64)).unwrap(),
+            Checksum::Sha256(_)
+        ));
+
+        // truncated, not hex, digest of another algorithm, unknown algorithm
+        assert!(Checksum::parse("md5", &md5[..30]).is_err());
+        assert!(Checksum::parse("md5", &"z".repeat(32)).is_err());
+        assert!(Checksum::parse("sha256", md5).is_err());
+        assert!(Checksum::parse("crc16", "bb3d").is_err());
+    }
+
+    #[test]
+    fn test_checksum_hasher() {
+        // digests of "123456789"
+        for (algo, value) in [
+            ("SHA-1", "f7c3bc1d808e04732adf679965ccc34ca7ae3441"),
+            (
+                "SHA-512",
+                "d9e6762dd1c8eaf6d61b3c6192fc408d4d6d5f1176d0c29169bc24e71c3f274ad27fcd5811b313d681f7e55ec02d73d499c95455b6b5bb503acf574fba8ffe85",
+            ),
+            (
+                "SHA-384",
+                "eb455d56d2c1a69de64e832011f3393d45f3fa31d6842f21af92d2fe469c499da5e3179847334a18479c8d1dedea1be3",
+            ),
+            ("crc32", "cbf43926"),
+            ("adler-32", "091e01de"),
+        ] {
+            let checksum = Checksum::parse(algo, value).unwrap();
+            let mut hasher = checksum.hasher(Some(9)).unwrap();
+            hasher.update(b"123456789");
+            assert_eq!(hex::encode(hasher.finalize()), checksum.value());
+        }
+        let blake2b = Checksum::parse("blake2b", &"0".repeat(128)).unwrap();
+        assert_eq!(blake2b.algorithm(), "blake2b");
+        assert_eq!(blake2b.hasher(None).unwrap().finalize().len(), 64);
+
+        // `git hash-object` of a file containing "hello world\n"
+        let git = Checksum::parse("git-sha1", "3b18e512dba79e4c8300dd08aeb37f8e728b8dad").unwrap();
+        assert!(git.hasher(None).is_none());
+        let mut hasher = git.hasher(Some(12)).unwrap();
+        hasher.update(b"hello world\n");
+        assert_eq!(hex::encode(hasher.finalize()), git.value());
+    }
 }
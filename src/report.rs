@@ -0,0 +1,310 @@
+use serde_json::{json, Value as JsonValue};
+use std::time::Duration;
+
+use url::Url;
+
+use crate::{Checksum, CrawlPath};
+
+/// Outcome of a single file in a [`DownloadReport`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileOutcome {
+    /// Downloaded, and both size and checksum match the repository metadata.
+    Verified,
+    /// Downloaded, and the checksum matches, but the repository lists no size, or a size of
+    /// 0 for a non-empty file, as several do for externally hosted files.
+    UnverifiedLength,
+    /// Downloaded, but the repository provides no checksum to validate against.
+    Unverified,
+    /// Downloaded without checksum from the repository, its sha256 computed while
+    /// downloading is recorded, see
+    /// [`DownloadOptions::self_checksum`](crate::DownloadOptions::self_checksum).
+    SelfComputed,
+    /// Not downloaded, with the reason.
+    Skipped(String),
+    /// Download or validation failed, with the error message.
+    Failed(String),
+    /// Would be downloaded, with the size listed by the repository, see
+    /// [`DownloadOptions::dry_run`](crate::DownloadOptions::dry_run).
+    Planned(Option<u64>),
+    /// Not downloaded, stored as a link to or copy of the identical file at the path, see
+    /// [`DownloadOptions::link_identical`](crate::DownloadOptions::link_identical).
+    Linked(CrawlPath),
+    /// Not downloaded, linked from the content already in the store, see
+    /// [`DownloadOptions::store`](crate::DownloadOptions::store).
+    Reused,
+    /// Download cancelled midway, its part file removed, see
+    /// [`DownloadOptions::cancel`](crate::DownloadOptions::cancel).
+    Cancelled,
+}
+
+impl FileOutcome {
+    /// Name of the outcome, e.g. `verified` or `skipped`, as in [`DownloadReport::stats`].
+    #[must_use]
+    pub fn label(&self) -> &'static str {
+        match self {
+            FileOutcome::Verified => "verified",
+            FileOutcome::UnverifiedLength => "unverified_length",
+            FileOutcome::Unverified => "unverified",
+            FileOutcome::SelfComputed => "self_computed",
+            FileOutcome::Skipped(_) => "skipped",
+            FileOutcome::Failed(_) => "failed",
+            FileOutcome::Planned(_) => "planned",
+            FileOutcome::Linked(_) => "linked",
+            FileOutcome::Reused => "reused",
+            FileOutcome::Cancelled => "cancelled",
+        }
+    }
+
+    /// Returns `true` if the file was downloaded, verified or not.
+    #[must_use]
+    pub fn is_downloaded(&self) -> bool {
+        matches!(
+            self,
+            FileOutcome::Verified
+                | FileOutcome::UnverifiedLength
+                | FileOutcome::Unverified
+                | FileOutcome::SelfComputed
+        )
+    }
+
+    /// Returns `true` if the file was downloaded and its checksum matches.
+    #[must_use]
+    pub fn is_checksum_verified(&self) -> bool {
+        matches!(self, FileOutcome::Verified | FileOutcome::UnverifiedLength)
+    }
+}
+
+/// Timing of the request of a single file, see [`FileReport::timing`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileTiming {
+    /// time from sending the request until the response headers, including connection setup,
+    /// redirects and retries.
+    pub response: Duration,
+    /// part of `response` spent opening connections, TLS handshakes included, `None` if
+    /// none was opened, e.g. one was reused, or the client was not built from
+    /// [`ClientOptions`](crate::ClientOptions).
+    pub connect: Option<Duration>,
+    /// time from sending the request until the first byte of the body, `None` for an empty
+    /// body.
+    pub first_byte: Option<Duration>,
+    /// number of times the request was sent again after a `Retry-After`.
+    pub retries: usize,
+}
+
+/// Record of how a single file was downloaded.
+#[derive(Debug, Clone)]
+pub struct FileReport {
+    /// relative crawl path of the file.
+    pub path: CrawlPath,
+    /// url the file is downloaded from.
+    pub download_url: Url,
+    /// checksum the downloaded file was verified against, `None` unless `Verified` or
+    /// `UnverifiedLength`, or the locally computed sha256 if `SelfComputed`.
+    pub checksum: Option<Checksum>,
+    /// bytes written to the destination, may be partial if the download failed.
+    pub bytes: u64,
+    /// wall time spent on the file.
+    pub elapsed: Duration,
+    /// timing of the request, `None` if the file was not requested on its own, e.g. skipped
+    /// before the request or extracted from the dataset archive.
+    pub timing: Option<FileTiming>,
+    pub outcome: FileOutcome,
+}
+
+impl FileReport {
+    /// Returns the error message if the file failed.
+    #[must_use]
+    pub fn error(&self) -> Option<&str> {
+        match &self.outcome {
+            FileOutcome::Failed(message) => Some(message),
+            _ => None,
+        }
+    }
+}
+
+/// Per-file summary of a dataset download, returned by
+/// [`DownloadExt::download_with_validation`](crate::DownloadExt::download_with_validation).
+///
+/// Files are listed in the order they finished.
+#[derive(Debug, Clone, Default)]
+pub struct DownloadReport {
+    pub files: Vec<FileReport>,
+    /// DOI of the downloaded dataset, see [`Dataset::doi`](crate::Dataset::doi).
+    pub doi: Option<String>,
+    /// `true` if the download was cancelled before every file was done, the files of the
+    /// report are then only those started, see
+    /// [`DownloadOptions::cancel`](crate::DownloadOptions::cancel).
+    pub cancelled: bool,
+}
+
+impl DownloadReport {
+    /// Returns `true` if no file failed and the download was not cancelled.
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        !self.cancelled && self.failed().next().is_none()
+    }
+
+    /// Iterates over the files that failed.
+    pub fn failed(&self) -> impl Iterator<Item = &FileReport> {
+        self.files
+            .iter()
+            .filter(|f| matches!(f.outcome, FileOutcome::Failed(_)))
+    }
+
+    /// Iterates over the files that were skipped.
+    pub fn skipped(&self) -> impl Iterator<Item = &FileReport> {
+        self.files
+            .iter()
+            .filter(|f| matches!(f.outcome, FileOutcome::Skipped(_)))
+    }
+
+    /// Iterates over the files that were downloaded, verified or not.
+    pub fn downloaded(&self) -> impl Iterator<Item = &FileReport> {
+        self.files.iter().filter(|f| f.outcome.is_downloaded())
+    }
+
+    /// Iterates over the files stored as a link to an identical downloaded file.
+    pub fn linked(&self) -> impl Iterator<Item = &FileReport> {
+        self.files
+            .iter()
+            .filter(|f| matches!(f.outcome, FileOutcome::Linked(_)))
+    }
+
+    /// Iterates over the files linked from the content store.
+    pub fn reused(&self) -> impl Iterator<Item = &FileReport> {
+        self.files
+            .iter()
+            .filter(|f| f.outcome == FileOutcome::Reused)
+    }
+
+    /// Iterates over the files a dry run would download.
+    pub fn planned(&self) -> impl Iterator<Item = &FileReport> {
+        self.files
+            .iter()
+            .filter(|f| matches!(f.outcome, FileOutcome::Planned(_)))
+    }
+
+    /// Total size of the files a dry run would download, of those with a known size.
+    #[must_use]
+    pub fn planned_bytes(&self) -> u64 {
+        self.files
+            .iter()
+            .filter_map(|f| match f.outcome {
+                FileOutcome::Planned(size) => size,
+                _ => None,
+            })
+            .sum()
+    }
+
+    /// Total bytes written to the destination.
+    #[must_use]
+    pub fn total_bytes(&self) -> u64 {
+        self.files.iter().map(|f| f.bytes).sum()
+    }
+
+    /// Per-file timing and bandwidth statistics as json, to benchmark repositories.
+    ///
+    /// Durations are in milliseconds and throughput in bytes per second, timing fields are
+    /// `null` for files without [`FileReport::timing`].
+    #[must_use]
+    pub fn stats(&self) -> JsonValue {
+        let millis = |d: Duration| d.as_secs_f64() * 1000.0;
+        let files: Vec<_> = self
+            .files
+            .iter()
+            .map(|file| {
+                let secs = file.elapsed.as_secs_f64();
+                json!({
+                    "path": file.path.as_str(),
+                    "host": file.download_url.host_str(),
+                    "outcome": file.outcome.label(),
+                    "bytes": file.bytes,
+                    "elapsed_ms": millis(file.elapsed),
+                    "response_ms": file.timing.map(|t| millis(t.response)),
+                    "connect_ms": file.timing.and_then(|t| t.connect).map(millis),
+                    "ttfb_ms": file.timing.and_then(|t| t.first_byte).map(millis),
+                    "throughput_bps": (secs > 0.0).then(|| file.bytes as f64 / secs),
+                    "retries": file.timing.map(|t| t.retries),
+                })
+            })
+            .collect();
+        json!({
+            "doi": self.doi,
+            "files": files,
+            "downloaded": self.downloaded().count(),
+            "skipped": self.skipped().count(),
+            "failed": self.failed().count(),
+            "planned": self.planned().count(),
+            "linked": self.linked().count(),
+            "reused": self.reused().count(),
+            "cancelled": self.cancelled,
+            "total_bytes": self.total_bytes(),
+            "retries": self.files.iter().filter_map(|f| f.timing).map(|t| t.retries).sum::<usize>(),
+        })
+    }
+}
+
+impl std::fmt::Display for DownloadReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.planned().next().is_some() {
+            let unknown = self
+                .planned()
+                .filter(|file| file.outcome == FileOutcome::Planned(None))
+                .count();
+            write!(
+                f,
+                "would download {} files ({} bytes",
+                self.planned().count(),
+                self.planned_bytes()
+            )?;
+            if unknown > 0 {
+                write!(f, ", {unknown} of unknown size")?;
+            }
+            writeln!(f, "), skipped {}", self.skipped().count())?;
+        } else {
+            if self.cancelled {
+                write!(f, "cancelled, ")?;
+            }
+            write!(
+                f,
+                "downloaded {} files ({} bytes), ",
+                self.downloaded().count(),
+                self.total_bytes()
+            )?;
+            let linked = self.linked().count();
+            if linked > 0 {
+                write!(f, "linked {linked}, ")?;
+            }
+            let reused = self.reused().count();
+            if reused > 0 {
+                write!(f, "reused {reused}, ")?;
+            }
+            writeln!(
+                f,
+                "skipped {}, failed {}",
+                self.skipped().count(),
+                self.failed().count(),
+            )?;
+        }
+        for file in &self.files {
+            match &file.outcome {
+                FileOutcome::Skipped(reason) => writeln!(f, "  skipped {}: {reason}", file.path)?,
+                FileOutcome::Failed(message) => writeln!(f, "  failed  {}: {message}", file.path)?,
+                FileOutcome::Planned(Some(size)) => {
+                    writeln!(f, "  {} ({size} bytes)", file.path)?;
+                }
+                FileOutcome::Planned(None) => writeln!(f, "  {} (unknown size)", file.path)?,
+                FileOutcome::Linked(original) => {
+                    writeln!(f, "  linked  {}: identical to {original}", file.path)?;
+                }
+                FileOutcome::Cancelled => writeln!(f, "  cancelled {}", file.path)?,
+                FileOutcome::Verified
+                | FileOutcome::UnverifiedLength
+                | FileOutcome::Unverified
+                | FileOutcome::SelfComputed
+                | FileOutcome::Reused => {}
+            }
+        }
+        Ok(())
+    }
+}
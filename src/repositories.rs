@@ -0,0 +1,242 @@
+use exn::{Exn, ResultExt};
+use serde_json::Value as JsonValue;
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+use tracing::warn;
+
+use crate::error::ErrorStatus;
+
+#[derive(Debug)]
+pub struct RepositoryConfigError {
+    pub message: String,
+    pub status: ErrorStatus,
+}
+
+impl std::fmt::Display for RepositoryConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "repository config fail: {}", self.message)
+    }
+}
+
+impl std::error::Error for RepositoryConfigError {}
+
+/// Repository installations known to the resolver besides its built-in domain lists, e.g.
+/// a Dataverse installation set up after the crate was released.
+///
+/// The config file lists the domains per repository software, in TOML:
+///
+/// ```toml
+/// dataone = ["data.example.org"]
+/// dataverse = ["dataverse.example.edu"]
+/// invenio = ["records.example.eu"]
+/// ```
+///
+/// or JSON with the same keys for files with the `.json` extension.
+///
+/// # Examples
+///
+/// ```
+/// use datahugger::RepositoryConfig;
+///
+/// let repositories = RepositoryConfig::new()
+///     .dataverse("dataverse.example.edu")
+///     .invenio("records.example.eu");
+/// assert!(!repositories.is_empty());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RepositoryConfig {
+    pub(crate) dataone: HashSet<String>,
+    pub(crate) dataverse: HashSet<String>,
+    pub(crate) invenio: HashSet<String>,
+}
+
+/// Domains at `key` of the parsed config, which must be an array of strings if present.
+fn domains(
+    value: Option<&JsonValue>,
+    key: &str,
+) -> Result<HashSet<String>, Exn<RepositoryConfigError>> {
+    let Some(value) = value else {
+        return Ok(HashSet::new());
+    };
+    let invalid = || RepositoryConfigError {
+        message: format!("'{key}' is not a list of domains"),
+        status: ErrorStatus::Permanent,
+    };
+    let items = value.as_array().ok_or_else(invalid)?;
+    items
+        .iter()
+        .map(|item| {
+            item.as_str()
+                .map(|domain| domain.trim().to_lowercase())
+                .ok_or_else(|| invalid().into())
+        })
+        .collect()
+}
+
+/// `datahugger` in the user config directory, `$XDG_CONFIG_HOME`, else `~/.config`, or
+/// `%APPDATA%` on Windows.
+pub(crate) fn user_config_dir() -> Option<PathBuf> {
+    let env_dir = |name: &str| {
+        std::env::var_os(name)
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+    };
+    let config_dir = env_dir("XDG_CONFIG_HOME")
+        .or_else(|| env_dir("HOME").map(|home| home.join(".config")))
+        .or_else(|| env_dir("APPDATA"))?;
+    Some(config_dir.join("datahugger"))
+}
+
+impl RepositoryConfig {
+    /// Creates a config without any additional installation.
+    #[must_use]
+    pub fn new() -> Self {
+        RepositoryConfig::default()
+    }
+
+    /// Returns `true` if no installation is added.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.dataone.is_empty() && self.dataverse.is_empty() && self.invenio.is_empty()
+    }
+
+    /// Adds the domain of a DataONE member node.
+    #[must_use]
+    pub fn dataone(mut self, domain: impl Into<String>) -> Self {
+        self.dataone.insert(domain.into().to_lowercase());
+        self
+    }
+
+    /// Adds the domain of a Dataverse installation.
+    #[must_use]
+    pub fn dataverse(mut self, domain: impl Into<String>) -> Self {
+        self.dataverse.insert(domain.into().to_lowercase());
+        self
+    }
+
+    /// Adds the domain of an InvenioRDM installation, downloaded with the Zenodo backend.
+    #[must_use]
+    pub fn invenio(mut self, domain: impl Into<String>) -> Self {
+        self.invenio.insert(domain.into().to_lowercase());
+        self
+    }
+
+    /// Parses a TOML config, see [`RepositoryConfig`] for the format.
+    ///
+    /// # Errors
+    /// Returns an error if `content` is not valid TOML or a key is not a list of domains.
+    pub fn from_toml(content: &str) -> Result<Self, Exn<RepositoryConfigError>> {
+        let table = content
+            .parse::<toml::Table>()
+            .or_raise(|| RepositoryConfigError {
+                message: "invalid TOML".to_string(),
+                status: ErrorStatus::Permanent,
+            })?;
+        // Safe to unwrap: a TOML table always converts to json
+        let json = serde_json::to_value(table).unwrap();
+        Self::from_json_value(&json)
+    }
+
+    /// Parses a JSON config, see [`RepositoryConfig`] for the format.
+    ///
+    /// # Errors
+    /// Returns an error if `content` is not valid JSON or a key is not a list of domains.
+    pub fn from_json(content: &str) -> Result<Self, Exn<RepositoryConfigError>> {
+        let json: JsonValue = serde_json::from_str(content).or_raise(|| RepositoryConfigError {
+            message: "invalid JSON".to_string(),
+            status: ErrorStatus::Permanent,
+        })?;
+        Self::from_json_value(&json)
+    }
+
+    pub(crate) fn from_json_value(json: &JsonValue) -> Result<Self, Exn<RepositoryConfigError>> {
+        Ok(RepositoryConfig {
+            dataone: domains(json.get("dataone"), "dataone")?,
+            dataverse: domains(json.get("dataverse"), "dataverse")?,
+            invenio: domains(json.get("invenio"), "invenio")?,
+        })
+    }
+
+    /// Adds the installations of `other`.
+    #[must_use]
+    pub fn extend(mut self, other: RepositoryConfig) -> Self {
+        self.dataone.extend(other.dataone);
+        self.dataverse.extend(other.dataverse);
+        self.invenio.extend(other.invenio);
+        self
+    }
+
+    /// Reads the config file at `path`, parsed as JSON if it has the `.json` extension and as
+    /// TOML otherwise.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or parsed.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Exn<RepositoryConfigError>> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).or_raise(|| RepositoryConfigError {
+            message: format!("cannot read '{}'", path.display()),
+            status: ErrorStatus::Permanent,
+        })?;
+        let config = if path.extension().is_some_and(|ext| ext == "json") {
+            Self::from_json(&content)
+        } else {
+            Self::from_toml(&content)
+        };
+        config.or_raise(|| RepositoryConfigError {
+            message: format!("cannot parse '{}'", path.display()),
+            status: ErrorStatus::Permanent,
+        })
+    }
+
+    /// Default location of the config file, `datahugger/repositories.toml` in the user
+    /// config directory (`$XDG_CONFIG_HOME`, else `~/.config`, or `%APPDATA%` on Windows).
+    #[must_use]
+    pub fn default_path() -> Option<PathBuf> {
+        Some(user_config_dir()?.join("repositories.toml"))
+    }
+
+    /// Reads the config file at [`RepositoryConfig::default_path`], empty if there is none.
+    ///
+    /// An invalid config file is ignored with a warning.
+    #[must_use]
+    pub fn load() -> Self {
+        let Some(path) = Self::default_path().filter(|path| path.is_file()) else {
+            return RepositoryConfig::default();
+        };
+        match Self::from_file(&path) {
+            Ok(config) => config,
+            Err(err) => {
+                warn!("ignore repository config: {err:?}");
+                RepositoryConfig::default()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repository_config_parse() {
+        let config = RepositoryConfig::from_toml(
+            r#"
+            dataverse = ["Dataverse.Example.edu"]
+            invenio = ["records.example.eu", "data.example.eu"]
+            "#,
+        )
+        .unwrap();
+        assert!(config.dataone.is_empty());
+        assert!(config.dataverse.contains("dataverse.example.edu"));
+        assert_eq!(config.invenio.len(), 2);
+
+        let config = RepositoryConfig::from_json(r#"{"dataone": ["data.example.org"]}"#).unwrap();
+        assert!(config.dataone.contains("data.example.org"));
+
+        assert!(RepositoryConfig::from_toml("dataverse = \"dataverse.example.edu\"").is_err());
+        assert!(RepositoryConfig::from_toml("dataverse = [1]").is_err());
+    }
+}
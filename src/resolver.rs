@@ -1,19 +1,27 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{collections::HashMap, str::FromStr, time::Duration};
 
 use exn::{Exn, OptionExt, ResultExt};
+use percent_encoding::percent_decode_str;
 use reqwest::{
-    header::{HeaderMap, HeaderValue, AUTHORIZATION, USER_AGENT},
-    ClientBuilder,
+    header::{HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION, LOCATION},
+    redirect::Policy,
+    StatusCode,
 };
 use serde_json::Value as JsonValue;
+use tracing::debug;
 use url::Url;
 
 use crate::{
     datasets::{
-        Arxiv, DataDryad, Dataone, DataverseDataset, DataverseFile, GitHub, HalScience,
-        HuggingFace, Zenodo, OSF,
+        dataverse_version_name, Arxiv, DataDryad, Dataone, DataverseDataset, DataverseFile, GitHub,
+        GitHubRelease, HalScience, HuggingFace, Pangaea, S3Public, SoftwareHeritage, Thredds,
+        Zenodo, OSF, PANGAEA_URL, SOFTWARE_HERITAGE_URL,
     },
+    http::get_json,
+    metadata::normalize_doi,
+    redirect::DEFAULT_MAX_HOPS,
     repo::Dataset,
+    AuthConfig, ClientOptions, DatasetMetadata, RepositoryConfig,
 };
 
 use crate::helper::json_extract;
@@ -63,7 +71,6 @@ static DATAONE_DOMAINS: LazyLock<HashSet<&'static str>> = LazyLock::new(|| {
         "get.iedadata.org",
         "usap-dc.org",
         "iys.hakai.org",
-        "doi.pangaea.de",
         "rvdata.us",
         "sead-published.ncsa.illinois.edu",
     ])
@@ -72,6 +79,19 @@ static DATAONE_DOMAINS: LazyLock<HashSet<&'static str>> = LazyLock::new(|| {
 static HAL_DOMAINS: LazyLock<HashSet<&'static str>> =
     LazyLock::new(|| HashSet::from(["hal.science", "inrae.fr"]));
 
+/// InvenioRDM installations, Zenodo being the largest one, all serving the Zenodo API.
+static INVENIO_DOMAINS: LazyLock<HashSet<&'static str>> = LazyLock::new(|| {
+    HashSet::from([
+        "zenodo.org",
+        "sandbox.zenodo.org",
+        "data.caltech.edu",
+        "rodare.hzdr.de",
+        "researchdata.tuwien.ac.at",
+        "repository.tugraz.at",
+        "fdat.uni-tuebingen.de",
+    ])
+});
+
 static DATAVERSE_DOMAINS: LazyLock<HashSet<&'static str>> = LazyLock::new(|| {
     HashSet::from([
         "www.march.es",
@@ -158,53 +178,49 @@ static DATAVERSE_DOMAINS: LazyLock<HashSet<&'static str>> = LazyLock::new(|| {
 async fn github_get_default_branch_commit(
     owner: &str,
     repo: &str,
+    token: Option<&str>,
+    client_options: &ClientOptions,
 ) -> Result<String, Exn<DispatchError>> {
-    // TODO: don't panic, and wrap client.get as client.get_json() to be used everywhere.
-    let user_agent = format!("datahugger-cli/{}", env!("CARGO_PKG_VERSION"));
     let mut headers = HeaderMap::new();
-    if let Ok(token) = std::env::var("GITHUB_TOKEN") {
-        headers.insert(
-            AUTHORIZATION,
-            HeaderValue::from_str(&format!("token {token}")).unwrap(),
-        );
+    if let Some(token) = token {
+        let value =
+            HeaderValue::from_str(&format!("token {token}")).or_raise(|| DispatchError {
+                message: "GitHub token is not a valid header value".to_string(),
+            })?;
+        headers.insert(AUTHORIZATION, value);
     }
-    headers.insert(USER_AGENT, HeaderValue::from_str(&user_agent).unwrap());
-    let client = ClientBuilder::new()
-        .user_agent(&user_agent)
+    let client = client_options
+        .builder()
         .default_headers(headers)
-        .use_native_tls()
         .build()
-        .unwrap();
-    let repo_url = format!("https://api.github.com/repos/{owner}/{repo}");
-    let resp: JsonValue = client
-        .get(&repo_url)
-        .send()
-        .await
-        .unwrap()
-        .error_for_status()
-        .unwrap()
-        .json()
+        .or_raise(|| DispatchError {
+            message: "fail to build the http client for GitHub".to_string(),
+        })?;
+    // Safe to unwrap:
+    // - the base URL is a hard-coded, valid absolute URL
+    // - `path_segments_mut` cannot fail for this URL scheme
+    let mut repo_url = Url::from_str("https://api.github.com/repos").unwrap();
+    repo_url.path_segments_mut().unwrap().extend([owner, repo]);
+    let resp = get_json(&client, &repo_url)
         .await
-        .unwrap();
+        .or_raise(|| DispatchError {
+            message: format!("not able to get the GitHub repository '{owner}/{repo}'"),
+        })?;
     let default_branch: String =
         json_extract(&resp, "default_branch").map_err(|_| DispatchError {
             message: "not able to get default branch".to_string(),
         })?;
 
-    let commits_url =
-        format!("https://api.github.com/repos/{owner}/{repo}/commits/{default_branch}");
-
-    let resp: JsonValue = client
-        .get(&commits_url)
-        .header("User-Agent", user_agent.clone())
-        .send()
-        .await
-        .unwrap()
-        .error_for_status()
+    let mut commits_url = repo_url;
+    commits_url
+        .path_segments_mut()
         .unwrap()
-        .json()
+        .extend(["commits", &default_branch]);
+    let resp = get_json(&client, &commits_url)
         .await
-        .unwrap();
+        .or_raise(|| DispatchError {
+            message: format!("not able to get the commit of branch '{default_branch}'"),
+        })?;
     let commit_sha: String = json_extract(&resp, "sha").map_err(|_| DispatchError {
         message: "not able to get default branch".to_string(),
     })?;
@@ -305,6 +321,215 @@ pub async fn resolve_doi_to_url(
     resolve_doi_to_url_with_base(client, doi, None, follow_redirects).await
 }
 
+/// A DOI resolved to its landing page by [`resolve_doi_redirects`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DoiRedirects {
+    /// Landing page at the end of the redirect chain.
+    pub url: String,
+    /// URLs requested, from the URL registered for the DOI to [`DoiRedirects::url`].
+    pub chain: Vec<String>,
+}
+
+async fn resolve_doi_redirects_with_base(
+    client: &reqwest::Client,
+    doi: &str,
+    base_url: Option<&str>,
+    max_hops: usize,
+) -> Result<DoiRedirects, Exn<ResolveError>> {
+    let mut url = resolve_doi_to_url_with_base(client, doi, base_url, false).await?;
+    let mut chain = vec![url.clone()];
+    loop {
+        let mut resp = client.head(&url).send().await.or_raise(|| ResolveError {
+            message: format!("failed to follow redirect for '{url}'"),
+        })?;
+        // some landing pages do not answer HEAD
+        if matches!(
+            resp.status(),
+            StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED
+        ) {
+            resp = client.get(&url).send().await.or_raise(|| ResolveError {
+                message: format!("failed to follow redirect for '{url}'"),
+            })?;
+        }
+        // redirects the client followed itself
+        if resp.url().as_str() != url {
+            url = resp.url().to_string();
+            chain.push(url.clone());
+        }
+        let location = resp
+            .status()
+            .is_redirection()
+            .then(|| resp.headers().get(LOCATION))
+            .flatten()
+            .and_then(|location| location.to_str().ok())
+            .and_then(|location| resp.url().join(location).ok());
+        let Some(location) = location else {
+            return Ok(DoiRedirects { url, chain });
+        };
+        if chain.iter().any(|visited| *visited == location.as_str()) {
+            exn::bail!(ResolveError {
+                message: format!("redirect loop resolving '{doi}' at '{location}'")
+            });
+        }
+        if chain.len() > max_hops {
+            exn::bail!(ResolveError {
+                message: format!("more than {max_hops} redirects resolving '{doi}'")
+            });
+        }
+        url = location.to_string();
+        chain.push(url.clone());
+    }
+}
+
+/// Resolves `doi` to its landing page, following the redirects from the URL registered
+/// for it, e.g. through a handle service to a repository record, for up to `max_hops`
+/// redirects.
+///
+/// The redirects are followed one by one, so `client` should not follow them itself,
+/// build it with [`reqwest::redirect::Policy::none`]. The returned
+/// [`DoiRedirects::chain`] records each URL on the way.
+///
+/// # Errors
+///
+/// Returns an error if the DOI cannot be resolved, a request fails, the redirects loop or
+/// there are more than `max_hops` of them.
+pub async fn resolve_doi_redirects(
+    client: &reqwest::Client,
+    doi: &str,
+    max_hops: usize,
+) -> Result<DoiRedirects, Exn<ResolveError>> {
+    resolve_doi_redirects_with_base(client, doi, None, max_hops).await
+}
+
+/// Media type of CSL-JSON, the citation metadata all DOI registration agencies serve.
+const CSL_JSON: &str = "application/vnd.citationstyles.csl+json";
+
+async fn resolve_doi_metadata_with_base(
+    client: &reqwest::Client,
+    doi: &str,
+    base_url: &str,
+) -> Result<DatasetMetadata, Exn<ResolveError>> {
+    let doi = normalize_doi(doi);
+    if !(doi.starts_with("10.") && doi.contains('/')) {
+        exn::bail!(ResolveError {
+            message: format!("Invalid DOI: '{doi}'"),
+        });
+    }
+    let resp = client
+        .get(format!("{base_url}/{doi}"))
+        .header(ACCEPT, CSL_JSON)
+        .send()
+        .await
+        .or_raise(|| ResolveError {
+            message: format!("failed to fetch the metadata of '{doi}'"),
+        })?;
+    let status = resp.status();
+    if !status.is_success() {
+        exn::bail!(ResolveError {
+            message: format!("failed to fetch the metadata of '{doi}': status {status}")
+        });
+    }
+    let csl: JsonValue = resp.json().await.or_raise(|| ResolveError {
+        message: format!("metadata of '{doi}' are not CSL-JSON"),
+    })?;
+    Ok(DatasetMetadata::from_csl_json(&csl))
+}
+
+/// Fetches the citation metadata of `doi` with DOI content negotiation: doi.org forwards
+/// the request for CSL-JSON to the registration agency of the DOI, e.g. DataCite or
+/// Crossref. Works for any DOI, also of datasets in repositories datahugger cannot
+/// download from.
+///
+/// Format a citation of the returned metadata with [`DatasetMetadata::citation`].
+///
+/// # Errors
+///
+/// Returns an error if the DOI is invalid or unknown, or its agency answers no CSL-JSON.
+pub async fn resolve_doi_metadata(
+    client: &reqwest::Client,
+    doi: &str,
+) -> Result<DatasetMetadata, Exn<ResolveError>> {
+    resolve_doi_metadata_with_base(client, doi, "https://doi.org").await
+}
+
+/// DOI of `input` if it is a bare DOI, `10.5281/zenodo.17867222`, prefixed by `doi:` or a
+/// `doi.org` url.
+fn doi_of(input: &str) -> Option<String> {
+    let input = input.trim();
+    let doi = match Url::from_str(input) {
+        Ok(url)
+            if matches!(
+                url.host_str(),
+                Some("doi.org" | "dx.doi.org" | "www.doi.org")
+            ) =>
+        {
+            percent_decode_str(url.path().trim_start_matches('/'))
+                .decode_utf8_lossy()
+                .into_owned()
+        }
+        _ => input.strip_prefix("doi:").unwrap_or(input).to_string(),
+    };
+    (doi.starts_with("10.") && doi.contains('/')).then_some(doi)
+}
+
+/// Landing url of a DOI of a repository whose landing urls are derived from the DOI, saving
+/// the round trip to doi.org.
+fn known_doi_url(doi: &str) -> Option<String> {
+    let (prefix, suffix) = doi.split_once('/')?;
+    match prefix {
+        // 10.5281/zenodo.17867222
+        "10.5281" => {
+            let id = suffix
+                .strip_prefix("zenodo.")
+                .or_else(|| suffix.strip_prefix("ZENODO."))?;
+            Some(format!("https://zenodo.org/records/{id}"))
+        }
+        // 10.5061/dryad.mj8m0
+        "10.5061" => Some(format!("https://datadryad.org/dataset/doi:{doi}")),
+        // 10.7910/DVN/KBHLOD, or a file of it 10.7910/DVN/KBHLOD/DHJ45U
+        "10.7910" => {
+            let page = match suffix.split('/').count() {
+                2 => "dataset",
+                3 => "file",
+                _ => return None,
+            };
+            Some(format!(
+                "https://dataverse.harvard.edu/{page}.xhtml?persistentId=doi:{doi}"
+            ))
+        }
+        // 10.1594/PANGAEA.942110
+        "10.1594" => Some(format!("https://doi.pangaea.de/{doi}")),
+        _ => None,
+    }
+}
+
+/// Landing url of `url`, resolved through doi.org if it is a DOI, else `url` itself.
+async fn landing_url(
+    url: &str,
+    client_options: &ClientOptions,
+) -> Result<String, Exn<DispatchError>> {
+    let Some(doi) = doi_of(url) else {
+        return Ok(url.to_string());
+    };
+    if let Some(url) = known_doi_url(&doi) {
+        return Ok(url);
+    }
+    let client = client_options
+        .builder()
+        .redirect(Policy::none())
+        .build()
+        .or_raise(|| DispatchError {
+            message: "fail to build client to resolve DOI".to_string(),
+        })?;
+    let redirects = resolve_doi_redirects(&client, &doi, DEFAULT_MAX_HOPS)
+        .await
+        .or_raise(|| DispatchError {
+            message: format!("cannot resolve DOI '{doi}' to a landing url"),
+        })?;
+    debug!("resolved DOI '{doi}' through {:?}", redirects.chain);
+    Ok(redirects.url)
+}
+
 /// Resolves a dataset URL into a [`Dataset`] by dispatching based on the
 /// URL's domain and structure.
 ///
@@ -312,11 +537,20 @@ pub async fn resolve_doi_to_url(
 /// (e.g., DataONE, Dataverse, arXiv, Hugging Face, Zenodo, GitHub, etc.).
 /// The resolution strategy depends on the domain and expected URL format.
 ///
+/// DOIs, bare (`10.5281/zenodo.17867222`), prefixed by `doi:` or as `doi.org` url, are
+/// resolved to their landing page first, following the redirects from their registered
+/// URL with [`resolve_doi_redirects`]. DOIs of Zenodo, Dryad, Harvard Dataverse and
+/// Pangaea are mapped to their landing url without requesting doi.org.
+///
+/// The DOI, given or found in the `persistentId` of a Dataverse url, is recorded as
+/// [`Dataset::doi`].
+///
 /// # Errors
 ///
 /// Returns an [`Exn<DispatchError>`] if:
 ///
 /// - The input string is not a valid URL.
+/// - The input is a DOI that doi.org cannot resolve.
 /// - Required URL components (e.g., domain, host, or path segments) are missing.
 /// - The URL structure does not match the expected format for a supported provider
 ///   (e.g., missing identifiers like `doi`, `persistentId`, repository info, etc.).
@@ -330,6 +564,9 @@ pub async fn resolve_doi_to_url(
 /// This function may panic for domains that are explicitly marked as
 /// unimplemented.
 ///
+/// The credentials are read from the environment, see [`AuthConfig::from_env`], use
+/// [`resolve_with_auth`] to pass them explicitly.
+///
 /// # Examples
 ///
 /// ```no_run
@@ -338,11 +575,348 @@ pub async fn resolve_doi_to_url(
 /// # Ok(())
 /// # }
 /// ```
-#[allow(clippy::too_many_lines)]
 pub async fn resolve(url: &str) -> Result<Dataset, Exn<DispatchError>> {
+    resolve_with_auth(url, AuthConfig::from_env()).await
+}
+
+/// Resolves a dataset URL like [`resolve`], attaching `auth` to the returned [`Dataset`].
+///
+/// The credentials are also used while resolving, e.g. to look up the default branch of a
+/// GitHub repository.
+///
+/// Repository installations of the user config file are recognized besides the built-in
+/// ones, see [`RepositoryConfig::load`].
+///
+/// # Errors
+///
+/// see [`resolve`].
+pub async fn resolve_with_auth(url: &str, auth: AuthConfig) -> Result<Dataset, Exn<DispatchError>> {
+    resolve_with_repositories(url, auth, &RepositoryConfig::load()).await
+}
+
+/// Resolves a dataset URL like [`resolve_with_auth`], recognizing the DataONE, Dataverse
+/// and InvenioRDM installations of `repositories` besides the built-in ones.
+///
+/// # Errors
+///
+/// see [`resolve`].
+pub async fn resolve_with_repositories(
+    url: &str,
+    auth: AuthConfig,
+    repositories: &RepositoryConfig,
+) -> Result<Dataset, Exn<DispatchError>> {
+    resolve_with_options(url, auth, repositories, &ClientOptions::default()).await
+}
+
+/// Resolves a dataset URL like [`resolve_with_repositories`], sending the requests made
+/// while resolving, e.g. to doi.org or to probe an unknown host, with `client_options`.
+///
+/// # Errors
+///
+/// see [`resolve`].
+pub async fn resolve_with_options(
+    url: &str,
+    auth: AuthConfig,
+    repositories: &RepositoryConfig,
+    client_options: &ClientOptions,
+) -> Result<Dataset, Exn<DispatchError>> {
+    let doi = doi_of(url);
+    let input = url.trim().to_string();
+    let url = landing_url(url, client_options).await?;
+    let doi = doi.or_else(|| persistent_doi(&url));
+    let dataset = dispatch(&url, &auth, repositories, client_options)
+        .await?
+        .with_auth(auth)
+        .with_resolved_from(input);
+    match doi {
+        Some(doi) => Ok(dataset.with_doi(doi)),
+        None => Ok(dataset),
+    }
+}
+
+/// DOI in the `persistentId` of a Dataverse landing url, e.g.
+/// `https://dataverse.harvard.edu/dataset.xhtml?persistentId=doi:10.7910/DVN/KBHLOD`.
+fn persistent_doi(url: &str) -> Option<String> {
+    let url = Url::from_str(url).ok()?;
+    let (_, id) = url.query_pairs().find(|(key, _)| key == "persistentId")?;
+    doi_of(id.strip_prefix("doi:")?)
+}
+
+/// Query parameters added by browsers, newsletters and social media to track where a visit
+/// comes from, they are never part of a dataset identifier. `ref` is kept, it selects the
+/// branch or tag on GitHub and GitLab.
+fn is_tracking_param(key: &str) -> bool {
+    key.starts_with("utm_")
+        || matches!(
+            key,
+            "ref_src" | "fbclid" | "gclid" | "msclkid" | "mc_cid" | "mc_eid" | "_ga"
+        )
+}
+
+/// Strips the fragment and the tracking query parameters of a landing url copied from a
+/// browser, e.g. `https://zenodo.org/records/17867222?utm_source=x#files`.
+fn normalize_url(mut url: Url) -> Url {
+    url.set_fragment(None);
+    if url.query().is_some() {
+        let pairs: Vec<(String, String)> = url
+            .query_pairs()
+            .filter(|(key, _)| !is_tracking_param(key))
+            .map(|(key, value)| (key.into_owned(), value.into_owned()))
+            .collect();
+        if pairs.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(pairs);
+        }
+    }
+    url
+}
+
+fn software_heritage_dataset(url: &Url) -> Result<Dataset, Exn<DispatchError>> {
+    // Safe to unwrap: the url is a hard-coded, valid absolute URL
+    let base_url = Url::from_str(SOFTWARE_HERITAGE_URL).unwrap();
+    let swhid = if url.scheme() == "swh" {
+        url.as_str().to_string()
+    } else {
+        let path = percent_decode_str(url.path()).decode_utf8_lossy();
+        let path = path.trim_start_matches('/');
+        match path.split_once('/') {
+            Some(("browse", rest)) => {
+                // browse/<object type>/<sha1_git>/
+                let mut segments = rest.split('/');
+                let object_type = match segments.next() {
+                    Some("content") => "cnt",
+                    Some("directory") => "dir",
+                    Some("revision") => "rev",
+                    Some("release") => "rel",
+                    Some("snapshot") => "snp",
+                    _ => "",
+                };
+                let id = segments.next().unwrap_or_default();
+                let id = id.strip_prefix("sha1_git:").unwrap_or(id);
+                format!("swh:1:{object_type}:{id}")
+            }
+            _ => path.to_string(),
+        }
+    };
+    let swh = SoftwareHeritage::from_swhid(&swhid, &base_url).ok_or_raise(|| DispatchError {
+        message: format!(
+            "no valid SWHID of a content, directory, revision, release or snapshot in '{url}'"
+        ),
+    })?;
+    Ok(Dataset::new(swh))
+}
+
+fn s3_dataset(url: &Url) -> Result<Option<Dataset>, Exn<DispatchError>> {
+    let host = url.host_str().unwrap_or_default();
+    let path = percent_decode_str(url.path())
+        .decode_utf8()
+        .or_raise(|| DispatchError {
+            message: format!("path of '{url}' is not valid utf-8"),
+        })?;
+    let path = path.trim_start_matches('/');
+
+    if url.scheme() == "s3" {
+        if host.is_empty() {
+            exn::bail!(DispatchError {
+                message: format!("no bucket in '{url}'")
+            });
+        }
+        return Ok(Some(Dataset::new(S3Public::aws(host, path))));
+    }
+    let Some(rest) = host.strip_suffix(".amazonaws.com") else {
+        return Ok(None);
+    };
+    if rest.starts_with("s3.") || rest.starts_with("s3-") || rest == "s3" {
+        // path style, bucket is the first segment
+        let (bucket, prefix) = path.split_once('/').unwrap_or((path, ""));
+        if bucket.is_empty() {
+            exn::bail!(DispatchError {
+                message: format!("no bucket in '{url}'")
+            });
+        }
+        let bucket_url = format!("{}://{host}/{bucket}/", url.scheme());
+        let bucket_url = Url::from_str(&bucket_url).or_raise(|| DispatchError {
+            message: format!("'{bucket_url}' is not valid url"),
+        })?;
+        return Ok(Some(Dataset::new(S3Public::new(&bucket_url, prefix))));
+    }
+    if rest.contains(".s3.") || rest.contains(".s3-") || rest.ends_with(".s3") {
+        // virtual hosted style, bucket is part of the host
+        let bucket_url = format!("{}://{host}/", url.scheme());
+        let bucket_url = Url::from_str(&bucket_url).or_raise(|| DispatchError {
+            message: format!("'{bucket_url}' is not valid url"),
+        })?;
+        return Ok(Some(Dataset::new(S3Public::new(&bucket_url, path))));
+    }
+    Ok(None)
+}
+
+/// Dataverse dataset or file of a landing url of any Dataverse installation.
+fn dataverse_dataset(url: &Url) -> Result<Dataset, Exn<DispatchError>> {
+    // https://dataverse.harvard.edu/dataset.xhtml?persistentId=doi:10.7910/DVN/KBHLOD
+    // https://dataverse.harvard.edu/file.xhtml?persistentId=doi:10.7910/DVN/KBHLOD/JCJCJC
+    let mut segments = url.path_segments().ok_or_else(|| DispatchError {
+        message: format!("'{url}' cannot be base"),
+    })?;
+    let typ = segments.next().ok_or_else(|| DispatchError {
+        message: format!("'{url}' no segments found"),
+    })?;
+    let queries = url.query_pairs();
+    let queries = queries.collect::<HashMap<_, _>>();
+    let Some(id) = queries.get("persistentId") else {
+        exn::bail!(DispatchError {
+            message: "query don't contains 'persistentId'".to_string()
+        })
+    };
+
+    let typ = typ.strip_suffix(".xhtml").ok_or_else(|| DispatchError {
+        message: "segment not in format *.xhtml".to_string(),
+    })?;
+    let base_url = origin_url(url);
+    // landing urls of a former version carry it, e.g. `&version=2.0`
+    let version = queries.get("version").map_or_else(
+        || ":latest-published".to_string(),
+        |v| dataverse_version_name(v),
+    );
+    match typ {
+        "dataset" => {
+            let dataset = Dataset::new(DataverseDataset::new(id.as_ref(), &base_url, &version));
+            Ok(dataset)
+        }
+        "file" => {
+            let dataset = Dataset::new(DataverseFile::new(id.as_ref(), &base_url, &version));
+            Ok(dataset)
+        }
+        ty => exn::bail!(DispatchError {
+            message: format!("{ty} is not valid type, can only be 'dataset' or 'file'")
+        }),
+    }
+}
+
+/// Whether `url` has the shape of a Dataverse landing url,
+/// `/dataset.xhtml?persistentId=...` or `/file.xhtml?persistentId=...`.
+fn has_dataverse_shape(url: &Url) -> bool {
+    let page = url.path_segments().and_then(|mut segments| segments.next());
+    matches!(page, Some("dataset.xhtml" | "file.xhtml"))
+        && url.query_pairs().any(|(key, _)| key == "persistentId")
+}
+
+/// Landing url of another Dataverse url carrying a `persistentId`, e.g.
+/// `/citation?persistentId=...`, `/api/datasets/:persistentId/?persistentId=...` or
+/// `/dataset.xhtml;jsessionid=...?persistentId=...`, `None` without a `persistentId`.
+fn dataverse_landing_url(url: &Url) -> Option<Url> {
+    let queries = url.query_pairs().collect::<HashMap<_, _>>();
+    let id = queries.get("persistentId")?;
+    let is_file = url
+        .path_segments()?
+        .any(|seg| seg.starts_with("file.xhtml") || matches!(seg, "files" | "datafile"));
+    let mut landing_url = origin_url(url);
+    landing_url.set_path(if is_file {
+        "file.xhtml"
+    } else {
+        "dataset.xhtml"
+    });
+    landing_url
+        .query_pairs_mut()
+        .append_pair("persistentId", id);
+    if let Some(version) = queries.get("version") {
+        landing_url
+            .query_pairs_mut()
+            .append_pair("version", version);
+    }
+    Some(landing_url)
+}
+
+/// Root url of the host of `url`, keeping its port, e.g. `https://data.caltech.edu/`.
+fn origin_url(url: &Url) -> Url {
+    let mut origin = url.clone();
+    origin.set_path("/");
+    origin.set_query(None);
+    origin.set_fragment(None);
+    origin
+}
+
+/// JSON at `path` of `base_url`, `None` on any failure, to probe the API of an unknown host.
+async fn probe_json(
+    base_url: &Url,
+    path: &str,
+    client_options: &ClientOptions,
+) -> Option<JsonValue> {
+    let url = base_url.join(path).ok()?;
+    let client = client_options
+        .builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .ok()?;
+    let resp = client.get(url).send().await.ok()?;
+    resp.error_for_status().ok()?.json().await.ok()
+}
+
+/// Whether `base_url` hosts a Dataverse installation, which answers
+/// `{"status": "OK", "data": {"version": ...}}` at `/api/info/version`.
+async fn is_dataverse_instance(base_url: &Url, client_options: &ClientOptions) -> bool {
+    let Some(json) = probe_json(base_url, "api/info/version", client_options).await else {
+        return false;
+    };
+    json.get("status").and_then(JsonValue::as_str) == Some("OK")
+        && json.pointer("/data/version").is_some()
+}
+
+/// Record id of an InvenioRDM landing or API url, `/records/<id>`, `/record/<id>` or
+/// `/api/records/<id>`.
+fn invenio_record_id(url: &Url) -> Option<&str> {
+    let mut segments = url.path_segments()?;
+    segments.find(|seg| matches!(*seg, "records" | "record"))?;
+    segments.next().filter(|id| !id.is_empty())
+}
+
+/// Whether `base_url` hosts an InvenioRDM installation, which answers a record search
+/// `{"hits": {"hits": [...]}}` at `/api/records`.
+async fn is_invenio_instance(base_url: &Url, client_options: &ClientOptions) -> bool {
+    probe_json(base_url, "api/records?size=1", client_options)
+        .await
+        .is_some_and(|json| json.pointer("/hits/hits").is_some_and(JsonValue::is_array))
+}
+
+/// Zenodo record of a landing url of any InvenioRDM installation.
+fn invenio_dataset(url: &Url) -> Result<Dataset, Exn<DispatchError>> {
+    // https://zenodo.org/records/17867222
+    // https://data.caltech.edu/records/f4rb5-hkd59
+    let record_id = invenio_record_id(url).ok_or_else(|| DispatchError {
+        message: format!("unable to parse record id from '{url}'"),
+    })?;
+    Ok(Dataset::new(Zenodo::new(record_id, &origin_url(url))))
+}
+
+#[allow(clippy::too_many_lines)]
+async fn dispatch(
+    url: &str,
+    auth: &AuthConfig,
+    repositories: &RepositoryConfig,
+    client_options: &ClientOptions,
+) -> Result<Dataset, Exn<DispatchError>> {
     let url = Url::from_str(url).or_raise(|| DispatchError {
         message: format!("'{url}' not a valid url"),
     })?;
+    let url = normalize_url(url);
+
+    // Public S3 bucket, prefix given by the path (or key of s3 urls)
+    // s3://openneuro.org/ds000001
+    // https://my-bucket.s3.eu-west-1.amazonaws.com/some/prefix
+    // https://s3.amazonaws.com/openneuro.org/ds000001
+    if let Some(dataset) = s3_dataset(&url)? {
+        return Ok(dataset);
+    }
+
+    // Software Heritage SWHID, bare or on the archive
+    // swh:1:dir:<sha1_git>
+    // https://archive.softwareheritage.org/swh:1:rev:<sha1_git>;origin=...
+    // https://archive.softwareheritage.org/browse/revision/<sha1_git>/
+    if url.scheme() == "swh" || url.domain() == Some("archive.softwareheritage.org") {
+        return software_heritage_dataset(&url);
+    }
+
     let scheme = url.scheme();
     let domain = url.domain().ok_or_else(|| DispatchError {
         message: "domain unresolved".to_string(),
@@ -352,7 +926,7 @@ pub async fn resolve(url: &str) -> Result<Dataset, Exn<DispatchError>> {
     })?;
 
     // DataOne spec hosted
-    if DATAONE_DOMAINS.contains(domain) {
+    if DATAONE_DOMAINS.contains(domain) || repositories.dataone.contains(domain) {
         // https://data.ess-dive.lbl.gov/view/doi%3A10.15485%2F1971251
         // resolved to xml at https://cn.dataone.org/cn/v2/object/doi%3A10.15485%2F1971251
         let base_url = format!("{scheme}://{host_str}");
@@ -373,44 +947,23 @@ pub async fn resolve(url: &str) -> Result<Dataset, Exn<DispatchError>> {
     }
 
     // Dataverse spec hosted
-    if DATAVERSE_DOMAINS.contains(domain) {
-        // https://dataverse.harvard.edu/dataset.xhtml?persistentId=doi:10.7910/DVN/KBHLOD
-        // https://dataverse.harvard.edu/file.xhtml?persistentId=doi:10.7910/DVN/KBHLOD/JCJCJC
-        let mut segments = url.path_segments().ok_or_else(|| DispatchError {
-            message: format!("'{url}' cannot be base"),
-        })?;
-        let typ = segments.next().ok_or_else(|| DispatchError {
-            message: format!("'{url}' no segments found"),
-        })?;
-        let queries = url.query_pairs();
-        let queries = queries.collect::<HashMap<_, _>>();
-        let Some(id) = queries.get("persistentId") else {
-            exn::bail!(DispatchError {
-                message: "query don't contains 'persistentId'".to_string()
-            })
-        };
+    if DATAVERSE_DOMAINS.contains(domain) || repositories.dataverse.contains(domain) {
+        return dataverse_dataset(&url);
+    }
 
-        let typ = typ.strip_suffix(".xhtml").ok_or_else(|| DispatchError {
-            message: "segment not in format *.xhtml".to_string(),
-        })?;
-        let base_url = format!("{scheme}://{host_str}");
-        let base_url = Url::from_str(&base_url).or_raise(|| DispatchError {
-            message: format!("'{base_url}' is not valid url"),
-        })?;
-        let version = ":latest-published".to_string();
-        match typ {
-            "dataset" => {
-                let dataset = Dataset::new(DataverseDataset::new(id.as_ref(), &base_url, &version));
-                return Ok(dataset);
-            }
-            "file" => {
-                let dataset = Dataset::new(DataverseFile::new(id.as_ref(), &base_url, &version));
-                return Ok(dataset);
-            }
-            ty => exn::bail!(DispatchError {
-                message: format!("{ty} is not valid type, can only be 'dataset' or 'file'")
-            }),
-        }
+    // InvenioRDM hosted, Zenodo and others
+    if INVENIO_DOMAINS.contains(domain) || repositories.invenio.contains(domain) {
+        return invenio_dataset(&url);
+    }
+
+    // THREDDS (or Hyrax) catalog on any host
+    // https://thredds.ucar.edu/thredds/catalog/grib/NCEP/GFS/Global_0p25deg/catalog.html
+    if url.path().contains("/thredds/catalog/")
+        || url.path().ends_with("/catalog.xml")
+        || url.path().ends_with("/catalog.html")
+    {
+        let dataset = Dataset::new(Thredds::new(&url));
+        return Ok(dataset);
     }
 
     match domain {
@@ -497,23 +1050,6 @@ pub async fn resolve(url: &str) -> Result<Dataset, Exn<DispatchError>> {
             let dataset = Dataset::new(HuggingFace::new(owner, repo, revision));
             Ok(dataset)
         }
-        "zenodo.org" => {
-            let segments = url
-                .path_segments()
-                .ok_or_else(|| DispatchError {
-                    message: format!("cannot get path segments of url '{}'", url.as_str()),
-                })?
-                .collect::<Vec<&str>>();
-            let record_id = if segments.len() >= 2 {
-                segments[1]
-            } else {
-                exn::bail!(DispatchError {
-                    message: format!("unable to parse dryad dataset id from '{url}'",)
-                })
-            };
-            let dataset = Dataset::new(Zenodo::new(record_id));
-            Ok(dataset)
-        }
         "github.com" => {
             let mut segments = url.path_segments().ok_or_else(|| DispatchError {
                 message: format!("cannot get path segments of url '{}'", url.as_str()),
@@ -527,16 +1063,48 @@ pub async fn resolve(url: &str) -> Result<Dataset, Exn<DispatchError>> {
                 message: format!("missing repo in url '{}'", url.as_str()),
             })?;
 
-            let dataset = if let Some(branch_or_commit) =
-                segments.next().and_then(|_| segments.next())
+            // https://github.com/<owner>/<repo>/releases/tag/<tag>
+            // https://github.com/<owner>/<repo>/releases/latest
+            if url
+                .path_segments()
+                .is_some_and(|mut s| s.nth(2) == Some("releases"))
             {
-                Dataset::new(GitHub::new(owner, repo_name, branch_or_commit))
+                let rest = segments.skip(1).collect::<Vec<_>>();
+                let tag = match rest.as_slice() {
+                    ["tag", tag @ ..] if !tag.is_empty() => Some(
+                        percent_decode_str(&tag.join("/"))
+                            .decode_utf8_lossy()
+                            .into_owned(),
+                    ),
+                    [] | ["latest"] | [""] => None,
+                    _ => exn::bail!(DispatchError {
+                        message: format!("unsupported GitHub release url '{url}'"),
+                    }),
+                };
+                return Ok(Dataset::new(GitHubRelease::new(owner, repo_name, tag)));
+            }
+
+            let branch_or_commit =
+                if let Some(branch_or_commit) = segments.next().and_then(|_| segments.next()) {
+                    branch_or_commit.to_string()
+                } else {
+                    github_get_default_branch_commit(
+                        owner,
+                        repo_name,
+                        auth.github.as_deref(),
+                        client_options,
+                    )
+                    .await?
+                };
+            let github = GitHub::new(owner, repo_name, branch_or_commit);
+            // the GraphQL API is only open to authenticated requests
+            let github = if auth.github.is_some() {
+                github.with_graphql()
             } else {
-                let branch_or_commit = github_get_default_branch_commit(owner, repo_name).await?;
-                Dataset::new(GitHub::new(owner, repo_name, branch_or_commit))
+                github
             };
 
-            Ok(dataset)
+            Ok(Dataset::new(github))
         }
         "datadryad.org" => {
             // example url: https://datadryad.org/dataset/doi:10.5061/dryad.mj8m0
@@ -561,6 +1129,23 @@ pub async fn resolve(url: &str) -> Result<Dataset, Exn<DispatchError>> {
             let dataset = Dataset::new(DataDryad::new(record_id, &base_url));
             Ok(dataset)
         }
+        "doi.pangaea.de" => {
+            // https://doi.pangaea.de/10.1594/PANGAEA.942110
+            let mut segments = url.path_segments().ok_or_else(|| DispatchError {
+                message: format!("cannot get path segments of url '{}'", url.as_str()),
+            })?;
+            let id = segments
+                .find(|seg| seg.starts_with("PANGAEA."))
+                .ok_or_else(|| DispatchError {
+                    message: format!("expect 'PANGAEA.<n>' in '{url}'"),
+                })?;
+            let base_url = Url::from_str(PANGAEA_URL).or_raise(|| DispatchError {
+                message: "invalid base url".to_string(),
+            })?;
+
+            let dataset = Dataset::new(Pangaea::new(id, &base_url));
+            Ok(dataset)
+        }
         "osf.io" => {
             let mut segments = url.path_segments().ok_or_else(|| DispatchError {
                 message: format!("cannot get path segments of url '{}'", url.as_str()),
@@ -584,6 +1169,22 @@ pub async fn resolve(url: &str) -> Result<Dataset, Exn<DispatchError>> {
             unimplemented!("help us! open an issue to request or PR to help us.")
         }
         _ => {
+            // Dataverse and InvenioRDM installations missing from the lists, recognized by
+            // their landing url or, for other urls, by their API
+            let base_url = origin_url(&url);
+            if has_dataverse_shape(&url) {
+                return dataverse_dataset(&url);
+            }
+            if invenio_record_id(&url).is_some()
+                && is_invenio_instance(&base_url, client_options).await
+            {
+                return invenio_dataset(&url);
+            }
+            if let Some(landing_url) = dataverse_landing_url(&url) {
+                if is_dataverse_instance(&base_url, client_options).await {
+                    return dataverse_dataset(&landing_url);
+                }
+            }
             exn::bail!(DispatchError {
                 message: format!("unknown domain: {domain}")
             })
@@ -594,9 +1195,10 @@ pub async fn resolve(url: &str) -> Result<Dataset, Exn<DispatchError>> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{client_builder, DatasetBackend};
     use std::time::Duration;
 
-    use wiremock::matchers::{method, path, query_param};
+    use wiremock::matchers::{header, method, path, query_param};
     use wiremock::{Mock, MockServer, ResponseTemplate};
     #[tokio::test]
     async fn test_resolve_dataverse_default() {
@@ -609,6 +1211,16 @@ mod tests {
             .downcast_ref::<DataverseDataset>()
             .unwrap();
         assert_eq!(qr.id.as_str(), "doi:10.7910/DVN/KBHLOD");
+        assert_eq!(qr.version, ":latest-published");
+
+        // former version
+        let qr = resolve(&format!("{url}&version=2")).await.unwrap();
+        let qr = qr
+            .backend
+            .as_any()
+            .downcast_ref::<DataverseDataset>()
+            .unwrap();
+        assert_eq!(qr.version, "2.0");
 
         // file
         let url =
@@ -618,6 +1230,96 @@ mod tests {
         assert_eq!(qr.id.as_str(), "doi:10.7910/DVN/KBHLOD/DHJ45U");
     }
 
+    #[tokio::test]
+    async fn test_resolve_dataverse_unlisted() {
+        // installation not in the list, recognized by the landing url
+        let url = "https://data.example.org/dataset.xhtml?persistentId=doi:10.1234/ABC";
+        let qr = resolve(url).await.unwrap();
+        let qr = qr
+            .backend
+            .as_any()
+            .downcast_ref::<DataverseDataset>()
+            .unwrap();
+        assert_eq!(qr.id.as_str(), "doi:10.1234/ABC");
+        assert_eq!(qr.base_url.as_str(), "https://data.example.org/");
+
+        // or by its API
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/info/version"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "status": "OK",
+                "data": { "version": "6.2", "build": "1234" },
+            })))
+            .mount(&mock_server)
+            .await;
+        let base_url = Url::parse(&format!("{}/", mock_server.uri())).unwrap();
+        assert!(is_dataverse_instance(&base_url, &ClientOptions::default()).await);
+        let base_url = Url::parse(&format!("{}/other/", mock_server.uri())).unwrap();
+        assert!(!is_dataverse_instance(&base_url, &ClientOptions::default()).await);
+
+        // other urls of a probed installation
+        let base_url = mock_server.uri().replace("127.0.0.1", "localhost");
+        for (url, id) in [
+            ("citation?persistentId=doi:10.1234/ABC", "doi:10.1234/ABC"),
+            (
+                "api/datasets/:persistentId/?persistentId=doi:10.1234/ABC",
+                "doi:10.1234/ABC",
+            ),
+            (
+                "dataset.xhtml;jsessionid=f00?persistentId=doi:10.1234/ABC",
+                "doi:10.1234/ABC",
+            ),
+        ] {
+            let qr = resolve(&format!("{base_url}/{url}")).await.unwrap();
+            let qr = qr
+                .backend
+                .as_any()
+                .downcast_ref::<DataverseDataset>()
+                .unwrap();
+            assert_eq!(qr.id.as_str(), id, "{url}");
+            assert_eq!(qr.base_url.as_str(), format!("{base_url}/"));
+        }
+        let url = format!("{base_url}/api/files/:persistentId?persistentId=doi:10.1234/ABC/DEF");
+        let qr = resolve(&url).await.unwrap();
+        let qr = qr.backend.as_any().downcast_ref::<DataverseFile>().unwrap();
+        assert_eq!(qr.id.as_str(), "doi:10.1234/ABC/DEF");
+        assert!(resolve(&format!("{base_url}/other")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_invenio_unlisted() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/records"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "hits": { "hits": [], "total": 0 },
+            })))
+            .mount(&mock_server)
+            .await;
+        // an ip is no domain, resolve by host name
+        let base_url = mock_server.uri().replace("127.0.0.1", "localhost");
+        let qr = resolve(&format!("{base_url}/records/abcde-12345"))
+            .await
+            .unwrap();
+        let qr = qr.backend.as_any().downcast_ref::<Zenodo>().unwrap();
+        assert_eq!(qr.id.as_str(), "abcde-12345");
+        assert_eq!(qr.base_url.as_str(), format!("{base_url}/"));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_configured_repositories() {
+        let url = "https://data.example.org/view/doi%3A10.1234%2FABC";
+        assert!(resolve_with_auth(url, AuthConfig::new()).await.is_err());
+
+        let repositories = RepositoryConfig::new().dataone("data.example.org");
+        let qr = resolve_with_repositories(url, AuthConfig::new(), &repositories)
+            .await
+            .unwrap();
+        let qr = qr.backend.as_any().downcast_ref::<Dataone>().unwrap();
+        assert_eq!(qr.id.as_str(), "doi%3A10.1234%2FABC");
+    }
+
     #[tokio::test]
     async fn test_resolve_default() {
         // osf.io
@@ -657,6 +1359,27 @@ mod tests {
         //     "<commit number that can change because by default is the commit of default branch>"
         // );
 
+        // github release
+        for (url, tag) in [
+            (
+                "https://github.com/EOSC-Data-Commons/datahugger-rs/releases/tag/v0.5.4",
+                Some("v0.5.4"),
+            ),
+            (
+                "https://github.com/EOSC-Data-Commons/datahugger-rs/releases/tag/release/2024.1",
+                Some("release/2024.1"),
+            ),
+            (
+                "https://github.com/EOSC-Data-Commons/datahugger-rs/releases/latest",
+                None,
+            ),
+        ] {
+            let qr = resolve(url).await.unwrap();
+            let qr = qr.backend.as_any().downcast_ref::<GitHubRelease>().unwrap();
+            assert_eq!(qr.repo.as_str(), "datahugger-rs");
+            assert_eq!(qr.tag.as_deref(), tag, "{url}");
+        }
+
         // hal
         let url = "https://hal.science/cel-01830944";
         let qr = resolve(url).await.unwrap();
@@ -666,6 +1389,8 @@ mod tests {
         // huggingface
         let url = "https://huggingface.co/datasets/HuggingFaceFW/finepdfs";
         let qr = resolve(url).await.unwrap();
+        assert_eq!(qr.backend_name(), "huggingface");
+        assert_eq!(qr.record_id(), "HuggingFaceFW/finepdfs");
         let qr = qr.backend.as_any().downcast_ref::<HuggingFace>().unwrap();
         assert_eq!(qr.owner.as_str(), "HuggingFaceFW");
         assert_eq!(qr.repo.as_str(), "finepdfs");
@@ -674,8 +1399,347 @@ mod tests {
         // zenodo
         let url = "https://zenodo.org/records/17867222";
         let qr = resolve(url).await.unwrap();
+        assert_eq!(qr.backend_name(), "zenodo");
+        assert_eq!(qr.record_id(), "17867222");
         let qr = qr.backend.as_any().downcast_ref::<Zenodo>().unwrap();
         assert_eq!(qr.id.as_str(), "17867222");
+        assert_eq!(qr.base_url.as_str(), "https://zenodo.org/");
+
+        // other InvenioRDM installation
+        let url = "https://data.caltech.edu/records/f4rb5-hkd59";
+        let qr = resolve(url).await.unwrap();
+        assert_eq!(qr.backend_name(), "invenio");
+        let qr = qr.backend.as_any().downcast_ref::<Zenodo>().unwrap();
+        assert_eq!(qr.id.as_str(), "f4rb5-hkd59");
+        assert_eq!(
+            qr.root_url().as_str(),
+            "https://data.caltech.edu/api/records/f4rb5-hkd59/files"
+        );
+
+        // thredds
+        let url = "https://thredds.ucar.edu/thredds/catalog/grib/NCEP/GFS/catalog.html";
+        let qr = resolve(url).await.unwrap();
+        let qr = qr.backend.as_any().downcast_ref::<Thredds>().unwrap();
+        assert_eq!(
+            qr.catalog_url.as_str(),
+            "https://thredds.ucar.edu/thredds/catalog/grib/NCEP/GFS/catalog.xml"
+        );
+
+        // pangaea
+        let url = "https://doi.pangaea.de/10.1594/PANGAEA.942110";
+        let qr = resolve(url).await.unwrap();
+        let qr = qr.backend.as_any().downcast_ref::<Pangaea>().unwrap();
+        assert_eq!(qr.id.as_str(), "PANGAEA.942110");
+
+        // software heritage
+        let rev = "aafb16d69fd30ff58afdd69036a26047f3aebdc6";
+        for url in [
+            format!("swh:1:rev:{rev}"),
+            format!("https://archive.softwareheritage.org/swh:1:rev:{rev};origin=https://github.com/x/y"),
+            format!("https://archive.softwareheritage.org/browse/revision/{rev}/"),
+        ] {
+            let qr = resolve(&url).await.unwrap();
+            let qr = qr.backend.as_any().downcast_ref::<SoftwareHeritage>().unwrap();
+            assert_eq!(qr.swhid(), format!("swh:1:rev:{rev}"));
+        }
+
+        // public s3 buckets
+        for (url, bucket_url, prefix) in [
+            (
+                "s3://openneuro.org/ds000001",
+                "https://s3.amazonaws.com/openneuro.org/",
+                "ds000001/",
+            ),
+            (
+                "https://my-bucket.s3.eu-west-1.amazonaws.com/some/prefix/",
+                "https://my-bucket.s3.eu-west-1.amazonaws.com/",
+                "some/prefix/",
+            ),
+            (
+                "https://s3.amazonaws.com/openneuro.org/ds000001",
+                "https://s3.amazonaws.com/openneuro.org/",
+                "ds000001/",
+            ),
+        ] {
+            let qr = resolve(url).await.unwrap();
+            let qr = qr.backend.as_any().downcast_ref::<S3Public>().unwrap();
+            assert_eq!(qr.bucket_url.as_str(), bucket_url);
+            assert_eq!(qr.prefix, prefix);
+        }
+    }
+
+    #[test]
+    fn test_normalize_url() {
+        for (url, expected) in [
+            (
+                "https://zenodo.org/records/17867222?utm_source=x&utm_medium=email#files",
+                "https://zenodo.org/records/17867222",
+            ),
+            (
+                "https://dataverse.harvard.edu/dataset.xhtml?persistentId=doi:10.7910/DVN/KBHLOD&ref_src=twsrc#citation",
+                "https://dataverse.harvard.edu/dataset.xhtml?persistentId=doi%3A10.7910%2FDVN%2FKBHLOD",
+            ),
+            (
+                "https://gitlab.com/group/project/-/archive/main?ref=v1.0&utm_source=x",
+                "https://gitlab.com/group/project/-/archive/main?ref=v1.0",
+            ),
+            ("https://osf.io/dezms/", "https://osf.io/dezms/"),
+        ] {
+            let url = normalize_url(Url::parse(url).unwrap());
+            assert_eq!(url.as_str(), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_with_fragment_and_tracking_params() {
+        let url = "https://dataverse.harvard.edu/dataset.xhtml?utm_source=x&persistentId=doi:10.7910/DVN/KBHLOD#files";
+        let qr = resolve(url).await.unwrap();
+        let qr = qr
+            .backend
+            .as_any()
+            .downcast_ref::<DataverseDataset>()
+            .unwrap();
+        assert_eq!(qr.id.as_str(), "doi:10.7910/DVN/KBHLOD");
+
+        let url = "https://zenodo.org/records/17867222?utm_source=newsletter#description";
+        let qr = resolve(url).await.unwrap();
+        let qr = qr.backend.as_any().downcast_ref::<Zenodo>().unwrap();
+        assert_eq!(qr.id.as_str(), "17867222");
+
+        let url = "https://osf.io/dezms/?ref=share#files";
+        let qr = resolve(url).await.unwrap();
+        let qr = qr.backend.as_any().downcast_ref::<OSF>().unwrap();
+        assert_eq!(qr.id.as_str(), "dezms");
+
+        let url = "https://arxiv.org/abs/2101.00001v1?fbclid=abc#S2";
+        let qr = resolve(url).await.unwrap();
+        let qr = qr.backend.as_any().downcast_ref::<Arxiv>().unwrap();
+        assert_eq!(qr.id.as_str(), "2101.00001v1");
+
+        let url = "https://datadryad.org/dataset/doi:10.5061/dryad.mj8m0?utm_campaign=x#citations";
+        let qr = resolve(url).await.unwrap();
+        let qr = qr.backend.as_any().downcast_ref::<DataDryad>().unwrap();
+        assert_eq!(qr.id.as_str(), "doi:10.5061/dryad.mj8m0");
+
+        let url = "https://hal.science/cel-01830944?utm_source=x#documents";
+        let qr = resolve(url).await.unwrap();
+        let qr = qr.backend.as_any().downcast_ref::<HalScience>().unwrap();
+        assert_eq!(qr.id.as_str(), "cel-01830944");
+
+        let url = "https://huggingface.co/datasets/HuggingFaceFW/finepdfs?ref=trending#usage";
+        let qr = resolve(url).await.unwrap();
+        let qr = qr.backend.as_any().downcast_ref::<HuggingFace>().unwrap();
+        assert_eq!(qr.repo.as_str(), "finepdfs");
+
+        let url =
+            "https://arcticdata.io/catalog/view/doi%3A10.18739%2FA2542JB2X?utm_source=x#files";
+        let qr = resolve(url).await.unwrap();
+        let qr = qr.backend.as_any().downcast_ref::<Dataone>().unwrap();
+        assert_eq!(qr.id.as_str(), "doi%3A10.18739%2FA2542JB2X");
+
+        let url =
+            "https://thredds.ucar.edu/thredds/catalog/grib/NCEP/GFS/catalog.html?utm_source=x#top";
+        let qr = resolve(url).await.unwrap();
+        let qr = qr.backend.as_any().downcast_ref::<Thredds>().unwrap();
+        assert_eq!(
+            qr.catalog_url.as_str(),
+            "https://thredds.ucar.edu/thredds/catalog/grib/NCEP/GFS/catalog.xml"
+        );
+
+        let url = "https://s3.amazonaws.com/openneuro.org/ds000001?utm_source=x#readme";
+        let qr = resolve(url).await.unwrap();
+        let qr = qr.backend.as_any().downcast_ref::<S3Public>().unwrap();
+        assert_eq!(qr.prefix, "ds000001/");
+    }
+
+    #[tokio::test]
+    async fn test_resolve_with_auth_headers() {
+        let auth = AuthConfig::new().zenodo("zen").dataverse("dv");
+
+        let ds = resolve_with_auth("https://zenodo.org/records/17867222", auth.clone())
+            .await
+            .unwrap();
+        assert_eq!(ds.auth_headers()["authorization"], "Bearer zen");
+
+        let url = "https://dataverse.harvard.edu/dataset.xhtml?persistentId=doi:10.7910/DVN/KBHLOD";
+        let ds = resolve_with_auth(url, auth).await.unwrap();
+        let headers = ds.auth_headers();
+        assert_eq!(headers["x-dataverse-key"], "dv");
+        assert!(!headers.contains_key("authorization"));
+
+        // no credential for the repository
+        let ds = resolve_with_auth("https://osf.io/dezms/", AuthConfig::new().zenodo("zen"))
+            .await
+            .unwrap();
+        assert!(ds.auth_headers().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_doi() {
+        assert_eq!(
+            doi_of("doi:10.5281/zenodo.1").as_deref(),
+            Some("10.5281/zenodo.1")
+        );
+        assert_eq!(
+            doi_of("https://doi.org/10.7910%2FDVN%2FKBHLOD").as_deref(),
+            Some("10.7910/DVN/KBHLOD")
+        );
+        assert_eq!(doi_of("https://zenodo.org/records/1"), None);
+
+        for url in [
+            "10.5281/zenodo.17867222",
+            "doi:10.5281/zenodo.17867222",
+            "https://doi.org/10.5281/zenodo.17867222",
+        ] {
+            let qr = resolve(url).await.unwrap();
+            assert_eq!(qr.doi.as_deref(), Some("10.5281/zenodo.17867222"));
+            let qr = qr.backend.as_any().downcast_ref::<Zenodo>().unwrap();
+            assert_eq!(qr.id.as_str(), "17867222");
+        }
+
+        let qr = resolve("https://dx.doi.org/10.5061/dryad.mj8m0")
+            .await
+            .unwrap();
+        let qr = qr.backend.as_any().downcast_ref::<DataDryad>().unwrap();
+        assert_eq!(qr.id.as_str(), "doi:10.5061/dryad.mj8m0");
+
+        let qr = resolve("10.7910/DVN/KBHLOD").await.unwrap();
+        let qr = qr
+            .backend
+            .as_any()
+            .downcast_ref::<DataverseDataset>()
+            .unwrap();
+        assert_eq!(qr.id.as_str(), "doi:10.7910/DVN/KBHLOD");
+        let qr = resolve("10.7910/DVN/KBHLOD/DHJ45U").await.unwrap();
+        let qr = qr.backend.as_any().downcast_ref::<DataverseFile>().unwrap();
+        assert_eq!(qr.id.as_str(), "doi:10.7910/DVN/KBHLOD/DHJ45U");
+
+        let qr = resolve(
+            "https://dataverse.harvard.edu/dataset.xhtml?persistentId=doi:10.7910/DVN/KBHLOD",
+        )
+        .await
+        .unwrap();
+        assert_eq!(qr.doi.as_deref(), Some("10.7910/DVN/KBHLOD"));
+        let qr = resolve("https://zenodo.org/records/17867222")
+            .await
+            .unwrap();
+        assert_eq!(qr.doi, None);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_doi_metadata() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/10.34894/0B7ZLK"))
+            .and(header("accept", CSL_JSON))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "type": "dataset",
+                "DOI": "10.34894/0B7ZLK",
+                "title": "Replication data",
+                "author": [{ "family": "Doe", "given": "Jane" }],
+                "issued": { "date-parts": [[2021, 12, 23]] },
+                "version": "1.0",
+            })))
+            .mount(&mock_server)
+            .await;
+        let client = client_builder().build().unwrap();
+
+        let metadata =
+            resolve_doi_metadata_with_base(&client, "doi:10.34894/0B7ZLK", &mock_server.uri())
+                .await
+                .unwrap();
+        assert_eq!(metadata.title.as_deref(), Some("Replication data"));
+        assert_eq!(metadata.creators, ["Doe, Jane"]);
+        assert_eq!(metadata.publication_date.as_deref(), Some("2021-12-23"));
+        assert_eq!(metadata.doi.as_deref(), Some("10.34894/0B7ZLK"));
+
+        // unknown to the mock server
+        assert!(
+            resolve_doi_metadata_with_base(&client, "10.1234/missing", &mock_server.uri())
+                .await
+                .is_err()
+        );
+        assert!(
+            resolve_doi_metadata_with_base(&client, "not a doi", &mock_server.uri())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolve_doi_redirects() {
+        let mock_server = MockServer::start().await;
+        let uri = mock_server.uri();
+        Mock::given(method("GET"))
+            .and(path("/10.34894/0B7ZLK"))
+            .and(query_param("type", "URL"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "responseCode": 1,
+                "values": [{ "type": "URL", "data": { "value": format!("{uri}/handle") } }]
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("HEAD"))
+            .and(path("/handle"))
+            .respond_with(ResponseTemplate::new(302).insert_header("location", "/citation"))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("HEAD"))
+            .and(path("/citation"))
+            .respond_with(
+                ResponseTemplate::new(301).insert_header("location", format!("{uri}/record")),
+            )
+            .mount(&mock_server)
+            .await;
+        // the record page does not answer HEAD
+        Mock::given(method("HEAD"))
+            .and(path("/record"))
+            .respond_with(ResponseTemplate::new(405))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/record"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("HEAD"))
+            .and(path("/loop"))
+            .respond_with(ResponseTemplate::new(302).insert_header("location", "/loop"))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/10.1234/loop"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "responseCode": 1,
+                "values": [{ "type": "URL", "data": { "value": format!("{uri}/loop") } }]
+            })))
+            .mount(&mock_server)
+            .await;
+        let client = client_builder().redirect(Policy::none()).build().unwrap();
+
+        let redirects = resolve_doi_redirects_with_base(&client, "10.34894/0B7ZLK", Some(&uri), 5)
+            .await
+            .unwrap();
+        assert_eq!(redirects.url, format!("{uri}/record"));
+        assert_eq!(
+            redirects.chain,
+            [
+                format!("{uri}/handle"),
+                format!("{uri}/citation"),
+                format!("{uri}/record")
+            ]
+        );
+
+        // bounded
+        let err = resolve_doi_redirects_with_base(&client, "10.34894/0B7ZLK", Some(&uri), 1)
+            .await
+            .unwrap_err();
+        assert!(err.message.starts_with("more than 1 redirects"));
+
+        let err = resolve_doi_redirects_with_base(&client, "10.1234/loop", Some(&uri), 5)
+            .await
+            .unwrap_err();
+        assert!(err.message.starts_with("redirect loop"));
     }
 
     #[tokio::test]
@@ -704,8 +1768,7 @@ mod tests {
             .mount(&mock_server)
             .await;
 
-        let client = reqwest::Client::builder()
-            .use_native_tls()
+        let client = client_builder()
             .timeout(Duration::from_secs(5))
             .build()
             .unwrap();
@@ -0,0 +1,156 @@
+use reqwest::{
+    header::{HeaderMap, RETRY_AFTER},
+    RequestBuilder, Response, StatusCode,
+};
+use std::{
+    future::Future,
+    time::{Duration, SystemTime},
+};
+use tracing::warn;
+
+use crate::metrics::record_request;
+
+/// Retries of a request answered with a `Retry-After`, before its response is returned as is.
+const MAX_RETRIES: usize = 5;
+
+/// Longest wait honored from a `Retry-After` header, a response asking to wait longer is
+/// returned as is.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(300);
+
+/// Wait before retrying asked by a `Retry-After` header, in seconds (`120`) or as HTTP date
+/// (`Wed, 21 Oct 2015 07:28:00 GMT`).
+pub(crate) fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = httpdate::parse_http_date(value).ok()?;
+    Some(at.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+/// Sends requests again when a rate limited repository asks to.
+pub(crate) trait RetryExt {
+    /// Sends the request, and when it is answered `429 Too Many Requests` (or
+    /// `503 Service Unavailable`) with a `Retry-After` header, e.g. by Zenodo or GitHub,
+    /// waits for the indicated time and sends it again.
+    ///
+    /// Gives up after a few retries or when asked to wait for more than 5 minutes, and
+    /// returns the last response, so callers surface the status as before. Requests with a
+    /// streaming body are sent once.
+    fn send_with_retry(self) -> impl Future<Output = Result<Response, reqwest::Error>> + Send;
+
+    /// Like [`send_with_retry`](RetryExt::send_with_retry), adding the number of retries
+    /// to `retries`.
+    fn send_with_retry_counted(
+        self,
+        retries: &mut usize,
+    ) -> impl Future<Output = Result<Response, reqwest::Error>> + Send;
+}
+
+impl RetryExt for RequestBuilder {
+    async fn send_with_retry(self) -> Result<Response, reqwest::Error> {
+        let mut retries = 0;
+        self.send_with_retry_counted(&mut retries).await
+    }
+
+    async fn send_with_retry_counted(
+        self,
+        retries: &mut usize,
+    ) -> Result<Response, reqwest::Error> {
+        let mut req = self;
+        let mut retried = false;
+        for _ in 0..MAX_RETRIES {
+            let Some(retry) = req.try_clone() else {
+                break;
+            };
+            record_request(retried);
+            let resp = req.send().await?;
+            let delay = match resp.status() {
+                StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => {
+                    retry_after(resp.headers()).filter(|delay| *delay <= MAX_RETRY_AFTER)
+                }
+                _ => None,
+            };
+            let Some(delay) = delay else {
+                return Ok(resp);
+            };
+            warn!(
+                "{} answered {}, retry in {}s",
+                resp.url(),
+                resp.status(),
+                delay.as_secs()
+            );
+            tokio::time::sleep(delay).await;
+            *retries += 1;
+            req = retry;
+            retried = true;
+        }
+        record_request(retried);
+        req.send().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::{header::HeaderValue, Client};
+    use std::time::Instant;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[test]
+    fn test_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("120"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(120)));
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+    }
+
+    #[tokio::test]
+    async fn test_send_with_retry() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/records"))
+            .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "1"))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/records"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/limited"))
+            .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "3600"))
+            .mount(&mock_server)
+            .await;
+
+        let client = Client::new();
+        let start = Instant::now();
+        let mut retries = 0;
+        let resp = client
+            .get(format!("{}/records", mock_server.uri()))
+            .send_with_retry_counted(&mut retries)
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(start.elapsed() >= Duration::from_secs(1));
+        assert_eq!(retries, 1);
+
+        // waiting an hour is not worth it, the status is surfaced
+        let resp = client
+            .get(format!("{}/limited", mock_server.uri()))
+            .send_with_retry()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+}
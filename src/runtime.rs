@@ -0,0 +1,94 @@
+//! Driving datahugger from outside of a Tokio runtime.
+//!
+//! The HTTP client and the file writes of a download need a Tokio runtime. Applications
+//! running another executor (async-std, smol, a GUI event loop) or plain threads do not
+//! have to start one themselves: [`spawn`] runs a future on a runtime owned by datahugger
+//! and returns a future any executor can await, [`block_on`] waits for it from synchronous
+//! code.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use datahugger::{client_builder, runtime, DownloadExt, DownloadOptions};
+//! use indicatif::MultiProgress;
+//!
+//! # fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let report = runtime::block_on(async {
+//!     let ds = datahugger::resolve("https://osf.io/3ua2c/").await?;
+//!     let client = client_builder().build()?;
+//!     let options = DownloadOptions::new();
+//!     let report = ds
+//!         .download_with_validation(&client, "/tmp/osf", MultiProgress::new(), &options)
+//!         .await?;
+//!     Ok::<_, Box<dyn std::error::Error>>(report)
+//! })?;
+//! println!("{report}");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{future::Future, sync::LazyLock};
+
+use tokio::runtime::{Builder, Handle, Runtime};
+
+static RUNTIME: LazyLock<Runtime> = LazyLock::new(|| {
+    Builder::new_multi_thread()
+        .enable_all()
+        .thread_name("datahugger")
+        .build()
+        .expect("unable to create tokio runtime")
+});
+
+/// Runs `future` to completion on a Tokio runtime and returns a future of its output, which
+/// can be awaited from any executor.
+///
+/// The future runs on the current Tokio runtime if called from one, else on a multi-thread
+/// runtime owned by datahugger, created on first use.
+///
+/// # Panics
+/// Resumes the panic of `future` if it panicked.
+pub fn spawn<F>(future: F) -> impl Future<Output = F::Output> + Send
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let handle = Handle::try_current().unwrap_or_else(|_| RUNTIME.handle().clone());
+    let task = handle.spawn(future);
+    async move {
+        match task.await {
+            Ok(output) => output,
+            Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+            Err(err) => panic!("datahugger task did not complete: {err}"),
+        }
+    }
+}
+
+/// Runs `future` to completion on the runtime owned by datahugger, blocking the current
+/// thread.
+///
+/// # Panics
+/// Panics if called from within a Tokio runtime, await the future or [`spawn`] it there.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    assert!(
+        Handle::try_current().is_err(),
+        "datahugger::runtime::block_on called from within a tokio runtime, await the future instead"
+    );
+    RUNTIME.block_on(future)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_on_and_spawn_outside_tokio() {
+        // a future driven by another executor, here a plain thread blocking on it
+        let output = futures_executor::block_on(spawn(async {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            Handle::current().runtime_flavor()
+        }));
+        assert_eq!(output, tokio::runtime::RuntimeFlavor::MultiThread);
+
+        assert_eq!(block_on(spawn(async { 1 + 1 })), 2);
+    }
+}
@@ -0,0 +1,110 @@
+use std::borrow::Cow;
+
+/// Operating system whose filesystems the downloaded file names must be valid on, see
+/// [`DownloadOptions::target_os`](crate::DownloadOptions::target_os).
+///
+/// Names listed by repositories are kept as is where the target allows them, and
+/// otherwise sanitized segment by segment, so a dataset downloaded on Linux onto a share
+/// read from Windows does not end up with files Windows cannot open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TargetOs {
+    /// The operating system the download runs on.
+    #[default]
+    Host,
+    /// Linux, macOS and other unix, only `NUL` is replaced.
+    Unix,
+    /// Windows, replaces `< > : " \ | ? *` and control characters by `_`, drops trailing dots
+    /// and spaces, and appends `_` to reserved device names such as `CON` or `COM1.txt`.
+    Windows,
+}
+
+const WINDOWS_RESERVED: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+impl TargetOs {
+    fn resolve(self) -> TargetOs {
+        match self {
+            TargetOs::Host if cfg!(windows) => TargetOs::Windows,
+            TargetOs::Host => TargetOs::Unix,
+            target => target,
+        }
+    }
+
+    /// Sanitizes every segment of the `/` separated relative `path` for the target,
+    /// borrowing `path` if it is valid as is.
+    #[must_use]
+    pub fn sanitize<'a>(&self, path: &'a str) -> Cow<'a, str> {
+        let target = self.resolve();
+        let segments = path
+            .split('/')
+            .map(|segment| target.sanitize_segment(segment))
+            .collect::<Vec<_>>();
+        if segments.iter().all(|s| matches!(s, Cow::Borrowed(_))) {
+            Cow::Borrowed(path)
+        } else {
+            Cow::Owned(segments.join("/"))
+        }
+    }
+
+    fn sanitize_segment(self, segment: &str) -> Cow<'_, str> {
+        match self {
+            TargetOs::Windows => {
+                if segment.is_empty() || segment == "." || segment == ".." {
+                    return Cow::Borrowed(segment);
+                }
+                let mut name = segment
+                    .chars()
+                    .map(|c| {
+                        if c.is_control() || r#"<>:"\|?*"#.contains(c) {
+                            '_'
+                        } else {
+                            c
+                        }
+                    })
+                    .collect::<String>();
+                name.truncate(name.trim_end_matches(['.', ' ']).len());
+                if name.is_empty() {
+                    name.push('_');
+                }
+                let stem = name.split('.').next().unwrap_or_default().trim_end();
+                if WINDOWS_RESERVED
+                    .iter()
+                    .any(|reserved| stem.eq_ignore_ascii_case(reserved))
+                {
+                    name.insert(stem.len(), '_');
+                }
+                if name == segment {
+                    Cow::Borrowed(segment)
+                } else {
+                    Cow::Owned(name)
+                }
+            }
+            _ if segment.contains('\0') => Cow::Owned(segment.replace('\0', "_")),
+            _ => Cow::Borrowed(segment),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_windows() {
+        let windows = TargetOs::Windows;
+        assert!(matches!(
+            windows.sanitize("data/a.csv"),
+            Cow::Borrowed("data/a.csv")
+        ));
+        assert_eq!(windows.sanitize("run 1: a?/b*.csv"), "run 1_ a_/b_.csv");
+        assert_eq!(windows.sanitize("notes./draft "), "notes/draft");
+        assert_eq!(windows.sanitize("con/aux.tar.gz"), "con_/aux_.tar.gz");
+        assert_eq!(windows.sanitize("COM1.txt/console"), "COM1_.txt/console");
+        assert_eq!(windows.sanitize("..."), "_");
+
+        assert_eq!(TargetOs::Unix.sanitize("run 1: a?/con"), "run 1: a?/con");
+        assert_eq!(TargetOs::Unix.sanitize("a\0b"), "a_b");
+    }
+}
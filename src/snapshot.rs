@@ -0,0 +1,588 @@
+use async_trait::async_trait;
+use exn::{Exn, OptionExt, ResultExt};
+use reqwest::{header::HeaderMap, Client};
+use serde_json::{json, Value as JsonValue};
+use std::{
+    any::Any,
+    fs,
+    path::Path,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use url::Url;
+
+use crate::{
+    crawler::ProgressManager,
+    error::ErrorStatus,
+    provenance::{checksum_str, unix_secs},
+    repo::{Endpoint, RepoError},
+    resolve_with_options, AccessRequirement, AuthConfig, Checksum, ClientOptions, CrawlPath,
+    Dataset, DatasetBackend, DatasetMetadata, DirMeta, DownloadPlan, Entry, FileMeta,
+    RepositoryConfig,
+};
+
+/// Version of the snapshot format, bumped on incompatible changes.
+const SNAPSHOT_FORMAT: u64 = 1;
+
+#[derive(Debug)]
+pub struct SnapshotError {
+    pub message: String,
+    pub status: ErrorStatus,
+}
+
+impl std::fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "snapshot fail: {}", self.message)
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+/// Pin of the exact state of a dataset, to commit next to a paper or a pipeline and
+/// download the very same files later, see [`Dataset::snapshot`] and
+/// [`Dataset::from_snapshot`].
+///
+/// Files are pinned with the size and checksums the repository lists, a file changed since
+/// the snapshot fails to validate when restored. Files the repository lists without
+/// checksum, e.g. on GitHub, are only pinned by size.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    /// URL or DOI the dataset was resolved from, resolved again on restore.
+    pub resolved_from: Option<String>,
+    /// Root url of the dataset in the repository API.
+    pub source_url: Url,
+    pub doi: Option<String>,
+    /// Name of the repository, see [`DatasetBackend::name`].
+    pub backend: String,
+    /// Identifier of the record in its repository, see [`DatasetBackend::record_id`].
+    pub record_id: String,
+    /// Version of the dataset, if the repository publishes one.
+    pub version: Option<String>,
+    pub resolved_at: SystemTime,
+    /// Version of datahugger that took the snapshot.
+    pub datahugger_version: String,
+    /// Files of the dataset, sorted by path.
+    pub files: Vec<FileMeta>,
+}
+
+fn invalid(message: String) -> SnapshotError {
+    SnapshotError {
+        message,
+        status: ErrorStatus::Permanent,
+    }
+}
+
+impl Snapshot {
+    /// Serializes the snapshot, times are unix time in seconds and checksums `<algo>:<hex>`.
+    #[must_use]
+    pub fn to_json(&self) -> JsonValue {
+        let files: Vec<_> = self
+            .files
+            .iter()
+            .map(|file| {
+                json!({
+                    "path": file.relative().as_str(),
+                    "download_url": file.download_url().as_str(),
+                    "size": file.size(),
+                    "checksums": file.checksum().iter().map(checksum_str).collect::<Vec<_>>(),
+                    "file_identifier": file.file_identifier(),
+                    "downloadable": file.is_downloadable(),
+                })
+            })
+            .collect();
+        json!({
+            "snapshot_format": SNAPSHOT_FORMAT,
+            "datahugger_version": self.datahugger_version,
+            "resolved_from": self.resolved_from,
+            "source_url": self.source_url.as_str(),
+            "doi": self.doi,
+            "backend": self.backend,
+            "record_id": self.record_id,
+            "version": self.version,
+            "resolved_at": unix_secs(self.resolved_at),
+            "files": files,
+        })
+    }
+
+    /// Parses a snapshot serialized with [`Snapshot::to_json`].
+    ///
+    /// # Errors
+    /// when a field is missing or malformed, or the snapshot was written in a newer format.
+    pub fn from_json(value: &JsonValue) -> Result<Self, Exn<SnapshotError>> {
+        let format = value
+            .get("snapshot_format")
+            .and_then(JsonValue::as_u64)
+            .ok_or_raise(|| invalid("no 'snapshot_format' in snapshot".to_string()))?;
+        if format > SNAPSHOT_FORMAT {
+            exn::bail!(invalid(format!(
+                "snapshot format {format} is newer than the supported {SNAPSHOT_FORMAT}, \
+                 upgrade datahugger"
+            )));
+        }
+        let str_field = |key: &str| value.get(key).and_then(JsonValue::as_str);
+        let required =
+            |key: &str| str_field(key).ok_or_raise(|| invalid(format!("no '{key}' in snapshot")));
+        let source_url = required("source_url")?;
+        let source_url = Url::parse(source_url)
+            .or_raise(|| invalid(format!("invalid 'source_url' '{source_url}' in snapshot")))?;
+        let files = value
+            .get("files")
+            .and_then(JsonValue::as_array)
+            .ok_or_raise(|| invalid("no 'files' in snapshot".to_string()))?
+            .iter()
+            .map(|file| file_from_json(file, &source_url))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Snapshot {
+            resolved_from: str_field("resolved_from").map(str::to_string),
+            doi: str_field("doi").map(str::to_string),
+            backend: required("backend")?.to_string(),
+            record_id: required("record_id")?.to_string(),
+            version: str_field("version").map(str::to_string),
+            resolved_at: UNIX_EPOCH
+                + Duration::from_secs(
+                    value
+                        .get("resolved_at")
+                        .and_then(JsonValue::as_u64)
+                        .unwrap_or_default(),
+                ),
+            datahugger_version: str_field("datahugger_version")
+                .unwrap_or_default()
+                .to_string(),
+            source_url,
+            files,
+        })
+    }
+
+    /// Writes the snapshot as pretty printed json to `path`.
+    ///
+    /// # Errors
+    /// when the file cannot be written.
+    pub fn write(&self, path: &Path) -> Result<(), Exn<SnapshotError>> {
+        // Safe to unwrap: a json value always serializes
+        let content = serde_json::to_vec_pretty(&self.to_json()).unwrap();
+        fs::write(path, content).or_raise(|| SnapshotError {
+            message: format!("cannot write snapshot at '{}'", path.display()),
+            status: ErrorStatus::Permanent,
+        })
+    }
+
+    /// Reads a snapshot written with [`Snapshot::write`].
+    ///
+    /// # Errors
+    /// when the file cannot be read or is not a valid snapshot.
+    pub fn read(path: &Path) -> Result<Self, Exn<SnapshotError>> {
+        let content = fs::read(path).or_raise(|| SnapshotError {
+            message: format!("cannot read snapshot at '{}'", path.display()),
+            status: ErrorStatus::Permanent,
+        })?;
+        let value: JsonValue = serde_json::from_slice(&content).or_raise(|| {
+            invalid(format!(
+                "snapshot at '{}' is not valid json",
+                path.display()
+            ))
+        })?;
+        Snapshot::from_json(&value)
+    }
+}
+
+fn file_from_json(file: &JsonValue, source_url: &Url) -> Result<FileMeta, Exn<SnapshotError>> {
+    let path = file
+        .get("path")
+        .and_then(JsonValue::as_str)
+        .ok_or_raise(|| invalid("file without 'path' in snapshot".to_string()))?;
+    let download_url = file
+        .get("download_url")
+        .and_then(JsonValue::as_str)
+        .and_then(|url| Url::parse(url).ok())
+        .ok_or_raise(|| invalid(format!("no valid 'download_url' of '{path}' in snapshot")))?;
+    let checksums = file
+        .get("checksums")
+        .and_then(JsonValue::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(JsonValue::as_str)
+        .map(|checksum| {
+            let (algo, _) = checksum.split_once(':').unwrap_or_default();
+            Checksum::parse(algo, checksum)
+                .or_raise(|| invalid(format!("invalid checksum of '{path}' in snapshot")))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let filename = path.rsplit('/').next().map(str::to_string);
+    Ok(FileMeta::new(
+        filename,
+        file.get("file_identifier")
+            .and_then(JsonValue::as_str)
+            .map(str::to_string),
+        CrawlPath::root().join(path),
+        Endpoint {
+            parent_url: source_url.clone(),
+            key: None,
+        },
+        download_url,
+        file.get("size").and_then(JsonValue::as_u64),
+        checksums,
+        None,
+        None,
+        None,
+        None,
+        file.get("downloadable")
+            .and_then(JsonValue::as_bool)
+            .unwrap_or(true),
+    ))
+}
+
+/// A dataset listing the files pinned in a snapshot instead of the current files of the
+/// record, with everything else, e.g. credentials, of the backend it was resolved to.
+struct PinnedDataset {
+    backend: Arc<dyn DatasetBackend>,
+    files: Vec<FileMeta>,
+}
+
+#[async_trait]
+impl DatasetBackend for PinnedDataset {
+    async fn list(&self, _client: &Client, _dir: DirMeta) -> Result<Vec<Entry>, Exn<RepoError>> {
+        // the root only, files are created with their parent folders
+        Ok(self.files.iter().cloned().map(Entry::File).collect())
+    }
+
+    fn root_url(&self) -> Url {
+        self.backend.root_url()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &'static str {
+        self.backend.name()
+    }
+
+    fn record_id(&self) -> String {
+        self.backend.record_id()
+    }
+
+    fn auth_headers(&self, auth: &AuthConfig) -> HeaderMap {
+        self.backend.auth_headers(auth)
+    }
+
+    async fn metadata(&self, client: &Client) -> Result<DatasetMetadata, Exn<RepoError>> {
+        self.backend.metadata(client).await
+    }
+
+    async fn raw_metadata(&self, client: &Client) -> Result<JsonValue, Exn<RepoError>> {
+        self.backend.raw_metadata(client).await
+    }
+
+    async fn access_requirements(
+        &self,
+        client: &Client,
+        auth: &AuthConfig,
+    ) -> Result<Vec<AccessRequirement>, Exn<RepoError>> {
+        self.backend.access_requirements(client, auth).await
+    }
+}
+
+impl Dataset {
+    /// Crawls the dataset and pins its current state: what it was resolved from, its
+    /// repository, record id and version, and the path, download url, size and checksums of
+    /// every file.
+    ///
+    /// # Errors
+    /// when crawling the dataset fails.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// use datahugger::{
+    ///     AuthConfig, ClientOptions, Dataset, DownloadExt, DownloadOptions, RepositoryConfig,
+    ///     Snapshot,
+    /// };
+    /// use indicatif::MultiProgress;
+    /// use std::path::Path;
+    ///
+    /// let client = reqwest::Client::new();
+    /// let ds = datahugger::resolve("https://zenodo.org/records/17867222").await?;
+    /// ds.snapshot(&client, MultiProgress::new())
+    ///     .await?
+    ///     .write(Path::new("data.pin.json"))?;
+    ///
+    /// // later, maybe on another machine
+    /// let snapshot = Snapshot::read(Path::new("data.pin.json"))?;
+    /// let ds = Dataset::from_snapshot(
+    ///     &snapshot,
+    ///     AuthConfig::from_env(),
+    ///     &RepositoryConfig::load(),
+    ///     &ClientOptions::default(),
+    /// )
+    /// .await?;
+    /// ds.download_with_validation(&client, "./data", MultiProgress::new(), &DownloadOptions::new())
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn snapshot(
+        &self,
+        client: &Client,
+        mp: impl ProgressManager,
+    ) -> Result<Snapshot, Exn<SnapshotError>> {
+        let plan = DownloadPlan::crawl(self.clone(), client, mp)
+            .await
+            .or_raise(|| SnapshotError {
+                message: format!("cannot crawl '{}'", self.backend.root_url()),
+                status: ErrorStatus::Temporary,
+            })?;
+        let mut files = plan.files().to_vec();
+        files.sort_by(|a, b| a.relative().as_str().cmp(b.relative().as_str()));
+        // best effort, not every repository versions its records
+        let version = self
+            .metadata(client)
+            .await
+            .ok()
+            .and_then(|metadata| metadata.version);
+        Ok(Snapshot {
+            resolved_from: self.resolved_from.clone(),
+            source_url: self.backend.root_url(),
+            doi: self.doi.clone(),
+            backend: self.backend_name().to_string(),
+            record_id: self.record_id(),
+            version,
+            resolved_at: self.resolved_at,
+            datahugger_version: env!("CARGO_PKG_VERSION").to_string(),
+            files,
+        })
+    }
+
+    /// Reconstructs the dataset pinned by `snapshot`, whose crawl lists the pinned files,
+    /// validated against the pinned sizes and checksums when downloaded.
+    ///
+    /// The dataset is resolved again from [`Snapshot::resolved_from`] like
+    /// [`crate::resolve_with_options`], with the credentials of `auth`, the installations of
+    /// `repositories` and the requests sent with `client_options`. The pinned version, if
+    /// the snapshot has one, is selected again, after which the dataset must still be the
+    /// pinned record.
+    ///
+    /// # Errors
+    /// when the dataset cannot be resolved, has no pinned version, or resolves to another
+    /// repository or record.
+    pub async fn from_snapshot(
+        snapshot: &Snapshot,
+        auth: AuthConfig,
+        repositories: &RepositoryConfig,
+        client_options: &ClientOptions,
+    ) -> Result<Dataset, Exn<SnapshotError>> {
+        let input = snapshot
+            .resolved_from
+            .clone()
+            .unwrap_or_else(|| snapshot.source_url.to_string());
+        let dataset = resolve_with_options(&input, auth, repositories, client_options)
+            .await
+            .or_raise(|| SnapshotError {
+                message: format!("cannot resolve '{input}' pinned in snapshot"),
+                status: ErrorStatus::Temporary,
+            })?;
+        let dataset = match &snapshot.version {
+            Some(version) => select_version(dataset, version, &input, client_options).await?,
+            None => dataset,
+        };
+        if dataset.backend_name() != snapshot.backend || dataset.record_id() != snapshot.record_id {
+            exn::bail!(invalid(format!(
+                "'{input}' resolves to {} record '{}', not the pinned {} record '{}'",
+                dataset.backend_name(),
+                dataset.record_id(),
+                snapshot.backend,
+                snapshot.record_id
+            )));
+        }
+        let backend = PinnedDataset {
+            backend: Arc::clone(&dataset.backend),
+            files: snapshot.files.clone(),
+        };
+        Ok(Dataset {
+            backend: Arc::new(backend),
+            auth: dataset.auth,
+            resolved_at: snapshot.resolved_at,
+            doi: snapshot.doi.clone(),
+            resolved_from: Some(input),
+        })
+    }
+}
+
+/// Selects the pinned `version` of `dataset` resolved from `input`, like `--dataset-version`
+/// did when the snapshot was taken. A dataset without selectable versions, e.g. a GitHub
+/// repository at a tag, must be at that version already.
+async fn select_version(
+    dataset: Dataset,
+    version: &str,
+    input: &str,
+    client_options: &ClientOptions,
+) -> Result<Dataset, Exn<SnapshotError>> {
+    let client = client_options
+        .builder()
+        .default_headers(dataset.auth_headers())
+        .build()
+        .or_raise(|| SnapshotError {
+            message: "cannot build the http client".to_string(),
+            status: ErrorStatus::Permanent,
+        })?;
+    let err = match dataset.clone().with_version(&client, version).await {
+        Ok(dataset) => return Ok(dataset),
+        Err(err) => err,
+    };
+    let current = dataset
+        .metadata(&client)
+        .await
+        .ok()
+        .and_then(|metadata| metadata.version);
+    if current.as_deref() == Some(version) {
+        return Ok(dataset);
+    }
+    Err(err).or_raise(|| {
+        invalid(format!(
+            "cannot select the pinned version '{version}' of '{input}'"
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        datasets::ZenodoJsonSrcDataset,
+        testutil::{zenodo_entry, NoProgress, TempDir},
+        DatahuggerError, DownloadExt, DownloadOptions,
+    };
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_snapshot_round_trip() {
+        let mock_server = MockServer::start().await;
+        let json = serde_json::json!({
+            "entries": [zenodo_entry(&mock_server.uri(), "data/a.csv", "a,b\n")]
+        });
+        let ds = Dataset::new(ZenodoJsonSrcDataset::new("1", json.to_string()))
+            .with_resolved_from("https://zenodo.org/records/1");
+        let client = Client::new();
+        let snapshot = ds.snapshot(&client, NoProgress).await.unwrap();
+
+        let value = snapshot.to_json();
+        assert_eq!(value["backend"], "zenodo");
+        assert_eq!(value["record_id"], "1");
+        assert_eq!(value["resolved_from"], "https://zenodo.org/records/1");
+        assert_eq!(
+            value["files"][0]["checksums"][0],
+            "md5:f69f5b72bc79a92dc70c63c9aa142e36"
+        );
+        let snapshot = Snapshot::from_json(&value).unwrap();
+        assert_eq!(snapshot.to_json(), value);
+
+        // the file changed since the snapshot
+        Mock::given(method("GET"))
+            .and(path("/files/data/a.csv"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("a,c\n"))
+            .mount(&mock_server)
+            .await;
+        let pinned = Dataset {
+            backend: Arc::new(PinnedDataset {
+                backend: Arc::clone(&ds.backend),
+                files: snapshot.files,
+            }),
+            ..ds
+        };
+        let dst = TempDir::new("snapshot");
+        let err = pinned
+            .download_with_validation(&client, &dst, NoProgress, &DownloadOptions::new())
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            DatahuggerError::from(&err),
+            DatahuggerError::ChecksumMismatch { .. }
+        ));
+
+        let mut value = value;
+        value["snapshot_format"] = 2.into();
+        assert!(Snapshot::from_json(&value).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_from_snapshot_version() {
+        let mock_server = MockServer::start().await;
+        let record = |id: &str, version: &str| {
+            serde_json::json!({
+                "id": id,
+                "metadata": { "title": "Data", "version": version },
+                "versions": { "index": id.parse::<u64>().unwrap() },
+            })
+        };
+        for (id, version) in [("1", "v1"), ("2", "v2")] {
+            Mock::given(method("GET"))
+                .and(path(format!("/api/records/{id}")))
+                .respond_with(ResponseTemplate::new(200).set_body_json(record(id, version)))
+                .mount(&mock_server)
+                .await;
+            Mock::given(method("GET"))
+                .and(path(format!("/api/records/{id}/files")))
+                .respond_with(
+                    ResponseTemplate::new(200).set_body_json(serde_json::json!({ "entries": [] })),
+                )
+                .mount(&mock_server)
+                .await;
+        }
+        Mock::given(method("GET"))
+            .and(path("/api/records/2/versions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "hits": { "hits": [record("2", "v2"), record("1", "v1")] },
+            })))
+            .mount(&mock_server)
+            .await;
+        // an ip is no domain, resolve by host name
+        let base_url = mock_server.uri().replace("127.0.0.1", "localhost");
+        let repositories = RepositoryConfig::new().invenio("localhost");
+        let options = ClientOptions::default();
+
+        // `snapshot --dataset-version v1` of the latest record
+        let input = format!("{base_url}/records/2");
+        let client = Client::new();
+        let snapshot = resolve_with_options(&input, AuthConfig::new(), &repositories, &options)
+            .await
+            .unwrap()
+            .with_version(&client, "v1")
+            .await
+            .unwrap()
+            .snapshot(&client, NoProgress)
+            .await
+            .unwrap();
+        assert_eq!(snapshot.resolved_from.as_deref(), Some(input.as_str()));
+        assert_eq!(snapshot.record_id, "1");
+        assert_eq!(snapshot.version.as_deref(), Some("v1"));
+
+        let ds = Dataset::from_snapshot(&snapshot, AuthConfig::new(), &repositories, &options)
+            .await
+            .unwrap();
+        assert_eq!(ds.record_id(), "1");
+        assert_eq!(ds.resolved_from.as_deref(), Some(input.as_str()));
+
+        let Err(err) = Dataset::from_snapshot(
+            &Snapshot {
+                version: Some("v3".to_string()),
+                ..snapshot
+            },
+            AuthConfig::new(),
+            &repositories,
+            &options,
+        )
+        .await
+        else {
+            panic!("restored a missing version");
+        };
+        assert!(
+            err.message
+                .contains("cannot select the pinned version 'v3'"),
+            "{err:?}"
+        );
+    }
+}
@@ -0,0 +1,306 @@
+use digest::Digest;
+use exn::{Exn, ResultExt};
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+};
+use tracing::debug;
+
+use crate::{error::ErrorStatus, Checksum};
+
+/// Directory of a [`ContentStore`] mapping the other checksums of an object to its sha256.
+const ALIASES_DIR: &str = "aliases";
+
+/// Suffix of the part files of the contents being stored, unique in the process.
+static PART_SEQ: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug)]
+pub struct StoreError {
+    pub message: String,
+    pub status: ErrorStatus,
+}
+
+impl std::fmt::Display for StoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "store fail: {}", self.message)
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+/// Local store of file contents by sha256, shared by the datasets downloaded with
+/// [`DownloadOptions::store`](crate::DownloadOptions::store).
+///
+/// Each content is stored once as `<root>/<sha256>`, the files of the datasets are hard
+/// links to it, so a file already fetched for another dataset, or another version of the
+/// same dataset, is linked instead of downloaded again. The other checksums a repository
+/// lists, e.g. md5, are mapped to the sha256 under `<root>/aliases`.
+///
+/// The store must be on the filesystem of the datasets: across filesystems the files are
+/// copied, which saves the transfer but not the space, and [`gc`](Self::gc) removes the
+/// stored copy.
+///
+/// # Examples
+///
+/// ```no_run
+/// use datahugger::ContentStore;
+///
+/// let store = ContentStore::new("/data/store");
+/// let gc = store.gc().unwrap();
+/// println!("removed {} files, {} bytes", gc.removed, gc.freed_bytes);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ContentStore {
+    root: PathBuf,
+}
+
+/// Outcome of [`ContentStore::gc`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StoreGc {
+    /// number of contents removed.
+    pub removed: usize,
+    /// total size of the removed contents.
+    pub freed_bytes: u64,
+}
+
+impl ContentStore {
+    #[must_use]
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        ContentStore { root: root.into() }
+    }
+
+    /// Directory of the store.
+    #[must_use]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Path the content with the hex `sha256` digest is stored at, whether it is or not.
+    #[must_use]
+    pub fn object_path(&self, sha256: &str) -> PathBuf {
+        self.root.join(sha256)
+    }
+
+    fn alias_path(&self, checksum: &Checksum) -> PathBuf {
+        self.root
+            .join(ALIASES_DIR)
+            .join(format!("{}-{}", checksum.algorithm(), checksum.value()))
+    }
+
+    /// Returns the path of the stored content with `checksum`, `None` if there is none.
+    #[must_use]
+    pub fn lookup(&self, checksum: &Checksum) -> Option<PathBuf> {
+        let sha256 = match checksum {
+            Checksum::Sha256(sha256) => sha256.clone(),
+            _ => fs::read_to_string(self.alias_path(checksum)).ok()?,
+        };
+        let path = self.object_path(sha256.trim());
+        path.is_file().then_some(path)
+    }
+
+    /// Stores the content of the file at `path`, whose `checksum` was verified if given,
+    /// and returns its sha256.
+    ///
+    /// A content not stored yet is stored as a hard link to `path`, else `path` is
+    /// replaced by a link to the stored content. `checksum` is recorded for
+    /// [`lookup`](Self::lookup) by other algorithms than sha256.
+    ///
+    /// # Errors
+    /// Returns an error if `path` cannot be read or the store cannot be written.
+    pub fn insert(
+        &self,
+        path: &Path,
+        checksum: Option<&Checksum>,
+    ) -> Result<String, Exn<StoreError>> {
+        let sha256 = match checksum {
+            Some(Checksum::Sha256(sha256)) => sha256.clone(),
+            _ => sha256_file(path)?,
+        };
+        self.insert_hashed(path, sha256, checksum)
+    }
+
+    /// Stores the content of the file at `path` like [`insert`](Self::insert), whose
+    /// `sha256` was computed while it was written.
+    pub(crate) fn insert_hashed(
+        &self,
+        path: &Path,
+        sha256: String,
+        checksum: Option<&Checksum>,
+    ) -> Result<String, Exn<StoreError>> {
+        let err = || StoreError {
+            message: format!("cannot store '{}'", path.display()),
+            status: ErrorStatus::Permanent,
+        };
+        fs::create_dir_all(self.root.join(ALIASES_DIR)).or_raise(err)?;
+        let object = self.object_path(&sha256);
+        if object.is_file() {
+            link_or_copy(&object, path).or_raise(err)?;
+        } else {
+            // linked or copied aside and renamed, for a concurrent insert of the same content
+            // to find it whole
+            let seq = PART_SEQ.fetch_add(1, Ordering::Relaxed);
+            let part = self.object_path(&format!("{sha256}.part-{}-{seq}", std::process::id()));
+            let _ = fs::remove_file(&part);
+            if let Err(e) = fs::hard_link(path, &part) {
+                debug!("cannot hard link '{}', copying it: {e}", path.display());
+                fs::copy(path, &part).or_raise(err)?;
+            }
+            fs::rename(&part, &object).or_raise(err)?;
+        }
+        if let Some(checksum) = checksum.filter(|c| !matches!(c, Checksum::Sha256(_))) {
+            fs::write(self.alias_path(checksum), &sha256).or_raise(err)?;
+        }
+        Ok(sha256)
+    }
+
+    /// Links the stored content at `object`, see [`lookup`](Self::lookup), to `dst`,
+    /// replacing a file already at `dst`. The content is copied where hard links are not
+    /// supported.
+    ///
+    /// # Errors
+    /// Returns an error if `dst` cannot be written.
+    pub fn materialize(&self, object: &Path, dst: &Path) -> Result<(), Exn<StoreError>> {
+        if let Some(parent) = dst.parent() {
+            fs::create_dir_all(parent).or_raise(|| StoreError {
+                message: format!("cannot create dir {}", parent.display()),
+                status: ErrorStatus::Permanent,
+            })?;
+        }
+        link_or_copy(object, dst).or_raise(|| StoreError {
+            message: format!("cannot link '{}' to '{}'", dst.display(), object.display()),
+            status: ErrorStatus::Permanent,
+        })
+    }
+
+    /// Removes the contents no dataset links to any more, and their aliases, e.g. once
+    /// old versions of the datasets are deleted.
+    ///
+    /// A content is unused when its only hard link is the store's. Hard link counts are
+    /// only known on unix, elsewhere nothing is removed.
+    ///
+    /// # Errors
+    /// Returns an error if the store cannot be listed or a content cannot be removed.
+    pub fn gc(&self) -> Result<StoreGc, Exn<StoreError>> {
+        let err = |message: String| StoreError {
+            message,
+            status: ErrorStatus::Permanent,
+        };
+        let mut gc = StoreGc::default();
+        let entries = fs::read_dir(&self.root)
+            .or_raise(|| err(format!("cannot list '{}'", self.root.display())))?;
+        for entry in entries {
+            let entry = entry.or_raise(|| err(format!("cannot list '{}'", self.root.display())))?;
+            let path = entry.path();
+            let Ok(meta) = fs::metadata(&path) else {
+                continue;
+            };
+            if meta.is_file() && is_unused(&meta) {
+                fs::remove_file(&path)
+                    .or_raise(|| err(format!("cannot remove '{}'", path.display())))?;
+                gc.removed += 1;
+                gc.freed_bytes += meta.len();
+            }
+        }
+        // aliases of removed contents
+        if let Ok(aliases) = fs::read_dir(self.root.join(ALIASES_DIR)) {
+            for alias in aliases.flatten() {
+                let sha256 = fs::read_to_string(alias.path()).unwrap_or_default();
+                if !self.object_path(sha256.trim()).is_file() {
+                    let _ = fs::remove_file(alias.path());
+                }
+            }
+        }
+        Ok(gc)
+    }
+}
+
+#[cfg(unix)]
+fn is_unused(meta: &fs::Metadata) -> bool {
+    std::os::unix::fs::MetadataExt::nlink(meta) <= 1
+}
+
+#[cfg(not(unix))]
+fn is_unused(_meta: &fs::Metadata) -> bool {
+    false
+}
+
+/// Hex sha256 of the content of the file at `path`.
+fn sha256_file(path: &Path) -> Result<String, Exn<StoreError>> {
+    let err = || StoreError {
+        message: format!("cannot read '{}'", path.display()),
+        status: ErrorStatus::Permanent,
+    };
+    let mut fh = fs::File::open(path).or_raise(err)?;
+    let mut hasher = sha2::Sha256::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = fh.read(&mut buf).or_raise(err)?;
+        if n == 0 {
+            return Ok(hex::encode(hasher.finalize()));
+        }
+        hasher.update(&buf[..n]);
+    }
+}
+
+/// Replaces `dst` by a hard link to `src`, or a copy of it, through a sibling renamed
+/// over `dst` so it is never missing.
+fn link_or_copy(src: &Path, dst: &Path) -> io::Result<()> {
+    let mut part = dst.as_os_str().to_owned();
+    part.push(".datahugger-link");
+    let part = PathBuf::from(part);
+    let _ = fs::remove_file(&part);
+    if let Err(e) = fs::hard_link(src, &part) {
+        debug!("cannot hard link '{}', copying it: {e}", dst.display());
+        fs::copy(src, &part)?;
+    }
+    fs::rename(&part, dst)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    #[test]
+    fn test_content_store() {
+        let dir = TempDir::new("content-store");
+        let store = ContentStore::new(dir.join("store"));
+        fs::create_dir_all(dir.join("v1")).unwrap();
+        fs::write(dir.join("v1/a.txt"), "hello").unwrap();
+        let md5 = Checksum::Md5("5d41402abc4b2a76b9719d911017c592".to_string());
+
+        let sha256 = store.insert(&dir.join("v1/a.txt"), Some(&md5)).unwrap();
+        assert_eq!(
+            sha256,
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        let object = store.lookup(&md5).unwrap();
+        assert_eq!(object, store.object_path(&sha256));
+        assert_eq!(
+            store.lookup(&Checksum::Sha256(sha256.clone())),
+            Some(object.clone())
+        );
+        assert!(store.lookup(&Checksum::Md5("0".repeat(32))).is_none());
+
+        store.materialize(&object, &dir.join("v2/a.txt")).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("v2/a.txt")).unwrap(), "hello");
+
+        // still linked from v2
+        fs::remove_dir_all(dir.join("v1")).unwrap();
+        assert_eq!(store.gc().unwrap(), StoreGc::default());
+        fs::remove_dir_all(dir.join("v2")).unwrap();
+        #[cfg(unix)]
+        {
+            assert_eq!(
+                store.gc().unwrap(),
+                StoreGc {
+                    removed: 1,
+                    freed_bytes: 5
+                }
+            );
+            assert!(store.lookup(&md5).is_none());
+        }
+    }
+}
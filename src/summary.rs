@@ -0,0 +1,147 @@
+use exn::{Exn, ResultExt};
+use indicatif::DecimalBytes;
+use reqwest::Client;
+use tracing::debug;
+use url::Url;
+
+use crate::{
+    crawler::{CrawlerError, ProgressManager},
+    error::ErrorStatus,
+    Dataset, DatasetMetadata, DownloadPlan,
+};
+
+/// What datahugger resolved a dataset to, see [`Dataset::summary`].
+#[derive(Debug, Clone)]
+pub struct DatasetSummary {
+    /// Name of the repository, see [`DatasetBackend::name`](crate::DatasetBackend::name).
+    pub backend: String,
+    /// Identifier of the record in its repository.
+    pub record_id: String,
+    /// API url the dataset is crawled from.
+    pub root_url: Url,
+    pub doi: Option<String>,
+    /// Number of files of the dataset.
+    pub files: usize,
+    /// Summed size in bytes of the files listed with a size.
+    pub size: u64,
+    /// Number of files listed without size, not counted in `size`.
+    pub unknown_size: usize,
+    /// Descriptive metadata, `None` if the repository provides none for the dataset.
+    pub metadata: Option<DatasetMetadata>,
+}
+
+impl Dataset {
+    /// Crawls the dataset and summarizes it: the repository and record it was resolved to,
+    /// the number and total size of its files and its metadata, to check what a URL is
+    /// before downloading it.
+    ///
+    /// # Errors
+    /// when crawling the dataset fails, metadata are fetched best effort.
+    pub async fn summary(
+        &self,
+        client: &Client,
+        mp: impl ProgressManager,
+    ) -> Result<DatasetSummary, Exn<CrawlerError>> {
+        let plan = DownloadPlan::crawl(self.clone(), client, mp)
+            .await
+            .or_raise(|| CrawlerError {
+                message: format!("cannot crawl '{}'", self.backend.root_url()),
+                status: ErrorStatus::Temporary,
+            })?;
+        let metadata = match self.metadata(client).await {
+            Ok(metadata) => Some(metadata),
+            Err(err) => {
+                debug!("no metadata for '{}': {err:?}", self.backend.root_url());
+                None
+            }
+        };
+        let files = plan.files();
+        Ok(DatasetSummary {
+            backend: self.backend_name().to_string(),
+            record_id: self.record_id(),
+            root_url: self.backend.root_url(),
+            doi: self
+                .doi
+                .clone()
+                .or_else(|| metadata.as_ref().and_then(|m| m.doi.clone())),
+            files: files.len(),
+            size: files.iter().filter_map(|f| f.size()).sum(),
+            unknown_size: files.iter().filter(|f| f.size().is_none()).count(),
+            metadata,
+        })
+    }
+}
+
+impl std::fmt::Display for DatasetSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "repository:  {}", self.backend)?;
+        writeln!(f, "record id:   {}", self.record_id)?;
+        writeln!(f, "api url:     {}", self.root_url)?;
+        if let Some(doi) = &self.doi {
+            writeln!(f, "doi:         {doi}")?;
+        }
+        write!(
+            f,
+            "files:       {} ({}, {} bytes)",
+            self.files,
+            DecimalBytes(self.size),
+            self.size
+        )?;
+        if self.unknown_size > 0 {
+            write!(f, ", {} of unknown size", self.unknown_size)?;
+        }
+        writeln!(f)?;
+        let Some(metadata) = &self.metadata else {
+            return Ok(());
+        };
+        let fields = [
+            ("title", metadata.title.clone()),
+            (
+                "creators",
+                Some(metadata.creators.join("; ")).filter(|c| !c.is_empty()),
+            ),
+            ("license", metadata.license.clone()),
+            ("published", metadata.publication_date.clone()),
+            ("version", metadata.version.clone()),
+        ];
+        for (name, value) in fields {
+            if let Some(value) = value {
+                writeln!(f, "{:<13}{value}", format!("{name}:"))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{datasets::ZenodoJsonSrcDataset, testutil::NoProgress};
+
+    #[tokio::test]
+    async fn test_dataset_summary() {
+        let entry = |key: &str, size: u64| {
+            serde_json::json!({
+                "key": key,
+                "file_id": format!("id-{key}"),
+                "version_id": "v1",
+                "size": size,
+                "links": { "content": format!("https://example.org/files/{key}") },
+                "checksum": "md5:f69f5b72bc79a92dc70c63c9aa142e36",
+                "created": "2024-01-01T00:00:00",
+                "updated": "2024-01-01T00:00:00",
+            })
+        };
+        let json = serde_json::json!({
+            "entries": [entry("a.csv", 1_500_000), entry("b.csv", 500_000)]
+        });
+        let ds = Dataset::new(ZenodoJsonSrcDataset::new("1", json.to_string()));
+        let summary = ds.summary(&Client::new(), NoProgress).await.unwrap();
+        assert_eq!(summary.files, 2);
+        assert_eq!(summary.size, 2_000_000);
+        assert_eq!(summary.unknown_size, 0);
+        assert!(summary
+            .to_string()
+            .contains("files:       2 (2.00 MB, 2000000 bytes)"));
+    }
+}
@@ -0,0 +1,160 @@
+//! Helpers shared by the unit tests.
+
+use digest::Digest;
+use indicatif::ProgressBar;
+use std::{
+    fs,
+    ops::Deref,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+};
+use url::Url;
+
+use crate::{crawler::ProgressManager, repo::Endpoint, Checksum, CrawlPath, FileMeta};
+
+/// Progress manager hiding every bar.
+#[derive(Clone)]
+pub(crate) struct NoProgress;
+
+impl ProgressManager for NoProgress {
+    fn insert(&self, _index: usize, _pb: ProgressBar) -> ProgressBar {
+        ProgressBar::hidden()
+    }
+
+    fn insert_from_back(&self, _index: usize, _pb: ProgressBar) -> ProgressBar {
+        ProgressBar::hidden()
+    }
+}
+
+/// Builder of a [`FileMeta`], downloaded from `https://example.org/<path>` unless
+/// another URL is set, with neither a size nor checksums.
+pub(crate) struct TestFile {
+    path: CrawlPath,
+    url: Option<Url>,
+    size: Option<u64>,
+    checksums: Vec<Checksum>,
+    created: Option<String>,
+    modified: Option<String>,
+}
+
+impl TestFile {
+    /// A file at `path` relative to the dataset root.
+    pub(crate) fn new(path: &str) -> Self {
+        TestFile::at(CrawlPath::root().join(path))
+    }
+
+    pub(crate) fn at(path: CrawlPath) -> Self {
+        TestFile {
+            path,
+            url: None,
+            size: None,
+            checksums: vec![],
+            created: None,
+            modified: None,
+        }
+    }
+
+    pub(crate) fn url(mut self, url: Url) -> Self {
+        self.url = Some(url);
+        self
+    }
+
+    pub(crate) fn size(mut self, size: u64) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    pub(crate) fn checksum(mut self, checksum: Checksum) -> Self {
+        self.checksums.push(checksum);
+        self
+    }
+
+    pub(crate) fn created(mut self, created: Option<&str>) -> Self {
+        self.created = created.map(str::to_string);
+        self
+    }
+
+    pub(crate) fn modified(mut self, modified: Option<&str>) -> Self {
+        self.modified = modified.map(str::to_string);
+        self
+    }
+
+    pub(crate) fn build(self) -> FileMeta {
+        let url = self.url.unwrap_or_else(|| {
+            Url::parse("https://example.org/")
+                .unwrap()
+                .join(self.path.relative().as_str())
+                .unwrap()
+        });
+        FileMeta::new(
+            None,
+            None,
+            self.path,
+            Endpoint {
+                parent_url: url.clone(),
+                key: None,
+            },
+            url,
+            self.size,
+            self.checksums,
+            None,
+            None,
+            self.created,
+            self.modified,
+            true,
+        )
+    }
+}
+
+/// Zenodo file entry of `content` served at `<server_uri>/files/<key>`, with its size and
+/// md5 checksum.
+pub(crate) fn zenodo_entry(server_uri: &str, key: &str, content: &str) -> serde_json::Value {
+    serde_json::json!({
+        "key": key,
+        "file_id": format!("id-{key}"),
+        "version_id": "v1",
+        "size": content.len(),
+        "links": { "content": format!("{server_uri}/files/{key}") },
+        "checksum": format!("md5:{}", hex::encode(md5::Md5::digest(content.as_bytes()))),
+        "created": "2024-01-01T00:00:00",
+        "updated": "2024-01-01T00:00:00",
+    })
+}
+
+/// Empty directory of a test, unique to the process and call so that tests running in
+/// parallel or again never share it, removed when dropped.
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    pub(crate) fn new(name: &str) -> Self {
+        static SEQ: AtomicU64 = AtomicU64::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "datahugger-test-{name}-{}-{}",
+            std::process::id(),
+            SEQ.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,20 @@
+use reqwest::ClientBuilder;
+
+#[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+compile_error!("either the `native-tls` or the `rustls` feature must be enabled");
+
+/// Returns a client builder using the TLS backend selected at build time.
+///
+/// With the default `native-tls` feature the platform library is used (OpenSSL on Linux).
+/// With the `rustls` feature, e.g. `--no-default-features --features rustls`, TLS is pure
+/// Rust and certificates are verified against the system store, so static musl builds do
+/// not depend on a system OpenSSL. rustls is used when both features are enabled.
+pub fn client_builder() -> ClientBuilder {
+    #[cfg(feature = "rustls")]
+    let builder = ClientBuilder::new().tls_backend_rustls();
+    #[cfg(all(feature = "native-tls", not(feature = "rustls")))]
+    let builder = ClientBuilder::new().tls_backend_native();
+    #[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+    let builder = ClientBuilder::new();
+    builder
+}
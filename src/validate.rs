@@ -0,0 +1,374 @@
+use exn::Exn;
+use futures_core::stream::Stream;
+use futures_util::StreamExt;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::{
+    crawler::CrawlerError, error::ErrorStatus, Checksum, DatahuggerError, FileMeta, FileOutcome,
+    Hasher,
+};
+
+/// Returns the checksum a downloaded file is validated against, sha256 is preferred when the
+/// repository provides several, crc32 and adler32 are only used as last resort.
+pub(crate) fn expected_checksum(file_meta: &FileMeta) -> Option<&Checksum> {
+    file_meta.checksum().iter().min_by_key(|c| match c {
+        Checksum::Sha256(_) => 0,
+        Checksum::Sha512(_) | Checksum::Sha384(_) | Checksum::Blake2b(_) | Checksum::Custom(_) => 1,
+        Checksum::Sha1(_) | Checksum::GitSha1(_) => 2,
+        Checksum::Md5(_) => 3,
+        Checksum::Crc32(_) | Checksum::Adler32(_) => 4,
+    })
+}
+
+/// Validates a downloaded file of `got_size` bytes, whose digest with the algorithm of
+/// [`expected_checksum`] is `got_checksum`, against the size and checksum listed for it.
+///
+/// A listed size of 0 only holds for an empty file, several repositories list externally
+/// hosted files with size 0. The length of such files and of files listed without size is
+/// not validated.
+pub(crate) fn validate_download(
+    file_meta: &FileMeta,
+    got_size: u64,
+    got_checksum: Option<String>,
+) -> Result<FileOutcome, Exn<CrawlerError>> {
+    let expected_size = file_meta.size().filter(|&size| size > 0 || got_size == 0);
+    if let Some(expected_size) = expected_size {
+        if got_size != expected_size {
+            exn::bail!(CrawlerError {
+                message: format!("size wrong, expect {expected_size}, got {got_size}"),
+                status: ErrorStatus::Permanent
+            })
+        }
+    }
+    let (Some(checksum), Some(got_checksum)) = (expected_checksum(file_meta), got_checksum) else {
+        return Ok(FileOutcome::Unverified);
+    };
+    if got_checksum != checksum.value() {
+        let cause = DatahuggerError::ChecksumMismatch {
+            expected: checksum.value().to_string(),
+            got: got_checksum,
+        };
+        let message = format!("{cause}");
+        return Err(Exn::new(cause).raise(CrawlerError {
+            message,
+            status: ErrorStatus::Permanent,
+        }));
+    }
+    if expected_size.is_some() {
+        Ok(FileOutcome::Verified)
+    } else {
+        Ok(FileOutcome::UnverifiedLength)
+    }
+}
+
+/// What to do with files a repository lists without checksum or size to validate them
+/// against, as arXiv, HAL, GitHub or DataONE do for some or all of their files, see
+/// [`DownloadOptions::validation_policy`](crate::DownloadOptions::validation_policy).
+///
+/// A listed size of 0 is taken for a placeholder once the file turns out not to be empty,
+/// the file is then only checked by the policy after it is downloaded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ValidationPolicy {
+    /// Fail such files, without downloading them, e.g. to mirror datasets for archival.
+    Strict,
+    /// Download such files with a warning, they are reported as
+    /// [`FileOutcome::Unverified`] or [`FileOutcome::UnverifiedLength`].
+    #[default]
+    WarnAndContinue,
+    /// Skip such files, with the reason.
+    Skip,
+}
+
+impl ValidationPolicy {
+    /// Applies the policy before `file_meta` is downloaded, returns why it is skipped if it
+    /// is, an error if it fails.
+    pub(crate) fn precheck(
+        self,
+        file_meta: &FileMeta,
+    ) -> Result<Option<String>, Exn<CrawlerError>> {
+        let missing = match (expected_checksum(file_meta), file_meta.size()) {
+            (Some(_), Some(_)) => return Ok(None),
+            (None, Some(_)) => "checksum",
+            (Some(_), None) => "size",
+            (None, None) => "checksum and size",
+        };
+        self.apply(file_meta, missing)
+    }
+
+    /// Applies the policy to the `outcome` of the download of `file_meta`, for what is only
+    /// known once it is downloaded.
+    pub(crate) fn check(
+        self,
+        file_meta: &FileMeta,
+        outcome: FileOutcome,
+    ) -> Result<FileOutcome, Exn<CrawlerError>> {
+        let missing = match outcome {
+            FileOutcome::Unverified | FileOutcome::SelfComputed => "checksum",
+            FileOutcome::UnverifiedLength => "valid size",
+            _ => return Ok(outcome),
+        };
+        Ok(self
+            .apply(file_meta, missing)?
+            .map_or(outcome, FileOutcome::Skipped))
+    }
+
+    fn apply(
+        self,
+        file_meta: &FileMeta,
+        missing: &str,
+    ) -> Result<Option<String>, Exn<CrawlerError>> {
+        match self {
+            ValidationPolicy::WarnAndContinue => Ok(None),
+            ValidationPolicy::Skip => Ok(Some(format!("listed without {missing}"))),
+            ValidationPolicy::Strict => exn::bail!(CrawlerError {
+                message: format!(
+                    "'{}' is listed without {missing}, required by the strict validation policy",
+                    file_meta.relative()
+                ),
+                status: ErrorStatus::Permanent,
+            }),
+        }
+    }
+}
+
+/// Incremental hashing of the content of a file, validated against the size and checksum
+/// listed by the repository once it ends.
+pub(crate) struct Validator {
+    hasher: Option<Hasher>,
+    self_checksum: bool,
+    /// sha256 of the content, when `hasher` is not one, see [`Validator::with_sha256`].
+    sha256: Option<Hasher>,
+}
+
+impl Validator {
+    /// Hashes with the algorithm of [`expected_checksum`], or sha256 if the repository
+    /// lists no checksum and `self_checksum` is set.
+    pub(crate) fn new(file_meta: &FileMeta, self_checksum: bool) -> Self {
+        let checksum = expected_checksum(file_meta);
+        let self_checksum = checksum.is_none() && self_checksum;
+        let hasher = if self_checksum {
+            Checksum::Sha256(String::new()).hasher(None)
+        } else {
+            checksum.and_then(|c| c.hasher(file_meta.size()))
+        };
+        Validator {
+            hasher,
+            self_checksum,
+            sha256: None,
+        }
+    }
+
+    /// Also hashes the content with sha256, the key of a
+    /// [`ContentStore`](crate::ContentStore), unless the checksum verified is one.
+    pub(crate) fn with_sha256(mut self) -> Self {
+        if !matches!(self.hasher, Some(Hasher::Sha256(_))) {
+            self.sha256 = Checksum::Sha256(String::new()).hasher(None);
+        }
+        self
+    }
+
+    /// Returns `true` if the content is hashed, `false` if there is no checksum to verify
+    /// nor sha256 to compute.
+    pub(crate) fn is_hashing(&self) -> bool {
+        self.hasher.is_some() || self.sha256.is_some()
+    }
+
+    pub(crate) fn update(&mut self, chunk: &[u8]) {
+        if let Some(hasher) = self.hasher.as_mut() {
+            hasher.update(chunk);
+        }
+        if let Some(sha256) = self.sha256.as_mut() {
+            sha256.update(chunk);
+        }
+    }
+
+    /// Validates the `got_size` bytes hashed against `file_meta`, returns the outcome, the
+    /// sha256 computed without checksum from the repository, and the sha256 of the content
+    /// if it was hashed with sha256.
+    pub(crate) fn finish(
+        self,
+        file_meta: &FileMeta,
+        got_size: u64,
+    ) -> Result<(FileOutcome, Option<Checksum>, Option<String>), Exn<CrawlerError>> {
+        let is_sha256 = matches!(self.hasher, Some(Hasher::Sha256(_)));
+        let got_checksum = self.hasher.map(|hasher| hex::encode(hasher.finalize()));
+        let sha256 = match self.sha256 {
+            Some(sha256) => Some(hex::encode(sha256.finalize())),
+            None => got_checksum.clone().filter(|_| is_sha256),
+        };
+        if self.self_checksum {
+            validate_download(file_meta, got_size, None)?;
+            Ok((
+                FileOutcome::SelfComputed,
+                got_checksum.map(Checksum::Sha256),
+                sha256,
+            ))
+        } else {
+            let outcome = validate_download(file_meta, got_size, got_checksum)?;
+            Ok((outcome, None, sha256))
+        }
+    }
+}
+
+/// Stream adaptor passing the content of a file through while hashing it, whose last item
+/// is an error if the content does not match the size and checksum the repository lists
+/// for the file.
+///
+/// It gives custom sinks, e.g. a database or an upload, the validation of
+/// [`DownloadExt::download_with_validation`](crate::DownloadExt::download_with_validation).
+/// The content must not be trusted before the stream ends without error.
+///
+/// # Examples
+///
+/// ```no_run
+/// # async fn run(file: datahugger::FileMeta) {
+/// use datahugger::{crawler::CrawlerError, error::ErrorStatus, ValidatedStream};
+/// use exn::ResultExt;
+/// use futures_util::{StreamExt, TryStreamExt};
+///
+/// let resp = reqwest::get(file.download_url()).await.unwrap();
+/// let body = resp.bytes_stream().map(|item| {
+///     item.or_raise(|| CrawlerError {
+///         message: "body error".to_string(),
+///         status: ErrorStatus::Temporary,
+///     })
+/// })
+/// .boxed();
+/// let mut stream = ValidatedStream::new(body, file);
+/// while let Some(bytes) = stream.try_next().await.unwrap() {
+///     // write bytes to the sink
+/// }
+/// assert!(stream.outcome().is_some());
+/// # }
+/// ```
+pub struct ValidatedStream<S> {
+    inner: S,
+    file_meta: FileMeta,
+    validator: Option<Validator>,
+    bytes: u64,
+    outcome: Option<FileOutcome>,
+}
+
+impl<S> ValidatedStream<S> {
+    /// Wraps `stream`, the content of `file_meta`.
+    #[must_use]
+    pub fn new(stream: S, file_meta: FileMeta) -> Self {
+        let validator = Validator::new(&file_meta, false);
+        ValidatedStream {
+            inner: stream,
+            file_meta,
+            validator: Some(validator),
+            bytes: 0,
+            outcome: None,
+        }
+    }
+
+    /// Number of bytes passed through so far.
+    #[must_use]
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Outcome of the validation once the stream ended without error, `None` before.
+    ///
+    /// [`FileOutcome::Unverified`] if the repository lists no checksum.
+    #[must_use]
+    pub fn outcome(&self) -> Option<&FileOutcome> {
+        self.outcome.as_ref()
+    }
+}
+
+impl<S, B> Stream for ValidatedStream<S>
+where
+    S: Stream<Item = Result<B, Exn<CrawlerError>>> + Unpin,
+    B: AsRef<[u8]>,
+{
+    type Item = Result<B, Exn<CrawlerError>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let item = match this.inner.poll_next_unpin(cx) {
+            Poll::Ready(item) => item,
+            Poll::Pending => return Poll::Pending,
+        };
+        match item {
+            Some(Ok(chunk)) => {
+                if let Some(validator) = this.validator.as_mut() {
+                    validator.update(chunk.as_ref());
+                }
+                this.bytes += chunk.as_ref().len() as u64;
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            Some(Err(err)) => Poll::Ready(Some(Err(err))),
+            None => {
+                let Some(validator) = this.validator.take() else {
+                    return Poll::Ready(None);
+                };
+                match validator.finish(&this.file_meta, this.bytes) {
+                    Ok((outcome, ..)) => {
+                        this.outcome = Some(outcome);
+                        Poll::Ready(None)
+                    }
+                    Err(err) => Poll::Ready(Some(Err(err))),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{crawler::CrawlerError, error::ErrorStatus, testutil::TestFile};
+    use bytes::Bytes;
+    use futures_util::{stream, TryStreamExt};
+
+    fn file_meta(size: u64, md5: &str) -> FileMeta {
+        TestFile::new("a.txt")
+            .size(size)
+            .checksum(Checksum::Md5(md5.to_string()))
+            .build()
+    }
+
+    fn chunks(
+        content: Vec<&'static str>,
+    ) -> impl Stream<Item = Result<Bytes, Exn<CrawlerError>>> + Unpin {
+        stream::iter(
+            content
+                .into_iter()
+                .map(|c| Ok(Bytes::from_static(c.as_bytes()))),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_validated_stream() {
+        // md5 of "hello"
+        let meta = file_meta(5, "5d41402abc4b2a76b9719d911017c592");
+        let mut stream = ValidatedStream::new(chunks(vec!["hel", "lo"]), meta.clone());
+        let got: Vec<Bytes> = (&mut stream).try_collect().await.unwrap();
+        assert_eq!(got.concat(), b"hello");
+        assert_eq!(stream.bytes(), 5);
+        assert_eq!(stream.outcome(), Some(&FileOutcome::Verified));
+
+        let mut stream = ValidatedStream::new(chunks(vec!["hallo"]), meta.clone());
+        assert!(stream.try_next().await.unwrap().is_some());
+        assert!(stream.try_next().await.is_err());
+        assert_eq!(stream.outcome(), None);
+
+        let mut stream = ValidatedStream::new(chunks(vec!["hell"]), meta);
+        let err = (&mut stream).try_collect::<Vec<_>>().await.unwrap_err();
+        assert!(err.to_string().contains("size wrong"));
+
+        let failing = stream::iter([Err(Exn::new(CrawlerError {
+            message: "broken".to_string(),
+            status: ErrorStatus::Temporary,
+        }))]);
+        let mut stream: ValidatedStream<_> =
+            ValidatedStream::new(failing, file_meta(0, "0".repeat(32).as_str()));
+        let res: Result<Option<Bytes>, _> = stream.try_next().await;
+        assert!(res.is_err());
+    }
+}
@@ -0,0 +1,388 @@
+use exn::{Exn, OptionExt, ResultExt};
+use reqwest::Client;
+use serde_json::Value as JsonValue;
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs::{self, File},
+    io::Read,
+    path::Path,
+};
+
+use crate::{
+    crawler::ProgressManager, error::ErrorStatus, validate::expected_checksum, Checksum, Dataset,
+    DownloadPlan, LOCK_FILE_NAME, MANIFEST_FILE_NAME,
+};
+
+#[derive(Debug)]
+pub struct VerifyError {
+    pub message: String,
+    pub status: ErrorStatus,
+}
+
+impl std::fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "verify fail: {}", self.message)
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// Outcome of a single file in a [`VerifyReport`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyOutcome {
+    /// Present, size and checksum (when known) match.
+    Intact,
+    /// Expected but not present in the directory.
+    Missing,
+    /// Present but its size or checksum differ, with the mismatch.
+    Modified(String),
+    /// Present in the directory but not part of the dataset.
+    Extra,
+}
+
+/// Result of checking a single local file.
+#[derive(Debug, Clone)]
+pub struct FileVerification {
+    /// path relative to the verified directory, with `/` separators.
+    pub path: String,
+    pub outcome: VerifyOutcome,
+}
+
+/// Per-file result of re-validating a downloaded dataset, returned by [`verify_manifest`]
+/// and [`verify_remote`].
+///
+/// Files are sorted by path.
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    pub files: Vec<FileVerification>,
+}
+
+impl VerifyReport {
+    /// Returns `true` if no file is missing, modified or extra.
+    #[must_use]
+    pub fn is_intact(&self) -> bool {
+        self.files
+            .iter()
+            .all(|f| f.outcome == VerifyOutcome::Intact)
+    }
+
+    /// Iterates over the files that are intact.
+    pub fn intact(&self) -> impl Iterator<Item = &FileVerification> {
+        self.files
+            .iter()
+            .filter(|f| f.outcome == VerifyOutcome::Intact)
+    }
+
+    /// Iterates over the files that are missing.
+    pub fn missing(&self) -> impl Iterator<Item = &FileVerification> {
+        self.files
+            .iter()
+            .filter(|f| f.outcome == VerifyOutcome::Missing)
+    }
+
+    /// Iterates over the files that were modified.
+    pub fn modified(&self) -> impl Iterator<Item = &FileVerification> {
+        self.files
+            .iter()
+            .filter(|f| matches!(f.outcome, VerifyOutcome::Modified(_)))
+    }
+
+    /// Iterates over the files that are not part of the dataset.
+    pub fn extra(&self) -> impl Iterator<Item = &FileVerification> {
+        self.files
+            .iter()
+            .filter(|f| f.outcome == VerifyOutcome::Extra)
+    }
+}
+
+impl std::fmt::Display for VerifyReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "intact {} files, missing {}, modified {}, extra {}",
+            self.intact().count(),
+            self.missing().count(),
+            self.modified().count(),
+            self.extra().count(),
+        )?;
+        for file in &self.files {
+            match &file.outcome {
+                VerifyOutcome::Intact => {}
+                VerifyOutcome::Missing => writeln!(f, "  missing  {}", file.path)?,
+                VerifyOutcome::Modified(mismatch) => {
+                    writeln!(f, "  modified {}: {mismatch}", file.path)?;
+                }
+                VerifyOutcome::Extra => writeln!(f, "  extra    {}", file.path)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A file the verified directory should contain.
+struct ExpectedFile {
+    path: String,
+    size: Option<u64>,
+    checksum: Option<Checksum>,
+}
+
+/// Re-validates the files of `dir` against the [`MANIFEST_FILE_NAME`] manifest written when
+/// it was downloaded, without contacting the repository.
+///
+/// Files listed without checksum are only checked for their size.
+///
+/// # Errors
+/// when the manifest cannot be read or is malformed, or a local file cannot be read.
+pub fn verify_manifest(dir: &Path) -> Result<VerifyReport, Exn<VerifyError>> {
+    let expected = read_manifest(dir)?;
+    verify_files(dir, expected)
+}
+
+/// Reads the files listed in the [`MANIFEST_FILE_NAME`] manifest of `dir`.
+fn read_manifest(dir: &Path) -> Result<Vec<ExpectedFile>, Exn<VerifyError>> {
+    let path = dir.join(MANIFEST_FILE_NAME);
+    let content = fs::read(&path).or_raise(|| VerifyError {
+        message: format!("cannot read manifest at '{}'", path.display()),
+        status: ErrorStatus::Permanent,
+    })?;
+    let manifest: JsonValue = serde_json::from_slice(&content).or_raise(|| VerifyError {
+        message: format!("manifest at '{}' is not valid json", path.display()),
+        status: ErrorStatus::Permanent,
+    })?;
+    let files = manifest
+        .get("files")
+        .and_then(JsonValue::as_array)
+        .ok_or_raise(|| VerifyError {
+            message: format!("no 'files' in manifest at '{}'", path.display()),
+            status: ErrorStatus::Permanent,
+        })?;
+
+    let mut expected = Vec::with_capacity(files.len());
+    for file in files {
+        let file_path = file
+            .get("path")
+            .and_then(JsonValue::as_str)
+            .ok_or_raise(|| VerifyError {
+                message: format!("file without 'path' in manifest at '{}'", path.display()),
+                status: ErrorStatus::Permanent,
+            })?;
+        let checksum = match file.get("checksum").and_then(JsonValue::as_str) {
+            Some(checksum) => {
+                let (algo, _) = checksum.split_once(':').unwrap_or_default();
+                Some(Checksum::parse(algo, checksum).or_raise(|| VerifyError {
+                    message: format!("invalid checksum of '{file_path}' in manifest"),
+                    status: ErrorStatus::Permanent,
+                })?)
+            }
+            None => None,
+        };
+        expected.push(ExpectedFile {
+            path: file_path.to_string(),
+            size: file.get("size").and_then(JsonValue::as_u64),
+            checksum,
+        });
+    }
+    Ok(expected)
+}
+
+/// Re-validates the files of `dir` against the file list of `dataset` crawled again from the
+/// repository, for directories downloaded without manifest.
+///
+/// Files filtered out at download time are reported as missing. Files the repository lists
+/// without checksum are checked against the checksum recorded in the manifest of `dir`, if
+/// any, e.g. computed with
+/// [`DownloadOptions::self_checksum`](crate::DownloadOptions::self_checksum).
+///
+/// # Errors
+/// when crawling the dataset fails, the manifest is malformed or a local file cannot be read.
+pub async fn verify_remote(
+    dataset: Dataset,
+    client: &Client,
+    dir: &Path,
+    mp: impl ProgressManager,
+) -> Result<VerifyReport, Exn<VerifyError>> {
+    let plan = DownloadPlan::crawl(dataset, client, mp)
+        .await
+        .or_raise(|| VerifyError {
+            message: "cannot crawl the dataset to verify against".to_string(),
+            status: ErrorStatus::Temporary,
+        })?;
+    let mut recorded = HashMap::new();
+    if dir.join(MANIFEST_FILE_NAME).exists() {
+        for file in read_manifest(dir)? {
+            if let Some(checksum) = file.checksum {
+                recorded.insert(file.path, checksum);
+            }
+        }
+    }
+    let expected = plan
+        .files()
+        .iter()
+        .filter(|f| f.is_downloadable())
+        .map(|f| {
+            let path = f.relative().as_str().to_string();
+            let checksum = expected_checksum(f)
+                .cloned()
+                .or_else(|| recorded.remove(&path));
+            ExpectedFile {
+                path,
+                size: f.size(),
+                checksum,
+            }
+        })
+        .collect();
+    verify_files(dir, expected)
+}
+
+fn verify_files(dir: &Path, expected: Vec<ExpectedFile>) -> Result<VerifyReport, Exn<VerifyError>> {
+    let mut local = BTreeSet::new();
+    list_local_files(dir, "", &mut local)?;
+
+    let mut files = Vec::with_capacity(expected.len());
+    for file in expected {
+        let outcome = if local.remove(&file.path) {
+            check_file(&dir.join(&file.path), &file)?
+        } else {
+            VerifyOutcome::Missing
+        };
+        files.push(FileVerification {
+            path: file.path,
+            outcome,
+        });
+    }
+    files.extend(local.into_iter().map(|path| FileVerification {
+        path,
+        outcome: VerifyOutcome::Extra,
+    }));
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(VerifyReport { files })
+}
+
+/// Collects the files under `dir` as `/` separated paths relative to the verified root,
+/// skipping the manifest and lock file of the root.
+fn list_local_files(
+    dir: &Path,
+    prefix: &str,
+    files: &mut BTreeSet<String>,
+) -> Result<(), Exn<VerifyError>> {
+    let read_dir = fs::read_dir(dir).or_raise(|| VerifyError {
+        message: format!("cannot read dir '{}'", dir.display()),
+        status: ErrorStatus::Permanent,
+    })?;
+    for entry in read_dir {
+        let entry = entry.or_raise(|| VerifyError {
+            message: format!("cannot read entry of dir '{}'", dir.display()),
+            status: ErrorStatus::Permanent,
+        })?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if prefix.is_empty() && (name == MANIFEST_FILE_NAME || name == LOCK_FILE_NAME) {
+            continue;
+        }
+        let path = format!("{prefix}{name}");
+        if entry.path().is_dir() {
+            list_local_files(&entry.path(), &format!("{path}/"), files)?;
+        } else {
+            files.insert(path);
+        }
+    }
+    Ok(())
+}
+
+fn check_file(path: &Path, expected: &ExpectedFile) -> Result<VerifyOutcome, Exn<VerifyError>> {
+    let err = || VerifyError {
+        message: format!("cannot read file '{}'", path.display()),
+        status: ErrorStatus::Permanent,
+    };
+    let size = fs::metadata(path).or_raise(err)?.len();
+    if let Some(expected_size) = expected.size {
+        if size != expected_size {
+            return Ok(VerifyOutcome::Modified(format!(
+                "size wrong, expect {expected_size}, got {size}"
+            )));
+        }
+    }
+    let Some(checksum) = &expected.checksum else {
+        return Ok(VerifyOutcome::Intact);
+    };
+
+    let mut hasher = checksum
+        .hasher(Some(size))
+        .expect("hasher of content of known length");
+    let mut file = File::open(path).or_raise(err)?;
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf).or_raise(err)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    let got = hex::encode(hasher.finalize());
+    if got == checksum.value() {
+        Ok(VerifyOutcome::Intact)
+    } else {
+        Ok(VerifyOutcome::Modified(format!(
+            "checksum wrong, expect {}, got {got}",
+            checksum.value()
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        datasets::ZenodoJsonSrcDataset,
+        testutil::{zenodo_entry, TempDir},
+        DownloadExt, DownloadOptions,
+    };
+    use indicatif::MultiProgress;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_verify_manifest() {
+        let mock_server = MockServer::start().await;
+        let mut entries = Vec::new();
+        for (key, content) in [("a.txt", "hello"), ("dir/b.txt", "world")] {
+            Mock::given(method("GET"))
+                .and(path(format!("/files/{key}")))
+                .respond_with(ResponseTemplate::new(200).set_body_string(content))
+                .mount(&mock_server)
+                .await;
+            entries.push(zenodo_entry(&mock_server.uri(), key, content));
+        }
+        let json = serde_json::json!({ "entries": entries });
+        let ds = Dataset::new(ZenodoJsonSrcDataset::new("1", json.to_string()));
+
+        let dst = TempDir::new("verify-manifest");
+        ds.download_with_validation(
+            &Client::new(),
+            &dst,
+            MultiProgress::new(),
+            &DownloadOptions::new().manifest(true),
+        )
+        .await
+        .unwrap();
+        assert!(verify_manifest(&dst).unwrap().is_intact());
+
+        fs::write(dst.join("a.txt"), "HELLO").unwrap();
+        fs::remove_file(dst.join("dir/b.txt")).unwrap();
+        fs::write(dst.join("dir/c.txt"), "new").unwrap();
+        let report = verify_manifest(&dst).unwrap();
+        assert!(!report.is_intact());
+        let outcomes = report
+            .files
+            .iter()
+            .map(|f| (f.path.as_str(), &f.outcome))
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            outcomes.as_slice(),
+            [
+                ("a.txt", VerifyOutcome::Modified(_)),
+                ("dir/b.txt", VerifyOutcome::Missing),
+                ("dir/c.txt", VerifyOutcome::Extra),
+            ]
+        ));
+    }
+}
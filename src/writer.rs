@@ -0,0 +1,219 @@
+use async_trait::async_trait;
+use exn::{Exn, OptionExt, ResultExt};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    pin::Pin,
+};
+use tokio::{fs, io::AsyncWrite};
+
+use crate::{error::ErrorStatus, CrawlPath};
+
+#[derive(Debug)]
+pub struct WriterError {
+    pub message: String,
+    pub status: ErrorStatus,
+}
+
+impl std::fmt::Display for WriterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "writer fail: {}", self.message)
+    }
+}
+
+impl std::error::Error for WriterError {}
+
+/// Sink the bytes of one file are streamed into, the object is complete once it is shut
+/// down, see [`AsyncWriteExt::shutdown`](tokio::io::AsyncWriteExt::shutdown).
+pub type ObjectSink = Pin<Box<dyn AsyncWrite + Send>>;
+
+/// Destination the files of a download are written to, see
+/// [`DownloadOptions::writer`](crate::DownloadOptions::writer).
+///
+/// Files are written to the local filesystem with [`LocalWriter`] by default. Implement it
+/// to stream files elsewhere, e.g. into a bucket, without staging them on local disk.
+#[async_trait]
+pub trait ObjectWriter: fmt::Debug + Send + Sync {
+    /// Opens the object at the relative crawl path `path` for writing, replacing the object
+    /// already there if any.
+    async fn create(&self, path: &CrawlPath) -> Result<ObjectSink, Exn<WriterError>>;
+
+    /// Returns `true` if objects are files below a local directory, which enables features
+    /// that need one, e.g. [`DownloadOptions::xattrs`](crate::DownloadOptions::xattrs).
+    fn is_local(&self) -> bool {
+        false
+    }
+}
+
+/// Suffix of the file a local download is written to until it is validated.
+pub(crate) const PART_SUFFIX: &str = ".part";
+
+/// Writes files below a local directory, creating the folders of their paths.
+#[derive(Debug, Clone)]
+pub struct LocalWriter {
+    root: PathBuf,
+}
+
+impl LocalWriter {
+    #[must_use]
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        LocalWriter { root: root.into() }
+    }
+
+    /// Path the file at the crawl path `path` is written to until it is validated and
+    /// renamed to its final path with [`LocalWriter::commit`], e.g. `data/a.csv.part`.
+    pub(crate) fn part_path(&self, path: &CrawlPath) -> PathBuf {
+        let mut part = self.root.join(path.relative()).into_os_string();
+        part.push(PART_SUFFIX);
+        PathBuf::from(part)
+    }
+
+    /// Opens the part file of `path` for writing, appending to its first `resume_at` bytes
+    /// when set, e.g. to resume an interrupted download, the bytes after them are dropped.
+    pub(crate) async fn create_part(
+        &self,
+        path: &CrawlPath,
+        resume_at: Option<u64>,
+    ) -> Result<ObjectSink, Exn<WriterError>> {
+        let part = self.part_path(path);
+        if let Some(offset) = resume_at {
+            let err = || WriterError {
+                message: format!("fail on open file at {}", part.display()),
+                status: ErrorStatus::Permanent,
+            };
+            let fh = fs::OpenOptions::new()
+                .append(true)
+                .open(&part)
+                .await
+                .or_raise(err)?;
+            fh.set_len(offset).await.or_raise(err)?;
+            Ok(Box::pin(fh))
+        } else {
+            open(&part).await
+        }
+    }
+
+    /// Atomically renames the part file of `path` to its final path, replacing the file
+    /// already there if any.
+    pub(crate) async fn commit(&self, path: &CrawlPath) -> Result<(), Exn<WriterError>> {
+        let part = self.part_path(path);
+        let dst = self.root.join(path.relative());
+        fs::rename(&part, &dst).await.or_raise(|| WriterError {
+            message: format!("fail to rename {} to {}", part.display(), dst.display()),
+            status: ErrorStatus::Permanent,
+        })
+    }
+
+    /// Removes the part file of `path`, e.g. once its content failed validation so that
+    /// it is not resumed, or once its download is cancelled. A missing part file is fine.
+    pub(crate) async fn discard(&self, path: &CrawlPath) {
+        let part = self.part_path(path);
+        match fs::remove_file(&part).await {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                tracing::warn!("fail to remove {}: {err}", part.display());
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Creates the file at `path` and the folders of its path.
+async fn open(path: &Path) -> Result<ObjectSink, Exn<WriterError>> {
+    // NOTE: like in zenodo, the file path can exist without its parent dir as Dir entity
+    // being created first. To cover that case, the folder of the path will be created no
+    // matter it existed or not using `create_dir_all`.
+    // See issue #54.
+    let parent_dir = path.parent().ok_or_raise(|| WriterError {
+        message: format!("connot get parent dir for '{}'", path.display()),
+        status: ErrorStatus::Permanent,
+    })?;
+    fs::create_dir_all(parent_dir)
+        .await
+        .or_raise(|| WriterError {
+            message: format!("connot create folder dir of '{}'", parent_dir.display()),
+            status: ErrorStatus::Permanent,
+        })?;
+    let fh = fs::File::create(path).await.or_raise(|| WriterError {
+        message: format!("fail on create file at {}", path.display()),
+        status: ErrorStatus::Permanent,
+    })?;
+    Ok(Box::pin(fh))
+}
+
+#[async_trait]
+impl ObjectWriter for LocalWriter {
+    async fn create(&self, path: &CrawlPath) -> Result<ObjectSink, Exn<WriterError>> {
+        open(&self.root.join(path.relative())).await
+    }
+
+    fn is_local(&self) -> bool {
+        true
+    }
+}
+
+/// Writes files as objects of an [`ObjectStore`](object_store::ObjectStore), e.g. an S3 or
+/// MinIO bucket, below a prefix. Large files are uploaded in parts.
+///
+/// ```no_run
+/// # fn run(store: std::sync::Arc<dyn object_store::ObjectStore>) {
+/// use datahugger::{DownloadOptions, ObjectStoreWriter};
+///
+/// let writer = ObjectStoreWriter::new(store, "datasets/zenodo-1234");
+/// let options = DownloadOptions::new().writer(writer);
+/// # }
+/// ```
+#[cfg(feature = "object-store")]
+#[derive(Debug, Clone)]
+pub struct ObjectStoreWriter {
+    store: std::sync::Arc<dyn object_store::ObjectStore>,
+    prefix: object_store::path::Path,
+}
+
+#[cfg(feature = "object-store")]
+impl ObjectStoreWriter {
+    #[must_use]
+    pub fn new(store: std::sync::Arc<dyn object_store::ObjectStore>, prefix: &str) -> Self {
+        ObjectStoreWriter {
+            store,
+            prefix: object_store::path::Path::from(prefix),
+        }
+    }
+}
+
+#[cfg(feature = "object-store")]
+#[async_trait]
+impl ObjectWriter for ObjectStoreWriter {
+    async fn create(&self, path: &CrawlPath) -> Result<ObjectSink, Exn<WriterError>> {
+        let location = self
+            .prefix
+            .parts()
+            .chain(object_store::path::Path::from(path.relative().as_str()).parts())
+            .collect::<object_store::path::Path>();
+        let sink = object_store::buffered::BufWriter::new(self.store.clone(), location);
+        Ok(Box::pin(sink))
+    }
+}
+
+#[cfg(all(test, feature = "object-store"))]
+mod tests {
+    use super::*;
+    use object_store::{memory::InMemory, ObjectStoreExt};
+    use std::sync::Arc;
+    use tokio::io::AsyncWriteExt;
+
+    #[tokio::test]
+    async fn test_object_store_writer() {
+        let store = Arc::new(InMemory::new());
+        let writer = ObjectStoreWriter::new(store.clone(), "bucket/prefix");
+        let mut sink = writer
+            .create(&CrawlPath::root().join("raw/a.csv"))
+            .await
+            .unwrap();
+        sink.write_all(b"a,b\n").await.unwrap();
+        sink.shutdown().await.unwrap();
+
+        let location = object_store::path::Path::from("bucket/prefix/raw/a.csv");
+        let bytes = store.get(&location).await.unwrap().bytes().await.unwrap();
+        assert_eq!(bytes.as_ref(), b"a,b\n");
+    }
+}
@@ -0,0 +1,602 @@
+use async_stream::try_stream;
+use bytes::Bytes;
+use exn::{Exn, OptionExt, ResultExt};
+use flate2::{Decompress, FlushDecompress, Status};
+use futures_core::stream::{BoxStream, Stream};
+use futures_util::{stream, StreamExt};
+use reqwest::{
+    header::{HeaderMap, HeaderValue, CONTENT_RANGE, RANGE},
+    Client, Response, StatusCode,
+};
+use tracing::warn;
+use url::Url;
+
+use crate::retry::RetryExt;
+use crate::{
+    crawler::CrawlerError,
+    error::ErrorStatus,
+    redirect::{default_client_options, follow_cross_host_with},
+    Checksum, ClientOptions, CrawlPath, Entry, FileMeta,
+};
+
+const EOCD_SIGNATURE: u32 = 0x0605_4b50;
+const EOCD_LEN: usize = 22;
+const ZIP64_EOCD_LOCATOR_SIGNATURE: u32 = 0x0706_4b50;
+const ZIP64_EOCD_LOCATOR_LEN: usize = 20;
+const ZIP64_EOCD_SIGNATURE: u32 = 0x0606_4b50;
+const ZIP64_EOCD_LEN: u64 = 56;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const CENTRAL_HEADER_LEN: usize = 46;
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const LOCAL_HEADER_LEN: u64 = 30;
+/// Id of the extra field holding the 64-bit sizes and offset of a zip64 member.
+const ZIP64_EXTRA_ID: u16 = 0x0001;
+
+/// Bytes read from the end of an archive to find its end of central directory record, which
+/// ends with a comment of at most 64 KiB and follows the zip64 locator if any.
+const TAIL_LEN: usize = ZIP64_EOCD_LOCATOR_LEN + EOCD_LEN + 0xFFFF;
+
+/// Location of a file inside a zip archive, from the central directory of the archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ZipMember {
+    /// Offset of the local file header in the archive.
+    header_offset: u64,
+    compressed_size: u64,
+    /// Compression method, only 0 (stored) and 8 (deflate) are supported.
+    method: u16,
+}
+
+/// A file listed in the central directory of an archive.
+#[derive(Debug)]
+struct CentralEntry {
+    name: String,
+    size: u64,
+    crc32: u32,
+    encrypted: bool,
+    member: ZipMember,
+}
+
+fn u16_at(buf: &[u8], at: usize) -> Option<u16> {
+    let bytes = buf.get(at..at + 2)?;
+    Some(u16::from_le_bytes(bytes.try_into().ok()?))
+}
+
+fn u32_at(buf: &[u8], at: usize) -> Option<u32> {
+    let bytes = buf.get(at..at + 4)?;
+    Some(u32::from_le_bytes(bytes.try_into().ok()?))
+}
+
+fn u64_at(buf: &[u8], at: usize) -> Option<u64> {
+    let bytes = buf.get(at..at + 8)?;
+    Some(u64::from_le_bytes(bytes.try_into().ok()?))
+}
+
+/// Requests the byte `range` of `url`, e.g. `0-29` or `-22` for the last 22 bytes.
+///
+/// # Errors
+/// when the request fails or the server answers without a partial content.
+async fn get_range(
+    client: &Client,
+    client_options: &ClientOptions,
+    url: &Url,
+    range: &str,
+) -> Result<Response, Exn<CrawlerError>> {
+    let mut headers = HeaderMap::new();
+    // Safe to unwrap: a byte range is ascii
+    headers.insert(
+        RANGE,
+        HeaderValue::from_str(&format!("bytes={range}")).unwrap(),
+    );
+    let resp = client
+        .get(url.clone())
+        .headers(headers.clone())
+        .send_with_retry()
+        .await
+        .or_raise(|| CrawlerError {
+            message: format!("fail at client sent GET {url}"),
+            status: ErrorStatus::Temporary,
+        })?;
+    let resp = follow_cross_host_with(resp, headers, client_options)
+        .await
+        .or_raise(|| CrawlerError {
+            message: format!("fail to follow the redirect of {url}"),
+            status: ErrorStatus::Temporary,
+        })?
+        .error_for_status()
+        .or_raise(|| CrawlerError {
+            message: format!("fail GET {url}"),
+            status: ErrorStatus::Permanent,
+        })?;
+    if resp.status() != StatusCode::PARTIAL_CONTENT {
+        exn::bail!(CrawlerError {
+            message: format!("{url} does not support range requests"),
+            status: ErrorStatus::Permanent,
+        });
+    }
+    Ok(resp)
+}
+
+/// Reads the byte `range` of `url`, with the total length of the resource if the server
+/// tells it. Redirects to another host are followed with the client of `client_options`.
+pub(crate) async fn read_range(
+    client: &Client,
+    client_options: &ClientOptions,
+    url: &Url,
+    range: &str,
+) -> Result<(Bytes, Option<u64>), Exn<CrawlerError>> {
+    let resp = get_range(client, client_options, url, range).await?;
+    // e.g. 'bytes 100-199/1000'
+    let total = resp
+        .headers()
+        .get(CONTENT_RANGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.rsplit_once('/'))
+        .and_then(|(_, total)| total.parse().ok());
+    let bytes = resp.bytes().await.or_raise(|| CrawlerError {
+        message: format!("Failed to get bytes from response of {url}"),
+        status: ErrorStatus::Temporary,
+    })?;
+    Ok((bytes, total))
+}
+
+/// Central directory location from the end of central directory record.
+struct CentralDirectory {
+    entries: u64,
+    size: u64,
+    offset: u64,
+}
+
+/// Parses the end of central directory record at the end of `tail`, returns the directory
+/// and the offset of the zip64 end of central directory record if the archive is a zip64
+/// one.
+fn parse_eocd(tail: &[u8]) -> Option<(CentralDirectory, Option<u64>)> {
+    let eocd = (0..=tail.len().checked_sub(EOCD_LEN)?)
+        .rev()
+        .find(|&at| u32_at(tail, at) == Some(EOCD_SIGNATURE))?;
+    let directory = CentralDirectory {
+        entries: u64::from(u16_at(tail, eocd + 10)?),
+        size: u64::from(u32_at(tail, eocd + 12)?),
+        offset: u64::from(u32_at(tail, eocd + 16)?),
+    };
+    // the zip64 locator precedes the record
+    let zip64 = eocd
+        .checked_sub(ZIP64_EOCD_LOCATOR_LEN)
+        .filter(|&at| u32_at(tail, at) == Some(ZIP64_EOCD_LOCATOR_SIGNATURE))
+        .and_then(|at| u64_at(tail, at + 8));
+    Some((directory, zip64))
+}
+
+fn parse_zip64_eocd(record: &[u8]) -> Option<CentralDirectory> {
+    if u32_at(record, 0)? != ZIP64_EOCD_SIGNATURE {
+        return None;
+    }
+    Some(CentralDirectory {
+        entries: u64_at(record, 32)?,
+        size: u64_at(record, 40)?,
+        offset: u64_at(record, 48)?,
+    })
+}
+
+/// Parses the `count` file headers of the central directory `cd`, directories are left out.
+fn parse_central_directory(cd: &[u8], count: u64) -> Option<Vec<CentralEntry>> {
+    let mut entries = Vec::new();
+    let mut at = 0;
+    for _ in 0..count {
+        let header = cd.get(at..at + CENTRAL_HEADER_LEN)?;
+        if u32_at(header, 0)? != CENTRAL_HEADER_SIGNATURE {
+            return None;
+        }
+        let flags = u16_at(header, 8)?;
+        let method = u16_at(header, 10)?;
+        let crc32 = u32_at(header, 16)?;
+        let mut compressed_size = u64::from(u32_at(header, 20)?);
+        let mut size = u64::from(u32_at(header, 24)?);
+        let name_len = usize::from(u16_at(header, 28)?);
+        let extra_len = usize::from(u16_at(header, 30)?);
+        let comment_len = usize::from(u16_at(header, 32)?);
+        let mut header_offset = u64::from(u32_at(header, 42)?);
+
+        let name_start = at + CENTRAL_HEADER_LEN;
+        let extra_start = name_start + name_len;
+        let name = String::from_utf8_lossy(cd.get(name_start..extra_start)?).into_owned();
+        let extra = cd.get(extra_start..extra_start + extra_len)?;
+        at = extra_start + extra_len + comment_len;
+
+        // the zip64 extra field holds, in this order, the values set to 0xFFFFFFFF above
+        let mut zip64 = zip64_extra(extra).chunks_exact(8).map(|value| {
+            // Safe to unwrap: chunks are 8 bytes long
+            u64::from_le_bytes(value.try_into().unwrap())
+        });
+        for value in [&mut size, &mut compressed_size, &mut header_offset] {
+            if *value == u64::from(u32::MAX) {
+                *value = zip64.next()?;
+            }
+        }
+        if name.ends_with('/') {
+            // folders are created on demand as parents of their files
+            continue;
+        }
+        entries.push(CentralEntry {
+            name,
+            size,
+            crc32,
+            encrypted: flags & 1 == 1,
+            member: ZipMember {
+                header_offset,
+                compressed_size,
+                method,
+            },
+        });
+    }
+    Some(entries)
+}
+
+/// Returns the data of the zip64 extended information in the `extra` fields of a header.
+fn zip64_extra(mut extra: &[u8]) -> &[u8] {
+    while let (Some(id), Some(len)) = (u16_at(extra, 0), u16_at(extra, 2)) {
+        let Some(data) = extra.get(4..4 + usize::from(len)) else {
+            break;
+        };
+        if id == ZIP64_EXTRA_ID {
+            return data;
+        }
+        extra = &extra[4 + usize::from(len)..];
+    }
+    &[]
+}
+
+/// Reads the central directory of the zip archive at `url` with range requests, without
+/// downloading the archive.
+async fn read_central_directory(
+    client: &Client,
+    url: &Url,
+) -> Result<Vec<CentralEntry>, Exn<CrawlerError>> {
+    let malformed = |what: &str| CrawlerError {
+        message: format!("{url} is not a valid zip archive, {what}"),
+        status: ErrorStatus::Permanent,
+    };
+    let options = default_client_options();
+    let (tail, total) = read_range(client, options, url, &format!("-{TAIL_LEN}")).await?;
+    let tail_start = total
+        .and_then(|total| total.checked_sub(tail.len() as u64))
+        .ok_or_raise(|| CrawlerError {
+            message: format!("no total length of {url} in its range response"),
+            status: ErrorStatus::Permanent,
+        })?;
+    let (mut directory, zip64) =
+        parse_eocd(&tail).ok_or_raise(|| malformed("no end of central directory"))?;
+    if let Some(record_offset) = zip64 {
+        let record = match record_offset.checked_sub(tail_start) {
+            Some(at) => tail.slice(usize::try_from(at).unwrap_or(usize::MAX).min(tail.len())..),
+            None => {
+                let range = format!("{record_offset}-{}", record_offset + ZIP64_EOCD_LEN - 1);
+                read_range(client, options, url, &range).await?.0
+            }
+        };
+        directory =
+            parse_zip64_eocd(&record).ok_or_raise(|| malformed("invalid zip64 end record"))?;
+    }
+    if directory.size == 0 {
+        return Ok(Vec::new());
+    }
+    let cd = match directory.offset.checked_sub(tail_start) {
+        Some(at) => tail.slice(usize::try_from(at).unwrap_or(usize::MAX).min(tail.len())..),
+        None => {
+            let end = directory.offset + directory.size - 1;
+            read_range(client, options, url, &format!("{}-{end}", directory.offset))
+                .await?
+                .0
+        }
+    };
+    parse_central_directory(&cd, directory.entries)
+        .ok_or_raise(|| malformed("invalid central directory"))
+}
+
+/// Member files of `archive`, in a folder named after it, e.g. member `a/b.csv` of
+/// `data.zip` at `data/a/b.csv`.
+fn member_files(archive: &FileMeta, entries: Vec<CentralEntry>) -> Vec<FileMeta> {
+    let relative = archive.relative();
+    let folder = &relative.as_str()[..relative.as_str().len() - ".zip".len()];
+    entries
+        .into_iter()
+        .filter(|entry| {
+            // members must stay inside the folder of the archive
+            let unsafe_path = entry.name.starts_with('/')
+                || entry.name.contains('\\')
+                || entry.name.split('/').any(|name| name == "..");
+            if unsafe_path {
+                warn!(path = %relative, "skip zip member '{}' outside the archive", entry.name);
+            }
+            !unsafe_path
+        })
+        .map(|entry| {
+            let filename = entry
+                .name
+                .rsplit('/')
+                .next()
+                .unwrap_or_default()
+                .to_string();
+            let downloadable = !entry.encrypted && matches!(entry.member.method, 0 | 8);
+            FileMeta::new(
+                Some(filename),
+                None,
+                CrawlPath::root().join(&format!("{folder}/{}", entry.name)),
+                archive.endpoint(),
+                archive.download_url(),
+                Some(entry.size),
+                vec![Checksum::Crc32(format!("{:08x}", entry.crc32))],
+                mime_guess::from_path(&entry.name).first(),
+                archive.version().map(str::to_string),
+                archive.creation_date().map(str::to_string),
+                archive.last_modification_date().map(str::to_string),
+                downloadable,
+            )
+            .with_zip_member(entry.member)
+        })
+        .collect()
+}
+
+/// Lists the files contained in the zip archive `archive`, reading its central directory with
+/// HTTP range requests, without downloading the archive.
+///
+/// Members are listed as for [`expand_zip`], and can be downloaded one by one, e.g. with
+/// [`Dataset::fetch_file`](crate::Dataset::fetch_file).
+///
+/// # Errors
+/// Returns an error if the server does not support range requests or the archive is not a
+/// valid zip archive.
+pub async fn zip_members(
+    client: &Client,
+    archive: &FileMeta,
+) -> Result<Vec<FileMeta>, Exn<CrawlerError>> {
+    let central = read_central_directory(client, &archive.download_url()).await?;
+    Ok(member_files(archive, central))
+}
+
+/// Replaces the zip archives among crawled `entries` by the files they contain, so that
+/// single members can be selected and downloaded without downloading the whole archive.
+///
+/// The central directory of each archive is read with HTTP range requests. Members are
+/// listed in a folder named after the archive, e.g. member `a/b.csv` of `data.zip` at
+/// `data/a/b.csv`, and validated against their CRC-32 once downloaded. Archives whose
+/// server does not support range requests are kept as they are.
+pub fn expand_zip<S>(
+    client: Client,
+    entries: S,
+) -> BoxStream<'static, Result<Entry, Exn<CrawlerError>>>
+where
+    S: Stream<Item = Result<Entry, Exn<CrawlerError>>> + Send + 'static,
+{
+    try_stream! {
+        for await entry in entries {
+            match entry? {
+                Entry::File(file_meta) if is_zip(&file_meta) => {
+                    match zip_members(&client, &file_meta).await {
+                        Ok(members) => {
+                            for member in members {
+                                yield Entry::File(member);
+                            }
+                        }
+                        Err(err) => {
+                            warn!(path = %file_meta.relative(), "keep the zip archive, cannot list its members: {err}");
+                            yield Entry::File(file_meta);
+                        }
+                    }
+                }
+                entry => yield entry,
+            }
+        }
+    }
+    .boxed()
+}
+
+fn is_zip(file_meta: &FileMeta) -> bool {
+    file_meta.is_downloadable()
+        && file_meta.zip_member().is_none()
+        && file_meta
+            .relative()
+            .as_str()
+            .to_lowercase()
+            .ends_with(".zip")
+}
+
+/// Inflates the next `chunk` of a deflate stream.
+fn inflate(inflater: &mut Decompress, mut chunk: &[u8]) -> Result<Bytes, Exn<CrawlerError>> {
+    let mut out = Vec::with_capacity(chunk.len() * 4);
+    loop {
+        if out.len() == out.capacity() {
+            out.reserve(out.capacity().max(64 * 1024));
+        }
+        let consumed = inflater.total_in();
+        let status = inflater
+            .decompress_vec(chunk, &mut out, FlushDecompress::None)
+            .or_raise(|| CrawlerError {
+                message: "invalid deflate data".to_string(),
+                status: ErrorStatus::Permanent,
+            })?;
+        // Safe to cast: no more than the chunk is consumed
+        #[allow(clippy::cast_possible_truncation)]
+        let consumed = (inflater.total_in() - consumed) as usize;
+        chunk = &chunk[consumed..];
+        let drained = chunk.is_empty() && out.len() < out.capacity();
+        if status == Status::StreamEnd || drained || (status == Status::BufError && consumed == 0) {
+            return Ok(Bytes::from(out));
+        }
+    }
+}
+
+/// Streams the uncompressed content of the zip `member` of the archive at `url`, from two
+/// range requests, one for its local header and one for its data.
+pub(crate) async fn member_stream(
+    client: &Client,
+    client_options: &ClientOptions,
+    url: &Url,
+    member: &ZipMember,
+) -> Result<BoxStream<'static, Result<Bytes, Exn<CrawlerError>>>, Exn<CrawlerError>> {
+    let offset = member.header_offset;
+    let (header, _) = read_range(
+        client,
+        client_options,
+        url,
+        &format!("{offset}-{}", offset + LOCAL_HEADER_LEN - 1),
+    )
+    .await?;
+    // the local name and extra fields may differ from the central directory ones
+    let data_offset = (|| {
+        if u32_at(&header, 0)? != LOCAL_HEADER_SIGNATURE {
+            return None;
+        }
+        let name_len = u64::from(u16_at(&header, 26)?);
+        let extra_len = u64::from(u16_at(&header, 28)?);
+        Some(offset + LOCAL_HEADER_LEN + name_len + extra_len)
+    })()
+    .ok_or_raise(|| CrawlerError {
+        message: format!("invalid local file header at {offset} of {url}"),
+        status: ErrorStatus::Permanent,
+    })?;
+    if member.compressed_size == 0 {
+        return Ok(stream::empty().boxed());
+    }
+
+    let end = data_offset + member.compressed_size - 1;
+    let resp = get_range(client, client_options, url, &format!("{data_offset}-{end}")).await?;
+    let body = resp.bytes_stream().map(|item| {
+        item.or_raise(|| CrawlerError {
+            message: "reqwest error stream".to_string(),
+            status: ErrorStatus::Temporary,
+        })
+    });
+    if member.method == 0 {
+        return Ok(body.boxed());
+    }
+    let mut inflater = Decompress::new(false);
+    Ok(body
+        .map(move |chunk| inflate(&mut inflater, &chunk?))
+        .boxed())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testutil::{TempDir, TestFile},
+        DownloadExt, DownloadOptions, DownloadPlan, FileFilter,
+    };
+    use futures_util::TryStreamExt;
+    use indicatif::MultiProgress;
+    use std::io::{Cursor, Write};
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, Request, Respond, ResponseTemplate,
+    };
+    use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+    /// Serves byte ranges of `body` as `206 Partial Content`.
+    struct RangeResponder {
+        body: Vec<u8>,
+    }
+
+    impl Respond for RangeResponder {
+        fn respond(&self, request: &Request) -> ResponseTemplate {
+            let len = self.body.len();
+            let Some(range) = request
+                .headers
+                .get("range")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("bytes="))
+            else {
+                return ResponseTemplate::new(200).set_body_bytes(self.body.clone());
+            };
+            let (start, end) = match range.split_once('-') {
+                Some(("", suffix)) => (len.saturating_sub(suffix.parse().unwrap()), len - 1),
+                Some((start, end)) => (start.parse().unwrap(), end.parse().unwrap()),
+                None => panic!("invalid range {range}"),
+            };
+            ResponseTemplate::new(206)
+                .insert_header("content-range", format!("bytes {start}-{end}/{len}"))
+                .set_body_bytes(self.body[start..=end].to_vec())
+        }
+    }
+
+    fn archive() -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        zip.start_file("a.csv", deflated).unwrap();
+        zip.write_all(&b"x,y\n1,2\n".repeat(1000)).unwrap();
+        zip.add_directory("docs/", stored).unwrap();
+        zip.start_file("docs/readme.txt", stored).unwrap();
+        zip.write_all(b"read me").unwrap();
+        zip.finish().unwrap().into_inner()
+    }
+
+    fn archive_meta(url: &str) -> FileMeta {
+        TestFile::new("data.zip")
+            .url(Url::parse(url).unwrap())
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_expand_zip_and_download_member() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/data.zip"))
+            .respond_with(RangeResponder { body: archive() })
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/no-range.zip"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(archive()))
+            .mount(&mock_server)
+            .await;
+
+        let client = Client::new();
+        let entries = stream::iter([Ok(Entry::File(archive_meta(&format!(
+            "{}/data.zip",
+            mock_server.uri()
+        ))))]);
+        let members = expand_zip(client.clone(), entries)
+            .map_ok(|entry| match entry {
+                Entry::File(file_meta) => file_meta,
+                Entry::Dir(_) => panic!("expect a file"),
+            })
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let paths = members
+            .iter()
+            .map(|f| f.relative().as_str().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["data/a.csv", "data/docs/readme.txt"]);
+        assert_eq!(members[0].size(), Some(8000));
+
+        let dst = TempDir::new("expand-zip");
+        let options =
+            DownloadOptions::new().filter(FileFilter::new(&["*.csv"], &[] as &[&str]).unwrap());
+        let report = DownloadPlan::new(members)
+            .download_with_validation(&client, &dst, MultiProgress::new(), &options)
+            .await
+            .unwrap();
+        assert_eq!(report.files.len(), 1);
+        assert_eq!(report.files[0].outcome, crate::FileOutcome::Verified);
+        let content = std::fs::read(dst.join("data/a.csv")).unwrap();
+        assert_eq!(content, b"x,y\n1,2\n".repeat(1000));
+        assert!(!dst.join("data/docs/readme.txt").exists());
+
+        // without range requests the archive is kept
+        let entries = stream::iter([Ok(Entry::File(archive_meta(&format!(
+            "{}/no-range.zip",
+            mock_server.uri()
+        ))))]);
+        let entries = expand_zip(client, entries)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let Entry::File(file_meta) = &entries[0] else {
+            panic!("expect a file")
+        };
+        assert_eq!(file_meta.relative().as_str(), "data.zip");
+        let archive = archive_meta(&format!("{}/no-range.zip", mock_server.uri()));
+        assert!(zip_members(&Client::new(), &archive).await.is_err());
+    }
+}
@@ -0,0 +1,39 @@
+use datahugger::{
+    datasets::{Arxiv, ArxivFormat},
+    DatasetBackend,
+};
+
+use crate::Fixtures;
+
+#[tokio::test]
+async fn test_arxiv_pdf() {
+    // the record is listed as its pdf without any request
+    let fixtures = Fixtures::start().await;
+    let arxiv = Arxiv::new("2401.01234");
+    let root = arxiv.root_url();
+    assert_eq!(
+        fixtures.tree(arxiv, &root).await,
+        ["2401.01234.pdf - - https://arxiv.org/pdf/2401.01234"]
+    );
+}
+
+#[tokio::test]
+async fn test_arxiv_formats() {
+    // the e-print source is listed next to the pdf
+    let fixtures = Fixtures::start().await;
+    let arxiv = Arxiv::new("2401.01234").formats([ArxivFormat::Pdf, ArxivFormat::Source]);
+    let root = arxiv.root_url();
+    assert_eq!(
+        fixtures.tree(arxiv, &root).await,
+        [
+            "2401.01234.pdf - - https://arxiv.org/pdf/2401.01234",
+            "2401.01234.tar.gz - - https://arxiv.org/e-print/2401.01234",
+        ]
+    );
+
+    let arxiv = Arxiv::new("2401.01234").formats([ArxivFormat::Source]);
+    assert_eq!(
+        fixtures.tree(arxiv, &root).await,
+        ["2401.01234.tar.gz - - https://arxiv.org/e-print/2401.01234"]
+    );
+}
@@ -0,0 +1,26 @@
+use datahugger::datasets::Dataone;
+
+use crate::Fixtures;
+
+#[tokio::test]
+async fn test_dataone_eml_and_system_metadata() {
+    let fixtures = Fixtures::start().await;
+    fixtures
+        .serve("/cn/v2/object/doi%3A10.15485%2F1971251", "dataone/eml.xml")
+        .await;
+    fixtures
+        .serve("/cn/v2/meta/urn:uuid:6b0f0a3e", "dataone/meta_6b0f0a3e.xml")
+        .await;
+    // no system metadata of 'urn:uuid:9c2d7e41', the EML values are kept
+
+    let dataone = Dataone::new(&fixtures.url("/"), "doi%3A10.15485%2F1971251");
+    let root = fixtures.url("/cn/v2/object/doi%3A10.15485%2F1971251");
+    assert_eq!(
+        fixtures.tree(dataone, &root).await,
+        [
+            // the system metadata is authoritative over the size and checksum of the EML
+            "site_map.pdf 48213 - {server}/cn/v2/resolve/urn%3Auuid%3A9c2d7e41",
+            "soil_respiration_2021.csv 52817 md5:9e107d9d372bb6826bd81d3542a419d6,sha1:2fd4e1c67a2d28fced849ee1bb76e7391b93eb12 {server}/cn/v2/resolve/urn%3Auuid%3A6b0f0a3e",
+        ]
+    );
+}
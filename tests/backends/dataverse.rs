@@ -0,0 +1,66 @@
+use datahugger::{
+    datasets::{DataverseDataset, DataverseFile, DataverseJsonSrcDataset},
+    DatasetBackend,
+};
+
+use crate::Fixtures;
+
+const DATASET_TREE: [&str; 3] = [
+    "data/survey.tab 20480 md5:5f363e0e58a95f06cbe9bbc662c5dfb6 {server}/api/access/datafile/3401",
+    "docs/codebook.pdf 88311 sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855 {server}/api/access/datafile/3402",
+    "participants.csv 5120 md5:b026324c6904b2a9cb4b88d6d61c81d1 {server}/api/access/datafile/3403 (not downloadable)",
+];
+
+#[tokio::test]
+async fn test_dataverse_dataset() {
+    let fixtures = Fixtures::start().await;
+    fixtures
+        .serve(
+            "/api/datasets/:persistentId/versions/:latest-published?persistentId=doi:10.7910/DVN/KBHLOD",
+            "dataverse/dataset.json",
+        )
+        .await;
+
+    let dataset = DataverseDataset::new(
+        "doi:10.7910/DVN/KBHLOD",
+        &fixtures.url("/"),
+        ":latest-published",
+    );
+    let root = dataset.root_url();
+    assert_eq!(fixtures.tree(dataset, &root).await, DATASET_TREE);
+}
+
+#[tokio::test]
+async fn test_dataverse_json_src() {
+    let fixtures = Fixtures::start().await;
+    let dataset = DataverseJsonSrcDataset::new(
+        "doi:10.7910/DVN/KBHLOD",
+        &fixtures.url("/"),
+        ":latest-published",
+        fixtures.read("dataverse/dataset.json"),
+    );
+    let root = dataset.root_url();
+    assert_eq!(fixtures.tree(dataset, &root).await, DATASET_TREE);
+}
+
+#[tokio::test]
+async fn test_dataverse_file() {
+    let fixtures = Fixtures::start().await;
+    fixtures
+        .serve(
+            "/api/files/:persistentId/versions/:latest-published?persistentId=doi:10.7910/DVN/KBHLOD/2FGHIJ",
+            "dataverse/file.json",
+        )
+        .await;
+
+    let file = DataverseFile::new(
+        "doi:10.7910/DVN/KBHLOD/2FGHIJ",
+        &fixtures.url("/"),
+        ":latest-published",
+    );
+    let root = file.root_url();
+    assert_eq!(
+        fixtures.tree(file, &root).await,
+        ["codebook.pdf 88311 sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855 {server}/api/access/datafile/3402"]
+    );
+}
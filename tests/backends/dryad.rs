@@ -0,0 +1,48 @@
+use datahugger::{datasets::DataDryad, DatasetBackend};
+
+use crate::Fixtures;
+
+#[tokio::test]
+async fn test_dryad_dataset() {
+    let fixtures = Fixtures::start().await;
+    fixtures
+        .serve(
+            "/api/v2/datasets/doi%3A10.5061%2Fdryad.2bvq83bkp",
+            "dryad/dataset.json",
+        )
+        .await;
+    fixtures
+        .serve("/api/v2/versions/218442/files", "dryad/files.json")
+        .await;
+    fixtures
+        .serve(
+            "/api/v2/versions/218442/files?page=2",
+            "dryad/files_page2.json",
+        )
+        .await;
+
+    let dryad = DataDryad::new("doi:10.5061/dryad.2bvq83bkp", &fixtures.url("/"));
+    let root = fixtures.url("/api/v2/datasets/doi%3A10.5061%2Fdryad.2bvq83bkp");
+    assert_eq!(
+        fixtures.tree(dryad, &root).await,
+        [
+            "README.md 4682 md5:9b2cf535f27731c974343645a3985328 {server}/api/v2/files/1450022/download",
+            "leaf_traits.csv 654321 sha256:f0e6a6a97042a4f1f1c87f5f7d44315b2d852c2df5c7991cc66241bf7072d1c4 {server}/api/v2/files/1450021/download",
+            // listed on the second page
+            "sites.kml 75000 adler32:3a0bcd36 {server}/api/v2/files/1450023/download",
+            "traits_model.R 2310 sha384:4b7e9c5077b437e55f0b5a54d68a581151821cef3d67e273618d2efe78a41cf74c63ede69b7d1a2cb44194e69b05ffd9 {server}/api/v2/files/1450024/download",
+        ]
+    );
+}
+
+#[test]
+fn test_dryad_archive_url() {
+    let dryad = DataDryad::new(
+        "doi:10.5061/dryad.2bvq83bkp",
+        &url::Url::parse("https://datadryad.org/").unwrap(),
+    );
+    assert_eq!(
+        dryad.archive_url().unwrap().as_str(),
+        "https://datadryad.org/api/v2/datasets/doi:10.5061%2Fdryad.2bvq83bkp/download"
+    );
+}
@@ -0,0 +1,78 @@
+use datahugger::datasets::GitHub;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::Fixtures;
+
+const TREE: [&str; 4] = [
+    "README.md 1432 gitsha1:a8a940627d132695a7e1ef2b3d5a5b1c8e2f5c11 https://raw.githubusercontent.com/rs4rse/vizmat/main/README.md",
+    "data/",
+    "data/benzene frame.pdb 0 gitsha1:e69de29bb2d1d6434b8b29ae775ad8c2e48c5391 https://raw.githubusercontent.com/rs4rse/vizmat/main/data/benzene%20frame.pdb",
+    "data/water.xyz 112 gitsha1:3c4e9cd789d88d8d89c1073707c3585e41b0e614 https://raw.githubusercontent.com/rs4rse/vizmat/main/data/water.xyz",
+];
+
+#[tokio::test]
+async fn test_github_rest_tree() {
+    let fixtures = Fixtures::start().await;
+    // the whole tree is listed by a single recursive request
+    fixtures
+        .serve(
+            "/repos/rs4rse/vizmat/git/trees/main?recursive=1",
+            "github/tree_main_recursive.json",
+        )
+        .await;
+
+    let github = GitHub::new("rs4rse", "vizmat", "main");
+    let root = fixtures.url("/repos/rs4rse/vizmat/git/trees/main");
+    assert_eq!(fixtures.tree(github, &root).await, TREE);
+}
+
+#[tokio::test]
+async fn test_github_rest_tree_truncated() {
+    let fixtures = Fixtures::start().await;
+    // a truncated recursive listing is listed directory by directory
+    fixtures
+        .serve(
+            "/repos/rs4rse/vizmat/git/trees/main?recursive=1",
+            "github/tree_main_truncated.json",
+        )
+        .await;
+    fixtures
+        .serve(
+            "/repos/rs4rse/vizmat/git/trees/main",
+            "github/tree_main.json",
+        )
+        .await;
+    fixtures
+        .serve(
+            "/repos/rs4rse/vizmat/git/trees/f484d249c660418515fb01c2b9662073663c242e",
+            "github/tree_data.json",
+        )
+        .await;
+
+    let github = GitHub::new("rs4rse", "vizmat", "main");
+    let root = fixtures.url("/repos/rs4rse/vizmat/git/trees/main");
+    assert_eq!(fixtures.tree(github, &root).await, TREE);
+}
+
+#[tokio::test]
+async fn test_github_graphql_tree() {
+    let fixtures = Fixtures::start().await;
+    Mock::given(method("POST"))
+        .and(path("/graphql"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_raw(fixtures.read("github/graphql.json"), "application/json"),
+        )
+        .expect(1)
+        .mount(&fixtures.server)
+        .await;
+
+    let mut github = GitHub::new("rs4rse", "vizmat", "main");
+    github.graphql_url = Some(fixtures.url("/graphql"));
+    let root = fixtures.url("/repos/rs4rse/vizmat/git/trees/main");
+    // the sub directories are listed by the same query
+    assert_eq!(fixtures.tree(github, &root).await, TREE);
+}
@@ -0,0 +1,51 @@
+use datahugger::{
+    datasets::{HalJsonSrcDataset, HalScience},
+    DatasetBackend,
+};
+
+use crate::Fixtures;
+
+// HAL lists no size nor checksum
+const TREE: [&str; 1] = [
+    "MAILLOT_Cours_inf340-systemes_information.pdf - - {server}/cel-01830944/file/MAILLOT_Cours_inf340-systemes_information.pdf",
+];
+
+#[tokio::test]
+async fn test_hal_search() {
+    let fixtures = Fixtures::start().await;
+    fixtures
+        .serve(
+            "/search/?q=halId_s:cel-01830944&wt=json&fl=halId_s,fileMain_s,files_s,fileType_s,producedDate_tdate,modifiedDate_tdate,version_i",
+            "hal/search.json",
+        )
+        .await;
+
+    let hal = HalScience::new("cel-01830944");
+    let mut root = fixtures.url("/search/");
+    root.set_query(hal.root_url().query());
+    assert_eq!(fixtures.tree(hal, &root).await, TREE);
+}
+
+#[tokio::test]
+async fn test_hal_json_src() {
+    let fixtures = Fixtures::start().await;
+    let hal = HalJsonSrcDataset::new("cel-01830944", fixtures.read("hal/search.json"));
+    let root = hal.root_url();
+    assert_eq!(fixtures.tree(hal, &root).await, TREE);
+}
+
+#[tokio::test]
+async fn test_hal_annexes() {
+    // annexes are listed under their original name, whatever their type
+    let fixtures = Fixtures::start().await;
+    let hal = HalJsonSrcDataset::new("hal-04123456", fixtures.read("hal/search_annexes.json"));
+    let root = hal.root_url();
+    assert_eq!(
+        fixtures.tree(hal, &root).await,
+        [
+            "article.pdf - - {server}/hal-04123456/file/article.pdf",
+            "code.zip - - {server}/hal-04123456/file/code.zip",
+            "measurements 2023.csv - - {server}/hal-04123456/file/measurements%202023.csv",
+        ]
+    );
+}
@@ -0,0 +1,34 @@
+use datahugger::datasets::HuggingFace;
+
+use crate::Fixtures;
+
+#[tokio::test]
+async fn test_huggingface_tree() {
+    let fixtures = Fixtures::start().await;
+    fixtures
+        .serve(
+            "/api/datasets/stanfordnlp/imdb/tree/main",
+            "huggingface/tree_main.json",
+        )
+        .await;
+    fixtures
+        .serve(
+            "/api/datasets/stanfordnlp/imdb/tree/main/data",
+            "huggingface/tree_data.json",
+        )
+        .await;
+
+    let hf = HuggingFace::new("stanfordnlp", "imdb", "main");
+    let root = fixtures.url("/api/datasets/stanfordnlp/imdb/tree/main");
+    assert_eq!(
+        fixtures.tree(hf, &root).await,
+        [
+            // only the lfs files have a sha256 of their content, the others their git blob id
+            ".gitattributes 1519 gitsha1:7d865e959b2466918c9863afca942d0fb89d7c9a https://huggingface.co/datasets/stanfordnlp/imdb/resolve/main/.gitattributes",
+            "README.md 2301 gitsha1:c9f2a4a1d0b7f3e8e2b1a0c9d8e7f6a5b4c3d2e1 https://huggingface.co/datasets/stanfordnlp/imdb/resolve/main/README.md",
+            "data/",
+            "data/test-00000-of-00001.parquet 33554432 sha256:fcde2b2edba56bf408601fb721fe9b5c338d10ee429ea04fae5511b68fbf8fb9 https://huggingface.co/datasets/stanfordnlp/imdb/resolve/main/data/test-00000-of-00001.parquet",
+            "data/train-00000-of-00001.parquet 268435456 sha256:2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae https://huggingface.co/datasets/stanfordnlp/imdb/resolve/main/data/train-00000-of-00001.parquet",
+        ]
+    );
+}
@@ -0,0 +1,150 @@
+//! Crawls every backend against recorded API responses and asserts the exact tree of entries
+//! it produces, the path, size, checksums and download url of every file, so that a change of
+//! what gets downloaded shows up as a diff of the expected trees.
+//!
+//! Responses are recorded in `fixtures/<backend>/` and served by wiremock, `{server}` in them
+//! is replaced by the url of the mock server.
+
+mod arxiv;
+mod dataone;
+mod dataverse;
+mod dryad;
+mod github;
+mod hal;
+mod huggingface;
+mod osf;
+mod pangaea;
+mod s3;
+mod softwareheritage;
+mod thredds;
+mod zenodo;
+
+use std::{path::Path, sync::Arc};
+
+use datahugger::{crawl, crawler::ProgressManager, DatasetBackend, DirMeta, Entry};
+use futures_util::TryStreamExt;
+use indicatif::ProgressBar;
+use reqwest::Client;
+use url::{form_urlencoded, Url};
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, Request, ResponseTemplate,
+};
+
+#[derive(Clone)]
+struct NoProgress;
+
+impl ProgressManager for NoProgress {
+    fn insert(&self, _index: usize, _pb: ProgressBar) -> ProgressBar {
+        ProgressBar::hidden()
+    }
+
+    fn insert_from_back(&self, _index: usize, _pb: ProgressBar) -> ProgressBar {
+        ProgressBar::hidden()
+    }
+}
+
+/// Mock server of a repository answering with recorded responses.
+pub struct Fixtures {
+    server: MockServer,
+}
+
+impl Fixtures {
+    pub async fn start() -> Self {
+        Fixtures {
+            server: MockServer::start().await,
+        }
+    }
+
+    /// Url of `path_and_query` on the mock server.
+    pub fn url(&self, path_and_query: &str) -> Url {
+        Url::parse(&format!("{}{path_and_query}", self.server.uri())).unwrap()
+    }
+
+    /// Reads the recorded response `fixture`, with `{server}` replaced by the url of the mock
+    /// server.
+    pub fn read(&self, fixture: &str) -> String {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/backends/fixtures")
+            .join(fixture);
+        std::fs::read_to_string(&path)
+            .unwrap_or_else(|err| panic!("cannot read fixture {}: {err}", path.display()))
+            .replace("{server}", &self.server.uri())
+    }
+
+    /// Answers GET requests of `path_and_query` with the recorded response `fixture`, the
+    /// query of the request must be exactly the one given.
+    pub async fn serve(&self, path_and_query: &str, fixture: &str) {
+        let (request_path, query) = path_and_query
+            .split_once('?')
+            .unwrap_or((path_and_query, ""));
+        let mut expected = form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect::<Vec<_>>();
+        expected.sort();
+        let content_type = if fixture.ends_with(".xml") {
+            "application/xml"
+        } else {
+            "application/json"
+        };
+        Mock::given(method("GET"))
+            .and(path(request_path))
+            .and(move |request: &Request| {
+                let mut query = request.url.query_pairs().into_owned().collect::<Vec<_>>();
+                query.sort();
+                query == expected
+            })
+            .respond_with(ResponseTemplate::new(200).set_body_raw(self.read(fixture), content_type))
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Crawls `backend` from the directory listed at `root`, and returns one line per entry:
+    /// `<path>/` for directories and `<path> <size> <checksums> <download url>` for files,
+    /// sorted, with the url of the mock server as `{server}`.
+    pub async fn tree<D>(&self, backend: D, root: &Url) -> Vec<String>
+    where
+        D: DatasetBackend + 'static,
+    {
+        let entries = crawl(
+            Client::new(),
+            Arc::new(backend),
+            DirMeta::new_root(root),
+            NoProgress,
+        )
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap_or_else(|err| panic!("crawl failed: {err:?}"));
+        let mut lines = entries
+            .iter()
+            .map(|entry| match entry {
+                Entry::Dir(dir) => format!("{}/", dir.relative()),
+                Entry::File(file) => {
+                    let size = file.size().map_or("-".to_string(), |size| size.to_string());
+                    let checksums = file
+                        .checksum()
+                        .iter()
+                        .map(|c| format!("{}:{}", c.algorithm(), c.value()))
+                        .collect::<Vec<_>>();
+                    let checksums = if checksums.is_empty() {
+                        "-".to_string()
+                    } else {
+                        checksums.join(",")
+                    };
+                    let mut line = format!(
+                        "{} {size} {checksums} {}",
+                        file.relative(),
+                        file.download_url()
+                    );
+                    if !file.is_downloadable() {
+                        line.push_str(" (not downloadable)");
+                    }
+                    line
+                }
+            })
+            .map(|line| line.replace(&self.server.uri(), "{server}"))
+            .collect::<Vec<_>>();
+        lines.sort();
+        lines
+    }
+}
@@ -0,0 +1,35 @@
+use datahugger::datasets::OSF;
+
+use crate::Fixtures;
+
+#[tokio::test]
+async fn test_osf_paginated_folders() {
+    let fixtures = Fixtures::start().await;
+    fixtures
+        .serve("/v2/nodes/3ua2c/files/osfstorage/", "osf/files.json")
+        .await;
+    fixtures
+        .serve(
+            "/v2/nodes/3ua2c/files/osfstorage/64a1f0c2e9d8b7001a2b3c4d/",
+            "osf/analysis.json",
+        )
+        .await;
+    fixtures
+        .serve(
+            "/v2/nodes/3ua2c/files/osfstorage/64a1f0c2e9d8b7001a2b3c4d/?page=2",
+            "osf/analysis_page2.json",
+        )
+        .await;
+
+    let osf = OSF::new("3ua2c");
+    let root = fixtures.url("/v2/nodes/3ua2c/files/osfstorage/");
+    assert_eq!(
+        fixtures.tree(osf, &root).await,
+        [
+            "analysis/",
+            "analysis/model.R 4096 sha256:6b86b273ff34fce19d6b804eff5a3f5747ada4eaa22f1d49c01e52ddb7875b4b {server}/download/64a1f0d1e9d8b7001a2b3c60/",
+            "analysis/results.csv 20933 sha256:d4735e3a265e16eee03f59718b9b5d03019c07d8b6c51f90da3a666eec13ab35 {server}/download/64a1f0d4e9d8b7001a2b3c61/",
+            "preregistration.pdf 183201 sha256:8a39d2abd3999ab73c34db2476849cddf303ce389b35826850f9a700589b4a90 {server}/download/64a1f0c5e9d8b7001a2b3c51/",
+        ]
+    );
+}
@@ -0,0 +1,40 @@
+use datahugger::{datasets::Pangaea, DatasetBackend};
+
+use crate::Fixtures;
+
+#[tokio::test]
+async fn test_pangaea_collection() {
+    let fixtures = Fixtures::start().await;
+    fixtures
+        .serve(
+            "/10.1594/PANGAEA.942110?format=metadata_jsonld",
+            "pangaea/collection.json",
+        )
+        .await;
+    fixtures
+        .serve(
+            "/10.1594/PANGAEA.942111?format=metadata_jsonld",
+            "pangaea/child_942111.json",
+        )
+        .await;
+    fixtures
+        .serve(
+            "/10.1594/PANGAEA.942112?format=metadata_jsonld",
+            "pangaea/child_942112.json",
+        )
+        .await;
+
+    let pangaea = Pangaea::new("PANGAEA.942110", &fixtures.url("/"));
+    let root = pangaea.root_url();
+    assert_eq!(
+        fixtures.tree(pangaea, &root).await,
+        [
+            // the zip of the collection bundles the child datasets, it is not listed
+            "PANGAEA.942111/",
+            "PANGAEA.942111/PANGAEA.942111.tab - - {server}/10.1594/PANGAEA.942111?format=textfile",
+            "PANGAEA.942112/",
+            "PANGAEA.942112/PANGAEA.942112.nc - - {server}/10.1594/PANGAEA.942112?format=netcdf",
+            "PANGAEA.942112/PANGAEA.942112.tab - - {server}/10.1594/PANGAEA.942112?format=textfile",
+        ]
+    );
+}
@@ -0,0 +1,39 @@
+use datahugger::{datasets::S3Public, DatasetBackend};
+
+use crate::Fixtures;
+
+#[tokio::test]
+async fn test_s3_prefixes_and_continuation() {
+    let fixtures = Fixtures::start().await;
+    fixtures
+        .serve(
+            "/openneuro.org/?list-type=2&delimiter=/&prefix=ds000001/",
+            "s3/list.xml",
+        )
+        .await;
+    fixtures
+        .serve(
+            "/openneuro.org/?list-type=2&delimiter=/&prefix=ds000001/&continuation-token=1ueGcxLPRx1Tr/XYExHnhbYLgveDs2J/wm36Hy4vbOwM=",
+            "s3/list_page2.xml",
+        )
+        .await;
+    fixtures
+        .serve(
+            "/openneuro.org/?list-type=2&delimiter=/&prefix=ds000001/sub-01/",
+            "s3/list_sub-01.xml",
+        )
+        .await;
+
+    let s3 = S3Public::new(&fixtures.url("/openneuro.org"), "ds000001");
+    let root = s3.root_url();
+    assert_eq!(
+        fixtures.tree(s3, &root).await,
+        [
+            "dataset_description.json 592 md5:5eb63bbbe01eeed093cb22bb8f5acdc3 {server}/openneuro.org/ds000001/dataset_description.json",
+            "participants.tsv 1130 md5:6f5902ac237024bdd0c176cb93063dc4 {server}/openneuro.org/ds000001/participants.tsv",
+            "sub-01/",
+            // the ETag of a multipart upload is not the md5 of the content
+            "sub-01/sub-01_T1w.nii.gz 10979182 - {server}/openneuro.org/ds000001/sub-01/sub-01_T1w.nii.gz",
+        ]
+    );
+}
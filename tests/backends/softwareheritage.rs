@@ -0,0 +1,40 @@
+use datahugger::{datasets::SoftwareHeritage, DatasetBackend};
+
+use crate::Fixtures;
+
+const REV: &str = "aafb16d69fd30ff58afdd69036a26047f3aebdc6";
+
+#[tokio::test]
+async fn test_software_heritage_revision() {
+    let fixtures = Fixtures::start().await;
+    fixtures
+        .serve(
+            &format!("/api/1/revision/{REV}/"),
+            "softwareheritage/revision.json",
+        )
+        .await;
+    fixtures
+        .serve(
+            "/api/1/directory/4b825dc642cb6eb9a060e54bf8d69288fbee4904/",
+            "softwareheritage/directory_root.json",
+        )
+        .await;
+    fixtures
+        .serve(
+            "/api/1/directory/b0e4c3b8e2b4e8a5c7f6f0b1c4f3d7f1c0b1a2c3/",
+            "softwareheritage/directory_src.json",
+        )
+        .await;
+
+    let swh = SoftwareHeritage::new("rev", REV, &fixtures.url("/"));
+    let root = swh.root_url();
+    assert_eq!(
+        fixtures.tree(swh, &root).await,
+        [
+            // the 'vendor' submodule is not archived in the directory
+            "README.md 4 sha256:88d4266fd4e6338d13b845fcf289579d209c897823b9217da3e161936f031589,sha1:a9993e364706816aba3e25717850c26c9cd0d89d {server}/api/1/content/sha1_git:8d777f385d3dfec8815d20f7496026dc8d777f38/raw/",
+            "src/",
+            "src/lib.rs 0 sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855,sha1:da39a3ee5e6b4b0d3255bfef95601890afd80709 {server}/api/1/content/sha1_git:e69de29bb2d1d6434b8b29ae775ad8c2e48c5391/raw/",
+        ]
+    );
+}
@@ -0,0 +1,53 @@
+use datahugger::{datasets::Thredds, DatasetBackend};
+
+use crate::Fixtures;
+
+async fn serve_catalogs(fixtures: &Fixtures) {
+    fixtures
+        .serve("/thredds/catalog/ocean/catalog.xml", "thredds/catalog.xml")
+        .await;
+    fixtures
+        .serve(
+            "/thredds/catalog/ocean/archive/catalog.xml",
+            "thredds/archive.xml",
+        )
+        .await;
+}
+
+#[tokio::test]
+async fn test_thredds_catalog() {
+    let fixtures = Fixtures::start().await;
+    serve_catalogs(&fixtures).await;
+
+    let thredds = Thredds::new(&fixtures.url("/thredds/catalog/ocean/catalog.html"));
+    let root = thredds.root_url();
+    assert_eq!(
+        fixtures.tree(thredds, &root).await,
+        [
+            "archive/",
+            "archive/sst_2023.nc 2048 - {server}/thredds/fileServer/ocean/archive/sst_2023.nc",
+            // sizes not given in bytes are rounded, so left unknown
+            "daily/d1.nc - - {server}/thredds/fileServer/ocean/daily/d1.nc",
+            "sst_2024.nc 1024 md5:8d777f385d3dfec8815d20f7496026dc {server}/thredds/fileServer/ocean/sst_2024.nc",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_thredds_subset() {
+    let fixtures = Fixtures::start().await;
+    serve_catalogs(&fixtures).await;
+
+    let thredds = Thredds::new(&fixtures.url("/thredds/catalog/ocean/catalog.xml"))
+        .subset("var=sst&north=50&south=40&west=0&east=10&accept=netcdf");
+    let root = thredds.root_url();
+    assert_eq!(
+        fixtures.tree(thredds, &root).await,
+        [
+            "archive/",
+            "archive/sst_2023.nc - - {server}/thredds/ncss/grid/ocean/archive/sst_2023.nc?var=sst&north=50&south=40&west=0&east=10&accept=netcdf",
+            "daily/d1.nc - - {server}/thredds/ncss/grid/ocean/daily/d1.nc?var=sst&north=50&south=40&west=0&east=10&accept=netcdf",
+            "sst_2024.nc - - {server}/thredds/ncss/grid/ocean/sst_2024.nc?var=sst&north=50&south=40&west=0&east=10&accept=netcdf",
+        ]
+    );
+}
@@ -0,0 +1,42 @@
+use datahugger::{
+    datasets::{Zenodo, ZenodoJsonSrcDataset},
+    DatasetBackend,
+};
+
+use crate::Fixtures;
+
+#[tokio::test]
+async fn test_zenodo_paginated_files() {
+    let fixtures = Fixtures::start().await;
+    fixtures
+        .serve("/api/records/17867222/files", "zenodo/files.json")
+        .await;
+    fixtures
+        .serve(
+            "/api/records/17867222/files?page=2",
+            "zenodo/files_page2.json",
+        )
+        .await;
+
+    let zenodo = Zenodo::new("17867222", &fixtures.url("/"));
+    let root = zenodo.root_url();
+    assert_eq!(
+        fixtures.tree(zenodo, &root).await,
+        [
+            "README.md 210 md5:d41d8cd98f00b204e9800998ecf8427e {server}/api/records/17867222/files/README.md/content",
+            "model weights.tar.gz 52428800 md5:0cc175b9c0f1b6a831c399e269772661 {server}/api/records/17867222/files/model%20weights.tar.gz/content",
+            "observations.csv 1024 md5:8d777f385d3dfec8815d20f7496026dc {server}/api/records/17867222/files/observations.csv/content",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_zenodo_json_src() {
+    let fixtures = Fixtures::start().await;
+    let zenodo = ZenodoJsonSrcDataset::new("17867222", fixtures.read("zenodo/files_page2.json"));
+    let root = zenodo.root_url();
+    assert_eq!(
+        fixtures.tree(zenodo, &root).await,
+        ["model weights.tar.gz 52428800 md5:0cc175b9c0f1b6a831c399e269772661 {server}/api/records/17867222/files/model%20weights.tar.gz/content"]
+    );
+}